@@ -12,7 +12,8 @@ mod build {
             .file("cpp/simdcomp/src/simdcomputil.c")
             .file("cpp/simdcomp/src/simdpackedselect.c")
             .file("cpp/simdcomp/src/simdfor.c")
-            .file("cpp/simdcomp_wrapper.c");
+            .file("cpp/simdcomp_wrapper.c")
+            .file("cpp/intersection.c");
 
         if !cfg!(debug_assertions) {
             config.opt_level(3);
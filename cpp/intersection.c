@@ -0,0 +1,71 @@
+#include <stdint.h>
+#include <stddef.h>
+
+#ifdef __SSE2__
+#include <emmintrin.h>
+#endif
+
+// Computes the intersection of two strictly increasing
+// arrays of uint32, and writes it in output.
+//
+// output should be large enough to host min(len_a, len_b) uint32.
+// Returns the number of elements written in output.
+size_t intersection_sorted(
+        const uint32_t* a,
+        const size_t len_a,
+        const uint32_t* b,
+        const size_t len_b,
+        uint32_t* output) {
+    size_t i = 0;
+    size_t j = 0;
+    size_t count = 0;
+#ifdef __SSE2__
+    // Blocks of 4 elements of a are compared against
+    // all of the rotations of blocks of 4 elements of b.
+    const size_t st_a = (len_a / 4) * 4;
+    const size_t st_b = (len_b / 4) * 4;
+    while (i < st_a && j < st_b) {
+        const __m128i v_a = _mm_loadu_si128((const __m128i*) (a + i));
+        __m128i v_b = _mm_loadu_si128((const __m128i*) (b + j));
+        __m128i cmp = _mm_cmpeq_epi32(v_a, v_b);
+        v_b = _mm_shuffle_epi32(v_b, _MM_SHUFFLE(0, 3, 2, 1));
+        cmp = _mm_or_si128(cmp, _mm_cmpeq_epi32(v_a, v_b));
+        v_b = _mm_shuffle_epi32(v_b, _MM_SHUFFLE(0, 3, 2, 1));
+        cmp = _mm_or_si128(cmp, _mm_cmpeq_epi32(v_a, v_b));
+        v_b = _mm_shuffle_epi32(v_b, _MM_SHUFFLE(0, 3, 2, 1));
+        cmp = _mm_or_si128(cmp, _mm_cmpeq_epi32(v_a, v_b));
+        const int mask = _mm_movemask_ps(_mm_castsi128_ps(cmp));
+        if (mask) {
+            int k;
+            for (k = 0; k < 4; k++) {
+                if (mask & (1 << k)) {
+                    output[count++] = a[i + k];
+                }
+            }
+        }
+        const uint32_t max_a = a[i + 3];
+        const uint32_t max_b = b[j + 3];
+        if (max_a <= max_b) {
+            i += 4;
+        }
+        if (max_b <= max_a) {
+            j += 4;
+        }
+    }
+#endif
+    // scalar merge on the remaining elements.
+    while (i < len_a && j < len_b) {
+        if (a[i] < b[j]) {
+            i++;
+        }
+        else if (a[i] > b[j]) {
+            j++;
+        }
+        else {
+            output[count++] = a[i];
+            i++;
+            j++;
+        }
+    }
+    return count;
+}
@@ -0,0 +1,167 @@
+use std::ascii::AsciiExt;
+use std::iter::Peekable;
+use std::str::CharIndices;
+
+/// Token emitted by the `CjkTokenizer`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Token {
+    /// Text of the token, as it should be indexed.
+    pub text: String,
+    /// Byte offset of the first character of the token in the original text.
+    pub offset_from: usize,
+    /// Byte offset right after the last character of the token in the original text.
+    pub offset_to: usize,
+    /// Position of the token, expressed in number of tokens.
+    pub position: u32,
+}
+
+/// Returns true iff the character belongs to one of the
+/// Chinese, Japanese or Korean scripts, that do not separate
+/// words with spaces.
+pub fn is_cjk(c: char) -> bool {
+    match c as u32 {
+        0x3040...0x309F |   // Hiragana
+        0x30A0...0x30FF |   // Katakana
+        0x3400...0x4DBF |   // CJK Unified Ideographs Extension A
+        0x4E00...0x9FFF |   // CJK Unified Ideographs
+        0xAC00...0xD7AF |   // Hangul Syllables
+        0xF900...0xFAFF |   // CJK Compatibility Ideographs
+        0xFF66...0xFF9F |   // Halfwidth Katakana
+        0x20000...0x2FA1F   // CJK Unified Ideographs Extension B and beyond
+            => true,
+        _ => false,
+    }
+}
+
+/// Tokenizer for texts in Chinese, Japanese or Korean,
+/// possibly mixed with texts in other languages.
+///
+/// Runs of CJK characters are split into overlapping bigrams:
+/// `北京大学` becomes `北京`, `京大`, `大学`. A CJK character that
+/// is alone in its run is emitted as a unigram.
+///
+/// The rest of the text is split the way the `SimpleTokenizer`
+/// does: words are runs of alphanumeric characters, and are
+/// lowercased.
+///
+/// This is the usual dictionary-free approach for CJK. It makes
+/// the index larger than a proper word segmentation would,
+/// but phrase queries on bigrams give good results.
+pub struct CjkTokenizer;
+
+impl CjkTokenizer {
+
+    /// Returns the tokens of `text`.
+    pub fn tokenize<'a>(&self, text: &'a str) -> CjkTokenIter<'a> {
+        CjkTokenIter {
+            chars: text.char_indices().peekable(),
+            previous_is_cjk: false,
+            position: 0u32,
+        }
+    }
+}
+
+/// Iterator over the tokens of a text.
+///
+/// See `CjkTokenizer`.
+pub struct CjkTokenIter<'a> {
+    chars: Peekable<CharIndices<'a>>,
+    previous_is_cjk: bool,
+    position: u32,
+}
+
+impl<'a> CjkTokenIter<'a> {
+
+    fn token(&mut self, text: String, offset_from: usize, offset_to: usize) -> Token {
+        let token = Token {
+            text: text,
+            offset_from: offset_from,
+            offset_to: offset_to,
+            position: self.position,
+        };
+        self.position += 1;
+        token
+    }
+}
+
+impl<'a> Iterator for CjkTokenIter<'a> {
+
+    type Item = Token;
+
+    fn next(&mut self) -> Option<Token> {
+        while let Some((offset, c)) = self.chars.next() {
+            if is_cjk(c) {
+                let previous_is_cjk = self.previous_is_cjk;
+                self.previous_is_cjk = true;
+                let next_cjk_opt = self.chars
+                    .peek()
+                    .cloned()
+                    .filter(|&(_, next_c)| is_cjk(next_c));
+                match next_cjk_opt {
+                    Some((next_offset, next_c)) => {
+                        let mut text = String::with_capacity(c.len_utf8() + next_c.len_utf8());
+                        text.push(c);
+                        text.push(next_c);
+                        return Some(self.token(text, offset, next_offset + next_c.len_utf8()));
+                    }
+                    None if !previous_is_cjk => {
+                        return Some(self.token(c.to_string(), offset, offset + c.len_utf8()));
+                    }
+                    None => {
+                        // the last character of the run was
+                        // already emitted as part of a bigram.
+                    }
+                }
+            }
+            else if c.is_alphanumeric() {
+                self.previous_is_cjk = false;
+                let mut text = String::new();
+                text.push(c.to_ascii_lowercase());
+                let mut offset_to = offset + c.len_utf8();
+                while let Some(&(next_offset, next_c)) = self.chars.peek() {
+                    if !next_c.is_alphanumeric() || is_cjk(next_c) {
+                        break;
+                    }
+                    text.push(next_c.to_ascii_lowercase());
+                    offset_to = next_offset + next_c.len_utf8();
+                    self.chars.next();
+                }
+                return Some(self.token(text, offset, offset_to));
+            }
+            else {
+                self.previous_is_cjk = false;
+            }
+        }
+        None
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_cjk_tokenizer() {
+        let text = "我爱北京 Hello, world!東京タワー x中y";
+        let tokens: Vec<Token> = CjkTokenizer.tokenize(text).collect();
+        let token_texts: Vec<&str> = tokens.iter().map(|token| &token.text[..]).collect();
+        assert_eq!(token_texts, vec!(
+            "我爱", "爱北", "北京",
+            "hello", "world",
+            "東京", "京タ", "タワ", "ワー",
+            "x", "中", "y"));
+        for (position, token) in tokens.iter().enumerate() {
+            assert_eq!(token.position, position as u32);
+            assert_eq!(text[token.offset_from..token.offset_to].to_lowercase(), token.text);
+        }
+        assert_eq!((tokens[3].offset_from, tokens[3].offset_to), (13, 18));
+    }
+
+    #[test]
+    fn test_cjk_tokenizer_empty() {
+        assert_eq!(CjkTokenizer.tokenize("").count(), 0);
+        assert_eq!(CjkTokenizer.tokenize(" !? ").count(), 0);
+    }
+}
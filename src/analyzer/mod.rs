@@ -1,7 +1,34 @@
 extern crate regex;
 
+mod cjk_tokenizer;
+pub use self::cjk_tokenizer::{CjkTokenizer, CjkTokenIter, Token};
+
+mod tokenizer;
+pub use self::tokenizer::{Tokenizer, TokenStream, RawTokenizer};
+pub use self::tokenizer::{SimpleTokenStream, RawTokenStream, CjkTokenStream};
+
+mod tokenizer_manager;
+pub use self::tokenizer_manager::{TokenizerManager, DEFAULT_TOKENIZER};
+
+mod ngram_tokenizer;
+pub use self::ngram_tokenizer::{NgramTokenizer, NgramTokenStream};
+
+mod url_email_tokenizer;
+pub use self::url_email_tokenizer::{UrlEmailTokenizer, UrlEmailTokenStream};
+
+mod token_filter;
+pub use self::token_filter::{TokenFilter, LowerCaser, StopWordFilter, ENGLISH_STOP_WORDS};
+pub use self::token_filter::{LowerCaserTokenStream, StopWordFilterTokenStream};
+
+mod stemmer;
+pub use self::stemmer::{Stemmer, StemmerTokenStream, stem};
+
+mod text_analyzer;
+pub use self::text_analyzer::TextAnalyzer;
+
 use std::str::Chars;
 use std::ascii::AsciiExt;
+use std::borrow::Cow;
 
 pub struct TokenIter<'a> {
     chars: Chars<'a>,
@@ -17,6 +44,15 @@ pub trait StreamingIterator<'a, T> {
 }
 
 impl<'a, 'b> TokenIter<'b> {
+    
+    /// Consumes the `TokenIter` and returns its internal
+    /// buffer, so that it can be reused for another text.
+    ///
+    /// See `SimpleTokenizer::tokenize_with_buffer`.
+    pub fn into_buffer(self) -> String {
+        self.term_buffer
+    }
+
     fn consume_token(&'a mut self) -> Option<&'a str> {
         for c in &mut self.chars { 
             if c.is_alphanumeric() {
@@ -52,17 +88,46 @@ impl<'a, 'b> StreamingIterator<'a, &'a str> for TokenIter<'b> {
     
 }
 
+/// Splits a text into words, made of alphanumeric characters,
+/// and lowercases them.
+///
+/// This is the default tokenizer.
 pub struct SimpleTokenizer;
 
 
 impl SimpleTokenizer {
 
+    /// Tokenizes a text.
     pub fn tokenize<'a>(&self, text: &'a str) -> TokenIter<'a> {
+        self.tokenize_with_buffer(text, String::new())
+    }
+
+    /// Tokenizes a text, using `term_buffer` as the 
+    /// `TokenIter` internal buffer.
+    ///
+    /// When tokenizing many texts in a row, the buffer
+    /// can be recovered via `TokenIter::into_buffer` and reused 
+    /// to avoid an allocation per text.
+    pub fn tokenize_with_buffer<'a>(&self, text: &'a str, mut term_buffer: String) -> TokenIter<'a> {
+        term_buffer.clear();
         TokenIter {
-           term_buffer: String::new(),
+           term_buffer: term_buffer,
            chars: text.chars(),
         }
-   }
+    }
+}
+
+/// Returns the single token an untokenized text
+/// is indexed as.
+///
+/// The text is taken as is, or lowercased if `lowercase` is true.
+pub fn keyword_token(text: &str, lowercase: bool) -> Cow<str> {
+    if lowercase {
+        Cow::Owned(text.to_lowercase())
+    }
+    else {
+        Cow::Borrowed(text)
+    }
 }
 
 
@@ -84,3 +149,74 @@ fn test_tokenizer_empty() {
     let mut term_reader = simple_tokenizer.tokenize("");
     assert_eq!(term_reader.next(), None);
 }
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use test::Bencher;
+
+    #[test]
+    fn test_tokenizer_reused_buffer() {
+        let simple_tokenizer = SimpleTokenizer;
+        let mut buffer = String::new();
+        {
+            let mut term_reader = simple_tokenizer.tokenize_with_buffer("supercalifragilistic expialidocious", buffer);
+            assert_eq!(term_reader.next().unwrap(), "supercalifragilistic");
+            buffer = term_reader.into_buffer();
+        }
+        {
+            let mut term_reader = simple_tokenizer.tokenize_with_buffer("tax", buffer);
+            assert_eq!(term_reader.next().unwrap(), "tax");
+            assert_eq!(term_reader.next(), None);
+            buffer = term_reader.into_buffer();
+        }
+        {
+            let mut term_reader = simple_tokenizer.tokenize_with_buffer("", buffer);
+            assert_eq!(term_reader.next(), None);
+        }
+    }
+
+    const BENCH_DOCS: [&'static str; 4] = [
+        "hello, happy tax payer!",
+        "the quick brown fox jumps over the lazy dog",
+        "a",
+        "lorem ipsum dolor sit amet, consectetur adipiscing elit",
+    ];
+
+    #[bench]
+    fn bench_tokenizer_new_buffer(b: &mut Bencher) {
+        let simple_tokenizer = SimpleTokenizer;
+        b.iter(|| {
+            let mut num_tokens = 0;
+            for _ in 0..1_000 {
+                for doc in &BENCH_DOCS {
+                    let mut term_reader = simple_tokenizer.tokenize(doc);
+                    while term_reader.next().is_some() {
+                        num_tokens += 1;
+                    }
+                }
+            }
+            num_tokens
+        });
+    }
+
+    #[bench]
+    fn bench_tokenizer_reused_buffer(b: &mut Bencher) {
+        let simple_tokenizer = SimpleTokenizer;
+        b.iter(|| {
+            let mut num_tokens = 0;
+            let mut buffer = String::new();
+            for _ in 0..1_000 {
+                for doc in &BENCH_DOCS {
+                    let mut term_reader = simple_tokenizer.tokenize_with_buffer(doc, buffer);
+                    while term_reader.next().is_some() {
+                        num_tokens += 1;
+                    }
+                    buffer = term_reader.into_buffer();
+                }
+            }
+            num_tokens
+        });
+    }
+}
@@ -0,0 +1,195 @@
+use super::{Tokenizer, TokenStream, Token};
+
+
+/// Emits the character n-grams of the text as tokens.
+///
+/// For instance, with `min_gram = 2` and `max_gram = 3`,
+/// `hello` is split into `he`, `hel`, `el`, `ell`, `ll`, `llo`, `lo`.
+/// If `prefix_only` is true, only the n-grams starting at the
+/// beginning of the text are emitted: `he`, `hel`.
+/// This makes it possible to search for substrings,
+/// or for prefixes in an autocomplete box, with a `TermQuery`.
+///
+/// N-grams are made of unicode characters, and their offsets
+/// are the byte range of the n-gram in the text.
+/// The text is taken as is, use a `TextAnalyzer` with a `LowerCaser`
+/// to get case-insensitive n-grams.
+#[derive(Clone, Debug)]
+pub struct NgramTokenizer {
+    min_gram: usize,
+    max_gram: usize,
+    prefix_only: bool,
+}
+
+impl NgramTokenizer {
+
+    /// Creates a `NgramTokenizer` emitting the n-grams
+    /// of `min_gram` to `max_gram` characters, included.
+    ///
+    /// # Panics
+    /// Panics if `min_gram` is 0 or greater than `max_gram`.
+    pub fn new(min_gram: usize, max_gram: usize, prefix_only: bool) -> NgramTokenizer {
+        assert!(min_gram > 0, "min_gram must be strictly positive.");
+        assert!(min_gram <= max_gram, "min_gram must not be greater than max_gram.");
+        NgramTokenizer {
+            min_gram: min_gram,
+            max_gram: max_gram,
+            prefix_only: prefix_only,
+        }
+    }
+}
+
+/// Token stream of the `NgramTokenizer`.
+pub struct NgramTokenStream<'a> {
+    text: &'a str,
+    // byte offset of each character, followed by the length of the text.
+    char_offsets: Vec<usize>,
+    tokenizer: NgramTokenizer,
+    // first character of the current n-gram.
+    start: usize,
+    // number of characters of the next n-gram.
+    num_chars: usize,
+    token: Token,
+    num_tokens: u32,
+}
+
+impl<'a> TokenStream for NgramTokenStream<'a> {
+
+    fn advance(&mut self) -> bool {
+        let text_num_chars = self.char_offsets.len() - 1;
+        loop {
+            if self.start >= text_num_chars || (self.tokenizer.prefix_only && self.start > 0) {
+                return false;
+            }
+            if self.num_chars > self.tokenizer.max_gram || self.start + self.num_chars > text_num_chars {
+                self.start += 1;
+                self.num_chars = self.tokenizer.min_gram;
+                continue;
+            }
+            let offset_from = self.char_offsets[self.start];
+            let offset_to = self.char_offsets[self.start + self.num_chars];
+            self.token.position = self.num_tokens;
+            self.num_tokens += 1;
+            self.token.offset_from = offset_from;
+            self.token.offset_to = offset_to;
+            self.token.text.clear();
+            self.token.text.push_str(&self.text[offset_from..offset_to]);
+            self.num_chars += 1;
+            return true;
+        }
+    }
+
+    fn token(&self) -> &Token {
+        &self.token
+    }
+
+    fn token_mut(&mut self) -> &mut Token {
+        &mut self.token
+    }
+}
+
+impl Tokenizer for NgramTokenizer {
+    fn token_stream<'a>(&self, text: &'a str) -> Box<TokenStream + 'a> {
+        let mut char_offsets: Vec<usize> = text.char_indices().map(|(offset, _)| offset).collect();
+        char_offsets.push(text.len());
+        box NgramTokenStream {
+            text: text,
+            char_offsets: char_offsets,
+            tokenizer: self.clone(),
+            start: 0,
+            num_chars: self.min_gram,
+            token: Token {
+                text: String::new(),
+                offset_from: 0,
+                offset_to: 0,
+                position: 0,
+            },
+            num_tokens: 0u32,
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use core::Index;
+    use schema::{Document, FieldValue, SchemaBuilder, Term, TEXT};
+
+    fn token_vec(tokenizer: &NgramTokenizer, text: &str) -> Vec<(String, usize, usize, u32)> {
+        let mut token_stream = tokenizer.token_stream(text);
+        let mut tokens = Vec::new();
+        while token_stream.advance() {
+            let token = token_stream.token();
+            assert_eq!(&text[token.offset_from..token.offset_to], token.text);
+            tokens.push((token.text.clone(), token.offset_from, token.offset_to, token.position));
+        }
+        tokens
+    }
+
+    fn texts(tokenizer: &NgramTokenizer, text: &str) -> Vec<String> {
+        token_vec(tokenizer, text).into_iter().map(|(text, _, _, _)| text).collect()
+    }
+
+    #[test]
+    fn test_ngram_tokenizer_prefix_only() {
+        assert_eq!(token_vec(&NgramTokenizer::new(2, 5, true), "hello"), vec!(
+            (String::from("he"), 0, 2, 0),
+            (String::from("hel"), 0, 3, 1),
+            (String::from("hell"), 0, 4, 2),
+            (String::from("hello"), 0, 5, 3),
+        ));
+        assert_eq!(texts(&NgramTokenizer::new(2, 3, true), "hello"), vec!("he", "hel"));
+        assert!(texts(&NgramTokenizer::new(2, 3, true), "h").is_empty());
+        assert!(texts(&NgramTokenizer::new(1, 3, true), "").is_empty());
+    }
+
+    #[test]
+    fn test_ngram_tokenizer() {
+        assert_eq!(texts(&NgramTokenizer::new(1, 2, false), "abc"), vec!("a", "ab", "b", "bc", "c"));
+        assert_eq!(texts(&NgramTokenizer::new(2, 3, false), "hello"),
+                   vec!("he", "hel", "el", "ell", "ll", "llo", "lo"));
+    }
+
+    #[test]
+    fn test_ngram_tokenizer_utf8() {
+        assert_eq!(token_vec(&NgramTokenizer::new(1, 2, false), "aé日"), vec!(
+            (String::from("a"), 0, 1, 0),
+            (String::from("aé"), 0, 3, 1),
+            (String::from("é"), 1, 3, 2),
+            (String::from("é日"), 1, 6, 3),
+            (String::from("日"), 3, 6, 4),
+        ));
+        assert_eq!(texts(&NgramTokenizer::new(2, 4, true), "東京タワー"), vec!("東京", "東京タ", "東京タワ"));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_ngram_tokenizer_invalid_grams() {
+        NgramTokenizer::new(3, 2, false);
+    }
+
+    #[test]
+    fn test_ngram_tokenizer_autocomplete() {
+        let mut schema_builder = SchemaBuilder::default();
+        let title_field = schema_builder.add_text_field("title", TEXT);
+        schema_builder.set_tokenizer(title_field, "autocomplete");
+        let index = Index::create_in_ram(schema_builder.build());
+        index.tokenizers().register("autocomplete", NgramTokenizer::new(2, 5, true));
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.add_document(doc!(title_field=>"hello"));
+            index_writer.add_document(doc!(title_field=>"help"));
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let doc_freq = |text: &str| searcher.doc_freq(&Term::from_field_text(title_field, text));
+        assert_eq!(doc_freq("he"), 2);
+        assert_eq!(doc_freq("hel"), 2);
+        assert_eq!(doc_freq("hell"), 1);
+        assert_eq!(doc_freq("help"), 1);
+        assert_eq!(doc_freq("ello"), 0);
+    }
+}
@@ -0,0 +1,327 @@
+use std::str;
+use super::{TokenFilter, TokenStream, Token};
+
+
+/// Reduces the english words to their stem, using
+/// the [Porter stemming algorithm](https://tartarus.org/martin/PorterStemmer/).
+///
+/// For instance, `running` becomes `run`, and `dogs` becomes `dog`.
+///
+/// Only lowercased ASCII words are stemmed, so the `Stemmer`
+/// typically comes after a `LowerCaser`. Other words are left unchanged.
+pub struct Stemmer;
+
+/// Token stream of the `Stemmer`.
+pub struct StemmerTokenStream<'a> {
+    tail: Box<TokenStream + 'a>,
+    stemmer: PorterStemmer,
+}
+
+impl<'a> TokenStream for StemmerTokenStream<'a> {
+
+    fn advance(&mut self) -> bool {
+        if !self.tail.advance() {
+            return false;
+        }
+        self.stemmer.stem(&mut self.tail.token_mut().text);
+        true
+    }
+
+    fn token(&self) -> &Token {
+        self.tail.token()
+    }
+
+    fn token_mut(&mut self) -> &mut Token {
+        self.tail.token_mut()
+    }
+
+    fn into_buffer(self: Box<Self>) -> String {
+        self.tail.into_buffer()
+    }
+}
+
+impl TokenFilter for Stemmer {
+    fn transform<'a>(&self, token_stream: Box<TokenStream + 'a>) -> Box<TokenStream + 'a> {
+        box StemmerTokenStream {
+            tail: token_stream,
+            stemmer: PorterStemmer::default(),
+        }
+    }
+}
+
+
+/// Returns the stem of an english word.
+///
+/// See `Stemmer`.
+pub fn stem(word: &str) -> String {
+    let mut word = String::from(word);
+    PorterStemmer::default().stem(&mut word);
+    word
+}
+
+
+// The word being stemmed is `b`. `j` is the length
+// of the stem left when removing the last suffix
+// matched by `.ends(...)`.
+#[derive(Default)]
+struct PorterStemmer {
+    b: Vec<u8>,
+    j: usize,
+}
+
+impl PorterStemmer {
+
+    fn stem(&mut self, word: &mut String) {
+        if word.len() <= 2 || !word.bytes().all(|c| c >= b'a' && c <= b'z') {
+            return;
+        }
+        self.b.clear();
+        self.b.extend_from_slice(word.as_bytes());
+        self.j = 0;
+        self.step1ab();
+        if self.b.len() > 1 {
+            self.step1c();
+            self.step2();
+            self.step3();
+            self.step4();
+            self.step5();
+        }
+        word.clear();
+        word.push_str(str::from_utf8(&self.b).expect("The stem of an ASCII word is ASCII"));
+    }
+
+    fn is_consonant(&self, i: usize) -> bool {
+        match self.b[i] {
+            b'a' | b'e' | b'i' | b'o' | b'u' => false,
+            b'y' => i == 0 || !self.is_consonant(i - 1),
+            _ => true,
+        }
+    }
+
+    // number of vowel-consonant sequences in the stem.
+    fn measure(&self) -> usize {
+        let mut n = 0;
+        let mut i = 0;
+        loop {
+            if i >= self.j {
+                return n;
+            }
+            if !self.is_consonant(i) {
+                break;
+            }
+            i += 1;
+        }
+        i += 1;
+        loop {
+            loop {
+                if i >= self.j {
+                    return n;
+                }
+                if self.is_consonant(i) {
+                    break;
+                }
+                i += 1;
+            }
+            i += 1;
+            n += 1;
+            loop {
+                if i >= self.j {
+                    return n;
+                }
+                if !self.is_consonant(i) {
+                    break;
+                }
+                i += 1;
+            }
+            i += 1;
+        }
+    }
+
+    fn has_vowel_in_stem(&self) -> bool {
+        (0..self.j).any(|i| !self.is_consonant(i))
+    }
+
+    fn ends_with_double_consonant(&self, len: usize) -> bool {
+        len >= 2 && self.b[len - 1] == self.b[len - 2] && self.is_consonant(len - 1)
+    }
+
+    // true iff the `len` first letters end with consonant-vowel-consonant,
+    // the last consonant not being `w`, `x` or `y`.
+    fn ends_with_cvc(&self, len: usize) -> bool {
+        if len < 3 || !self.is_consonant(len - 1) || self.is_consonant(len - 2) || !self.is_consonant(len - 3) {
+            return false;
+        }
+        match self.b[len - 1] {
+            b'w' | b'x' | b'y' => false,
+            _ => true,
+        }
+    }
+
+    fn ends(&mut self, suffix: &str) -> bool {
+        if !self.b.ends_with(suffix.as_bytes()) {
+            return false;
+        }
+        self.j = self.b.len() - suffix.len();
+        true
+    }
+
+    fn set_to(&mut self, suffix: &str) {
+        self.b.truncate(self.j);
+        self.b.extend_from_slice(suffix.as_bytes());
+    }
+
+    fn replace_if_measured(&mut self, suffix: &str) {
+        if self.measure() > 0 {
+            self.set_to(suffix);
+        }
+    }
+
+    // Replaces the first matching suffix if the stem
+    // has at least one vowel-consonant sequence.
+    fn replace_first(&mut self, replacements: &[(&str, &str)]) {
+        for &(suffix, replacement) in replacements {
+            if self.ends(suffix) {
+                self.replace_if_measured(replacement);
+                return;
+            }
+        }
+    }
+
+    // plurals and -ed or -ing.
+    fn step1ab(&mut self) {
+        if self.b.ends_with(b"s") {
+            if self.ends("sses") {
+                let len = self.b.len();
+                self.b.truncate(len - 2);
+            } else if self.ends("ies") {
+                self.set_to("i");
+            } else if !self.b.ends_with(b"ss") {
+                self.b.pop();
+            }
+        }
+        if self.ends("eed") {
+            if self.measure() > 0 {
+                self.b.pop();
+            }
+        } else if (self.ends("ed") || self.ends("ing")) && self.has_vowel_in_stem() {
+            let j = self.j;
+            self.b.truncate(j);
+            if self.ends("at") {
+                self.set_to("ate");
+            } else if self.ends("bl") {
+                self.set_to("ble");
+            } else if self.ends("iz") {
+                self.set_to("ize");
+            } else if self.ends_with_double_consonant(j) {
+                match self.b[j - 1] {
+                    b'l' | b's' | b'z' => {}
+                    _ => {
+                        self.b.pop();
+                    }
+                }
+            } else if self.measure() == 1 && self.ends_with_cvc(j) {
+                self.set_to("e");
+            }
+        }
+    }
+
+    // terminal y to i when there is another vowel in the stem.
+    fn step1c(&mut self) {
+        if self.ends("y") && self.has_vowel_in_stem() {
+            let last = self.b.len() - 1;
+            self.b[last] = b'i';
+        }
+    }
+
+    // double suffixes to single ones.
+    fn step2(&mut self) {
+        self.replace_first(&[
+            ("ational", "ate"), ("tional", "tion"),
+            ("enci", "ence"), ("anci", "ance"),
+            ("izer", "ize"),
+            ("bli", "ble"), ("alli", "al"), ("entli", "ent"), ("eli", "e"), ("ousli", "ous"),
+            ("ization", "ize"), ("ation", "ate"), ("ator", "ate"),
+            ("alism", "al"), ("iveness", "ive"), ("fulness", "ful"), ("ousness", "ous"),
+            ("aliti", "al"), ("iviti", "ive"), ("biliti", "ble"),
+            ("logi", "log"),
+        ]);
+    }
+
+    // -ic-, -full, -ness etc.
+    fn step3(&mut self) {
+        self.replace_first(&[
+            ("icate", "ic"), ("ative", ""), ("alize", "al"),
+            ("iciti", "ic"), ("ical", "ic"), ("ful", ""), ("ness", ""),
+        ]);
+    }
+
+    // -ant, -ence etc. when the stem has at least
+    // two vowel-consonant sequences.
+    fn step4(&mut self) {
+        const SUFFIXES: [&'static str; 19] = [
+            "al", "ance", "ence", "er", "ic", "able", "ible", "ant", "ement",
+            "ment", "ent", "ion", "ou", "ism", "ate", "iti", "ous", "ive", "ize",
+        ];
+        for suffix in &SUFFIXES {
+            if self.ends(suffix) {
+                if *suffix == "ion" && !(self.j >= 1 && (self.b[self.j - 1] == b's' || self.b[self.j - 1] == b't')) {
+                    continue;
+                }
+                if self.measure() > 1 {
+                    let j = self.j;
+                    self.b.truncate(j);
+                }
+                return;
+            }
+        }
+    }
+
+    // final -e, and -ll.
+    fn step5(&mut self) {
+        let len = self.b.len();
+        self.j = len;
+        match self.b[len - 1] {
+            b'e' => {
+                let measure = self.measure();
+                if measure > 1 || measure == 1 && !self.ends_with_cvc(len - 1) {
+                    self.b.pop();
+                }
+            }
+            b'l' => {
+                if self.ends_with_double_consonant(len) && self.measure() > 1 {
+                    self.b.pop();
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::stem;
+
+    #[test]
+    fn test_stem() {
+        let words = [
+            ("caresses", "caress"), ("ponies", "poni"), ("ties", "ti"),
+            ("caress", "caress"), ("cats", "cat"), ("feed", "feed"),
+            ("agreed", "agre"), ("plastered", "plaster"), ("bled", "bled"),
+            ("motoring", "motor"), ("sing", "sing"), ("conflated", "conflat"),
+            ("troubled", "troubl"), ("sized", "size"), ("hopping", "hop"),
+            ("tanned", "tan"), ("falling", "fall"), ("hissing", "hiss"),
+            ("fizzed", "fizz"), ("failing", "fail"), ("filing", "file"),
+            ("happy", "happi"), ("sky", "sky"), ("relational", "relat"),
+            ("generalization", "gener"), ("hopeful", "hope"),
+            ("adjustable", "adjust"), ("electricity", "electr"),
+            ("adoption", "adopt"), ("controlling", "control"),
+            ("running", "run"), ("dogs", "dog"),
+            ("go", "go"), ("Dogs", "Dogs"), ("école", "école"),
+        ];
+        for &(word, expected) in &words {
+            assert_eq!(stem(word), expected, "stem of {}", word);
+        }
+    }
+}
@@ -0,0 +1,124 @@
+use super::{Tokenizer, TokenStream, TokenFilter};
+
+
+/// A tokenizer followed by a chain of `TokenFilter`s.
+///
+/// A `TextAnalyzer` is itself a `Tokenizer`, so that it can be
+/// registered in the `TokenizerManager` of an index, and used
+/// for the text of the documents as well as for the text of the queries.
+///
+/// ```rust
+/// use tantivy::{TextAnalyzer, SimpleTokenizer, LowerCaser, StopWordFilter, Stemmer};
+///
+/// let analyzer = TextAnalyzer::new(SimpleTokenizer)
+///     .filter(LowerCaser)
+///     .filter(StopWordFilter::english())
+///     .filter(Stemmer);
+/// ```
+pub struct TextAnalyzer {
+    tokenizer: Box<Tokenizer>,
+    token_filters: Vec<Box<TokenFilter>>,
+}
+
+impl TextAnalyzer {
+
+    /// Creates a `TextAnalyzer` without any filter.
+    pub fn new<T: Tokenizer + 'static>(tokenizer: T) -> TextAnalyzer {
+        TextAnalyzer {
+            tokenizer: box tokenizer,
+            token_filters: Vec::new(),
+        }
+    }
+
+    /// Appends a filter, applied after the filters
+    /// previously appended.
+    pub fn filter<F: TokenFilter + 'static>(mut self, token_filter: F) -> TextAnalyzer {
+        self.token_filters.push(box token_filter);
+        self
+    }
+}
+
+impl Tokenizer for TextAnalyzer {
+    fn token_stream<'a>(&self, text: &'a str) -> Box<TokenStream + 'a> {
+        self.token_stream_with_buffer(text, String::new())
+    }
+
+    fn token_stream_with_buffer<'a>(&self, text: &'a str, buffer: String) -> Box<TokenStream + 'a> {
+        self.token_filters
+            .iter()
+            .fold(self.tokenizer.token_stream_with_buffer(text, buffer),
+                  |token_stream, token_filter| token_filter.transform(token_stream))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use analyzer::{SimpleTokenizer, LowerCaser, StopWordFilter, Stemmer};
+    use core::Index;
+    use indexer::NoMergePolicy;
+    use query::QueryParser;
+    use collector::tests::TestCollector;
+    use schema::{Document, FieldValue, SchemaBuilder, Term, TEXT};
+
+    fn english_analyzer() -> TextAnalyzer {
+        TextAnalyzer::new(SimpleTokenizer)
+            .filter(LowerCaser)
+            .filter(StopWordFilter::english())
+            .filter(Stemmer)
+    }
+
+    #[test]
+    fn test_text_analyzer() {
+        let mut token_stream = english_analyzer().token_stream("The Running Dogs");
+        let mut tokens = Vec::new();
+        while token_stream.advance() {
+            let token = token_stream.token();
+            tokens.push((token.text.clone(), token.position, token.offset_from, token.offset_to));
+        }
+        assert_eq!(tokens, vec!(
+            (String::from("run"), 1, 4, 11),
+            (String::from("dog"), 2, 12, 16),
+        ));
+        let mut token_stream = TextAnalyzer::new(SimpleTokenizer).token_stream("Dogs");
+        assert!(token_stream.advance());
+        assert_eq!(token_stream.token().text, "dogs");
+    }
+
+    #[test]
+    fn test_text_analyzer_index() {
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        schema_builder.set_tokenizer(text_field, "english");
+        let index = Index::create_in_ram(schema_builder.build());
+        index.tokenizers().register("english", english_analyzer());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.set_merge_policy(box NoMergePolicy);
+            index_writer.add_document(doc!(text_field=>"The Running Dogs"));
+            index_writer.add_document(doc!(text_field=>"a dog runs in the park"));
+            index_writer.add_document(doc!(text_field=>"dogs of the park"));
+            assert!(index_writer.commit().is_ok());
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let doc_freq = |text: &str| searcher.doc_freq(&Term::from_field_text(text_field, text));
+        assert_eq!(doc_freq("run"), 2);
+        assert_eq!(doc_freq("dog"), 3);
+        assert_eq!(doc_freq("the"), 0);
+        assert_eq!(doc_freq("running"), 0);
+        let query_parser = QueryParser::for_index(&index, vec![text_field]);
+        let search = |query: &str| {
+            let query = query_parser.parse_query(query).unwrap();
+            let mut test_collector = TestCollector::default();
+            searcher.search(&*query, &mut test_collector).unwrap();
+            test_collector.docs()
+        };
+        assert_eq!(search("RUNS"), vec!(0, 1));
+        assert_eq!(search("\"running dog\""), vec!(0));
+        assert_eq!(search("\"the running dogs\""), vec!(0));
+        assert_eq!(search("\"park dog\""), Vec::<u32>::new());
+    }
+}
@@ -0,0 +1,184 @@
+use std::collections::HashSet;
+use std::mem;
+use std::sync::Arc;
+use super::{TokenStream, Token};
+
+
+/// Transforms the tokens emitted by a tokenizer.
+///
+/// Token filters are chained after a tokenizer in a `TextAnalyzer`.
+/// They are applied lazily, one token at a time, by wrapping
+/// the token stream of the tokenizer.
+///
+/// A filter dropping tokens must keep the position of the
+/// remaining tokens unchanged, so that the gaps left by the dropped
+/// tokens are preserved in the index.
+pub trait TokenFilter: Send + Sync {
+    /// Wraps `token_stream` into the token stream
+    /// of the filtered tokens.
+    fn transform<'a>(&self, token_stream: Box<TokenStream + 'a>) -> Box<TokenStream + 'a>;
+}
+
+
+/// Lowercases the text of the tokens.
+///
+/// Unlike the `SimpleTokenizer`, which only lowercases
+/// ASCII characters, all of the unicode characters are lowercased.
+pub struct LowerCaser;
+
+/// Token stream of the `LowerCaser`.
+pub struct LowerCaserTokenStream<'a> {
+    tail: Box<TokenStream + 'a>,
+    buffer: String,
+}
+
+impl<'a> TokenStream for LowerCaserTokenStream<'a> {
+
+    fn advance(&mut self) -> bool {
+        if !self.tail.advance() {
+            return false;
+        }
+        let token = self.tail.token_mut();
+        if token.text.chars().any(|c| c.is_uppercase()) {
+            self.buffer.clear();
+            for c in token.text.chars() {
+                self.buffer.extend(c.to_lowercase());
+            }
+            mem::swap(&mut token.text, &mut self.buffer);
+        }
+        true
+    }
+
+    fn token(&self) -> &Token {
+        self.tail.token()
+    }
+
+    fn token_mut(&mut self) -> &mut Token {
+        self.tail.token_mut()
+    }
+
+    fn into_buffer(self: Box<Self>) -> String {
+        self.tail.into_buffer()
+    }
+}
+
+impl TokenFilter for LowerCaser {
+    fn transform<'a>(&self, token_stream: Box<TokenStream + 'a>) -> Box<TokenStream + 'a> {
+        box LowerCaserTokenStream {
+            tail: token_stream,
+            buffer: String::new(),
+        }
+    }
+}
+
+
+/// Common english words, removed by `StopWordFilter::english()`.
+pub const ENGLISH_STOP_WORDS: [&'static str; 33] = [
+    "a", "an", "and", "are", "as", "at", "be", "but", "by",
+    "for", "if", "in", "into", "is", "it", "no", "not", "of",
+    "on", "or", "such", "that", "the", "their", "then", "there",
+    "these", "they", "this", "to", "was", "will", "with",
+];
+
+/// Removes the tokens belonging to a list of stop words.
+///
+/// The words are compared to the text of the tokens as is,
+/// so the `StopWordFilter` typically comes after a `LowerCaser`.
+///
+/// The positions of the remaining tokens are left unchanged.
+/// For instance, in `the running dogs`, `running` keeps
+/// the position 1.
+#[derive(Clone)]
+pub struct StopWordFilter {
+    words: Arc<HashSet<String>>,
+}
+
+impl StopWordFilter {
+
+    /// Creates a `StopWordFilter` removing `words`.
+    pub fn new<S: Into<String>>(words: Vec<S>) -> StopWordFilter {
+        StopWordFilter {
+            words: Arc::new(words.into_iter().map(Into::into).collect()),
+        }
+    }
+
+    /// Creates a `StopWordFilter` removing
+    /// the `ENGLISH_STOP_WORDS`.
+    pub fn english() -> StopWordFilter {
+        StopWordFilter::new(ENGLISH_STOP_WORDS.to_vec())
+    }
+}
+
+/// Token stream of the `StopWordFilter`.
+pub struct StopWordFilterTokenStream<'a> {
+    tail: Box<TokenStream + 'a>,
+    words: Arc<HashSet<String>>,
+}
+
+impl<'a> TokenStream for StopWordFilterTokenStream<'a> {
+
+    fn advance(&mut self) -> bool {
+        while self.tail.advance() {
+            if !self.words.contains(&self.tail.token().text) {
+                return true;
+            }
+        }
+        false
+    }
+
+    fn token(&self) -> &Token {
+        self.tail.token()
+    }
+
+    fn token_mut(&mut self) -> &mut Token {
+        self.tail.token_mut()
+    }
+
+    fn into_buffer(self: Box<Self>) -> String {
+        self.tail.into_buffer()
+    }
+}
+
+impl TokenFilter for StopWordFilter {
+    fn transform<'a>(&self, token_stream: Box<TokenStream + 'a>) -> Box<TokenStream + 'a> {
+        box StopWordFilterTokenStream {
+            tail: token_stream,
+            words: self.words.clone(),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use analyzer::{Tokenizer, RawTokenizer, SimpleTokenizer};
+
+    fn token_vec(token_stream: &mut TokenStream) -> Vec<(String, u32)> {
+        let mut tokens = Vec::new();
+        while token_stream.advance() {
+            let token = token_stream.token();
+            tokens.push((token.text.clone(), token.position));
+        }
+        tokens
+    }
+
+    #[test]
+    fn test_lower_caser() {
+        let mut token_stream = LowerCaser.transform(RawTokenizer.token_stream("ÉCOLE Tax"));
+        assert_eq!(token_vec(&mut *token_stream), vec!((String::from("école tax"), 0)));
+    }
+
+    #[test]
+    fn test_stop_word_filter() {
+        let stop_word_filter = StopWordFilter::new(vec!("the", "of"));
+        let mut token_stream = stop_word_filter.transform(SimpleTokenizer.token_stream("the tale of the bard"));
+        assert_eq!(token_vec(&mut *token_stream), vec!(
+            (String::from("tale"), 1),
+            (String::from("bard"), 4),
+        ));
+        let mut token_stream = StopWordFilter::english().transform(SimpleTokenizer.token_stream("to be or not to be"));
+        assert!(token_vec(&mut *token_stream).is_empty());
+    }
+}
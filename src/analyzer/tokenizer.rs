@@ -0,0 +1,279 @@
+use std::ascii::AsciiExt;
+use std::str::CharIndices;
+use super::{SimpleTokenizer, CjkTokenizer, CjkTokenIter, Token};
+
+
+/// Splits a text into tokens, at indexing time as well as
+/// at query time.
+///
+/// Tokenizers are registered by name in a `TokenizerManager`,
+/// and text fields refer to them by name, see `SchemaBuilder::set_tokenizer`.
+pub trait Tokenizer: Send + Sync {
+    /// Returns the stream of the tokens of `text`.
+    fn token_stream<'a>(&self, text: &'a str) -> Box<TokenStream + 'a>;
+
+    /// Returns the stream of the tokens of `text`, whose
+    /// current token reuses the allocation of `buffer`.
+    ///
+    /// The buffer can be recovered via `TokenStream::into_buffer`,
+    /// and reused from one text to another.
+    /// By default, the buffer is dropped.
+    fn token_stream_with_buffer<'a>(&self, text: &'a str, buffer: String) -> Box<TokenStream + 'a> {
+        let _ = buffer;
+        self.token_stream(text)
+    }
+}
+
+/// Stream of the tokens of a text, in the order of their positions.
+///
+/// The current token is reused from one token to
+/// another, to avoid an allocation per token.
+pub trait TokenStream {
+    /// Advances to the next token.
+    ///
+    /// Returns false if there are no more tokens.
+    fn advance(&mut self) -> bool;
+
+    /// Returns the current token.
+    ///
+    /// Its value is unspecified before the first call
+    /// to `.advance()`, or after `.advance()` returned false.
+    fn token(&self) -> &Token;
+
+    /// Returns the current token, mutably.
+    ///
+    /// This is used by the `TokenFilter`s to modify
+    /// the token in place.
+    fn token_mut(&mut self) -> &mut Token;
+
+    /// Consumes the stream, and returns the buffer
+    /// of the text of its token, cleared.
+    ///
+    /// See `Tokenizer::token_stream_with_buffer`.
+    /// By default, the buffer is dropped and a new one is returned.
+    fn into_buffer(self: Box<Self>) -> String {
+        String::new()
+    }
+}
+
+fn empty_token() -> Token {
+    Token {
+        text: String::new(),
+        offset_from: 0,
+        offset_to: 0,
+        position: 0,
+    }
+}
+
+
+/// Token stream of the `SimpleTokenizer`.
+pub struct SimpleTokenStream<'a> {
+    chars: CharIndices<'a>,
+    token: Token,
+    num_tokens: u32,
+}
+
+impl<'a> TokenStream for SimpleTokenStream<'a> {
+
+    fn advance(&mut self) -> bool {
+        self.token.text.clear();
+        // skipping non-letter characters.
+        while let Some((offset, c)) = self.chars.next() {
+            if c.is_alphanumeric() {
+                self.token.offset_from = offset;
+                self.token.offset_to = offset + c.len_utf8();
+                self.token.text.push(c.to_ascii_lowercase());
+                for (offset, c) in &mut self.chars {
+                    if !c.is_alphanumeric() {
+                        break;
+                    }
+                    self.token.offset_to = offset + c.len_utf8();
+                    self.token.text.push(c.to_ascii_lowercase());
+                }
+                self.token.position = self.num_tokens;
+                self.num_tokens += 1;
+                return true;
+            }
+        }
+        false
+    }
+
+    fn token(&self) -> &Token {
+        &self.token
+    }
+
+    fn token_mut(&mut self) -> &mut Token {
+        &mut self.token
+    }
+
+    fn into_buffer(self: Box<Self>) -> String {
+        let mut buffer = self.token.text;
+        buffer.clear();
+        buffer
+    }
+}
+
+impl Tokenizer for SimpleTokenizer {
+    fn token_stream<'a>(&self, text: &'a str) -> Box<TokenStream + 'a> {
+        self.token_stream_with_buffer(text, String::new())
+    }
+
+    fn token_stream_with_buffer<'a>(&self, text: &'a str, mut buffer: String) -> Box<TokenStream + 'a> {
+        buffer.clear();
+        let mut token = empty_token();
+        token.text = buffer;
+        box SimpleTokenStream {
+            chars: text.char_indices(),
+            token: token,
+            num_tokens: 0u32,
+        }
+    }
+}
+
+
+/// Emits the whole text as a single token, as is.
+///
+/// This is how untokenized fields are indexed.
+pub struct RawTokenizer;
+
+/// Token stream of the `RawTokenizer`.
+pub struct RawTokenStream {
+    token: Token,
+    has_token: bool,
+}
+
+impl TokenStream for RawTokenStream {
+
+    fn advance(&mut self) -> bool {
+        let has_token = self.has_token;
+        self.has_token = false;
+        has_token
+    }
+
+    fn token(&self) -> &Token {
+        &self.token
+    }
+
+    fn token_mut(&mut self) -> &mut Token {
+        &mut self.token
+    }
+}
+
+impl Tokenizer for RawTokenizer {
+    fn token_stream<'a>(&self, text: &'a str) -> Box<TokenStream + 'a> {
+        box RawTokenStream {
+            token: Token {
+                text: String::from(text),
+                offset_from: 0,
+                offset_to: text.len(),
+                position: 0,
+            },
+            has_token: true,
+        }
+    }
+}
+
+
+/// Token stream of the `CjkTokenizer`.
+pub struct CjkTokenStream<'a> {
+    token_iter: CjkTokenIter<'a>,
+    token: Token,
+}
+
+impl<'a> TokenStream for CjkTokenStream<'a> {
+
+    fn advance(&mut self) -> bool {
+        match self.token_iter.next() {
+            Some(token) => {
+                self.token = token;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn token(&self) -> &Token {
+        &self.token
+    }
+
+    fn token_mut(&mut self) -> &mut Token {
+        &mut self.token
+    }
+}
+
+impl Tokenizer for CjkTokenizer {
+    fn token_stream<'a>(&self, text: &'a str) -> Box<TokenStream + 'a> {
+        box CjkTokenStream {
+            token_iter: self.tokenize(text),
+            token: empty_token(),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use analyzer::StreamingIterator;
+
+    fn token_vec(tokenizer: &Tokenizer, text: &str) -> Vec<Token> {
+        let mut token_stream = tokenizer.token_stream(text);
+        let mut tokens = Vec::new();
+        while token_stream.advance() {
+            tokens.push(token_stream.token().clone());
+        }
+        tokens
+    }
+
+    #[test]
+    fn test_simple_token_stream() {
+        let text = "Hello, happy Tax payer!";
+        let tokens = token_vec(&SimpleTokenizer, text);
+        let token_texts: Vec<&str> = tokens.iter().map(|token| &token.text[..]).collect();
+        assert_eq!(token_texts, vec!("hello", "happy", "tax", "payer"));
+        for (position, token) in tokens.iter().enumerate() {
+            assert_eq!(token.position, position as u32);
+            assert_eq!(text[token.offset_from..token.offset_to].to_lowercase(), token.text);
+        }
+        // the token stream and the `TokenIter` agree.
+        let mut token_iter = SimpleTokenizer.tokenize(text);
+        for token in &tokens {
+            assert_eq!(token_iter.next(), Some(&token.text[..]));
+        }
+        assert!(token_vec(&SimpleTokenizer, " !? ").is_empty());
+    }
+
+    #[test]
+    fn test_token_stream_with_buffer() {
+        let mut buffer = String::with_capacity(100);
+        for &(text, expected) in &[("supercalifragilistic expialidocious", "supercalifragilistic"), ("tax", "tax")] {
+            let buffer_ptr = buffer.as_ptr();
+            let mut token_stream = SimpleTokenizer.token_stream_with_buffer(text, buffer);
+            assert!(token_stream.advance());
+            assert_eq!(token_stream.token().text, expected);
+            assert_eq!(token_stream.token().text.as_ptr(), buffer_ptr);
+            buffer = token_stream.into_buffer();
+            assert!(buffer.is_empty());
+            assert_eq!(buffer.as_ptr(), buffer_ptr);
+        }
+    }
+
+    #[test]
+    fn test_raw_token_stream() {
+        assert_eq!(token_vec(&RawTokenizer, "Hello, World"), vec!(Token {
+            text: String::from("Hello, World"),
+            offset_from: 0,
+            offset_to: 12,
+            position: 0,
+        }));
+    }
+
+    #[test]
+    fn test_cjk_token_stream() {
+        let text = "東京タワー tower";
+        let tokens = token_vec(&CjkTokenizer, text);
+        let expected: Vec<Token> = CjkTokenizer.tokenize(text).collect();
+        assert_eq!(tokens, expected);
+    }
+}
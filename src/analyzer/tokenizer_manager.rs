@@ -0,0 +1,99 @@
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use super::{Tokenizer, SimpleTokenizer, RawTokenizer, CjkTokenizer, UrlEmailTokenizer};
+use super::{TextAnalyzer, LowerCaser, StopWordFilter, Stemmer};
+
+
+/// Name of the tokenizer used by the text fields that
+/// do not define one.
+pub const DEFAULT_TOKENIZER: &'static str = "default";
+
+/// Registry of the tokenizers of an index, by name.
+///
+/// Text fields refer to their tokenizer by name, so that
+/// the same tokenizer is used at indexing time, by the `IndexWriter`,
+/// and at query time, by the `QueryParser`.
+///
+/// The following tokenizers are registered by default:
+///
+/// - `default`: the `SimpleTokenizer`,
+/// - `raw`: the `RawTokenizer`,
+/// - `cjk`: the `CjkTokenizer`,
+/// - `url_email`: the `UrlEmailTokenizer`,
+/// - `en_stem`: a `TextAnalyzer` lowercasing the tokens of the `SimpleTokenizer`,
+///   removing the english stop words and stemming the remaining words.
+///
+/// Clones of a `TokenizerManager` share the same tokenizers:
+/// a tokenizer registered on the manager of an `Index` is
+/// visible from all of its clones.
+#[derive(Clone)]
+pub struct TokenizerManager {
+    tokenizers: Arc<RwLock<HashMap<String, Arc<Tokenizer>>>>,
+}
+
+impl TokenizerManager {
+
+    /// Registers a tokenizer under `name`, replacing the
+    /// tokenizer previously registered under this name if any.
+    ///
+    /// Tokenizers must be registered before the `IndexWriter`
+    /// is created, and a tokenizer that was used to index
+    /// documents should not be replaced afterwards.
+    pub fn register<T: Tokenizer + 'static>(&self, name: &str, tokenizer: T) {
+        self.tokenizers
+            .write()
+            .unwrap()
+            .insert(String::from(name), Arc::new(tokenizer));
+    }
+
+    /// Returns the tokenizer registered under `name`.
+    pub fn get(&self, name: &str) -> Option<Arc<Tokenizer>> {
+        self.tokenizers
+            .read()
+            .unwrap()
+            .get(name)
+            .cloned()
+    }
+}
+
+impl Default for TokenizerManager {
+    fn default() -> TokenizerManager {
+        let tokenizer_manager = TokenizerManager {
+            tokenizers: Arc::default(),
+        };
+        tokenizer_manager.register(DEFAULT_TOKENIZER, SimpleTokenizer);
+        tokenizer_manager.register("raw", RawTokenizer);
+        tokenizer_manager.register("cjk", CjkTokenizer);
+        tokenizer_manager.register("url_email", UrlEmailTokenizer);
+        tokenizer_manager.register("en_stem",
+            TextAnalyzer::new(SimpleTokenizer)
+                .filter(LowerCaser)
+                .filter(StopWordFilter::english())
+                .filter(Stemmer));
+        tokenizer_manager
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_tokenizer_manager() {
+        let tokenizer_manager = TokenizerManager::default();
+        assert!(tokenizer_manager.get(DEFAULT_TOKENIZER).is_some());
+        assert!(tokenizer_manager.get("raw").is_some());
+        assert!(tokenizer_manager.get("en_stem").is_some());
+        assert!(tokenizer_manager.get("url_email").is_some());
+        assert!(tokenizer_manager.get("code").is_none());
+        let clone = tokenizer_manager.clone();
+        clone.register("code", RawTokenizer);
+        let tokenizer = tokenizer_manager.get("code").unwrap();
+        let mut token_stream = tokenizer.token_stream("camelCase");
+        assert!(token_stream.advance());
+        assert_eq!(token_stream.token().text, "camelCase");
+        assert!(!token_stream.advance());
+    }
+}
@@ -0,0 +1,180 @@
+use std::ascii::AsciiExt;
+use regex::Regex;
+use super::{Tokenizer, TokenStream, Token};
+
+
+lazy_static! {
+    static ref URL_EMAIL_PTN: Regex = Regex::new(
+        r#"(?i)(?:\b[a-z][a-z0-9+.-]*://|\bwww\.)[^\s<>"'()\[\]{}]+|[a-z0-9._%+-]+@[a-z0-9-]+(?:\.[a-z0-9-]+)+"#
+    ).unwrap();
+}
+
+// punctuation ending a sentence rather than an url.
+fn is_trailing_punctuation(c: char) -> bool {
+    match c {
+        '.' | ',' | ';' | ':' | '!' | '?' => true,
+        _ => false,
+    }
+}
+
+/// Splits a text into words as the `SimpleTokenizer` does,
+/// but keeps the urls and the email addresses as single tokens.
+///
+/// For instance, `contact me at a@b.com or http://x.io` is split into
+/// `contact`, `me`, `at`, `a@b.com`, `or` and `http://x.io`,
+/// so that a `TermQuery` on an email address matches exactly.
+///
+/// Urls start with a scheme (`http://`, `ftp://`...) or with `www.`,
+/// and end at the next whitespace or bracket. The punctuation at the
+/// end of a url is assumed to belong to the sentence, and is left out.
+///
+/// As with the `SimpleTokenizer`, the tokens are lowercased
+/// (ASCII only), and their offsets are their byte range in the text.
+#[derive(Clone, Copy, Debug)]
+pub struct UrlEmailTokenizer;
+
+/// Token stream of the `UrlEmailTokenizer`.
+pub struct UrlEmailTokenStream<'a> {
+    text: &'a str,
+    // byte ranges of the urls and email addresses of the text.
+    matches: Vec<(usize, usize)>,
+    next_match: usize,
+    offset: usize,
+    token: Token,
+    num_tokens: u32,
+}
+
+impl<'a> UrlEmailTokenStream<'a> {
+    fn emit(&mut self, offset_from: usize, offset_to: usize) {
+        self.token.offset_from = offset_from;
+        self.token.offset_to = offset_to;
+        self.token.text.clear();
+        for c in self.text[offset_from..offset_to].chars() {
+            self.token.text.push(c.to_ascii_lowercase());
+        }
+        self.token.position = self.num_tokens;
+        self.num_tokens += 1;
+        self.offset = offset_to;
+    }
+
+    fn next_match_start(&self) -> usize {
+        self.matches
+            .get(self.next_match)
+            .map(|&(offset_from, _)| offset_from)
+            .unwrap_or(self.text.len())
+    }
+}
+
+impl<'a> TokenStream for UrlEmailTokenStream<'a> {
+
+    fn advance(&mut self) -> bool {
+        while self.offset < self.text.len() {
+            let next_match_start = self.next_match_start();
+            if self.offset == next_match_start {
+                let (offset_from, offset_to) = self.matches[self.next_match];
+                self.next_match += 1;
+                self.emit(offset_from, offset_to);
+                return true;
+            }
+            let c = self.text[self.offset..].chars().next().unwrap();
+            if c.is_alphanumeric() {
+                // the word stops at the next url or email address.
+                let offset_from = self.offset;
+                let mut offset_to = self.offset;
+                for (offset, c) in self.text[offset_from..next_match_start].char_indices() {
+                    if !c.is_alphanumeric() {
+                        break;
+                    }
+                    offset_to = offset_from + offset + c.len_utf8();
+                }
+                self.emit(offset_from, offset_to);
+                return true;
+            }
+            self.offset += c.len_utf8();
+        }
+        false
+    }
+
+    fn token(&self) -> &Token {
+        &self.token
+    }
+
+    fn token_mut(&mut self) -> &mut Token {
+        &mut self.token
+    }
+}
+
+impl Tokenizer for UrlEmailTokenizer {
+    fn token_stream<'a>(&self, text: &'a str) -> Box<TokenStream + 'a> {
+        let matches: Vec<(usize, usize)> = URL_EMAIL_PTN
+            .find_iter(text)
+            .map(|url_or_email| {
+                let url_or_email_text = url_or_email.as_str().trim_right_matches(is_trailing_punctuation);
+                (url_or_email.start(), url_or_email.start() + url_or_email_text.len())
+            })
+            .filter(|&(offset_from, offset_to)| offset_from < offset_to)
+            .collect();
+        box UrlEmailTokenStream {
+            text: text,
+            matches: matches,
+            next_match: 0,
+            offset: 0,
+            token: Token {
+                text: String::new(),
+                offset_from: 0,
+                offset_to: 0,
+                position: 0,
+            },
+            num_tokens: 0u32,
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn token_vec(text: &str) -> Vec<(String, usize, usize, u32)> {
+        let mut token_stream = UrlEmailTokenizer.token_stream(text);
+        let mut tokens = Vec::new();
+        while token_stream.advance() {
+            let token = token_stream.token();
+            tokens.push((token.text.clone(), token.offset_from, token.offset_to, token.position));
+        }
+        tokens
+    }
+
+    fn token_texts(text: &str) -> Vec<String> {
+        token_vec(text).into_iter().map(|(token_text, _, _, _)| token_text).collect()
+    }
+
+    #[test]
+    fn test_url_email_tokenizer() {
+        let text = "contact me at a@b.com or http://x.io";
+        let tokens = token_vec(text);
+        assert_eq!(tokens, vec!(
+            (String::from("contact"), 0, 7, 0),
+            (String::from("me"), 8, 10, 1),
+            (String::from("at"), 11, 13, 2),
+            (String::from("a@b.com"), 14, 21, 3),
+            (String::from("or"), 22, 24, 4),
+            (String::from("http://x.io"), 25, 36, 5),
+        ));
+        for (token_text, offset_from, offset_to, _) in tokens {
+            assert_eq!(&text[offset_from..offset_to], &token_text[..]);
+        }
+    }
+
+    #[test]
+    fn test_url_email_tokenizer_punctuation() {
+        assert_eq!(token_texts("See https://tantivy.rs/docs?q=1, or Mail@Example.org."),
+                   vec!("see", "https://tantivy.rs/docs?q=1", "or", "mail@example.org"));
+        assert_eq!(token_texts("(www.example.com) é-mail: first.last+tag@sub.example.co.uk!"),
+                   vec!("www.example.com", "é", "mail", "first.last+tag@sub.example.co.uk"));
+        // neither an url nor an email address.
+        assert_eq!(token_texts("a@b, http: x.io"), vec!("a", "b", "http", "x", "io"));
+        assert!(token_texts(" ... ").is_empty());
+    }
+}
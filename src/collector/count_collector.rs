@@ -1,4 +1,4 @@
-use super::Collector;
+use super::{Collector, FruitCollector, SegmentCollector};
 use DocId;
 use Score;
 use Result;
@@ -37,6 +37,90 @@ impl Collector for CountCollector {
     }
 }
 
+
+/// `FruitCollector` counting how many documents
+/// match the query.
+///
+/// Its fruit is the count.
+#[derive(Default)]
+pub struct Count;
+
+/// Collector of a segment for `Count`.
+#[derive(Default)]
+pub struct CountSegmentCollector {
+    count: usize,
+}
+
+impl SegmentCollector for CountSegmentCollector {
+    type Fruit = usize;
+
+    fn collect(&mut self, _: DocId, _: Score) {
+        self.count += 1;
+    }
+
+    fn harvest(self) -> usize {
+        self.count
+    }
+}
+
+impl FruitCollector for Count {
+    type Fruit = usize;
+    type Child = CountSegmentCollector;
+
+    fn for_segment(&self, _: SegmentLocalId, _: &SegmentReader) -> Result<CountSegmentCollector> {
+        Ok(CountSegmentCollector::default())
+    }
+
+    fn merge_fruits(&self, counts: Vec<usize>) -> usize {
+        counts.into_iter().sum()
+    }
+}
+
+
+/// `FruitCollector` counting how many documents match
+/// the query, and summing up their scores.
+///
+/// Its fruit is the `(count, sum of the scores)` pair.
+#[derive(Default)]
+pub struct CountAndScoreSum;
+
+/// Collector of a segment for `CountAndScoreSum`.
+#[derive(Default)]
+pub struct CountAndScoreSumSegmentCollector {
+    count: usize,
+    score_sum: Score,
+}
+
+impl SegmentCollector for CountAndScoreSumSegmentCollector {
+    type Fruit = (usize, Score);
+
+    fn collect(&mut self, _: DocId, score: Score) {
+        self.count += 1;
+        self.score_sum += score;
+    }
+
+    fn harvest(self) -> (usize, Score) {
+        (self.count, self.score_sum)
+    }
+}
+
+impl FruitCollector for CountAndScoreSum {
+    type Fruit = (usize, Score);
+    type Child = CountAndScoreSumSegmentCollector;
+
+    fn for_segment(&self, _: SegmentLocalId, _: &SegmentReader) -> Result<CountAndScoreSumSegmentCollector> {
+        Ok(CountAndScoreSumSegmentCollector::default())
+    }
+
+    fn merge_fruits(&self, fruits: Vec<(usize, Score)>) -> (usize, Score) {
+        fruits
+            .into_iter()
+            .fold((0, 0f32), |(count, score_sum), (segment_count, segment_score_sum)| {
+                (count + segment_count, score_sum + segment_score_sum)
+            })
+    }
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -0,0 +1,60 @@
+use super::Collector;
+use DocId;
+use Score;
+use Result;
+use SegmentReader;
+use SegmentLocalId;
+use DocAddress;
+use std::collections::HashSet;
+
+/// `ExcludingCollector` wraps another collector and 
+/// does not push it the documents of a given set of `DocAddress`es.
+///
+/// The excluded documents are simply skipped : 
+/// the scores of the other documents are not affected.
+///
+/// See `Searcher::search_excluding`.
+pub struct ExcludingCollector<'a, C: Collector> {
+    inner: C,
+    excluded: &'a HashSet<DocAddress>,
+    segment_local_id: SegmentLocalId,
+}
+
+impl<'a, C: Collector> ExcludingCollector<'a, C> {
+    /// Wraps a collector, excluding the documents of `excluded`.
+    pub fn wrap(inner: C, excluded: &'a HashSet<DocAddress>) -> ExcludingCollector<'a, C> {
+        ExcludingCollector {
+            inner: inner,
+            excluded: excluded,
+            segment_local_id: 0,
+        }
+    }
+
+    /// Returns the underlying collector.
+    pub fn inner(&self,) -> &C {
+        &self.inner
+    }
+
+    /// Consumes the `ExcludingCollector` and returns
+    /// the underlying collector.
+    pub fn into_inner(self,) -> C {
+        self.inner
+    }
+}
+
+impl<'a, C: Collector> Collector for ExcludingCollector<'a, C> {
+    fn set_segment(&mut self, segment_local_id: SegmentLocalId, segment: &SegmentReader) -> Result<()> {
+        self.segment_local_id = segment_local_id;
+        self.inner.set_segment(segment_local_id, segment)
+    }
+
+    fn collect(&mut self, doc: DocId, score: Score) {
+        if !self.excluded.contains(&DocAddress(self.segment_local_id, doc)) {
+            self.inner.collect(doc, score);
+        }
+    }
+
+    fn is_aborted(&self,) -> bool {
+        self.inner.is_aborted()
+    }
+}
@@ -0,0 +1,240 @@
+use std::collections::BTreeMap;
+use std::str;
+use bit_set::BitSet;
+use super::Collector;
+use DocId;
+use Score;
+use Result;
+use SegmentReader;
+use SegmentLocalId;
+use postings::SegmentPostingsOption;
+use postings::DocSet;
+use schema::{Field, Term, Facet};
+use schema::FACET_SEP;
+
+
+struct FacetCounts {
+    facet: Facet,
+    counts: BTreeMap<Facet, usize>,
+    // documents of the current segment, for each child of `facet`.
+    segment_children: Vec<(Facet, BitSet)>,
+}
+
+/// `FacetCollector` counts the documents matching the query
+/// under each child of a set of facets.
+///
+/// For instance, for the facet `/electronics`, it returns
+/// the number of documents under `/electronics/phones`,
+/// `/electronics/laptops`, ...
+/// A document is counted once under a child, even if several
+/// of its facets are descendants of this child.
+///
+/// The facets are read from the term dictionary of `field`,
+/// the untokenized text field in which they are indexed.
+/// On each segment, the subtree of each facet is scanned, and the
+/// documents of each child are gathered in a bitset, so that
+/// the cost of a collected document is proportional to the number
+/// of children.
+pub struct FacetCollector {
+    field: Field,
+    facet_counts: Vec<FacetCounts>,
+}
+
+impl FacetCollector {
+
+    /// Creates a facet collector reading the facets of `field`.
+    pub fn for_field(field: Field) -> FacetCollector {
+        FacetCollector {
+            field: field,
+            facet_counts: Vec::new(),
+        }
+    }
+
+    /// Counts the documents under each child of `facet`.
+    ///
+    /// Facets must be added before the search.
+    pub fn add_facet(&mut self, facet: Facet) {
+        if self.facet_counts.iter().any(|facet_counts| facet_counts.facet == facet) {
+            return;
+        }
+        self.facet_counts.push(FacetCounts {
+            facet: facet,
+            counts: BTreeMap::new(),
+            segment_children: Vec::new(),
+        });
+    }
+
+    /// Returns the children of `facet` containing at least one
+    /// of the collected documents, in lexicographic order,
+    /// together with their number of documents.
+    ///
+    /// The result is empty if `facet` was not added.
+    pub fn counts(&self, facet: &Facet) -> Vec<(Facet, usize)> {
+        self.facet_counts
+            .iter()
+            .find(|facet_counts| facet_counts.facet == *facet)
+            .map(|facet_counts| {
+                facet_counts.counts
+                    .iter()
+                    .filter(|&(_, &count)| count > 0)
+                    .map(|(child, &count)| (child.clone(), count))
+                    .collect()
+            })
+            .unwrap_or_else(Vec::new)
+    }
+}
+
+// Returns the encoded child of the facet encoded as `prefix`
+// which `facet` descends from, or None if `facet` is not
+// a strict descendant of `prefix`.
+fn child_facet<'a>(prefix: &str, facet: &'a str) -> Option<&'a str> {
+    if facet.len() <= prefix.len() || !facet.starts_with(prefix) {
+        return None;
+    }
+    let suffix = &facet[prefix.len()..];
+    if !suffix.starts_with(FACET_SEP) {
+        // `/electronics` is not a descendant of `/elec`.
+        return None;
+    }
+    let child_len = suffix[1..]
+        .find(FACET_SEP)
+        .map(|pos| pos + 1)
+        .unwrap_or(suffix.len());
+    Some(&facet[..prefix.len() + child_len])
+}
+
+impl Collector for FacetCollector {
+
+    fn set_segment(&mut self, _: SegmentLocalId, reader: &SegmentReader) -> Result<()> {
+        let max_doc = reader.max_doc() as usize;
+        for facet_counts in &mut self.facet_counts {
+            let mut segment_children: BTreeMap<Facet, BitSet> = BTreeMap::new();
+            let prefix = Term::from_field_text(self.field, facet_counts.facet.encoded_str());
+            for (term_bytes, _) in reader.term_infos().prefix_iter(prefix.as_slice()) {
+                let term = Term::from_bytes(&term_bytes);
+                let child = match str::from_utf8(term.value())
+                    .ok()
+                    .and_then(|facet| child_facet(facet_counts.facet.encoded_str(), facet)) {
+                    Some(child) => Facet::from_text(child),
+                    None => {
+                        continue;
+                    }
+                };
+                if let Some(mut postings) = reader.read_postings(&term, SegmentPostingsOption::NoFreq) {
+                    let docs = segment_children
+                        .entry(child)
+                        .or_insert_with(|| BitSet::with_capacity(max_doc));
+                    while postings.advance() {
+                        docs.insert(postings.doc() as usize);
+                    }
+                }
+            }
+            for child in segment_children.keys() {
+                facet_counts.counts.entry(child.clone()).or_insert(0);
+            }
+            facet_counts.segment_children = segment_children.into_iter().collect();
+        }
+        Ok(())
+    }
+
+    fn collect(&mut self, doc: DocId, _: Score) {
+        for facet_counts in &mut self.facet_counts {
+            for &(ref child, ref docs) in &facet_counts.segment_children {
+                if docs.contains(doc as usize) {
+                    *facet_counts.counts.get_mut(child).expect("Child is registered on set_segment") += 1;
+                }
+            }
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use super::child_facet;
+    use core::Index;
+    use indexer::NoMergePolicy;
+    use schema::{Document, FieldValue, SchemaBuilder, TEXT, STRING};
+    use query::{Query, TermQuery};
+
+    #[test]
+    fn test_child_facet() {
+        assert_eq!(child_facet("/a", "/a/b/c"), Some("/a/b"));
+        assert_eq!(child_facet("/a", "/a/b"), Some("/a/b"));
+        assert_eq!(child_facet("", "/a/b"), Some("/a"));
+        assert_eq!(child_facet("/a", "/a"), None);
+        assert_eq!(child_facet("/a", "/ab/c"), None);
+        assert_eq!(child_facet("/a/b", "/a"), None);
+    }
+
+    #[test]
+    fn test_facet_collector() {
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let category_field = schema_builder.add_text_field("category", STRING);
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.set_merge_policy(box NoMergePolicy);
+            let products: Vec<(&str, Vec<&str>)> = vec!(
+                ("phone on sale", vec!("/electronics/phones/smartphones")),
+                ("phone on sale", vec!("/electronics/phones/smartphones", "/electronics/phones/accessories")),
+                ("laptop on sale", vec!("/electronics/laptops")),
+                ("laptop", vec!("/electronics/laptops")),
+                ("tablet on sale", vec!("/electronics/tablets", "/electronics/laptops/convertibles")),
+                ("cable on sale", vec!("/electronics")),
+                ("elec on sale", vec!("/elec/misc")),
+                ("shirt on sale", vec!("/clothing/shirts")),
+            );
+            for (i, &(text, ref categories)) in products.iter().enumerate() {
+                let mut doc = Document::default();
+                doc.add_text(text_field, text);
+                for category in categories {
+                    doc.add_text(category_field, Facet::from_text(category).encoded_str());
+                }
+                index_writer.add_document(doc);
+                if i == 3 {
+                    index_writer.commit().unwrap();
+                }
+            }
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        assert_eq!(searcher.segment_readers().len(), 2);
+        let query = TermQuery::new(Term::from_field_text(text_field, "sale"), SegmentPostingsOption::NoFreq);
+        let mut facet_collector = FacetCollector::for_field(category_field);
+        facet_collector.add_facet(Facet::root());
+        facet_collector.add_facet(Facet::from_text("/electronics"));
+        facet_collector.add_facet(Facet::from_text("/electronics/phones"));
+        facet_collector.add_facet(Facet::from_text("/books"));
+        query.search(&searcher, &mut facet_collector).unwrap();
+        let counts = |facet: &str| -> Vec<(String, usize)> {
+            facet_collector
+                .counts(&Facet::from_text(facet))
+                .into_iter()
+                .map(|(child, count)| (format!("{}", child), count))
+                .collect()
+        };
+        assert_eq!(counts("/"), vec!(
+            ("/clothing".to_string(), 1),
+            ("/elec".to_string(), 1),
+            ("/electronics".to_string(), 5),
+        ));
+        // the tablet is counted once under `/electronics/laptops`,
+        // and the laptop which is not on sale is not counted.
+        assert_eq!(counts("/electronics"), vec!(
+            ("/electronics/laptops".to_string(), 2),
+            ("/electronics/phones".to_string(), 2),
+            ("/electronics/tablets".to_string(), 1),
+        ));
+        assert_eq!(counts("/electronics/phones"), vec!(
+            ("/electronics/phones/accessories".to_string(), 1),
+            ("/electronics/phones/smartphones".to_string(), 2),
+        ));
+        assert!(counts("/books").is_empty());
+        assert!(counts("/clothing").is_empty());
+    }
+}
@@ -0,0 +1,122 @@
+use super::Collector;
+use DocId;
+use Score;
+use Result;
+use SegmentReader;
+use SegmentLocalId;
+
+/// `LimitedCollector` wraps another collector and aborts
+/// the search once more than `max_docs_scored` documents
+/// have been evaluated.
+///
+/// It is meant to protect against queries that are too expensive.
+/// The first `max_docs_scored` documents are pushed to the underlying 
+/// collector, which then holds a partial result. 
+/// `.is_over_budget()` tells whether the search was aborted.
+pub struct LimitedCollector<C: Collector> {
+    inner: C,
+    max_docs_scored: usize,
+    num_docs_scored: usize,
+    over_budget: bool,
+}
+
+impl<C: Collector> LimitedCollector<C> {
+    /// Wraps a collector, limiting the number of documents
+    /// that may be scored to `max_docs_scored`.
+    pub fn wrap(inner: C, max_docs_scored: usize) -> LimitedCollector<C> {
+        LimitedCollector {
+            inner: inner,
+            max_docs_scored: max_docs_scored,
+            num_docs_scored: 0,
+            over_budget: false,
+        }
+    }
+
+    /// Returns true iff the search was aborted because
+    /// it exceeded the budget.
+    pub fn is_over_budget(&self,) -> bool {
+        self.over_budget
+    }
+
+    /// Returns the number of documents pushed to the
+    /// underlying collector.
+    pub fn num_docs_scored(&self,) -> usize {
+        self.num_docs_scored
+    }
+
+    /// Returns the underlying collector.
+    pub fn inner(&self,) -> &C {
+        &self.inner
+    }
+
+    /// Consumes the `LimitedCollector` and returns
+    /// the underlying collector.
+    pub fn into_inner(self,) -> C {
+        self.inner
+    }
+}
+
+impl<C: Collector> Collector for LimitedCollector<C> {
+    fn set_segment(&mut self, segment_local_id: SegmentLocalId, segment: &SegmentReader) -> Result<()> {
+        self.inner.set_segment(segment_local_id, segment)
+    }
+
+    fn collect(&mut self, doc: DocId, score: Score) {
+        if self.num_docs_scored == self.max_docs_scored {
+            self.over_budget = true;
+            return;
+        }
+        self.num_docs_scored += 1;
+        self.inner.collect(doc, score);
+    }
+
+    fn is_aborted(&self,) -> bool {
+        self.over_budget || self.inner.is_aborted()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use core::Index;
+    use collector::CountCollector;
+    use query::TermQuery;
+    use schema::{Document, FieldValue, SchemaBuilder, Term, TEXT};
+    use postings::SegmentPostingsOption;
+
+    #[test]
+    fn test_limited_collector() {
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            for _ in 0..10 {
+                index_writer.add_document(doc!(text_field=>"a"));
+            }
+            index_writer.commit().unwrap();
+            for _ in 0..10 {
+                index_writer.add_document(doc!(text_field=>"a"));
+            }
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let query = TermQuery::new(Term::from_field_text(text_field, "a"), SegmentPostingsOption::NoFreq);
+        {
+            let mut limited_collector = LimitedCollector::wrap(CountCollector::default(), 3);
+            searcher.search(&query, &mut limited_collector).unwrap();
+            assert!(limited_collector.is_over_budget());
+            assert_eq!(limited_collector.num_docs_scored(), 3);
+            assert_eq!(limited_collector.into_inner().count(), 3);
+        }
+        {
+            let mut limited_collector = LimitedCollector::wrap(CountCollector::default(), 20);
+            searcher.search(&query, &mut limited_collector).unwrap();
+            assert!(!limited_collector.is_over_budget());
+            assert_eq!(limited_collector.into_inner().count(), 20);
+        }
+    }
+}
@@ -5,17 +5,28 @@ use Score;
 use Result;
 
 mod count_collector;
-pub use self::count_collector::CountCollector;
+pub use self::count_collector::{CountCollector, Count, CountSegmentCollector};
+pub use self::count_collector::{CountAndScoreSum, CountAndScoreSumSegmentCollector};
 
 mod multi_collector;
 pub use self::multi_collector::MultiCollector;
 
 mod top_collector;
-pub use self::top_collector::TopCollector;
+pub use self::top_collector::{TopCollector, TopDocs, TopDocsSegmentCollector};
+pub use self::top_collector::{TopDocsByField, TopDocsByFieldSegmentCollector, Order};
 
 mod chained_collector;
 pub use self::chained_collector::chain;
 
+mod limited_collector;
+pub use self::limited_collector::LimitedCollector;
+
+mod excluding_collector;
+pub use self::excluding_collector::ExcludingCollector;
+
+mod facet_collector;
+pub use self::facet_collector::FacetCollector;
+
 /// Collectors are in charge of collecting and retaining relevant 
 /// information from the document found and scored by the query.
 ///
@@ -44,13 +55,22 @@ pub use self::chained_collector::chain;
 /// - `...`
 /// - `.collect(last_doc_of_last_segment)`
 ///
-/// Segments are not guaranteed to be visited in any specific order.
+/// Segments are not guaranteed to be visited in any specific order,
+/// but within a segment, the documents are collected once each,
+/// by increasing doc id.
 pub trait Collector {
     /// `set_segment` is called before beginning to enumerate 
     /// on this segment.
     fn set_segment(&mut self, segment_local_id: SegmentLocalId, segment: &SegmentReader) -> Result<()>;
     /// The query pushes the scored document to the collector via this method.
     fn collect(&mut self, doc: DocId, score: Score);
+    /// Returns true if the collector does not accept any more documents.
+    ///
+    /// The search is then stopped, without visiting the remaining
+    /// documents and segments.
+    fn is_aborted(&self,) -> bool {
+        false
+    }
 }
 
 
@@ -62,6 +82,47 @@ impl<'a, C: Collector> Collector for &'a mut C {
     fn collect(&mut self, doc: DocId, score: Score) {
         (*self).collect(doc, score);
     }
+    fn is_aborted(&self,) -> bool {
+        (**self).is_aborted()
+    }
+}
+
+
+/// Collects the documents of a single segment,
+/// and produces a `Fruit` out of them.
+///
+/// See `FruitCollector`.
+pub trait SegmentCollector {
+    /// The result of the collection of the segment.
+    type Fruit;
+    /// The query pushes the scored document to the collector via this method.
+    fn collect(&mut self, doc: DocId, score: Score);
+    /// Consumes the collector and returns its fruit.
+    fn harvest(self) -> Self::Fruit;
+}
+
+/// Collector working segment by segment.
+///
+/// Unlike `Collector`, the collector itself is not mutated during
+/// the search. For each segment, it creates a `SegmentCollector`
+/// that collects the documents of this segment only, and
+/// produces its own `Fruit`.
+/// The fruits of all of the segments are then merged 
+/// into the fruit of the search.
+///
+/// As segment collectors are independent from one another,
+/// segments can be collected separately and in any order.
+///
+/// See `Searcher::search_fruit`.
+pub trait FruitCollector {
+    /// The result of the collection.
+    type Fruit;
+    /// The collector in charge of a single segment.
+    type Child: SegmentCollector<Fruit=Self::Fruit>;
+    /// Creates the collector of the given segment.
+    fn for_segment(&self, segment_local_id: SegmentLocalId, segment: &SegmentReader) -> Result<Self::Child>;
+    /// Merges the fruits of the different segments.
+    fn merge_fruits(&self, fruits: Vec<Self::Fruit>) -> Self::Fruit;
 }
 
 
@@ -157,6 +218,78 @@ pub mod tests {
     }
 
 
+    /// Counts the matching documents for each value of a fast field,
+    /// and records the order in which they were collected.
+    struct HistogramCollector {
+        field: Field,
+        ff_reader: Option<U32FastFieldReader>,
+        segment_local_id: SegmentLocalId,
+        histogram: Vec<usize>,
+        collected: Vec<(SegmentLocalId, DocId)>,
+    }
+
+    impl Collector for HistogramCollector {
+        fn set_segment(&mut self, segment_local_id: SegmentLocalId, reader: &SegmentReader) -> Result<()> {
+            self.segment_local_id = segment_local_id;
+            self.ff_reader = reader.get_fast_field_reader(self.field);
+            Ok(())
+        }
+
+        fn collect(&mut self, doc: DocId, _score: Score) {
+            let val = self.ff_reader.as_ref().unwrap().get(doc) as usize;
+            self.histogram[val] += 1;
+            self.collected.push((self.segment_local_id, doc));
+        }
+    }
+
+    #[test]
+    fn test_histogram_collector() {
+        use schema::{Document, SchemaBuilder, FieldValue, Term, TEXT, FAST};
+        use core::Index;
+        use indexer::NoMergePolicy;
+        use query::{Query, TermQuery};
+        use postings::SegmentPostingsOption;
+
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let bucket_field = schema_builder.add_u32_field("bucket", FAST);
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.set_merge_policy(box NoMergePolicy);
+            index_writer.add_document(doc!(text_field=>"a", bucket_field=>2u32));
+            index_writer.add_document(doc!(text_field=>"b", bucket_field=>0u32));
+            index_writer.add_document(doc!(text_field=>"a", bucket_field=>0u32));
+            index_writer.commit().unwrap();
+            index_writer.add_document(doc!(text_field=>"a", bucket_field=>2u32));
+            index_writer.add_document(doc!(text_field=>"a b", bucket_field=>1u32));
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let mut collector = HistogramCollector {
+            field: bucket_field,
+            ff_reader: None,
+            segment_local_id: 0,
+            histogram: vec!(0; 3),
+            collected: Vec::new(),
+        };
+        let query = TermQuery::new(Term::from_field_text(text_field, "a"), SegmentPostingsOption::NoFreq);
+        query.search(&searcher, &mut collector).unwrap();
+        assert_eq!(collector.histogram, vec!(1, 1, 2));
+        assert_eq!(collector.collected.len(), 4);
+        // each segment is visited once, and its documents
+        // are collected by increasing doc id.
+        let segment_local_ids: Vec<SegmentLocalId> = collector.collected
+            .iter()
+            .map(|&(segment_local_id, _)| segment_local_id)
+            .collect();
+        assert!(segment_local_ids == vec!(0, 0, 1, 1) || segment_local_ids == vec!(1, 1, 0, 0));
+        for docs in collector.collected.chunks(2) {
+            assert!(docs[0].1 < docs[1].1);
+        }
+    }
+
     #[bench]
     fn build_collector(b: &mut Bencher) {
         b.iter(|| {
@@ -1,7 +1,10 @@
-use super::Collector;
+use super::{Collector, FruitCollector, SegmentCollector};
+use fastfield::U32FastFieldReader;
+use schema::Field;
 use SegmentReader;
 use SegmentLocalId;
 use DocAddress;
+use Error;
 use Result;
 use std::collections::BinaryHeap;
 use std::cmp::Ordering;
@@ -22,13 +25,15 @@ impl PartialOrd for GlobalScoredDoc {
     }
 }
 
+// Ties are broken by doc address, so that the
+// top documents do not depend on the order of collection.
 impl Ord for GlobalScoredDoc {
     #[inline]
     fn cmp(&self, other: &GlobalScoredDoc) -> Ordering {
-        other.score.partial_cmp(&self.score)
-        .unwrap_or(
-            other.doc_address.cmp(&self.doc_address)
-        )
+        match other.score.partial_cmp(&self.score) {
+            Some(Ordering::Equal) | None => self.doc_address.cmp(&other.doc_address),
+            Some(ordering) => ordering,
+        }
     }
 }
 
@@ -44,10 +49,18 @@ impl Eq for GlobalScoredDoc {}
 /// The Top Collector keeps track of the K documents
 /// with the best scores.
 ///
-/// The implementation is based on a `BinaryHeap`.
-/// The theorical complexity is `O(n log K)`.
+/// Documents with the same score are ranked by
+/// increasing `DocAddress`.
+///
+/// With an offset, the `offset` best documents are skipped,
+/// and the collector returns the next K documents.
+///
+/// The implementation is based on a `BinaryHeap`, holding
+/// at most `K + offset` documents.
+/// The theorical complexity is `O(n log (K + offset))`.
 pub struct TopCollector {
     limit: usize,
+    offset: usize,
     heap: BinaryHeap<GlobalScoredDoc>,
     segment_id: u32,
 }
@@ -64,10 +77,28 @@ impl TopCollector {
         }
         TopCollector {
             limit: limit,
+            offset: 0,
             heap: BinaryHeap::with_capacity(limit),
             segment_id: 0,
         }
     }
+
+    /// Skips the `offset` best documents.
+    ///
+    /// `TopCollector::with_limit(limit).and_offset(offset)`
+    /// returns the documents ranked from `offset` to `offset + limit`
+    /// excluded, for instance to fetch a page of results.
+    pub fn and_offset(mut self, offset: usize) -> TopCollector {
+        self.offset = offset;
+        let capacity = self.capacity();
+        self.heap.reserve(capacity);
+        self
+    }
+
+    // number of documents kept in the heap.
+    fn capacity(&self,) -> usize {
+        self.limit + self.offset
+    }
     
     /// Returns K best documents sorted in decreasing order.
     /// 
@@ -91,15 +122,16 @@ impl TopCollector {
             .collect();
         scored_docs.sort();
         scored_docs.into_iter()
+            .skip(self.offset)
             .map(|GlobalScoredDoc {score, doc_address}| (score, doc_address))
             .collect()
     }
 
-    /// Return true iff at least K documents have gone through
+    /// Return true iff at least `K + offset` documents have gone through
     /// the collector.
     #[inline]
     pub fn at_capacity(&self, ) -> bool {
-        self.heap.len() >= self.limit
+        self.heap.len() >= self.capacity()
     }
 }
 
@@ -111,20 +143,19 @@ impl Collector for TopCollector {
     }
 
     fn collect(&mut self, doc: DocId, score: Score) {
+        let wrapped_doc = GlobalScoredDoc {
+            score: score,
+            doc_address: DocAddress(self.segment_id, doc)
+        };
         if self.at_capacity() {
             // It's ok to unwrap as long as a limit of 0 is forbidden.
             let limit_doc: GlobalScoredDoc = *self.heap.peek().expect("Top collector with size 0 is forbidden");
-            if limit_doc.score < score {
+            if wrapped_doc < limit_doc {
                 let mut mut_head = self.heap.peek_mut().expect("Top collector with size 0 is forbidden");
-                mut_head.score = score;
-                mut_head.doc_address = DocAddress(self.segment_id, doc);               
+                *mut_head = wrapped_doc;
             }
         }
         else {
-            let wrapped_doc = GlobalScoredDoc {
-                score: score,
-                doc_address: DocAddress(self.segment_id, doc)
-            };
             self.heap.push(wrapped_doc);
         }
 
@@ -132,6 +163,203 @@ impl Collector for TopCollector {
 }
 
 
+/// `FruitCollector` keeping track of the K documents
+/// with the best scores.
+///
+/// Its fruit is the list of the documents, along with their
+/// score, sorted by decreasing score.
+///
+/// See `TopCollector` for the ranking of the documents
+/// and the meaning of the offset.
+pub struct TopDocs {
+    limit: usize,
+    offset: usize,
+}
+
+impl TopDocs {
+    /// Creates a top docs collector, with a number of documents equal to "limit".
+    ///
+    /// # Panics
+    /// The method panics if limit is 0
+    pub fn with_limit(limit: usize) -> TopDocs {
+        if limit < 1 {
+            panic!("Limit must be strictly greater than 0.");
+        }
+        TopDocs {
+            limit: limit,
+            offset: 0,
+        }
+    }
+
+    /// Skips the `offset` best documents.
+    ///
+    /// Each segment collector keeps `limit + offset` documents,
+    /// and the offset is applied when merging their fruits.
+    pub fn and_offset(mut self, offset: usize) -> TopDocs {
+        self.offset = offset;
+        self
+    }
+
+    /// Ranks the documents by the value of the u32 fast field `field`
+    /// rather than by score, in the given `order`.
+    ///
+    /// The limit and the offset are kept.
+    pub fn order_by_fast_field(self, field: Field, order: Order) -> TopDocsByField {
+        TopDocsByField {
+            limit: self.limit,
+            offset: self.offset,
+            field: field,
+            order: order,
+        }
+    }
+}
+
+/// Collector of a segment for `TopDocs`.
+pub struct TopDocsSegmentCollector {
+    top_collector: TopCollector,
+}
+
+impl SegmentCollector for TopDocsSegmentCollector {
+    type Fruit = Vec<(Score, DocAddress)>;
+
+    fn collect(&mut self, doc: DocId, score: Score) {
+        self.top_collector.collect(doc, score);
+    }
+
+    fn harvest(self) -> Vec<(Score, DocAddress)> {
+        self.top_collector.score_docs()
+    }
+}
+
+impl FruitCollector for TopDocs {
+    type Fruit = Vec<(Score, DocAddress)>;
+    type Child = TopDocsSegmentCollector;
+
+    fn for_segment(&self, segment_id: SegmentLocalId, segment: &SegmentReader) -> Result<TopDocsSegmentCollector> {
+        let mut top_collector = TopCollector::with_limit(self.limit + self.offset);
+        try!(top_collector.set_segment(segment_id, segment));
+        Ok(TopDocsSegmentCollector {
+            top_collector: top_collector,
+        })
+    }
+
+    fn merge_fruits(&self, fruits: Vec<Vec<(Score, DocAddress)>>) -> Vec<(Score, DocAddress)> {
+        let mut top_collector = TopCollector::with_limit(self.limit).and_offset(self.offset);
+        for (score, DocAddress(segment_id, doc)) in fruits.into_iter().flat_map(|fruit| fruit.into_iter()) {
+            top_collector.segment_id = segment_id;
+            top_collector.collect(doc, score);
+        }
+        top_collector.score_docs()
+    }
+}
+
+
+/// Order of the documents ranked by a fast field.
+///
+/// See `TopDocs::order_by_fast_field`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Order {
+    /// Lowest values first.
+    Asc,
+    /// Highest values first.
+    Desc,
+}
+
+// Key of a value such that the best documents have the lowest keys.
+// The mapping is its own inverse.
+fn sort_key(order: Order, val: u32) -> u32 {
+    match order {
+        Order::Asc => val,
+        Order::Desc => !val,
+    }
+}
+
+/// `FruitCollector` keeping track of the K documents
+/// with the best values for a u32 fast field, ignoring their score.
+///
+/// Its fruit is the list of the documents, along with their
+/// value, in the requested order. Documents with the same value
+/// are ranked by increasing `DocAddress`.
+///
+/// Each segment collector reads the fast field of its segment,
+/// and keeps a heap of at most `K + offset` documents.
+///
+/// See `TopDocs::order_by_fast_field`.
+pub struct TopDocsByField {
+    limit: usize,
+    offset: usize,
+    field: Field,
+    order: Order,
+}
+
+/// Collector of a segment for `TopDocsByField`.
+pub struct TopDocsByFieldSegmentCollector {
+    fast_field_reader: U32FastFieldReader,
+    order: Order,
+    segment_id: SegmentLocalId,
+    limit: usize,
+    // max-heap of the sort keys, its top being the worst document kept.
+    heap: BinaryHeap<(u32, DocAddress)>,
+}
+
+impl SegmentCollector for TopDocsByFieldSegmentCollector {
+    type Fruit = Vec<(u32, DocAddress)>;
+
+    fn collect(&mut self, doc: DocId, _score: Score) {
+        let key = (sort_key(self.order, self.fast_field_reader.get(doc)), DocAddress(self.segment_id, doc));
+        if self.heap.len() < self.limit {
+            self.heap.push(key);
+        }
+        else if key < *self.heap.peek().expect("Heap is at capacity, so it is not empty") {
+            self.heap.pop();
+            self.heap.push(key);
+        }
+    }
+
+    fn harvest(self) -> Vec<(u32, DocAddress)> {
+        let order = self.order;
+        self.heap
+            .into_sorted_vec()
+            .into_iter()
+            .map(|(key, doc_address)| (sort_key(order, key), doc_address))
+            .collect()
+    }
+}
+
+impl FruitCollector for TopDocsByField {
+    type Fruit = Vec<(u32, DocAddress)>;
+    type Child = TopDocsByFieldSegmentCollector;
+
+    fn for_segment(&self, segment_id: SegmentLocalId, segment: &SegmentReader) -> Result<TopDocsByFieldSegmentCollector> {
+        let fast_field_reader = try!(segment
+            .get_fast_field_reader(self.field)
+            .ok_or_else(|| Error::SchemaError(format!("Failed to find a u32_reader for field {:?}", self.field))));
+        Ok(TopDocsByFieldSegmentCollector {
+            fast_field_reader: fast_field_reader,
+            order: self.order,
+            segment_id: segment_id,
+            limit: self.limit + self.offset,
+            heap: BinaryHeap::with_capacity(self.limit + self.offset + 1),
+        })
+    }
+
+    fn merge_fruits(&self, fruits: Vec<Vec<(u32, DocAddress)>>) -> Vec<(u32, DocAddress)> {
+        let order = self.order;
+        let mut keys: Vec<(u32, DocAddress)> = fruits
+            .into_iter()
+            .flat_map(|fruit| fruit.into_iter())
+            .map(|(val, doc_address)| (sort_key(order, val), doc_address))
+            .collect();
+        keys.sort();
+        keys.into_iter()
+            .skip(self.offset)
+            .take(self.limit)
+            .map(|(key, doc_address)| (sort_key(order, key), doc_address))
+            .collect()
+    }
+}
+
+
 #[cfg(test)]
 mod tests {
 
@@ -187,6 +415,142 @@ mod tests {
 
     }
 
+    #[test]
+    fn test_top_collector_ties() {
+        let docs = vec!((5, 0.5), (3, 0.5), (7, 0.9), (1, 0.5), (2, 0.1));
+        for &reverse in &[false, true] {
+            let mut top_collector = TopCollector::with_limit(3);
+            let mut docs = docs.clone();
+            if reverse {
+                docs.reverse();
+            }
+            for (doc, score) in docs {
+                top_collector.collect(doc, score);
+            }
+            let score_docs: Vec<(Score, DocId)> = top_collector.score_docs()
+                .into_iter()
+                .map(|(score, doc_address)| (score, doc_address.doc()))
+                .collect();
+            assert_eq!(score_docs, vec!((0.9, 7), (0.5, 1), (0.5, 3)));
+        }
+    }
+
+    fn collect_pages(limit: usize, offset: usize) -> Vec<(Score, DocAddress)> {
+        let mut top_collector = TopCollector::with_limit(limit).and_offset(offset);
+        for segment_id in 0..3 {
+            top_collector.segment_id = 2 - segment_id;
+            for doc in 0..20 {
+                top_collector.collect(doc, (doc % 4) as Score);
+                assert!(top_collector.heap.len() <= limit + offset);
+            }
+        }
+        top_collector.score_docs()
+    }
+
+    #[test]
+    fn test_top_collector_offset() {
+        let page_0 = collect_pages(7, 0);
+        let page_1 = collect_pages(7, 7);
+        assert_eq!(page_0.len(), 7);
+        assert_eq!(page_1.len(), 7);
+        let pages: Vec<(Score, DocAddress)> = page_0.into_iter().chain(page_1.into_iter()).collect();
+        assert_eq!(pages, collect_pages(14, 0));
+        assert_eq!(&pages[..3], &[(3.0, DocAddress(0, 3)), (3.0, DocAddress(0, 7)), (3.0, DocAddress(0, 11))]);
+        // fewer than limit documents after the offset.
+        assert_eq!(collect_pages(10, 55).len(), 5);
+        assert!(collect_pages(10, 60).is_empty());
+    }
+
+    #[test]
+    fn test_top_docs_offset() {
+        use schema::{Document, SchemaBuilder, FieldValue, Term, TEXT};
+        use core::Index;
+        use indexer::NoMergePolicy;
+        use query::TermQuery;
+        use postings::SegmentPostingsOption;
+
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.set_merge_policy(box NoMergePolicy);
+            for _ in 0..2 {
+                for i in 0..10 {
+                    // many documents share the same score.
+                    let text = if i % 3 == 0 { "a a b" } else { "a b" };
+                    index_writer.add_document(doc!(text_field=>text));
+                }
+                index_writer.commit().unwrap();
+            }
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let query = TermQuery::new(Term::from_field_text(text_field, "a"), SegmentPostingsOption::Freq);
+        let page = |limit: usize, offset: usize| {
+            let top_docs = searcher.search_fruit(&query, &TopDocs::with_limit(limit).and_offset(offset)).unwrap();
+            let mut top_collector = TopCollector::with_limit(limit).and_offset(offset);
+            searcher.search(&query, &mut top_collector).unwrap();
+            assert_eq!(top_collector.score_docs(), top_docs);
+            top_docs
+        };
+        let mut pages = page(6, 0);
+        pages.extend(page(6, 6));
+        assert_eq!(pages, page(12, 0));
+        assert_eq!(page(6, 18).len(), 2);
+    }
+
+    #[test]
+    fn test_top_docs_order_by_fast_field() {
+        use schema::{Document, SchemaBuilder, FieldValue, Term, FAST, TEXT};
+        use core::Index;
+        use indexer::NoMergePolicy;
+        use query::TermQuery;
+        use postings::SegmentPostingsOption;
+
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let price_field = schema_builder.add_u32_field("price", FAST);
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.set_merge_policy(box NoMergePolicy);
+            for prices in &[[30u32, 10, 20, 10], [20, 40, 10, 30]] {
+                for &price in prices {
+                    // the most relevant documents are the most expensive ones.
+                    let text = if price >= 30 { "a a a" } else { "a b c" };
+                    index_writer.add_document(doc!(text_field=>text, price_field=>price));
+                }
+                // ignored by the query.
+                index_writer.add_document(doc!(text_field=>"b", price_field=>0u32));
+                index_writer.commit().unwrap();
+            }
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        assert_eq!(searcher.segment_readers().len(), 2);
+        let query = TermQuery::new(Term::from_field_text(text_field, "a"), SegmentPostingsOption::Freq);
+        let top_docs = |limit: usize, offset: usize, order: Order| {
+            let collector = TopDocs::with_limit(limit).and_offset(offset).order_by_fast_field(price_field, order);
+            searcher.search_fruit(&query, &collector)
+                .unwrap()
+                .into_iter()
+                .map(|(price, DocAddress(segment_ord, doc))| (price, segment_ord, doc))
+                .collect::<Vec<(u32, u32, DocId)>>()
+        };
+        // ties are broken by doc address, whatever the order.
+        assert_eq!(top_docs(5, 0, Order::Asc), vec!((10, 0, 1), (10, 0, 3), (10, 1, 2), (20, 0, 2), (20, 1, 0)));
+        assert_eq!(top_docs(5, 0, Order::Desc), vec!((40, 1, 1), (30, 0, 0), (30, 1, 3), (20, 0, 2), (20, 1, 0)));
+        assert_eq!(top_docs(3, 2, Order::Asc), vec!((10, 1, 2), (20, 0, 2), (20, 1, 0)));
+        assert_eq!(top_docs(10, 0, Order::Desc).len(), 8);
+        let mut pages = top_docs(3, 0, Order::Desc);
+        pages.extend(top_docs(3, 3, Order::Desc));
+        pages.extend(top_docs(3, 6, Order::Desc));
+        assert_eq!(pages, top_docs(10, 0, Order::Desc));
+        let text_order = TopDocs::with_limit(3).order_by_fast_field(text_field, Order::Asc);
+        assert!(searcher.search_fruit(&query, &text_order).is_err());
+    }
+
     #[test]
     #[should_panic]
     fn test_top_0() {
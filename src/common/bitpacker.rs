@@ -82,15 +82,20 @@ impl BitUnpacker {
         }
     }
     
-    pub fn get(&self, idx: usize) -> u32 {
+    /// Returns the number of values the data can hold.
+    pub fn capacity(&self,) -> usize {
         if self.num_bits == 0 {
-            return 0;
+            usize::max_value()
         }
-        let addr = (idx * self.num_bits) / 8;
-        let bit_shift = idx * self.num_bits - addr * 8;
-        let val_unshifted_unmasked: u64;
+        else {
+            self.data_len * 8 / self.num_bits
+        }
+    }
+
+    #[inline]
+    fn read_u64(&self, addr: usize) -> u64 {
         if addr + 8 <= self.data_len {
-            val_unshifted_unmasked = unsafe { * (self.data_ptr.offset(addr as isize) as *const u64) };
+            unsafe { * (self.data_ptr.offset(addr as isize) as *const u64) }
         }
         else {  
             let mut arr = [0u8; 8];
@@ -99,11 +104,39 @@ impl BitUnpacker {
                     arr[i] = unsafe { *self.data_ptr.offset( (addr + i) as isize) };
                 }
             }
-            val_unshifted_unmasked = unsafe { mem::transmute::<[u8; 8], u64>(arr) };
+            unsafe { mem::transmute::<[u8; 8], u64>(arr) }
+        }
+    }
+
+    pub fn get(&self, idx: usize) -> u32 {
+        if self.num_bits == 0 {
+            return 0;
         }
+        let addr = (idx * self.num_bits) / 8;
+        let bit_shift = idx * self.num_bits - addr * 8;
+        let val_unshifted_unmasked: u64 = self.read_u64(addr);
         let val_shifted = (val_unshifted_unmasked >> bit_shift) as u32;
         (val_shifted & self.mask)
     }
+
+    /// Unpacks the `output.len()` values starting 
+    /// at `start` into `output`.
+    pub fn get_range(&self, start: usize, output: &mut [u32]) {
+        if self.num_bits == 0 {
+            for output_val in output.iter_mut() {
+                *output_val = 0;
+            }
+            return;
+        }
+        let mut bit_addr = start * self.num_bits;
+        for output_val in output.iter_mut() {
+            let addr = bit_addr / 8;
+            let bit_shift = bit_addr & 7;
+            let val_shifted = (self.read_u64(addr) >> bit_shift) as u32;
+            *output_val = val_shifted & self.mask;
+            bit_addr += self.num_bits;
+        }
+    }
         
 }
 
@@ -147,6 +180,9 @@ mod test {
         for (i, val) in vals.iter().enumerate() {
             assert_eq!(bitunpacker.get(i), *val);
         }
+        let mut output = vec!(0u32; len / 2);
+        bitunpacker.get_range(len - output.len(), &mut output);
+        assert_eq!(&output[..], &vals[len - output.len()..]);
     }
     
     #[test]
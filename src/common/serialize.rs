@@ -49,6 +49,17 @@ impl<Left: BinarySerializable, Right: BinarySerializable> BinarySerializable for
     }
 }
 
+impl BinarySerializable for u16 {
+    fn serialize(&self, writer: &mut Write) -> io::Result<usize> {
+        writer.write_u16::<Endianness>(*self)
+              .map(|_| 2)
+    }
+
+    fn deserialize(reader: &mut Read) -> io::Result<u16> {
+        reader.read_u16::<Endianness>()
+    }
+}
+
 impl BinarySerializable for u32 {
     fn serialize(&self, writer: &mut Write) -> io::Result<usize> {
         writer.write_u32::<Endianness>(*self)
@@ -127,6 +138,12 @@ mod test {
         serialize_test(5u8, 1);
     }
 
+    #[test]
+    fn test_serialize_u16() {
+        serialize_test(3u16, 2);
+        serialize_test(u16::max_value(), 2);
+    }
+
     #[test]
     fn test_serialize_u32() {
         serialize_test(3u32, 4);
@@ -0,0 +1,360 @@
+use std::io::{self, Write};
+use super::{BlockEncoder, BlockSkipEntry};
+use super::NUM_DOCS_PER_BLOCK;
+use common::BinarySerializable;
+use compression::VIntEncoder;
+
+/// Streams sorted lists of doc ids to a `Write`.
+///
+/// The doc ids are recorded one at a time, optionally together with
+/// their term frequency and their payload. Every full block of
+/// `NUM_DOCS_PER_BLOCK` doc ids is compressed and written directly
+/// from the buffer of the `BlockEncoder`, followed by the blocks of
+/// its term frequencies and payloads, if any. The remaining values
+/// are encoded with variable int encoding on `.finish()`.
+///
+/// A `BlockSkipEntry` is kept for each full block of doc ids, with
+/// its offset relative to the start of the list. They can be written
+/// after the list with `.write_skip_entries()`.
+///
+/// Several lists can be written one after the other, as the
+/// `PostingsSerializer` does for the terms of a segment.
+/// The output of a list of doc ids alone is the same as that
+/// of `CompositeEncoder::compress_sorted`.
+pub struct BlockPostingsWriter<W: Write> {
+    block_encoder: BlockEncoder,
+    write: W,
+    doc_ids: Vec<u32>,
+    term_freqs: Vec<u32>,
+    payloads: Vec<u32>,
+    last_doc_id_encoded: u32,
+    written_bytes: usize,
+    num_blocks: usize,
+    list_start: usize,
+    skip_entries: Vec<BlockSkipEntry>,
+}
+
+impl<W: Write> BlockPostingsWriter<W> {
+
+    pub fn new(write: W) -> BlockPostingsWriter<W> {
+        BlockPostingsWriter {
+            block_encoder: BlockEncoder::new(),
+            write: write,
+            doc_ids: Vec::with_capacity(NUM_DOCS_PER_BLOCK),
+            term_freqs: Vec::with_capacity(NUM_DOCS_PER_BLOCK),
+            payloads: Vec::with_capacity(NUM_DOCS_PER_BLOCK),
+            last_doc_id_encoded: 0u32,
+            written_bytes: 0,
+            num_blocks: 0,
+            list_start: 0,
+            skip_entries: Vec::new(),
+        }
+    }
+
+    /// Records a doc id, writing a block if it completes one.
+    ///
+    /// Doc ids are expected to be recorded in increasing order.
+    pub fn record(&mut self, doc_id: u32) -> io::Result<()> {
+        self.record_with(doc_id, None, None)
+    }
+
+    /// Records a doc id together with its term frequency and
+    /// its payload, writing a block if it completes one.
+    ///
+    /// Within a list, either all of the doc ids have a term frequency
+    /// or none of them has, and the same goes for the payloads.
+    pub fn record_with(&mut self, doc_id: u32, term_freq: Option<u32>, payload: Option<u32>) -> io::Result<()> {
+        if self.doc_ids.is_empty() && self.num_blocks == 0 {
+            self.list_start = self.written_bytes;
+            self.skip_entries.clear();
+        }
+        self.doc_ids.push(doc_id);
+        if let Some(term_freq) = term_freq {
+            self.term_freqs.push(term_freq);
+        }
+        if let Some(payload) = payload {
+            self.payloads.push(payload);
+        }
+        if self.doc_ids.len() == NUM_DOCS_PER_BLOCK {
+            try!(self.write_block());
+        }
+        Ok(())
+    }
+
+    fn write_block(&mut self) -> io::Result<()> {
+        self.skip_entries.push(BlockSkipEntry {
+            last_val: self.doc_ids[NUM_DOCS_PER_BLOCK - 1],
+            offset: self.written_bytes - self.list_start,
+        });
+        {
+            let block_encoded = self.block_encoder
+                .compress_block_sorted(&self.doc_ids, self.last_doc_id_encoded);
+            try!(self.write.write_all(block_encoded));
+            self.written_bytes += block_encoded.len();
+        }
+        if !self.term_freqs.is_empty() {
+            let block_encoded = self.block_encoder
+                .compress_block_unsorted(&self.term_freqs);
+            try!(self.write.write_all(block_encoded));
+            self.written_bytes += block_encoded.len();
+            self.term_freqs.clear();
+        }
+        if !self.payloads.is_empty() {
+            let block_encoded = self.block_encoder
+                .compress_block_unsorted(&self.payloads);
+            try!(self.write.write_all(block_encoded));
+            self.written_bytes += block_encoded.len();
+            self.payloads.clear();
+        }
+        self.last_doc_id_encoded = self.doc_ids[NUM_DOCS_PER_BLOCK - 1];
+        self.num_blocks += 1;
+        self.doc_ids.clear();
+        Ok(())
+    }
+
+    /// Returns the number of bytes written so far.
+    ///
+    /// Before a call to `.record()`, it is the offset
+    /// of the next block within the output.
+    pub fn written_bytes(&self) -> usize {
+        self.written_bytes
+    }
+
+    /// Returns the number of full blocks of the current list written so far.
+    pub fn num_blocks(&self) -> usize {
+        self.num_blocks
+    }
+
+    /// Encodes the remaining doc ids, term frequencies and payloads
+    /// of the current list with variable int encoding, and returns
+    /// the total number of bytes written and the number of full
+    /// blocks of the list.
+    ///
+    /// The next doc ids recorded start a new list.
+    pub fn finish(&mut self) -> io::Result<(usize, usize)> {
+        {
+            let vint_encoded = self.block_encoder
+                .compress_vint_sorted(&self.doc_ids, self.last_doc_id_encoded);
+            try!(self.write.write_all(vint_encoded));
+            self.written_bytes += vint_encoded.len();
+        }
+        for vals in &[&self.term_freqs, &self.payloads] {
+            let vint_encoded = self.block_encoder.compress_vint_unsorted(vals);
+            try!(self.write.write_all(vint_encoded));
+            self.written_bytes += vint_encoded.len();
+        }
+        let num_blocks = self.num_blocks;
+        self.doc_ids.clear();
+        self.term_freqs.clear();
+        self.payloads.clear();
+        self.last_doc_id_encoded = 0u32;
+        self.num_blocks = 0;
+        Ok((self.written_bytes, num_blocks))
+    }
+
+    /// Writes the skip entries of the list last finished, one per full
+    /// block, and returns the number of bytes written.
+    ///
+    /// Their offsets are relative to the start of the list.
+    pub fn write_skip_entries(&mut self) -> io::Result<usize> {
+        let mut written_bytes = 0;
+        for skip_entry in &self.skip_entries {
+            written_bytes += try!(skip_entry.serialize(&mut self.write));
+        }
+        self.written_bytes += written_bytes;
+        self.skip_entries.clear();
+        Ok(written_bytes)
+    }
+
+    /// Writes already encoded bytes verbatim, typically
+    /// a whole list copied from another segment.
+    ///
+    /// # Panics
+    /// Panics if a list is being written, i.e. if doc ids
+    /// were recorded since the last call to `.finish()`.
+    pub fn write_raw(&mut self, bytes: &[u8]) -> io::Result<()> {
+        assert!(self.doc_ids.is_empty() && self.num_blocks == 0,
+                "Raw bytes cannot be written in the middle of a list.");
+        try!(self.write.write_all(bytes));
+        self.written_bytes += bytes.len();
+        Ok(())
+    }
+
+    /// Flushes the underlying `Write`.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.write.flush()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use test::Bencher;
+    use super::*;
+    use compression::{CompositeEncoder, CompositeDecoder, BlockDecoder, VIntDecoder};
+    use compression::{compress_sorted_blocks, read_skip_entries, SKIP_ENTRY_NUM_BYTES};
+    use compression::tests::generate_array;
+
+    #[test]
+    fn test_block_postings_writer() {
+        for &num_docs in &[0, 1, 127, 128, 129, 10_000] {
+            let data = generate_array(num_docs, 0.1);
+            let mut buffer: Vec<u8> = Vec::new();
+            let (written_bytes, num_blocks) = {
+                let mut postings_writer = BlockPostingsWriter::new(&mut buffer);
+                for &doc_id in &data {
+                    postings_writer.record(doc_id).unwrap();
+                }
+                postings_writer.finish().unwrap()
+            };
+            assert_eq!(written_bytes, buffer.len());
+            assert_eq!(num_blocks, num_docs / NUM_DOCS_PER_BLOCK);
+            let mut encoder = CompositeEncoder::new();
+            assert_eq!(&buffer[..], encoder.compress_sorted(&data));
+            let mut decoder = CompositeDecoder::new();
+            assert_eq!(decoder.uncompress_sorted(&buffer, num_docs), &data[..]);
+        }
+    }
+
+    #[test]
+    fn test_block_postings_writer_block_offsets() {
+        let data = generate_array(1_000, 0.1);
+        let mut buffer: Vec<u8> = Vec::new();
+        let mut block_offsets = Vec::new();
+        {
+            let mut postings_writer = BlockPostingsWriter::new(&mut buffer);
+            for (i, &doc_id) in data.iter().enumerate() {
+                if i % NUM_DOCS_PER_BLOCK == 0 {
+                    block_offsets.push(postings_writer.written_bytes());
+                }
+                postings_writer.record(doc_id).unwrap();
+                assert_eq!(postings_writer.num_blocks(), (i + 1) / NUM_DOCS_PER_BLOCK);
+            }
+            postings_writer.finish().unwrap();
+        }
+        let mut encoder = BlockEncoder::new();
+        let mut offset = 0u32;
+        for (block, &block_offset) in data.chunks(NUM_DOCS_PER_BLOCK).zip(block_offsets.iter()) {
+            if block.len() < NUM_DOCS_PER_BLOCK {
+                break;
+            }
+            let block_encoded = encoder.compress_block_sorted(block, offset);
+            assert_eq!(&buffer[block_offset..block_offset + block_encoded.len()], block_encoded);
+            offset = block[block.len() - 1];
+        }
+    }
+
+    #[test]
+    fn test_block_postings_writer_several_lists() {
+        let data = generate_array(300, 0.1);
+        let term_freqs: Vec<u32> = (0..300u32).map(|i| 1 + i % 7).collect();
+        let mut buffer: Vec<u8> = Vec::new();
+        let list_offset = {
+            let mut postings_writer = BlockPostingsWriter::new(&mut buffer);
+            for &doc_id in &data[..10] {
+                postings_writer.record(doc_id).unwrap();
+            }
+            let (list_offset, num_blocks) = postings_writer.finish().unwrap();
+            assert_eq!(num_blocks, 0);
+            for (&doc_id, &term_freq) in data.iter().zip(term_freqs.iter()) {
+                postings_writer.record_with(doc_id, Some(term_freq), Some(term_freq * 2)).unwrap();
+            }
+            let (written_bytes, num_blocks) = postings_writer.finish().unwrap();
+            assert_eq!(num_blocks, 2);
+            postings_writer.write_raw(&[1u8, 2u8]).unwrap();
+            assert_eq!(postings_writer.written_bytes(), written_bytes + 2);
+            list_offset
+        };
+        // the second list restarts from a doc id offset of 0,
+        // and its blocks of doc ids are followed by those of the
+        // term frequencies and payloads.
+        let mut decoder = BlockDecoder::new();
+        let mut data_left: &[u8] = &buffer[list_offset..];
+        let mut offset = 0u32;
+        for block_ord in 0..2 {
+            let block = block_ord * NUM_DOCS_PER_BLOCK..(block_ord + 1) * NUM_DOCS_PER_BLOCK;
+            data_left = decoder.uncompress_block_sorted(data_left, offset);
+            assert_eq!(decoder.output_array(), &data[block.clone()]);
+            offset = data[block.end - 1];
+            data_left = decoder.uncompress_block_unsorted(data_left);
+            assert_eq!(decoder.output_array(), &term_freqs[block.clone()]);
+            data_left = decoder.uncompress_block_unsorted(data_left);
+            assert_eq!(decoder.output_array()[3], term_freqs[block.start + 3] * 2);
+        }
+        data_left = decoder.uncompress_vint_sorted(data_left, offset, 44);
+        assert_eq!(decoder.output_array(), &data[256..]);
+        data_left = decoder.uncompress_vint_unsorted(data_left, 44);
+        assert_eq!(decoder.output_array(), &term_freqs[256..]);
+        data_left = decoder.uncompress_vint_unsorted(data_left, 44);
+        assert_eq!(data_left, &[1u8, 2u8]);
+    }
+
+    #[test]
+    fn test_block_postings_writer_skip_entries() {
+        let data = generate_array(1_000, 0.1);
+        let mut buffer: Vec<u8> = Vec::new();
+        let (list_start, list_end) = {
+            let mut postings_writer = BlockPostingsWriter::new(&mut buffer);
+            for &doc_id in &data[..10] {
+                postings_writer.record(doc_id).unwrap();
+            }
+            let (list_start, _) = postings_writer.finish().unwrap();
+            assert_eq!(postings_writer.write_skip_entries().unwrap(), 0);
+            for &doc_id in &data {
+                postings_writer.record(doc_id).unwrap();
+            }
+            let (list_end, num_blocks) = postings_writer.finish().unwrap();
+            assert_eq!(postings_writer.write_skip_entries().unwrap(),
+                       num_blocks * SKIP_ENTRY_NUM_BYTES);
+            (list_start, list_end)
+        };
+        // the skip entries of the full blocks are the same as those of
+        // `compress_sorted_blocks`, and are relative to the start of the list.
+        let (_, mut expected_skip_entries) = compress_sorted_blocks(&data);
+        expected_skip_entries.pop();
+        let skip_entries = read_skip_entries(&buffer[list_end..]);
+        assert_eq!(skip_entries, expected_skip_entries);
+        let mut decoder = BlockDecoder::new();
+        assert_eq!(decoder.seek(&buffer[list_start..list_end], &skip_entries, data[500]),
+                   Some((data[500], 500 % NUM_DOCS_PER_BLOCK)));
+    }
+
+    const BENCH_NUM_INTS: usize = 99_968;
+
+    #[bench]
+    fn bench_block_postings_writer(b: &mut Bencher) {
+        let data = generate_array(BENCH_NUM_INTS, 0.1);
+        let mut buffer: Vec<u8> = Vec::with_capacity(500_000);
+        b.iter(|| {
+            buffer.clear();
+            let mut postings_writer = BlockPostingsWriter::new(&mut buffer);
+            for &doc_id in &data {
+                postings_writer.record(doc_id).unwrap();
+            }
+            postings_writer.finish().unwrap()
+        });
+    }
+
+    #[bench]
+    fn bench_manual_block_loop(b: &mut Bencher) {
+        let data = generate_array(BENCH_NUM_INTS, 0.1);
+        let mut buffer: Vec<u8> = Vec::with_capacity(500_000);
+        let mut encoder = BlockEncoder::new();
+        b.iter(|| {
+            buffer.clear();
+            let mut doc_ids = Vec::with_capacity(NUM_DOCS_PER_BLOCK);
+            let mut offset = 0u32;
+            for &doc_id in &data {
+                doc_ids.push(doc_id);
+                if doc_ids.len() == NUM_DOCS_PER_BLOCK {
+                    let block_encoded = encoder.compress_block_sorted(&doc_ids, offset).to_vec();
+                    buffer.write_all(&block_encoded).unwrap();
+                    offset = doc_id;
+                    doc_ids.clear();
+                }
+            }
+            buffer.write_all(encoder.compress_vint_sorted(&doc_ids, offset)).unwrap();
+        });
+    }
+}
@@ -1,37 +1,144 @@
 use common::bitpacker::compute_num_bits;
-use common::bitpacker::{BitPacker, BitUnpacker};
 use std::cmp;
-use std::io::Write;
 use super::NUM_DOCS_PER_BLOCK;
+use super::{padding_for, write_block_header, read_block_header, pad_block};
 
-const COMPRESSED_BLOCK_MAX_SIZE: usize = NUM_DOCS_PER_BLOCK * 4 + 1; 
+// A full block takes at most `1 + NUM_DOCS_PER_BLOCK * 4` bytes
+// once bitpacked, but a vint encoded tail of up to `NUM_DOCS_PER_BLOCK - 1`
+// values may take up to 5 bytes per value.
+const COMPRESSED_BLOCK_MAX_SIZE: usize = NUM_DOCS_PER_BLOCK * 5 + 1;
 
-pub fn compress_sorted(vals: &mut [u32], mut output: &mut [u8], offset: u32) -> usize {
+// The blocks are bitpacked using the exact same layout as `simdcomp`,
+// so that an index written with the `simdcompression` feature
+// can be read without it, and vice versa.
+//
+// The values are dispatched in 4 lanes in a round-robin fashion.
+// Each lane is bitpacked into 32-bit words (lowest bits first),
+// and the words of the 4 lanes are interleaved, as they would be
+// in a sequence of 128-bit SSE registers.
+const NUM_LANES: usize = 4;
+
+fn write_word(output: &mut [u8], word_id: usize, word: u32) {
+    let start = word_id * 4;
+    output[start] = word as u8;
+    output[start + 1] = (word >> 8) as u8;
+    output[start + 2] = (word >> 16) as u8;
+    output[start + 3] = (word >> 24) as u8;
+}
+
+fn read_word(data: &[u8], word_id: usize) -> u32 {
+    let start = word_id * 4;
+    (data[start] as u32) |
+        ((data[start + 1] as u32) << 8) |
+        ((data[start + 2] as u32) << 16) |
+        ((data[start + 3] as u32) << 24)
+}
+
+/// Bitpacks the `NUM_DOCS_PER_BLOCK` values of `vals` using `num_bits`
+/// bits per value, and returns the number of bytes written.
+fn pack(vals: &[u32], num_bits: u8, output: &mut [u8]) -> usize {
+    let num_bits = num_bits as usize;
+    for lane in 0..NUM_LANES {
+        let mut word_id = lane;
+        let mut buffer = 0u64;
+        let mut buffer_len = 0;
+        for j in 0..NUM_DOCS_PER_BLOCK / NUM_LANES {
+            let i = j * NUM_LANES + lane;
+            buffer |= (vals[i] as u64) << buffer_len;
+            buffer_len += num_bits;
+            if buffer_len >= 32 {
+                write_word(output, word_id, buffer as u32);
+                word_id += NUM_LANES;
+                buffer >>= 32;
+                buffer_len -= 32;
+            }
+        }
+    }
+    num_bits * NUM_DOCS_PER_BLOCK / 8
+}
+
+/// Unpacks `NUM_DOCS_PER_BLOCK` values bitpacked by `pack`,
+/// and returns the number of bytes consumed.
+fn unpack(data: &[u8], num_bits: u8, output: &mut [u32]) -> usize {
+    let num_bits = num_bits as usize;
+    let mask = (1u64 << num_bits) - 1;
+    for lane in 0..NUM_LANES {
+        let mut word_id = lane;
+        let mut buffer = 0u64;
+        let mut buffer_len = 0;
+        for j in 0..NUM_DOCS_PER_BLOCK / NUM_LANES {
+            let i = j * NUM_LANES + lane;
+            if buffer_len < num_bits {
+                buffer |= (read_word(data, word_id) as u64) << buffer_len;
+                word_id += NUM_LANES;
+                buffer_len += 32;
+            }
+            output[i] = (buffer & mask) as u32;
+            buffer >>= num_bits;
+            buffer_len -= num_bits;
+        }
+    }
+    num_bits * NUM_DOCS_PER_BLOCK / 8
+}
+
+pub fn compress_sorted(vals: &mut [u32], output: &mut [u8], offset: u32) -> usize {
     let mut max_delta = 0; 
     {
         let mut local_offset = offset;
         for i in 0..NUM_DOCS_PER_BLOCK {
             let val = vals[i];
-            let delta = val - local_offset;
+            let delta = val.wrapping_sub(local_offset);
             max_delta = cmp::max(max_delta, delta);
             vals[i] = delta;
             local_offset = val;
         }
     }
     let num_bits = compute_num_bits(max_delta);
-    output.write_all(&[num_bits]).unwrap();
-    let mut bit_packer = BitPacker::new(num_bits as usize);
-    for val in vals {
-        bit_packer.write(*val, &mut output).unwrap();
+    if num_bits == 32 {
+        // like simdcomp, values are stored as is
+        // when deltas require 32 bits.
+        let mut val = offset;
+        for i in 0..NUM_DOCS_PER_BLOCK {
+            val = val.wrapping_add(vals[i]);
+            vals[i] = val;
+        }
     }
-    1 + bit_packer.close(&mut output).expect("packing in memory should never fail")
+    output[0] = num_bits;
+    1 + pack(vals, num_bits, &mut output[1..])
 }
 
 
+/// Computes the intersection of two strictly increasing
+/// arrays, and writes it in `output`.
+///
+/// Returns the length of the intersection.
+pub fn intersection_sorted(left: &[u32], right: &[u32], output: &mut [u32]) -> usize {
+    let mut i = 0;
+    let mut j = 0;
+    let mut count = 0;
+    while i < left.len() && j < right.len() {
+        match left[i].cmp(&right[j]) {
+            cmp::Ordering::Less => {
+                i += 1;
+            }
+            cmp::Ordering::Greater => {
+                j += 1;
+            }
+            cmp::Ordering::Equal => {
+                output[count] = left[i];
+                count += 1;
+                i += 1;
+                j += 1;
+            }
+        }
+    }
+    count
+}
+
 
 pub struct BlockEncoder {
-    pub output: [u8; COMPRESSED_BLOCK_MAX_SIZE],
-    pub output_len: usize,
+    output: [u8; COMPRESSED_BLOCK_MAX_SIZE],
+    output_len: usize,
     input_buffer: [u32; NUM_DOCS_PER_BLOCK],
 }
 
@@ -45,32 +152,62 @@ impl BlockEncoder {
         }    
     }
     
+    /// Returns the number of values appended to a block
+    /// of `len` values to pad it to a full block.
+    pub fn padding_for(len: usize) -> usize {
+        padding_for(len)
+    }
+
+    /// Compresses a block of at most `NUM_DOCS_PER_BLOCK` sorted values.
+    ///
+    /// Smaller blocks are padded with their last value,
+    /// and their actual number of values is recorded in the block.
     pub fn compress_block_sorted(&mut self, vals: &[u32], offset: u32) -> &[u8] {
-        self.input_buffer.clone_from_slice(vals);
-        let compressed_size = compress_sorted(&mut self.input_buffer, &mut self.output, offset);
-        &self.output[..compressed_size]
+        let header_len = write_block_header(vals.len(), &mut self.output);
+        let pad_val = vals.last().cloned().unwrap_or(offset);
+        pad_block(vals, pad_val, &mut self.input_buffer);
+        let compressed_size = compress_sorted(&mut self.input_buffer, &mut self.output[header_len..], offset);
+        self.output_len = header_len + compressed_size;
+        self.compressed()
     }
     
+    /// Compresses a block of at most `NUM_DOCS_PER_BLOCK` values.
+    ///
+    /// Smaller blocks are padded with `0`,
+    /// and their actual number of values is recorded in the block.
     pub fn compress_block_unsorted(&mut self, vals: &[u32]) -> &[u8] {       
+        let header_len = write_block_header(vals.len(), &mut self.output);
+        pad_block(vals, 0u32, &mut self.input_buffer);
         let compressed_size: usize = {
-            let mut output: &mut [u8] = &mut self.output;        
-            let max = vals.iter().cloned().max().expect("compress unsorted called with an empty array");
+            let max = self.input_buffer.iter().cloned().max().unwrap();
             let num_bits = compute_num_bits(max);
-            output.write_all(&[num_bits]).unwrap();
-            let mut bit_packer = BitPacker::new(num_bits as usize);
-            for val in vals {
-                bit_packer.write(*val, &mut output).unwrap();
-            }
-            1 + bit_packer.close(&mut output).expect("packing in memory should never fail")
+            let output = &mut self.output[header_len..];
+            output[0] = num_bits;
+            1 + pack(&self.input_buffer, num_bits, &mut output[1..])
         };
-        &self.output[..compressed_size]
+        self.output_len = header_len + compressed_size;
+        self.compressed()
+    }
+
+    /// Returns the last compressed block.
+    ///
+    /// Only the bytes written by the last compression
+    /// are returned.
+    pub fn compressed(&self,) -> &[u8] {
+        &self.output[..self.output_len]
+    }
+
+    /// Compresses a block using `compress`, which receives the
+    /// output buffer and returns the number of bytes it wrote.
+    pub fn compress_with<F: FnOnce(&mut [u8]) -> usize>(&mut self, compress: F) -> &[u8] {
+        self.output_len = compress(&mut self.output);
+        self.compressed()
     }
-    
 }
 
 pub struct BlockDecoder {
-    pub output: [u32; COMPRESSED_BLOCK_MAX_SIZE],
-    pub output_len: usize,
+    output: [u32; COMPRESSED_BLOCK_MAX_SIZE],
+    output_len: usize,
 }
 
 
@@ -80,6 +217,8 @@ impl BlockDecoder {
     }
     
     pub fn with_val(val: u32) -> BlockDecoder {
+        #[cfg(test)]
+        super::NUM_BLOCK_DECODERS.with(|num_block_decoders| num_block_decoders.set(num_block_decoders.get() + 1));
         BlockDecoder {
             output: [val; COMPRESSED_BLOCK_MAX_SIZE],
             output_len: 0,
@@ -87,32 +226,40 @@ impl BlockDecoder {
     }
     
     pub fn uncompress_block_sorted<'a>(&mut self, compressed_data: &'a [u8], mut offset: u32) -> &'a[u8] {
+        let (num_els, header_len) = read_block_header(compressed_data);
+        let compressed_data = &compressed_data[header_len..];
         let consumed_size = {
             let num_bits = compressed_data[0];
-            let bit_unpacker = BitUnpacker::new(&compressed_data[1..], num_bits as usize);
-            for i in 0..NUM_DOCS_PER_BLOCK {
-                let delta = bit_unpacker.get(i);
-                let val = offset + delta;
-                self.output[i] = val;
-                offset = val;
+            let packed_size = unpack(&compressed_data[1..], num_bits, &mut self.output);
+            if num_bits < 32 {
+                for i in 0..NUM_DOCS_PER_BLOCK {
+                    offset = offset.wrapping_add(self.output[i]);
+                    self.output[i] = offset;
+                }
             }
-            1 + (num_bits as usize * NUM_DOCS_PER_BLOCK + 7) / 8  
+            1 + packed_size
         };
-        self.output_len = NUM_DOCS_PER_BLOCK;
+        self.output_len = num_els;
         &compressed_data[consumed_size..]
     }
     
     pub fn uncompress_block_unsorted<'a>(&mut self, compressed_data: &'a [u8]) -> &'a[u8] {
+        let (num_els, header_len) = read_block_header(compressed_data);
+        let compressed_data = &compressed_data[header_len..];
         let num_bits = compressed_data[0];
-        let bit_unpacker = BitUnpacker::new(&compressed_data[1..], num_bits as usize);
-        for i in 0..NUM_DOCS_PER_BLOCK {
-            self.output[i] = bit_unpacker.get(i);
-        }
-        let consumed_size = 1 + (num_bits as usize * NUM_DOCS_PER_BLOCK + 7) / 8;
-        self.output_len = NUM_DOCS_PER_BLOCK;
+        let consumed_size = 1 + unpack(&compressed_data[1..], num_bits, &mut self.output);
+        self.output_len = num_els;
         &compressed_data[consumed_size..]
     }
     
+    /// Decompresses `num_els` values using `uncompress`, which receives 
+    /// the output buffer and returns the number of bytes it consumed.
+    pub fn uncompress_with<F: FnOnce(&mut [u32]) -> usize>(&mut self, num_els: usize, uncompress: F) -> usize {
+        let consumed_size = uncompress(&mut self.output);
+        self.output_len = num_els;
+        consumed_size
+    }
+
     #[inline]
     pub fn output_array(&self,) -> &[u32] {
         &self.output[..self.output_len]
@@ -124,4 +271,21 @@ impl BlockDecoder {
     }
 }
 
+/// Cloning a `BlockDecoder` copies its buffer, so that
+/// each thread of a parallel search can get its own decoder.
+///
+/// The clone does not share anything with the original decoder.
+/// `output_len` is carried over: the clone's `output_array()`
+/// is that of the original decoder, until a block is decoded.
+impl Clone for BlockDecoder {
+    fn clone(&self) -> BlockDecoder {
+        #[cfg(test)]
+        super::NUM_BLOCK_DECODERS.with(|num_block_decoders| num_block_decoders.set(num_block_decoders.get() + 1));
+        BlockDecoder {
+            output: self.output,
+            output_len: self.output_len,
+        }
+    }
+}
+
 
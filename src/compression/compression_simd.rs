@@ -1,6 +1,10 @@
 use super::NUM_DOCS_PER_BLOCK;
+use super::{padding_for, write_block_header, read_block_header, pad_block};
 
-const COMPRESSED_BLOCK_MAX_SIZE: usize = NUM_DOCS_PER_BLOCK * 4 + 1; 
+// A full block takes at most `1 + NUM_DOCS_PER_BLOCK * 4` bytes
+// once bitpacked, but a vint encoded tail of up to `NUM_DOCS_PER_BLOCK - 1`
+// values may take up to 5 bytes per value.
+const COMPRESSED_BLOCK_MAX_SIZE: usize = NUM_DOCS_PER_BLOCK * 5 + 1;
 
 mod simdcomp {
     use libc::size_t;
@@ -23,6 +27,13 @@ mod simdcomp {
         pub fn uncompress_unsorted(
             compressed_data: *const u8,
             output: *mut u32) -> size_t;
+
+        pub fn intersection_sorted(
+            left: *const u32,
+            left_len: size_t,
+            right: *const u32,
+            right_len: size_t,
+            output: *mut u32) -> size_t;
     }
 }
 
@@ -42,10 +53,24 @@ fn uncompress_unsorted(compressed_data: &[u8], output: &mut [u32]) -> usize {
     unsafe { simdcomp::uncompress_unsorted(compressed_data.as_ptr(), output.as_mut_ptr()) }
 }
 
+/// Computes the intersection of two strictly increasing
+/// arrays, and writes it in `output`.
+///
+/// Returns the length of the intersection.
+pub fn intersection_sorted(left: &[u32], right: &[u32], output: &mut [u32]) -> usize {
+    unsafe {
+        simdcomp::intersection_sorted(
+            left.as_ptr(), left.len(),
+            right.as_ptr(), right.len(),
+            output.as_mut_ptr())
+    }
+}
+
 
 pub struct BlockEncoder {
-    pub output: [u8; COMPRESSED_BLOCK_MAX_SIZE],
-    pub output_len: usize,
+    output: [u8; COMPRESSED_BLOCK_MAX_SIZE],
+    output_len: usize,
+    input_buffer: [u32; NUM_DOCS_PER_BLOCK],
 }
 
 impl BlockEncoder {
@@ -54,24 +79,70 @@ impl BlockEncoder {
         BlockEncoder {
             output: [0u8; COMPRESSED_BLOCK_MAX_SIZE],
             output_len: 0,
+            input_buffer: [0u32; NUM_DOCS_PER_BLOCK],
         }    
     }
     
+    /// Returns the number of values appended to a block
+    /// of `len` values to pad it to a full block.
+    pub fn padding_for(len: usize) -> usize {
+        padding_for(len)
+    }
+
+    /// Compresses a block of at most `NUM_DOCS_PER_BLOCK` sorted values.
+    ///
+    /// Smaller blocks are padded with their last value,
+    /// and their actual number of values is recorded in the block.
     pub fn compress_block_sorted(&mut self, vals: &[u32], offset: u32) -> &[u8] {
-        let compressed_size = compress_sorted(vals, &mut self.output, offset);
-        &self.output[..compressed_size]
+        let header_len = write_block_header(vals.len(), &mut self.output);
+        let compressed_size = if header_len == 0 {
+            compress_sorted(vals, &mut self.output, offset)
+        }
+        else {
+            let pad_val = vals.last().cloned().unwrap_or(offset);
+            pad_block(vals, pad_val, &mut self.input_buffer);
+            compress_sorted(&self.input_buffer, &mut self.output[header_len..], offset)
+        };
+        self.output_len = header_len + compressed_size;
+        self.compressed()
     }
     
+    /// Compresses a block of at most `NUM_DOCS_PER_BLOCK` values.
+    ///
+    /// Smaller blocks are padded with `0`,
+    /// and their actual number of values is recorded in the block.
     pub fn compress_block_unsorted(&mut self, vals: &[u32]) -> &[u8] {
-        let compressed_size = compress_unsorted(vals, &mut self.output);
-        &self.output[..compressed_size]
+        let header_len = write_block_header(vals.len(), &mut self.output);
+        let compressed_size = if header_len == 0 {
+            compress_unsorted(vals, &mut self.output)
+        }
+        else {
+            pad_block(vals, 0u32, &mut self.input_buffer);
+            compress_unsorted(&self.input_buffer, &mut self.output[header_len..])
+        };
+        self.output_len = header_len + compressed_size;
+        self.compressed()
+    }
+
+    /// Returns the last compressed block.
+    ///
+    /// Only the bytes written by the last compression
+    /// are returned.
+    pub fn compressed(&self,) -> &[u8] {
+        &self.output[..self.output_len]
+    }
+
+    /// Compresses a block using `compress`, which receives the
+    /// output buffer and returns the number of bytes it wrote.
+    pub fn compress_with<F: FnOnce(&mut [u8]) -> usize>(&mut self, compress: F) -> &[u8] {
+        self.output_len = compress(&mut self.output);
+        self.compressed()
     }
-    
 }
 
 pub struct BlockDecoder {
-    pub output: [u32; COMPRESSED_BLOCK_MAX_SIZE],
-    pub output_len: usize,
+    output: [u32; COMPRESSED_BLOCK_MAX_SIZE],
+    output_len: usize,
 }
 
 
@@ -81,6 +152,8 @@ impl BlockDecoder {
     }
     
     pub fn with_val(val: u32) -> BlockDecoder {
+        #[cfg(test)]
+        super::NUM_BLOCK_DECODERS.with(|num_block_decoders| num_block_decoders.set(num_block_decoders.get() + 1));
         BlockDecoder {
             output: [val; COMPRESSED_BLOCK_MAX_SIZE],
             output_len: 0,
@@ -88,17 +161,29 @@ impl BlockDecoder {
     }
     
     pub fn uncompress_block_sorted<'a>(&mut self, compressed_data: &'a [u8], offset: u32) -> &'a[u8] {
+        let (num_els, header_len) = read_block_header(compressed_data);
+        let compressed_data = &compressed_data[header_len..];
         let consumed_size = uncompress_sorted(compressed_data, &mut self.output, offset);
-        self.output_len = NUM_DOCS_PER_BLOCK;
+        self.output_len = num_els;
         &compressed_data[consumed_size..]
     }
     
     pub fn uncompress_block_unsorted<'a>(&mut self, compressed_data: &'a [u8]) -> &'a[u8] {
+        let (num_els, header_len) = read_block_header(compressed_data);
+        let compressed_data = &compressed_data[header_len..];
         let consumed_size = uncompress_unsorted(compressed_data, &mut self.output);
-        self.output_len = NUM_DOCS_PER_BLOCK;
+        self.output_len = num_els;
         &compressed_data[consumed_size..]
     }
     
+    /// Decompresses `num_els` values using `uncompress`, which receives 
+    /// the output buffer and returns the number of bytes it consumed.
+    pub fn uncompress_with<F: FnOnce(&mut [u32]) -> usize>(&mut self, num_els: usize, uncompress: F) -> usize {
+        let consumed_size = uncompress(&mut self.output);
+        self.output_len = num_els;
+        consumed_size
+    }
+
     #[inline]
     pub fn output_array(&self,) -> &[u32] {
         &self.output[..self.output_len]
@@ -110,4 +195,21 @@ impl BlockDecoder {
     }
 }
 
+/// Cloning a `BlockDecoder` copies its buffer, so that
+/// each thread of a parallel search can get its own decoder.
+///
+/// The clone does not share anything with the original decoder.
+/// `output_len` is carried over: the clone's `output_array()`
+/// is that of the original decoder, until a block is decoded.
+impl Clone for BlockDecoder {
+    fn clone(&self) -> BlockDecoder {
+        #[cfg(test)]
+        super::NUM_BLOCK_DECODERS.with(|num_block_decoders| num_block_decoders.set(num_block_decoders.get() + 1));
+        BlockDecoder {
+            output: self.output,
+            output_len: self.output_len,
+        }
+    }
+}
+
 
@@ -1,19 +1,115 @@
 #![allow(dead_code)]
 
+use std::cmp;
+use std::io;
+use common::BinarySerializable;
+#[cfg(test)]
+use std::cell::Cell;
+
 
 mod composite;
 pub use self::composite::{CompositeEncoder, CompositeDecoder};
 
+mod block_postings_writer;
+pub use self::block_postings_writer::BlockPostingsWriter;
+
 #[cfg(feature="simdcompression")]
 mod compression_simd;
 #[cfg(feature="simdcompression")]
 pub use self::compression_simd::{BlockEncoder, BlockDecoder};
+#[cfg(feature="simdcompression")]
+use self::compression_simd::intersection_sorted;
 
 
-#[cfg(not(feature="simdcompression"))]
+// The pure Rust implementation is also compiled for the tests
+// when `simdcompression` is enabled, in order to check
+// that both implementations produce the same bytes.
+#[cfg(any(not(feature="simdcompression"), test))]
 mod compression_nosimd;
 #[cfg(not(feature="simdcompression"))]
 pub use self::compression_nosimd::{BlockEncoder, BlockDecoder};
+#[cfg(not(feature="simdcompression"))]
+use self::compression_nosimd::intersection_sorted;
+
+
+#[cfg(test)]
+thread_local! {
+    // number of `BlockDecoder`s created by the current thread,
+    // used to check that guaranteed-empty queries do not create any.
+    pub static NUM_BLOCK_DECODERS: Cell<usize> = Cell::new(0);
+}
+
+
+/// Computes the intersection of two strictly increasing
+/// arrays of `u32`, typically two decoded blocks of doc ids,
+/// and writes it at the beginning of `out`.
+///
+/// When tantivy is compiled with the `simdcompression` feature,
+/// the intersection uses SSE2 instructions. Otherwise,
+/// it falls back to a scalar merge.
+///
+/// Returns the number of elements in the intersection.
+///
+/// # Panics
+/// Panics if `out` cannot hold the intersection,
+/// i.e. if its length is lower than that of `a` or `b`.
+pub fn simd_intersect(a: &[u32], b: &[u32], out: &mut [u32]) -> usize {
+    assert!(out.len() >= cmp::min(a.len(), b.len()));
+    intersection_sorted(a, b, out)
+}
+
+
+// Blocks of less than `NUM_DOCS_PER_BLOCK` values are padded
+// to a full block, and prefixed with a header byte holding their
+// number of values, flagged with `PARTIAL_BLOCK_FLAG`.
+//
+// Full blocks do not have this header. Their first byte is their 
+// number of bits, which is at most 32, so that both cases
+// cannot be confused.
+const PARTIAL_BLOCK_FLAG: u8 = 128u8;
+
+/// Returns the number of values appended to a block of `len` values
+/// to pad it to a multiple of `NUM_DOCS_PER_BLOCK`.
+fn padding_for(len: usize) -> usize {
+    (NUM_DOCS_PER_BLOCK - len % NUM_DOCS_PER_BLOCK) % NUM_DOCS_PER_BLOCK
+}
+
+/// Writes the header of a block of `num_vals` values at the beginning 
+/// of `output`, and returns its length.
+///
+/// # Panics
+/// Panics if `num_vals` is greater than `NUM_DOCS_PER_BLOCK`.
+fn write_block_header(num_vals: usize, output: &mut [u8]) -> usize {
+    assert!(num_vals <= NUM_DOCS_PER_BLOCK, "A block cannot contain more than {} values.", NUM_DOCS_PER_BLOCK);
+    if num_vals == NUM_DOCS_PER_BLOCK {
+        0
+    }
+    else {
+        output[0] = PARTIAL_BLOCK_FLAG | (num_vals as u8);
+        1
+    }
+}
+
+/// Reads the header written by `write_block_header`, and
+/// returns the number of values of the block and the length of the header.
+fn read_block_header(compressed_data: &[u8]) -> (usize, usize) {
+    let first_byte = compressed_data[0];
+    if first_byte & PARTIAL_BLOCK_FLAG != 0 {
+        ((first_byte & !PARTIAL_BLOCK_FLAG) as usize, 1)
+    }
+    else {
+        (NUM_DOCS_PER_BLOCK, 0)
+    }
+}
+
+/// Copies `vals` at the beginning of `block`, and fills
+/// the rest of the block with `pad_val`.
+fn pad_block(vals: &[u32], pad_val: u32, block: &mut [u32]) {
+    block[..vals.len()].copy_from_slice(vals);
+    for val in &mut block[vals.len()..] {
+        *val = pad_val;
+    }
+}
 
 
 pub trait VIntEncoder {
@@ -29,46 +125,50 @@ pub trait VIntDecoder {
 impl VIntEncoder for BlockEncoder{
     
     fn compress_vint_sorted(&mut self, input: &[u32], mut offset: u32) -> &[u8] {
-        let mut byte_written = 0;
-        for &v in input {
-            let mut to_encode: u32 = v - offset;
-            offset = v;
-            loop {
-                let next_byte: u8 = (to_encode % 128u32) as u8;
-                to_encode /= 128u32;
-                if to_encode == 0u32 {
-                    self.output[byte_written] = next_byte | 128u8;
-                    byte_written += 1;
-                    break;
-                }
-                else {
-                    self.output[byte_written] = next_byte;
-                    byte_written += 1;
+        self.compress_with(|output| {
+            let mut byte_written = 0;
+            for &v in input {
+                let mut to_encode: u32 = v - offset;
+                offset = v;
+                loop {
+                    let next_byte: u8 = (to_encode % 128u32) as u8;
+                    to_encode /= 128u32;
+                    if to_encode == 0u32 {
+                        output[byte_written] = next_byte | 128u8;
+                        byte_written += 1;
+                        break;
+                    }
+                    else {
+                        output[byte_written] = next_byte;
+                        byte_written += 1;
+                    }
                 }
             }
-        }
-        &self.output[..byte_written]
+            byte_written
+        })
     }
     
     fn compress_vint_unsorted(&mut self, input: &[u32]) -> &[u8] {
-        let mut byte_written = 0;
-        for &v in input {
-            let mut to_encode: u32 = v;
-            loop {
-                let next_byte: u8 = (to_encode % 128u32) as u8;
-                to_encode /= 128u32;
-                if to_encode == 0u32 {
-                    self.output[byte_written] = next_byte | 128u8;
-                    byte_written += 1;
-                    break;
-                }
-                else {
-                    self.output[byte_written] = next_byte;
-                    byte_written += 1;
+        self.compress_with(|output| {
+            let mut byte_written = 0;
+            for &v in input {
+                let mut to_encode: u32 = v;
+                loop {
+                    let next_byte: u8 = (to_encode % 128u32) as u8;
+                    to_encode /= 128u32;
+                    if to_encode == 0u32 {
+                        output[byte_written] = next_byte | 128u8;
+                        byte_written += 1;
+                        break;
+                    }
+                    else {
+                        output[byte_written] = next_byte;
+                        byte_written += 1;
+                    }
                 }
             }
-        }
-        &self.output[..byte_written]
+            byte_written
+        })
     }
 } 
 
@@ -79,52 +179,159 @@ impl VIntDecoder for BlockDecoder {
         compressed_data: &'a [u8],
         offset: u32,
         num_els: usize) -> &'a [u8] {
-        let mut read_byte = 0;
-        let mut result = offset;
-        for i in 0..num_els {
-            let mut shift = 0u32;
-            loop {
-                let cur_byte = compressed_data[read_byte];
-                read_byte += 1;
-                result += ((cur_byte % 128u8) as u32) << shift;
-                if cur_byte & 128u8 != 0u8 {
-                    break;
+        let consumed_size = self.uncompress_with(num_els, |output| {
+            let mut read_byte = 0;
+            let mut result = offset;
+            for i in 0..num_els {
+                let mut shift = 0u32;
+                loop {
+                    let cur_byte = compressed_data[read_byte];
+                    read_byte += 1;
+                    result += ((cur_byte % 128u8) as u32) << shift;
+                    if cur_byte & 128u8 != 0u8 {
+                        break;
+                    }
+                    shift += 7;
                 }
-                shift += 7;
+                output[i] = result;
             }
-            self.output[i] = result;
-        }
-        self.output_len = num_els;
-        &compressed_data[read_byte..]
+            read_byte
+        });
+        &compressed_data[consumed_size..]
     }
     
     fn uncompress_vint_unsorted<'a>(
         &mut self,
         compressed_data: &'a [u8],
         num_els: usize) -> &'a [u8] {
-        let mut read_byte = 0;
-        for i in 0..num_els {
-            let mut result = 0u32;
-            let mut shift = 0u32;
-            loop {
-                let cur_byte = compressed_data[read_byte];
-                read_byte += 1;
-                result += ((cur_byte % 128u8) as u32) << shift;
-                if cur_byte & 128u8 != 0u8 {
-                    break;
+        let consumed_size = self.uncompress_with(num_els, |output| {
+            let mut read_byte = 0;
+            for i in 0..num_els {
+                let mut result = 0u32;
+                let mut shift = 0u32;
+                loop {
+                    let cur_byte = compressed_data[read_byte];
+                    read_byte += 1;
+                    result += ((cur_byte % 128u8) as u32) << shift;
+                    if cur_byte & 128u8 != 0u8 {
+                        break;
+                    }
+                    shift += 7;
                 }
-                shift += 7;
+                output[i] = result;
             }
-            self.output[i] = result;
-        }
-        self.output_len = num_els;
-        &compressed_data[read_byte..]
+            read_byte
+        });
+        &compressed_data[consumed_size..]
     }
     
 }
 
-    
-    
+
+
+/// Skip information about a block of sorted values,
+/// compressed with `BlockEncoder::compress_block_sorted`.
+///
+/// See `compress_sorted_blocks` and `BlockDecoder::seek`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BlockSkipEntry {
+    /// Last value of the block.
+    pub last_val: u32,
+    /// Offset of the block within the compressed data.
+    pub offset: usize,
+}
+
+/// Number of bytes of a serialized `BlockSkipEntry`.
+pub const SKIP_ENTRY_NUM_BYTES: usize = 8;
+
+impl BinarySerializable for BlockSkipEntry {
+    fn serialize(&self, writer: &mut io::Write) -> io::Result<usize> {
+        Ok(
+            try!(self.last_val.serialize(writer)) +
+            try!((self.offset as u32).serialize(writer))
+        )
+    }
+    fn deserialize(reader: &mut io::Read) -> io::Result<Self> {
+        let last_val = try!(u32::deserialize(reader));
+        let offset = try!(u32::deserialize(reader));
+        Ok(BlockSkipEntry {
+            last_val: last_val,
+            offset: offset as usize,
+        })
+    }
+}
+
+/// Reads the skip entries serialized one after the other in `data`.
+///
+/// # Panics
+/// Panics if the length of `data` is not a multiple of `SKIP_ENTRY_NUM_BYTES`.
+pub fn read_skip_entries(mut data: &[u8]) -> Vec<BlockSkipEntry> {
+    assert_eq!(data.len() % SKIP_ENTRY_NUM_BYTES, 0);
+    let mut skip_entries = Vec::with_capacity(data.len() / SKIP_ENTRY_NUM_BYTES);
+    while !data.is_empty() {
+        skip_entries.push(BlockSkipEntry::deserialize(&mut data).unwrap());
+    }
+    skip_entries
+}
+
+/// Returns the number of bytes of the block compressed at the beginning
+/// of `compressed_data` by `BlockEncoder::compress_block_sorted`
+/// or `BlockEncoder::compress_block_unsorted`, without decompressing it.
+pub fn compressed_block_len(compressed_data: &[u8]) -> usize {
+    let (_, header_len) = read_block_header(compressed_data);
+    let num_bits = compressed_data[header_len] as usize;
+    header_len + 1 + num_bits * NUM_DOCS_PER_BLOCK / 8
+}
+
+/// Compresses sorted values as a sequence of blocks
+/// of `NUM_DOCS_PER_BLOCK` values, the last block being possibly partial.
+///
+/// Returns the compressed data, together with the skip entries of the blocks.
+pub fn compress_sorted_blocks(vals: &[u32]) -> (Vec<u8>, Vec<BlockSkipEntry>) {
+    let mut encoder = BlockEncoder::new();
+    let mut compressed_data = Vec::new();
+    let mut skip_entries = Vec::new();
+    let mut offset = 0u32;
+    for block in vals.chunks(NUM_DOCS_PER_BLOCK) {
+        let last_val = block[block.len() - 1];
+        skip_entries.push(BlockSkipEntry {
+            last_val: last_val,
+            offset: compressed_data.len(),
+        });
+        compressed_data.extend_from_slice(encoder.compress_block_sorted(block, offset));
+        offset = last_val;
+    }
+    (compressed_data, skip_entries)
+}
+
+impl BlockDecoder {
+
+    /// Decodes the block containing the first value greater or equal
+    /// to `target`, and returns this value and its index in `.output_array()`.
+    ///
+    /// `compressed_data` and `skip_entries` are typically obtained from
+    /// `compress_sorted_blocks`. The containing block is found by a binary
+    /// search on the skip entries, so that only this block is decoded.
+    /// Seeks do not depend on the previous ones: seeking to a `target`
+    /// lower than the previous one goes back to the start of its block.
+    ///
+    /// Returns `None` if all of the values are lower than `target`.
+    pub fn seek(&mut self, compressed_data: &[u8], skip_entries: &[BlockSkipEntry], target: u32) -> Option<(u32, usize)> {
+        let block_ord = match skip_entries.binary_search_by(|skip_entry| skip_entry.last_val.cmp(&target)) {
+            Ok(block_ord) | Err(block_ord) => block_ord,
+        };
+        if block_ord == skip_entries.len() {
+            return None;
+        }
+        let offset = if block_ord == 0 { 0u32 } else { skip_entries[block_ord - 1].last_val };
+        self.uncompress_block_sorted(&compressed_data[skip_entries[block_ord].offset..], offset);
+        // the last value of the block is greater or equal to `target`.
+        self.output_array()
+            .iter()
+            .position(|&val| val >= target)
+            .map(|idx| (self.output(idx), idx))
+    }
+}
 
 pub const NUM_DOCS_PER_BLOCK: usize = 128; //< should be a power of 2 to let the compiler optimize.
 
@@ -136,6 +343,8 @@ pub mod tests {
     use rand::XorShiftRng;
     use super::*;
     use test::Bencher;
+    use std::sync::Arc;
+    use std::thread;
     
     fn generate_array_with_seed(n: usize, ratio: f32, seed_val: u32) -> Vec<u32> {
         let seed: &[u32; 4] = &[1, 2, 3, seed_val];
@@ -165,6 +374,22 @@ pub mod tests {
         }
     }
 
+    #[test]
+    fn test_encoder_compressed() {
+        let mut encoder = BlockEncoder::new();
+        assert!(encoder.compressed().is_empty());
+        let vals: Vec<u32> = (0u32..128u32).map(|i| i * 1_000).collect();
+        let compressed_len = encoder.compress_block_sorted(&vals, 0).len();
+        assert_eq!(encoder.compressed().len(), compressed_len);
+        // compressing a smaller block does not expose 
+        // the stale bytes of the previous block.
+        let compressed_len = encoder.compress_vint_sorted(&[1u32, 2u32], 0).len();
+        assert_eq!(compressed_len, 2);
+        assert_eq!(encoder.compressed(), &[129u8, 129u8]);
+        let compressed_len = encoder.compress_block_unsorted(&vals).len();
+        assert_eq!(encoder.compressed().len(), compressed_len);
+    }
+
     #[test]
     fn test_encode_sorted_block_with_offset() {
         let vals: Vec<u32> = (0u32..128u32).map(|i| 11 + i*7).collect();
@@ -252,6 +477,303 @@ pub mod tests {
     }
 
 
+    #[test]
+    fn test_padding_for() {
+        assert_eq!(BlockEncoder::padding_for(100), 28);
+        assert_eq!(BlockEncoder::padding_for(1), 127);
+        assert_eq!(BlockEncoder::padding_for(NUM_DOCS_PER_BLOCK), 0);
+        assert_eq!(BlockEncoder::padding_for(NUM_DOCS_PER_BLOCK + 100), 28);
+    }
+
+    #[test]
+    fn test_encode_partial_block() {
+        let vals: Vec<u32> = (0u32..100u32).map(|i| 11 + i * 7).collect();
+        let mut encoder = BlockEncoder::new();
+        let mut decoder = BlockDecoder::new();
+        {
+            let mut compressed: Vec<u8> = Vec::new();
+            compressed.extend_from_slice(encoder.compress_block_sorted(&vals, 10));
+            compressed.push(173u8);
+            let remaining_data = decoder.uncompress_block_sorted(&compressed, 10);
+            assert_eq!(remaining_data, &[173u8]);
+        }
+        assert_eq!(decoder.output_array(), &vals[..]);
+        let unsorted_vals: Vec<u32> = (0u32..100u32).map(|i| (i * 7) % 12).collect();
+        {
+            let mut compressed: Vec<u8> = Vec::new();
+            compressed.extend_from_slice(encoder.compress_block_unsorted(&unsorted_vals));
+            compressed.push(173u8);
+            let remaining_data = decoder.uncompress_block_unsorted(&compressed);
+            assert_eq!(remaining_data, &[173u8]);
+        }
+        assert_eq!(decoder.output_array(), &unsorted_vals[..]);
+        {
+            let compressed_data = encoder.compress_block_sorted(&[], 10);
+            assert!(decoder.uncompress_block_sorted(compressed_data, 10).is_empty());
+        }
+        assert!(decoder.output_array().is_empty());
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_encode_block_too_large() {
+        let vals: Vec<u32> = (0u32..129u32).collect();
+        BlockEncoder::new().compress_block_sorted(&vals, 0);
+    }
+
+    #[test]
+    fn test_encode_vint_empty_and_single() {
+        let mut encoder = BlockEncoder::new();
+        let mut decoder = BlockDecoder::new();
+        for offset in &[0u32, 3u32] {
+            assert!(encoder.compress_vint_sorted(&[], *offset).is_empty());
+            assert_eq!(decoder.uncompress_vint_sorted(&[173u8], *offset, 0), &[173u8]);
+            assert!(decoder.output_array().is_empty());
+        }
+        assert!(encoder.compress_vint_unsorted(&[]).is_empty());
+        assert_eq!(decoder.uncompress_vint_unsorted(&[173u8], 0), &[173u8]);
+        assert!(decoder.output_array().is_empty());
+        {
+            let mut compressed: Vec<u8> = Vec::new();
+            compressed.extend_from_slice(encoder.compress_vint_sorted(&[300u32], 10));
+            assert_eq!(compressed.len(), 2);
+            compressed.push(173u8);
+            assert_eq!(decoder.uncompress_vint_sorted(&compressed, 10, 1), &[173u8]);
+            assert_eq!(decoder.output_array(), &[300u32]);
+        }
+        {
+            let mut compressed: Vec<u8> = Vec::new();
+            compressed.extend_from_slice(encoder.compress_vint_unsorted(&[7u32]));
+            assert_eq!(compressed, vec!(7u8 | 128u8));
+            compressed.push(173u8);
+            assert_eq!(decoder.uncompress_vint_unsorted(&compressed, 1), &[173u8]);
+            assert_eq!(decoder.output_array(), &[7u32]);
+        }
+    }
+
+    #[test]
+    fn test_encode_vint_large_values() {
+        // the longest possible tail, with values requiring 5 bytes each.
+        let input: Vec<u32> = (0..NUM_DOCS_PER_BLOCK as u32 - 1)
+            .map(|i| u32::max_value() - i)
+            .collect();
+        let mut encoder = BlockEncoder::new();
+        let mut decoder = BlockDecoder::new();
+        {
+            let encoded_data = encoder.compress_vint_unsorted(&input);
+            assert_eq!(encoded_data.len(), input.len() * 5);
+            assert!(decoder.uncompress_vint_unsorted(encoded_data, input.len()).is_empty());
+        }
+        assert_eq!(decoder.output_array(), &input[..]);
+        let sorted_input: Vec<u32> = input.iter().cloned().rev().collect();
+        {
+            let encoded_data = encoder.compress_vint_sorted(&sorted_input, 0);
+            assert!(decoder.uncompress_vint_sorted(encoded_data, 0, input.len()).is_empty());
+        }
+        assert_eq!(decoder.output_array(), &sorted_input[..]);
+    }
+
+    fn generate_block(rng: &mut XorShiftRng, num_bits: u8) -> Vec<u32> {
+        let mask = if num_bits == 32 { u32::max_value() } else { (1u32 << num_bits) - 1 };
+        (0..NUM_DOCS_PER_BLOCK)
+            .map(|_| rng.next_u32() & mask)
+            .collect()
+    }
+
+    fn generate_sorted_block(rng: &mut XorShiftRng, num_bits: u8, offset: u32) -> Vec<u32> {
+        let mut val = offset;
+        generate_block(rng, num_bits)
+            .into_iter()
+            .map(|delta| {
+                val = val.wrapping_add(delta);
+                val
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_nosimd_roundtrip_random_blocks() {
+        use super::compression_nosimd::{BlockEncoder, BlockDecoder};
+        let mut rng: XorShiftRng = XorShiftRng::from_seed([1, 2, 3, 4]);
+        let mut encoder = BlockEncoder::new();
+        let mut decoder = BlockDecoder::new();
+        for num_bits in 0u8..33u8 {
+            let vals = generate_block(&mut rng, num_bits);
+            {
+                let compressed_data = encoder.compress_block_unsorted(&vals);
+                assert_eq!(compressed_data.len(), 1 + num_bits as usize * 16);
+                assert!(decoder.uncompress_block_unsorted(compressed_data).is_empty());
+            }
+            assert_eq!(decoder.output_array(), &vals[..]);
+            let offset = rng.next_u32() % 1_000;
+            let vals = generate_sorted_block(&mut rng, num_bits, offset);
+            {
+                let compressed_data = encoder.compress_block_sorted(&vals, offset);
+                assert_eq!(compressed_data.len(), 1 + num_bits as usize * 16);
+                assert!(decoder.uncompress_block_sorted(compressed_data, offset).is_empty());
+            }
+            assert_eq!(decoder.output_array(), &vals[..]);
+        }
+    }
+
+    #[cfg(feature="simdcompression")]
+    #[test]
+    fn test_simd_and_nosimd_are_identical() {
+        use super::compression_simd;
+        use super::compression_nosimd;
+        let mut rng: XorShiftRng = XorShiftRng::from_seed([5, 6, 7, 8]);
+        let mut simd_encoder = compression_simd::BlockEncoder::new();
+        let mut nosimd_encoder = compression_nosimd::BlockEncoder::new();
+        let mut simd_decoder = compression_simd::BlockDecoder::new();
+        let mut nosimd_decoder = compression_nosimd::BlockDecoder::new();
+        for _ in 0..10 {
+            for num_bits in 0u8..33u8 {
+                let vals = generate_block(&mut rng, num_bits);
+                {
+                    let compressed_data = simd_encoder.compress_block_unsorted(&vals);
+                    assert_eq!(compressed_data, nosimd_encoder.compress_block_unsorted(&vals));
+                    simd_decoder.uncompress_block_unsorted(compressed_data);
+                    nosimd_decoder.uncompress_block_unsorted(compressed_data);
+                }
+                assert_eq!(simd_decoder.output_array(), &vals[..]);
+                assert_eq!(nosimd_decoder.output_array(), &vals[..]);
+                let offset = rng.next_u32() % 1_000;
+                let vals = generate_sorted_block(&mut rng, num_bits, offset);
+                {
+                    let compressed_data = simd_encoder.compress_block_sorted(&vals, offset);
+                    assert_eq!(compressed_data, nosimd_encoder.compress_block_sorted(&vals, offset));
+                    simd_decoder.uncompress_block_sorted(compressed_data, offset);
+                    nosimd_decoder.uncompress_block_sorted(compressed_data, offset);
+                }
+                assert_eq!(simd_decoder.output_array(), &vals[..]);
+                assert_eq!(nosimd_decoder.output_array(), &vals[..]);
+                let partial_vals = &vals[..num_bits as usize * 3];
+                assert_eq!(simd_encoder.compress_block_sorted(partial_vals, offset),
+                           nosimd_encoder.compress_block_sorted(partial_vals, offset));
+                assert_eq!(simd_encoder.compress_block_unsorted(partial_vals),
+                           nosimd_encoder.compress_block_unsorted(partial_vals));
+            }
+        }
+    }
+
+    fn scalar_intersect(a: &[u32], b: &[u32]) -> Vec<u32> {
+        a.iter()
+            .cloned()
+            .filter(|val| b.binary_search(val).is_ok())
+            .collect()
+    }
+
+    #[test]
+    fn test_block_decoder_seek() {
+        let vals = generate_array(1_000, 0.1);
+        let (compressed_data, skip_entries) = compress_sorted_blocks(&vals);
+        assert_eq!(skip_entries.len(), 8);
+        assert_eq!(skip_entries[0], BlockSkipEntry { last_val: vals[127], offset: 0 });
+        assert_eq!(skip_entries[7].last_val, vals[999]);
+        let mut decoder = BlockDecoder::new();
+        let expected_seek = |target: u32| {
+            vals.iter()
+                .position(|&val| val >= target)
+                .map(|ord| (vals[ord], ord % NUM_DOCS_PER_BLOCK))
+        };
+        for &target in &[0u32, vals[0], vals[127], vals[127] + 1, vals[500] - 1, vals[500], vals[999]] {
+            assert_eq!(decoder.seek(&compressed_data, &skip_entries, target), expected_seek(target));
+        }
+        // seeking backwards
+        assert_eq!(decoder.seek(&compressed_data, &skip_entries, vals[3]), Some((vals[3], 3)));
+        assert_eq!(decoder.output_array().len(), NUM_DOCS_PER_BLOCK);
+        assert_eq!(decoder.seek(&compressed_data, &skip_entries, vals[999] + 1), None);
+        // within the last, partial, block.
+        assert_eq!(decoder.seek(&compressed_data, &skip_entries, vals[990]), Some((vals[990], 990 - 896)));
+        assert_eq!(decoder.output_array().len(), 1_000 - 896);
+        assert_eq!(decoder.seek(&[], &[], 0u32), None);
+        // the skip entries go through their serialization.
+        let mut skip_data = Vec::new();
+        for skip_entry in &skip_entries {
+            skip_entry.serialize(&mut skip_data).unwrap();
+        }
+        assert_eq!(skip_data.len(), 8 * SKIP_ENTRY_NUM_BYTES);
+        assert_eq!(read_skip_entries(&skip_data), skip_entries);
+        // the length of a block can be read without decoding it.
+        assert_eq!(compressed_block_len(&compressed_data[skip_entries[2].offset..]),
+                   skip_entries[3].offset - skip_entries[2].offset);
+    }
+
+    #[test]
+    fn test_block_decoder_clone() {
+        let vals = generate_array(128, 0.1);
+        let other_vals: Vec<u32> = (0u32..5u32).collect();
+        let mut encoder = BlockEncoder::new();
+        let compressed_data: Arc<Vec<u8>> = Arc::new(encoder.compress_block_sorted(&vals, 0).to_vec());
+        let other_compressed_data = encoder.compress_vint_sorted(&other_vals, 0).to_vec();
+        let mut decoder = BlockDecoder::new();
+        decoder.uncompress_block_sorted(&compressed_data, 0);
+        let mut cloned_decoder = decoder.clone();
+        assert_eq!(cloned_decoder.output_array(), &vals[..]);
+        // the buffers are not shared.
+        cloned_decoder.uncompress_vint_sorted(&other_compressed_data, 0, other_vals.len());
+        assert_eq!(cloned_decoder.output_array(), &other_vals[..]);
+        assert_eq!(decoder.output_array(), &vals[..]);
+        // both decoders decode the same block concurrently.
+        let handles: Vec<thread::JoinHandle<Vec<u32>>> = vec!(decoder, cloned_decoder)
+            .into_iter()
+            .map(|mut decoder| {
+                let compressed_data = compressed_data.clone();
+                thread::spawn(move || {
+                    for _ in 0..1_000 {
+                        decoder.uncompress_block_sorted(&compressed_data, 0);
+                    }
+                    decoder.output_array().to_vec()
+                })
+            })
+            .collect();
+        let outputs: Vec<Vec<u32>> = handles
+            .into_iter()
+            .map(|handle| handle.join().unwrap())
+            .collect();
+        assert_eq!(outputs[0], vals);
+        assert_eq!(outputs[1], vals);
+    }
+
+    #[test]
+    fn test_simd_intersect() {
+        let test_intersect = |a: &[u32], b: &[u32]| {
+            let mut out = vec!(0u32; cmp::min(a.len(), b.len()));
+            let len = simd_intersect(a, b, &mut out);
+            assert_eq!(&out[..len], &scalar_intersect(a, b)[..]);
+        };
+        test_intersect(&[], &[1, 2]);
+        test_intersect(&[1, 3, 5, 7, 9], &[1, 2, 3, 4, 5, 6, 7, 8, 9]);
+        test_intersect(&[0, 1, 2, 3], &[4, 5, 6, 7]);
+        test_intersect(&[4, 5, 6, 7], &[0, 1, 2, 3, 4]);
+        for seed in 0..10u32 {
+            let a = generate_array_with_seed(NUM_DOCS_PER_BLOCK, 0.1, seed);
+            let b = generate_array_with_seed(NUM_DOCS_PER_BLOCK + seed as usize, 0.2, seed + 100);
+            test_intersect(&a, &b);
+            test_intersect(&b, &a);
+            test_intersect(&a, &a);
+        }
+    }
+
+    #[bench]
+    fn bench_simd_intersect_dense(b: &mut Bencher) {
+        let left = generate_array_with_seed(NUM_DOCS_PER_BLOCK, 0.5, 1);
+        let right = generate_array_with_seed(NUM_DOCS_PER_BLOCK, 0.5, 2);
+        let mut out = vec!(0u32; NUM_DOCS_PER_BLOCK);
+        b.iter(|| {
+            simd_intersect(&left, &right, &mut out)
+        });
+    }
+
+    #[bench]
+    fn bench_scalar_intersect_dense(b: &mut Bencher) {
+        let left = generate_array_with_seed(NUM_DOCS_PER_BLOCK, 0.5, 1);
+        let right = generate_array_with_seed(NUM_DOCS_PER_BLOCK, 0.5, 2);
+        b.iter(|| {
+            scalar_intersect(&left, &right)
+        });
+    }
+
     #[bench]
     fn bench_compress(b: &mut Bencher) {
         let mut encoder = BlockEncoder::new();
@@ -0,0 +1,255 @@
+use Result;
+use Error;
+use core::Index;
+use core::META_FILEPATH;
+use core::IndexMeta;
+use directory::{Directory, FileProtection, ManagedDirectory};
+use indexer::SegmentManager;
+use indexer::LOCKFILE_NAME;
+use indexer::operation_log_files;
+use rustc_serialize::json;
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::io::Write;
+
+
+/// Files of a commit of an index, protected from the
+/// garbage collection for as long as the guard is alive.
+///
+/// The files may for instance be hardlinked or copied to a backup
+/// directory: merges and commits may still happen meanwhile, but the
+/// files of the snapshot are not deleted until the guard is dropped.
+///
+/// The protection only applies to the garbage collection of the
+/// `IndexWriter`s opened on the same `Index` (or on one of its clones).
+///
+/// See `Index::backup_file_list`.
+pub struct BackupGuard {
+    files: HashSet<PathBuf>,
+    meta_data: Vec<u8>,
+    _file_protections: Vec<FileProtection>,
+}
+
+impl BackupGuard {
+    /// Returns the files of the snapshot, including `META_FILEPATH`.
+    ///
+    /// The paths are relative to the directory of the index.
+    pub fn files(&self) -> &HashSet<PathBuf> {
+        &self.files
+    }
+
+    /// Returns the content of the meta file of the snapshot.
+    ///
+    /// The meta file is replaced, rather than deleted, by the commits.
+    /// A backup may therefore write this content in place of
+    /// the meta file, if a commit happened since the snapshot.
+    pub fn meta_data(&self) -> &[u8] {
+        &self.meta_data
+    }
+}
+
+/// Point-in-time copy of the last commit of an index, taken
+/// while the index is being written.
+///
+/// The files of the commit are protected from the garbage collection
+/// until the snapshot is dropped, as with a `BackupGuard`, while the
+/// `IndexWriter` keeps on committing and merging.
+///
+/// See `Index::snapshot`.
+pub struct Snapshot {
+    directory: ManagedDirectory,
+    opstamp: u64,
+    backup_guard: BackupGuard,
+}
+
+impl Snapshot {
+    /// Returns the opstamp of the commit of the snapshot.
+    pub fn opstamp(&self) -> u64 {
+        self.opstamp
+    }
+
+    /// Returns the files to copy, including `META_FILEPATH`.
+    ///
+    /// The paths are relative to the directory of the index.
+    pub fn files(&self) -> &HashSet<PathBuf> {
+        self.backup_guard.files()
+    }
+
+    /// Copies the files of the snapshot to `directory`, which can then
+    /// be opened as an index at the point of the snapshot.
+    ///
+    /// The meta file is written last, with the content
+    /// it had when the snapshot was taken.
+    pub fn copy_to(&self, directory: &mut Directory) -> Result<()> {
+        for path in self.files() {
+            if *path == *META_FILEPATH {
+                continue;
+            }
+            let source = try!(self.directory.open_read(path));
+            let mut write = try!(directory.open_write(path));
+            try!(write.write_all(&source));
+            try!(write.flush());
+        }
+        try!(directory.atomic_write(&META_FILEPATH, self.backup_guard.meta_data()));
+        Ok(())
+    }
+}
+
+fn decode_metas(meta_data: &[u8]) -> Result<IndexMeta> {
+    json::decode(&String::from_utf8_lossy(meta_data))
+        .map_err(|e| Error::CorruptedFile(META_FILEPATH.clone(), Box::new(e)))
+}
+
+/// Takes a snapshot of the last commit of `index`.
+pub fn snapshot(index: &Index) -> Result<Snapshot> {
+    let backup_guard = try!(backup_file_list(index));
+    let index_meta = try!(decode_metas(backup_guard.meta_data()));
+    Ok(Snapshot {
+        directory: index.directory().clone(),
+        opstamp: index_meta.opstamp,
+        backup_guard: backup_guard,
+    })
+}
+
+/// Lists and protects the files of the last commit of `index`.
+///
+/// The meta file is read again once the files are protected: if a
+/// commit happened meanwhile, the garbage collection may
+/// already have deleted some of them, and the snapshot is taken again.
+pub fn backup_file_list(index: &Index) -> Result<BackupGuard> {
+    let directory = index.directory();
+    let mut meta_data = try!(directory.atomic_read(&META_FILEPATH));
+    loop {
+        let index_meta = try!(decode_metas(&meta_data));
+        let segment_manager = SegmentManager::from_segments(index_meta.segments,
+                                                            index.segment_access_counters().clone());
+        let log_files = try!(operation_log_files(directory, index_meta.opstamp));
+        let files: HashSet<PathBuf> = segment_manager
+            .list_files()
+            .into_iter()
+            // the operation log is not managed, but belongs to the commit.
+            .chain(log_files)
+            // the lock file belongs to the `IndexWriter`, not to the commit.
+            .filter(|path| *path != PathBuf::from(LOCKFILE_NAME))
+            .filter(|path| directory.exists(path))
+            .collect();
+        let file_protections: Vec<FileProtection> = files
+            .iter()
+            .map(|path| directory.protect_file_from_delete(path))
+            .collect();
+        let current_meta_data = try!(directory.atomic_read(&META_FILEPATH));
+        if current_meta_data == meta_data {
+            return Ok(BackupGuard {
+                files: files,
+                meta_data: meta_data,
+                _file_protections: file_protections,
+            });
+        }
+        meta_data = current_meta_data;
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use core::Index;
+    use core::META_FILEPATH;
+    use directory::{Directory, MmapDirectory};
+    use query::TermQuery;
+    use collector::CountCollector;
+    use postings::SegmentPostingsOption;
+    use schema::Term;
+    use indexer::{NoMergePolicy, LOCKFILE_NAME};
+    use schema::{Document, FieldValue, SchemaBuilder, TEXT};
+    use futures::Future;
+    use std::path::PathBuf;
+    use tempdir::TempDir;
+
+    #[test]
+    fn test_backup_file_list() {
+        let tempdir = TempDir::new("index").unwrap();
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create(tempdir.path(), schema_builder.build()).unwrap();
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        index_writer.set_merge_policy(box NoMergePolicy);
+        for text in &["a", "b"] {
+            index_writer.add_document(doc!(text_field=>*text));
+            index_writer.commit().unwrap();
+        }
+        let backup_guard = index.backup_file_list().unwrap();
+        let files = backup_guard.files().clone();
+        assert!(files.contains(&*META_FILEPATH));
+        assert!(!files.contains(&PathBuf::from(LOCKFILE_NAME)));
+        assert_eq!(backup_guard.meta_data(), &index.directory().atomic_read(&META_FILEPATH).unwrap()[..]);
+        let segment_ids = index.searchable_segment_ids().unwrap();
+        assert_eq!(segment_ids.len(), 2);
+        for segment_meta in index.segments().unwrap() {
+            assert!(segment_meta.list_files().is_subset(&files));
+        }
+
+        // the merged segments are not garbage collected.
+        index_writer.merge(&segment_ids).wait().unwrap();
+        index_writer.garbage_collect_files().unwrap();
+        assert!(backup_guard.meta_data() != &index.directory().atomic_read(&META_FILEPATH).unwrap()[..]);
+        for path in &files {
+            assert!(index.directory().exists(path), "{:?} was deleted", path);
+        }
+
+        drop(backup_guard);
+        index_writer.garbage_collect_files().unwrap();
+        let num_deleted_files = files
+            .iter()
+            .filter(|path| !index.directory().exists(path))
+            .count();
+        assert!(num_deleted_files > 0);
+        assert!(index.directory().exists(&*META_FILEPATH));
+    }
+
+    #[test]
+    fn test_snapshot() {
+        let tempdir = TempDir::new("index").unwrap();
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create(tempdir.path(), schema_builder.build()).unwrap();
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        index_writer.set_merge_policy(box NoMergePolicy);
+        for text in &["a", "a b", "b"] {
+            index_writer.add_document(doc!(text_field=>*text));
+            index_writer.commit().unwrap();
+        }
+        let snapshot = index.snapshot().unwrap();
+        assert_eq!(snapshot.opstamp(), index.opstamp());
+
+        // writing goes on after the snapshot.
+        index_writer.delete_term(Term::from_field_text(text_field, "a"));
+        index_writer.add_document(doc!(text_field=>"c"));
+        index_writer.commit().unwrap();
+        let segment_ids = index.searchable_segment_ids().unwrap();
+        index_writer.merge(&segment_ids).wait().unwrap();
+        index_writer.garbage_collect_files().unwrap();
+        index.load_searchers().unwrap();
+        assert_eq!(index.searcher().num_docs(), 2);
+
+        let backup_dir = TempDir::new("backup").unwrap();
+        let mut directory = MmapDirectory::open(backup_dir.path()).unwrap();
+        snapshot.copy_to(&mut directory).unwrap();
+        drop(snapshot);
+        let snapshot_index = Index::open(backup_dir.path()).unwrap();
+        snapshot_index.load_searchers().unwrap();
+        let searcher = snapshot_index.searcher();
+        assert_eq!(searcher.num_docs(), 3);
+        assert_eq!(searcher.segment_readers().len(), 3);
+        let count_docs = |text: &str| {
+            let term_query = TermQuery::new(Term::from_field_text(text_field, text), SegmentPostingsOption::NoFreq);
+            let mut count_collector = CountCollector::default();
+            searcher.search(&term_query, &mut count_collector).unwrap();
+            count_collector.count()
+        };
+        assert_eq!(count_docs("a"), 2);
+        assert_eq!(count_docs("b"), 2);
+        assert_eq!(count_docs("c"), 0);
+        assert!(snapshot_index.check_integrity().unwrap().is_ok());
+    }
+}
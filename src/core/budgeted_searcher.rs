@@ -0,0 +1,338 @@
+use Result;
+use Error;
+use core::Searcher;
+use core::Segment;
+use core::SegmentReader;
+use core::CollectionStatistics;
+use collector::Collector;
+use common::TimerTree;
+use directory::FileProtection;
+use query::Query;
+use schema::{Document, Term};
+use std::collections::HashMap;
+use std::cmp;
+use std::sync::{Condvar, Mutex, MutexGuard};
+use DocAddress;
+use SegmentLocalId;
+
+
+#[derive(Default)]
+struct OpenReaders {
+    // the open readers that are not in use,
+    // least recently used first.
+    idle_readers: Vec<(usize, SegmentReader)>,
+    num_in_use: usize,
+    peak_num_open: usize,
+}
+
+impl OpenReaders {
+    fn num_open(&self) -> usize {
+        self.idle_readers.len() + self.num_in_use
+    }
+}
+
+/// Searches the segments of an index while keeping at most
+/// `max_open_readers` segment readers open at the same time.
+///
+/// Unlike the `Searcher`, which opens all of the readers of its segments
+/// upfront, the readers are opened on demand, and the least recently
+/// used ones are closed to stay within the budget.
+/// This bounds the file handles and the memory used by indexes with
+/// many segments, at the cost of reopening the readers.
+///
+/// A query is run on chunks of `max_open_readers` segments,
+/// one chunk after the other. The total number of documents and the
+/// document frequencies of the terms of the query are computed over
+/// all of the segments, so that the scores do not depend on the
+/// budget. The average field norms, and the terms expanded from a
+/// pattern (fuzzy, regex, wildcard...) are however those of the chunk.
+///
+/// When several threads share the searcher, a thread waits for
+/// the readers of the other threads to be released rather than
+/// exceed the budget.
+pub struct BudgetedSearcher {
+    segments: Vec<Segment>,
+    max_open_readers: usize,
+    open_readers: Mutex<OpenReaders>,
+    readers_released: Condvar,
+    // the files of the segments are not garbage collected
+    // as long as the searcher is alive, even if their reader is closed.
+    _file_protections: Vec<FileProtection>,
+}
+
+impl BudgetedSearcher {
+
+    /// Creates a searcher over `segments`, keeping at most
+    /// `max_open_readers` of their readers open at the same time.
+    /// No reader is opened until it is needed.
+    ///
+    /// See `Index::budgeted_searcher`.
+    ///
+    /// # Panics
+    /// Panics if `max_open_readers` is 0.
+    pub fn new(segments: Vec<Segment>, max_open_readers: usize) -> BudgetedSearcher {
+        assert!(max_open_readers > 0, "At least one segment reader must be allowed to be open.");
+        let file_protections = segments
+            .iter()
+            .flat_map(|segment| segment.protect_from_delete())
+            .collect();
+        BudgetedSearcher {
+            segments: segments,
+            max_open_readers: max_open_readers,
+            open_readers: Mutex::new(OpenReaders::default()),
+            readers_released: Condvar::new(),
+            _file_protections: file_protections,
+        }
+    }
+
+    /// Returns the segments of the searcher.
+    pub fn segments(&self) -> &[Segment] {
+        &self.segments
+    }
+
+    /// Returns the number of segments of the searcher.
+    pub fn num_segments(&self) -> usize {
+        self.segments.len()
+    }
+
+    /// Returns the maximum number of segment readers
+    /// open at the same time.
+    pub fn max_open_readers(&self) -> usize {
+        self.max_open_readers
+    }
+
+    /// Returns the overall number of documents in the index.
+    pub fn num_docs(&self) -> u64 {
+        self.segments
+            .iter()
+            .map(|segment| segment.meta().num_docs() as u64)
+            .sum()
+    }
+
+    /// Returns the number of segment readers currently open.
+    pub fn num_open_segment_readers(&self) -> usize {
+        self.lock_open_readers().num_open()
+    }
+
+    /// Returns the highest number of segment readers
+    /// ever open at the same time.
+    pub fn peak_open_segment_readers(&self) -> usize {
+        self.lock_open_readers().peak_num_open
+    }
+
+    /// Fetches a document from tantivy's store given a `DocAddress`.
+    pub fn doc(&self, doc_address: &DocAddress) -> Result<Document> {
+        let DocAddress(segment_local_id, doc_id) = *doc_address;
+        let segment_ord = segment_local_id as usize;
+        if segment_ord >= self.num_segments() {
+            return Err(Error::InvalidArgument(format!("{:?} refers to a segment out of range", doc_address)));
+        }
+        let segment_readers = try!(self.take_readers(&[segment_ord]));
+        let doc_res = segment_readers[0].doc(doc_id);
+        self.release_readers(&[segment_ord], segment_readers);
+        doc_res
+    }
+
+    /// Returns the overall number of documents containing
+    /// the given term.
+    pub fn doc_freq(&self, term: &Term) -> Result<u64> {
+        let mut doc_freq = 0u64;
+        for segment_ord in 0..self.num_segments() {
+            let segment_readers = try!(self.take_readers(&[segment_ord]));
+            doc_freq += segment_readers[0].doc_freq(term) as u64;
+            self.release_readers(&[segment_ord], segment_readers);
+        }
+        Ok(doc_freq)
+    }
+
+    /// Returns the statistics of all of the segments, including
+    /// the document frequencies of the terms of `query`.
+    ///
+    /// The terms are those of `Query::query_terms`.
+    pub fn query_collection_statistics(&self, query: &Query) -> Result<CollectionStatistics> {
+        let mut terms = Vec::new();
+        query.query_terms(&mut terms);
+        let mut doc_freqs: HashMap<Term, u64> = terms
+            .into_iter()
+            .map(|term| (term, 0u64))
+            .collect();
+        for segment_ord in 0..self.num_segments() {
+            let segment_readers = try!(self.take_readers(&[segment_ord]));
+            for (term, doc_freq) in &mut doc_freqs {
+                *doc_freq += segment_readers[0].doc_freq(term) as u64;
+            }
+            self.release_readers(&[segment_ord], segment_readers);
+        }
+        let mut collection_statistics = CollectionStatistics::new(self.num_docs());
+        collection_statistics.doc_freqs = doc_freqs;
+        Ok(collection_statistics)
+    }
+
+    /// Runs a query on the segments of the searcher.
+    ///
+    /// The segments are passed to the collector in order,
+    /// with their ordinal in the searcher.
+    pub fn search(&self, query: &Query, collector: &mut Collector) -> Result<TimerTree> {
+        let collection_statistics = try!(self.query_collection_statistics(query));
+        let segment_ords: Vec<usize> = (0..self.num_segments()).collect();
+        let mut timer_tree = TimerTree::default();
+        {
+            let mut search_timer = timer_tree.open("search");
+            for chunk_ords in segment_ords.chunks(self.max_open_readers) {
+                if collector.is_aborted() {
+                    break;
+                }
+                let segment_readers = try!(self.take_readers(chunk_ords));
+                let mut chunk_searcher = Searcher::from(segment_readers);
+                chunk_searcher.set_collection_statistics(Some(collection_statistics.clone()));
+                let search_res = {
+                    let _chunk_search_timer = search_timer.open("chunk_search");
+                    search_chunk(&chunk_searcher, chunk_ords[0], query, collector)
+                };
+                // the readers are released even if the search failed.
+                self.release_readers(chunk_ords, chunk_searcher.into_segment_readers());
+                try!(search_res);
+            }
+        }
+        Ok(timer_tree)
+    }
+
+    fn lock_open_readers(&self) -> MutexGuard<OpenReaders> {
+        self.open_readers
+            .lock()
+            .expect("The open segment readers should never be poisoned")
+    }
+
+    // Takes the readers of the given segments out of the idle readers,
+    // opening the missing ones. Waits for the readers used by other
+    // threads to be released if taking them would exceed the budget.
+    fn take_readers(&self, segment_ords: &[usize]) -> Result<Vec<SegmentReader>> {
+        assert!(segment_ords.len() <= self.max_open_readers);
+        let mut open_readers = self.lock_open_readers();
+        while open_readers.num_in_use + segment_ords.len() > self.max_open_readers {
+            open_readers = self.readers_released
+                .wait(open_readers)
+                .expect("The open segment readers should never be poisoned");
+        }
+        let mut segment_readers = Vec::with_capacity(segment_ords.len());
+        for &segment_ord in segment_ords {
+            let position_opt = open_readers.idle_readers
+                .iter()
+                .position(|&(idle_segment_ord, _)| idle_segment_ord == segment_ord);
+            let segment_reader = match position_opt {
+                Some(position) => open_readers.idle_readers.remove(position).1,
+                None => {
+                    while !open_readers.idle_readers.is_empty() && open_readers.num_open() >= self.max_open_readers {
+                        open_readers.idle_readers.remove(0);
+                    }
+                    match SegmentReader::open(self.segments[segment_ord].clone()) {
+                        Ok(segment_reader) => segment_reader,
+                        Err(e) => {
+                            let taken_ords = &segment_ords[..segment_readers.len()];
+                            self.put_back_readers(&mut open_readers, taken_ords, segment_readers);
+                            return Err(e);
+                        }
+                    }
+                }
+            };
+            open_readers.num_in_use += 1;
+            open_readers.peak_num_open = cmp::max(open_readers.peak_num_open, open_readers.num_open());
+            segment_readers.push(segment_reader);
+        }
+        Ok(segment_readers)
+    }
+
+    fn release_readers(&self, segment_ords: &[usize], segment_readers: Vec<SegmentReader>) {
+        let mut open_readers = self.lock_open_readers();
+        self.put_back_readers(&mut open_readers, segment_ords, segment_readers);
+    }
+
+    fn put_back_readers(&self,
+                        open_readers: &mut OpenReaders,
+                        segment_ords: &[usize],
+                        segment_readers: Vec<SegmentReader>) {
+        assert_eq!(segment_ords.len(), segment_readers.len());
+        open_readers.num_in_use -= segment_readers.len();
+        for (&segment_ord, segment_reader) in segment_ords.iter().zip(segment_readers) {
+            open_readers.idle_readers.push((segment_ord, segment_reader));
+        }
+        while open_readers.num_open() > self.max_open_readers {
+            open_readers.idle_readers.remove(0);
+        }
+        self.readers_released.notify_all();
+    }
+}
+
+
+fn search_chunk(chunk_searcher: &Searcher,
+                first_segment_ord: usize,
+                query: &Query,
+                collector: &mut Collector) -> Result<()> {
+    let weight = try!(query.weight(chunk_searcher));
+    for (chunk_ord, segment_reader) in chunk_searcher.segment_readers().iter().enumerate() {
+        if collector.is_aborted() {
+            break;
+        }
+        let segment_ord = first_segment_ord + chunk_ord;
+        try!(collector.set_segment(segment_ord as SegmentLocalId, segment_reader));
+        let mut scorer = try!(weight.scorer(segment_reader));
+        segment_reader.record_access();
+        scorer.collect(collector);
+    }
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use schema::{Document, FieldValue, SchemaBuilder, Term, TEXT, STORED};
+    use query::TermQuery;
+    use collector::TopCollector;
+    use postings::SegmentPostingsOption;
+    use indexer::NoMergePolicy;
+    use Index;
+    use DocAddress;
+
+    #[test]
+    fn test_budgeted_searcher() {
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT | STORED);
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.set_merge_policy(box NoMergePolicy);
+            for i in 0..8 {
+                index_writer.add_document(doc!(text_field => "a"));
+                if i % 2 == 0 {
+                    index_writer.add_document(doc!(text_field => "a b b"));
+                }
+                index_writer.commit().unwrap();
+            }
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        assert_eq!(searcher.num_segments(), 8);
+        for &max_open_readers in &[1, 2, 3] {
+            let budgeted_searcher = index.budgeted_searcher(max_open_readers).unwrap();
+            assert_eq!(budgeted_searcher.num_segments(), 8);
+            assert_eq!(budgeted_searcher.num_docs(), 12);
+            assert_eq!(budgeted_searcher.num_open_segment_readers(), 0);
+            for text in &["a", "b"] {
+                let term = Term::from_field_text(text_field, text);
+                let query = TermQuery::new(term.clone(), SegmentPostingsOption::Freq);
+                let mut top_collector = TopCollector::with_limit(20);
+                searcher.search(&query, &mut top_collector).unwrap();
+                let mut budgeted_top_collector = TopCollector::with_limit(20);
+                budgeted_searcher.search(&query, &mut budgeted_top_collector).unwrap();
+                assert_eq!(budgeted_top_collector.score_docs(), top_collector.score_docs());
+                assert_eq!(budgeted_searcher.doc_freq(&term).unwrap(), searcher.doc_freq(&term) as u64);
+            }
+            let doc = budgeted_searcher.doc(&DocAddress(6, 1)).unwrap();
+            assert_eq!(doc.get_first(text_field).unwrap().text(), "a b b");
+            assert!(budgeted_searcher.doc(&DocAddress(8, 0)).is_err());
+            assert!(budgeted_searcher.num_open_segment_readers() <= max_open_readers);
+            assert_eq!(budgeted_searcher.peak_open_segment_readers(), max_open_readers);
+        }
+    }
+}
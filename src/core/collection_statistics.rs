@@ -0,0 +1,57 @@
+use std::collections::HashMap;
+use schema::{Field, Term};
+
+/// Statistics of a whole collection of documents, used
+/// in place of the statistics of the searcher to compute
+/// the idf of the terms.
+///
+/// When a collection is split into several shards, each
+/// searching its own index, the idf of a term is computed
+/// from the documents of the shard only, and the scores of
+/// different shards cannot be compared.
+/// Injecting the statistics of the whole collection in each
+/// searcher (see `Searcher::set_collection_statistics`) makes
+/// them consistent.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CollectionStatistics {
+    /// Total number of documents of the collection.
+    pub total_docs: u64,
+    /// Number of documents of the collection, for the fields
+    /// for which it differs from `total_docs`.
+    ///
+    /// For instance, the number of documents in which the field
+    /// is present.
+    pub field_doc_counts: HashMap<Field, u64>,
+    /// Number of documents of the collection containing each term.
+    ///
+    /// The terms that are absent from this map use the
+    /// document frequency of the searcher.
+    pub doc_freqs: HashMap<Term, u64>,
+}
+
+impl CollectionStatistics {
+
+    /// Creates statistics for a collection of `total_docs` documents.
+    pub fn new(total_docs: u64) -> CollectionStatistics {
+        CollectionStatistics {
+            total_docs: total_docs,
+            field_doc_counts: HashMap::new(),
+            doc_freqs: HashMap::new(),
+        }
+    }
+
+    /// Returns the number of documents to consider
+    /// when computing the idf of the terms of `field`.
+    pub fn num_docs(&self, field: Field) -> u64 {
+        self.field_doc_counts
+            .get(&field)
+            .cloned()
+            .unwrap_or(self.total_docs)
+    }
+
+    /// Returns the number of documents of the collection
+    /// containing `term`, if it is known.
+    pub fn doc_freq(&self, term: &Term) -> Option<u64> {
+        self.doc_freqs.get(term).cloned()
+    }
+}
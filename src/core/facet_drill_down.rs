@@ -0,0 +1,109 @@
+use bit_set::BitSet;
+use core::SegmentReader;
+use schema::{Field, Term, Facet};
+use schema::FACET_SEP;
+use postings::SegmentPostingsOption;
+use postings::DocSet;
+
+/// Narrows a set of results down to the documents belonging to a facet.
+///
+/// Interactive faceted navigation typically refines the results
+/// of a query one facet at a time. Rather than running the query again
+/// with an additional filter, the drill-down intersects the documents
+/// previously matched by the query with the documents of the facet.
+///
+/// Results are handled per segment, as a `BitSet` of the `DocId`s
+/// of the segment.
+pub struct FacetDrillDown;
+
+impl FacetDrillDown {
+
+    /// Returns the documents of `base` that belong to `facet`
+    /// or to one of its descendants.
+    ///
+    /// `field` is the untokenized text field the facets of the documents
+    /// are indexed in, and `base` the results of the segment of `reader`.
+    pub fn apply(base: &BitSet, field: Field, facet: &Facet, reader: &SegmentReader) -> BitSet {
+        let mut result = BitSet::with_capacity(reader.max_doc() as usize);
+        if base.is_empty() {
+            return result;
+        }
+        let prefix = Term::from_field_text(field, facet.encoded_str());
+        let prefix_len = prefix.as_slice().len();
+        for (term_bytes, _) in reader.term_infos().prefix_iter(prefix.as_slice()) {
+            // `/electronics` is not a descendant of `/elec`.
+            let is_descendant = term_bytes.len() == prefix_len ||
+                term_bytes[prefix_len] == FACET_SEP as u8;
+            if !is_descendant {
+                continue;
+            }
+            let term = Term::from_bytes(&term_bytes);
+            if let Some(mut postings) = reader.read_postings(&term, SegmentPostingsOption::NoFreq) {
+                while postings.advance() {
+                    let doc = postings.doc() as usize;
+                    if base.contains(doc) {
+                        result.insert(doc);
+                    }
+                }
+            }
+        }
+        result
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use core::Index;
+    use schema::{Document, FieldValue, SchemaBuilder, TEXT, STRING};
+    use collector::tests::TestCollector;
+    use query::TermQuery;
+
+    #[test]
+    fn test_facet_drill_down() {
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let category_field = schema_builder.add_text_field("category", STRING);
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            let products = [
+                ("phone on sale", "/electronics/phones"),   // 0
+                ("laptop on sale", "/electronics/laptops"), // 1
+                ("shirt on sale", "/clothing/shirts"),      // 2
+                ("laptop", "/electronics/laptops"),         // 3
+                ("cable on sale", "/electronics"),          // 4
+                ("elec on sale", "/elec"),                  // 5
+                ("deleted tv on sale", "/electronics/tv"),  // 6
+            ];
+            for &(text, category) in &products {
+                let category = Facet::from_text(category);
+                index_writer.add_document(doc!(text_field=>text, category_field=>category.encoded_str()));
+            }
+            index_writer.delete_term(Term::from_field_text(text_field, "deleted"));
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let segment_reader = searcher.segment_reader(0);
+        let base: BitSet = {
+            let query = TermQuery::new(Term::from_field_text(text_field, "sale"), SegmentPostingsOption::NoFreq);
+            let mut test_collector = TestCollector::default();
+            searcher.search(&query, &mut test_collector).unwrap();
+            test_collector.docs().into_iter().map(|doc| doc as usize).collect()
+        };
+        assert_eq!(base.iter().collect::<Vec<usize>>(), vec!(0, 1, 2, 4, 5));
+        let drill_down = |base: &BitSet, facet: &str| {
+            FacetDrillDown::apply(base, category_field, &Facet::from_text(facet), segment_reader)
+        };
+        let electronics = drill_down(&base, "/electronics");
+        assert_eq!(electronics.iter().collect::<Vec<usize>>(), vec!(0, 1, 4));
+        let laptops = drill_down(&electronics, "/electronics/laptops");
+        assert_eq!(laptops.iter().collect::<Vec<usize>>(), vec!(1));
+        assert_eq!(drill_down(&base, "/"), base);
+        assert!(drill_down(&base, "/books").is_empty());
+        assert!(drill_down(&BitSet::new(), "/electronics").is_empty());
+    }
+}
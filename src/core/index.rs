@@ -1,18 +1,33 @@
 use Result;
 use Error;
 use schema::Schema;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::borrow::BorrowMut;
 use std::fmt;
+use std::cmp;
 use rustc_serialize::json;
 use core::SegmentId;
 use directory::{Directory, MmapDirectory, RAMDirectory};
+use directory::error::FileError;
 use indexer::index_writer::open_index_writer;
+use indexer::LOCKFILE_NAME;
 use core::searcher::Searcher;
+use fastfield::FastFieldColumnCache;
+use core::ResultCache;
+use core::SegmentAccessCounters;
+use core::SearchPool;
+use analyzer::TokenizerManager;
+use core::IntegrityReport;
+use super::integrity::check_segment_integrity;
 use std::convert::From;
 use num_cpus;
 use super::segment::Segment;
 use core::SegmentReader;
+use core::BudgetedSearcher;
+use core::{IndexReader, ReloadPolicy};
+use core::{BackupGuard, Snapshot};
+use super::backup::{backup_file_list, snapshot};
 use super::pool::Pool;
 use core::SegmentMeta;
 use super::pool::LeasedItem;
@@ -22,10 +37,19 @@ use IndexWriter;
 use directory::ManagedDirectory;
 use core::META_FILEPATH;
 use super::segment::create_segment;
-use indexer::segment_updater::save_new_metas;
+use indexer::segment_updater::{save_metas, save_new_metas};
+use indexer::Operation;
+use indexer::load_operation_log;
 
 const NUM_SEARCHERS: usize = 12;
 
+// The statistics and the similarity set on a leased searcher
+// only apply to the queries of the lease.
+fn reset_searcher(searcher: &mut Searcher) {
+    searcher.set_collection_statistics(None);
+    searcher.set_similarity(None);
+}
+
 fn load_metas(directory: &Directory) -> Result<IndexMeta> {
     let meta_data = directory.atomic_read(&META_FILEPATH)?;
     let meta_string = String::from_utf8_lossy(&meta_data);
@@ -38,6 +62,17 @@ pub struct Index {
     directory: ManagedDirectory,
     schema: Schema,
     searcher_pool: Arc<Pool<Searcher>>,
+    frozen: Arc<AtomicBool>,
+    // set while a writer of the index holds the lockfile.
+    has_writer: Arc<AtomicBool>,
+    // serializes the freeze and the opening of the writers.
+    writer_lock: Arc<Mutex<()>>,
+    // 0 if the number of search threads depends on the number of segments.
+    num_search_threads: Arc<AtomicUsize>,
+    // threads shared by the searchers, created on demand.
+    search_pool: Arc<Mutex<Option<SearchPool>>>,
+    segment_access_counters: SegmentAccessCounters,
+    tokenizers: TokenizerManager,
 }
 
 
@@ -82,7 +117,14 @@ impl Index {
         let index = Index {
             directory: directory,
             schema: schema,
-            searcher_pool: Arc::new(Pool::new()),
+            searcher_pool: Arc::new(Pool::with_reset(reset_searcher)),
+            frozen: Arc::new(AtomicBool::new(metas.is_frozen())),
+            has_writer: Arc::new(AtomicBool::new(false)),
+            writer_lock: Arc::new(Mutex::new(())),
+            num_search_threads: Arc::new(AtomicUsize::new(0)),
+            search_pool: Arc::default(),
+            segment_access_counters: SegmentAccessCounters::default(),
+            tokenizers: TokenizerManager::default(),
         };
         try!(index.load_searchers());
         Ok(index)
@@ -123,27 +165,156 @@ impl Index {
     ///
     /// # Errors
     /// If the lockfile already exists, returns `Error::FileAlreadyExists`.
+    /// If the index is frozen, returns `Error::ReadOnlyError`.
+    /// If the tokenizer of a field is not registered, returns `Error::SchemaError`.
     /// # Panics
     /// If the heap size per thread is too small, panics.
     pub fn writer_with_num_threads(&self,
                                    num_threads: usize,
                                    heap_size_in_bytes: usize)
                                    -> Result<IndexWriter> {
+        let _writer_lock = self.writer_lock.lock().expect("Writer lock poisoned");
+        if self.is_frozen() {
+            return Err(Error::ReadOnlyError);
+        }
         open_index_writer(self, num_threads, heap_size_in_bytes)
     }
 
 
+    /// Opens a new index writer whose indexing threads
+    /// share a memory budget of `memory_budget_in_bytes`.
+    ///
+    /// Unlike `writer_with_num_threads`, whose heap size is per thread,
+    /// the budget is divided evenly among the `num_threads` threads.
+    /// Each of them holds the segment it is building, that is its term
+    /// dictionary and its postings, in memory. When this memory gets
+    /// close to the thread's share of the budget, the segment is
+    /// flushed to the directory, and a new segment is started.
+    ///
+    /// The flushed segments are uncommitted: they only become
+    /// searchable after `IndexWriter::commit`.
+    ///
+    /// # Errors
+    /// As for `writer_with_num_threads`.
+    /// # Panics
+    /// If the share of the budget of a thread is too small,
+    /// or if `num_threads` is 0, panics.
+    pub fn writer_with_memory_budget(&self,
+                                     num_threads: usize,
+                                     memory_budget_in_bytes: usize)
+                                     -> Result<IndexWriter> {
+        assert!(num_threads > 0, "At least one indexing thread is required.");
+        self.writer_with_num_threads(num_threads, memory_budget_in_bytes / num_threads)
+    }
+
     /// Creates a multithreaded writer
     /// It just calls `writer_with_num_threads` with the number of cores as `num_threads`
     ///
     /// # Errors
     /// If the lockfile already exists, returns `Error::FileAlreadyExists`.
+    /// If the index is frozen, returns `Error::ReadOnlyError`.
+    /// If the tokenizer of a field is not registered, returns `Error::SchemaError`.
     /// # Panics
     /// If the heap size per thread is too small, panics.
     pub fn writer(&self, heap_size_in_bytes: usize) -> Result<IndexWriter> {
         self.writer_with_num_threads(num_cpus::get(), heap_size_in_bytes)
     }
 
+    /// Marks the index as read-only.
+    ///
+    /// Once frozen, opening a writer returns `Error::ReadOnlyError`,
+    /// and the writers opened before the freeze refuse to commit.
+    /// Searchers keep on working normally.
+    ///
+    /// The frozen state is shared by all of the clones of the `Index`,
+    /// and persisted in the meta file: opening the index again
+    /// from its directory yields a frozen index.
+    ///
+    /// If `release_writer_lock` is true, the lockfile of the index
+    /// is removed as well. This is useful to get rid of a stale
+    /// lockfile, left by a writer that did not terminate properly.
+    /// The lockfile of a writer of this `Index` that is still alive
+    /// is left in place: it is removed when the writer is dropped.
+    pub fn freeze(&self, release_writer_lock: bool) -> Result<()> {
+        let _writer_lock = self.writer_lock.lock().expect("Writer lock poisoned");
+        self.frozen.store(true, Ordering::SeqCst);
+        let metas = try!(load_metas(self.directory()));
+        if !metas.is_frozen() {
+            try!(save_metas(metas.segments,
+                            metas.schema,
+                            metas.opstamp,
+                            true,
+                            self.directory.box_clone().borrow_mut()));
+        }
+        if release_writer_lock && !self.has_writer.load(Ordering::SeqCst) {
+            match self.directory.delete(Path::new(LOCKFILE_NAME)) {
+                Ok(()) | Err(FileError::FileDoesNotExist(_)) => {}
+                Err(e) => { return Err(From::from(e)); }
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns true iff the index has been frozen.
+    ///
+    /// See `Index::freeze`.
+    pub fn is_frozen(&self) -> bool {
+        self.frozen.load(Ordering::SeqCst)
+    }
+
+    /// Returns the flag set while a writer
+    /// of the index holds the lockfile.
+    #[doc(hidden)]
+    pub fn has_writer(&self) -> &Arc<AtomicBool> {
+        &self.has_writer
+    }
+
+    /// Sets the number of threads the searchers collect
+    /// the segments with, in `Searcher::search_fruit`.
+    ///
+    /// By default, (`None`) there is one thread per segment,
+    /// up to the number of CPUs.
+    ///
+    /// The number of threads is shared by all of the clones
+    /// of the `Index`, and it applies to the searchers loaded
+    /// afterwards, see `.load_searchers()`.
+    ///
+    /// The threads are taken from a pool shared by the searchers,
+    /// so that searching does not spawn any thread.
+    ///
+    /// # Panics
+    /// Panics if the number of threads is `Some(0)`.
+    pub fn set_num_search_threads(&self, num_search_threads: Option<usize>) {
+        assert!(num_search_threads != Some(0), "At least one thread is required to search.");
+        self.num_search_threads.store(num_search_threads.unwrap_or(0), Ordering::SeqCst);
+    }
+
+    /// Returns the number of threads the searchers collect
+    /// the segments with, if it was set.
+    ///
+    /// See `Index::set_num_search_threads`.
+    pub fn num_search_threads(&self) -> Option<usize> {
+        match self.num_search_threads.load(Ordering::SeqCst) {
+            0 => None,
+            num_search_threads => Some(num_search_threads),
+        }
+    }
+
+    // Returns the pool of threads shared by the searchers, with
+    // `.num_search_threads()` threads, or one per CPU by default.
+    fn search_pool(&self) -> SearchPool {
+        let num_threads = self.num_search_threads().unwrap_or_else(num_cpus::get);
+        let mut search_pool = self.search_pool.lock().unwrap();
+        let is_stale = search_pool
+            .as_ref()
+            .map(|search_pool| search_pool.num_threads() != num_threads)
+            .unwrap_or(true);
+        if is_stale {
+            *search_pool = Some(SearchPool::new(num_threads));
+        }
+        search_pool.as_ref().unwrap().clone()
+    }
+
     /// Accessor to the index schema
     ///
     /// The schema is actually cloned.
@@ -172,6 +343,25 @@ impl Index {
         create_segment(self.clone(), segment_meta)
     }
 
+    /// Returns the number of scorers created on each segment
+    /// by the searchers of the index.
+    #[doc(hidden)]
+    pub fn segment_access_counters(&self) -> &SegmentAccessCounters {
+        &self.segment_access_counters
+    }
+
+    /// Returns the tokenizers of the index.
+    ///
+    /// Custom tokenizers must be registered before indexing
+    /// documents or parsing queries, and they have to be registered
+    /// again each time the index is opened, as they are not persisted:
+    /// only their name is recorded in the schema.
+    ///
+    /// See `SchemaBuilder::set_tokenizer`.
+    pub fn tokenizers(&self) -> &TokenizerManager {
+        &self.tokenizers
+    }
+
     /// Return a reference to the index directory.
     pub fn directory(&self) -> &ManagedDirectory {
         &self.directory
@@ -182,6 +372,42 @@ impl Index {
         &mut self.directory
     }
 
+    /// Returns the committed operations of the operation log,
+    /// starting from the operation with the opstamp `from_opstamp`,
+    /// in opstamp order.
+    ///
+    /// The log records the documents added, the delete operations,
+    /// and the commits. Unlike the index itself, it is only meant for
+    /// auditing: the operations that were rolled back
+    /// are not logged, and the log can be compacted,
+    /// see `IndexWriter::compact_operation_log`.
+    pub fn operation_log(&self, from_opstamp: u64) -> Result<impl Iterator<Item=Operation>> {
+        let last_commit_opstamp = load_metas(self.directory())?.opstamp;
+        let operations = load_operation_log(self.directory(), last_commit_opstamp)?;
+        Ok(operations
+            .into_iter()
+            .filter(move |operation| operation.opstamp() >= from_opstamp))
+    }
+
+    /// Returns the files of the last commit, for a
+    /// filesystem-level backup of a live index.
+    ///
+    /// The files, `META_FILEPATH` included, are not deleted
+    /// by the garbage collection until the returned guard is dropped,
+    /// even if they are merged or deleted meanwhile.
+    pub fn backup_file_list(&self) -> Result<BackupGuard> {
+        backup_file_list(self)
+    }
+
+    /// Takes a snapshot of the last commit, for an online backup.
+    ///
+    /// The files of the commit are not garbage collected until the
+    /// snapshot is dropped, while writing and merging goes on.
+    /// See `Snapshot::copy_to`.
+    pub fn snapshot(&self) -> Result<Snapshot> {
+        snapshot(self)
+    }
+
     /// Reads the meta.json and returns the list of
     /// segments in the last commit.
     pub fn segments(&self) -> Result<Vec<SegmentMeta>> {
@@ -205,19 +431,51 @@ impl Index {
     pub fn load_searchers(&self) -> Result<()> {
         let searchable_segments = self.searchable_segments()?;
         let mut searchers = Vec::new();
+        let fast_field_cache = Arc::new(FastFieldColumnCache::default());
+        let result_cache = Arc::new(ResultCache::default());
+        let num_search_threads = self.num_search_threads()
+            .unwrap_or_else(|| cmp::max(1, cmp::min(searchable_segments.len(), num_cpus::get())));
+        let search_pool = if num_search_threads > 1 { Some(self.search_pool()) } else { None };
         for _ in 0..NUM_SEARCHERS {
             let searchable_segments_clone = searchable_segments.clone();
             let segment_readers: Vec<SegmentReader> = try!(searchable_segments_clone
                 .into_iter()
                 .map(SegmentReader::open)
                 .collect());
-            let searcher = Searcher::from(segment_readers);
+            let mut searcher = Searcher::new(segment_readers, fast_field_cache.clone(), result_cache.clone());
+            if let Some(ref search_pool) = search_pool {
+                searcher.set_search_pool(search_pool.clone());
+            }
+            searcher.set_num_search_threads(num_search_threads);
             searchers.push(searcher);
         }
         self.searcher_pool.publish_new_generation(searchers);
         Ok(())
     }
 
+    /// Checks the consistency of the searchable segments of the index.
+    ///
+    /// For each segment, it checks that
+    /// - the number of documents of the doc store, the fast fields
+    /// and the field norms match the number of documents of the segment,
+    /// - the footer of the doc store is valid,
+    /// - the term dictionary is sorted, and the postings only refer
+    /// to documents of the segment,
+    /// - the delete bitset is within the bounds of the segment.
+    ///
+    /// All of the problems detected are reported.
+    /// An error is only returned if the segments cannot be listed.
+    pub fn check_integrity(&self) -> Result<IntegrityReport> {
+        let mut report = IntegrityReport::default();
+        for segment in self.searchable_segments()? {
+            let segment_id = segment.id();
+            for problem in check_segment_integrity(segment) {
+                report.add_problem(segment_id, problem);
+            }
+        }
+        Ok(report)
+    }
+
     /// Returns a searcher
     ///
     /// This method should be called every single time a search
@@ -231,6 +489,42 @@ impl Index {
     pub fn searcher(&self) -> LeasedItem<Searcher> {
         self.searcher_pool.acquire()
     }
+
+    /// Creates a searcher on the searchable segments keeping
+    /// at most `max_open_readers` segment readers open at the same time.
+    ///
+    /// This is useful for indexes with too many segments for all of
+    /// their readers to be opened at once. See `BudgetedSearcher`.
+    ///
+    /// # Panics
+    /// Panics if `max_open_readers` is 0.
+    pub fn budgeted_searcher(&self, max_open_readers: usize) -> Result<BudgetedSearcher> {
+        let searchable_segments = self.searchable_segments()?;
+        Ok(BudgetedSearcher::new(searchable_segments, max_open_readers))
+    }
+
+    /// Creates an `IndexReader`, reloading the searchers
+    /// of the index according to `reload_policy`.
+    ///
+    /// This is useful to search an index written by another
+    /// process, or through another `Index` handle.
+    pub fn reader(&self, reload_policy: ReloadPolicy) -> Result<IndexReader> {
+        IndexReader::new(self.clone(), reload_policy)
+    }
+
+    /// Opens a new searcher on the searchable segments,
+    /// outside of the pool of searchers.
+    ///
+    /// Unlike `.searcher()`, the searcher is owned by the caller.
+    /// It does not share its caches with the pooled searchers,
+    /// and is not affected by `.load_searchers()`.
+    pub fn open_searcher(&self) -> Result<Searcher> {
+        let segment_readers: Vec<SegmentReader> = try!(self.searchable_segments()?
+            .into_iter()
+            .map(SegmentReader::open)
+            .collect());
+        Ok(Searcher::from(segment_readers))
+    }
 }
 
 
@@ -246,6 +540,13 @@ impl Clone for Index {
             directory: self.directory.clone(),
             schema: self.schema.clone(),
             searcher_pool: self.searcher_pool.clone(),
+            frozen: self.frozen.clone(),
+            has_writer: self.has_writer.clone(),
+            writer_lock: self.writer_lock.clone(),
+            num_search_threads: self.num_search_threads.clone(),
+            search_pool: self.search_pool.clone(),
+            segment_access_counters: self.segment_access_counters.clone(),
+            tokenizers: self.tokenizers.clone(),
         }
     }
 }
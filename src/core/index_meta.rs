@@ -14,6 +14,8 @@ pub struct IndexMeta {
     pub segments: Vec<SegmentMeta>,
     pub schema: Schema,
     pub opstamp: u64,
+    // `None` for the indexes created before the freeze was persisted.
+    pub frozen: Option<bool>,
 }
 
 impl IndexMeta {
@@ -22,6 +24,14 @@ impl IndexMeta {
             segments: vec!(),
             schema: schema,
             opstamp: 0u64,
+            frozen: None,
         }
     }
+
+    /// Returns true iff the index has been frozen.
+    ///
+    /// See `Index::freeze`.
+    pub fn is_frozen(&self) -> bool {
+        self.frozen.unwrap_or(false)
+    }
 }
@@ -0,0 +1,178 @@
+use Result;
+use core::Index;
+use core::Searcher;
+use core::META_FILEPATH;
+use super::pool::LeasedItem;
+use directory::Directory;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+
+/// Defines when an `IndexReader` reloads its searchers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReloadPolicy {
+    /// The searchers are only reloaded by calling `IndexReader::reload`.
+    Manual,
+    /// The searchers are reloaded when a commit is detected,
+    /// that is when the meta file of the index changes.
+    OnCommit,
+}
+
+struct ReloadState {
+    // content of the meta file the searchers were loaded from.
+    meta_data: Vec<u8>,
+    last_check: Instant,
+}
+
+/// Gives access to the searchers of an index,
+/// and reloads them according to a `ReloadPolicy`.
+///
+/// This makes it possible for a process to search an index
+/// written by another process: with `ReloadPolicy::OnCommit`,
+/// the commits of the writer are taken into account
+/// without any coordination between the processes.
+///
+/// Reloading publishes a new generation of searchers.
+/// The searchers acquired before the reload keep on working
+/// against the segments they were opened on.
+pub struct IndexReader {
+    index: Index,
+    reload_policy: ReloadPolicy,
+    min_reload_interval: Duration,
+    reload_state: Mutex<ReloadState>,
+}
+
+impl IndexReader {
+
+    /// Creates a reader over `index`, loading its searchers.
+    ///
+    /// See also `Index::reader`.
+    pub fn new(index: Index, reload_policy: ReloadPolicy) -> Result<IndexReader> {
+        let meta_data = try!(index.directory().atomic_read(&META_FILEPATH));
+        try!(index.load_searchers());
+        Ok(IndexReader {
+            index: index,
+            reload_policy: reload_policy,
+            min_reload_interval: Duration::from_millis(500),
+            reload_state: Mutex::new(ReloadState {
+                meta_data: meta_data,
+                last_check: Instant::now(),
+            }),
+        })
+    }
+
+    /// Returns the index of the reader.
+    pub fn index(&self) -> &Index {
+        &self.index
+    }
+
+    /// Returns the reload policy of the reader.
+    pub fn reload_policy(&self) -> ReloadPolicy {
+        self.reload_policy
+    }
+
+    /// Sets the minimum interval between two checks of
+    /// the meta file with `ReloadPolicy::OnCommit`, 500ms by default.
+    ///
+    /// A burst of commits within the interval then results
+    /// in a single reload.
+    pub fn set_min_reload_interval(&mut self, min_reload_interval: Duration) {
+        self.min_reload_interval = min_reload_interval;
+    }
+
+    /// Reloads the searchers if the index has changed
+    /// since they were loaded.
+    ///
+    /// Returns true iff the searchers were reloaded.
+    pub fn reload(&self) -> Result<bool> {
+        let mut reload_state = self.reload_state.lock().expect("Reload state lock poisoned");
+        self.reload_if_changed(&mut reload_state)
+    }
+
+    fn reload_if_changed(&self, reload_state: &mut ReloadState) -> Result<bool> {
+        reload_state.last_check = Instant::now();
+        let meta_data = try!(self.index.directory().atomic_read(&META_FILEPATH));
+        if meta_data == reload_state.meta_data {
+            return Ok(false);
+        }
+        try!(self.index.load_searchers());
+        reload_state.meta_data = meta_data;
+        Ok(true)
+    }
+
+    /// Returns a searcher, as `Index::searcher` does.
+    ///
+    /// With `ReloadPolicy::OnCommit`, the searchers are reloaded
+    /// first if a commit happened since they were loaded. The meta file
+    /// of the index is checked at most once per minimum reload interval.
+    pub fn searcher(&self) -> Result<LeasedItem<Searcher>> {
+        if self.reload_policy == ReloadPolicy::OnCommit {
+            let mut reload_state = self.reload_state.lock().expect("Reload state lock poisoned");
+            if reload_state.last_check.elapsed() >= self.min_reload_interval {
+                try!(self.reload_if_changed(&mut reload_state));
+            }
+        }
+        Ok(self.index.searcher())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::ReloadPolicy;
+    use core::Index;
+    use schema::{Document, FieldValue, SchemaBuilder, Term, TEXT};
+    use std::time::Duration;
+    use tempdir::TempDir;
+
+    #[test]
+    fn test_index_reader_reload() {
+        let tempdir = TempDir::new("index").unwrap();
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create(tempdir.path(), schema_builder.build()).unwrap();
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        index_writer.add_document(doc!(text_field=>"a"));
+        index_writer.commit().unwrap();
+
+        // the readers open their own handle on the index.
+        let manual_reader = Index::open(tempdir.path()).unwrap().reader(ReloadPolicy::Manual).unwrap();
+        let mut on_commit_reader = Index::open(tempdir.path()).unwrap().reader(ReloadPolicy::OnCommit).unwrap();
+        on_commit_reader.set_min_reload_interval(Duration::from_millis(0));
+        assert_eq!(manual_reader.searcher().unwrap().num_docs(), 1);
+        assert_eq!(on_commit_reader.searcher().unwrap().num_docs(), 1);
+        assert!(!manual_reader.reload().unwrap());
+
+        index_writer.add_document(doc!(text_field=>"b"));
+        index_writer.commit().unwrap();
+        let manual_searcher = manual_reader.searcher().unwrap();
+        assert_eq!(manual_searcher.num_docs(), 1);
+        assert_eq!(on_commit_reader.searcher().unwrap().num_docs(), 2);
+
+        assert!(manual_reader.reload().unwrap());
+        assert_eq!(manual_reader.searcher().unwrap().num_docs(), 2);
+        // the searcher acquired before the reload still works on its segments.
+        assert_eq!(manual_searcher.num_docs(), 1);
+        assert_eq!(manual_searcher.doc_freq(&Term::from_field_text(text_field, "a")), 1);
+    }
+
+    #[test]
+    fn test_index_reader_min_reload_interval() {
+        let tempdir = TempDir::new("index").unwrap();
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create(tempdir.path(), schema_builder.build()).unwrap();
+        let mut reader = Index::open(tempdir.path()).unwrap().reader(ReloadPolicy::OnCommit).unwrap();
+        reader.set_min_reload_interval(Duration::from_secs(3_600));
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        for text in &["a", "b", "c"] {
+            index_writer.add_document(doc!(text_field=>*text));
+            index_writer.commit().unwrap();
+            // the burst of commits is not checked for.
+            assert_eq!(reader.searcher().unwrap().num_docs(), 0);
+        }
+        assert!(reader.reload().unwrap());
+        assert_eq!(reader.searcher().unwrap().num_docs(), 3);
+    }
+}
@@ -0,0 +1,541 @@
+use DocId;
+use core::Segment;
+use core::SegmentId;
+use core::SegmentReader;
+use core::SegmentComponent;
+use core::SegmentMeta;
+use common::HasLen;
+use fastfield::U32FastFieldReader;
+use fastfield::delete::DeleteBitSet;
+use fst::Streamer;
+use postings::{DocSet, SegmentPostingsOption};
+use schema::{Field, Schema, Term};
+use store;
+use store::StoreReader;
+use core::Index;
+use datastruct::stacker::{Heap, TermHashFunction};
+use indexer::SegmentWriter;
+use indexer::operation::AddOperation;
+use indexer::index_writer::HEAP_SIZE_LIMIT;
+use std::path::PathBuf;
+use std::io::Write;
+use Directory;
+use Error;
+use Result;
+
+
+/// Inconsistency detected within a segment.
+///
+/// See `Index::check_integrity`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IntegrityProblem {
+    /// The segment could not be opened.
+    CannotOpen(String),
+    /// The footer of the doc store is corrupted.
+    InvalidStoreFooter,
+    /// The number of documents of the segment meta does
+    /// not match the one of the segment info.
+    MaxDocMismatch {
+        /// `max_doc` as recorded in the index meta.
+        meta_max_doc: DocId,
+        /// `max_doc` as recorded in the segment.
+        segment_max_doc: DocId,
+    },
+    /// The number of documents in the doc store does not match
+    /// the number of documents of the segment.
+    StoreDocCountMismatch {
+        /// `max_doc` of the segment.
+        max_doc: DocId,
+        /// Number of documents in the store.
+        store_max_doc: DocId,
+    },
+    /// A fast field (or field norm) is missing.
+    MissingFastField(Field),
+    /// A fast field (or field norm) holds less values than
+    /// there are documents in the segment.
+    FastFieldTooShort {
+        /// The fast field.
+        field: Field,
+        /// Number of values the fast field can hold.
+        capacity: usize,
+        /// `max_doc` of the segment.
+        max_doc: DocId,
+    },
+    /// The terms of the term dictionary are not sorted.
+    TermsNotSorted,
+    /// The postings of a term start after the end of the postings file.
+    PostingsOffsetOutOfBounds(Term),
+    /// The postings of a term refer to a document beyond `max_doc`.
+    PostingsDocOutOfBounds {
+        /// The term.
+        term: Term,
+        /// The document.
+        doc: DocId,
+        /// `max_doc` of the segment.
+        max_doc: DocId,
+    },
+    /// The postings of a term are not sorted by increasing doc id.
+    PostingsNotSorted(Term),
+    /// The delete bitset marks a document beyond `max_doc` as deleted.
+    DeleteBitSetOutOfBounds {
+        /// The first deleted document beyond `max_doc`.
+        doc: DocId,
+        /// `max_doc` of the segment.
+        max_doc: DocId,
+    },
+    /// The number of deleted documents of the segment meta does
+    /// not match the delete bitset.
+    DeletedDocCountMismatch {
+        /// Number of deleted documents as recorded in the index meta.
+        meta_num_deleted_docs: u32,
+        /// Number of documents marked in the delete bitset.
+        num_deleted_docs: u32,
+    },
+}
+
+/// Report of `Index::check_integrity`.
+///
+/// It lists all of the problems detected, segment by segment.
+#[derive(Debug, Default)]
+pub struct IntegrityReport {
+    problems: Vec<(SegmentId, IntegrityProblem)>,
+}
+
+impl IntegrityReport {
+
+    /// Returns true iff no problem was detected.
+    pub fn is_ok(&self,) -> bool {
+        self.problems.is_empty()
+    }
+
+    /// Returns the problems detected, with the id of their segment.
+    pub fn problems(&self,) -> &[(SegmentId, IntegrityProblem)] {
+        &self.problems
+    }
+
+    #[doc(hidden)]
+    pub fn add_problem(&mut self, segment_id: SegmentId, problem: IntegrityProblem) {
+        self.problems.push((segment_id, problem));
+    }
+}
+
+
+fn check_fast_field(field: Field, fast_field_reader: Option<U32FastFieldReader>, max_doc: DocId, problems: &mut Vec<IntegrityProblem>) {
+    match fast_field_reader {
+        Some(fast_field_reader) => {
+            let capacity = fast_field_reader.capacity();
+            if capacity < max_doc as usize {
+                problems.push(IntegrityProblem::FastFieldTooShort {
+                    field: field,
+                    capacity: capacity,
+                    max_doc: max_doc,
+                });
+            }
+        }
+        None => {
+            problems.push(IntegrityProblem::MissingFastField(field));
+        }
+    }
+}
+
+fn check_postings(segment_reader: &SegmentReader, problems: &mut Vec<IntegrityProblem>) {
+    let max_doc = segment_reader.max_doc();
+    let postings_data = segment_reader.postings_data().as_slice();
+    // deleted documents are checked too.
+    let no_deletes = DeleteBitSet::empty();
+    let mut previous_key: Option<Vec<u8>> = None;
+    let mut keys = segment_reader.term_infos().keys();
+    while let Some(key) = keys.next() {
+        if let Some(ref previous_key) = previous_key {
+            if previous_key.as_slice() >= key {
+                problems.push(IntegrityProblem::TermsNotSorted);
+                return;
+            }
+        }
+        previous_key = Some(key.to_vec());
+        let term = Term::from_bytes(key);
+        let term_info = match segment_reader.get_term_info(&term) {
+            Some(term_info) => term_info,
+            None => continue,
+        };
+        let offset = term_info.postings_offset as usize;
+        if offset > postings_data.len() {
+            problems.push(IntegrityProblem::PostingsOffsetOutOfBounds(term));
+            continue;
+        }
+        // the postings are decoded according to the options of their field,
+        // including the term frequencies and the payloads.
+        let mut postings = match segment_reader.read_postings_with_delete_bitset(&term, SegmentPostingsOption::NoFreq, &no_deletes) {
+            Some(postings) => postings,
+            None => continue,
+        };
+        let mut previous_doc: Option<DocId> = None;
+        while postings.advance() {
+            let doc = postings.doc();
+            if doc >= max_doc {
+                problems.push(IntegrityProblem::PostingsDocOutOfBounds {
+                    term: term.clone(),
+                    doc: doc,
+                    max_doc: max_doc,
+                });
+                break;
+            }
+            if previous_doc.map(|previous_doc| previous_doc >= doc).unwrap_or(false) {
+                problems.push(IntegrityProblem::PostingsNotSorted(term.clone()));
+                break;
+            }
+            previous_doc = Some(doc);
+        }
+    }
+}
+
+/// Checks the consistency of the files of a segment,
+/// and returns all of the problems detected.
+pub fn check_segment_integrity(segment: Segment) -> Vec<IntegrityProblem> {
+    let mut problems = Vec::new();
+    let segment_meta = segment.meta().clone();
+    let schema = segment.schema();
+    match segment.open_read(SegmentComponent::STORE) {
+        Ok(store_data) => {
+            if !store::has_valid_footer(&store_data) {
+                // opening the segment would panic.
+                problems.push(IntegrityProblem::InvalidStoreFooter);
+                return problems;
+            }
+        }
+        Err(e) => {
+            problems.push(IntegrityProblem::CannotOpen(format!("{:?}", e)));
+            return problems;
+        }
+    }
+    let segment_reader = match SegmentReader::open(segment) {
+        Ok(segment_reader) => segment_reader,
+        Err(e) => {
+            problems.push(IntegrityProblem::CannotOpen(format!("{:?}", e)));
+            return problems;
+        }
+    };
+    let max_doc = segment_reader.max_doc();
+    if segment_meta.max_doc() != max_doc {
+        problems.push(IntegrityProblem::MaxDocMismatch {
+            meta_max_doc: segment_meta.max_doc(),
+            segment_max_doc: max_doc,
+        });
+    }
+    let store_max_doc = segment_reader.get_store_reader().max_doc;
+    if store_max_doc != max_doc {
+        problems.push(IntegrityProblem::StoreDocCountMismatch {
+            max_doc: max_doc,
+            store_max_doc: store_max_doc,
+        });
+    }
+    for (field_id, field_entry) in schema.fields().iter().enumerate() {
+        let field = Field(field_id as u8);
+        if field_entry.is_u32_fast() {
+            check_fast_field(field, segment_reader.get_fast_field_reader(field), max_doc, &mut problems);
+        }
+        if field_entry.has_norms() {
+            check_fast_field(field, segment_reader.get_fieldnorms_reader(field), max_doc, &mut problems);
+        }
+    }
+    let delete_bitset = segment_reader.delete_bitset();
+    if segment_meta.has_deletes() {
+        if let Some(doc) = delete_bitset.delete_set().iter().find(|&doc| doc >= max_doc) {
+            problems.push(IntegrityProblem::DeleteBitSetOutOfBounds {
+                doc: doc,
+                max_doc: max_doc,
+            });
+        }
+        else if delete_bitset.len() as u32 != segment_meta.num_deleted_docs() {
+            problems.push(IntegrityProblem::DeletedDocCountMismatch {
+                meta_num_deleted_docs: segment_meta.num_deleted_docs(),
+                num_deleted_docs: delete_bitset.len() as u32,
+            });
+        }
+    }
+    check_postings(&segment_reader, &mut problems);
+    problems
+}
+
+
+/// Rebuilds the term dictionary of a segment, together with
+/// its postings and positions, from the stored documents, into
+/// the segment `rebuilt_segment_id`.
+///
+/// The postings do not record their terms, so the term dictionary cannot
+/// be derived from them. Instead, the stored documents of the segment are
+/// indexed again. The other files of the segment are copied as they are:
+/// the documents keep their doc ids, and the deleted documents are
+/// indexed as well, as the delete file remains valid.
+/// The files of `segment` are left untouched.
+///
+/// All of the indexed fields must be stored, and must not have payloads.
+/// The segment is expected to have been indexed without limit
+/// on the number of tokens per field.
+///
+/// Returns the meta of the rebuilt segment.
+///
+/// See `IndexWriter::rebuild_term_dict`.
+pub fn rebuild_term_dict(index: &Index, segment: &Segment, rebuilt_segment_id: SegmentId) -> Result<SegmentMeta> {
+    let schema = segment.schema();
+    for field_entry in schema.fields() {
+        if field_entry.is_indexed() && (!field_entry.is_stored() || field_entry.has_payloads()) {
+            return Err(Error::InvalidArgument(format!(
+                "The terms of field {:?} cannot be recovered from the stored documents.",
+                field_entry.name())));
+        }
+    }
+    let store_data = try!(segment.open_read(SegmentComponent::STORE));
+    if !store::has_valid_footer(&store_data) {
+        return Err(Error::CorruptedFile(segment.relative_path(SegmentComponent::STORE),
+                                        "Invalid doc store footer".into()));
+    }
+    let store_reader = StoreReader::open(store_data, segment.meta().store_compressor());
+    let reindexed_segment = index.new_segment();
+    let mut reindexed_meta = reindexed_segment.meta().clone();
+    reindexed_meta.set_has_term_vectors(schema.has_term_vectors());
+    let rebuilt_meta = segment.meta().with_segment_id(rebuilt_segment_id);
+    let rebuild_result = write_rebuilt_segment(index, &store_reader, &schema, reindexed_segment, segment.meta(), &rebuilt_meta);
+    // the other files of the reindexed segment are not needed.
+    let reindexed_files: Vec<PathBuf> = reindexed_meta.list_files().into_iter().collect();
+    index.clone().directory_mut().delete_managed_files(&reindexed_files);
+    rebuild_result.map(|_| rebuilt_meta)
+}
+
+/// Indexes the stored documents into `reindexed_segment`, and writes
+/// the segment `rebuilt_meta` from its term dictionary, postings and positions,
+/// and from the other files of the segment `segment_meta`.
+fn write_rebuilt_segment(index: &Index,
+                         store_reader: &StoreReader,
+                         schema: &Schema,
+                         reindexed_segment: Segment,
+                         segment_meta: &SegmentMeta,
+                         rebuilt_meta: &SegmentMeta) -> Result<()> {
+    let reindexed_meta = reindexed_segment.meta().clone();
+    let heap = Heap::with_capacity(HEAP_SIZE_LIMIT as usize);
+    {
+        let mut segment_writer = try!(SegmentWriter::for_segment(&heap, reindexed_segment, schema, TermHashFunction::default()));
+        for doc_id in 0..store_reader.max_doc {
+            let add_operation = AddOperation {
+                opstamp: 0u64,
+                document: try!(store_reader.get(doc_id)),
+            };
+            try!(segment_writer.add_document(&add_operation, schema));
+        }
+        try!(segment_writer.finalize());
+    }
+    let mut directory = index.directory().box_clone();
+    for component in SegmentComponent::iterator() {
+        let source_path = match *component {
+            SegmentComponent::TERMS | SegmentComponent::POSTINGS | SegmentComponent::POSITIONS => {
+                reindexed_meta.relative_path(*component)
+            }
+            _ => segment_meta.relative_path(*component),
+        };
+        if !directory.exists(&source_path) {
+            continue;
+        }
+        let data = try!(directory.open_read(&source_path));
+        let mut target_write = try!(directory.open_write(&rebuilt_meta.relative_path(*component)));
+        try!(target_write.write_all(data.as_slice()));
+        try!(target_write.flush());
+    }
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::IntegrityProblem;
+    use core::{Index, SegmentComponent};
+    use indexer::NoMergePolicy;
+    use schema::{self, Document, FieldValue, Term};
+    use Directory;
+    use collector::tests::TestCollector;
+    use postings::SegmentPostingsOption;
+    use query::TermQuery;
+    use DocAddress;
+    use Error;
+
+    #[test]
+    fn test_check_integrity() {
+        let mut schema_builder = schema::SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", schema::TEXT | schema::STORED);
+        let num_field = schema_builder.add_u32_field("num", schema::FAST);
+        // the fields without norms have no field norm to check.
+        let title_field = schema_builder.add_text_field("title", schema::TEXT.set_norms(false));
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.set_merge_policy(box NoMergePolicy);
+            index_writer.add_document(doc!(text_field=>"a b", num_field=>1u32, title_field=>"t"));
+            index_writer.add_document(doc!(text_field=>"b c", num_field=>2u32));
+            index_writer.commit().unwrap();
+            for i in 0..300u32 {
+                index_writer.add_document(doc!(text_field=>"a d", num_field=>i, title_field=>"t"));
+            }
+            index_writer.delete_term(Term::from_field_text(text_field, "c"));
+            index_writer.commit().unwrap();
+        }
+        let report = index.check_integrity().unwrap();
+        assert!(report.is_ok(), "{:?}", report);
+
+        // swapping the doc stores of the two segments.
+        let segments = index.searchable_segments().unwrap();
+        assert_eq!(segments.len(), 2);
+        let store_data: Vec<Vec<u8>> = segments
+            .iter()
+            .map(|segment| segment.open_read(SegmentComponent::STORE).unwrap().as_slice().to_vec())
+            .collect();
+        let mut directory = index.directory().box_clone();
+        directory.atomic_write(&segments[0].relative_path(SegmentComponent::STORE), &store_data[1]).unwrap();
+        directory.atomic_write(&segments[1].relative_path(SegmentComponent::STORE), &store_data[0]).unwrap();
+
+        let report = index.check_integrity().unwrap();
+        assert!(!report.is_ok());
+        let mut problems: Vec<_> = report.problems().to_vec();
+        problems.sort_by_key(|&(_, ref problem)| match *problem {
+            IntegrityProblem::StoreDocCountMismatch { max_doc, .. } => max_doc,
+            _ => 0,
+        });
+        let max_docs: Vec<u32> = segments.iter().map(|segment| segment.meta().max_doc()).collect();
+        let (small, large) = if max_docs[0] < max_docs[1] { (0, 1) } else { (1, 0) };
+        assert_eq!(problems, vec!(
+            (segments[small].id(), IntegrityProblem::StoreDocCountMismatch { max_doc: 2, store_max_doc: 300 }),
+            (segments[large].id(), IntegrityProblem::StoreDocCountMismatch { max_doc: 300, store_max_doc: 2 }),
+        ));
+    }
+
+    #[test]
+    fn test_check_integrity_payloads() {
+        let mut schema_builder = schema::SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", schema::TEXT);
+        let tag_field = schema_builder.add_text_field("tag", schema::STRING);
+        schema_builder.enable_payloads(text_field);
+        schema_builder.enable_payloads(tag_field);
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            for i in 0..1_000u32 {
+                let mut doc = doc!(text_field=>"a b a", tag_field=>"tag");
+                doc.set_payload(text_field, "a", i * 7);
+                doc.set_payload(tag_field, "tag", i);
+                index_writer.add_document(doc);
+            }
+            index_writer.commit().unwrap();
+        }
+        let report = index.check_integrity().unwrap();
+        assert!(report.is_ok(), "{:?}", report);
+    }
+
+    #[test]
+    fn test_check_integrity_json() {
+        let mut schema_builder = schema::SchemaBuilder::default();
+        let attributes_field = schema_builder.add_json_field("attributes", schema::JSON_INDEXED);
+        let schema = schema_builder.build();
+        let index = Index::create_in_ram(schema.clone());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            for i in 0..1_000u32 {
+                let doc_json = format!(r#"{{"attributes": {{"color": "red red", "size": {}}}}}"#, i % 3);
+                index_writer.add_document(schema.parse_document(&doc_json).unwrap());
+            }
+            index_writer.commit().unwrap();
+        }
+        let report = index.check_integrity().unwrap();
+        assert!(report.is_ok(), "{:?}", report);
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let term = Term::from_field_json_text(attributes_field, "color", "red");
+        let term_query = TermQuery::new(term, SegmentPostingsOption::NoFreq);
+        let mut test_collector = TestCollector::default();
+        searcher.search(&term_query, &mut test_collector).unwrap();
+        assert_eq!(test_collector.docs().len(), 1_000);
+    }
+
+    #[test]
+    fn test_rebuild_term_dict() {
+        let mut schema_builder = schema::SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", schema::TEXT | schema::STORED);
+        let tag_field = schema_builder.add_text_field("tag", schema::STRING | schema::STORED);
+        let num_field = schema_builder.add_u32_field("num", schema::U32_INDEXED | schema::U32_STORED);
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            for i in 0..300u32 {
+                index_writer.add_document(doc!(
+                    text_field=>format!("a{} b{} c a{}", i % 7, i % 13, i % 5),
+                    tag_field=>format!("tag{}", i % 3),
+                    num_field=>i % 11));
+            }
+            index_writer.delete_term(Term::from_field_text(tag_field, "tag1"));
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let terms = vec!(
+            Term::from_field_text(text_field, "a3"),
+            Term::from_field_text(text_field, "b12"),
+            Term::from_field_text(text_field, "c"),
+            Term::from_field_text(tag_field, "tag2"),
+            Term::from_field_u32(num_field, 4u32));
+        let lookup_terms = || {
+            let searcher = index.searcher();
+            terms.iter()
+                .map(|term| {
+                    let term_query = TermQuery::new(term.clone(), SegmentPostingsOption::NoFreq);
+                    let mut test_collector = TestCollector::default();
+                    searcher.search(&term_query, &mut test_collector).unwrap();
+                    test_collector.docs()
+                })
+                .collect::<Vec<_>>()
+        };
+        let positions = || {
+            index.searcher().term_positions(&DocAddress(0, 35), &Term::from_field_text(text_field, "a0"))
+        };
+        let expected_lookups = lookup_terms();
+        assert!(expected_lookups.iter().all(|docs| !docs.is_empty()));
+        assert_eq!(positions(), vec!(0, 3));
+
+        let segment = index.searchable_segments().unwrap().into_iter().next().unwrap();
+        let terms_path = segment.relative_path(SegmentComponent::TERMS);
+        let terms_data = segment.open_read(SegmentComponent::TERMS).unwrap().as_slice().to_vec();
+        index.directory().box_clone().atomic_write(&terms_path, &[1u8, 2u8, 3u8]).unwrap();
+        assert!(!index.check_integrity().unwrap().is_ok());
+        assert!(index.load_searchers().is_err());
+
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.rebuild_term_dict(segment.id()).unwrap();
+        }
+        let report = index.check_integrity().unwrap();
+        assert!(report.is_ok(), "{:?}", report);
+        // the rebuilt segment takes the place of the segment.
+        let rebuilt_segment = index.searchable_segments().unwrap().into_iter().next().unwrap();
+        assert!(rebuilt_segment.id() != segment.id());
+        assert_eq!(rebuilt_segment.meta().num_deleted_docs(), segment.meta().num_deleted_docs());
+        assert_eq!(rebuilt_segment.open_read(SegmentComponent::TERMS).unwrap().as_slice(), &terms_data[..]);
+        index.load_searchers().unwrap();
+        assert_eq!(lookup_terms(), expected_lookups);
+        assert_eq!(positions(), vec!(0, 3));
+    }
+
+    #[test]
+    fn test_rebuild_term_dict_requires_stored_fields() {
+        let mut schema_builder = schema::SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", schema::TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.add_document(doc!(text_field=>"a b"));
+            index_writer.commit().unwrap();
+        }
+        let segment_id = index.searchable_segment_ids().unwrap()[0];
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        match index_writer.rebuild_term_dict(segment_id) {
+            Err(Error::InvalidArgument(_)) => {}
+            _ => panic!("Expected InvalidArgument error"),
+        }
+        assert_eq!(index.searchable_segment_ids().unwrap(), vec!(segment_id));
+    }
+}
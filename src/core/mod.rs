@@ -8,18 +8,41 @@ mod index_meta;
 mod pool;
 mod segment_meta;
 mod term_iterator;
+mod ranked_stream;
+mod result_cache;
+mod segment_access_counters;
+mod integrity;
+mod collection_statistics;
+mod facet_drill_down;
+mod multi_index_searcher;
+mod budgeted_searcher;
+mod index_reader;
+mod backup;
+mod search_pool;
 
 pub use self::searcher::Searcher;
+pub use self::search_pool::SearchPool;
 pub use self::segment_component::SegmentComponent;
 pub use self::segment_id::SegmentId;
 pub use self::segment_reader::SegmentReader;
+pub use self::budgeted_searcher::BudgetedSearcher;
 pub use self::segment::Segment;
 pub use self::segment::SegmentInfo;
 pub use self::segment::SerializableSegment;
+pub use self::segment::{copy_segment_files, rename_segment_files};
 pub use self::index::Index;
+pub use self::index_reader::{IndexReader, ReloadPolicy};
+pub use self::backup::{BackupGuard, Snapshot};
 pub use self::segment_meta::SegmentMeta;
 pub use self::index_meta::IndexMeta;
 pub use self::term_iterator::TermIterator;
+pub use self::ranked_stream::RankedStream;
+pub use self::result_cache::ResultCache;
+pub use self::segment_access_counters::SegmentAccessCounters;
+pub use self::integrity::{IntegrityReport, IntegrityProblem, rebuild_term_dict};
+pub use self::collection_statistics::CollectionStatistics;
+pub use self::facet_drill_down::FacetDrillDown;
+pub use self::multi_index_searcher::{MultiIndexSearcher, IndexDocAddress};
 
 
 use std::path::PathBuf;
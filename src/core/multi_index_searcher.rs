@@ -0,0 +1,264 @@
+use Result;
+use core::Searcher;
+use core::CollectionStatistics;
+use collector::TopCollector;
+use query::Query;
+use schema::{Document, Term};
+use std::collections::HashMap;
+use std::cmp::Ordering;
+use DocAddress;
+use Score;
+
+
+/// `IndexDocAddress` identifies a document given
+/// a `MultiIndexSearcher`.
+///
+/// It consists in the ordinal of the index in the list of
+/// searchers of the `MultiIndexSearcher`, and the `DocAddress`
+/// of the document within this index.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct IndexDocAddress(pub usize, pub DocAddress);
+
+impl IndexDocAddress {
+    /// Returns the ordinal of the index of the document.
+    pub fn index_ord(&self) -> usize {
+        self.0
+    }
+
+    /// Returns the address of the document within its index.
+    pub fn doc_address(&self) -> DocAddress {
+        self.1
+    }
+}
+
+
+/// Searches several indexes as if they were a single one.
+///
+/// The query is run on each of the searchers, and their
+/// results are merged by score.
+///
+/// Scores are only comparable if the indexes share the same
+/// schema and similar statistics. 
+/// `.share_collection_statistics()` makes the searchers compute
+/// the idf of the terms from the total number of documents
+/// of all of the indexes, and from the number of documents
+/// containing the terms in all of the indexes.
+pub struct MultiIndexSearcher {
+    searchers: Vec<Searcher>,
+    shared_statistics: bool,
+}
+
+impl MultiIndexSearcher {
+
+    /// Creates a `MultiIndexSearcher`.
+    ///
+    /// The position of a searcher in `searchers` is the ordinal
+    /// used to identify its index in the `IndexDocAddress`es.
+    pub fn new(searchers: Vec<Searcher>) -> MultiIndexSearcher {
+        MultiIndexSearcher {
+            searchers: searchers,
+            shared_statistics: false,
+        }
+    }
+
+    /// Returns the searchers of the indexes.
+    pub fn searchers(&self) -> &[Searcher] {
+        &self.searchers
+    }
+
+    /// Returns the statistics of the union of the indexes.
+    pub fn collection_statistics(&self) -> CollectionStatistics {
+        let total_docs = self.searchers
+            .iter()
+            .map(|searcher| searcher.num_docs() as u64)
+            .sum();
+        CollectionStatistics::new(total_docs)
+    }
+
+    /// Returns the statistics of the union of the indexes,
+    /// including the document frequencies of the terms of `query`.
+    ///
+    /// The terms are those of `Query::query_terms`.
+    pub fn query_collection_statistics(&self, query: &Query) -> CollectionStatistics {
+        let mut terms = Vec::new();
+        query.query_terms(&mut terms);
+        let mut collection_statistics = self.collection_statistics();
+        let doc_freqs: HashMap<Term, u64> = terms
+            .into_iter()
+            .map(|term| {
+                let doc_freq = self.searchers
+                    .iter()
+                    .map(|searcher| searcher.doc_freq(&term) as u64)
+                    .sum();
+                (term, doc_freq)
+            })
+            .collect();
+        collection_statistics.doc_freqs = doc_freqs;
+        collection_statistics
+    }
+
+    /// Sets the statistics of the union of the indexes
+    /// on each of the searchers.
+    ///
+    /// The document frequencies of the terms of each query are
+    /// then summed over the indexes in `.search_top_docs(...)`.
+    /// See `.query_collection_statistics(...)`.
+    pub fn share_collection_statistics(&mut self) {
+        let collection_statistics = self.collection_statistics();
+        self.set_collection_statistics(collection_statistics);
+        self.shared_statistics = true;
+    }
+
+    fn set_collection_statistics(&mut self, collection_statistics: CollectionStatistics) {
+        for searcher in &mut self.searchers {
+            searcher.set_collection_statistics(Some(collection_statistics.clone()));
+        }
+    }
+
+    /// Fetches a document given its `IndexDocAddress`.
+    pub fn doc(&self, index_doc_address: &IndexDocAddress) -> Result<Document> {
+        let IndexDocAddress(index_ord, ref doc_address) = *index_doc_address;
+        self.searchers[index_ord].doc(doc_address)
+    }
+
+    /// Returns the `limit` best documents for `query` over all of the indexes,
+    /// by decreasing score.
+    ///
+    /// Documents with the same score are ordered by `IndexDocAddress`.
+    pub fn search_top_docs(&mut self, query: &Query, limit: usize) -> Result<Vec<(Score, IndexDocAddress)>> {
+        if self.shared_statistics {
+            let collection_statistics = self.query_collection_statistics(query);
+            self.set_collection_statistics(collection_statistics);
+        }
+        let mut score_docs: Vec<(Score, IndexDocAddress)> = Vec::new();
+        for (index_ord, searcher) in self.searchers.iter().enumerate() {
+            let mut top_collector = TopCollector::with_limit(limit);
+            try!(searcher.search(query, &mut top_collector));
+            score_docs.extend(top_collector
+                .score_docs()
+                .into_iter()
+                .map(|(score, doc_address)| (score, IndexDocAddress(index_ord, doc_address))));
+        }
+        score_docs.sort_by(|&(left_score, ref left_address), &(right_score, ref right_address)| {
+            right_score
+                .partial_cmp(&left_score)
+                .unwrap_or(Ordering::Equal)
+                .then_with(|| left_address.cmp(right_address))
+        });
+        score_docs.truncate(limit);
+        Ok(score_docs)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use core::Index;
+    use schema::{Document, FieldValue, Term, Schema, SchemaBuilder, Field, TEXT, STORED};
+    use query::TermQuery;
+    use postings::SegmentPostingsOption;
+
+    fn create_index(schema: &Schema, text_field: Field, texts: &[&str]) -> Index {
+        let index = Index::create_in_ram(schema.clone());
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        for text in texts {
+            index_writer.add_document(doc!(text_field=>*text));
+        }
+        index_writer.commit().unwrap();
+        index
+    }
+
+    #[test]
+    fn test_multi_index_searcher() {
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT | STORED);
+        let schema = schema_builder.build();
+        let index_0 = create_index(&schema, text_field, &["a", "a a a", "b", "a a"]);
+        let index_1 = create_index(&schema, text_field, &["b", "a a", "a a a a", "c"]);
+        let mut multi_index_searcher = MultiIndexSearcher::new(vec!(
+            index_0.open_searcher().unwrap(),
+            index_1.open_searcher().unwrap()));
+        let query = TermQuery::new(Term::from_field_text(text_field, "a"), SegmentPostingsOption::Freq);
+        let top_docs = multi_index_searcher.search_top_docs(&query, 4).unwrap();
+        assert_eq!(top_docs.len(), 4);
+        assert!(top_docs.iter().any(|&(_, index_doc_address)| index_doc_address.index_ord() == 0));
+        assert!(top_docs.iter().any(|&(_, index_doc_address)| index_doc_address.index_ord() == 1));
+        // the result is the best 4 of the 5 documents matching in either index.
+        let mut expected_docs: Vec<(Score, IndexDocAddress)> = Vec::new();
+        for (index_ord, searcher) in multi_index_searcher.searchers().iter().enumerate() {
+            let mut top_collector = TopCollector::with_limit(10);
+            searcher.search(&query, &mut top_collector).unwrap();
+            for (score, doc_address) in top_collector.score_docs() {
+                expected_docs.push((score, IndexDocAddress(index_ord, doc_address)));
+            }
+        }
+        assert_eq!(expected_docs.len(), 5);
+        expected_docs.sort_by(|left, right| right.0.partial_cmp(&left.0).unwrap());
+        for (top_doc, expected_doc) in top_docs.iter().zip(expected_docs.iter()) {
+            assert_eq!(top_doc.0, expected_doc.0);
+        }
+        for (top_doc, next_top_doc) in top_docs.iter().zip(top_docs[1..].iter()) {
+            assert!(top_doc.0 >= next_top_doc.0);
+        }
+        let IndexDocAddress(index_ord, doc_address) = top_docs[0].1;
+        let doc = multi_index_searcher.doc(&top_docs[0].1).unwrap();
+        let expected_doc = multi_index_searcher.searchers()[index_ord].doc(&doc_address).unwrap();
+        assert_eq!(doc.get_first(text_field).unwrap().text(), expected_doc.get_first(text_field).unwrap().text());
+    }
+
+    #[test]
+    fn test_multi_index_searcher_shared_statistics() {
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let schema = schema_builder.build();
+        let index_0 = create_index(&schema, text_field, &["a", "b", "b", "b", "b", "b"]);
+        let index_1 = create_index(&schema, text_field, &["a", "c"]);
+        let mut multi_index_searcher = MultiIndexSearcher::new(vec!(
+            index_0.open_searcher().unwrap(),
+            index_1.open_searcher().unwrap()));
+        let query = TermQuery::new(Term::from_field_text(text_field, "a"), SegmentPostingsOption::Freq);
+        {
+            let top_docs = multi_index_searcher.search_top_docs(&query, 2).unwrap();
+            assert_eq!(top_docs[0].1, IndexDocAddress(0, DocAddress(0, 0)));
+            assert!(top_docs[0].0 > top_docs[1].0);
+        }
+        assert_eq!(multi_index_searcher.collection_statistics(), CollectionStatistics::new(8));
+        multi_index_searcher.share_collection_statistics();
+        {
+            let top_docs = multi_index_searcher.search_top_docs(&query, 2).unwrap();
+            assert_eq!(top_docs[0].0, top_docs[1].0);
+            assert_eq!(top_docs[0].1, IndexDocAddress(0, DocAddress(0, 0)));
+            assert_eq!(top_docs[1].1, IndexDocAddress(1, DocAddress(0, 0)));
+        }
+    }
+
+    #[test]
+    fn test_multi_index_searcher_shared_doc_freqs() {
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let schema = schema_builder.build();
+        // "a" is common in the first index, and rare in the second one.
+        let index_0 = create_index(&schema, text_field, &["a", "a", "a", "b"]);
+        let index_1 = create_index(&schema, text_field, &["a", "b", "b", "b"]);
+        let mut multi_index_searcher = MultiIndexSearcher::new(vec!(
+            index_0.open_searcher().unwrap(),
+            index_1.open_searcher().unwrap()));
+        let query = TermQuery::new(Term::from_field_text(text_field, "a"), SegmentPostingsOption::Freq);
+        {
+            let top_docs = multi_index_searcher.search_top_docs(&query, 4).unwrap();
+            assert_eq!(top_docs[0].1, IndexDocAddress(1, DocAddress(0, 0)));
+            assert!(top_docs[0].0 > top_docs[1].0);
+        }
+        let a_term = Term::from_field_text(text_field, "a");
+        assert_eq!(multi_index_searcher.query_collection_statistics(&query).doc_freq(&a_term), Some(4));
+        multi_index_searcher.share_collection_statistics();
+        let top_docs = multi_index_searcher.search_top_docs(&query, 4).unwrap();
+        // both indexes use the idf of the union of the indexes.
+        assert!(top_docs.iter().all(|&(score, _)| score == top_docs[0].0));
+        for searcher in multi_index_searcher.searchers() {
+            assert_eq!(searcher.idf_doc_freq(&a_term), 4);
+        }
+    }
+}
@@ -12,8 +12,9 @@ pub struct GenerationItem<T> {
 
 pub struct Pool<T> {
     queue: Arc<MsQueue<GenerationItem<T>>>,
-    freshest_generation: AtomicUsize,
+    freshest_generation: Arc<AtomicUsize>,
     next_generation: AtomicUsize,
+    reset: Option<fn(&mut T)>,
 }
 
 impl<T> Pool<T> {
@@ -21,8 +22,21 @@ impl<T> Pool<T> {
     pub fn new() -> Pool<T> {
         Pool {
             queue: Arc::new(MsQueue::new()),
-            freshest_generation: AtomicUsize::default(),
+            freshest_generation: Arc::default(),
             next_generation: AtomicUsize::default(),
+            reset: None,
+        }
+    }
+
+    /// Creates a pool calling `reset` on the leased items
+    /// when they are released, before they are recycled.
+    ///
+    /// The changes made to an item through its lease
+    /// then do not leak to the next leases.
+    pub fn with_reset(reset: fn(&mut T)) -> Pool<T> {
+        Pool {
+            reset: Some(reset),
+            .. Pool::new()
         }
     }
 
@@ -36,6 +50,15 @@ impl<T> Pool<T> {
             self.queue.push(gen_item);
         }
         self.advertise_generation(next_generation);
+        // the obsolete items are dropped right away, rather than
+        // on the next calls to `acquire`, so that they release
+        // their resources.
+        while let Some(gen_item) = self.queue.try_pop() {
+            if gen_item.generation >= next_generation {
+                self.queue.push(gen_item);
+                break;
+            }
+        }
     }
     
     /// At the exit of this method,  
@@ -65,6 +88,8 @@ impl<T> Pool<T> {
                 return LeasedItem {
                     gen_item: Some(gen_item),
                     recycle_queue: self.queue.clone(),
+                    freshest_generation: self.freshest_generation.clone(),
+                    reset: self.reset,
                 }
             }
             else {
@@ -81,6 +106,8 @@ impl<T> Pool<T> {
 pub struct LeasedItem<T> {
     gen_item: Option<GenerationItem<T>>,
     recycle_queue: Arc<MsQueue<GenerationItem<T>>>,
+    freshest_generation: Arc<AtomicUsize>,
+    reset: Option<fn(&mut T)>,
 }
 
 impl<T> Deref for LeasedItem<T> {
@@ -100,8 +127,14 @@ impl<T> DerefMut for LeasedItem<T> {
 
 impl<T> Drop for LeasedItem<T> {
     fn drop(&mut self) {
-        let gen_item: GenerationItem<T> = mem::replace(&mut self.gen_item, None).expect("Unwrapping a leased item should never fail");
-        self.recycle_queue.push(gen_item);
+        let mut gen_item: GenerationItem<T> = mem::replace(&mut self.gen_item, None).expect("Unwrapping a leased item should never fail");
+        // obsolete items are not recycled.
+        if gen_item.generation >= self.freshest_generation.load(Ordering::Acquire) {
+            if let Some(reset) = self.reset {
+                reset(&mut gen_item.item);
+            }
+            self.recycle_queue.push(gen_item);
+        }
     }
 }
 
@@ -111,6 +144,7 @@ impl<T> Drop for LeasedItem<T> {
 mod tests {
 
     use std::iter;
+    use std::sync::Arc;
     use super::Pool;
 
     #[test]
@@ -127,4 +161,30 @@ mod tests {
             assert_eq!(*pool.acquire(), 11);
         }
     }
+
+    #[test]
+    fn test_pool_drops_obsolete_items() {
+        let item = Arc::new(10);
+        let pool = Pool::new();
+        pool.publish_new_generation(vec!(item.clone(), item.clone()));
+        let leased_item = pool.acquire();
+        assert_eq!(Arc::strong_count(&item), 3);
+        pool.publish_new_generation(vec!(Arc::new(11)));
+        assert_eq!(Arc::strong_count(&item), 2);
+        drop(leased_item);
+        assert_eq!(Arc::strong_count(&item), 1);
+        assert_eq!(**pool.acquire(), 11);
+    }
+
+    #[test]
+    fn test_pool_with_reset() {
+        let pool: Pool<Vec<usize>> = Pool::with_reset(Vec::clear);
+        pool.publish_new_generation(vec!(Vec::new()));
+        {
+            let mut leased_item = pool.acquire();
+            leased_item.push(1);
+            assert_eq!(*leased_item, vec!(1));
+        }
+        assert!(pool.acquire().is_empty());
+    }
 }
\ No newline at end of file
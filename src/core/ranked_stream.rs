@@ -0,0 +1,199 @@
+use Result;
+use DocId;
+use DocAddress;
+use Score;
+use SegmentLocalId;
+use core::SegmentReader;
+use query::Weight;
+use query::Scorer;
+use postings::DocSet;
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+
+/// Number of documents fetched by the first scan of a segment.
+/// It doubles on every subsequent scan.
+const INITIAL_BATCH_SIZE: usize = 64;
+
+
+#[derive(Clone, Copy)]
+struct ScoredDoc {
+    score: Score,
+    doc: DocId,
+}
+
+impl ScoredDoc {
+    /// Better documents come first : higher scores first,
+    /// and then lower doc ids first.
+    fn rank_cmp(&self, other: &ScoredDoc) -> Ordering {
+        other.score
+            .partial_cmp(&self.score)
+            .unwrap_or(Ordering::Equal)
+            .then(self.doc.cmp(&other.doc))
+    }
+}
+
+// The heap used to compute the top documents is a max-heap,
+// and its top is the worst of the documents kept so far.
+impl Ord for ScoredDoc {
+    fn cmp(&self, other: &ScoredDoc) -> Ordering {
+        self.rank_cmp(other)
+    }
+}
+
+impl PartialOrd for ScoredDoc {
+    fn partial_cmp(&self, other: &ScoredDoc) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for ScoredDoc {
+    fn eq(&self, other: &ScoredDoc) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for ScoredDoc {}
+
+
+/// Returns the `limit` best documents of the scorer, best first.
+fn top_docs(scorer: &mut Scorer, limit: usize) -> Vec<ScoredDoc> {
+    let mut heap: BinaryHeap<ScoredDoc> = BinaryHeap::with_capacity(limit + 1);
+    while scorer.advance() {
+        heap.push(ScoredDoc {
+            score: scorer.score(),
+            doc: scorer.doc(),
+        });
+        if heap.len() > limit {
+            heap.pop();
+        }
+    }
+    heap.into_sorted_vec()
+}
+
+
+/// Stream of the documents of a segment, in decreasing score order.
+///
+/// Rather than scoring and sorting all of the documents of
+/// the segment, the segment is scanned for its top `limit` documents.
+/// When these are consumed, the segment is scanned again
+/// with a doubled `limit`.
+struct SegmentRankedStream<'a> {
+    segment_ord: SegmentLocalId,
+    segment_reader: &'a SegmentReader,
+    batch: Vec<ScoredDoc>,
+    cursor: usize,
+    num_consumed: usize,
+    limit: usize,
+    exhausted: bool,
+}
+
+impl<'a> SegmentRankedStream<'a> {
+
+    fn new(segment_ord: SegmentLocalId, segment_reader: &'a SegmentReader) -> SegmentRankedStream<'a> {
+        SegmentRankedStream {
+            segment_ord: segment_ord,
+            segment_reader: segment_reader,
+            batch: Vec::new(),
+            cursor: 0,
+            num_consumed: 0,
+            limit: INITIAL_BATCH_SIZE / 2,
+            exhausted: false,
+        }
+    }
+
+    fn fetch(&mut self, weight: &Weight) -> Result<()> {
+        self.limit *= 2;
+        // if the scorer cannot be created, the stream ends here.
+        self.exhausted = true;
+        let mut scorer = try!(weight.scorer(self.segment_reader));
+        self.segment_reader.record_access();
+        let mut top_docs = top_docs(&mut scorer, self.limit);
+        self.exhausted = top_docs.len() < self.limit;
+        self.batch = top_docs.split_off(self.num_consumed);
+        self.cursor = 0;
+        Ok(())
+    }
+
+    /// Returns the current best document of the stream,
+    /// scanning the segment again if needed.
+    fn head(&mut self, weight: &Weight) -> Result<Option<ScoredDoc>> {
+        if self.cursor == self.batch.len() {
+            if self.exhausted {
+                return Ok(None);
+            }
+            try!(self.fetch(weight));
+        }
+        Ok(self.batch.get(self.cursor).cloned())
+    }
+
+    fn consume(&mut self) {
+        self.cursor += 1;
+        self.num_consumed += 1;
+    }
+}
+
+
+/// Iterator over the documents matching a query,
+/// in decreasing score order.
+///
+/// Documents with the same score are ordered by `DocAddress`.
+///
+/// The documents are computed lazily, making it possible
+/// to paginate deeply without keeping all of the
+/// documents in memory.
+///
+/// See `Searcher::ranked_stream`.
+pub struct RankedStream<'a> {
+    weight: Box<Weight>,
+    segment_streams: Vec<SegmentRankedStream<'a>>,
+}
+
+impl<'a> RankedStream<'a> {
+    #[doc(hidden)]
+    pub fn new(weight: Box<Weight>, segment_readers: &'a [SegmentReader]) -> Result<RankedStream<'a>> {
+        let mut segment_streams: Vec<SegmentRankedStream<'a>> = segment_readers
+            .iter()
+            .enumerate()
+            .map(|(segment_ord, segment_reader)| SegmentRankedStream::new(segment_ord as SegmentLocalId, segment_reader))
+            .collect();
+        for segment_stream in &mut segment_streams {
+            try!(segment_stream.fetch(&*weight));
+        }
+        Ok(RankedStream {
+            weight: weight,
+            segment_streams: segment_streams,
+        })
+    }
+}
+
+impl<'a> Iterator for RankedStream<'a> {
+    type Item = (Score, DocAddress);
+
+    fn next(&mut self) -> Option<(Score, DocAddress)> {
+        let mut best: Option<(usize, ScoredDoc)> = None;
+        for (ord, segment_stream) in self.segment_streams.iter_mut().enumerate() {
+            let head = match segment_stream.head(&*self.weight) {
+                Ok(head) => head,
+                Err(e) => {
+                    error!("Failed to score segment {}: {:?}", segment_stream.segment_ord, e);
+                    None
+                }
+            };
+            if let Some(scored_doc) = head {
+                let is_better = best
+                    .as_ref()
+                    .map(|&(_, ref best_doc)| scored_doc.score > best_doc.score)
+                    .unwrap_or(true);
+                if is_better {
+                    best = Some((ord, scored_doc));
+                }
+            }
+        }
+        best.map(|(ord, scored_doc)| {
+            let segment_stream = &mut self.segment_streams[ord];
+            segment_stream.consume();
+            (scored_doc.score, DocAddress(segment_stream.segment_ord, scored_doc.doc))
+        })
+    }
+}
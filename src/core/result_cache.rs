@@ -0,0 +1,58 @@
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use Score;
+use DocAddress;
+
+
+/// Cache of the top documents of queries, by query key.
+///
+/// The cache is shared by all of the `Searcher`s of a
+/// same generation. Reloading the searchers creates a new,
+/// empty, cache, so that the cached results are never stale.
+///
+/// Only the results of a `TopCollector` are cached, as
+/// they only depend on the query, the limit and the segments.
+///
+/// See `Searcher::search_top_docs_cached`.
+#[derive(Default)]
+pub struct ResultCache {
+    top_docs: RwLock<HashMap<(String, usize), Arc<Vec<(Score, DocAddress)>>>>,
+    num_hits: AtomicUsize,
+}
+
+impl ResultCache {
+
+    /// Returns the top documents cached for the given query key and limit.
+    pub fn get(&self, query_key: &str, limit: usize) -> Option<Arc<Vec<(Score, DocAddress)>>> {
+        let key = (query_key.to_string(), limit);
+        let top_docs = get!(self.top_docs.read().unwrap().get(&key).cloned());
+        self.num_hits.fetch_add(1, Ordering::SeqCst);
+        Some(top_docs)
+    }
+
+    /// Caches the top documents of the given query key and limit.
+    pub fn insert(&self, query_key: &str, limit: usize, top_docs: Vec<(Score, DocAddress)>) -> Arc<Vec<(Score, DocAddress)>> {
+        let top_docs = Arc::new(top_docs);
+        self.top_docs
+            .write()
+            .unwrap()
+            .insert((query_key.to_string(), limit), top_docs.clone());
+        top_docs
+    }
+
+    /// Returns the number of searches served from the cache.
+    pub fn num_hits(&self) -> usize {
+        self.num_hits.load(Ordering::SeqCst)
+    }
+
+    /// Returns the number of results in the cache.
+    pub fn len(&self) -> usize {
+        self.top_docs.read().unwrap().len()
+    }
+
+    /// Returns true iff the cache is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
@@ -0,0 +1,127 @@
+use futures::Future;
+use futures_cpupool::CpuPool;
+use std::panic::{self, AssertUnwindSafe};
+
+
+/// Pool of threads the searchers collect the segments with.
+///
+/// The searchers loaded by an `Index` share the same pool,
+/// so that searching does not spawn any thread.
+///
+/// See `Index::set_num_search_threads`.
+#[derive(Clone)]
+pub struct SearchPool {
+    pool: CpuPool,
+    num_threads: usize,
+}
+
+// a task borrowed by `SearchPool::scoped`, with its type erased
+// so that it can be sent to the threads of the pool.
+struct ErasedTask {
+    task: *const (),
+    run: unsafe fn(*const ()),
+}
+
+// the task is `Sync`, and outlives its runs.
+unsafe impl Send for ErasedTask {}
+
+unsafe fn run_task<F: Fn()>(task: *const ()) {
+    (*(task as *const F))()
+}
+
+impl SearchPool {
+
+    /// Creates a pool of `num_threads` threads.
+    ///
+    /// # Panics
+    /// Panics if `num_threads` is 0.
+    pub fn new(num_threads: usize) -> SearchPool {
+        assert!(num_threads > 0, "At least one thread is required to search.");
+        SearchPool {
+            pool: CpuPool::new(num_threads),
+            num_threads: num_threads,
+        }
+    }
+
+    /// Returns the number of threads of the pool.
+    pub fn num_threads(&self,) -> usize {
+        self.num_threads
+    }
+
+    /// Runs `task` `num_tasks` times on the threads of the pool,
+    /// and waits for all of the runs to end.
+    ///
+    /// Unlike the futures spawned on a `CpuPool`, `task` may
+    /// borrow the data of the caller.
+    ///
+    /// # Panics
+    /// If a run of `task` panics, the panic is propagated
+    /// once all of the runs have ended.
+    pub fn scoped<F: Fn() + Sync>(&self, num_tasks: usize, task: &F) {
+        let runs: Vec<_> = (0..num_tasks)
+            .map(|_| {
+                let erased_task = ErasedTask {
+                    task: task as *const F as *const (),
+                    run: run_task::<F>,
+                };
+                self.pool.spawn_fn(move || -> Result<(), ()> {
+                    unsafe { (erased_task.run)(erased_task.task) };
+                    Ok(())
+                })
+            })
+            .collect();
+        // `task` must outlive all of its runs,
+        // including when one of them panicked.
+        let mut panic_payload = None;
+        for run in runs {
+            if let Err(payload) = panic::catch_unwind(AssertUnwindSafe(|| run.wait())) {
+                panic_payload = Some(payload);
+            }
+        }
+        if let Some(payload) = panic_payload {
+            panic::resume_unwind(payload);
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::SearchPool;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn test_search_pool_scoped() {
+        let search_pool = SearchPool::new(3);
+        assert_eq!(search_pool.num_threads(), 3);
+        let vals: Vec<usize> = (0..100).collect();
+        let next_val = AtomicUsize::new(0);
+        let sum = AtomicUsize::new(0);
+        for _ in 0..2 {
+            next_val.store(0, Ordering::SeqCst);
+            search_pool.scoped(3, &|| {
+                loop {
+                    let i = next_val.fetch_add(1, Ordering::SeqCst);
+                    if i >= vals.len() {
+                        break;
+                    }
+                    sum.fetch_add(vals[i], Ordering::SeqCst);
+                }
+            });
+        }
+        assert_eq!(sum.load(Ordering::SeqCst), 2 * 4_950);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_search_pool_scoped_panic() {
+        let search_pool = SearchPool::new(2);
+        let num_runs = AtomicUsize::new(0);
+        search_pool.scoped(4, &|| {
+            if num_runs.fetch_add(1, Ordering::SeqCst) == 0 {
+                panic!("first run");
+            }
+        });
+    }
+}
@@ -1,14 +1,31 @@
 use Result;
 use core::SegmentReader;
 use schema::Document;
-use collector::Collector;
+use collector::{Collector, FruitCollector, SegmentCollector};
+use collector::ExcludingCollector;
+use std::collections::HashSet;
 use common::TimerTree;
-use query::Query;
+use query::{Query, QueryCost, Explanation, Weight};
 use DocId;
 use DocAddress;
+use SegmentLocalId;
+use postings::{DocSet, IntersectionDocSet, SegmentPostingsOption};
 use schema::Term;
 use core::TermIterator;
+use core::RankedStream;
 use std::fmt;
+use std::cmp;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use core::SearchPool;
+use schema::{Field, Similarity};
+use fastfield::FastFieldColumnCache;
+use futures_cpupool::CpuPool;
+use collector::TopCollector;
+use core::ResultCache;
+use core::CollectionStatistics;
+use Score;
+use Error;
 
 
 /// Holds a list of `SegmentReader`s ready for search.
@@ -18,20 +35,84 @@ use std::fmt;
 /// 
 pub struct Searcher {
     segment_readers: Vec<SegmentReader>,
+    fast_field_cache: Arc<FastFieldColumnCache>,
+    result_cache: Arc<ResultCache>,
+    collection_statistics: Option<CollectionStatistics>,
+    similarity: Option<Similarity>,
+    num_search_threads: usize,
+    search_pool: Option<SearchPool>,
 }
 
 
 impl Searcher {
 
+    /// Creates a `Searcher` sharing the given cache of fast field columns
+    /// and cache of results.
+    ///
+    /// All of the searchers of a same generation share the same caches.
+    #[doc(hidden)]
+    pub fn new(segment_readers: Vec<SegmentReader>,
+               fast_field_cache: Arc<FastFieldColumnCache>,
+               result_cache: Arc<ResultCache>) -> Searcher {
+        Searcher {
+            segment_readers: segment_readers,
+            fast_field_cache: fast_field_cache,
+            result_cache: result_cache,
+            collection_statistics: None,
+            similarity: None,
+            num_search_threads: 1,
+            search_pool: None,
+        }
+    }
+
     /// Fetches a document from tantivy's store given a `DocAddress`.
     ///
     /// The searcher uses the segment ordinal to route the
     /// the request to the right `Segment`. 
     pub fn doc(&self, doc_address: &DocAddress) -> Result<Document> {
         let DocAddress(segment_local_id, doc_id) = *doc_address;
-        let segment_reader = &self.segment_readers[segment_local_id as usize];
+        let segment_reader = self.segment_reader(segment_local_id as usize);
         segment_reader.doc(doc_id)
     }
+
+    /// Fetches a document from tantivy's store given a `DocAddress`,
+    /// keeping only the values of the given fields.
+    ///
+    /// Only the values of these fields are decoded. Decoding stops
+    /// once all of them have been read, so that fetching the fields
+    /// placed first by the stored-field order of the schema
+    /// (see `SchemaBuilder::set_stored_fields_order`) is even cheaper.
+    pub fn doc_fields(&self, doc_address: &DocAddress, fields: &[Field]) -> Result<Document> {
+        let DocAddress(segment_local_id, doc_id) = *doc_address;
+        let segment_reader = self.segment_reader(segment_local_id as usize);
+        segment_reader.doc_fields(doc_id, fields)
+    }
+
+    /// Returns true iff `doc_address` points to a document
+    /// of this searcher that has not been deleted.
+    ///
+    /// Addresses are only meaningful for the searcher they
+    /// were obtained from. Unlike `doc`, this method does not
+    /// panic if the address is out of bounds.
+    pub fn exists(&self, doc_address: &DocAddress) -> bool {
+        let DocAddress(segment_local_id, doc_id) = *doc_address;
+        if segment_local_id as usize >= self.num_segments() {
+            return false;
+        }
+        let segment_reader = self.segment_reader(segment_local_id as usize);
+        doc_id < segment_reader.max_doc() && !segment_reader.is_deleted(doc_id)
+    }
+    
+    /// Returns the positions of `term` in the document
+    /// at `doc_address`.
+    ///
+    /// The result is empty if the document does not contain
+    /// the term, or if the term's field does not index positions.
+    pub fn term_positions(&self, doc_address: &DocAddress, term: &Term) -> Vec<u32> {
+        let DocAddress(segment_local_id, doc_id) = *doc_address;
+        let segment_reader = self.segment_reader(segment_local_id as usize);
+        segment_reader.term_positions(doc_id, term)
+    }
     
     /// Returns the overall number of documents in the index.
     pub fn num_docs(&self,) -> DocId {
@@ -44,12 +125,129 @@ impl Searcher {
     /// Return the overall number of documents containing
     /// the given term. 
     pub fn doc_freq(&self, term: &Term) -> u32 {
-        self.segment_readers
-            .iter()
-            .map(|segment_reader| segment_reader.doc_freq(term))
+        (0..self.num_segments())
+            .map(|segment_ord| self.segment_reader(segment_ord).doc_freq(term))
             .fold(0u32, |acc, val| acc + val)
     }
 
+    /// Returns an `Error::SchemaError` if `field` is not indexed,
+    /// in which case it cannot be searched.
+    pub fn check_indexed(&self, field: Field) -> Result<()> {
+        if self.num_segments() == 0 {
+            return Ok(());
+        }
+        let segment_reader = self.segment_reader(0);
+        let field_entry = segment_reader.schema().get_field_entry(field);
+        if field_entry.is_indexed() {
+            Ok(())
+        } else {
+            Err(Error::SchemaError(format!("Field <{}> is not indexed, it cannot be searched.", field_entry.name())))
+        }
+    }
+
+    /// Returns the number of documents containing both
+    /// `term_a` and `term_b`.
+    ///
+    /// The postings of the two terms are intersected in each segment.
+    /// Deleted documents are not counted.
+    pub fn cooccurrence(&self, term_a: &Term, term_b: &Term) -> u64 {
+        let mut num_docs = 0u64;
+        for segment_ord in 0..self.num_segments() {
+            let segment_reader = self.segment_reader(segment_ord);
+            let postings = (segment_reader.read_postings(term_a, SegmentPostingsOption::NoFreq),
+                            segment_reader.read_postings(term_b, SegmentPostingsOption::NoFreq));
+            let mut intersection = match postings {
+                (Some(postings_a), Some(postings_b)) => IntersectionDocSet::from(vec!(postings_a, postings_b)),
+                _ => {
+                    continue;
+                }
+            };
+            while intersection.advance() {
+                num_docs += 1;
+            }
+        }
+        num_docs
+    }
+
+    /// Returns the number of documents matching the query.
+    ///
+    /// This is cheaper than collecting the documents with a
+    /// `CountCollector`, as the query is run through its
+    /// `.matching_weight(...)`: the statistics used to score the
+    /// documents, such as the idf of the terms, are not computed.
+    /// For a `TermQuery`, the document frequency of the term is
+    /// used directly in the segments without deleted documents.
+    ///
+    /// Deleted documents are not counted.
+    pub fn count(&self, query: &Query) -> Result<usize> {
+        let weight = try!(query.matching_weight(self));
+        let mut count = 0usize;
+        for segment_ord in 0..self.num_segments() {
+            let segment_reader = self.segment_reader(segment_ord);
+            count += try!(weight.count(&segment_reader)) as usize;
+            segment_reader.record_access();
+        }
+        Ok(count)
+    }
+
+    /// Returns the terms that are in the term dictionary
+    /// of at least one segment, in their original order.
+    ///
+    /// This is cheaper than computing their document frequency,
+    /// as the term infos are not read.
+    pub fn filter_existing_terms(&self, terms: Vec<Term>) -> Vec<Term> {
+        terms
+            .into_iter()
+            .filter(|term| {
+                (0..self.num_segments())
+                    .any(|segment_ord| self.segment_reader(segment_ord).contains_term(term))
+            })
+            .collect()
+    }
+
+    /// Sets the statistics of the collection used to compute
+    /// the idf of the terms, in place of the statistics
+    /// of this searcher. `None` restores the local statistics.
+    ///
+    /// The document frequencies of the terms that are not
+    /// part of the statistics are still those of this searcher.
+    ///
+    /// The statistics of a searcher leased by an `Index`
+    /// are reset when the lease is released.
+    pub fn set_collection_statistics(&mut self, collection_statistics: Option<CollectionStatistics>) {
+        self.collection_statistics = collection_statistics;
+    }
+
+    /// Returns the statistics of the collection, if they were set.
+    pub fn collection_statistics(&self) -> Option<&CollectionStatistics> {
+        self.collection_statistics.as_ref()
+    }
+
+    /// Returns the number of documents used to compute
+    /// the idf of the terms of `field`.
+    ///
+    /// This is the overall number of documents of the index, unless
+    /// the statistics of the collection were set.
+    pub fn idf_num_docs(&self, field: Field) -> u64 {
+        match self.collection_statistics {
+            Some(ref collection_statistics) => collection_statistics.num_docs(field),
+            None => self.num_docs() as u64,
+        }
+    }
+
+    /// Returns the number of documents containing `term`
+    /// used to compute its idf.
+    ///
+    /// This is `.doc_freq(term)`, unless the statistics of
+    /// the collection were set and include the term.
+    pub fn idf_doc_freq(&self, term: &Term) -> u32 {
+        self.collection_statistics
+            .as_ref()
+            .and_then(|collection_statistics| collection_statistics.doc_freq(term))
+            .map(|doc_freq| cmp::min(doc_freq, u32::max_value() as u64) as u32)
+            .unwrap_or_else(|| self.doc_freq(term))
+    }
+
     /// Returns a Stream over all of the sorted unique terms of
     /// the searcher.
     ///
@@ -62,27 +260,330 @@ impl Searcher {
         TermIterator::from(self.segment_readers())
     }
 
+    /// Sets the similarity used to score the terms of all of
+    /// the fields, in place of the similarities defined in the schema.
+    /// `None` restores the similarities of the schema.
+    ///
+    /// Like the statistics of the collection, the similarity
+    /// of a searcher leased by an `Index` is reset when
+    /// the lease is released.
+    pub fn set_similarity(&mut self, similarity: Option<Similarity>) {
+        self.similarity = similarity;
+    }
+
+    /// Sets the number of threads `.search_fruit(...)` collects
+    /// the segments with. With a single thread, the segments are
+    /// collected one after the other by the calling thread.
+    ///
+    /// The threads are those of the pool of the searcher, which
+    /// is replaced by a new pool if it has fewer threads.
+    /// The searchers loaded by an `Index` share its pool.
+    ///
+    /// See `Index::set_num_search_threads`.
+    ///
+    /// # Panics
+    /// Panics if `num_search_threads` is 0.
+    pub fn set_num_search_threads(&mut self, num_search_threads: usize) {
+        assert!(num_search_threads > 0, "At least one thread is required to search.");
+        let has_enough_threads = self.search_pool
+            .as_ref()
+            .map(|search_pool| search_pool.num_threads() >= num_search_threads)
+            .unwrap_or(false);
+        if num_search_threads > 1 && !has_enough_threads {
+            self.search_pool = Some(SearchPool::new(num_search_threads));
+        }
+        self.num_search_threads = num_search_threads;
+    }
+
+    /// Sets the pool of threads `.search_fruit(...)`
+    /// collects the segments with.
+    #[doc(hidden)]
+    pub fn set_search_pool(&mut self, search_pool: SearchPool) {
+        self.search_pool = Some(search_pool);
+    }
+
+    /// Returns the number of threads `.search_fruit(...)`
+    /// collects the segments with.
+    pub fn num_search_threads(&self) -> usize {
+        self.num_search_threads
+    }
+
+    /// Returns the similarity used to score the terms of `field`.
+    ///
+    /// This is the similarity set with `.set_similarity(...)` if any,
+    /// and the similarity of the field in the schema otherwise.
+    pub fn similarity(&self, field: Field) -> Similarity {
+        if let Some(similarity) = self.similarity {
+            return similarity;
+        }
+        if self.num_segments() == 0 {
+            return Similarity::default();
+        }
+        self.segment_reader(0).schema().get_field_entry(field).similarity()
+    }
+
+    /// Returns the average field norm of `field`, that is
+    /// the average number of tokens in the field.
+    ///
+    /// Deleted documents are included.
+    pub fn average_fieldnorm(&self, field: Field) -> f32 {
+        let mut num_docs = 0u64;
+        let mut total_fieldnorm = 0u64;
+        for segment_ord in 0..self.num_segments() {
+            let segment_reader = self.segment_reader(segment_ord);
+            if let Some(fieldnorm_reader) = segment_reader.get_fieldnorms_reader(field) {
+                let mut fieldnorms = vec!(0u32; segment_reader.max_doc() as usize);
+                fieldnorm_reader.get_range(0, &mut fieldnorms);
+                total_fieldnorm += fieldnorms.iter().map(|&fieldnorm| fieldnorm as u64).sum::<u64>();
+                num_docs += segment_reader.max_doc() as u64;
+            }
+        }
+        if num_docs == 0 {
+            0f32
+        }
+        else {
+            total_fieldnorm as f32 / num_docs as f32
+        }
+    }
+
     /// Return the list of segment readers
     pub fn segment_readers(&self,) -> &[SegmentReader] {
         &self.segment_readers
     }
     
+    /// Consumes the searcher and returns its segment readers.
+    pub fn into_segment_readers(self) -> Vec<SegmentReader> {
+        self.segment_readers
+    }
+
+    /// Returns the number of segments of the searcher.
+    pub fn num_segments(&self,) -> usize {
+        self.segment_readers.len()
+    }
+
     /// Returns the segment_reader associated with the given segment_ordinal
     pub fn segment_reader(&self, segment_ord: usize) -> &SegmentReader {
         &self.segment_readers[segment_ord]
     }
        
+    /// Returns the cache of the fast field columns loaded
+    /// by this searcher's generation.
+    pub fn fast_field_cache(&self) -> &FastFieldColumnCache {
+        &self.fast_field_cache
+    }
+
+    /// Returns the fully decoded column of the fast field `field`
+    /// for the segment `segment_ord`.
+    ///
+    /// The column is loaded on the first call, and then 
+    /// reused until the searchers are reloaded.
+    ///
+    /// Returns `None` if the field is not a u32 fast field.
+    pub fn fast_field_column(&self, segment_ord: usize, field: Field) -> Option<Arc<Vec<u32>>> {
+        self.fast_field_cache.get_or_load(segment_ord, &self.segment_reader(segment_ord), field)
+    }
+
+    /// Loads the columns of the fast fields `fields` of all
+    /// of the segments in the cache of fast field columns,
+    /// so that the first queries using them do not pay for their loading.
+    ///
+    /// The columns of a segment are loaded concurrently on
+    /// the threads of `pool`. See `SegmentReader::warm_fast_fields_parallel`.
+    /// The fields that are not u32 fast fields are ignored.
+    pub fn warm_fast_fields(&self, fields: &[Field], pool: &CpuPool) {
+        for segment_ord in 0..self.num_segments() {
+            let columns = self.segment_reader(segment_ord).warm_fast_fields_parallel(fields, pool);
+            for (&field, column_opt) in fields.iter().zip(columns) {
+                if let Some(column) = column_opt {
+                    self.fast_field_cache.insert(segment_ord, field, column);
+                }
+            }
+        }
+    }
+
+    /// Sorts documents by increasing value of the fast field `field`.
+    ///
+    /// The sort is stable : documents with the same value 
+    /// keep their relative order.
+    ///
+    /// # Errors
+    /// If the field is not a u32 fast field, returns `Error::SchemaError`.
+    pub fn sort_by_fast_field(&self, doc_addresses: &mut [DocAddress], field: Field) -> Result<()> {
+        let mut columns: Vec<Option<Arc<Vec<u32>>>> = vec![None; self.num_segments()];
+        for doc_address in doc_addresses.iter() {
+            let segment_ord = doc_address.segment_ord() as usize;
+            if columns[segment_ord].is_none() {
+                let column = try!(self.fast_field_column(segment_ord, field)
+                    .ok_or_else(|| Error::SchemaError(format!("Field {:?} is not a u32 fast field.", field))));
+                columns[segment_ord] = Some(column);
+            }
+        }
+        doc_addresses.sort_by_key(|doc_address| {
+            let DocAddress(segment_ord, doc_id) = *doc_address;
+            columns[segment_ord as usize].as_ref().unwrap()[doc_id as usize]
+        });
+        Ok(())
+    }
+
+    /// Returns an iterator over the documents matching the query,
+    /// in decreasing score order.
+    ///
+    /// Unlike collecting the documents with a `TopCollector`,
+    /// the documents are computed lazily, as the iterator is consumed.
+    /// This is useful for deep pagination.
+    pub fn ranked_stream<'a>(&'a self, query: &Query) -> Result<RankedStream<'a>> {
+        let weight = try!(query.weight(self));
+        RankedStream::new(weight, self.segment_readers())
+    }
+
+    /// Estimates the cost of executing a query, without executing it.
+    ///
+    /// The estimate only relies on the term dictionaries, and makes it
+    /// possible to reject or queue the expensive queries.
+    /// See `QueryCost`.
+    pub fn estimate_cost(&self, query: &Query) -> QueryCost {
+        query.estimate_cost(self)
+    }
+
+    /// Explains the score of a document for a query.
+    ///
+    /// The value of the returned `Explanation` is the score
+    /// the collectors are given for this document, and its details
+    /// show how each subquery contributes to it.
+    ///
+    /// Returns an `InvalidArgument` error if the document
+    /// does not match the query.
+    pub fn explain(&self, query: &Query, doc_address: &DocAddress) -> Result<Explanation> {
+        let DocAddress(segment_ord, doc) = *doc_address;
+        let weight = try!(query.weight(self));
+        let segment_reader = self.segment_reader(segment_ord as usize);
+        match try!(weight.explain(&segment_reader, doc)) {
+            Some(explanation) => Ok(explanation),
+            None => Err(Error::InvalidArgument(format!("{:?} does not match {:?}", doc_address, query))),
+        }
+    }
+
     /// Runs a query on the segment readers wrapped by the searcher
     pub fn search<C: Collector>(&self, query: &Query, collector: &mut C) -> Result<TimerTree> {
         query.search(self, collector)
     }
+
+    /// Runs a query on the segment readers wrapped by the searcher,
+    /// using a `FruitCollector`.
+    ///
+    /// Each segment is collected by its own `SegmentCollector`, and the
+    /// resulting fruits are merged, in the order of the segments.
+    ///
+    /// If the searcher has several search threads, (see
+    /// `.set_num_search_threads(...)`) the segments are
+    /// distributed among the threads of its pool, and
+    /// collected in parallel.
+    pub fn search_fruit<C>(&self, query: &Query, collector: &C) -> Result<C::Fruit>
+        where C: FruitCollector + Sync, C::Fruit: Send {
+        let weight = try!(query.weight(self));
+        let search_pool = match self.search_pool {
+            Some(ref search_pool) if self.num_search_threads > 1 && self.num_segments() > 1 => search_pool,
+            _ => {
+                let mut fruits = Vec::with_capacity(self.num_segments());
+                for segment_ord in 0..self.num_segments() {
+                    let segment_reader = self.segment_reader(segment_ord);
+                    fruits.push(try!(collect_segment(&*weight, collector, segment_ord, &segment_reader)));
+                }
+                return Ok(collector.merge_fruits(fruits));
+            }
+        };
+        let segment_readers = self.segment_readers();
+        let num_threads = cmp::min(self.num_search_threads, segment_readers.len());
+        let next_segment_ord = AtomicUsize::new(0);
+        let segment_fruits: Mutex<Vec<(usize, Result<C::Fruit>)>> = Mutex::new(Vec::with_capacity(segment_readers.len()));
+        {
+            let weight = &*weight;
+            search_pool.scoped(num_threads, &|| {
+                loop {
+                    let segment_ord = next_segment_ord.fetch_add(1, Ordering::SeqCst);
+                    if segment_ord >= segment_readers.len() {
+                        break;
+                    }
+                    let fruit = collect_segment(weight, collector, segment_ord, &segment_readers[segment_ord]);
+                    segment_fruits.lock().unwrap().push((segment_ord, fruit));
+                }
+            });
+        }
+        let mut segment_fruits = segment_fruits.into_inner().unwrap();
+        segment_fruits.sort_by_key(|&(segment_ord, _)| segment_ord);
+        let mut fruits: Vec<C::Fruit> = Vec::with_capacity(segment_fruits.len());
+        for (_, fruit) in segment_fruits {
+            fruits.push(try!(fruit));
+        }
+        Ok(collector.merge_fruits(fruits))
+    }
+
+    /// Runs a query on the segment readers wrapped by the searcher,
+    /// without pushing the documents of `excluded` to the collector.
+    ///
+    /// The scores of the other documents are not affected.
+    pub fn search_excluding<C: Collector>(&self, query: &Query, collector: &mut C, excluded: &HashSet<DocAddress>) -> Result<TimerTree> {
+        let mut excluding_collector = ExcludingCollector::wrap(collector, excluded);
+        query.search(self, &mut excluding_collector)
+    }
+
+    /// Runs a query on the segments committed with `label` only,
+    /// ignoring the other segments of the searcher.
+    ///
+    /// The documents keep their address within the searcher, and
+    /// their scores are computed with the statistics of all of the segments.
+    ///
+    /// See `IndexWriter::commit_with_label`.
+    pub fn search_label<C: Collector>(&self, query: &Query, collector: &mut C, label: &str) -> Result<TimerTree> {
+        query.search_segments(self, collector, &|segment_reader| segment_reader.label() == Some(label))
+    }
+
+    /// Returns the cache of the results of this searcher's generation.
+    pub fn result_cache(&self) -> &ResultCache {
+        &self.result_cache
+    }
+
+    /// Returns the `limit` best documents for `query`, as
+    /// `TopCollector::score_docs` would.
+    ///
+    /// `query_key` identifies the query. The results are cached under
+    /// this key until the searchers are reloaded, and two different
+    /// queries must not share a key.
+    ///
+    /// The cache is bypassed if the statistics of the
+    /// collection or the similarity were set, as they change the scores.
+    pub fn search_top_docs_cached(&self, query_key: &str, query: &Query, limit: usize) -> Result<Arc<Vec<(Score, DocAddress)>>> {
+        let has_custom_scoring = self.collection_statistics.is_some() || self.similarity.is_some();
+        if !has_custom_scoring {
+            if let Some(top_docs) = self.result_cache.get(query_key, limit) {
+                return Ok(top_docs);
+            }
+        }
+        let mut top_collector = TopCollector::with_limit(limit);
+        try!(query.search(self, &mut top_collector));
+        if has_custom_scoring {
+            return Ok(Arc::new(top_collector.score_docs()));
+        }
+        Ok(self.result_cache.insert(query_key, limit, top_collector.score_docs()))
+    }
+}
+
+fn collect_segment<C: FruitCollector>(weight: &Weight,
+                                      collector: &C,
+                                      segment_ord: usize,
+                                      segment_reader: &SegmentReader) -> Result<C::Fruit> {
+    let mut segment_collector = try!(collector.for_segment(segment_ord as SegmentLocalId, segment_reader));
+    let mut scorer = try!(weight.scorer(segment_reader));
+    segment_reader.record_access();
+    while scorer.advance() {
+        segment_collector.collect(scorer.doc(), scorer.score());
+    }
+    Ok(segment_collector.harvest())
 }
 
 impl From<Vec<SegmentReader>> for Searcher {
     fn from(segment_readers: Vec<SegmentReader>) -> Searcher {
-        Searcher {
-            segment_readers: segment_readers,
-        }
+        Searcher::new(segment_readers, Arc::default(), Arc::default())
     }
 }
 
@@ -94,4 +595,726 @@ impl fmt::Debug for Searcher {
             .collect::<Vec<_>>();
         write!(f, "Searcher({:?})", segment_ids)
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+
+    use core::Index;
+    use schema::{Document, Field, FieldValue, Term, SchemaBuilder, TEXT, STRING, STORED, FAST};
+    use collector::{TopCollector, TopDocs, CountCollector, Count, CountAndScoreSum};
+    use indexer::NoMergePolicy;
+    use std::collections::HashSet;
+    use query::{QueryParser, TermQuery};
+    use postings::SegmentPostingsOption;
+    use query::{Query, Weight};
+    use core::Searcher;
+    use std::any::Any;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use DocAddress;
+    use DocId;
+    use Result;
+    use Score;
+    use futures_cpupool::CpuPool;
+    use schema::Similarity;
+    use core::CollectionStatistics;
+    use num_cpus;
+    use std::cmp;
+    use test::Bencher;
+
+    #[test]
+    fn test_term_positions() {
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let string_field = schema_builder.add_text_field("string", STRING);
+        let schema = schema_builder.build();
+        let index = Index::create_in_ram(schema);
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.add_document(doc!(text_field=>"a b c a d a", string_field=>"a"));
+            index_writer.add_document(doc!(text_field=>"d e"));
+            assert!(index_writer.commit().is_ok());
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let term_a = Term::from_field_text(text_field, "a");
+        let term_d = Term::from_field_text(text_field, "d");
+        assert_eq!(searcher.term_positions(&DocAddress(0, 0), &term_a), vec![0, 3, 5]);
+        assert_eq!(searcher.term_positions(&DocAddress(0, 0), &term_d), vec![4]);
+        assert_eq!(searcher.term_positions(&DocAddress(0, 1), &term_d), vec![0]);
+        assert!(searcher.term_positions(&DocAddress(0, 1), &term_a).is_empty());
+        let term_missing = Term::from_field_text(text_field, "z");
+        assert!(searcher.term_positions(&DocAddress(0, 0), &term_missing).is_empty());
+        // positions are not indexed for this field.
+        let term_string = Term::from_field_text(string_field, "a");
+        assert!(searcher.term_positions(&DocAddress(0, 0), &term_string).is_empty());
+    }
+
+    #[test]
+    fn test_doc_fields() {
+        let mut schema_builder = SchemaBuilder::default();
+        let fields: Vec<Field> = (0..10)
+            .map(|i| schema_builder.add_text_field(&format!("field{}", i), STORED))
+            .collect();
+        let schema = schema_builder.build();
+        let index = Index::create_in_ram(schema);
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            let mut doc = Document::default();
+            for (i, &field) in fields.iter().enumerate() {
+                doc.add_text(field, &format!("value{}", i));
+            }
+            index_writer.add_document(doc);
+            assert!(index_writer.commit().is_ok());
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let doc_address = DocAddress(0, 0);
+        assert_eq!(searcher.doc(&doc_address).unwrap().len(), 10);
+        let doc = searcher.doc_fields(&doc_address, &[fields[7], fields[2]]).unwrap();
+        assert_eq!(doc.len(), 2);
+        assert_eq!(doc.get_first(fields[2]).unwrap().text(), "value2");
+        assert_eq!(doc.get_first(fields[7]).unwrap().text(), "value7");
+        assert!(doc.get_first(fields[3]).is_none());
+        assert!(searcher.doc_fields(&doc_address, &[]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_doc_exists() {
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT | STORED);
+        let schema = schema_builder.build();
+        let index = Index::create_in_ram(schema);
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.add_document(doc!(text_field=>"a"));
+            index_writer.add_document(doc!(text_field=>"b"));
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let doc = searcher.doc(&DocAddress(0, 1)).unwrap();
+        assert_eq!(doc.get_first(text_field).unwrap().text(), "b");
+        assert!(searcher.exists(&DocAddress(0, 0)));
+        assert!(searcher.exists(&DocAddress(0, 1)));
+        assert!(!searcher.exists(&DocAddress(0, 2)));
+        assert!(!searcher.exists(&DocAddress(1, 0)));
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.delete_term(Term::from_field_text(text_field, "a"));
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        assert!(!searcher.exists(&DocAddress(0, 0)));
+        assert!(searcher.exists(&DocAddress(0, 1)));
+    }
+
+    #[test]
+    fn test_filter_existing_terms() {
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let other_field = schema_builder.add_text_field("other", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.set_merge_policy(box NoMergePolicy);
+            index_writer.add_document(doc!(text_field=>"a b"));
+            index_writer.commit().unwrap();
+            index_writer.add_document(doc!(text_field=>"c", other_field=>"d"));
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        assert_eq!(searcher.segment_readers().len(), 2);
+        let term = |field: Field, text: &str| Term::from_field_text(field, text);
+        let terms = vec!(
+            term(text_field, "c"),
+            term(text_field, "z"),
+            term(text_field, "a"),
+            term(text_field, "d"),
+            term(other_field, "d"),
+            term(other_field, "a"),
+            term(text_field, "b"),
+        );
+        assert_eq!(searcher.filter_existing_terms(terms), vec!(
+            term(text_field, "c"),
+            term(text_field, "a"),
+            term(other_field, "d"),
+            term(text_field, "b"),
+        ));
+        assert!(searcher.filter_existing_terms(Vec::new()).is_empty());
+    }
+
+    #[test]
+    fn test_warm_fast_fields() {
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let fields: Vec<Field> = (0..20)
+            .map(|i| schema_builder.add_u32_field(&format!("field{}", i), FAST))
+            .collect();
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.set_merge_policy(box NoMergePolicy);
+            for doc_id in 0..1_000u32 {
+                let mut doc = Document::default();
+                for (i, &field) in fields.iter().enumerate() {
+                    doc.add_u32(field, doc_id * (i as u32 + 1) % 997);
+                }
+                index_writer.add_document(doc);
+                if doc_id == 600 {
+                    index_writer.commit().unwrap();
+                }
+            }
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        assert_eq!(searcher.segment_readers().len(), 2);
+        let pool = CpuPool::new(4);
+        let mut warmed_fields = fields.clone();
+        warmed_fields.push(text_field);
+        for segment_reader in searcher.segment_readers() {
+            let columns = segment_reader.warm_fast_fields_parallel(&warmed_fields, &pool);
+            assert_eq!(columns.len(), warmed_fields.len());
+            for (&field, column) in warmed_fields.iter().zip(columns.iter()) {
+                assert_eq!(*column, segment_reader.fast_field_column(field));
+            }
+            assert!(columns[fields.len()].is_none());
+        }
+        searcher.warm_fast_fields(&warmed_fields, &pool);
+        assert_eq!(searcher.fast_field_cache().num_loads(), 2 * fields.len());
+        for (segment_ord, segment_reader) in searcher.segment_readers().iter().enumerate() {
+            for (i, &field) in fields.iter().enumerate() {
+                let column = searcher.fast_field_column(segment_ord, field).unwrap();
+                let fast_field_reader = segment_reader.get_fast_field_reader(field).unwrap();
+                assert_eq!(column.len(), segment_reader.max_doc() as usize);
+                for (doc, &val) in column.iter().enumerate() {
+                    assert_eq!(val, fast_field_reader.get(doc as DocId));
+                }
+                if segment_ord == 1 {
+                    // the first document of the second segment is 601.
+                    assert_eq!(column[0], 601 * (i as u32 + 1) % 997);
+                }
+            }
+        }
+        // the columns were all served from the cache.
+        assert_eq!(searcher.fast_field_cache().num_loads(), 2 * fields.len());
+    }
+
+    #[test]
+    fn test_sort_by_fast_field() {
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let date_field = schema_builder.add_u32_field("date", FAST);
+        let schema = schema_builder.build();
+        let index = Index::create_in_ram(schema);
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.add_document(doc!(text_field=>"a", date_field=>30u32));
+            index_writer.add_document(doc!(text_field=>"b", date_field=>10u32));
+            index_writer.commit().unwrap();
+            index_writer.add_document(doc!(text_field=>"c", date_field=>20u32));
+            index_writer.add_document(doc!(text_field=>"d", date_field=>10u32));
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let doc_addresses = vec![DocAddress(0, 0), DocAddress(0, 1), DocAddress(1, 0), DocAddress(1, 1)];
+        let sort = |expected_num_loads: usize| {
+            let searcher = index.searcher();
+            let mut sorted_doc_addresses = doc_addresses.clone();
+            searcher.sort_by_fast_field(&mut sorted_doc_addresses, date_field).unwrap();
+            assert_eq!(searcher.fast_field_cache().num_loads(), expected_num_loads);
+            sorted_doc_addresses
+        };
+        let sorted_doc_addresses = sort(2);
+        assert_eq!(sorted_doc_addresses, vec![DocAddress(0, 1), DocAddress(1, 1), DocAddress(1, 0), DocAddress(0, 0)]);
+        // the columns loaded by the first sort are reused.
+        assert_eq!(sort(2), sorted_doc_addresses);
+        {
+            let searcher = index.searcher();
+            assert_eq!(searcher.fast_field_cache().memory_usage(), 16);
+            let mut doc_addresses = doc_addresses.clone();
+            assert!(searcher.sort_by_fast_field(&mut doc_addresses, text_field).is_err());
+        }
+        // reloading the searchers invalidates the cache.
+        index.load_searchers().unwrap();
+        assert_eq!(index.searcher().fast_field_cache().num_loads(), 0);
+        assert_eq!(sort(2), sorted_doc_addresses);
+    }
+
+    #[test]
+    fn test_ranked_stream() {
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let schema = schema_builder.build();
+        let index = Index::create_in_ram(schema);
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            for num_fillers in 0..300 {
+                // the more fillers, the lower the score.
+                let mut text = String::from("a");
+                for _ in 0..num_fillers {
+                    text.push_str(" x");
+                }
+                if num_fillers % 3 == 0 {
+                    text = text.replace("a", "b");
+                }
+                index_writer.add_document(doc!(text_field=>text));
+                if num_fillers % 100 == 99 {
+                    index_writer.commit().unwrap();
+                }
+            }
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        assert_eq!(searcher.segment_readers().len(), 3);
+        let term_query = TermQuery::new(Term::from_field_text(text_field, "a"), SegmentPostingsOption::Freq);
+        let ranked_docs: Vec<(f32, DocAddress)> = searcher.ranked_stream(&term_query).unwrap().collect();
+        assert_eq!(ranked_docs.len(), 200);
+        for k in &[1, 10, 150, 200] {
+            let mut top_collector = TopCollector::with_limit(*k);
+            searcher.search(&term_query, &mut top_collector).unwrap();
+            let ranked_docs_head: Vec<(f32, DocAddress)> = searcher.ranked_stream(&term_query).unwrap().take(*k).collect();
+            assert_eq!(ranked_docs_head, top_collector.score_docs());
+            assert_eq!(&ranked_docs[..*k], &ranked_docs_head[..]);
+        }
+        let missing_query = TermQuery::new(Term::from_field_text(text_field, "c"), SegmentPostingsOption::Freq);
+        assert_eq!(searcher.ranked_stream(&missing_query).unwrap().count(), 0);
+    }
+
+
+    #[test]
+    fn test_search_excluding() {
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let schema = schema_builder.build();
+        let index = Index::create_in_ram(schema);
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.add_document(doc!(text_field=>"a x x"));
+            index_writer.add_document(doc!(text_field=>"a"));
+            index_writer.commit().unwrap();
+            index_writer.add_document(doc!(text_field=>"a x"));
+            index_writer.add_document(doc!(text_field=>"b"));
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let term_query = TermQuery::new(Term::from_field_text(text_field, "a"), SegmentPostingsOption::Freq);
+        let mut top_collector = TopCollector::with_limit(3);
+        searcher.search(&term_query, &mut top_collector).unwrap();
+        let score_docs = top_collector.score_docs();
+        assert_eq!(score_docs[0].1, DocAddress(0, 1));
+        assert_eq!(score_docs[1].1, DocAddress(1, 0));
+
+        let mut excluded = HashSet::new();
+        excluded.insert(DocAddress(0, 1));
+        // excluding a document that does not match has no effect.
+        excluded.insert(DocAddress(1, 1));
+        let mut top_collector = TopCollector::with_limit(3);
+        searcher.search_excluding(&term_query, &mut top_collector, &excluded).unwrap();
+        assert_eq!(top_collector.score_docs(), score_docs[1..].to_vec());
+    }
+
+    #[test]
+    fn test_cooccurrence() {
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let tag_field = schema_builder.add_text_field("tag", STRING);
+        let schema = schema_builder.build();
+        let index = Index::create_in_ram(schema);
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.set_merge_policy(box NoMergePolicy);
+            index_writer.add_document(doc!(text_field=>"rust search", tag_field=>"lib"));
+            index_writer.add_document(doc!(text_field=>"rust", tag_field=>"lib"));
+            index_writer.add_document(doc!(text_field=>"search engine"));
+            index_writer.commit().unwrap();
+            index_writer.add_document(doc!(text_field=>"rust search engine", tag_field=>"lib"));
+            index_writer.add_document(doc!(text_field=>"search rust", tag_field=>"app"));
+            index_writer.add_document(doc!(text_field=>"engine"));
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        assert_eq!(searcher.num_segments(), 2);
+        let text_term = |text: &str| Term::from_field_text(text_field, text);
+        let lib_term = Term::from_field_text(tag_field, "lib");
+        assert_eq!(searcher.cooccurrence(&text_term("rust"), &text_term("search")), 3);
+        assert_eq!(searcher.cooccurrence(&text_term("search"), &text_term("rust")), 3);
+        assert_eq!(searcher.cooccurrence(&text_term("search"), &text_term("engine")), 2);
+        assert_eq!(searcher.cooccurrence(&text_term("rust"), &lib_term), 3);
+        assert_eq!(searcher.cooccurrence(&text_term("rust"), &text_term("rust")), searcher.doc_freq(&text_term("rust")) as u64);
+        assert_eq!(searcher.cooccurrence(&text_term("rust"), &text_term("missing")), 0);
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.delete_term(Term::from_field_text(tag_field, "app"));
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        // deleted documents are not counted.
+        assert_eq!(searcher.cooccurrence(&text_term("rust"), &text_term("search")), 2);
+    }
+
+    #[test]
+    fn test_count() {
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let tag_field = schema_builder.add_text_field("tag", STRING);
+        // the statistics of bm25 are not needed to count.
+        schema_builder.set_similarity(text_field, Similarity::bm25());
+        let schema = schema_builder.build();
+        let index = Index::create_in_ram(schema);
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.set_merge_policy(box NoMergePolicy);
+            index_writer.add_document(doc!(text_field=>"rust search", tag_field=>"lib"));
+            index_writer.add_document(doc!(text_field=>"rust", tag_field=>"app"));
+            index_writer.add_document(doc!(text_field=>"search engine"));
+            index_writer.commit().unwrap();
+            index_writer.add_document(doc!(text_field=>"rust search engine", tag_field=>"lib"));
+            index_writer.add_document(doc!(text_field=>"search rust", tag_field=>"app"));
+            index_writer.add_document(doc!(text_field=>"engine"));
+            index_writer.commit().unwrap();
+            // leaves a segment with deletes, and one without.
+            index_writer.delete_term(Term::from_field_text(tag_field, "app"));
+            index_writer.add_document(doc!(text_field=>"rust engine"));
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        assert_eq!(searcher.num_segments(), 3);
+        assert!(searcher.segment_readers().iter().any(|segment_reader| segment_reader.num_deleted_docs() > 0));
+        assert!(searcher.segment_readers().iter().any(|segment_reader| segment_reader.num_deleted_docs() == 0));
+        let query_parser = QueryParser::for_index(&index, vec!(text_field));
+        for query_str in &["rust", "search", "engine", "missing", "rust AND search", "rust OR engine", "+rust -engine", "\"search engine\"", "tag:app", "rust^2"] {
+            let query = query_parser.parse_query(query_str).unwrap();
+            let mut top_collector = TopCollector::with_limit(100);
+            searcher.search(&*query, &mut top_collector).unwrap();
+            assert_eq!(searcher.count(&*query).unwrap(), top_collector.docs().len(), "{}", query_str);
+        }
+        let rust_query = TermQuery::new(Term::from_field_text(text_field, "rust"), SegmentPostingsOption::NoFreq);
+        assert_eq!(searcher.count(&rust_query).unwrap(), 3);
+        assert_eq!(searcher.count(&TermQuery::new(Term::from_field_text(tag_field, "app"), SegmentPostingsOption::NoFreq)).unwrap(), 0);
+    }
+
+    /// Query counting the number of times it is run.
+    #[derive(Debug)]
+    struct CountingQuery {
+        term_query: TermQuery,
+        num_runs: AtomicUsize,
+    }
+
+    impl Query for CountingQuery {
+        fn as_any(&self) -> &Any {
+            self
+        }
+
+        fn weight(&self, searcher: &Searcher) -> Result<Box<Weight>> {
+            self.num_runs.fetch_add(1, Ordering::SeqCst);
+            self.term_query.weight(searcher)
+        }
+    }
+
+    #[test]
+    fn test_search_top_docs_cached() {
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let schema = schema_builder.build();
+        let index = Index::create_in_ram(schema);
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.add_document(doc!(text_field=>"a x x"));
+            index_writer.add_document(doc!(text_field=>"a"));
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let query = CountingQuery {
+            term_query: TermQuery::new(Term::from_field_text(text_field, "a"), SegmentPostingsOption::Freq),
+            num_runs: AtomicUsize::new(0),
+        };
+        let score_docs = {
+            let searcher = index.searcher();
+            let mut top_collector = TopCollector::with_limit(2);
+            searcher.search(&query.term_query, &mut top_collector).unwrap();
+            top_collector.score_docs()
+        };
+        {
+            let searcher = index.searcher();
+            let top_docs = searcher.search_top_docs_cached("a", &query, 2).unwrap();
+            assert_eq!(*top_docs, score_docs);
+            assert_eq!(query.num_runs.load(Ordering::SeqCst), 1);
+            assert_eq!(searcher.result_cache().num_hits(), 0);
+        }
+        {
+            let searcher = index.searcher();
+            let top_docs = searcher.search_top_docs_cached("a", &query, 2).unwrap();
+            assert_eq!(*top_docs, score_docs);
+            assert_eq!(query.num_runs.load(Ordering::SeqCst), 1);
+            assert_eq!(searcher.result_cache().num_hits(), 1);
+            // the limit is part of the key.
+            searcher.search_top_docs_cached("a", &query, 1).unwrap();
+            assert_eq!(query.num_runs.load(Ordering::SeqCst), 2);
+        }
+        // reloading the searchers invalidates the cache.
+        index.load_searchers().unwrap();
+        {
+            let searcher = index.searcher();
+            assert!(searcher.result_cache().is_empty());
+            searcher.search_top_docs_cached("a", &query, 2).unwrap();
+            assert_eq!(query.num_runs.load(Ordering::SeqCst), 3);
+        }
+    }
+
+    #[test]
+    fn test_search_fruit() {
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let schema = schema_builder.build();
+        let index = Index::create_in_ram(schema);
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.set_merge_policy(box NoMergePolicy);
+            for i in 0..20 {
+                let mut text = "a ".repeat(i % 7 + 1);
+                text.push_str(&"z ".repeat(i % 3 + 1));
+                index_writer.add_document(doc!(text_field=>text));
+                if i % 8 == 7 {
+                    index_writer.commit().unwrap();
+                }
+            }
+            index_writer.add_document(doc!(text_field=>"b"));
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        assert_eq!(searcher.segment_readers().len(), 3);
+        let term_query = TermQuery::new(Term::from_field_text(text_field, "a"), SegmentPostingsOption::Freq);
+
+        let mut top_collector = TopCollector::with_limit(5);
+        searcher.search(&term_query, &mut top_collector).unwrap();
+        let top_docs = searcher.search_fruit(&term_query, &TopDocs::with_limit(5)).unwrap();
+        assert_eq!(top_docs.len(), 5);
+        assert_eq!(top_docs, top_collector.score_docs());
+
+        let mut count_collector = CountCollector::default();
+        searcher.search(&term_query, &mut count_collector).unwrap();
+        assert_eq!(searcher.search_fruit(&term_query, &Count).unwrap(), 20);
+        assert_eq!(count_collector.count(), 20);
+    }
+
+    fn multi_segment_index(num_segments: usize, num_docs_per_segment: usize) -> (Index, Field) {
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.set_merge_policy(box NoMergePolicy);
+            for segment_ord in 0..num_segments {
+                for i in 0..num_docs_per_segment {
+                    let mut text = "a ".repeat((segment_ord + i) % 7 + 1);
+                    text.push_str(&"z ".repeat(i % 3 + 1));
+                    index_writer.add_document(doc!(text_field=>text));
+                }
+                index_writer.commit().unwrap();
+            }
+        }
+        (index, text_field)
+    }
+
+    #[test]
+    fn test_search_fruit_in_parallel() {
+        let (index, text_field) = multi_segment_index(8, 50);
+        assert_eq!(index.num_search_threads(), None);
+        index.load_searchers().unwrap();
+        let expected_num_threads = cmp::max(1, cmp::min(8, num_cpus::get()));
+        assert_eq!(index.searcher().num_search_threads(), expected_num_threads);
+
+        index.set_num_search_threads(Some(1));
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        assert_eq!(searcher.num_search_threads(), 1);
+        assert_eq!(searcher.segment_readers().len(), 8);
+        let term_query = TermQuery::new(Term::from_field_text(text_field, "a"), SegmentPostingsOption::Freq);
+        let top_docs = searcher.search_fruit(&term_query, &TopDocs::with_limit(30)).unwrap();
+        let (count, score_sum) = searcher.search_fruit(&term_query, &CountAndScoreSum).unwrap();
+        assert_eq!(count, 400);
+        for num_search_threads in vec![2, 3, 8, 16] {
+            index.set_num_search_threads(Some(num_search_threads));
+            index.load_searchers().unwrap();
+            let searcher = index.searcher();
+            assert_eq!(searcher.num_search_threads(), num_search_threads);
+            assert_eq!(searcher.search_fruit(&term_query, &TopDocs::with_limit(30)).unwrap(), top_docs);
+            assert_eq!(searcher.search_fruit(&term_query, &Count).unwrap(), 400);
+            // the fruits are merged in the same order whatever the threads.
+            assert_eq!(searcher.search_fruit(&term_query, &CountAndScoreSum).unwrap(), (count, score_sum));
+        }
+    }
+
+    fn bench_search_fruit(b: &mut Bencher, num_search_threads: usize) {
+        let (index, text_field) = multi_segment_index(8, 5_000);
+        index.set_num_search_threads(Some(num_search_threads));
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let term_query = TermQuery::new(Term::from_field_text(text_field, "a"), SegmentPostingsOption::Freq);
+        b.iter(|| searcher.search_fruit(&term_query, &TopDocs::with_limit(10)).unwrap());
+    }
+
+    #[bench]
+    fn bench_search_fruit_single_thread(b: &mut Bencher) {
+        bench_search_fruit(b, 1);
+    }
+
+    #[bench]
+    fn bench_search_fruit_eight_threads(b: &mut Bencher) {
+        bench_search_fruit(b, 8);
+    }
+
+    #[test]
+    fn test_count_and_score_sum() {
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let schema = schema_builder.build();
+        let index = Index::create_in_ram(schema);
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.set_merge_policy(box NoMergePolicy);
+            for i in 0..10 {
+                index_writer.add_document(doc!(text_field=>"a ".repeat(i % 4 + 1)));
+                if i % 4 == 3 {
+                    index_writer.commit().unwrap();
+                }
+            }
+            index_writer.add_document(doc!(text_field=>"b"));
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        assert_eq!(searcher.segment_readers().len(), 3);
+        let term_query = TermQuery::new(Term::from_field_text(text_field, "a"), SegmentPostingsOption::Freq);
+        let (count, score_sum) = searcher.search_fruit(&term_query, &CountAndScoreSum).unwrap();
+        let top_docs = searcher.search_fruit(&term_query, &TopDocs::with_limit(100)).unwrap();
+        assert_eq!(count, 10);
+        assert_eq!(top_docs.len(), 10);
+        let expected_score_sum: Score = top_docs.iter().map(|&(score, _)| score).sum();
+        assert!((score_sum - expected_score_sum).abs() < 1e-4f32);
+        assert_eq!(searcher.search_fruit(&TermQuery::new(Term::from_field_text(text_field, "c"), SegmentPostingsOption::Freq), &CountAndScoreSum).unwrap(), (0, 0f32));
+    }
+
+
+    #[test]
+    fn test_collection_statistics() {
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let title_field = schema_builder.add_text_field("title", TEXT);
+        schema_builder.set_similarity(text_field, Similarity::bm25());
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.add_document(doc!(text_field=>"a b", title_field=>"a"));
+            index_writer.add_document(doc!(text_field=>"b"));
+            index_writer.add_document(doc!(text_field=>"c"));
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let mut searcher = index.searcher();
+        let text_query = TermQuery::new(Term::from_field_text(text_field, "a"), SegmentPostingsOption::Freq);
+        let title_query = TermQuery::new(Term::from_field_text(title_field, "a"), SegmentPostingsOption::Freq);
+        let idf = |searcher: &Searcher, term_query: &TermQuery| {
+            let weight = term_query.specialized_weight(searcher);
+            weight.similarity.idf(weight.num_docs, weight.doc_freq)
+        };
+        let top_score = |searcher: &Searcher, term_query: &TermQuery| {
+            searcher.search_fruit(term_query, &TopDocs::with_limit(1)).unwrap()[0].0
+        };
+        let local_idf = idf(&searcher, &text_query);
+        let local_score = top_score(&searcher, &text_query);
+        let local_title_idf = idf(&searcher, &title_query);
+        assert_eq!(searcher.idf_num_docs(text_field), 3);
+
+        let mut collection_statistics = CollectionStatistics::new(1_000);
+        collection_statistics.field_doc_counts.insert(title_field, 2);
+        searcher.set_collection_statistics(Some(collection_statistics.clone()));
+        assert_eq!(searcher.collection_statistics(), Some(&collection_statistics));
+        assert_eq!(searcher.idf_num_docs(text_field), 1_000);
+        assert_eq!(searcher.idf_num_docs(title_field), 2);
+        // a term is rarer within a larger collection.
+        assert!(idf(&searcher, &text_query) > local_idf);
+        assert!(top_score(&searcher, &text_query) > local_score);
+        assert!(idf(&searcher, &title_query) < local_title_idf);
+
+        searcher.set_collection_statistics(None);
+        assert_eq!(idf(&searcher, &text_query), local_idf);
+        assert_eq!(top_score(&searcher, &text_query), local_score);
+
+        // the statistics do not leak to the next leases.
+        searcher.set_collection_statistics(Some(collection_statistics));
+        drop(searcher);
+        let searchers: Vec<_> = (0..12).map(|_| index.searcher()).collect();
+        assert!(searchers.iter().all(|searcher| searcher.collection_statistics().is_none()));
+    }
+
+    #[test]
+    fn test_set_similarity() {
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            // 0, `a` appears once
+            index_writer.add_document(doc!(text_field=>"a b c d e f g h i j"));
+            // 1, `a` is repeated, with the same field length
+            index_writer.add_document(doc!(text_field=>"a a a a a a a a a a"));
+            index_writer.add_document(doc!(text_field=>"k"));
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let mut searcher = index.searcher();
+        let term_query = TermQuery::new(Term::from_field_text(text_field, "a"), SegmentPostingsOption::Freq);
+        let scores = |searcher: &Searcher| -> (Score, Score) {
+            let score_docs = searcher.search_top_docs_cached("a", &term_query, 2).unwrap();
+            assert_eq!(score_docs.len(), 2);
+            let score = |doc_address: DocAddress| {
+                score_docs.iter()
+                    .find(|score_doc| score_doc.1 == doc_address)
+                    .map(|score_doc| score_doc.0)
+                    .unwrap()
+            };
+            (score(DocAddress(0, 1)), score(DocAddress(0, 0)))
+        };
+        assert_eq!(searcher.similarity(text_field), Similarity::TfIdf);
+        let (repeated_score, single_score) = scores(&searcher);
+        // the score grows with the square root of the term frequency.
+        let tfidf_ratio = repeated_score / single_score;
+        assert!((tfidf_ratio - 10f32.sqrt()).abs() < 1e-4f32);
+
+        searcher.set_similarity(Some(Similarity::bm25()));
+        assert_eq!(searcher.similarity(text_field), Similarity::bm25());
+        let (repeated_score, single_score) = scores(&searcher);
+        // the term frequency saturates.
+        assert!(repeated_score > single_score);
+        assert!(repeated_score / single_score < tfidf_ratio);
+        // both documents have 10 tokens, for an average of 7.
+        let norm = 1.2f32 * (0.25f32 + 0.75f32 * 10f32 / 7f32);
+        let expected_ratio = 10f32 * (1f32 + norm) / (10f32 + norm);
+        assert!((repeated_score / single_score - expected_ratio).abs() < 1e-4f32);
+
+        searcher.set_similarity(Some(Similarity::Constant));
+        assert_eq!(scores(&searcher), (1f32, 1f32));
+
+        searcher.set_similarity(None);
+        assert_eq!(searcher.similarity(text_field), Similarity::TfIdf);
+        let (repeated_score, single_score) = scores(&searcher);
+        assert!((repeated_score / single_score - 10f32.sqrt()).abs() < 1e-4f32);
+
+        // the similarity does not leak to the next leases.
+        searcher.set_similarity(Some(Similarity::Constant));
+        drop(searcher);
+        let searchers: Vec<_> = (0..12).map(|_| index.searcher()).collect();
+        assert!(searchers.iter().all(|searcher| searcher.similarity(text_field) == Similarity::TfIdf));
+    }
+
+}
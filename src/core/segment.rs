@@ -4,14 +4,18 @@ use schema::Schema;
 use DocId;
 use std::fmt;
 use core::SegmentId;
-use directory::{ReadOnlySource, WritePtr};
+use directory::{ReadOnlySource, WritePtr, FileProtection};
 use indexer::segment_serializer::SegmentSerializer;
 use super::SegmentComponent;
 use core::Index;
 use std::result;
 use directory::Directory;
 use core::SegmentMeta;
+use store::Compressor;
 use directory::error::{FileError, OpenWriteError};
+use std::io::Write;
+use std::sync::Arc;
+use std::sync::atomic::AtomicUsize;
 
 /// A segment is a piece of the index.
 #[derive(Clone)]
@@ -38,6 +42,11 @@ pub fn create_segment(index: Index, meta: SegmentMeta) -> Segment {
 
 impl Segment {
     
+    /// Returns the index the segment belongs to.
+    pub fn index(&self,) -> &Index {
+        &self.index
+    }
+
     /// Returns our index's schema.
     pub fn schema(&self,) -> Schema {
         self.index.schema()
@@ -53,6 +62,16 @@ impl Segment {
         self.meta.set_delete_meta(num_deleted_docs, opstamp);
     }
 
+    #[doc(hidden)]
+    pub fn set_store_compressor(&mut self, store_compressor: Compressor) {
+        self.meta.set_store_compressor(store_compressor);
+    }
+
+    #[doc(hidden)]
+    pub fn set_store_block_size(&mut self, store_block_size: usize) {
+        self.meta.set_store_block_size(store_block_size);
+    }
+
     /// Returns the segment's id.
     pub fn id(&self,) -> SegmentId {
         self.meta.id()
@@ -66,6 +85,23 @@ impl Segment {
         self.meta.relative_path(component)
     }
 
+    /// Protects the files of the segment from being deleted
+    /// by the garbage collection, until the returned
+    /// `FileProtection`s are dropped.
+    pub fn protect_from_delete(&self) -> Vec<FileProtection> {
+        self.meta
+            .list_files()
+            .iter()
+            .map(|path| self.index.directory().protect_file_from_delete(path))
+            .collect()
+    }
+
+    /// Returns the counter of the scorers created on the segment,
+    /// shared by all of the readers of the segment.
+    pub fn access_counter(&self) -> Arc<AtomicUsize> {
+        self.index.segment_access_counters().counter(self.id())
+    }
+
     /// Open one of the component file for read.
     pub fn open_read(&self, component: SegmentComponent) -> result::Result<ReadOnlySource, FileError> {
         let path = self.relative_path(component);
@@ -81,6 +117,50 @@ impl Segment {
     }
 }
 
+/// Copies the files of the segment described by `source_meta`
+/// from the `source` directory to the `target` directory,
+/// naming them after `target_meta`.
+///
+/// Components that do not exist in the source directory
+/// (e.g. the delete file of a segment without deletes)
+/// are simply skipped.
+pub fn copy_segment_files(source: &Directory,
+                          source_meta: &SegmentMeta,
+                          target: &mut Directory,
+                          target_meta: &SegmentMeta) -> Result<()> {
+    for component in SegmentComponent::iterator() {
+        let source_path = source_meta.relative_path(*component);
+        if !source.exists(&source_path) {
+            continue;
+        }
+        let source_data = try!(source.open_read(&source_path));
+        let target_path = target_meta.relative_path(*component);
+        let mut target_write = try!(target.open_write(&target_path));
+        try!(target_write.write_all(source_data.as_slice()));
+        try!(target_write.flush());
+    }
+    Ok(())
+}
+
+/// Renames all of the files of a segment within a directory,
+/// using a fresh `SegmentId`.
+///
+/// Returns the `SegmentMeta` of the renamed segment.
+pub fn rename_segment_files(directory: &mut Directory, segment_meta: &SegmentMeta) -> Result<SegmentMeta> {
+    let renamed_meta = segment_meta.with_segment_id(segment_meta.id().rename_to_fresh());
+    {
+        let source = directory.box_clone();
+        try!(copy_segment_files(&*source, segment_meta, directory, &renamed_meta));
+    }
+    for component in SegmentComponent::iterator() {
+        let path = segment_meta.relative_path(*component);
+        if directory.exists(&path) {
+            try!(directory.delete(&path));
+        }
+    }
+    Ok(renamed_meta)
+}
+
 pub trait SerializableSegment {
     /// Writes a view of a segment by pushing information
     /// to the `SegmentSerializer`.
@@ -93,4 +173,51 @@ pub trait SerializableSegment {
 #[derive(Clone,Debug,RustcDecodable,RustcEncodable)]
 pub struct SegmentInfo {
 	pub max_doc: DocId,
-}
\ No newline at end of file
+	// the ranges `start..end` of the blocks of more than one document,
+	// see `IndexWriter::add_document_block`.
+	// `None` for the segments written before the blocks were recorded.
+	pub blocks: Option<Vec<(DocId, DocId)>>,
+}
+
+impl SegmentInfo {
+	/// Returns the ranges `start..end` of the document blocks
+	/// of more than one document, in doc id order.
+	pub fn blocks(&self) -> &[(DocId, DocId)] {
+		self.blocks
+			.as_ref()
+			.map(|blocks| &blocks[..])
+			.unwrap_or(&[])
+	}
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::rename_segment_files;
+    use core::{Index, SegmentReader};
+    use schema::{self, Document, FieldValue};
+    use Directory;
+
+    #[test]
+    fn test_rename_segment_files() {
+        let mut schema_builder = schema::SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", schema::TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.add_document(doc!(text_field=>"a"));
+            index_writer.add_document(doc!(text_field=>"b"));
+            index_writer.commit().unwrap();
+        }
+        let segment_meta = index.segments().unwrap()[0].clone();
+        let mut directory = index.directory().box_clone();
+        let renamed_meta = rename_segment_files(&mut *directory, &segment_meta).unwrap();
+        assert!(renamed_meta.id() != segment_meta.id());
+        assert_eq!(renamed_meta.max_doc(), 2);
+        for path in segment_meta.list_files() {
+            assert!(!directory.exists(&path));
+        }
+        let segment_reader = SegmentReader::open(index.segment(renamed_meta)).unwrap();
+        assert_eq!(segment_reader.num_docs(), 2);
+    }
+}
@@ -0,0 +1,86 @@
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use core::SegmentId;
+
+
+/// Number of scorers created on each segment of an index.
+///
+/// The counters are shared by all of the `SegmentReader`s of
+/// the index, whatever the searcher they belong to, so that they
+/// measure how hot a segment is. They are not persisted.
+///
+/// See `IndexWriter::segment_access_counts`.
+#[derive(Clone, Default)]
+pub struct SegmentAccessCounters {
+    counters: Arc<RwLock<HashMap<SegmentId, Arc<AtomicUsize>>>>,
+}
+
+impl SegmentAccessCounters {
+
+    /// Returns the counter of the given segment,
+    /// creating it if it does not exist yet.
+    pub fn counter(&self, segment_id: SegmentId) -> Arc<AtomicUsize> {
+        if let Some(counter) = self.counters.read().unwrap().get(&segment_id) {
+            return counter.clone();
+        }
+        self.counters
+            .write()
+            .unwrap()
+            .entry(segment_id)
+            .or_insert_with(Arc::default)
+            .clone()
+    }
+
+    /// Returns the number of scorers created on the given segment.
+    pub fn access_count(&self, segment_id: SegmentId) -> usize {
+        self.counters
+            .read()
+            .unwrap()
+            .get(&segment_id)
+            .map(|counter| counter.load(Ordering::SeqCst))
+            .unwrap_or(0)
+    }
+
+    /// Removes the counter of the given segment.
+    pub fn remove(&self, segment_id: &SegmentId) {
+        self.counters.write().unwrap().remove(segment_id);
+    }
+
+    /// Removes the counters of the segments
+    /// for which `is_alive` returns false.
+    pub fn retain<F: Fn(&SegmentId) -> bool>(&self, is_alive: F) {
+        self.counters
+            .write()
+            .unwrap()
+            .retain(|segment_id, _| is_alive(segment_id));
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::SegmentAccessCounters;
+    use core::SegmentId;
+    use std::sync::atomic::Ordering;
+
+    #[test]
+    fn test_segment_access_counters() {
+        let counters = SegmentAccessCounters::default();
+        let segment_id = SegmentId::generate_random();
+        assert_eq!(counters.access_count(segment_id), 0);
+        counters.counter(segment_id).fetch_add(1, Ordering::SeqCst);
+        counters.counter(segment_id).fetch_add(1, Ordering::SeqCst);
+        assert_eq!(counters.access_count(segment_id), 2);
+        assert_eq!(counters.access_count(SegmentId::generate_random()), 0);
+        let other_segment_id = SegmentId::generate_random();
+        counters.counter(other_segment_id).fetch_add(1, Ordering::SeqCst);
+        counters.retain(|id| *id == other_segment_id);
+        assert_eq!(counters.access_count(segment_id), 0);
+        assert_eq!(counters.access_count(other_segment_id), 1);
+        counters.remove(&other_segment_id);
+        assert_eq!(counters.access_count(other_segment_id), 0);
+        assert!(counters.counters.read().unwrap().is_empty());
+    }
+}
@@ -1,4 +1,9 @@
-#[derive(Copy, Clone)]
+/// Enum of the different files a segment is made of.
+///
+/// The file of a component is named after the segment id,
+/// followed by the extension of the component.
+/// See `SegmentMeta::relative_path`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum SegmentComponent {
     INFO,
     POSTINGS,
@@ -7,13 +12,14 @@ pub enum SegmentComponent {
     FIELDNORMS,
     TERMS,
     STORE,
+    TERMVECTORS,
     DELETE
 }
 
 impl SegmentComponent {
     
     pub fn iterator() -> impl Iterator<Item=&'static SegmentComponent> {
-        static SEGMENT_COMPONENTS: [SegmentComponent;  8] = [
+        static SEGMENT_COMPONENTS: [SegmentComponent;  9] = [
             SegmentComponent::INFO,
             SegmentComponent::POSTINGS,
             SegmentComponent::POSITIONS,
@@ -21,9 +27,29 @@ impl SegmentComponent {
             SegmentComponent::FIELDNORMS,
             SegmentComponent::TERMS,
             SegmentComponent::STORE,
+            SegmentComponent::TERMVECTORS,
             SegmentComponent::DELETE
         ];
         SEGMENT_COMPONENTS.into_iter()
     }
+
+    /// Returns the extension of the files of this component,
+    /// without the leading dot.
+    ///
+    /// Delete files are additionally prefixed by their opstamp,
+    /// e.g. `<segment_id>.<opstamp>.del`.
+    pub fn extension(&self) -> &'static str {
+        match *self {
+            SegmentComponent::INFO => "info",
+            SegmentComponent::POSTINGS => "idx",
+            SegmentComponent::POSITIONS => "pos",
+            SegmentComponent::FASTFIELDS => "fast",
+            SegmentComponent::FIELDNORMS => "fieldnorm",
+            SegmentComponent::TERMS => "term",
+            SegmentComponent::STORE => "store",
+            SegmentComponent::TERMVECTORS => "tv",
+            SegmentComponent::DELETE => "del",
+        }
+    }
     
 }
\ No newline at end of file
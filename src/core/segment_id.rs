@@ -47,6 +47,15 @@ impl SegmentId {
         SegmentId(create_uuid())
     }
 
+    /// Returns a new random `SegmentId`, to be used
+    /// in place of this one.
+    ///
+    /// This is useful when importing a segment in an index
+    /// that already contains a segment with the same id.
+    pub fn rename_to_fresh(&self) -> SegmentId {
+        SegmentId::generate_random()
+    }
+
 
     /// Returns a shorter identifier of the segment.
     ///
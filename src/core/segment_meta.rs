@@ -2,6 +2,16 @@ use core::SegmentId;
 use super::SegmentComponent;
 use std::path::PathBuf;
 use std::collections::HashSet;
+use store::{Compressor, DEFAULT_BLOCK_SIZE};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Returns the current time, in seconds since the UNIX epoch.
+fn now_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0u64)
+}
 
 #[derive(Clone, Debug, RustcDecodable,RustcEncodable)]
 struct DeleteMeta {
@@ -18,17 +28,33 @@ pub struct SegmentMeta {
     segment_id: SegmentId,
     max_doc: u32,
     deletes: Option<DeleteMeta>, 
+    opstamp: Option<u64>,
+    store_compressor: Option<Compressor>,
+    store_block_size: Option<u32>,
+    label: Option<String>,
+    created_at: Option<u64>,
+    fieldnorms: Option<bool>,
+    term_vectors: Option<bool>,
 }
 
 impl SegmentMeta {
 
     /// Creates a new segment meta for 
     /// a segment with no deletes and no documents.
+    ///
+    /// The segment is timestamped with the current time.
     pub fn new(segment_id: SegmentId) -> SegmentMeta {
         SegmentMeta {
             segment_id: segment_id,
             max_doc: 0,
             deletes: None,
+            opstamp: None,
+            store_compressor: None,
+            store_block_size: None,
+            label: None,
+            created_at: Some(now_timestamp()),
+            fieldnorms: None,
+            term_vectors: None,
         }
     }
 
@@ -51,8 +77,15 @@ impl SegmentMeta {
     /// This is useful as the way tantivy removes files
     /// is by removing all files that have been created by tantivy
     /// and are not used by any segment anymore.
+    ///
+    /// The delete file is only listed if the segment has deletes,
+    /// the field norm file if the segment has field norms,
+    /// and the term vector file if the segment has term vectors.
     pub fn list_files(&self) -> HashSet<PathBuf> {
         SegmentComponent::iterator()
+            .filter(|component| **component != SegmentComponent::DELETE || self.has_deletes())
+            .filter(|component| **component != SegmentComponent::FIELDNORMS || self.has_fieldnorms())
+            .filter(|component| **component != SegmentComponent::TERMVECTORS || self.has_term_vectors())
             .map(|component| {
                 self.relative_path(*component)
             })
@@ -64,19 +97,14 @@ impl SegmentMeta {
     ///  
     /// It just joins the segment id with the extension 
     /// associated to a segment component.
+    /// All of the segment file names are built here.
     pub fn relative_path(&self, component: SegmentComponent) -> PathBuf {
-        use self::SegmentComponent::*;
-        let mut path = self.id().uuid_string();
-        path.push_str(&*match component {
-            POSITIONS => ".pos".to_string(),
-            INFO => ".info".to_string(),
-            POSTINGS => ".idx".to_string(),
-            TERMS => ".term".to_string(),
-            STORE => ".store".to_string(),
-            FASTFIELDS => ".fast".to_string(),
-            FIELDNORMS => ".fieldnorm".to_string(),
-            DELETE => {format!(".{}.del", self.delete_opstamp().unwrap_or(0))},
-        });
+        let path = match component {
+            SegmentComponent::DELETE => {
+                format!("{}.{}.{}", self.id().uuid_string(), self.delete_opstamp().unwrap_or(0), component.extension())
+            }
+            _ => format!("{}.{}", self.id().uuid_string(), component.extension()),
+        };
         PathBuf::from(path)
     }
 
@@ -102,17 +130,136 @@ impl SegmentMeta {
             .map(|delete_meta| delete_meta.opstamp)
     }
 
+    /// Returns the opstamp of the last document
+    /// added to the segment.
+    ///
+    /// For a merged segment, it is the highest opstamp of
+    /// the segments that were merged.
+    /// It is `None` if it is unknown, which is the case of segments
+    /// created by older versions of tantivy.
+    pub fn opstamp(&self) -> Option<u64> {
+        self.opstamp
+    }
+
+    /// Returns the codec used to compress the store
+    /// of the segment.
+    ///
+    /// Segments created by older versions of tantivy
+    /// are compressed with LZ4.
+    pub fn store_compressor(&self) -> Compressor {
+        self.store_compressor.unwrap_or(Compressor::Lz4)
+    }
+
+    /// Returns the size of the blocks of the store
+    /// of the segment, before compression.
+    ///
+    /// It is only needed to write the store:
+    /// the blocks are located through the skip list of the store.
+    pub fn store_block_size(&self) -> usize {
+        self.store_block_size
+            .map(|store_block_size| store_block_size as usize)
+            .unwrap_or(DEFAULT_BLOCK_SIZE)
+    }
+
+    /// Returns the label of the commit in which the segment
+    /// was published, if any.
+    ///
+    /// See `IndexWriter::commit_with_label`.
+    pub fn label(&self) -> Option<&str> {
+        self.label.as_ref().map(|label| label.as_str())
+    }
+
+    /// Returns the time at which the segment was created,
+    /// in seconds since the UNIX epoch.
+    ///
+    /// A merged segment is timestamped at the end of the merge.
+    /// It is `None` for segments created by older versions of tantivy.
+    pub fn created_at(&self) -> Option<u64> {
+        self.created_at
+    }
+
+    /// Returns true iff the segment has a field norm file,
+    /// that is iff at least one of the fields of the schema
+    /// has field norms.
+    ///
+    /// See `TextOptions::set_norms`.
+    pub fn has_fieldnorms(&self) -> bool {
+        self.fieldnorms.unwrap_or(true)
+    }
+
+    /// Returns true iff the segment has a term vector file,
+    /// that is iff at least one of the fields of the schema
+    /// has term vectors.
+    ///
+    /// See `TextOptions::set_term_vectors`.
+    pub fn has_term_vectors(&self) -> bool {
+        self.term_vectors.unwrap_or(false)
+    }
+
     /// Returns true iff the segment meta contains
     /// delete information.
     pub fn has_deletes(&self) -> bool {
         self.deletes.is_some()
     }
 
+    /// Returns a copy of this segment meta,
+    /// with a different segment id.
+    #[doc(hidden)]
+    pub fn with_segment_id(&self, segment_id: SegmentId) -> SegmentMeta {
+        SegmentMeta {
+            segment_id: segment_id,
+            max_doc: self.max_doc,
+            deletes: self.deletes.clone(),
+            opstamp: self.opstamp,
+            store_compressor: self.store_compressor,
+            store_block_size: self.store_block_size,
+            label: self.label.clone(),
+            created_at: self.created_at,
+            fieldnorms: self.fieldnorms,
+            term_vectors: self.term_vectors,
+        }
+    }
+
     #[doc(hidden)]
     pub fn set_max_doc(&mut self, max_doc: u32) {
         self.max_doc = max_doc;
     }
 
+    #[doc(hidden)]
+    pub fn set_opstamp(&mut self, opstamp: u64) {
+        self.opstamp = Some(opstamp);
+    }
+
+    #[doc(hidden)]
+    pub fn set_store_compressor(&mut self, store_compressor: Compressor) {
+        self.store_compressor = Some(store_compressor);
+    }
+
+    #[doc(hidden)]
+    pub fn set_store_block_size(&mut self, store_block_size: usize) {
+        self.store_block_size = Some(store_block_size as u32);
+    }
+
+    #[doc(hidden)]
+    pub fn set_label(&mut self, label: &str) {
+        self.label = Some(String::from(label));
+    }
+
+    #[doc(hidden)]
+    pub fn set_created_at(&mut self, created_at: u64) {
+        self.created_at = Some(created_at);
+    }
+
+    #[doc(hidden)]
+    pub fn set_has_fieldnorms(&mut self, has_fieldnorms: bool) {
+        self.fieldnorms = Some(has_fieldnorms);
+    }
+
+    #[doc(hidden)]
+    pub fn set_has_term_vectors(&mut self, has_term_vectors: bool) {
+        self.term_vectors = Some(has_term_vectors);
+    }
+
     #[doc(hidden)]
     pub fn set_delete_meta(&mut self, num_deleted_docs: u32, opstamp: u64) {
         self.deletes = Some(DeleteMeta {
@@ -121,3 +268,44 @@ impl SegmentMeta {
         });
     }
 }
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use core::SegmentId;
+
+    #[test]
+    fn test_list_files() {
+        let segment_id = SegmentId::generate_random();
+        let mut segment_meta = SegmentMeta::new(segment_id);
+        segment_meta.set_max_doc(10);
+        segment_meta.set_delete_meta(2, 7u64);
+        let uuid = segment_id.uuid_string();
+        let expected_files: HashSet<PathBuf> = ["info", "idx", "pos", "fast", "fieldnorm", "term", "store", "7.del"]
+            .iter()
+            .map(|extension| PathBuf::from(format!("{}.{}", uuid, extension)))
+            .collect();
+        assert_eq!(segment_meta.list_files(), expected_files);
+        assert_eq!(segment_meta.relative_path(SegmentComponent::STORE), PathBuf::from(format!("{}.store", uuid)));
+        let segment_meta_without_deletes = SegmentMeta::new(segment_id);
+        assert_eq!(segment_meta_without_deletes.list_files().len(), expected_files.len() - 1);
+        assert!(segment_meta_without_deletes
+            .list_files()
+            .iter()
+            .all(|path| path.extension().unwrap() != "del"));
+        let mut segment_meta_without_fieldnorms = SegmentMeta::new(segment_id);
+        segment_meta_without_fieldnorms.set_has_fieldnorms(false);
+        assert_eq!(segment_meta_without_fieldnorms.list_files().len(), expected_files.len() - 2);
+        assert!(!segment_meta_without_fieldnorms
+            .list_files()
+            .contains(&segment_meta.relative_path(SegmentComponent::FIELDNORMS)));
+        let mut segment_meta_with_term_vectors = SegmentMeta::new(segment_id);
+        segment_meta_with_term_vectors.set_has_term_vectors(true);
+        assert_eq!(segment_meta_with_term_vectors.list_files().len(), expected_files.len());
+        assert!(segment_meta_with_term_vectors
+            .list_files()
+            .contains(&PathBuf::from(format!("{}.tv", uuid))));
+    }
+}
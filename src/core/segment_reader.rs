@@ -6,8 +6,10 @@ use schema::Term;
 use common::HasLen;
 use fastfield::delete::DeleteBitSet;
 use store::StoreReader;
+use termvector::TermVectorReader;
 use schema::Document;
 use directory::ReadOnlySource;
+use directory::FileProtection;
 use DocId;
 use std::str;
 use postings::TermInfo;
@@ -18,12 +20,18 @@ use core::SegmentInfo;
 use schema::Field;
 use postings::SegmentPostingsOption;
 use postings::SegmentPostings;
-use fastfield::{U32FastFieldsReader, U32FastFieldReader};
+use postings::{DocSet, Postings, SkipResult};
+use fastfield::{U32FastFieldsReader, U32FastFieldReader, MultiValuedFastFieldReader, FastFieldWidth};
 use schema::Schema;
 use schema::FieldType;
 use postings::FreqHandler;
+use compression::{NUM_DOCS_PER_BLOCK, SKIP_ENTRY_NUM_BYTES};
 use schema::TextIndexingOptions;
 use error::Error;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use futures::{future, Future};
+use futures_cpupool::CpuPool;
 
 
 /// Entry point to access all of the datastructures of the `Segment`
@@ -33,6 +41,7 @@ use error::Error;
 /// - store
 /// - fast field readers
 /// - field norm reader
+/// - term vector reader
 ///
 /// The segment reader has a very low memory footprint,
 /// as close to all of the memory data is mmapped.
@@ -40,14 +49,20 @@ use error::Error;
 pub struct SegmentReader {
     segment_info: SegmentInfo,
     segment_id: SegmentId,
+    label: Option<String>,
     term_infos: FstMap<TermInfo>,
     postings_data: ReadOnlySource,
     store_reader: StoreReader,
     fast_fields_reader: U32FastFieldsReader,
     fieldnorms_reader: U32FastFieldsReader,
+    term_vector_reader: TermVectorReader,
     delete_bitset: DeleteBitSet,
     positions_data: ReadOnlySource,
     schema: Schema,
+    // the files of the segment are not garbage collected
+    // as long as the reader is alive.
+    _file_protections: Vec<FileProtection>,
+    access_counter: Arc<AtomicUsize>,
 }
 
 impl SegmentReader {
@@ -68,12 +83,34 @@ impl SegmentReader {
         self.segment_info.max_doc - self.num_deleted_docs()
     }
     
+    /// Returns the ranges `start..end` of the blocks of
+    /// more than one document of the segment, in doc id order.
+    ///
+    /// See `IndexWriter::add_document_block`.
+    pub fn doc_blocks(&self) -> &[(DocId, DocId)] {
+        self.segment_info.blocks()
+    }
+
     /// Return the number of documents that have been
     /// deleted in the segment.
     pub fn num_deleted_docs(&self) -> DocId {
         self.delete_bitset.len() as DocId
     }
 
+    /// Records the creation of a scorer on the segment.
+    ///
+    /// This is called by the searcher every time a query is run
+    /// on the segment.
+    pub fn record_access(&self) {
+        self.access_counter.fetch_add(1, Ordering::SeqCst);
+    }
+
+    /// Returns the number of scorers created on the segment,
+    /// by this reader or by any other reader of the same segment.
+    pub fn access_count(&self) -> usize {
+        self.access_counter.load(Ordering::SeqCst)
+    }
+
     /// Accessor to a segment's fast field reader given a field.
     pub fn get_fast_field_reader(&self, field: Field) -> Option<U32FastFieldReader> {
         /// Returns the u32 fast value reader if the field
@@ -84,6 +121,57 @@ impl SegmentReader {
         ///
         /// # Panics
         /// May panic if the index is corrupted.
+        self.fast_field_source(field).map(U32FastFieldReader::open)
+    }
+
+    /// Returns the reader of the multivalued fast field `field`.
+    ///
+    /// Returns `None` if the field is not a multivalued u32 fast field.
+    /// See `SchemaBuilder::set_multivalued`.
+    pub fn get_multivalued_fast_field_reader(&self, field: Field) -> Option<MultiValuedFastFieldReader> {
+        let field_entry = self.schema.get_field_entry(field);
+        if !field_entry.is_u32_multivalued_fast() {
+            warn!("Field <{}> is not defined as a multivalued fast field.", field_entry.name());
+            return None;
+        }
+        self.fast_fields_reader.get_multivalued_field(field)
+    }
+
+    /// Returns the fully decoded column of the fast field `field`.
+    ///
+    /// Returns `None` if the field is not a u32 fast field.
+    pub fn fast_field_column(&self, field: Field) -> Option<Vec<u32>> {
+        let max_doc = self.max_doc();
+        self.fast_field_source(field)
+            .map(|source| decode_fast_field_column(source, max_doc))
+    }
+
+    /// Returns the fully decoded columns of the fast fields `fields`,
+    /// in the same order.
+    ///
+    /// The columns are independent from one another, and they are
+    /// decoded concurrently on the threads of `pool`. The call blocks
+    /// until all of them are decoded.
+    ///
+    /// The column of a field is `None` if it is not a u32 fast field.
+    pub fn warm_fast_fields_parallel(&self, fields: &[Field], pool: &CpuPool) -> Vec<Option<Vec<u32>>> {
+        let max_doc = self.max_doc();
+        let column_futures: Vec<_> = fields
+            .iter()
+            .map(|&field| {
+                let source_opt = self.fast_field_source(field);
+                pool.spawn_fn(move || {
+                    let column_opt = source_opt.map(|source| decode_fast_field_column(source, max_doc));
+                    Ok::<Option<Vec<u32>>, ()>(column_opt)
+                })
+            })
+            .collect();
+        future::join_all(column_futures)
+            .wait()
+            .expect("Decoding a fast field column cannot fail")
+    }
+
+    fn fast_field_source(&self, field: Field) -> Option<ReadOnlySource> {
         let field_entry = self.schema.get_field_entry(field);
         match field_entry.field_type() {
             &FieldType::Str(_) => {
@@ -91,17 +179,50 @@ impl SegmentReader {
                 None
             },
             &FieldType::U32(ref u32_options) => {
-                if u32_options.is_fast() {
-                    self.fast_fields_reader.get_field(field)
+                if field_entry.is_u32_multivalued_fast() {
+                    warn!("Field <{}> is a multivalued fast field.", field_entry.name());
+                    None
+                }
+                else if u32_options.is_fast() {
+                    self.fast_fields_reader.field_source(field)
                 }
                 else {
                     warn!("Field <{}> is not defined as a fast field.", field_entry.name());
                     None
                 }
             },
+            &FieldType::Date(_) => {
+                warn!("Field <{}> is not a fast field. It is a date field, and fast date fields are not supported yet.", field_entry.name());
+                None
+            },
+            &FieldType::Json(_) => {
+                warn!("Field <{}> is not a u32 fast field. It is a JSON field.", field_entry.name());
+                None
+            },
         }
     }
     
+    /// Returns the number of bits per value and the base value
+    /// of each of the fast fields of the segment.
+    ///
+    /// This is useful to analyze the storage used by
+    /// the fast fields: their width only depends on the
+    /// range of their values within the segment.
+    pub fn fast_field_widths(&self) -> Vec<FastFieldWidth> {
+        self.schema
+            .fields()
+            .iter()
+            .enumerate()
+            .filter(|&(_, field_entry)| field_entry.is_u32_fast())
+            .map(|(field_id, _)| Field(field_id as u8))
+            .filter_map(|field| {
+                self.fast_fields_reader
+                    .get_field(field)
+                    .map(|reader| FastFieldWidth::from_reader(field, &reader))
+            })
+            .collect()
+    }
+
     /// Accessor to the segment's `Field norms`'s reader.
     ///
     /// Field norms are the length (in tokens) of the fields.
@@ -113,6 +234,15 @@ impl SegmentReader {
         self.fieldnorms_reader.get_field(field) 
     }
         
+    /// Returns true iff the term is in the term dictionary
+    /// of the segment.
+    ///
+    /// The term may still be absent from all of the
+    /// documents that are not deleted.
+    pub fn contains_term(&self, term: &Term) -> bool {
+        self.term_infos.contains_key(term.as_slice())
+    }
+
     /// Returns the number of documents containing the term.
     pub fn doc_freq(&self, term: &Term) -> u32 {
         match self.get_term_info(term) {
@@ -128,6 +258,7 @@ impl SegmentReader {
 
     /// Open a new segment for reading.
     pub fn open(segment: Segment) -> Result<SegmentReader> {
+        let file_protections = segment.protect_from_delete();
         let segment_info_reader = try!(segment.open_read(SegmentComponent::INFO));
         let segment_info_data = try!(
             str::from_utf8(&*segment_info_reader)
@@ -145,14 +276,30 @@ impl SegmentReader {
         );
         let source = try!(segment.open_read(SegmentComponent::TERMS));
         let term_infos = try!(FstMap::from_source(source));
-        let store_reader = StoreReader::from(try!(segment.open_read(SegmentComponent::STORE)));
+        let store_source = try!(segment.open_read(SegmentComponent::STORE));
+        let store_reader = StoreReader::open(store_source, segment.meta().store_compressor());
         let postings_shared_mmap = try!(segment.open_read(SegmentComponent::POSTINGS));
         
         let fast_field_data = try!(segment.open_read(SegmentComponent::FASTFIELDS));
         let fast_fields_reader = try!(U32FastFieldsReader::open(fast_field_data));
         
-        let fieldnorms_data = try!(segment.open_read(SegmentComponent::FIELDNORMS));
-        let fieldnorms_reader = try!(U32FastFieldsReader::open(fieldnorms_data));
+        let fieldnorms_reader =
+            if segment.meta().has_fieldnorms() {
+                let fieldnorms_data = try!(segment.open_read(SegmentComponent::FIELDNORMS));
+                try!(U32FastFieldsReader::open(fieldnorms_data))
+            }
+            else {
+                U32FastFieldsReader::empty()
+            };
+
+        let term_vector_reader =
+            if segment.meta().has_term_vectors() {
+                let term_vector_data = try!(segment.open_read(SegmentComponent::TERMVECTORS));
+                try!(TermVectorReader::open(term_vector_data))
+            }
+            else {
+                TermVectorReader::empty()
+            };
         
         let positions_data = segment
             .open_read(SegmentComponent::POSITIONS)
@@ -161,7 +308,7 @@ impl SegmentReader {
         let delete_bitset =
             if segment.meta().has_deletes() {
                 let delete_data = segment.open_read(SegmentComponent::DELETE)?;
-                DeleteBitSet::open(delete_data)
+                DeleteBitSet::open(delete_data, segment.meta().max_doc(), segment.meta().num_deleted_docs())?
             }
             else {
                 DeleteBitSet::empty()
@@ -173,12 +320,16 @@ impl SegmentReader {
             postings_data: postings_shared_mmap,
             term_infos: term_infos,
             segment_id: segment.id(),
+            label: segment.meta().label().map(String::from),
             store_reader: store_reader,
             fast_fields_reader: fast_fields_reader,
             fieldnorms_reader: fieldnorms_reader,
+            term_vector_reader: term_vector_reader,
             delete_bitset: delete_bitset,
             positions_data: positions_data,
             schema: schema,
+            _file_protections: file_protections,
+            access_counter: segment.access_counter(),
         })
     }
     
@@ -186,6 +337,18 @@ impl SegmentReader {
     pub fn term_infos(&self) -> &FstMap<TermInfo> {
         &self.term_infos
     }
+
+    /// Iterates over all of the terms of a field, in lexicographic
+    /// order, together with their document frequency.
+    ///
+    /// Terms are returned as their value bytes, without the field.
+    /// The document frequencies are read from the term dictionary,
+    /// so that deleted documents are still counted.
+    pub fn term_doc_freqs<'a>(&'a self, field: Field) -> impl Iterator<Item=(Vec<u8>, u32)> + 'a {
+        self.term_infos
+            .prefix_iter(&[field.0])
+            .map(|(term_bytes, term_info)| (term_bytes[1..].to_vec(), term_info.doc_freq))
+    }
        
     /// Returns the document (or to be accurate, its stored field)
     /// bearing the given doc id.
@@ -195,6 +358,31 @@ impl SegmentReader {
         self.store_reader.get(doc_id)
     }
 
+    /// Returns the term vector of a field of a document: the terms
+    /// of the field in the document, sorted, along with their term frequency.
+    ///
+    /// The term vector is empty if the field has no term vectors.
+    /// See `TextOptions::set_term_vectors`.
+    pub fn term_vector(&self, doc_id: DocId, field: Field) -> Result<Vec<(Term, u32)>> {
+        let mut term_vector = try!(self.term_vector_reader.get(doc_id));
+        term_vector.retain(|&(ref term, _)| term.field() == field);
+        Ok(term_vector)
+    }
+
+    /// Accessor to the segment's `TermVectorReader`.
+    #[doc(hidden)]
+    pub fn get_term_vector_reader(&self) -> &TermVectorReader {
+        &self.term_vector_reader
+    }
+
+    /// Returns the document bearing the given doc id,
+    /// with only the stored values of the given fields.
+    ///
+    /// See `StoreReader::get_first_fields`.
+    pub fn doc_fields(&self, doc_id: DocId, fields: &[Field]) -> Result<Document> {
+        self.store_reader.get_first_fields(doc_id, fields)
+    }
+
 
     /// Returns the segment postings associated with the term, and with the given option,
     /// or `None` if the term has never been encounterred and indexed. 
@@ -206,6 +394,14 @@ impl SegmentReader {
     /// For instance, requesting `SegmentPostingsOption::FreqAndPositions` for a `TextIndexingOptions`
     /// that does not index position will return a `SegmentPostings` with `DocId`s and frequencies.
     pub fn read_postings(&self, term: &Term, option: SegmentPostingsOption) -> Option<SegmentPostings> {
+        self.read_postings_with_delete_bitset(term, option, &self.delete_bitset)
+    }
+
+    /// Returns the segment postings associated with the term, like `read_postings`,
+    /// but skipping the documents of `delete_bitset` rather than
+    /// the deleted documents of the segment.
+    #[doc(hidden)]
+    pub fn read_postings_with_delete_bitset<'a>(&'a self, term: &Term, option: SegmentPostingsOption, delete_bitset: &'a DeleteBitSet) -> Option<SegmentPostings<'a>> {
         let field = term.field();
         let field_entry = self.schema.get_field_entry(field);
         let term_info = get!(self.get_term_info(&term));
@@ -216,7 +412,16 @@ impl SegmentReader {
                 let indexing_options = options.get_indexing_options();
                 match option {
                     SegmentPostingsOption::NoFreq => {
-                        FreqHandler::new_without_freq()
+                        if indexing_options.is_termfreq_enabled() {
+                            // the blocks of doc ids are interleaved with the
+                            // blocks of term frequencies, which therefore need
+                            // to be decoded to reach the next block of doc ids
+                            // (and the payloads).
+                            FreqHandler::new_with_freq()
+                        }
+                        else {
+                            FreqHandler::new_without_freq()
+                        }
                     }
                     SegmentPostingsOption::Freq => {
                         if indexing_options.is_termfreq_enabled() {
@@ -241,14 +446,82 @@ impl SegmentReader {
                     }
                 }
             }
+            FieldType::Json(_) => {
+                // the leaves of JSON fields are indexed with their term frequencies.
+                FreqHandler::new_with_freq()
+            }
             _ => {
                 FreqHandler::new_without_freq()
             }
         };
-        Some(SegmentPostings::from_data(term_info.doc_freq, postings_data, &self.delete_bitset, freq_handler))
+        let num_blocks = term_info.doc_freq as usize / NUM_DOCS_PER_BLOCK;
+        let skip_data = if num_blocks > 0 && !freq_handler.has_positions() {
+            self.postings_skip_data(term, offset, num_blocks)
+        }
+        else {
+            &[]
+        };
+        let segment_postings = SegmentPostings::from_data(term_info.doc_freq, postings_data, delete_bitset, freq_handler)
+            .with_skip_data(skip_data);
+        if field_entry.has_payloads() {
+            Some(segment_postings.with_payloads())
+        }
+        else {
+            Some(segment_postings)
+        }
     }
     
 
+    /// Returns the positions of `term` within the document `doc`.
+    ///
+    /// The result is empty if the document does not contain
+    /// the term, or if the term's field does not index positions.
+    pub fn term_positions(&self, doc: DocId, term: &Term) -> Vec<u32> {
+        match self.read_postings(term, SegmentPostingsOption::FreqAndPositions) {
+            Some(mut postings) => {
+                if postings.skip_next(doc) == SkipResult::Reached {
+                    postings.positions().to_vec()
+                }
+                else {
+                    Vec::new()
+                }
+            }
+            None => Vec::new()
+        }
+    }
+
+    /// Returns the largest position of a token of `field`,
+    /// over all of the documents of the segment.
+    ///
+    /// Positions account for the gaps between the values of
+    /// a multivalued field, as well as for the truncation
+    /// to `max_tokens_per_field`.
+    /// All of the postings of the field are scanned,
+    /// so this method is slow and meant for diagnosis.
+    ///
+    /// Returns `None` if the field does not index positions,
+    /// or if none of its tokens were indexed.
+    pub fn max_position(&self, field: Field) -> Option<u32> {
+        match *self.schema.get_field_entry(field).field_type() {
+            FieldType::Str(ref text_options) if text_options.get_indexing_options().is_position_enabled() => {}
+            _ => {
+                return None;
+            }
+        }
+        let mut max_position: Option<u32> = None;
+        for (term_bytes, _) in self.term_infos.prefix_iter(&[field.0]) {
+            let term = Term::from_bytes(&term_bytes);
+            if let Some(mut postings) = self.read_postings(&term, SegmentPostingsOption::FreqAndPositions) {
+                while postings.advance() {
+                    if let Some(&last_position) = postings.positions().last() {
+                        max_position = Some(max_position.map_or(last_position, |max_position| max_position.max(last_position)));
+                    }
+                }
+            }
+        }
+        max_position
+    }
+
     /// Returns the posting list associated with a term.
     ///
     /// If the term is not found, return None.
@@ -264,21 +537,91 @@ impl SegmentReader {
                     _ => SegmentPostingsOption::NoFreq,
                 }
             }
-            FieldType::U32(_) => SegmentPostingsOption::NoFreq
+            FieldType::U32(_) | FieldType::Date(_) => SegmentPostingsOption::NoFreq,
+            FieldType::Json(_) => SegmentPostingsOption::Freq,
         };
         self.read_postings(term, segment_posting_option)
     }
     
+    /// Returns the raw, compressed bytes of the posting list
+    /// associated with the term, or `None` if the term does not exist.
+    ///
+    /// Doc ids are encoded relatively to the start of the posting list,
+    /// so the bytes are self-contained : together with the term `doc_freq`
+    /// and its `raw_positions_bytes`, they can be copied verbatim into
+    /// another segment using `PostingsSerializer::write_raw_postings`.
+    ///
+    /// Deleted documents are not filtered out.
+    pub fn raw_postings_bytes(&self, term: &Term) -> Option<&[u8]> {
+        let term_info = get!(self.get_term_info(term));
+        let start = term_info.postings_offset as usize;
+        let stop = self.term_infos
+            .get_next(term.as_slice())
+            .map(|next_term_info| next_term_info.postings_offset as usize)
+            .unwrap_or(self.postings_data.len());
+        Some(&self.postings_data.as_slice()[start..stop])
+    }
+
+    // the skip entries of the full blocks of a posting list
+    // are written right after it, see `PostingsSerializer::close_term`.
+    fn postings_skip_data(&self, term: &Term, start: usize, num_blocks: usize) -> &[u8] {
+        let stop = self.term_infos
+            .get_next(term.as_slice())
+            .map(|next_term_info| next_term_info.postings_offset as usize)
+            .unwrap_or(self.postings_data.len());
+        let skip_len = num_blocks * SKIP_ENTRY_NUM_BYTES;
+        if stop > self.postings_data.len() || stop < start + skip_len {
+            // corrupted offsets
+            return &[];
+        }
+        &self.postings_data.as_slice()[stop - skip_len..stop]
+    }
+
+    /// Returns the raw, compressed bytes of the positions
+    /// associated with the term, or `None` if the term does not exist.
+    ///
+    /// The result is empty if positions are not indexed for the term's field.
+    ///
+    /// See `.raw_postings_bytes(...)`.
+    pub fn raw_positions_bytes(&self, term: &Term) -> Option<&[u8]> {
+        let term_info = get!(self.get_term_info(term));
+        let start = term_info.positions_offset as usize;
+        let stop = self.term_infos
+            .get_next(term.as_slice())
+            .map(|next_term_info| next_term_info.positions_offset as usize)
+            .unwrap_or(self.positions_data.len());
+        Some(&self.positions_data.as_slice()[start..stop])
+    }
+    
+    /// Returns the raw data of all of the posting lists of the segment.
+    #[doc(hidden)]
+    pub fn postings_data(&self) -> &ReadOnlySource {
+        &self.postings_data
+    }
+
     /// Returns the term info associated with the term.
     pub fn get_term_info(&self, term: &Term) -> Option<TermInfo> {
         self.term_infos.get(term.as_slice())
     }
 
+    /// Returns the schema of the segment.
+    pub fn schema(&self) -> &Schema {
+        &self.schema
+    }
+
     /// Returns the segment id
     pub fn segment_id(&self) -> SegmentId {
         self.segment_id
     }
 
+    /// Returns the label of the commit in which the
+    /// segment was published, if any.
+    ///
+    /// See `IndexWriter::commit_with_label`.
+    pub fn label(&self) -> Option<&str> {
+        self.label.as_ref().map(|label| label.as_str())
+    }
+
     /// Returns the bitset representing
     /// the documents that have been deleted.
     pub fn delete_bitset(&self) -> &DeleteBitSet {
@@ -294,6 +637,14 @@ impl SegmentReader {
 }
 
 
+fn decode_fast_field_column(source: ReadOnlySource, max_doc: DocId) -> Vec<u32> {
+    let fast_field_reader = U32FastFieldReader::open(source);
+    let mut column = vec!(0u32; max_doc as usize);
+    fast_field_reader.get_range(0, &mut column);
+    column
+}
+
+
 impl fmt::Debug for SegmentReader {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "SegmentReader({:?})", self.segment_id)
@@ -4,6 +4,7 @@ use std::io;
 use std::io::Write;
 use fst;
 use fst::raw::Fst;
+use fst::{IntoStreamer, Streamer};
 
 use directory::ReadOnlySource;
 use common::BinarySerializable;
@@ -92,11 +93,33 @@ impl<V: BinarySerializable> FstMap<V> {
         self.fst_index.keys()
     }
 
+    /// Returns the number of keys of the map.
+    pub fn len(&self,) -> usize {
+        self.fst_index.len()
+    }
+
+    /// Returns a builder for a stream over a range of keys.
+    pub fn range(&self,) -> fst::map::StreamBuilder {
+        self.fst_index.range()
+    }
+
+    /// Returns a builder for a stream over the keys
+    /// matched by an automaton.
+    pub fn search<A: fst::Automaton>(&self, automaton: A) -> fst::map::StreamBuilder<A> {
+        self.fst_index.search(automaton)
+    }
+
     pub fn from_source(source: ReadOnlySource)  -> io::Result<FstMap<V>> {
         let total_len = source.len();
+        if total_len < 4 {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "Term dictionary is truncated"));
+        }
         let length_offset = total_len - 4;
         let mut split_len_buffer: &[u8] = &source.as_slice()[length_offset..];
         let footer_size = try!(u32::deserialize(&mut split_len_buffer)) as  usize;
+        if footer_size > length_offset {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "Term dictionary is truncated"));
+        }
         let split_len = length_offset - footer_size;
         let fst_source = source.slice(0, split_len);
         let values_source = source.slice(split_len, length_offset);
@@ -114,11 +137,67 @@ impl<V: BinarySerializable> FstMap<V> {
         V::deserialize(&mut cursor).expect("Data in FST is corrupted")
     }
 
+    /// Returns true iff the key is in the map.
+    ///
+    /// Contrary to `.get(...)`, the value is not deserialized.
+    pub fn contains_key<K: AsRef<[u8]>>(&self, key: K) -> bool {
+        self.fst_index.contains_key(key)
+    }
+
     pub fn get<K: AsRef<[u8]>>(&self, key: K) -> Option<V> {
         self.fst_index
             .get(key)
             .map(|offset| self.read_value(offset))
     }
+
+    /// Iterates over the keys starting with `prefix`, in
+    /// lexicographic order, together with their value.
+    pub fn prefix_iter<'a>(&'a self, prefix: &[u8]) -> FstMapIter<'a, V> {
+        let stream = self.fst_index
+            .range()
+            .ge(prefix)
+            .into_stream();
+        FstMapIter {
+            stream: stream,
+            fst_map: self,
+            prefix: prefix.to_vec(),
+        }
+    }
+
+    /// Returns the value associated to the smallest key
+    /// strictly greater than `key`.
+    pub fn get_next<K: AsRef<[u8]>>(&self, key: K) -> Option<V> {
+        let mut stream = self.fst_index
+            .range()
+            .gt(key)
+            .into_stream();
+        stream
+            .next()
+            .map(|(_, offset)| self.read_value(offset))
+    }
+}
+
+/// Iterator over the (key, value) pairs of an `FstMap`
+/// whose key starts with a given prefix.
+///
+/// See `FstMap::prefix_iter`.
+pub struct FstMapIter<'a, V: 'a + BinarySerializable> {
+    stream: fst::map::Stream<'a>,
+    fst_map: &'a FstMap<V>,
+    prefix: Vec<u8>,
+}
+
+impl<'a, V: BinarySerializable> Iterator for FstMapIter<'a, V> {
+    type Item = (Vec<u8>, V);
+
+    fn next(&mut self,) -> Option<(Vec<u8>, V)> {
+        match self.stream.next() {
+            Some((key, offset)) if key.starts_with(&self.prefix) => {
+                Some((key.to_vec(), self.fst_map.read_value(offset)))
+            }
+            _ => None,
+        }
+    }
 }
 
 #[cfg(test)]
@@ -143,10 +222,16 @@ mod tests {
         let fstmap: FstMap<u32> = FstMap::from_source(source).unwrap();
         assert_eq!(fstmap.get("abc"), Some(34u32));
         assert_eq!(fstmap.get("abcd"), Some(346u32));
+        assert!(fstmap.contains_key("abc"));
+        assert!(!fstmap.contains_key("ab"));
         let mut keys = fstmap.keys();
         assert_eq!(keys.next().unwrap(), "abc".as_bytes());
         assert_eq!(keys.next().unwrap(), "abcd".as_bytes());
         assert_eq!(keys.next(), None);
+        let prefixed: Vec<(Vec<u8>, u32)> = fstmap.prefix_iter(b"abc").collect();
+        assert_eq!(prefixed, vec!((b"abc".to_vec(), 34u32), (b"abcd".to_vec(), 346u32)));
+        assert_eq!(fstmap.prefix_iter(b"abcd").count(), 1);
+        assert_eq!(fstmap.prefix_iter(b"b").count(), 0);
     }
 
 }
@@ -1,17 +1,76 @@
 use std::iter;
+use std::mem;
 use std::marker::PhantomData;
+use std::hash::{Hasher, BuildHasher, BuildHasherDefault};
+use byteorder::{ByteOrder, LittleEndian};
 use super::heap::{Heap, HeapAllocable, BytesRef};
 
 
+const FX_SEED: u64 = 0x51_7c_c1_b7_27_22_0a_95;
 
+/// `FxHasher` is the fast, non-cryptographic hash function
+/// used in `rustc` and `Firefox`.
+///
+/// It is not resistant to hash flooding.
+#[derive(Default, Clone, Copy)]
+pub struct FxHasher {
+    hash: u64,
+}
+
+impl FxHasher {
+    #[inline]
+    fn add_to_hash(&mut self, word: u64) {
+        self.hash = (self.hash.rotate_left(5) ^ word).wrapping_mul(FX_SEED);
+    }
+}
+
+impl Hasher for FxHasher {
+    #[inline]
+    fn write(&mut self, mut bytes: &[u8]) {
+        while bytes.len() >= 8 {
+            self.add_to_hash(LittleEndian::read_u64(bytes));
+            bytes = &bytes[8..];
+        }
+        if !bytes.is_empty() {
+            let mut word = 0u64;
+            for (i, &b) in bytes.iter().enumerate() {
+                word |= (b as u64) << (i * 8);
+            }
+            self.add_to_hash(word);
+        }
+    }
 
-/// dbj2 hash function
-fn djb2(key: &[u8]) -> u64 {
-    let mut state: u64 = 5381; 
-    for &b in key {
-        state = (state << 5).wrapping_add(state).wrapping_add(b as u64);
+    #[inline]
+    fn finish(&self) -> u64 {
+        self.hash
+    }
+}
+
+/// Builds `FxHasher`s.
+pub type FxBuildHasher = BuildHasherDefault<FxHasher>;
+
+
+/// Hash function used to index the terms
+/// of the segment being written.
+///
+/// The terms are sorted before being serialized, so that
+/// the choice of the hash function does not affect
+/// the resulting segment.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TermHashFunction {
+    /// FxHash is fast, but an adversary controlling
+    /// the indexed text can craft terms that
+    /// collide and degrade indexing performance.
+    FxHash,
+    /// SipHash with random keys is slower, but
+    /// resistant to hash flooding.
+    SipHash,
+}
+
+impl Default for TermHashFunction {
+    fn default() -> TermHashFunction {
+        TermHashFunction::FxHash
     }
-    state
 }
 
 impl Default for BytesRef {
@@ -57,17 +116,27 @@ pub enum Entry {
 /// the computation of the hash of the key twice,
 /// or copying the key as long as there is no insert.
 ///
-pub struct HashMap<'a, V> where V: HeapAllocable {
+/// The hash function is defined by the `BuildHasher` type parameter,
+/// and defaults to `FxHash`.
+pub struct HashMap<'a, V, H = FxBuildHasher> where V: HeapAllocable, H: BuildHasher {
     table: Box<[KeyValue]>,
     heap: &'a Heap,
     _phantom: PhantomData<V>,
+    hash_builder: H,
+    hash_shift: u32,
     mask: usize,
     occupied: Vec<usize>,
 }
 
 impl<'a, V> HashMap<'a, V> where V: HeapAllocable {
-
     pub fn new(num_bucket_power_of_2: usize, heap: &'a Heap) -> HashMap<'a, V> {
+        HashMap::with_hasher(num_bucket_power_of_2, heap, FxBuildHasher::default())
+    }
+}
+
+impl<'a, V, H> HashMap<'a, V, H> where V: HeapAllocable, H: BuildHasher {
+
+    pub fn with_hasher(num_bucket_power_of_2: usize, heap: &'a Heap, hash_builder: H) -> HashMap<'a, V, H> {
         let table_size = 1 << num_bucket_power_of_2;
         let table: Vec<KeyValue> = iter::repeat(KeyValue::default())
             .take(table_size)
@@ -76,21 +145,65 @@ impl<'a, V> HashMap<'a, V> where V: HeapAllocable {
             table: table.into_boxed_slice(),
             heap: heap,
             _phantom: PhantomData,
+            hash_builder: hash_builder,
+            hash_shift: 64 - num_bucket_power_of_2 as u32,
             mask: table_size - 1,
             occupied: Vec::with_capacity(table_size / 2),
         }
     }
 
+    // The bucket is taken from the high bits of the hash,
+    // as the low bits of `FxHash` only depend on
+    // the first bytes of the key.
     #[inline]
     fn bucket(&self, key: &[u8]) -> usize {
-        let hash: u64 = djb2(key);
-        (hash as usize) & self.mask
+        let mut hasher = self.hash_builder.build_hasher();
+        hasher.write(key);
+        (hasher.finish() >> self.hash_shift) as usize
     }
 
     fn get_key(&self, bytes_ref: BytesRef) -> &[u8] {
         self.heap.get_slice(bytes_ref)
     }
 
+    /// Returns the number of keys in the `HashMap`.
+    pub fn len(&self,) -> usize {
+        self.occupied.len()
+    }
+
+    /// Returns the number of keys the `HashMap` was sized for:
+    /// half of its buckets, to keep the probe sequences short.
+    ///
+    /// Beyond that, the table keeps working, but grows
+    /// outside of the heap.
+    pub fn capacity(&self,) -> usize {
+        (self.mask + 1) / 2
+    }
+
+    // Doubles the number of buckets, so that
+    // the probe sequences always end on an empty bucket.
+    fn grow(&mut self) {
+        let table_size = (self.mask + 1) * 2;
+        let old_table = mem::replace(&mut self.table, iter::repeat(KeyValue::default())
+            .take(table_size)
+            .collect::<Vec<KeyValue>>()
+            .into_boxed_slice());
+        self.hash_shift -= 1;
+        self.mask = table_size - 1;
+        for bucket in self.occupied.iter_mut() {
+            let kv = old_table[*bucket];
+            let key_bytes = self.heap.get_slice(kv.key);
+            let mut hasher = self.hash_builder.build_hasher();
+            hasher.write(key_bytes);
+            let mut new_bucket = (hasher.finish() >> self.hash_shift) as usize;
+            while !self.table[new_bucket].is_empty() {
+                new_bucket = (new_bucket + 1) & self.mask;
+            }
+            self.table[new_bucket] = kv;
+            *bucket = new_bucket;
+        }
+    }
+
     pub fn set_bucket(&mut self, key_bytes: &[u8], bucket: usize, addr: u32) -> u32 {
         self.occupied.push(bucket);
         self.table[bucket] = KeyValue {
@@ -131,7 +244,14 @@ impl<'a, V> HashMap<'a, V> where V: HeapAllocable {
             Entry::Occupied(addr) => {
                 self.heap.get_mut_ref(addr)
             }
-            Entry::Vacant(bucket) => {
+            Entry::Vacant(mut bucket) => {
+                if (self.occupied.len() + 1) * 4 > (self.mask + 1) * 3 {
+                    self.grow();
+                    bucket = match self.lookup(key.as_ref()) {
+                        Entry::Vacant(bucket) => bucket,
+                        Entry::Occupied(_) => unreachable!(),
+                    };
+                }
                 let (addr, val): (u32, &mut V) = self.heap.allocate_object();
                 self.set_bucket(key.as_ref(), bucket, addr);
                 val
@@ -161,10 +281,9 @@ mod tests {
     
     use super::*;
     use super::super::heap::{Heap, HeapAllocable};
-    use super::djb2;
     use test::Bencher;
-    use std::collections::hash_map::DefaultHasher;
-    use std::hash::Hasher;
+    use std::collections::hash_map::{DefaultHasher, RandomState};
+    use std::hash::{Hasher, BuildHasher};
 
     struct TestValue {
         val: u32,
@@ -211,11 +330,51 @@ mod tests {
         assert!(!iter_values.next().is_some());
     }
 
+    #[test]
+    fn test_hash_map_grows() {
+        let heap = Heap::with_capacity(2_000_000);
+        let mut hash_map: HashMap<TestValue> = HashMap::new(4, &heap);
+        assert_eq!(hash_map.capacity(), 8);
+        for i in 0..100u32 {
+            hash_map.get_or_create(format!("key{}", i)).val = i;
+        }
+        assert_eq!(hash_map.len(), 100);
+        assert!(hash_map.capacity() >= 100);
+        for i in 0..100u32 {
+            assert_eq!(hash_map.get_or_create(format!("key{}", i)).val, i);
+        }
+        assert_eq!(hash_map.len(), 100);
+    }
+
+    #[test]
+    fn test_hash_map_siphash() {
+        let heap = Heap::with_capacity(2_000_000);
+        let mut hash_map: HashMap<TestValue, RandomState> = HashMap::with_hasher(18, &heap, RandomState::new());
+        hash_map.get_or_create("abc").val = 3u32;
+        hash_map.get_or_create("abcd").val = 4u32;
+        assert_eq!(hash_map.get_or_create("abc").val, 3u32);
+        assert_eq!(hash_map.get_or_create("abcd").val, 4u32);
+    }
+
+    #[test]
+    fn test_fxhasher() {
+        let hash = |bytes: &[u8]| {
+            let mut hasher = FxHasher::default();
+            hasher.write(bytes);
+            hasher.finish()
+        };
+        assert_eq!(hash(b"abwer"), hash(b"abwer"));
+        assert!(hash(b"abwer") != hash(b"abwes"));
+        assert!(hash(b"abcdefgh_1") != hash(b"abcdefgh_2"));
+    }
+
     #[bench]
-    fn bench_djb2(bench: &mut Bencher) {
+    fn bench_fxhash(bench: &mut Bencher) {
         let v = String::from("abwer");
         bench.iter(|| {
-            djb2(v.as_bytes())
+            let mut h = FxHasher::default();
+            h.write(v.as_bytes());
+            h.finish()
         });
     }
 
@@ -229,5 +388,27 @@ mod tests {
         });
     }
 
+    fn bench_hash_map_insert<H: BuildHasher + Clone>(bench: &mut Bencher, hash_builder: H) {
+        let keys: Vec<String> = (0..10_000).map(|i| format!("term{}", i * 7)).collect();
+        let heap = Heap::with_capacity(20_000_000);
+        bench.iter(|| {
+            heap.clear();
+            let mut hash_map: HashMap<TestValue, H> = HashMap::with_hasher(15, &heap, hash_builder.clone());
+            for key in &keys {
+                hash_map.get_or_create(key).val += 1;
+            }
+        });
+    }
+
+    #[bench]
+    fn bench_hash_map_insert_fxhash(bench: &mut Bencher) {
+        bench_hash_map_insert(bench, FxBuildHasher::default());
+    }
+
+    #[bench]
+    fn bench_hash_map_insert_siphash(bench: &mut Bencher) {
+        bench_hash_map_insert(bench, RandomState::new());
+    }
+
 }
 
@@ -4,7 +4,7 @@ mod expull;
 
 pub use self::heap::{Heap, HeapAllocable};
 pub use self::expull::ExpUnrolledLinkedList;
-pub use self::hashmap::{HashMap, Entry};
+pub use self::hashmap::{HashMap, Entry, TermHashFunction, FxHasher, FxBuildHasher};
 
 
 
@@ -32,6 +32,10 @@ pub trait Directory: fmt::Debug + Send + Sync + 'static {
     ///
     /// Removing a file will not affect an eventual
     /// existing ReadOnlySource pointing to it.
+    ///
+    /// Implementations may defer the physical removal
+    /// of the file until no `ReadOnlySource` is pointing to it,
+    /// but the file should not be visible anymore.
     /// 
     /// Removing a nonexistent file, yields a
     /// `FileError::DoesNotExist`.
@@ -40,6 +44,14 @@ pub trait Directory: fmt::Debug + Send + Sync + 'static {
     /// Returns true iff the file exists
     fn exists(&self, path: &Path) -> bool;
 
+    /// Returns the length of a file, in bytes.
+    ///
+    /// By default, the file is opened for read. Implementations
+    /// should override it if opening a file is costly.
+    fn file_len(&self, path: &Path) -> result::Result<u64, FileError> {
+        self.open_read(path).map(|source| source.len() as u64)
+    }
+
     /// Opens a writer for the *virtual file* associated with 
     /// a Path.
     ///
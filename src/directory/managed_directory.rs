@@ -5,7 +5,7 @@ use std::result;
 use std::io;
 use Directory;
 use std::sync::{Arc, RwLock};
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::io::Write;
 use rustc_serialize::json;
 use core::MANAGED_FILEPATH;
@@ -21,10 +21,43 @@ use Error;
 /// Thanks to this list, it implements a `garbage_collect` method
 /// that removes the files that were created by tantivy and are not
 /// useful anymore.
+///
+/// Files may also be protected from deletion, for as long
+/// as they are being read. (see `protect_file_from_delete`)
 #[derive(Debug)]
 pub struct ManagedDirectory {
     directory: Box<Directory>,
     managed_paths: Arc<RwLock<HashSet<PathBuf>>>,
+    protected_files: Arc<RwLock<HashMap<PathBuf, usize>>>,
+}
+
+/// Protects a file from being deleted by the `ManagedDirectory`.
+///
+/// The file is protected until the `FileProtection` is dropped.
+/// A file may be protected several times, in which case it
+/// is protected until all of its protections are dropped.
+#[derive(Debug)]
+pub struct FileProtection {
+    path: PathBuf,
+    protected_files: Arc<RwLock<HashMap<PathBuf, usize>>>,
+}
+
+impl Drop for FileProtection {
+    fn drop(&mut self) {
+        let mut protected_files = self.protected_files
+            .write()
+            .expect("Protected files lock poisoned");
+        let remove_protection = match protected_files.get_mut(&self.path) {
+            Some(count) => {
+                *count -= 1;
+                *count == 0
+            }
+            None => false,
+        };
+        if remove_protection {
+            protected_files.remove(&self.path);
+        }
+    }
 }
 
 impl ManagedDirectory {
@@ -39,12 +72,14 @@ impl ManagedDirectory {
                 Ok(ManagedDirectory {
                     directory: box directory,
                     managed_paths: Arc::new(RwLock::new(managed_files)),
+                    protected_files: Arc::default(),
                 })
             }
             Err(FileError::FileDoesNotExist(_)) => {
                 Ok(ManagedDirectory {
                     directory: box directory,
                     managed_paths: Arc::default(),
+                    protected_files: Arc::default(),
                 })
             }
             Err(FileError::IOError(e)) => {
@@ -64,26 +99,51 @@ impl ManagedDirectory {
     /// If a file cannot be deleted (for permission reasons for instance)
     /// an error is simply logged, and the file remains in the list of managed
     /// files.
+    ///
+    /// Protected files are not deleted either, but remain in the list
+    /// of managed files, so that they are deleted by a later call.
     pub fn garbage_collect(&mut self, living_files: HashSet<PathBuf>) {
+        let files_to_delete: Vec<PathBuf> = self.managed_paths
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|managed_path| !living_files.contains(*managed_path))
+            .cloned()
+            .collect();
+        self.delete_managed_files(&files_to_delete);
+    }
+
+    /// Deletes the given files.
+    ///
+    /// Only the files created by `tantivy` and that are not
+    /// protected are deleted: the other files are ignored.
+    ///
+    /// As for `garbage_collect`, this method does not panick
+    /// nor returns errors.
+    pub fn delete_managed_files(&mut self, files_to_delete: &[PathBuf]) {
         let mut managed_has_changed: bool = false;
         {
-            let mut files_to_delete = vec!();
+            let protected_files = self.protected_files
+                .read()
+                .expect("Protected files lock poisoned");
             let mut managed_paths_write = self.managed_paths.write().unwrap();
-            for managed_path in managed_paths_write.iter() {
-                if !living_files.contains(managed_path) {
-                    files_to_delete.push(managed_path.clone());
-                }
-            }
             for file_to_delete in files_to_delete {
-                match self.directory.delete(&file_to_delete) {
+                if !managed_paths_write.contains(file_to_delete) {
+                    continue;
+                }
+                if protected_files.contains_key(file_to_delete) {
+                    info!("Not deleting {:?}, as it is still in use.", file_to_delete);
+                    continue;
+                }
+                match self.directory.delete(file_to_delete) {
                     Ok(_) => {
                         info!("Deleted {:?}", file_to_delete);
-                        managed_has_changed |= managed_paths_write.remove(&file_to_delete);
+                        managed_has_changed |= managed_paths_write.remove(file_to_delete);
                     }
                     Err(file_error) => {
                         match file_error {
                             FileError::FileDoesNotExist(_) => {
-                                managed_has_changed |= managed_paths_write.remove(&file_to_delete);
+                                managed_has_changed |= managed_paths_write.remove(file_to_delete);
                             }
                             FileError::IOError(_) => {
                                 if !cfg!(target_os = "windows") {
@@ -104,6 +164,21 @@ impl ManagedDirectory {
         }
     }
 
+    /// Protects a file from being deleted, until the returned
+    /// `FileProtection` is dropped.
+    pub fn protect_file_from_delete(&self, path: &Path) -> FileProtection {
+        let path = path.to_owned();
+        *self.protected_files
+            .write()
+            .expect("Protected files lock poisoned")
+            .entry(path.clone())
+            .or_insert(0) += 1;
+        FileProtection {
+            path: path,
+            protected_files: self.protected_files.clone(),
+        }
+    }
+
     /// Saves the file containing the list of existing files
     /// that were created by tantivy.
     fn save_managed_paths(&mut self,) -> io::Result<()> {
@@ -136,6 +211,15 @@ impl ManagedDirectory {
         }
         Ok(())
     }
+
+    /// Atomically writes a file that is not managed
+    /// by the directory.
+    ///
+    /// The file is never removed by the garbage collection:
+    /// it is up to the caller to delete it.
+    pub fn atomic_write_unmanaged(&mut self, path: &Path, data: &[u8]) -> io::Result<()> {
+        self.directory.atomic_write(path, data)
+    }
 }
 
 impl Directory for ManagedDirectory {
@@ -165,6 +249,10 @@ impl Directory for ManagedDirectory {
     fn exists(&self, path: &Path) -> bool {
         self.directory.exists(path)
     }
+
+    fn file_len(&self, path: &Path) -> result::Result<u64, FileError> {
+        self.directory.file_len(path)
+    }
     
     fn box_clone(&self) -> Box<Directory> {
         box self.clone()
@@ -177,6 +265,7 @@ impl Clone for ManagedDirectory {
         ManagedDirectory {
             directory: self.directory.box_clone(),
             managed_paths: self.managed_paths.clone(),   
+            protected_files: self.protected_files.clone(),
         }
     }
 }
@@ -188,7 +277,7 @@ impl Clone for ManagedDirectory {
 mod tests {
 
     use super::*;
-    use directory::MmapDirectory;
+    use directory::{MmapDirectory, RAMDirectory};
     use std::path::Path;   
     use std::io::Write;
     use tempdir::TempDir;
@@ -246,6 +335,22 @@ mod tests {
         }   
     }
 
+    #[test]
+    fn test_managed_directory_protect_file() {
+        let mut managed_directory = ManagedDirectory::new(RAMDirectory::create()).unwrap();
+        managed_directory.atomic_write(*TEST_PATH1, &vec!(0u8,1u8)).unwrap();
+        let protection = managed_directory.protect_file_from_delete(*TEST_PATH1);
+        let protection_clone = managed_directory.clone().protect_file_from_delete(*TEST_PATH1);
+        managed_directory.garbage_collect(HashSet::new());
+        assert!(managed_directory.exists(*TEST_PATH1));
+        drop(protection);
+        managed_directory.garbage_collect(HashSet::new());
+        assert!(managed_directory.exists(*TEST_PATH1));
+        drop(protection_clone);
+        managed_directory.garbage_collect(HashSet::new());
+        assert!(!managed_directory.exists(*TEST_PATH1));
+    }
+
     #[test]
     fn test_managed_directory_gc_while_mmapped() {
         let tempdir = TempDir::new("index").unwrap();
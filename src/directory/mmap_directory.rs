@@ -74,6 +74,9 @@ pub struct CacheInfo {
 struct MmapCache {
     counters: CacheCounters,
     cache: HashMap<PathBuf, Weak<Mmap>>,
+    // Files that have been deleted while still being mmapped.
+    // They are physically removed once the last reader is gone.
+    pending_deletes: HashMap<PathBuf, Weak<Mmap>>,
     purge_weak_limit: usize,
 }
 
@@ -84,6 +87,7 @@ impl Default for MmapCache {
         MmapCache {
             counters: CacheCounters::default(),
             cache: HashMap::new(),
+            pending_deletes: HashMap::new(),
             purge_weak_limit: STARTING_PURGE_WEAK_LIMIT,
         }
     }
@@ -105,6 +109,25 @@ impl MmapCache {
         }
     }
 
+    /// Removes the files pending deletion that
+    /// are not mmapped anymore.
+    fn purge_pending_deletes(&mut self) {
+        let mut pending_deletes = HashMap::new();
+        mem::swap(&mut pending_deletes, &mut self.pending_deletes);
+        for (full_path, weak_ref) in pending_deletes {
+            if weak_ref.upgrade().is_some() {
+                self.pending_deletes.insert(full_path, weak_ref);
+                continue;
+            }
+            if let Err(e) = fs::remove_file(&full_path) {
+                if e.kind() != io::ErrorKind::NotFound {
+                    error!("Failed to delete {:?}: {:?}", full_path, e);
+                    self.pending_deletes.insert(full_path, weak_ref);
+                }
+            }
+        }
+    }
+
     fn get_info(&mut self) -> CacheInfo {
         self.cleanup();
         let paths: Vec<PathBuf> = self.cache.keys()
@@ -158,12 +181,18 @@ impl MmapCache {
 ///
 /// The Mmap object are cached to limit the 
 /// system calls. 
+///
+/// On Unix, deleting a file unlinks it right away :
+/// existing mmaps remain valid until they are dropped.
+/// On Windows, mmapped files cannot be deleted, so
+/// the deletion of a file that is still mmapped is deferred
+/// until its last reader is gone.
 #[derive(Clone)]
 pub struct MmapDirectory {
     root_path: PathBuf,
     mmap_cache: Arc<RwLock<MmapCache>>,
     _temp_directory: Arc<Option<TempDir>>,
-    
+    defer_deletes: bool,
 }
 
 impl fmt::Debug for MmapDirectory {
@@ -184,7 +213,8 @@ impl MmapDirectory {
         let directory = MmapDirectory {
             root_path: PathBuf::from(tempdir_path),
             mmap_cache: Arc::new(RwLock::new(MmapCache::default())),
-            _temp_directory: Arc::new(Some(tempdir))
+            _temp_directory: Arc::new(Some(tempdir)),
+            defer_deletes: cfg!(windows),
         };
         Ok(directory)
     }
@@ -204,7 +234,8 @@ impl MmapDirectory {
             Ok(MmapDirectory {
                 root_path: PathBuf::from(directory_path),
                 mmap_cache: Arc::new(RwLock::new(MmapCache::default())),
-                _temp_directory: Arc::new(None)
+                _temp_directory: Arc::new(None),
+                defer_deletes: cfg!(windows),
             })
         }
     }
@@ -296,6 +327,10 @@ impl Directory for MmapDirectory {
                 make_io_err(format!("Failed to acquired write lock on mmap cache while reading {:?}", path))
             ))?;
         
+        if mmap_cache.pending_deletes.contains_key(&full_path) {
+            return Err(FileError::FileDoesNotExist(path.to_owned()));
+        }
+
         Ok(mmap_cache.get_mmap(full_path)?
             .map(MmapReadOnly::from)
             .map(ReadOnlySource::Mmap)
@@ -307,6 +342,13 @@ impl Directory for MmapDirectory {
         debug!("Open Write {:?}", path);
         let full_path = self.resolve_path(path);
         
+        self.mmap_cache
+            .write()
+            .map_err(|_| OpenWriteError::IOError(
+                make_io_err(format!("Failed to acquired write lock on mmap cache while writing {:?}", path))
+            ))?
+            .purge_pending_deletes();
+        
         let open_res = OpenOptions::new()
             .write(true)
             .create_new(true)
@@ -342,10 +384,24 @@ impl Directory for MmapDirectory {
             .map_err(|_| 
                  FileError::IOError(make_io_err(format!("Failed to acquired write lock on mmap cache while deleting {:?}", path))))
         );
+        mmap_cache.purge_pending_deletes();
+        if mmap_cache.pending_deletes.contains_key(&full_path) {
+            return Err(FileError::FileDoesNotExist(path.to_owned()));
+        }
         // Removing the entry in the MMap cache.
         // The munmap will appear on Drop,
         // when the last reference is gone.
-        mmap_cache.cache.remove(&full_path);
+        let weak_ref_opt = mmap_cache.cache.remove(&full_path);
+        if self.defer_deletes {
+            if let Some(weak_ref) = weak_ref_opt {
+                if weak_ref.upgrade().is_some() {
+                    // The file is still mmapped by a reader.
+                    // Its deletion is deferred until the last reader is gone.
+                    mmap_cache.pending_deletes.insert(full_path, weak_ref);
+                    return Ok(());
+                }
+            }
+        }
         match fs::remove_file(&full_path) {
             Ok(_) => {
                 self.sync_directory()
@@ -364,7 +420,28 @@ impl Directory for MmapDirectory {
 
     fn exists(&self, path: &Path) -> bool {
         let full_path = self.resolve_path(path);
-        full_path.exists()
+        let is_pending_delete = self.mmap_cache
+            .read()
+            .map(|mmap_cache| mmap_cache.pending_deletes.contains_key(&full_path))
+            .unwrap_or(false);
+        !is_pending_delete && full_path.exists()
+    }
+
+    fn file_len(&self, path: &Path) -> result::Result<u64, FileError> {
+        if !self.exists(path) {
+            return Err(FileError::FileDoesNotExist(path.to_owned()));
+        }
+        let full_path = self.resolve_path(path);
+        fs::metadata(&full_path)
+            .map(|metadata| metadata.len())
+            .map_err(|e| {
+                if e.kind() == io::ErrorKind::NotFound {
+                    FileError::FileDoesNotExist(path.to_owned())
+                }
+                else {
+                    FileError::IOError(e)
+                }
+            })
     }
 
     fn atomic_read(&self, path: &Path) -> Result<Vec<u8>, FileError> {
@@ -430,6 +507,24 @@ mod tests {
         assert_eq!(readonlymap.len(), 0);
     }
 
+    #[test]
+    fn test_file_len() {
+        let mut mmap_directory = MmapDirectory::create_from_tempdir().unwrap();
+        let path = PathBuf::from("test");
+        {
+            let mut w = mmap_directory.open_write(&path).unwrap();
+            w.write_all("abc".as_bytes()).unwrap();
+            w.flush().unwrap();
+        }
+        assert_eq!(mmap_directory.file_len(&path).unwrap(), 3);
+        // the length is read without mmapping the file.
+        let cache_info = mmap_directory.get_cache_info();
+        assert_eq!(cache_info.counters.miss_empty, 0);
+        assert!(cache_info.mmapped.is_empty());
+        mmap_directory.delete(&path).unwrap();
+        assert!(mmap_directory.file_len(&path).is_err());
+    }
+
     #[test]
     fn test_cache() {
         let content = "abc".as_bytes();
@@ -497,4 +592,29 @@ mod tests {
         
     }
 
+    #[test]
+    fn test_deferred_delete() {
+        let mut mmap_directory = MmapDirectory::create_from_tempdir().unwrap();
+        mmap_directory.defer_deletes = true;
+        let path = PathBuf::from("test");
+        let full_path = mmap_directory.resolve_path(&path);
+        {
+            let mut w = mmap_directory.open_write(&path).unwrap();
+            w.write_all(&[1, 2, 3]).unwrap();
+            w.flush().unwrap();
+        }
+        {
+            let read_handle = mmap_directory.open_read(&path).unwrap();
+            mmap_directory.delete(&path).unwrap();
+            // the file is hidden but physically remains until the reader is gone.
+            assert!(!mmap_directory.exists(&path));
+            assert!(full_path.exists());
+            assert!(mmap_directory.open_read(&path).is_err());
+            assert!(mmap_directory.delete(&path).is_err());
+            assert_eq!(read_handle.as_slice(), &[1u8, 2u8, 3u8]);
+        }
+        mmap_directory.delete(Path::new("some_other_path")).unwrap_err();
+        assert!(!full_path.exists());
+    }
+
 }
@@ -15,7 +15,7 @@ pub use self::read_only_source::ReadOnlySource;
 pub use self::directory::Directory;
 pub use self::ram_directory::RAMDirectory;
 pub use self::mmap_directory::MmapDirectory;
-pub use self::managed_directory::ManagedDirectory;
+pub use self::managed_directory::{ManagedDirectory, FileProtection};
 
 /// Synonym of Seek + Write
 pub trait SeekableWrite: Seek + Write {}
@@ -33,6 +33,13 @@ mod tests {
     use super::*;
     use std::path::Path;   
     use std::io::{Write, Seek, SeekFrom};
+    use core::Index;
+    use collector::TopCollector;
+    use postings::SegmentPostingsOption;
+    use query::{BooleanQuery, Occur, Query, TermQuery};
+    use schema::{Document, FieldValue, SchemaBuilder, Term, STORED, TEXT};
+    use DocAddress;
+    use Score;
 
     lazy_static! {
         static ref TEST_PATH: &'static Path = Path::new("some_path_for_test");
@@ -128,31 +135,78 @@ mod tests {
             let read_handle = directory.open_read(*TEST_PATH).unwrap();
             {
                 assert_eq!(&*read_handle, &[1u8, 2u8, 3u8, 4u8]);
-
-                // Mapped files can't be deleted on Windows
-                if !cfg!(windows) {
-                    assert!(directory.delete(*TEST_PATH).is_ok());
-                    assert_eq!(&*read_handle, &[1u8, 2u8, 3u8, 4u8]);
-                }
-
+                assert!(directory.delete(*TEST_PATH).is_ok());
+                assert!(!directory.exists(*TEST_PATH));
+                assert_eq!(&*read_handle, &[1u8, 2u8, 3u8, 4u8]);
                 assert!(directory.delete(Path::new("SomeOtherPath")).is_err());
             }
         }
 
-        if cfg!(windows) {
-            assert!(directory.delete(*TEST_PATH).is_ok());
-        }
-
         assert!(directory.open_read(*TEST_PATH).is_err());
         assert!(directory.delete(*TEST_PATH).is_err());
     }
 
+    fn test_atomic_write(directory: &mut Directory) {
+        let path = Path::new("some_meta_for_test");
+        assert!(directory.atomic_read(path).is_err());
+        directory.atomic_write(path, b"first").unwrap();
+        assert_eq!(directory.atomic_read(path).unwrap(), b"first");
+        // unlike `open_write`, overwriting is allowed.
+        directory.atomic_write(path, b"second").unwrap();
+        assert_eq!(directory.atomic_read(path).unwrap(), b"second");
+        assert!(directory.exists(path));
+    }
+
     fn test_directory(directory: &mut Directory) {
         test_simple(directory);
         test_seek(directory);
         test_rewrite_forbidden(directory);
         test_write_create_the_file(directory);
         test_directory_delete(directory);
+        test_atomic_write(directory);
+    }
+
+    fn index_and_search(index: Index) -> Vec<(Score, DocAddress, String)> {
+        let text_field = index.schema().get_field("text").unwrap();
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.add_document(doc!(text_field=>"a b c"));
+            index_writer.add_document(doc!(text_field=>"a a d"));
+            index_writer.commit().unwrap();
+            index_writer.add_document(doc!(text_field=>"b d"));
+            index_writer.add_document(doc!(text_field=>"a e"));
+            index_writer.delete_term(Term::from_field_text(text_field, "c"));
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let term_query = |text: &str| -> Box<Query> {
+            box TermQuery::new(Term::from_field_text(text_field, text), SegmentPostingsOption::Freq)
+        };
+        let query = BooleanQuery::from(vec!(
+            (Occur::Should, term_query("a")),
+            (Occur::Should, term_query("d")),
+        ));
+        let mut top_collector = TopCollector::with_limit(10);
+        searcher.search(&query, &mut top_collector).unwrap();
+        top_collector.score_docs()
+            .into_iter()
+            .map(|(score, doc_address)| {
+                let doc = searcher.doc(&doc_address).unwrap();
+                (score, doc_address, doc.get_first(text_field).unwrap().text().to_string())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_same_results_on_ram_and_mmap_directories() {
+        let mut schema_builder = SchemaBuilder::default();
+        schema_builder.add_text_field("text", TEXT | STORED);
+        let schema = schema_builder.build();
+        let ram_results = index_and_search(Index::create_in_ram(schema.clone()));
+        let mmap_results = index_and_search(Index::create_from_tempdir(schema).unwrap());
+        assert_eq!(ram_results.len(), 3);
+        assert_eq!(ram_results, mmap_results);
     }
 
 }
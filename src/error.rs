@@ -38,6 +38,10 @@ pub enum Error {
     ErrorInThread(String),
     /// An Error appeared related to the lack of a field.
     SchemaError(String),
+    /// The index has been frozen and cannot be written to anymore.
+    ///
+    /// See `Index::freeze`.
+    ReadOnlyError,
     
 }
 
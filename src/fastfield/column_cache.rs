@@ -0,0 +1,71 @@
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::mem;
+use schema::Field;
+use core::SegmentReader;
+
+
+/// Cache of fast field columns, fully decoded in memory.
+///
+/// Decoding a column is costly, but accessing a decoded column
+/// is as fast as it gets. The columns are loaded lazily, the first time they
+/// are requested, and shared by all of the `Searcher`s of a
+/// same generation.
+///
+/// Reloading the searchers creates a new, empty, cache.
+#[derive(Default)]
+pub struct FastFieldColumnCache {
+    columns: RwLock<HashMap<(usize, Field), Arc<Vec<u32>>>>,
+    num_loads: AtomicUsize,
+}
+
+impl FastFieldColumnCache {
+
+    /// Returns the column of the fast field `field` for the segment
+    /// `segment_ord`, loading it if it is not in the cache yet.
+    ///
+    /// Returns `None` if the field is not a u32 fast field.
+    pub fn get_or_load(&self, segment_ord: usize, segment_reader: &SegmentReader, field: Field) -> Option<Arc<Vec<u32>>> {
+        let key = (segment_ord, field);
+        if let Some(column) = self.columns.read().unwrap().get(&key) {
+            return Some(column.clone());
+        }
+        let mut columns = self.columns.write().unwrap();
+        // another thread may have loaded the column in the meanwhile.
+        if let Some(column) = columns.get(&key) {
+            return Some(column.clone());
+        }
+        let column = Arc::new(get!(segment_reader.fast_field_column(field)));
+        self.num_loads.fetch_add(1, Ordering::SeqCst);
+        columns.insert(key, column.clone());
+        Some(column)
+    }
+
+    /// Inserts a column loaded beforehand, unless the column
+    /// is already in the cache.
+    ///
+    /// See `Searcher::warm_fast_fields`.
+    pub fn insert(&self, segment_ord: usize, field: Field, column: Vec<u32>) {
+        let mut columns = self.columns.write().unwrap();
+        if !columns.contains_key(&(segment_ord, field)) {
+            self.num_loads.fetch_add(1, Ordering::SeqCst);
+            columns.insert((segment_ord, field), Arc::new(column));
+        }
+    }
+
+    /// Returns the number of columns loaded so far.
+    pub fn num_loads(&self) -> usize {
+        self.num_loads.load(Ordering::SeqCst)
+    }
+
+    /// Returns the number of bytes used by the columns in the cache.
+    pub fn memory_usage(&self) -> usize {
+        self.columns
+            .read()
+            .unwrap()
+            .values()
+            .map(|column| column.len() * mem::size_of::<u32>())
+            .sum()
+    }
+}
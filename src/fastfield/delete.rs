@@ -1,75 +1,418 @@
-use bit_set::BitSet;
 use directory::WritePtr;
 use std::io::Write;
+use std::io::Read;
 use std::io;
+use std::sync::Arc;
 use directory::ReadOnlySource;
 use DocId;
 use common::HasLen;
+use common::BinarySerializable;
+use common::VInt;
 
-pub fn write_delete_bitset(delete_bitset: &BitSet, writer: &mut WritePtr) -> io::Result<()> {
-    let max_doc = delete_bitset.capacity();
-    let mut byte = 0u8;
-    let mut shift = 0u8;
-    for doc in 0..max_doc {
-        if delete_bitset.contains(doc) {
-            byte |= 1 << shift;
+/// Number of 64-bits words of a bitmap container.
+const BITMAP_NUM_WORDS: usize = (1 << 16) / 64;
+
+/// Above this cardinality, an array container
+/// is larger than a bitmap container.
+const ARRAY_MAX_LEN: usize = 4096;
+
+const ARRAY_CONTAINER: u8 = 0;
+const BITMAP_CONTAINER: u8 = 1;
+
+/// First byte of a versioned `.del` file.
+///
+/// Unversioned files start with the number of containers.
+/// This byte is the encoding of 0, so that it is only ever
+/// followed by other bytes in a versioned file.
+const VERSIONED_MARKER: u8 = 128u8;
+
+/// Version of the format of the `.del` files.
+const DELETE_SET_VERSION: u8 = 1;
+
+/// Maximum number of containers of a `DeleteSet`, one per
+/// possible value of the 16 high bits of a doc id.
+const MAX_NUM_CONTAINERS: u64 = 1 << 16;
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// Set of the docs sharing the same 16 high bits,
+/// identified by their 16 low bits.
+#[derive(Clone, Debug)]
+enum Container {
+    /// Sorted doc ids. Used for sparse containers.
+    Array(Vec<u16>),
+    /// One bit per doc id. Used for dense containers.
+    Bitmap(Vec<u64>),
+}
+
+impl Container {
+
+    fn contains(&self, low: u16) -> bool {
+        match *self {
+            Container::Array(ref vals) => vals.binary_search(&low).is_ok(),
+            Container::Bitmap(ref words) => {
+                words[(low / 64) as usize] & (1u64 << (low % 64)) != 0
+            }
+        }
+    }
+
+    /// Returns true iff the value was not already in the container.
+    fn insert(&mut self, low: u16) -> bool {
+        let to_bitmap = match *self {
+            Container::Array(ref mut vals) => {
+                match vals.binary_search(&low) {
+                    Ok(_) => { return false; }
+                    Err(pos) => { vals.insert(pos, low); }
+                }
+                vals.len() > ARRAY_MAX_LEN
+            }
+            Container::Bitmap(ref mut words) => {
+                let word = &mut words[(low / 64) as usize];
+                let mask = 1u64 << (low % 64);
+                if *word & mask != 0 {
+                    return false;
+                }
+                *word |= mask;
+                return true;
+            }
+        };
+        if to_bitmap {
+            let mut words = vec![0u64; BITMAP_NUM_WORDS];
+            if let Container::Array(ref vals) = *self {
+                for &val in vals {
+                    words[(val / 64) as usize] |= 1u64 << (val % 64);
+                }
+            }
+            *self = Container::Bitmap(words);
         }
-        if shift == 7 {
-            writer.write_all(&[byte])?;
-            shift = 0;
-            byte = 0;
+        true
+    }
+
+    fn len(&self) -> usize {
+        match *self {
+            Container::Array(ref vals) => vals.len(),
+            Container::Bitmap(ref words) => {
+                words.iter().map(|word| word.count_ones() as usize).sum()
+            }
         }
-        else {
-            shift += 1;
+    }
+
+    fn vals<'a>(&'a self) -> Box<Iterator<Item=u16> + 'a> {
+        match *self {
+            Container::Array(ref vals) => box vals.iter().cloned(),
+            Container::Bitmap(ref words) => {
+                let vals = (0..BITMAP_NUM_WORDS)
+                    .flat_map(move |i| {
+                        let word = words[i];
+                        (0..64u16)
+                            .filter(move |&bit| word & (1u64 << bit) != 0)
+                            .map(move |bit| (i as u16) * 64 + bit)
+                    });
+                box vals
+            }
+        }
+    }
+}
+
+impl BinarySerializable for Container {
+    fn serialize(&self, writer: &mut Write) -> io::Result<usize> {
+        match *self {
+            Container::Array(ref vals) => {
+                Ok(try!(ARRAY_CONTAINER.serialize(writer)) + try!(vals.serialize(writer)))
+            }
+            Container::Bitmap(ref words) => {
+                Ok(try!(BITMAP_CONTAINER.serialize(writer)) + try!(words.serialize(writer)))
+            }
         }
     }
-    if max_doc % 8 > 0 {
-        writer.write_all(&[byte])?;
+
+    // The lengths are checked before anything is allocated,
+    // and the values of an array container must be sorted.
+    fn deserialize(reader: &mut Read) -> io::Result<Container> {
+        match try!(u8::deserialize(reader)) {
+            ARRAY_CONTAINER => {
+                let num_vals = try!(VInt::deserialize(reader)).val();
+                if num_vals == 0 || num_vals > ARRAY_MAX_LEN as u64 {
+                    return Err(invalid_data("Invalid array container length"));
+                }
+                let mut vals: Vec<u16> = Vec::with_capacity(num_vals as usize);
+                for _ in 0..num_vals {
+                    let val = try!(u16::deserialize(reader));
+                    if vals.last().map(|&previous_val| previous_val >= val).unwrap_or(false) {
+                        return Err(invalid_data("Unsorted array container"));
+                    }
+                    vals.push(val);
+                }
+                Ok(Container::Array(vals))
+            }
+            BITMAP_CONTAINER => {
+                let num_words = try!(VInt::deserialize(reader)).val();
+                if num_words != BITMAP_NUM_WORDS as u64 {
+                    return Err(invalid_data("Invalid bitmap container"));
+                }
+                let mut words: Vec<u64> = Vec::with_capacity(BITMAP_NUM_WORDS);
+                for _ in 0..BITMAP_NUM_WORDS {
+                    words.push(try!(u64::deserialize(reader)));
+                }
+                Ok(Container::Bitmap(words))
+            }
+            _ => Err(invalid_data("Unknown container type")),
+        }
+    }
+}
+
+
+/// Set of deleted doc ids.
+///
+/// Doc ids are split in chunks of 2^16 docs sharing the same
+/// high bits, in the manner of roaring bitmaps.
+/// Each chunk is stored either as a sorted array of doc ids
+/// when it is sparse, or as a bitmap when it has more
+/// than 4096 docs.
+///
+/// This is the format of the `.del` file of a segment,
+/// preceded by a version header. See `write_delete_set`.
+#[derive(Clone, Debug, Default)]
+pub struct DeleteSet {
+    // sorted by key
+    containers: Vec<(u16, Container)>,
+    len: usize,
+}
+
+impl DeleteSet {
+
+    /// Creates an empty `DeleteSet`.
+    pub fn new() -> DeleteSet {
+        DeleteSet::default()
+    }
+
+    fn container(&self, key: u16) -> Option<&Container> {
+        self.containers
+            .binary_search_by_key(&key, |&(container_key, _)| container_key)
+            .ok()
+            .map(|pos| &self.containers[pos].1)
+    }
+
+    /// Marks a document as deleted.
+    ///
+    /// Returns true iff the document was not already deleted.
+    pub fn insert(&mut self, doc: DocId) -> bool {
+        let key = (doc >> 16) as u16;
+        let pos = match self.containers.binary_search_by_key(&key, |&(container_key, _)| container_key) {
+            Ok(pos) => pos,
+            Err(pos) => {
+                self.containers.insert(pos, (key, Container::Array(Vec::new())));
+                pos
+            }
+        };
+        let inserted = self.containers[pos].1.insert(doc as u16);
+        if inserted {
+            self.len += 1;
+        }
+        inserted
+    }
+
+    /// Returns true iff the document is deleted.
+    pub fn contains(&self, doc: DocId) -> bool {
+        self.container((doc >> 16) as u16)
+            .map(|container| container.contains(doc as u16))
+            .unwrap_or(false)
+    }
+
+    /// Iterates over the deleted documents, in increasing order.
+    pub fn iter<'a>(&'a self) -> impl Iterator<Item=DocId> + 'a {
+        self.containers
+            .iter()
+            .flat_map(|&(key, ref container)| {
+                let high = (key as DocId) << 16;
+                container.vals().map(move |low| high | low as DocId)
+            })
+    }
+
+    // Returns the highest deleted document, if any.
+    fn last_doc(&self) -> Option<DocId> {
+        self.containers
+            .last()
+            .and_then(|&(key, ref container)| {
+                container.vals().last().map(|low| ((key as DocId) << 16) | low as DocId)
+            })
+    }
+
+    /// Returns the number of containers stored as a sorted array.
+    pub fn num_array_containers(&self) -> usize {
+        self.containers
+            .iter()
+            .filter(|&&(_, ref container)| match *container {
+                Container::Array(_) => true,
+                Container::Bitmap(_) => false,
+            })
+            .count()
+    }
+
+    /// Returns the number of containers stored as a bitmap.
+    pub fn num_bitmap_containers(&self) -> usize {
+        self.containers.len() - self.num_array_containers()
+    }
+
+    /// Serializes the set.
+    pub fn serialize(&self, writer: &mut Write) -> io::Result<usize> {
+        self.containers.serialize(writer)
     }
+
+    /// Deserializes a set written with `serialize`.
+    ///
+    /// Returns an `InvalidData` error if the containers are
+    /// not sorted, or if their length is not valid.
+    pub fn deserialize(reader: &mut Read) -> io::Result<DeleteSet> {
+        let num_containers = try!(VInt::deserialize(reader)).val();
+        if num_containers > MAX_NUM_CONTAINERS {
+            return Err(invalid_data("Invalid number of containers"));
+        }
+        let mut containers: Vec<(u16, Container)> = Vec::new();
+        for _ in 0..num_containers {
+            let key = try!(u16::deserialize(reader));
+            if containers.last().map(|&(previous_key, _)| previous_key >= key).unwrap_or(false) {
+                return Err(invalid_data("Unsorted containers"));
+            }
+            let container = try!(Container::deserialize(reader));
+            containers.push((key, container));
+        }
+        let len = containers
+            .iter()
+            .map(|&(_, ref container)| container.len())
+            .sum();
+        Ok(DeleteSet {
+            containers: containers,
+            len: len,
+        })
+    }
+}
+
+impl HasLen for DeleteSet {
+    fn len(&self) -> usize {
+        self.len
+    }
+}
+
+/// Writes a `.del` file.
+///
+/// The set is preceded by a marker byte and by
+/// the version of the format.
+pub fn write_delete_set(delete_set: &DeleteSet, writer: &mut WritePtr) -> io::Result<()> {
+    VERSIONED_MARKER.serialize(writer)?;
+    DELETE_SET_VERSION.serialize(writer)?;
+    delete_set.serialize(writer)?;
     writer.flush()
 }
 
+/// Reads a `.del` file written with one bit per document,
+/// the lowest bit of each byte first.
+///
+/// This was the format of the `.del` files before `DeleteSet`.
+/// These files have no header, and are recognized by their length,
+/// which depends on `max_doc` only, and by their number of bits set,
+/// which is the number of deleted docs of the segment.
+///
+/// Returns `None` if `data` is not such a file.
+fn read_legacy_delete_bitset(data: &[u8], max_doc: DocId, num_deleted_docs: DocId) -> Option<DeleteSet> {
+    let max_doc = max_doc as usize;
+    // the bitset was written up to its capacity, which
+    // is a whole number of blocks of at most 64 bits.
+    let min_len = (max_doc + 7) / 8;
+    let max_len = (max_doc + 63) / 64 * 8;
+    if data.len() < min_len || data.len() > max_len {
+        return None;
+    }
+    let num_bits_set: usize = data
+        .iter()
+        .map(|b| b.count_ones() as usize)
+        .sum();
+    if num_bits_set != num_deleted_docs as usize {
+        return None;
+    }
+    let mut delete_set = DeleteSet::new();
+    for (byte_offset, &b) in data.iter().enumerate() {
+        for shift in 0..8 {
+            if b & (1u8 << shift) != 0 {
+                let doc = byte_offset * 8 + shift;
+                if doc >= max_doc {
+                    return None;
+                }
+                delete_set.insert(doc as DocId);
+            }
+        }
+    }
+    Some(delete_set)
+}
+
+/// Reads a `.del` file, in any of its formats.
+///
+/// `max_doc` and `num_deleted_docs` are those of the segment meta.
+/// They are used to recognize the format, and the resulting
+/// set is checked against them.
+fn read_delete_set(data: &[u8], max_doc: DocId, num_deleted_docs: DocId) -> io::Result<DeleteSet> {
+    if let Some(delete_set) = read_legacy_delete_bitset(data, max_doc, num_deleted_docs) {
+        return Ok(delete_set);
+    }
+    let mut reader: &[u8] =
+        if data.len() > 1 && data[0] == VERSIONED_MARKER {
+            let version = data[1];
+            if version != DELETE_SET_VERSION {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, format!("Unsupported delete file version {}", version)));
+            }
+            &data[2..]
+        }
+        else {
+            data
+        };
+    let delete_set = try!(DeleteSet::deserialize(&mut reader));
+    if !reader.is_empty() {
+        return Err(invalid_data("Trailing bytes in delete file"));
+    }
+    if delete_set.len() != num_deleted_docs as usize {
+        return Err(invalid_data("The delete file does not match the number of deleted docs"));
+    }
+    if delete_set.last_doc().map(|doc| doc >= max_doc).unwrap_or(false) {
+        return Err(invalid_data("The delete file refers to a doc out of the segment"));
+    }
+    Ok(delete_set)
+}
+
 #[derive(Clone)]
 pub struct DeleteBitSet {
-    data: ReadOnlySource,
-    len: usize,  
+    delete_set: Arc<DeleteSet>,
 }
 
 impl DeleteBitSet {
 
-    pub fn open(data: ReadOnlySource) -> DeleteBitSet {
-        let num_deleted: usize = data
-            .as_slice()
-            .iter()
-            .map(|b| b.count_ones() as usize)
-            .sum();
-        DeleteBitSet {
-            data: data,
-            len: num_deleted,
-        }
+    /// Opens the `.del` file of a segment with `max_doc` documents,
+    /// `num_deleted_docs` of which are deleted.
+    pub fn open(data: ReadOnlySource, max_doc: DocId, num_deleted_docs: DocId) -> io::Result<DeleteBitSet> {
+        let delete_set = read_delete_set(data.as_slice(), max_doc, num_deleted_docs)?;
+        Ok(DeleteBitSet {
+            delete_set: Arc::new(delete_set),
+        })
     }
 
     pub fn empty() -> DeleteBitSet {
         DeleteBitSet {
-            data: ReadOnlySource::empty(),
-            len: 0,
+            delete_set: Arc::default(),
         }
     }
 
+    /// Returns the underlying `DeleteSet`.
+    pub fn delete_set(&self) -> &DeleteSet {
+        &self.delete_set
+    }
+
     pub fn has_deletes(&self) -> bool {
         self.len() > 0
     }
 
     pub fn is_deleted(&self, doc: DocId) -> bool {
-        if self.len == 0 {
-            false
-        }
-        else {
-            let byte_offset = doc / 8u32;
-            let b: u8 = (*self.data)[byte_offset as usize];
-            let shift = (doc & 7u32) as u8;
-            b & (1u8 << shift) != 0    
-        }
+        self.delete_set.contains(doc)
     }
 
 }
@@ -77,51 +420,137 @@ impl DeleteBitSet {
 
 impl HasLen for DeleteBitSet {
     fn len(&self) -> usize {
-        self.len
+        self.delete_set.len()
     }
 }
 
 #[cfg(test)]
 mod tests {
     use std::path::PathBuf;
-    use bit_set::BitSet;
+    use std::collections::HashSet;
     use directory::*;
     use super::*;
 
-    fn test_delete_bitset_helper(bitset: &BitSet) {
+    fn test_delete_set_helper(docs: &[DocId], max_doc: DocId) -> DeleteSet {
+        let mut delete_set = DeleteSet::new();
+        for &doc in docs {
+            assert!(delete_set.insert(doc));
+        }
+        assert!(!delete_set.insert(docs[0]));
+        assert_eq!(delete_set.len(), docs.len());
         let test_path = PathBuf::from("test");
         let mut directory = RAMDirectory::create();
         {
             let mut writer = directory.open_write(&*test_path).unwrap();
-            write_delete_bitset(bitset, &mut writer).unwrap();
+            write_delete_set(&delete_set, &mut writer).unwrap();
         }
         {
             let source = directory.open_read(&test_path).unwrap();
-            let delete_bitset = DeleteBitSet::open(source);
-            let n = bitset.capacity();
-            for doc in 0..n {
-                assert_eq!(bitset.contains(doc), delete_bitset.is_deleted(doc as DocId));
+            let delete_bitset = DeleteBitSet::open(source, max_doc, docs.len() as DocId).unwrap();
+            let deleted: HashSet<DocId> = docs.iter().cloned().collect();
+            for doc in 0..max_doc {
+                assert_eq!(deleted.contains(&doc), delete_bitset.is_deleted(doc));
             }
-            assert_eq!(delete_bitset.len(), bitset.len());
+            assert_eq!(delete_bitset.len(), docs.len());
+            let mut sorted_docs = docs.to_vec();
+            sorted_docs.sort();
+            assert_eq!(delete_bitset.delete_set().iter().collect::<Vec<DocId>>(), sorted_docs);
+            assert_eq!(delete_bitset.delete_set().num_array_containers(), delete_set.num_array_containers());
         }
+        delete_set
+    }
+
+    #[test]
+    fn test_delete_set_sparse() {
+        let delete_set = test_delete_set_helper(&[9, 1, 70_000, 200_000, 200_001], 210_000);
+        assert_eq!(delete_set.num_array_containers(), 3);
+        assert_eq!(delete_set.num_bitmap_containers(), 0);
+    }
+
+    #[test]
+    fn test_delete_set_dense() {
+        let docs: Vec<DocId> = (0..100_000u32).filter(|doc| doc % 3 != 0).collect();
+        let delete_set = test_delete_set_helper(&docs, 100_010);
+        assert_eq!(delete_set.num_array_containers(), 0);
+        assert_eq!(delete_set.num_bitmap_containers(), 2);
     }
 
     #[test]
-    fn test_delete_bitset() {
+    fn test_delete_set_versions() {
+        let mut delete_set = DeleteSet::new();
+        delete_set.insert(3);
+        delete_set.insert(70_000);
+        let test_path = PathBuf::from("test");
+        let mut directory = RAMDirectory::create();
         {
-            let mut bitset = BitSet::with_capacity(10);
-            bitset.insert(1);
-            bitset.insert(9);
-            test_delete_bitset_helper(&bitset);
+            let mut writer = directory.open_write(&*test_path).unwrap();
+            write_delete_set(&delete_set, &mut writer).unwrap();
         }
-        {
-            let mut bitset = BitSet::with_capacity(8);
-            bitset.insert(1);
-            bitset.insert(2);
-            bitset.insert(3);
-            bitset.insert(5);
-            bitset.insert(7);
-            test_delete_bitset_helper(&bitset);
+        let data = directory.atomic_read(&*test_path).unwrap();
+        assert_eq!(&data[..2], &[VERSIONED_MARKER, DELETE_SET_VERSION]);
+        // unversioned files are still readable.
+        let mut unversioned_data: Vec<u8> = Vec::new();
+        delete_set.serialize(&mut unversioned_data).unwrap();
+        let mut empty_data: Vec<u8> = Vec::new();
+        DeleteSet::new().serialize(&mut empty_data).unwrap();
+        assert_eq!(empty_data, vec!(VERSIONED_MARKER));
+        for data in &[data, unversioned_data] {
+            let delete_set = read_delete_set(data, 80_000, 2).unwrap();
+            assert_eq!(delete_set.iter().collect::<Vec<DocId>>(), vec!(3, 70_000));
+            // the set must match the segment meta.
+            assert!(read_delete_set(data, 80_000, 3).is_err());
+            assert!(read_delete_set(data, 70_000, 2).is_err());
+        }
+        assert!(read_delete_set(&empty_data, 10, 0).unwrap().iter().next().is_none());
+        assert!(read_delete_set(&[VERSIONED_MARKER, DELETE_SET_VERSION + 1, 128u8], 10, 0).is_err());
+    }
+
+    #[test]
+    fn test_legacy_delete_bitset() {
+        // one bit per doc, lowest bit first, up to the
+        // capacity of the bitset.
+        let mut data = vec![0u8; 4];
+        for &doc in &[1u32, 9, 19] {
+            data[(doc / 8) as usize] |= 1 << (doc % 8);
         }
+        let delete_set = read_delete_set(&data, 20, 3).unwrap();
+        assert_eq!(delete_set.iter().collect::<Vec<DocId>>(), vec!(1, 9, 19));
+        assert!(read_delete_set(&data[..3], 20, 3).unwrap().contains(19));
+        // a bitset starting with the bytes of a versioned header.
+        let legacy_data = [VERSIONED_MARKER, DELETE_SET_VERSION, 0u8, 0u8];
+        let delete_set = read_delete_set(&legacy_data, 32, 2).unwrap();
+        assert_eq!(delete_set.iter().collect::<Vec<DocId>>(), vec!(7, 8));
+        // bits set past max_doc.
+        assert!(read_legacy_delete_bitset(&data, 19, 3).is_none());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_delete_set_invalid() {
+        let mut array_data: Vec<u8> = Vec::new();
+        VInt(1).serialize(&mut array_data).unwrap();
+        0u16.serialize(&mut array_data).unwrap();
+        ARRAY_CONTAINER.serialize(&mut array_data).unwrap();
+        // a huge number of values, that must not be allocated.
+        VInt(1 << 40).serialize(&mut array_data).unwrap();
+        assert!(DeleteSet::deserialize(&mut &array_data[..]).is_err());
+        let mut unsorted_data: Vec<u8> = Vec::new();
+        VInt(1).serialize(&mut unsorted_data).unwrap();
+        0u16.serialize(&mut unsorted_data).unwrap();
+        Container::Array(vec!(5, 3)).serialize(&mut unsorted_data).unwrap();
+        assert!(DeleteSet::deserialize(&mut &unsorted_data[..]).is_err());
+        let mut unsorted_keys_data: Vec<u8> = Vec::new();
+        VInt(2).serialize(&mut unsorted_keys_data).unwrap();
+        2u16.serialize(&mut unsorted_keys_data).unwrap();
+        Container::Array(vec!(3)).serialize(&mut unsorted_keys_data).unwrap();
+        1u16.serialize(&mut unsorted_keys_data).unwrap();
+        Container::Array(vec!(3)).serialize(&mut unsorted_keys_data).unwrap();
+        assert!(DeleteSet::deserialize(&mut &unsorted_keys_data[..]).is_err());
+    }
+
+    #[test]
+    fn test_delete_set_empty() {
+        let delete_bitset = DeleteBitSet::empty();
+        assert!(!delete_bitset.has_deletes());
+        assert!(!delete_bitset.is_deleted(0));
+    }
+}
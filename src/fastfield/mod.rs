@@ -8,16 +8,20 @@
 /// They are useful when a field is required for all or most of
 /// the `DocSet` : for instance for scoring, grouping, filtering, or facetting.
 /// 
-/// Currently only u32 fastfield are supported.
+/// Currently only u32 fastfield are supported. They hold one value
+/// per document, or any number of values if they are multivalued.
 
 mod reader;
 mod writer;
 mod serializer;
+mod column_cache;
 pub mod delete;
 
-pub use self::writer::{U32FastFieldsWriter, U32FastFieldWriter};
-pub use self::reader::{U32FastFieldsReader, U32FastFieldReader};
+pub use self::writer::{U32FastFieldsWriter, U32FastFieldWriter, MultiValuedFastFieldWriter};
+pub use self::reader::{U32FastFieldsReader, U32FastFieldReader, MultiValuedFastFieldReader};
+pub use self::reader::FastFieldWidth;
 pub use self::serializer::FastFieldSerializer;
+pub use self::column_cache::FastFieldColumnCache;
 
 #[cfg(test)]
 mod tests {
@@ -27,12 +31,15 @@ mod tests {
     use directory::{Directory, WritePtr, RAMDirectory};
     use schema::Document;
     use schema::{Schema, SchemaBuilder};
-    use schema::FAST;
+    use schema::{FAST, U32_INDEXED};
+    use schema::FieldValue;
+    use Index;
     use test::Bencher;
     use test;
     use rand::Rng;
     use rand::SeedableRng;
     use rand::XorShiftRng;
+    use futures::Future;
 
     lazy_static! {
         static ref SCHEMA: Schema = {
@@ -59,6 +66,104 @@ mod tests {
         assert_eq!(test_fastfield.get(2), 300); 
     }
 
+    #[test]
+    fn test_fastfield_bits_per_value() {
+        assert_eq!(U32FastFieldReader::from(vec!(100, 200, 300)).bits_per_value(), 8);
+        let small_range = U32FastFieldReader::from(vec!(1_000_000, 1_000_003, 1_000_001));
+        assert_eq!(small_range.bits_per_value(), 2);
+        assert_eq!(small_range.min_val(), 1_000_000);
+        assert_eq!(U32FastFieldReader::from(vec!(7, 7)).bits_per_value(), 0);
+        assert_eq!(U32FastFieldReader::from(vec!(65_535, 0)).bits_per_value(), 16);
+    }
+
+    #[test]
+    fn test_fast_field_widths() {
+        let mut schema_builder = SchemaBuilder::default();
+        let small_field = schema_builder.add_u32_field("small", FAST);
+        let large_field = schema_builder.add_u32_field("large", FAST);
+        schema_builder.add_u32_field("not_fast", U32_INDEXED);
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            for i in 0..10u32 {
+                index_writer.add_document(doc!(small_field=>1_000 + i % 4, large_field=>i * 100_000));
+            }
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let widths = searcher.segment_reader(0).fast_field_widths();
+        assert_eq!(widths, vec!(
+            FastFieldWidth {
+                field: small_field,
+                bits_per_value: 2,
+                min_val: 1_000,
+                max_val: 1_003,
+            },
+            FastFieldWidth {
+                field: large_field,
+                bits_per_value: 20,
+                min_val: 0,
+                max_val: 900_000,
+            }));
+    }
+
+    #[test]
+    fn test_multivalued_fast_field() {
+        let mut schema_builder = SchemaBuilder::default();
+        let tags_field = schema_builder.add_u32_field("tags", FAST);
+        schema_builder.set_multivalued(tags_field);
+        let score_field = schema_builder.add_u32_field("score", FAST);
+        let index = Index::create_in_ram(schema_builder.build());
+        let tags_of = |segment_reader: &::SegmentReader| -> Vec<Vec<u32>> {
+            let tags_reader = segment_reader.get_multivalued_fast_field_reader(tags_field).unwrap();
+            let mut vals = vec!(17u32);
+            (0..segment_reader.max_doc())
+                .map(|doc| {
+                    tags_reader.get_vals(doc, &mut vals);
+                    assert_eq!(tags_reader.num_vals(doc), vals.len());
+                    vals.clone()
+                })
+                .collect()
+        };
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.add_document(doc!(score_field=>1));
+            index_writer.add_document(doc!(tags_field=>7));
+            index_writer.add_document(doc!(tags_field=>30, tags_field=>10, tags_field=>20));
+            index_writer.commit().unwrap();
+            index_writer.add_document(doc!(tags_field=>5, tags_field=>5));
+            index_writer.add_document(doc!(score_field=>2));
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        {
+            let searcher = index.searcher();
+            let segment_reader = searcher.segment_readers()
+                .iter()
+                .find(|segment_reader| segment_reader.max_doc() == 3)
+                .unwrap();
+            assert_eq!(tags_of(segment_reader), vec!(vec!(), vec!(7), vec!(30, 10, 20)));
+            assert!(segment_reader.get_fast_field_reader(tags_field).is_none());
+            assert!(segment_reader.get_multivalued_fast_field_reader(score_field).is_none());
+            assert_eq!(segment_reader.get_fast_field_reader(score_field).unwrap().get(0), 1);
+        }
+        {
+            let segment_ids = index.searchable_segment_ids().unwrap();
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.merge(&segment_ids).wait().unwrap();
+            index_writer.wait_merging_threads().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        assert_eq!(searcher.segment_readers().len(), 1);
+        let merged_tags = tags_of(searcher.segment_reader(0));
+        assert_eq!(merged_tags.len(), 5);
+        let mut sorted_tags = merged_tags.clone();
+        sorted_tags.sort();
+        assert_eq!(sorted_tags, vec!(vec!(), vec!(), vec!(5, 5), vec!(7), vec!(30, 10, 20)));
+    }
+
     #[test]
     fn test_intfastfield_small() {
         let path = Path::new("test");
@@ -190,6 +295,54 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_intfastfield_get_range() {
+        let vals: Vec<u32> = (0u32..1_000u32).map(|i| 1_000 + (i * 7_919) % 3_001).collect();
+        let fast_field_reader = U32FastFieldReader::from(vals.clone());
+        let mut output = vec!(0u32; 300);
+        fast_field_reader.get_range(0, &mut output);
+        assert_eq!(&output[..], &vals[0..300]);
+        fast_field_reader.get_range(700, &mut output);
+        assert_eq!(&output[..], &vals[700..1_000]);
+        for (i, val) in output.iter().enumerate() {
+            assert_eq!(*val, fast_field_reader.get(700 + i as u32));
+        }
+        fast_field_reader.get_range(13, &mut output[..0]);
+    }
+
+    #[bench]
+    fn bench_intfastfield_scan_get(b: &mut Bencher) {
+        let fast_field_reader = U32FastFieldReader::from(generate_permutation());
+        b.iter(|| {
+            let n = test::black_box(100_000u32);
+            let mut a = 0u32;
+            for doc in 0u32..n {
+                a ^= fast_field_reader.get(doc);
+            }
+            a
+        });
+    }
+
+    #[bench]
+    fn bench_intfastfield_scan_get_range(b: &mut Bencher) {
+        let fast_field_reader = U32FastFieldReader::from(generate_permutation());
+        let mut buffer = vec!(0u32; 1_024);
+        b.iter(|| {
+            let n = test::black_box(100_000u32);
+            let mut a = 0u32;
+            let mut start = 0u32;
+            while start < n {
+                let len = ::std::cmp::min(buffer.len() as u32, n - start) as usize;
+                fast_field_reader.get_range(start, &mut buffer[..len]);
+                for val in &buffer[..len] {
+                    a ^= *val;
+                }
+                start += len as u32;
+            }
+            a
+        });
+    }
+
     #[bench]
     fn bench_intfastfield_linear_veclookup(b: &mut Bencher) {
         let permutation = generate_permutation();
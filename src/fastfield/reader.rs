@@ -27,6 +27,7 @@ pub struct U32FastFieldReader {
     bit_unpacker: BitUnpacker,
     min_val: u32,
     max_val: u32,
+    num_bits: u8,
 }
 
 impl U32FastFieldReader {
@@ -43,6 +44,14 @@ impl U32FastFieldReader {
         self.max_val
     }
 
+    /// Returns the number of bits used to encode each value.
+    ///
+    /// Values are encoded as their difference with `min_val()`,
+    /// so the width only depends on the range of the values.
+    pub fn bits_per_value(&self,) -> u8 {
+        self.num_bits
+    }
+
     /// Opens a new fast field reader given a read only source.
     ///
     /// # Panics
@@ -67,12 +76,34 @@ impl U32FastFieldReader {
             bit_unpacker: bit_unpacker,
             min_val: min_val,
             max_val: max_val,
+            num_bits: num_bits,
         }
     }
 
     pub fn get(&self, doc: DocId) -> u32 {
         self.min_val + self.bit_unpacker.get(doc as usize)
     }
+
+    /// Returns the number of values the fast field data can hold.
+    ///
+    /// It is at least the `max_doc` of the segment, unless
+    /// the data is corrupted.
+    pub fn capacity(&self,) -> usize {
+        self.bit_unpacker.capacity()
+    }
+
+    /// Fills `output` with the values of the documents 
+    /// `start..start + output.len()`.
+    ///
+    /// This is faster than calling `.get(...)` for each document, 
+    /// and should be preferred when scanning a contiguous 
+    /// range of documents.
+    pub fn get_range(&self, start: DocId, output: &mut [u32]) {
+        self.bit_unpacker.get_range(start as usize, output);
+        for output_val in output.iter_mut() {
+            *output_val += self.min_val;
+        }
+    }
 }
 
 
@@ -100,12 +131,92 @@ impl From<Vec<u32>> for U32FastFieldReader {
      }
 }
 
+/// Encoding of the fast field of a segment.
+///
+/// See `SegmentReader::fast_field_widths`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FastFieldWidth {
+    /// The fast field.
+    pub field: Field,
+    /// Number of bits used to encode each value.
+    pub bits_per_value: u8,
+    /// Base value, that is the minimum value of the field
+    /// in the segment.
+    pub min_val: u32,
+    /// Maximum value of the field in the segment.
+    pub max_val: u32,
+}
+
+impl FastFieldWidth {
+    /// Describes the encoding of the fast field `field`,
+    /// read by `reader`.
+    pub fn from_reader(field: Field, reader: &U32FastFieldReader) -> FastFieldWidth {
+        FastFieldWidth {
+            field: field,
+            bits_per_value: reader.bits_per_value(),
+            min_val: reader.min_val(),
+            max_val: reader.max_val(),
+        }
+    }
+}
+
+/// Reader of a multivalued u32 fast field.
+///
+/// See `SchemaBuilder::set_multivalued`.
+pub struct MultiValuedFastFieldReader {
+    idx_reader: U32FastFieldReader,
+    vals_reader: U32FastFieldReader,
+}
+
+impl MultiValuedFastFieldReader {
+
+    /// Opens a multivalued fast field reader given
+    /// the data of its `idx` and `vals` columns.
+    ///
+    /// # Panics
+    /// Panics if the data is corrupted.
+    pub fn open(idx_data: ReadOnlySource, vals_data: ReadOnlySource) -> MultiValuedFastFieldReader {
+        MultiValuedFastFieldReader {
+            idx_reader: U32FastFieldReader::open(idx_data),
+            vals_reader: U32FastFieldReader::open(vals_data),
+        }
+    }
+
+    /// Returns the number of values of the document `doc`.
+    pub fn num_vals(&self, doc: DocId) -> usize {
+        (self.idx_reader.get(doc + 1) - self.idx_reader.get(doc)) as usize
+    }
+
+    /// Fills `vals` with the values of the document `doc`,
+    /// in the order they were added to the document.
+    ///
+    /// `vals` is emptied first, and stays empty if
+    /// the document has no value.
+    pub fn get_vals(&self, doc: DocId, vals: &mut Vec<u32>) {
+        let start = self.idx_reader.get(doc);
+        let stop = self.idx_reader.get(doc + 1);
+        vals.clear();
+        vals.resize((stop - start) as usize, 0u32);
+        self.vals_reader.get_range(start, &mut vals[..]);
+    }
+}
+
 pub struct U32FastFieldsReader {
     source: ReadOnlySource,
-    field_offsets: HashMap<Field, (u32, u32)>,
+    // the columns of each field: one for the regular fast fields,
+    // two, `idx` and `vals`, for the multivalued ones.
+    field_offsets: HashMap<Field, Vec<(u32, u32)>>,
 }
 
 impl U32FastFieldsReader {
+    /// Returns a reader without any field.
+    pub fn empty() -> U32FastFieldsReader {
+        U32FastFieldsReader {
+            source: ReadOnlySource::empty(),
+            field_offsets: HashMap::new(),
+        }
+    }
+
     pub fn open(source: ReadOnlySource) -> io::Result<U32FastFieldsReader> {
         let header_offset;
         let field_offsets: Vec<(Field, u32)>;
@@ -125,10 +236,13 @@ impl U32FastFieldsReader {
             .map(|&(_, offset)| offset)
             .collect();
         end_offsets.push(header_offset);
-        let mut field_offsets_map: HashMap<Field, (u32, u32)> = HashMap::new();
+        let mut field_offsets_map: HashMap<Field, Vec<(u32, u32)>> = HashMap::new();
         for (field_start_offsets, stop_offset) in field_offsets.iter().zip(end_offsets.iter().skip(1)) {
             let (field, start_offset) = *field_start_offsets;
-            field_offsets_map.insert(field, (start_offset, *stop_offset));
+            field_offsets_map
+                .entry(field)
+                .or_insert_with(Vec::new)
+                .push((start_offset, *stop_offset));
         }
         Ok(U32FastFieldsReader {
             field_offsets: field_offsets_map,
@@ -145,11 +259,39 @@ impl U32FastFieldsReader {
     /// # Panics
     /// May panic if the index is corrupted.
     pub fn get_field(&self, field: Field) -> Option<U32FastFieldReader> {
-        self.field_offsets
-            .get(&field)
-            .map(|&(start, stop)| {
-                let field_source = self.source.slice(start as usize, stop as usize);
-                U32FastFieldReader::open(field_source)
-            })
+        self.field_source(field).map(U32FastFieldReader::open)
+    }
+
+    /// Returns the data of the fast field `field`, which
+    /// can be opened with `U32FastFieldReader::open`.
+    ///
+    /// Unlike the reader, the data can be sent to another thread.
+    pub fn field_source(&self, field: Field) -> Option<ReadOnlySource> {
+        match self.field_offsets.get(&field) {
+            Some(columns) if columns.len() == 1 => {
+                let (start, stop) = columns[0];
+                Some(self.source.slice(start as usize, stop as usize))
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the reader of the multivalued fast field `field`.
+    ///
+    /// Returns None if the field is not a multivalued fast field.
+    ///
+    /// # Panics
+    /// May panic if the index is corrupted.
+    pub fn get_multivalued_field(&self, field: Field) -> Option<MultiValuedFastFieldReader> {
+        match self.field_offsets.get(&field) {
+            Some(columns) if columns.len() == 2 => {
+                let (idx_start, idx_stop) = columns[0];
+                let (vals_start, vals_stop) = columns[1];
+                Some(MultiValuedFastFieldReader::open(
+                    self.source.slice(idx_start as usize, idx_stop as usize),
+                    self.source.slice(vals_start as usize, vals_stop as usize)))
+            }
+            _ => None,
+        }
     }
 }
@@ -6,6 +6,7 @@ use DocId;
 
 pub struct U32FastFieldsWriter {
     field_writers: Vec<U32FastFieldWriter>,
+    multivalued_field_writers: Vec<MultiValuedFastFieldWriter>,
 }
 
 impl U32FastFieldsWriter {
@@ -17,7 +18,14 @@ impl U32FastFieldsWriter {
             .filter(|&(_, field_entry)| field_entry.is_u32_fast()) 
             .map(|(field_id, _)| Field(field_id as u8))
             .collect();
-        U32FastFieldsWriter::new(u32_fields)
+        let mut fast_fields_writer = U32FastFieldsWriter::new(u32_fields);
+        fast_fields_writer.multivalued_field_writers = schema.fields()
+            .iter()
+            .enumerate()
+            .filter(|&(_, field_entry)| field_entry.is_u32_multivalued_fast())
+            .map(|(field_id, _)| MultiValuedFastFieldWriter::new(Field(field_id as u8)))
+            .collect();
+        fast_fields_writer
     }
 
     pub fn new(fields: Vec<Field>) -> U32FastFieldsWriter {
@@ -26,6 +34,7 @@ impl U32FastFieldsWriter {
                 .into_iter()
                 .map(U32FastFieldWriter::new)
                 .collect(),
+            multivalued_field_writers: Vec::new(),
         }
     }
     
@@ -34,17 +43,29 @@ impl U32FastFieldsWriter {
             .iter_mut()
             .find(|field_writer| field_writer.field == field)
     }
+
+    pub fn get_multivalued_field_writer(&mut self, field: Field) -> Option<&mut MultiValuedFastFieldWriter> {
+        self.multivalued_field_writers
+            .iter_mut()
+            .find(|field_writer| field_writer.field == field)
+    }
     
     pub fn add_document(&mut self, doc: &Document) {
         for field_writer in &mut self.field_writers {
             field_writer.add_document(doc);
         }
+        for multivalued_field_writer in &mut self.multivalued_field_writers {
+            multivalued_field_writer.add_document(doc);
+        }
     }
 
     pub fn serialize(&self, serializer: &mut FastFieldSerializer) -> io::Result<()> {
         for field_writer in &self.field_writers {
             try!(field_writer.serialize(serializer));
         }
+        for multivalued_field_writer in &self.multivalued_field_writers {
+            try!(multivalued_field_writer.serialize(serializer));
+        }
         Ok(())
     }
     
@@ -56,7 +77,9 @@ impl U32FastFieldsWriter {
         for field_writer in &mut self.field_writers {
             field_writer.fill_val_up_to(doc);
         }
-            
+        for multivalued_field_writer in &mut self.multivalued_field_writers {
+            multivalued_field_writer.fill_val_up_to(doc);
+        }
     }
 }
 
@@ -119,3 +142,72 @@ impl U32FastFieldWriter {
         serializer.close_field()
     }
 }
+
+
+/// Writer of a multivalued u32 fast field.
+///
+/// The field is serialized as two u32 fast fields:
+/// - `idx`, the offset of the first value of each document
+/// in `vals`, followed by the total number of values,
+/// - `vals`, the values of all of the documents, one
+/// document after the other.
+pub struct MultiValuedFastFieldWriter {
+    field: Field,
+    doc_index: Vec<u32>,
+    vals: Vec<u32>,
+}
+
+impl MultiValuedFastFieldWriter {
+    pub fn new(field: Field) -> MultiValuedFastFieldWriter {
+        MultiValuedFastFieldWriter {
+            field: field,
+            doc_index: Vec::new(),
+            vals: Vec::new(),
+        }
+    }
+
+    /// Ensures all of the documents up to `doc` (included)
+    /// have been added.
+    ///
+    /// The missing documents have no value.
+    fn fill_val_up_to(&mut self, doc: DocId) {
+        let target = doc as usize + 1;
+        debug_assert!(self.doc_index.len() <= target);
+        while self.doc_index.len() < target {
+            self.add_vals(&[]);
+        }
+    }
+
+    /// Adds a document with the values `vals`.
+    pub fn add_vals(&mut self, vals: &[u32]) {
+        self.doc_index.push(self.vals.len() as u32);
+        self.vals.extend_from_slice(vals);
+    }
+
+    pub fn add_document(&mut self, doc: &Document) {
+        self.doc_index.push(self.vals.len() as u32);
+        for value in doc.get_all(self.field) {
+            match *value {
+                Value::U32(val) => self.vals.push(val),
+                _ => panic!("Expected a u32field, got {:?} ", value),
+            }
+        }
+    }
+
+    pub fn serialize(&self, serializer: &mut FastFieldSerializer) -> io::Result<()> {
+        let num_vals = self.vals.len() as u32;
+        try!(serializer.new_u32_fast_field(self.field, 0, num_vals));
+        for &offset in self.doc_index.iter().chain(Some(&num_vals)) {
+            try!(serializer.add_val(offset));
+        }
+        try!(serializer.close_field());
+        let zero = 0;
+        let min = *self.vals.iter().min().unwrap_or(&zero);
+        let max = *self.vals.iter().max().unwrap_or(&min);
+        try!(serializer.new_u32_fast_field(self.field, min, max));
+        for &val in &self.vals {
+            try!(serializer.add_val(val));
+        }
+        serializer.close_field()
+    }
+}
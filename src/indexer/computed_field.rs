@@ -0,0 +1,38 @@
+use schema::Document;
+use schema::Field;
+
+
+/// A u32 field whose value is computed at indexing time,
+/// from the other fields of the document.
+///
+/// This is typically useful to sort on a denormalized value,
+/// e.g. a combination of a popularity and a recency.
+///
+/// See `IndexWriter::add_computed_field`.
+pub struct ComputedField {
+    field: Field,
+    compute: Box<Fn(&Document) -> u32>,
+}
+
+impl ComputedField {
+
+    /// Creates a computed field, populating `field`
+    /// with the value returned by `compute`.
+    pub fn new<F: Fn(&Document) -> u32 + 'static>(field: Field, compute: F) -> ComputedField {
+        ComputedField {
+            field: field,
+            compute: box compute,
+        }
+    }
+
+    /// Returns the field populated by this computed field.
+    pub fn field(&self,) -> Field {
+        self.field
+    }
+
+    /// Adds the computed value to the document.
+    pub fn populate(&self, document: &mut Document) {
+        let value = (self.compute)(document);
+        document.add_u32(self.field, value);
+    }
+}
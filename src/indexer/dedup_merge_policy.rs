@@ -0,0 +1,61 @@
+use super::merge_policy::{MergePolicy, MergeCandidate};
+use super::DefaultMergePolicy;
+use core::SegmentMeta;
+use schema::Field;
+
+
+/// `DedupMergePolicy` deduplicates documents 
+/// sharing the same value for a key field when merging segments.
+///
+/// For each key value, only the newest version of the document 
+/// is kept in the merged segment. Documents from the segment with 
+/// the highest opstamp are considered the newest, and within
+/// a segment, documents added last are considered the newest.
+///
+/// The key field needs to be either an indexed u32 fast field, 
+/// or a stored field. Documents without any value for the key field 
+/// are never deduplicated: as their fast field reads `0`, a fast field
+/// that is not indexed could not tell them apart from the key `0`.
+///
+/// The choice of the segments to merge is delegated to an 
+/// underlying merge policy.
+#[derive(Debug)]
+pub struct DedupMergePolicy {
+    key_field: Field,
+    merge_policy: Box<MergePolicy>,
+}
+
+impl DedupMergePolicy {
+    /// Creates a `DedupMergePolicy` relying on the 
+    /// default merge policy to select the segments to merge.
+    pub fn new(key_field: Field) -> DedupMergePolicy {
+        DedupMergePolicy::wrap(key_field, box DefaultMergePolicy::default())
+    }
+
+    /// Creates a `DedupMergePolicy` relying on `merge_policy`
+    /// to select the segments to merge.
+    pub fn wrap(key_field: Field, merge_policy: Box<MergePolicy>) -> DedupMergePolicy {
+        DedupMergePolicy {
+            key_field: key_field,
+            merge_policy: merge_policy,
+        }
+    }
+}
+
+impl MergePolicy for DedupMergePolicy {
+    fn compute_merge_candidates(&self, segments: &[SegmentMeta]) -> Vec<MergeCandidate> {
+        self.merge_policy.compute_merge_candidates(segments)
+    }
+
+    fn box_clone(&self) -> Box<MergePolicy> {
+        box DedupMergePolicy::wrap(self.key_field, self.merge_policy.box_clone())
+    }
+
+    fn dedup_field(&self) -> Option<Field> {
+        Some(self.key_field)
+    }
+
+    fn max_docs_per_segment(&self) -> Option<u32> {
+        self.merge_policy.max_docs_per_segment()
+    }
+}
@@ -29,6 +29,10 @@ impl InnerDeleteQueue {
         self.ro_chunks.clear();
         self.last_chunk.clear();
     }
+
+    pub fn len(&self) -> usize {
+        self.ro_chunks.len() + self.last_chunk.len()
+    }
 }
 
 
@@ -50,6 +54,13 @@ impl DeleteQueueSnapshot {
     pub fn clear(&mut self) {
         self.0.clear();
     }
+
+    pub fn len(&self) -> usize {
+        self.0
+            .iter()
+            .map(|chunk| chunk.len())
+            .sum()
+    }
 }
 
 #[derive(Clone, Default)]
@@ -67,12 +78,18 @@ impl DeleteQueue {
     pub fn clear(&self) {
         self.0.write().unwrap().clear();
     }
+
+    /// Returns the number of delete operations in the queue.
+    pub fn len(&self) -> usize {
+        self.0.read().unwrap().len()
+    }
 }
 
 #[cfg(test)]
 mod tests {
 
     use super::{DeleteQueue, DeleteOperation};
+    use indexer::operation::DeleteTarget;
     use schema::{Term, Field};
 
     #[test]
@@ -83,14 +100,16 @@ mod tests {
             let field = Field(1u8);
             DeleteOperation {
                 opstamp: i as u64,
-                term: Term::from_field_u32(field, i as u32)
+                target: DeleteTarget::Term(Term::from_field_u32(field, i as u32)),
             }
         };
 
         delete_queue.push(make_op(1));
         delete_queue.push(make_op(2));
+        assert_eq!(delete_queue.len(), 2);
 
         let snapshot = delete_queue.snapshot();
+        assert_eq!(delete_queue.len(), 2);
         {
             let mut operations_it = snapshot.iter();
             assert_eq!(operations_it.next().unwrap().opstamp, 1);
@@ -106,6 +125,8 @@ mod tests {
         // operations does not own a lock on the queue.
         delete_queue.push(make_op(3));
         let snapshot2 = delete_queue.snapshot();
+        assert_eq!(delete_queue.len(), 3);
+        assert_eq!(snapshot.len(), 2);
         {
             // operations is not affected by
             // the push that occurs after.
@@ -1,5 +1,7 @@
 use Directory;
 use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 use directory::error::OpenWriteError;
 
 pub const LOCKFILE_NAME: &'static str = ".tantivy-indexer.lock";
@@ -10,15 +12,22 @@ pub const LOCKFILE_NAME: &'static str = ".tantivy-indexer.lock";
 ///
 /// Only one lock can exist at a time for a given directory.
 /// The lock is release automatically on `Drop`.
+///
+/// `is_locked` is set for as long as the lock is held.
 pub struct DirectoryLock {
     directory: Box<Directory>,
+    is_locked: Arc<AtomicBool>,
 }
 
 impl DirectoryLock {
-    pub fn lock(mut directory: Box<Directory>) -> Result<DirectoryLock, OpenWriteError> {
+    pub fn lock(mut directory: Box<Directory>, is_locked: Arc<AtomicBool>) -> Result<DirectoryLock, OpenWriteError> {
         let lockfile_path = Path::new(LOCKFILE_NAME);
         try!(directory.open_write(lockfile_path));
-        Ok(DirectoryLock { directory: directory })
+        is_locked.store(true, Ordering::SeqCst);
+        Ok(DirectoryLock {
+            directory: directory,
+            is_locked: is_locked,
+        })
     }
 }
 
@@ -28,5 +37,6 @@ impl Drop for DirectoryLock {
         if let Err(e) = self.directory.delete(lockfile_path) {
             error!("Failed to remove the lock file. {:?}", e);
         }
+        self.is_locked.store(false, Ordering::SeqCst);
     }
 }
\ No newline at end of file
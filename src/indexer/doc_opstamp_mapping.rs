@@ -15,8 +15,10 @@ use DocId;
 // The doc to opstamp mapping stores precisely an array
 // indexed by doc id and storing the opstamp of the document.
 // 
-// This mapping is (for the moment) stricly increasing
-// because of the way document id are allocated.
+// This mapping is stricly increasing for the segments
+// written by the indexing workers, because of the way
+// document id are allocated. It is not for merged segments,
+// whose documents come from several segments.
 #[derive(Clone)]
 pub enum DocToOpstampMapping {
     WithMap(Arc<Vec<u64>>),
@@ -48,6 +50,28 @@ impl DocToOpstampMapping {
             DocToOpstampMapping::None => DocId::max_value(),
         }
     }
+
+    /// Returns the opstamp of the operation that added
+    /// the document `doc`, if it is known.
+    pub fn opstamp(&self, doc: DocId) -> Option<u64> {
+        match *self {
+            DocToOpstampMapping::WithMap(ref doc_opstamps) => Some(doc_opstamps[doc as usize]),
+            DocToOpstampMapping::None => None,
+        }
+    }
+
+    /// Returns true iff the document `doc` was added
+    /// before the operation `target_opstamp`, and may therefore
+    /// be deleted by it.
+    ///
+    /// Unlike `compute_doc_limit`, this does not require
+    /// the mapping to be increasing.
+    pub fn is_before(&self, doc: DocId, target_opstamp: u64) -> bool {
+        match *self {
+            DocToOpstampMapping::WithMap(ref doc_opstamps) => doc_opstamps[doc as usize] < target_opstamp,
+            DocToOpstampMapping::None => true,
+        }
+    }
 }
 
 #[cfg(test)]
@@ -90,4 +114,16 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn test_doc_to_opstamp_mapping_is_before() {
+        assert!(DocToOpstampMapping::None.is_before(3, 0u64));
+        assert_eq!(DocToOpstampMapping::None.opstamp(3), None);
+        let doc_to_opstamp_mapping = DocToOpstampMapping::from(vec!(12u64, 1u64, 17u64));
+        assert_eq!(doc_to_opstamp_mapping.opstamp(1), Some(1u64));
+        assert!(!doc_to_opstamp_mapping.is_before(0, 12u64));
+        assert!(doc_to_opstamp_mapping.is_before(0, 13u64));
+        assert!(doc_to_opstamp_mapping.is_before(1, 2u64));
+        assert!(!doc_to_opstamp_mapping.is_before(2, 13u64));
+    }
 }
\ No newline at end of file
@@ -0,0 +1,156 @@
+use core::SegmentId;
+use core::SegmentMeta;
+
+/// Summary of how fragmented the committed segments
+/// of an index are.
+///
+/// It is meant to help deciding when to optimize (merge) an index.
+#[derive(Clone, Debug)]
+pub struct FragmentationReport {
+    num_segments: usize,
+    average_segment_size: f32,
+    deleted_ratios: Vec<(SegmentId, f32)>,
+    fragmentation_score: f32,
+}
+
+/// Ratio of deleted docs within a segment.
+fn deleted_ratio(segment_meta: &SegmentMeta) -> f32 {
+    if segment_meta.max_doc() == 0 {
+        0f32
+    }
+    else {
+        segment_meta.num_deleted_docs() as f32 / segment_meta.max_doc() as f32
+    }
+}
+
+impl<'a> From<&'a [SegmentMeta]> for FragmentationReport {
+    fn from(segment_metas: &'a [SegmentMeta]) -> FragmentationReport {
+        let num_segments = segment_metas.len();
+        if num_segments == 0 {
+            return FragmentationReport {
+                num_segments: 0,
+                average_segment_size: 0f32,
+                deleted_ratios: Vec::new(),
+                fragmentation_score: 0f32,
+            };
+        }
+        let total_num_docs: u64 = segment_metas
+            .iter()
+            .map(|segment_meta| segment_meta.num_docs() as u64)
+            .sum();
+        let total_max_doc: u64 = segment_metas
+            .iter()
+            .map(|segment_meta| segment_meta.max_doc() as u64)
+            .sum();
+        let deleted_ratios = segment_metas
+            .iter()
+            .map(|segment_meta| (segment_meta.id(), deleted_ratio(segment_meta)))
+            .collect();
+        let total_deleted_ratio = 
+            if total_max_doc == 0 {
+                0f32
+            }
+            else {
+                (total_max_doc - total_num_docs) as f32 / total_max_doc as f32
+            };
+        // A single segment without any deletes has a score of 0.
+        // The score gets closer to 1 as the number of segments
+        // or the ratio of deleted docs grows.
+        let segment_count_factor = 1f32 - 1f32 / num_segments as f32;
+        let fragmentation_score = 1f32 - (1f32 - segment_count_factor) * (1f32 - total_deleted_ratio);
+        FragmentationReport {
+            num_segments: num_segments,
+            average_segment_size: total_num_docs as f32 / num_segments as f32,
+            deleted_ratios: deleted_ratios,
+            fragmentation_score: fragmentation_score,
+        }
+    }
+}
+
+impl FragmentationReport {
+
+    /// Returns the number of committed segments.
+    pub fn num_segments(&self) -> usize {
+        self.num_segments
+    }
+
+    /// Returns the average number of (non-deleted)
+    /// documents per segment.
+    pub fn average_segment_size(&self) -> f32 {
+        self.average_segment_size
+    }
+
+    /// Returns, for each segment, the ratio of 
+    /// its documents that are deleted.
+    pub fn deleted_ratios(&self) -> &[(SegmentId, f32)] {
+        &self.deleted_ratios
+    }
+
+    /// Returns a fragmentation score between 0 and 1.
+    ///
+    /// It is 0 for an index consisting of a single segment without
+    /// deletes, and increases with the number of segments and 
+    /// the overall ratio of deleted documents.
+    pub fn fragmentation_score(&self) -> f32 {
+        self.fragmentation_score
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use core::SegmentId;
+    use core::SegmentMeta;
+
+    fn segment_meta(max_doc: u32, num_deleted_docs: u32) -> SegmentMeta {
+        let mut segment_meta = SegmentMeta::new(SegmentId::generate_random());
+        segment_meta.set_max_doc(max_doc);
+        if num_deleted_docs > 0 {
+            segment_meta.set_delete_meta(num_deleted_docs, 1u64);
+        }
+        segment_meta
+    }
+
+    #[test]
+    fn test_fragmentation_report_empty() {
+        let report = FragmentationReport::from(&[][..]);
+        assert_eq!(report.num_segments(), 0);
+        assert_eq!(report.fragmentation_score(), 0f32);
+    }
+
+    #[test]
+    fn test_fragmentation_report_single_segment() {
+        let report = FragmentationReport::from(&[segment_meta(100, 0)][..]);
+        assert_eq!(report.num_segments(), 1);
+        assert_eq!(report.average_segment_size(), 100f32);
+        assert_eq!(report.fragmentation_score(), 0f32);
+    }
+
+    #[test]
+    fn test_fragmentation_report() {
+        let segment_metas = vec!(
+            segment_meta(100, 0),
+            segment_meta(100, 50),
+            segment_meta(200, 150),
+            segment_meta(0, 0),
+        );
+        let report = FragmentationReport::from(&segment_metas[..]);
+        assert_eq!(report.num_segments(), 4);
+        assert_eq!(report.average_segment_size(), 50f32);
+        let deleted_ratios: Vec<f32> = report
+            .deleted_ratios()
+            .iter()
+            .map(|&(_, ratio)| ratio)
+            .collect();
+        assert_eq!(deleted_ratios, vec!(0f32, 0.5f32, 0.75f32, 0f32));
+        for (&(segment_id, _), segment_meta) in report.deleted_ratios().iter().zip(segment_metas.iter()) {
+            assert_eq!(segment_id, segment_meta.id());
+        }
+        // 200 deleted docs out of 400
+        assert_eq!(report.fragmentation_score(), 1f32 - 0.25f32 * 0.5f32);
+        let less_fragmented = FragmentationReport::from(&segment_metas[..2]);
+        assert!(less_fragmented.fragmentation_score() < report.fragmentation_score());
+    }
+}
@@ -1,4 +1,3 @@
-use bit_set::BitSet;
 use chan;
 use core::Index;
 use core::Segment;
@@ -6,27 +5,50 @@ use core::SegmentComponent;
 use core::SegmentId;
 use core::SegmentMeta;
 use core::SegmentReader;
-use datastruct::stacker::Heap;
+use core::Searcher;
+use core::copy_segment_files;
+use core::SerializableSegment;
+use datastruct::stacker::{Heap, TermHashFunction};
 use Error;
 use Directory;
-use fastfield::delete::write_delete_bitset;
+use fastfield::delete::{DeleteSet, write_delete_set};
+use common::HasLen;
 use indexer::delete_queue::DeleteQueueSnapshot;
 use futures::Canceled;
 use futures::Future;
+use futures::future;
 use indexer::delete_queue::DeleteQueue;
 use indexer::doc_opstamp_mapping::DocToOpstampMapping;
 use indexer::MergePolicy;
-use indexer::operation::DeleteOperation;
+use indexer::MergeProgress;
+use indexer::FragmentationReport;
+use indexer::SegmentStats;
+use indexer::operation::{DeleteOperation, DeleteTarget};
+use indexer::operation::Operation;
+use indexer::operation_log::{append_operation_log, compact_operation_log};
 use indexer::SegmentEntry;
+use indexer::SegmentSerializer;
+use indexer::merger::IndexMerger;
+use indexer::SegmentState;
 use indexer::SegmentWriter;
+use indexer::SegmentBuilder;
+use indexer::{resolve_tokenizers, exceeds_heap};
+use analyzer::Tokenizer;
+use indexer::ComputedField;
 use postings::DocSet;
 use postings::SegmentPostingsOption;
+use query::Query;
+use rustc_serialize::json;
 use Result;
 use schema::Document;
 use schema::Schema;
 use schema::Term;
+use store::Compressor;
+use std::iter::Peekable;
 use std::mem;
+use std::result;
 use std::mem::swap; 
+use std::sync::{Arc, RwLock};
 use std::thread;
 use std::thread::JoinHandle;
 use super::directory_lock::DirectoryLock;
@@ -41,10 +63,38 @@ pub const MARGIN_IN_BYTES: u32 = 10_000_000u32;
 pub const HEAP_SIZE_LIMIT: u32 = MARGIN_IN_BYTES * 3u32;
 
 // Add document will block if the number of docs waiting in the queue to be indexed reaches PIPELINE_MAX_SIZE_IN_DOCS
+// (a block of documents only counts as one).
 const PIPELINE_MAX_SIZE_IN_DOCS: usize = 10_000;
 
-type DocumentSender = chan::Sender<AddOperation>;
-type DocumentReceiver = chan::Receiver<AddOperation>;
+// Documents are sent in blocks, each block being
+// indexed contiguously within a single segment.
+type DocumentSender = chan::Sender<Vec<AddOperation>>;
+type DocumentReceiver = chan::Receiver<Vec<AddOperation>>;
+
+/// Defines what the `IndexWriter` does with the documents
+/// that do not fit in the memory budget of an indexing thread.
+///
+/// A block of documents is considered oversized if indexing it
+/// exhausts the heap of an indexing thread, or the term table of
+/// one of its fields.
+///
+/// See `IndexWriter::set_oversized_document_policy`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OversizedDocumentPolicy {
+    /// The documents are indexed, the heap overflowing
+    /// if needed, and their segment is flushed right after them.
+    ///
+    /// This is the default.
+    OwnSegment,
+    /// The documents are not indexed.
+    ///
+    /// `IndexWriter::try_add_document` returns an error,
+    /// while `add_document` logs it and discards the documents.
+    ///
+    /// The documents are tokenized before being sent to the
+    /// indexing threads, to check that they fit in an empty heap.
+    Reject,
+}
 
 /// `IndexWriter` is the user entry-point to add document to an index.
 ///
@@ -62,7 +112,7 @@ pub struct IndexWriter {
 
     heap_size_in_bytes_per_thread: usize,
 
-    workers_join_handle: Vec<JoinHandle<Result<()>>>,
+    workers_join_handle: Vec<JoinHandle<Result<Option<SegmentEntry>>>>,
 
     document_receiver: DocumentReceiver,
     document_sender: DocumentSender,
@@ -77,8 +127,26 @@ pub struct IndexWriter {
 
     delete_queue: DeleteQueue,
 
+    term_hash_function: Arc<RwLock<TermHashFunction>>,
+
+    max_tokens_per_field: Arc<RwLock<u32>>,
+
+    computed_fields: Vec<ComputedField>,
+
+    oversized_document_policy: OversizedDocumentPolicy,
+
+    // tokenizer of each of the tokenized fields, used
+    // to check the size of the documents to reject.
+    tokenizers: Vec<Option<Arc<Tokenizer>>>,
+
+    // operations of the current transaction,
+    // appended to the operation log on commit.
+    pending_operations: Vec<Operation>,
+
     uncommitted_opstamp: u64,
     committed_opstamp: u64,
+    // metas of the segments published by the last commit.
+    committed_segment_metas: Vec<SegmentMeta>,
 }
 
 // IndexWriter cannot be sent to another thread.
@@ -110,8 +178,12 @@ pub fn open_index_writer(index: &Index,
         panic!(format!("The heap size per thread needs to be at least {}.",
                         HEAP_SIZE_LIMIT));
     }
+
+    // fail early rather than in the indexing threads
+    // if a tokenizer is missing.
+    let tokenizers = try!(resolve_tokenizers(&index.schema(), index.tokenizers()));
     
-    let directory_lock = try!(DirectoryLock::lock(index.directory().box_clone()));
+    let directory_lock = try!(DirectoryLock::lock(index.directory().box_clone(), index.has_writer().clone()));
     
     let (document_sender, document_receiver): (DocumentSender, DocumentReceiver) =
         chan::sync(PIPELINE_MAX_SIZE_IN_DOCS);
@@ -138,8 +210,20 @@ pub fn open_index_writer(index: &Index,
 
         delete_queue: delete_queue,
 
+        term_hash_function: Arc::default(),
+
+        max_tokens_per_field: Arc::new(RwLock::new(u32::max_value())),
+        oversized_document_policy: OversizedDocumentPolicy::OwnSegment,
+
+        tokenizers: tokenizers,
+
+        computed_fields: Vec::new(),
+
+        pending_operations: Vec::new(),
+
         committed_opstamp: index.opstamp(),
         uncommitted_opstamp: index.opstamp(),
+        committed_segment_metas: try!(index.segments()),
 
         generation: 0,
 
@@ -164,11 +248,14 @@ pub fn advance_deletes(
         
         let segment_reader = SegmentReader::open(segment.clone())?;
         
-        let mut delete_bitset = BitSet::with_capacity(segment_reader.max_doc() as usize);
+        let mut delete_set = DeleteSet::new();
         
         let mut last_opstamp_opt: Option<u64> = None;
 
         let previous_delete_opstamp_opt = segment.meta().delete_opstamp();
+
+        // searcher over the segment alone, to run the delete queries.
+        let mut query_searcher: Option<Searcher> = None;
         
         for delete_op in delete_operations.iter() {
 
@@ -180,16 +267,30 @@ pub fn advance_deletes(
             }
 
             // A delete operation should only affect
-            // document that were inserted after it.
-            // 
-            // Limit doc helps identify the first document
-            // that may be affected by the delete operation.
-            let limit_doc = doc_opstamps.compute_doc_limit(delete_op.opstamp);
-            if let Some(mut docset) = segment_reader.read_postings(&delete_op.term, SegmentPostingsOption::NoFreq) {
-                while docset.advance() {
-                    let deleted_doc = docset.doc();
-                    if deleted_doc < limit_doc {
-                        delete_bitset.insert(deleted_doc as usize);
+            // document that were inserted before it.
+            match delete_op.target {
+                DeleteTarget::Term(ref term) => {
+                    if let Some(mut docset) = segment_reader.read_postings(term, SegmentPostingsOption::NoFreq) {
+                        while docset.advance() {
+                            let deleted_doc = docset.doc();
+                            if doc_opstamps.is_before(deleted_doc, delete_op.opstamp) {
+                                delete_set.insert(deleted_doc);
+                            }
+                        }
+                    }
+                }
+                DeleteTarget::Query(ref query) => {
+                    if query_searcher.is_none() {
+                        query_searcher = Some(Searcher::from(vec!(SegmentReader::open(segment.clone())?)));
+                    }
+                    let searcher = query_searcher.as_ref().unwrap();
+                    let weight = query.weight(searcher)?;
+                    let mut scorer = weight.scorer(&searcher.segment_readers()[0])?;
+                    while scorer.advance() {
+                        let deleted_doc = scorer.doc();
+                        if doc_opstamps.is_before(deleted_doc, delete_op.opstamp) {
+                            delete_set.insert(deleted_doc);
+                        }
                     }
                 }
             }
@@ -203,33 +304,49 @@ pub fn advance_deletes(
         if let Some(last_opstamp) = last_opstamp_opt {
             for doc in 0u32..segment_reader.max_doc() {
                 if segment_reader.is_deleted(doc) {
-                    delete_bitset.insert(doc as usize);
+                    delete_set.insert(doc);
                 }
             }
-            let num_deleted_docs = delete_bitset.len();
+            let num_deleted_docs = delete_set.len();
             segment.set_delete_meta(num_deleted_docs as u32, last_opstamp);
             let mut delete_file = segment.open_write(SegmentComponent::DELETE)?;    
-            write_delete_bitset(&delete_bitset, &mut delete_file)?;
+            write_delete_set(&delete_set, &mut delete_file)?;
         }
 
         Ok(segment.meta().clone())
 }
 
-fn index_documents(heap: &mut Heap,
+// Indexes documents into `segment` until the buffer is full,
+// or until there are no more documents.
+//
+// Returns the entry of the segment, which is not added
+// to the segment updater yet, and true iff the documents ran out.
+fn index_documents<I: Iterator<Item=Vec<AddOperation>>>(heap: &mut Heap,
                    segment: Segment,
                    schema: &Schema,
-                   generation: usize,
-                   document_iterator: &mut Iterator<Item=AddOperation>,
-                   segment_updater: &mut SegmentUpdater)
-                   -> Result<bool> {
+                   term_hash_function: TermHashFunction,
+                   max_tokens_per_field: u32,
+                   document_iterator: &mut Peekable<I>)
+                   -> Result<(SegmentEntry, bool)> {
     heap.clear();
     let segment_id = segment.id();
-    let mut segment_writer = try!(SegmentWriter::for_segment(heap, segment, &schema));
-    for doc in document_iterator {
-        try!(segment_writer.add_document(&doc, &schema));
+    let store_compressor = segment.meta().store_compressor();
+    let store_block_size = segment.meta().store_block_size();
+    let mut segment_writer = try!(SegmentWriter::for_segment(heap, segment, &schema, term_hash_function));
+    segment_writer.set_max_tokens_per_field(max_tokens_per_field);
+    let mut is_exhausted = true;
+    while let Some(docs) = document_iterator.next() {
+        try!(segment_writer.add_document_block(&docs, &schema));
+        if segment_writer.is_heap_exhausted() {
+            info!("Oversized documents, flushing segment with maxdoc={}.",
+                  segment_writer.max_doc());
+            is_exhausted = false;
+            break;
+        }
         if segment_writer.is_buffer_full() {
             info!("Buffer limit reached, flushing segment with maxdoc={}.",
                   segment_writer.max_doc());
+            is_exhausted = false;
             break;
         }
     }
@@ -244,12 +361,17 @@ fn index_documents(heap: &mut Heap,
     // let segment_entry = advance_deletes(&mut segment, delete_queue, delete_position, )?;
     let mut segment_meta = SegmentMeta::new(segment_id);
     segment_meta.set_max_doc(num_docs);
+    segment_meta.set_store_compressor(store_compressor);
+    segment_meta.set_store_block_size(store_block_size);
+    segment_meta.set_has_fieldnorms(schema.has_fieldnorms());
+    segment_meta.set_has_term_vectors(schema.has_term_vectors());
+    if let Some(&last_opstamp) = doc_opstamps.last() {
+        segment_meta.set_opstamp(last_opstamp);
+    }
 
     let mut segment_entry = SegmentEntry::new(segment_meta);
     segment_entry.set_doc_to_opstamp(DocToOpstampMapping::from(doc_opstamps));
-
-    Ok(segment_updater.add_segment(generation, segment_entry))
-
+    Ok((segment_entry, is_exhausted))
 }
 
 
@@ -290,21 +412,27 @@ impl IndexWriter {
     fn add_indexing_worker(&mut self) -> Result<()> {
         let schema = self.index.schema();
         let document_receiver_clone = self.document_receiver.clone();
-        let mut segment_updater = self.segment_updater.clone();
+        let segment_updater = self.segment_updater.clone();
         let mut heap = Heap::with_capacity(self.heap_size_in_bytes_per_thread);
         
         let generation = self.generation;
+        let term_hash_function = self.term_hash_function.clone();
+        let max_tokens_per_field = self.max_tokens_per_field.clone();
 
-        let join_handle: JoinHandle<Result<()>> =
+        let join_handle: JoinHandle<Result<Option<SegmentEntry>>> =
             thread::Builder::new()
             .name(format!("indexing thread {} for gen {}", self.worker_id, generation))
             .spawn(move || {
                 
+                // the segment being written when the documents ran out.
+                // It is left to the `IndexWriter`, which adds it on flush or commit.
+                let mut last_segment_entry = None;
+                // the iterator outlives the segments, as a document
+                // peeked by a segment may be indexed in the next one.
+                let mut document_iterator = document_receiver_clone
+                    .into_iter()
+                    .peekable();
                 loop {
-                    let mut document_iterator = document_receiver_clone.clone()
-                        .into_iter()
-                        .peekable();
-
                     // the peeking here is to avoid
                     // creating a new segment's files
                     // if no document are available.
@@ -314,18 +442,30 @@ impl IndexWriter {
                     // our local iterator.
                     if document_iterator.peek().is_some() {
                         let segment = segment_updater.new_segment();
-                        index_documents(&mut heap,
+                        let term_hash_function = *term_hash_function
+                            .read()
+                            .expect("Term hash function lock poisoned");
+                        let max_tokens_per_field = *max_tokens_per_field
+                            .read()
+                            .expect("Max tokens per field lock poisoned");
+                        let (segment_entry, is_exhausted) = index_documents(&mut heap,
                                         segment,
                                         &schema,
-                                        generation,
-                                        &mut document_iterator,
-                                        &mut segment_updater)?;
+                                        term_hash_function,
+                                        max_tokens_per_field,
+                                        &mut document_iterator)?;
+                        if is_exhausted {
+                            last_segment_entry = Some(segment_entry);
+                        }
+                        else {
+                            segment_updater.add_segment(generation, segment_entry);
+                        }
                     }
                     else {
                         // No more documents.
                         // Happens when there is a commit, or if the `IndexWriter`
                         // was dropped.
-                        return Ok(())
+                        return Ok(last_segment_entry)
                     }
 
                     
@@ -336,6 +476,93 @@ impl IndexWriter {
         Ok(())
     }
 
+    /// Returns fragmentation metrics about the committed segments.
+    ///
+    /// They can be used to decide when to optimize the index.
+    pub fn fragmentation_report(&self) -> FragmentationReport {
+        self.segment_updater.fragmentation_report()
+    }
+
+    /// Returns the state of all of the segments of the index,
+    /// including the uncommitted segments and the segments
+    /// being written.
+    ///
+    /// The snapshot is consistent: a segment appears at most once.
+    pub fn segment_states(&self) -> Vec<(SegmentId, SegmentState)> {
+        self.segment_updater.segment_states()
+    }
+
+    /// Returns the document counts, the size on disk and the state
+    /// of all of the segments of the index.
+    ///
+    /// See `SegmentManager::segment_stats`.
+    pub fn segment_stats(&self) -> Vec<SegmentStats> {
+        self.segment_updater.segment_stats()
+    }
+
+    /// Returns the number of scorers created on each of the
+    /// uncommitted and committed segments by the searchers of the
+    /// index, since the index was opened.
+    ///
+    /// The frequently queried segments have the highest counts.
+    /// The counts are not persisted.
+    pub fn segment_access_counts(&self) -> Vec<(SegmentId, usize)> {
+        self.segment_updater.segment_access_counts()
+    }
+
+    /// Returns the time at which a committed or uncommitted segment
+    /// was created, in seconds since the UNIX epoch.
+    ///
+    /// This is useful to implement age-based merge or retention policies.
+    /// See `SegmentMeta::created_at`.
+    pub fn segment_created_at(&self, segment_id: &SegmentId) -> Option<u64> {
+        self.segment_updater.segment_created_at(segment_id)
+    }
+
+    /// Returns the opstamp of the last delete operation applied
+    /// to a committed or uncommitted segment.
+    ///
+    /// The deletes are applied to the segments on commit, so that
+    /// pending deletes have a greater opstamp.
+    /// Returns `None` if no delete was ever applied to the segment.
+    pub fn segment_delete_opstamp(&self, segment_id: &SegmentId) -> Option<u64> {
+        self.segment_updater.segment_delete_opstamp(segment_id)
+    }
+
+    /// Returns the metas of the segments saved in the meta file
+    /// by the last commit, along with the opstamp of the commit.
+    /// Before the first commit of the writer, these are the segments
+    /// of the meta file it was opened on.
+    ///
+    /// This only includes committed segments: the segments flushed
+    /// since the last commit, see `flush()`, are left out.
+    /// The segments merged or dropped after the commit are not
+    /// reflected either until the next commit, even though their
+    /// swap is persisted right away.
+    ///
+    /// Together with the opstamp returned by `commit()`, this identifies
+    /// the durable state of the index.
+    pub fn committed_segment_metas(&self) -> &[SegmentMeta] {
+        &self.committed_segment_metas
+    }
+
+    /// Returns the metas of the committed segments, from the largest
+    /// to the smallest in number of documents.
+    ///
+    /// This is useful to inspect the index before
+    /// merging segments manually.
+    pub fn committed_segments_by_size(&self) -> Vec<SegmentMeta> {
+        self.segment_updater.committed_segments_by_size()
+    }
+
+    /// Returns the memory the indexing threads may use
+    /// for the segments they are building, in bytes.
+    ///
+    /// See `Index::writer_with_memory_budget`.
+    pub fn memory_budget(&self) -> usize {
+        self.heap_size_in_bytes_per_thread * self.num_threads
+    }
+
     /// Accessor to the merge policy.
     pub fn get_merge_policy(&self) -> Box<MergePolicy> {
         self.segment_updater.get_merge_policy()
@@ -346,6 +573,162 @@ impl IndexWriter {
         self.segment_updater.set_merge_policy(merge_policy);
     }
     
+    /// Accessor to the maximum number of merges running
+    /// at the same time, or `None` if there is no such bound.
+    pub fn get_merge_threads(&self) -> Option<usize> {
+        self.segment_updater.get_merge_threads()
+    }
+
+    /// Bounds the number of merges running at the same time
+    /// to `num_merge_threads`. By default, there is no bound, and
+    /// each merge runs on a thread of its own as soon as it is started.
+    ///
+    /// The merges started past the bound, by the merge policy or with
+    /// `.merge(...)`, are queued, and run in order as the running merges end.
+    /// Their segments are marked as being merged in the meantime.
+    ///
+    /// See also `set_merge_backpressure`.
+    ///
+    /// # Panics
+    /// If `num_merge_threads` is 0.
+    pub fn set_merge_threads(&self, num_merge_threads: usize) {
+        assert!(num_merge_threads > 0, "There must be at least one merge thread.");
+        self.segment_updater.set_merge_threads(Some(num_merge_threads));
+    }
+
+    /// Returns true iff the commits wait for the pending merges.
+    pub fn get_merge_backpressure(&self) -> bool {
+        self.segment_updater.get_merge_backpressure()
+    }
+
+    /// If `merge_backpressure` is true, `commit()` blocks until
+    /// all of the queued merges have been started on a merge thread.
+    ///
+    /// When the merges cannot keep up with the commits, this slows
+    /// down the ingestion rather than letting the number of segments grow.
+    /// It only applies when the number of merge threads is bounded,
+    /// as the merges are otherwise never queued. Disabled by default.
+    pub fn set_merge_backpressure(&self, merge_backpressure: bool) {
+        self.segment_updater.set_merge_backpressure(merge_backpressure);
+    }
+
+    /// Returns the number of merges waiting for a merge thread.
+    ///
+    /// See `set_merge_threads`.
+    pub fn num_pending_merges(&self) -> usize {
+        self.segment_updater.num_pending_merges()
+    }
+
+    /// Sets the callback observing the progress of the merges,
+    /// or removes it if `merge_progress` is `None`.
+    ///
+    /// See `MergeProgress`.
+    pub fn set_merge_progress(&self, merge_progress: Option<MergeProgress>) {
+        self.segment_updater.set_merge_progress(merge_progress);
+    }
+
+    /// Accessor to the codec used to compress the store
+    /// of the segments being written.
+    pub fn get_store_compressor(&self) -> Compressor {
+        self.segment_updater.get_store_compressor()
+    }
+
+    /// Sets the codec used to compress the store of the
+    /// segments written from now on, including the segments
+    /// resulting from a merge. LZ4 is used by default.
+    ///
+    /// The codec of each segment is recorded in its `SegmentMeta`,
+    /// so that segments compressed with different codecs
+    /// can coexist in the index, and be merged together.
+    pub fn set_store_compressor(&self, store_compressor: Compressor) {
+        self.segment_updater.set_store_compressor(store_compressor);
+    }
+
+    /// Accessor to the size of the blocks of the store
+    /// of the segments being written, before compression.
+    pub fn get_store_block_size(&self) -> usize {
+        self.segment_updater.get_store_block_size()
+    }
+
+    /// Sets the size of the blocks of the store of the segments
+    /// written from now on, before compression. 16KB by default.
+    ///
+    /// Fetching a document decompresses its whole block:
+    /// smaller blocks make `searcher.doc(...)` faster,
+    /// while larger blocks compress better.
+    pub fn set_store_block_size(&self, store_block_size: usize) {
+        self.segment_updater.set_store_block_size(store_block_size);
+    }
+
+    /// Accessor to the hash function used to index the terms
+    /// of the segments being written.
+    pub fn get_term_hash_function(&self) -> TermHashFunction {
+        *self.term_hash_function.read().expect("Term hash function lock poisoned")
+    }
+
+    /// Set the hash function used to index the terms
+    /// of the segments being written.
+    ///
+    /// `TermHashFunction::FxHash`, the default, is fast.
+    /// If the indexed text may be crafted by an adversary,
+    /// `TermHashFunction::SipHash` prevents hash flooding.
+    ///
+    /// The hash function does not affect the resulting segments.
+    /// It is used for the segments created after this call.
+    pub fn set_term_hash_function(&self, term_hash_function: TermHashFunction) {
+        *self.term_hash_function.write().expect("Term hash function lock poisoned") = term_hash_function;
+    }
+
+    /// Accessor to the maximum number of tokens indexed
+    /// for each field of a document.
+    pub fn get_max_tokens_per_field(&self) -> u32 {
+        *self.max_tokens_per_field.read().expect("Max tokens per field lock poisoned")
+    }
+
+    /// Set the maximum number of tokens indexed for each
+    /// field of a document.
+    ///
+    /// The tokens past this limit are dropped, and a warning is logged.
+    /// By default, there is no limit.
+    ///
+    /// It is used for the segments created after this call.
+    pub fn set_max_tokens_per_field(&self, max_tokens_per_field: u32) {
+        *self.max_tokens_per_field.write().expect("Max tokens per field lock poisoned") = max_tokens_per_field;
+    }
+    
+    /// Returns the policy applied to the documents that do not fit
+    /// in the memory budget of an indexing thread.
+    pub fn get_oversized_document_policy(&self) -> OversizedDocumentPolicy {
+        self.oversized_document_policy
+    }
+
+    /// Sets the policy applied to the documents that do not fit
+    /// in the memory budget of an indexing thread.
+    ///
+    /// By default, they are indexed in a segment of their own.
+    /// See `OversizedDocumentPolicy`.
+    pub fn set_oversized_document_policy(&mut self, oversized_document_policy: OversizedDocumentPolicy) {
+        self.oversized_document_policy = oversized_document_policy;
+    }
+
+    /// Registers a field computed from the other fields
+    /// of each document added afterwards.
+    ///
+    /// The computed field is evaluated in `add_document`, in the
+    /// order of registration, so that it can rely on the fields computed
+    /// before it.
+    ///
+    /// # Errors
+    /// If the field is not a u32 fast field, returns `Error::SchemaError`.
+    pub fn add_computed_field(&mut self, computed_field: ComputedField) -> Result<()> {
+        let field = computed_field.field();
+        if !self.index.schema().get_field_entry(field).is_u32_fast() {
+            return Err(Error::SchemaError(format!("Field {:?} is not a u32 fast field.", field)));
+        }
+        self.computed_fields.push(computed_field);
+        Ok(())
+    }
+
     fn start_workers(&mut self) -> Result<()> {
         for _ in 0..self.num_threads {
             try!(self.add_indexing_worker());
@@ -354,10 +737,224 @@ impl IndexWriter {
     }
 
     /// Merges a given list of segments
-    pub fn merge(&mut self, segment_ids: &[SegmentId]) -> impl Future<Item=SegmentEntry, Error=Canceled> {
+    ///
+    /// The future resolves to the resulting segments: a single one,
+    /// unless the merge policy caps the number of documents per segment.
+    /// See `MergePolicy::max_docs_per_segment`.
+    pub fn merge(&mut self, segment_ids: &[SegmentId]) -> impl Future<Item=Vec<SegmentEntry>, Error=Canceled> {
         self.segment_updater.start_merge(segment_ids)
     }
 
+    /// Merges all of the committed segments into a single segment.
+    ///
+    /// The merge goes through the same path as `.merge(...)`.
+    /// Segments committed while the merge is running, as well as
+    /// the segments already being merged, are left out.
+    ///
+    /// A single segment is merged as well if it has deleted
+    /// documents, so that they get purged.
+    /// If there is no segment to merge, or a single segment without
+    /// deleted documents, the future resolves right away to no segment.
+    pub fn merge_all(&mut self) -> Box<Future<Item=Vec<SegmentEntry>, Error=Canceled>> {
+        let segment_metas = self.segment_updater.mergeable_committed_segments();
+        let is_merged = match segment_metas.len() {
+            0 => true,
+            1 => segment_metas[0].num_deleted_docs() == 0,
+            _ => false,
+        };
+        if is_merged {
+            return box future::ok(Vec::new());
+        }
+        let segment_ids: Vec<SegmentId> = segment_metas
+            .iter()
+            .map(|segment_meta| segment_meta.id())
+            .collect();
+        box self.merge(&segment_ids)
+    }
+
+    /// Deletes the files that are not used by the index anymore,
+    /// such as the files of segments that were merged or rolled back.
+    ///
+    /// The files of the committed and uncommitted segments, the files of
+    /// the segments being written, and the meta file are kept.
+    /// The files of the segments that are still being read by a
+    /// `Searcher`, including searchers of a former generation,
+    /// are kept as well. They are deleted by a later call once the
+    /// searchers have been dropped.
+    pub fn garbage_collect_files(&mut self) -> Result<()> {
+        self.segment_updater.garbage_collect_files()
+    }
+
+    /// Removes a committed segment from the index.
+    ///
+    /// The removal is persisted right away, and the
+    /// files of the segment are garbage collected.
+    /// Documents added since the last commit are left
+    /// untouched.
+    ///
+    /// The ids of the committed segments can be listed
+    /// using `Index::searchable_segment_ids()`.
+    ///
+    /// # Errors
+    /// If the segment is not committed, or is currently
+    /// being merged, returns `Error::InvalidArgument`.
+    pub fn drop_segment(&mut self, segment_id: SegmentId) -> Result<()> {
+        self.segment_updater.drop_segment(segment_id)
+    }
+
+    /// Rebuilds the term dictionary of a committed segment, together
+    /// with its postings and positions, from its stored documents.
+    ///
+    /// This makes it possible to repair a segment whose term dictionary,
+    /// postings or positions were reported as corrupted by
+    /// `Index::check_integrity()`. The rebuilt segment is written under a
+    /// new id, and takes the place of the segment, like the result of a merge:
+    /// the swap is persisted right away, and the files of the segment are
+    /// garbage collected. The searchers need to be reloaded afterwards.
+    ///
+    /// All of the indexed fields must be stored, and must not have payloads.
+    ///
+    /// # Errors
+    /// If this index is frozen, returns `Error::ReadOnlyError`.
+    /// If the segment is not committed or is currently being merged,
+    /// or if one of the indexed fields is not stored or has payloads,
+    /// returns `Error::InvalidArgument`.
+    pub fn rebuild_term_dict(&mut self, segment_id: SegmentId) -> Result<()> {
+        if self.index.is_frozen() {
+            return Err(Error::ReadOnlyError);
+        }
+        self.segment_updater.rebuild_term_dict(segment_id)
+    }
+
+    /// Imports all of the committed segments of another index.
+    ///
+    /// The files of the segments are copied into this index's
+    /// directory. If a segment's id is already used in this index,
+    /// the imported segment is given a fresh id rather than
+    /// overwriting the existing files.
+    ///
+    /// Like adds, the imported documents will be visible
+    /// only after calling `commit()`. Delete operations 
+    /// issued before the import do not affect the imported documents.
+    ///
+    /// Returns the opstamp of the import.
+    ///
+    /// # Errors
+    /// If the schema of `index` differs from the schema of
+    /// this index, returns `Error::SchemaError`.
+    /// If this index is frozen, returns `Error::ReadOnlyError`.
+    pub fn add_index(&mut self, index: &Index) -> Result<u64> {
+        if self.index.is_frozen() {
+            return Err(Error::ReadOnlyError);
+        }
+        if json::encode(&index.schema()).ok() != json::encode(&self.index.schema()).ok() {
+            return Err(Error::SchemaError(String::from("Cannot import an index with a different schema.")));
+        }
+        let opstamp = self.stamp();
+        let source = index.directory().box_clone();
+        let mut target = self.index.directory().box_clone();
+        for source_meta in index.segments()? {
+            let collides = source_meta
+                .list_files()
+                .iter()
+                .any(|path| target.exists(path));
+            let segment_id = if collides {
+                source_meta.id().rename_to_fresh()
+            }
+            else {
+                source_meta.id()
+            };
+            let mut target_meta = SegmentMeta::new(segment_id);
+            target_meta.set_max_doc(source_meta.max_doc());
+            target_meta.set_store_compressor(source_meta.store_compressor());
+            target_meta.set_store_block_size(source_meta.store_block_size());
+            target_meta.set_has_fieldnorms(source_meta.has_fieldnorms());
+            target_meta.set_has_term_vectors(source_meta.has_term_vectors());
+            target_meta.set_opstamp(opstamp);
+            if source_meta.has_deletes() {
+                target_meta.set_delete_meta(source_meta.num_deleted_docs(), opstamp);
+            }
+            try!(copy_segment_files(&*source, &source_meta, &mut *target, &target_meta));
+            let num_docs = target_meta.max_doc();
+            let doc_opstamps = vec![opstamp; num_docs as usize];
+            let mut segment_entry = SegmentEntry::new(target_meta);
+            segment_entry.set_doc_to_opstamp(DocToOpstampMapping::from(doc_opstamps));
+            self.segment_updater.add_segment(self.generation, segment_entry);
+            self.push_add_operations(opstamp, num_docs);
+        }
+        Ok(opstamp)
+    }
+
+    /// Builds a segment with a `SegmentBuilder`, and adds it to the index.
+    ///
+    /// This is a lower level alternative to `add_document`,
+    /// for bulk loads: the rows or columns of values pushed to the
+    /// builder are indexed right away into a single segment, in the
+    /// calling thread, without going through the indexing pipeline.
+    /// The heap of the builder holds the postings of the segment. Like for
+    /// the indexing threads, a margin of `MARGIN_IN_BYTES` of the heap is
+    /// kept for the last row: once the heap is full, `add_row` returns
+    /// an error, and the segment has to be built with a larger heap.
+    ///
+    /// The computed fields of the writer are populated for each row.
+    /// Like adds, the rows will be visible only after calling `commit()`.
+    ///
+    /// All of the rows share the returned opstamp.
+    ///
+    /// # Errors
+    /// If this index is frozen, returns `Error::ReadOnlyError`.
+    /// If `heap_size_in_bytes` is not larger than the margin,
+    /// returns `Error::InvalidArgument`.
+    /// If `build` returns an error, no segment is added,
+    /// and the error is returned.
+    pub fn build_segment<F>(&mut self, heap_size_in_bytes: usize, build: F) -> Result<u64>
+        where F: FnOnce(&mut SegmentBuilder) -> Result<()> {
+        if self.index.is_frozen() {
+            return Err(Error::ReadOnlyError);
+        }
+        if heap_size_in_bytes <= MARGIN_IN_BYTES as usize {
+            return Err(Error::InvalidArgument(format!("The heap size of the segment builder needs to be larger than {}.",
+                MARGIN_IN_BYTES)));
+        }
+        let opstamp = self.stamp();
+        let heap = Heap::with_capacity(heap_size_in_bytes);
+        let segment = self.segment_updater.new_segment();
+        let segment_id = segment.id();
+        let segment_meta_res = SegmentBuilder::for_segment(
+                &heap,
+                segment,
+                self.index.schema(),
+                self.get_term_hash_function(),
+                &self.computed_fields,
+                opstamp)
+            .and_then(|mut segment_builder| {
+                segment_builder.set_max_tokens_per_field(self.get_max_tokens_per_field());
+                // the segment is written even if `build` fails,
+                // so that its files are closed properly.
+                let build_res = build(&mut segment_builder);
+                let segment_meta = try!(segment_builder.finalize());
+                build_res.map(|_| segment_meta)
+            });
+        let segment_meta = match segment_meta_res {
+            Ok(ref segment_meta) if segment_meta.max_doc() > 0 => segment_meta.clone(),
+            Ok(_) => {
+                self.segment_updater.abort_write(&segment_id);
+                return Ok(opstamp);
+            }
+            Err(e) => {
+                self.segment_updater.abort_write(&segment_id);
+                return Err(e);
+            }
+        };
+        let num_docs = segment_meta.max_doc();
+        let doc_opstamps = vec![opstamp; num_docs as usize];
+        let mut segment_entry = SegmentEntry::new(segment_meta);
+        segment_entry.set_doc_to_opstamp(DocToOpstampMapping::from(doc_opstamps));
+        self.segment_updater.add_segment(self.generation, segment_entry);
+        self.push_add_operations(opstamp, num_docs);
+        Ok(opstamp)
+    }
+
     /// Closes the current document channel send.
     /// and replace all the channels by new ones.
     ///
@@ -427,12 +1024,169 @@ impl IndexWriter {
         )?;
 
         self.delete_queue.clear();
+        self.pending_operations.clear();
 
         // reset the opstamp
         self.uncommitted_opstamp = self.committed_opstamp;
         Ok(self.committed_opstamp)
     }
 
+    /// Stops the indexing workers once they have indexed all of
+    /// the pending documents, and starts new ones.
+    ///
+    /// Returns the entries of the segments the former workers were
+    /// writing, which are not added to the segment updater yet.
+    fn flush_workers(&mut self) -> Result<Vec<SegmentEntry>> {
+        // this will drop the current document channel
+        // and recreate a new one channels.
+        self.recreate_document_channel();
+
+        let mut former_workers_join_handle = Vec::new();
+        swap(&mut former_workers_join_handle,
+             &mut self.workers_join_handle);
+        
+        let mut segment_entries = Vec::new();
+        for worker_handle in former_workers_join_handle {
+            let indexing_worker_result = try!(worker_handle.join()
+                .map_err(|e| Error::ErrorInThread(format!("{:?}", e))));
+            segment_entries.extend(try!(indexing_worker_result));
+            // add a new worker for the next generation.
+            try!(self.add_indexing_worker());
+        }
+        Ok(segment_entries)
+    }
+
+    /// Flushes the documents being indexed into a new segment,
+    /// without committing it.
+    ///
+    /// Returns the meta of the new segment, or `None` if there
+    /// was no document to flush. Each indexing thread writes its
+    /// own segment: when several of them have documents,
+    /// their segments are merged into the new segment.
+    /// The segments written earlier because the indexing heap
+    /// was full are left as they are.
+    ///
+    /// Like the documents it contains, the segment is
+    /// only searchable after calling `commit()`, and it
+    /// is discarded by `rollback()`. It is searchable
+    /// right away by a near-real-time searcher.
+    /// See `nrt_searcher()`.
+    ///
+    /// # Errors
+    /// If the segments of the indexing threads cannot be merged,
+    /// they are added as they are, and the error is returned.
+    pub fn flush(&mut self) -> Result<Option<SegmentMeta>> {
+        let mut segment_entries = try!(self.flush_workers());
+        let segment_entry = match segment_entries.len() {
+            0 => {
+                return Ok(None);
+            }
+            1 => segment_entries.pop().expect("There is one segment entry"),
+            _ => {
+                match self.merge_flushed_segments(&segment_entries) {
+                    Ok(merged_segment_entry) => {
+                        self.delete_flushed_segments(&segment_entries);
+                        merged_segment_entry
+                    }
+                    Err(e) => {
+                        for segment_entry in segment_entries {
+                            self.segment_updater.add_segment(self.generation, segment_entry);
+                        }
+                        return Err(e);
+                    }
+                }
+            }
+        };
+        let segment_meta = segment_entry.meta().clone();
+        try!(self.segment_updater.add_segment_and_wait(self.generation, segment_entry));
+        Ok(Some(segment_meta))
+    }
+
+    // Merges the segments flushed by the indexing threads into a
+    // new segment, whose documents keep their opstamps.
+    fn merge_flushed_segments(&self, segment_entries: &[SegmentEntry]) -> Result<SegmentEntry> {
+        let segments: Vec<Segment> = segment_entries
+            .iter()
+            .map(|segment_entry| self.index.segment(segment_entry.meta().clone()))
+            .collect();
+        let mut merged_segment = self.segment_updater.new_segment();
+        let merged_segment_id = merged_segment.id();
+        let merge_result = IndexMerger::open(self.index.schema(), &segments)
+            .and_then(|merger| {
+                let segment_serializer = try!(SegmentSerializer::for_segment(&mut merged_segment));
+                let num_docs = try!(merger.write(segment_serializer));
+                Ok((num_docs, merger.merged_doc_ids()))
+            });
+        let (num_docs, merged_doc_ids) = match merge_result {
+            Ok(merge_output) => merge_output,
+            Err(e) => {
+                self.segment_updater.abort_write(&merged_segment_id);
+                return Err(e);
+            }
+        };
+        let mut segment_meta = SegmentMeta::new(merged_segment_id);
+        segment_meta.set_max_doc(num_docs);
+        segment_meta.set_store_compressor(merged_segment.meta().store_compressor());
+        segment_meta.set_store_block_size(merged_segment.meta().store_block_size());
+        segment_meta.set_has_fieldnorms(self.index.schema().has_fieldnorms());
+        segment_meta.set_has_term_vectors(self.index.schema().has_term_vectors());
+        if let Some(opstamp) = segment_entries.iter().filter_map(|segment_entry| segment_entry.meta().opstamp()).max() {
+            segment_meta.set_opstamp(opstamp);
+        }
+        let mut doc_opstamps = vec![0u64; num_docs as usize];
+        for segment_entry in segment_entries {
+            for doc_id in 0..segment_entry.meta().max_doc() {
+                if let Some((_, merged_doc_id)) = merged_doc_ids.get(&segment_entry.segment_id(), doc_id) {
+                    doc_opstamps[merged_doc_id as usize] = segment_entry.doc_to_opstamp().opstamp(doc_id).unwrap_or(0u64);
+                }
+            }
+        }
+        let mut segment_entry = SegmentEntry::new(segment_meta);
+        segment_entry.set_doc_to_opstamp(DocToOpstampMapping::from(doc_opstamps));
+        Ok(segment_entry)
+    }
+
+    // Deletes the files of the segments flushed by the
+    // indexing threads, once they have been merged.
+    fn delete_flushed_segments(&self, segment_entries: &[SegmentEntry]) {
+        let mut files = Vec::new();
+        for segment_entry in segment_entries {
+            self.segment_updater.abort_write(&segment_entry.segment_id());
+            files.extend(segment_entry.meta().list_files());
+        }
+        self.index.clone().directory_mut().delete_managed_files(&files);
+    }
+
+    /// Opens a near-real-time searcher.
+    ///
+    /// Unlike the searchers of the `Index`, it searches the
+    /// uncommitted segments as well as the committed ones,
+    /// so that the documents are searchable before `commit()`
+    /// is called. Only the documents that have been flushed
+    /// into segments are visible: see `flush()`.
+    ///
+    /// The searcher works on a snapshot of the segment set.
+    /// It is not affected by the merges, commits and rollbacks
+    /// happening after it is opened: the files of its segments
+    /// are not deleted as long as it is alive.
+    ///
+    /// The deletes of the current transaction are not visible,
+    /// as they are only applied to the segments on commit.
+    pub fn nrt_searcher(&self) -> Result<Searcher> {
+        self.reopen_nrt_searcher(Searcher::from(Vec::new()))
+    }
+
+    /// Reopens a near-real-time searcher, to take into account
+    /// the segments added, merged or removed since it was opened.
+    ///
+    /// The segment readers of `searcher` that are still up to date
+    /// are reused, so that only the new segments are opened.
+    pub fn reopen_nrt_searcher(&self, searcher: Searcher) -> Result<Searcher> {
+        let segment_readers = self.segment_updater
+            .open_nrt_segment_readers(searcher.into_segment_readers())?;
+        Ok(Searcher::from(segment_readers))
+    }
+
     /// Commits all of the pending changes
     ///
     /// A call to commit blocks.
@@ -440,15 +1194,76 @@ impl IndexWriter {
     /// were added since the last commit are published
     /// and persisted.
     ///
+    /// The documents being indexed are flushed into new segments
+    /// first, like with `flush()`, except that the segments of the
+    /// indexing threads are not merged together.
+    ///
     /// In case of a crash or an hardware failure (as
     /// long as the hard disk is spared), it will be possible
     /// to resume indexing from this point.
     ///
-    /// Commit returns the `opstamp` of the last document
-    /// that made it in the commit.
+    /// Commit returns the `opstamp` of the commit, which is
+    /// greater than the opstamps of all of the operations it publishes.
+    /// Each commit gets a new opstamp, even if there was
+    /// no pending change. The metas of the committed segments
+    /// are then available with `committed_segment_metas()`.
     ///
+    /// # Errors
+    /// If the index has been frozen, returns `Error::ReadOnlyError`.
+    /// The pending changes are kept, and can be discarded with
+    /// `rollback()`.
+    /// The same goes if the commit fails, e.g. when a pending delete
+    /// query cannot be run: the pending operations are published
+    /// by the next commit.
     pub fn commit(&mut self) -> Result<u64> {
+        self.commit_generation(None)
+    }
+
+    /// Commits all of the pending changes, like `commit()`, and labels
+    /// the segments published by this commit with `label`.
+    ///
+    /// The label is stored in the `SegmentMeta` of the segments,
+    /// which makes it possible to search the segments of a given label only,
+    /// with `Searcher::search_label`, or to drop them with `drop_segment`.
+    /// Several commits may share the same label.
+    ///
+    /// Segments with different labels are never merged together
+    /// by the merge policy. A segment resulting from a merge keeps
+    /// the label of its segments if they all share the same one.
+    pub fn commit_with_label(&mut self, label: &str) -> Result<u64> {
+        self.commit_generation(Some(String::from(label)))
+    }
+
+    fn commit_generation(&mut self, label: Option<String>) -> Result<u64> {
 
+        if self.index.is_frozen() {
+            return Err(Error::ReadOnlyError);
+        }
+
+        let mut operations = mem::replace(&mut self.pending_operations, Vec::new());
+        let commit_opstamp = self.stamp();
+        info!("committing {}", commit_opstamp);
+        operations.push(Operation::Commit { opstamp: commit_opstamp });
+
+        // the committed opstamp is only updated once the commit
+        // succeeded. Otherwise, the operations are pending again,
+        // and are logged by the next commit.
+        match self.commit_operations(commit_opstamp, &operations, label) {
+            Ok(committed_segment_metas) => {
+                self.committed_opstamp = commit_opstamp;
+                self.committed_segment_metas = committed_segment_metas;
+                self.delete_queue.clear();
+                Ok(commit_opstamp)
+            }
+            Err(e) => {
+                operations.pop();
+                self.pending_operations = operations;
+                Err(e)
+            }
+        }
+    }
+
+    fn commit_operations(&mut self, commit_opstamp: u64, operations: &[Operation], label: Option<String>) -> Result<Vec<SegmentMeta>> {
         // here, because we join all of the worker threads,
         // all of the segment update for this commit have been
         // sent.
@@ -456,37 +1271,43 @@ impl IndexWriter {
         // No document belonging to the next generation have been
         // pushed too, because add_document can only happen
         // on this thread.
+        for segment_entry in try!(self.flush_workers()) {
+            self.segment_updater.add_segment(self.generation, segment_entry);
+        }
 
-        // This will move uncommitted segments to the state of
-        // committed segments.
-        self.committed_opstamp = self.stamp();
-        info!("committing {}", self.committed_opstamp);
+        // the operations are logged before the meta is saved:
+        // a log file which is not reachable from the meta
+        // is simply ignored.
+        append_operation_log(self.index.directory_mut(),
+                             self.committed_opstamp,
+                             commit_opstamp,
+                             operations)?;
 
-        // this will drop the current document channel
-        // and recreate a new one channels.
-        self.recreate_document_channel();
+        // This will move uncommitted segments to the state of
+        // committed segments, and wait for the segment update
+        // thread to have processed the info.
+        self.segment_updater.commit(commit_opstamp, label)
+    }
 
-        let mut former_workers_join_handle = Vec::new();
-        swap(&mut former_workers_join_handle,
-             &mut self.workers_join_handle);
-        
-        for worker_handle in former_workers_join_handle {
-            let indexing_worker_result = try!(worker_handle.join()
-                .map_err(|e| Error::ErrorInThread(format!("{:?}", e))));
-            try!(indexing_worker_result);
-            // add a new worker for the next generation.
-            try!(self.add_indexing_worker());
+    // logs the documents of a segment added as a whole,
+    // which share the same opstamp.
+    fn push_add_operations(&mut self, opstamp: u64, num_docs: u32) {
+        for _ in 0..num_docs {
+            self.pending_operations.push(Operation::Add { opstamp: opstamp });
         }
+    }
 
-        
-
-        // wait for the segment update thread to have processed the info
-        self.segment_updater
-            .commit(self.committed_opstamp)?;
-        
-        self.delete_queue.clear();
-        Ok(self.committed_opstamp)
-    }    
+    /// Removes the operations preceding the last commit
+    /// whose opstamp is lower or equal to `opstamp`
+    /// from the operation log, along with this commit.
+    ///
+    /// Returns the number of operations removed.
+    ///
+    /// See `Index::operation_log`.
+    pub fn compact_operation_log(&mut self, opstamp: u64) -> Result<usize> {
+        let last_commit_opstamp = self.committed_opstamp;
+        compact_operation_log(self.index.directory_mut(), last_commit_opstamp, opstamp)
+    }
 
     /// Delete all documents containing a given term.
     ///
@@ -498,39 +1319,215 @@ impl IndexWriter {
     /// only after calling `commit()`.
     pub fn delete_term(&mut self, term: Term) -> u64 {
         let opstamp = self.stamp();
-        let delete_operation = DeleteOperation {
+        self.pending_operations.push(Operation::Delete {
             opstamp: opstamp,
-            term: term,
-        };
-        self.delete_queue.push(delete_operation);
+            term: term.clone(),
+        });
+        self.delete_queue.push(DeleteOperation {
+            opstamp: opstamp,
+            target: DeleteTarget::Term(term),
+        });
         opstamp
     }
 
-    fn stamp(&mut self) -> u64 {
-        let opstamp = self.uncommitted_opstamp;
-        self.uncommitted_opstamp += 1u64;
+    /// Delete all documents matching a given query.
+    ///
+    /// As with `delete_term(...)`, only the documents added
+    /// before the delete operation are affected, and the deletion
+    /// is visible only after calling `commit()`.
+    ///
+    /// The query is run on each of the segments independently,
+    /// when the delete is applied. The operation log only records
+    /// the opstamp of the delete, see `Operation::DeleteQuery`.
+    pub fn delete_query(&mut self, query: Box<Query>) -> u64 {
+        let opstamp = self.stamp();
+        self.pending_operations.push(Operation::DeleteQuery { opstamp: opstamp });
+        self.delete_queue.push(DeleteOperation {
+            opstamp: opstamp,
+            target: DeleteTarget::Query(Arc::from(query)),
+        });
         opstamp
     }
 
-    /// Adds a document.
+    /// Replaces all of the documents matching `query` by `documents`.
     ///
-    /// If the indexing pipeline is full, this call may block.
+    /// This deletes the documents matching the query, as
+    /// `delete_query(...)` does, and then adds the new documents.
+    /// As they get later opstamps, the new documents are never
+    /// deleted by the query, even if they match it.
     ///
-    /// The opstamp is an increasing `u64` that can
-    /// be used by the client to align commits with its own
-    /// document queue.
+    /// Like any other operation, the replacement becomes visible
+    /// at once with `commit()`, or is discarded by `rollback()`.
     ///
-    /// Currently it represents the number of documents that
-    /// have been added since the creation of the index.
-    pub fn add_document(&mut self, document: Document) -> u64 {
-        let opstamp = self.stamp();
-        let add_operation = AddOperation {
-            opstamp: opstamp,
-            document: document,
+    /// The new documents are added as a block, see `add_document_block`.
+    ///
+    /// Returns the opstamp of the last operation.
+    ///
+    /// # Errors
+    /// If the documents do not fit in the memory budget of an indexing
+    /// thread and the `OversizedDocumentPolicy` is `Reject`, nothing
+    /// is replaced : the documents are discarded, the delete is not
+    /// queued, and `Error::InvalidArgument` is returned.
+    pub fn replace_documents(&mut self, query: Box<Query>, documents: Vec<Document>) -> Result<u64> {
+        if documents.is_empty() {
+            return Ok(self.delete_query(query));
+        }
+        // the delete is stamped first, so that it does not affect the new
+        // documents, but only queued once the documents are accepted.
+        let delete_opstamp = self.stamp();
+        let (add_operations, opstamp) = match self.stamp_document_block(documents) {
+            Ok(stamped) => stamped,
+            Err(opstamp) => return Err(self.oversized_error(opstamp)),
         };
-        self.document_sender.send(add_operation);
+        self.pending_operations.push(Operation::DeleteQuery { opstamp: delete_opstamp });
+        self.delete_queue.push(DeleteOperation {
+            opstamp: delete_opstamp,
+            target: DeleteTarget::Query(Arc::from(query)),
+        });
+        self.send_add_operations(add_operations);
+        Ok(opstamp)
+    }
+
+    /// Returns the number of delete operations
+    /// that have not been committed yet.
+    ///
+    /// This number is reset to 0 by `commit()` and `rollback()`.
+    pub fn pending_deletes(&self) -> usize {
+        self.delete_queue.len()
+    }
+
+    fn stamp(&mut self) -> u64 {
+        let opstamp = self.uncommitted_opstamp;
+        self.uncommitted_opstamp += 1u64;
         opstamp
     }
+
+    /// Adds a document.
+    ///
+    /// If the indexing pipeline is full, this call may block.
+    ///
+    /// The opstamp is an increasing `u64` that can
+    /// be used by the client to align commits with its own
+    /// document queue.
+    ///
+    /// Currently it represents the number of documents that
+    /// have been added since the creation of the index.
+    ///
+    /// If the index has been frozen, the document is discarded.
+    ///
+    /// If the document does not fit in the memory budget of
+    /// an indexing thread, see `OversizedDocumentPolicy`.
+    pub fn add_document(&mut self, document: Document) -> u64 {
+        self.add_document_block(vec![document])
+    }
+
+    /// Adds a document, like `add_document`.
+    ///
+    /// # Errors
+    /// If the document does not fit in the memory budget of an indexing
+    /// thread and the `OversizedDocumentPolicy` is `Reject`, the document
+    /// is discarded, and `Error::InvalidArgument` is returned.
+    pub fn try_add_document(&mut self, document: Document) -> Result<u64> {
+        self.try_add_document_block(vec![document])
+    }
+
+    /// Adds a block of documents.
+    ///
+    /// The documents are indexed contiguously, in the given order,
+    /// and within the same segment. Merges preserve this order, even
+    /// when the index is sorted or the merged segments are capped.
+    /// This is what `ToParentBlockJoinQuery` relies on: a block
+    /// consists of the child documents, followed by their parent.
+    ///
+    /// Each document gets its own opstamp. The opstamp of the
+    /// last document of the block is returned.
+    ///
+    /// Documents of a block should be deleted together, for instance
+    /// by deleting a term that all of them contain.
+    ///
+    /// If the index has been frozen, the documents are discarded.
+    ///
+    /// # Panics
+    /// Panics if `documents` is empty.
+    pub fn add_document_block(&mut self, documents: Vec<Document>) -> u64 {
+        match self.send_document_block(documents) {
+            Ok(opstamp) => opstamp,
+            Err(opstamp) => {
+                error!("The documents up to opstamp {} do not fit in the memory budget, and are discarded.", opstamp);
+                opstamp
+            }
+        }
+    }
+
+    /// Adds a block of documents, like `add_document_block`.
+    ///
+    /// # Errors
+    /// If the block does not fit in the memory budget of an indexing
+    /// thread and the `OversizedDocumentPolicy` is `Reject`, the documents
+    /// are discarded, and `Error::InvalidArgument` is returned.
+    ///
+    /// # Panics
+    /// Panics if `documents` is empty.
+    pub fn try_add_document_block(&mut self, documents: Vec<Document>) -> Result<u64> {
+        self.send_document_block(documents)
+            .map_err(|opstamp| self.oversized_error(opstamp))
+    }
+
+    fn oversized_error(&self, opstamp: u64) -> Error {
+        Error::InvalidArgument(format!("The documents up to opstamp {} do not fit in the memory budget of {} bytes.",
+                                       opstamp,
+                                       self.heap_size_in_bytes_per_thread))
+    }
+
+    // Stamps the documents and sends them to the indexing threads.
+    // The documents rejected by the `OversizedDocumentPolicy`
+    // are not sent, in which case their last opstamp is returned as an error.
+    fn send_document_block(&mut self, documents: Vec<Document>) -> result::Result<u64, u64> {
+        let (add_operations, opstamp) = try!(self.stamp_document_block(documents));
+        self.send_add_operations(add_operations);
+        Ok(opstamp)
+    }
+
+    // Stamps the documents and computes their computed fields, without
+    // sending them. Returns their add operations, which are empty if
+    // the index is frozen, and the opstamp of the last document.
+    // The documents rejected by the `OversizedDocumentPolicy` are
+    // discarded, in which case their last opstamp is returned as an error.
+    fn stamp_document_block(&mut self, documents: Vec<Document>) -> result::Result<(Vec<AddOperation>, u64), u64> {
+        assert!(!documents.is_empty(), "A block must contain at least one document.");
+        let is_frozen = self.index.is_frozen();
+        let mut add_operations = Vec::with_capacity(documents.len());
+        let mut opstamp = 0u64;
+        for mut document in documents {
+            opstamp = self.stamp();
+            if is_frozen {
+                warn!("The index is frozen. Document {} is discarded.", opstamp);
+                continue;
+            }
+            for computed_field in &self.computed_fields {
+                computed_field.populate(&mut document);
+            }
+            add_operations.push(AddOperation {
+                opstamp: opstamp,
+                document: document,
+            });
+        }
+        if self.oversized_document_policy == OversizedDocumentPolicy::Reject &&
+           exceeds_heap(&self.index.schema(), &self.tokenizers, &add_operations, self.heap_size_in_bytes_per_thread as u32) {
+            return Err(opstamp);
+        }
+        Ok((add_operations, opstamp))
+    }
+
+    // Logs the add operations, and sends them to the indexing threads.
+    fn send_add_operations(&mut self, add_operations: Vec<AddOperation>) {
+        for add_operation in &add_operations {
+            self.pending_operations.push(Operation::Add { opstamp: add_operation.opstamp });
+        }
+        if !add_operations.is_empty() {
+            self.document_sender.send(add_operations);
+        }
+    }
 }
 
 
@@ -540,11 +1537,41 @@ impl IndexWriter {
 mod tests {
 
     use indexer::NoMergePolicy;
-    use schema::{self, Document};
+    use indexer::{MergeCandidate, MergePolicy};
+    use core::SegmentMeta;
+    use std::sync::{Arc, Mutex, mpsc};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use super::{HEAP_SIZE_LIMIT, MARGIN_IN_BYTES};
+    use super::OversizedDocumentPolicy;
+    use store::Compressor;
+    use futures::Future;
+    use schema::{self, Document, Field, MaxTermLength, TermLengthPolicy};
+    use schema::FieldValue;
     use Index;
+    use SegmentReader;
+    use SegmentId;
     use Term;
+    use TermHashFunction;
+    use core::SegmentComponent;
     use Error;
     use env_logger;
+    use DocAddress;
+    use indexer::ComputedField;
+    use query::{Query, QueryParser, TermQuery, Weight};
+    use Searcher;
+    use Result;
+    use std::any::Any;
+    use postings::SegmentPostingsOption;
+    use collector::tests::TestCollector;
+    use collector::TopCollector;
+    use std::io::Write;
+    use Directory;
+    use std::path::Path;
+    use indexer::LOCKFILE_NAME;
+    use std::path::PathBuf;
+    use core::META_FILEPATH;
+    use indexer::Operation;
+    use indexer::Column;
 
     #[test]
     fn test_lockfile_stops_duplicates() {
@@ -562,7 +1589,7 @@ mod tests {
         let schema_builder = schema::SchemaBuilder::default();
         let index = Index::create_in_ram(schema_builder.build());
         let index_writer = index.writer(40_000_000).unwrap();
-        assert_eq!(format!("{:?}", index_writer.get_merge_policy()), "LogMergePolicy { min_merge_size: 8, min_layer_size: 10000, level_log_size: 0.75 }");
+        assert_eq!(format!("{:?}", index_writer.get_merge_policy()), "LogMergePolicy { min_merge_size: 8, min_layer_size: 10000, level_log_size: 0.75, max_docs_per_segment: None }");
         let merge_policy = box NoMergePolicy::default();
         index_writer.set_merge_policy(merge_policy);
         assert_eq!(format!("{:?}", index_writer.get_merge_policy()), "NoMergePolicy");
@@ -580,6 +1607,96 @@ mod tests {
         let _index_writer_two = index.writer(40_000_000).unwrap();
     }
     
+    #[test]
+    fn test_freeze() {
+        let mut schema_builder = schema::SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", schema::TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let num_docs_containing = |s: &str| {
+            let searcher = index.searcher();
+            searcher.doc_freq(&Term::from_field_text(text_field, s))
+        };
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        index_writer.add_document(doc!(text_field=>"a"));
+        index_writer.commit().unwrap();
+        assert!(!index.is_frozen());
+        index.freeze(false).unwrap();
+        assert!(index.is_frozen());
+        assert!(index.clone().is_frozen());
+        match index.writer(40_000_000) {
+            Err(Error::ReadOnlyError) => {}
+            _ => panic!("Expected ReadOnlyError error"),
+        }
+        index_writer.add_document(doc!(text_field=>"a b"));
+        match index_writer.commit() {
+            Err(Error::ReadOnlyError) => {}
+            _ => panic!("Expected ReadOnlyError error"),
+        }
+        match index_writer.add_index(&index) {
+            Err(Error::ReadOnlyError) => {}
+            _ => panic!("Expected ReadOnlyError error"),
+        }
+        index_writer.rollback().unwrap();
+        drop(index_writer);
+        index.load_searchers().unwrap();
+        assert_eq!(num_docs_containing("a"), 1);
+        assert_eq!(num_docs_containing("b"), 0);
+        let mut test_collector = TestCollector::default();
+        let query = TermQuery::new(Term::from_field_text(text_field, "a"), SegmentPostingsOption::NoFreq);
+        index.searcher().search(&query, &mut test_collector).unwrap();
+        assert_eq!(test_collector.docs(), vec!(0));
+    }
+
+    #[test]
+    fn test_freeze_releases_stale_lockfile() {
+        let schema_builder = schema::SchemaBuilder::default();
+        let index = Index::create_in_ram(schema_builder.build());
+        let lockfile_path = Path::new(LOCKFILE_NAME);
+        let mut directory = index.directory().clone();
+        directory.open_write(lockfile_path).unwrap();
+        index.freeze(false).unwrap();
+        assert!(index.directory().exists(lockfile_path));
+        index.freeze(true).unwrap();
+        assert!(!index.directory().exists(lockfile_path));
+        // releasing an absent lock is not an error.
+        index.freeze(true).unwrap();
+    }
+
+    #[test]
+    fn test_freeze_keeps_the_lockfile_of_a_live_writer() {
+        let schema_builder = schema::SchemaBuilder::default();
+        let index = Index::create_in_ram(schema_builder.build());
+        let lockfile_path = Path::new(LOCKFILE_NAME);
+        let index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        index.freeze(true).unwrap();
+        assert!(index.directory().exists(lockfile_path));
+        drop(index_writer);
+        assert!(!index.directory().exists(lockfile_path));
+    }
+
+    #[test]
+    fn test_freeze_is_persisted() {
+        use tempdir::TempDir;
+        let tempdir = TempDir::new("index").unwrap();
+        let mut schema_builder = schema::SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", schema::TEXT);
+        let index = Index::create(tempdir.path(), schema_builder.build()).unwrap();
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.add_document(doc!(text_field=>"a"));
+            index_writer.commit().unwrap();
+        }
+        assert!(!Index::open(tempdir.path()).unwrap().is_frozen());
+        index.freeze(false).unwrap();
+        let index = Index::open(tempdir.path()).unwrap();
+        assert!(index.is_frozen());
+        assert_eq!(index.searcher().num_docs(), 1);
+        match index.writer(40_000_000) {
+            Err(Error::ReadOnlyError) => {}
+            _ => panic!("Expected ReadOnlyError error"),
+        }
+    }
+
     #[test]
     fn test_commit_and_rollback() {
         let mut schema_builder = schema::SchemaBuilder::default();
@@ -659,5 +1776,1633 @@ mod tests {
         }
     }
 
+    /// Merges all of the segments it is given, as one merge,
+    /// as soon as there are two of them.
+    #[derive(Debug, Clone)]
+    struct MergeAllPolicy;
+
+    impl MergePolicy for MergeAllPolicy {
+        fn compute_merge_candidates(&self, segments: &[SegmentMeta]) -> Vec<MergeCandidate> {
+            if segments.len() < 2 {
+                return Vec::new();
+            }
+            let segment_ids = segments
+                .iter()
+                .map(|segment_meta| segment_meta.id())
+                .collect();
+            vec!(MergeCandidate(segment_ids))
+        }
 
+        fn box_clone(&self) -> Box<MergePolicy> {
+            box MergeAllPolicy
+        }
+    }
+
+    /// Never suggests any merge, and makes the first merge panic.
+    #[derive(Debug, Clone)]
+    struct PanicOnceMergePolicy {
+        panic_once: Arc<AtomicBool>,
+    }
+
+    impl MergePolicy for PanicOnceMergePolicy {
+        fn compute_merge_candidates(&self, _segments: &[SegmentMeta]) -> Vec<MergeCandidate> {
+            Vec::new()
+        }
+
+        fn box_clone(&self) -> Box<MergePolicy> {
+            box self.clone()
+        }
+
+        // called by the merging thread.
+        fn dedup_field(&self) -> Option<Field> {
+            if self.panic_once.swap(false, Ordering::SeqCst) {
+                panic!("The merge failed");
+            }
+            None
+        }
+    }
+
+    #[test]
+    fn test_merge_threads() {
+        let mut schema_builder = schema::SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", schema::TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        assert_eq!(index_writer.get_merge_threads(), None);
+        assert!(!index_writer.get_merge_backpressure());
+        index_writer.set_merge_threads(1);
+        index_writer.set_merge_backpressure(true);
+        assert_eq!(index_writer.get_merge_threads(), Some(1));
+        assert!(index_writer.get_merge_backpressure());
+        // the merges are only started by the commits,
+        // each one starting at most one merge.
+        index_writer.set_merge_policy(box MergeAllPolicy);
+        for _ in 0..100 {
+            index_writer.add_document(doc!(text_field=>"a"));
+            index_writer.commit().expect("commit failed");
+            // the commit waited for the merges queued by the previous ones,
+            // so that at most the merge of this commit is pending.
+            assert!(index_writer.num_pending_merges() <= 1);
+        }
+        index_writer.wait_merging_threads().expect("waiting merging thread failed");
+        index.load_searchers().unwrap();
+        assert_eq!(index.searcher().num_docs(), 100);
+        assert!(index.searchable_segments().unwrap().len() < 16);
+    }
+
+    #[test]
+    fn test_merge_threads_panic() {
+        let mut schema_builder = schema::SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", schema::TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        index_writer.set_merge_threads(1);
+        index_writer.set_merge_policy(box PanicOnceMergePolicy {
+            panic_once: Arc::new(AtomicBool::new(true)),
+        });
+        for _ in 0..4 {
+            index_writer.add_document(doc!(text_field=>"a"));
+            index_writer.commit().expect("commit failed");
+        }
+        let segment_ids = index.searchable_segment_ids().unwrap();
+        assert_eq!(segment_ids.len(), 4);
+        let failing_merge = index_writer.merge(&segment_ids[..2]);
+        // queued until the first merge releases its merge thread.
+        let queued_merge = index_writer.merge(&segment_ids[2..]);
+        assert!(failing_merge.wait().is_err());
+        assert_eq!(queued_merge.wait().unwrap().len(), 1);
+        assert_eq!(index_writer.num_pending_merges(), 0);
+        // the panic is still reported.
+        assert!(index_writer.wait_merging_threads().is_err());
+    }
+
+    #[test]
+    fn test_failed_merge_segments_ready() {
+        use indexer::SegmentState;
+        let mut schema_builder = schema::SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", schema::TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        index_writer.set_merge_policy(box NoMergePolicy::default());
+        for _ in 0..2 {
+            index_writer.add_document(doc!(text_field=>"a"));
+            index_writer.commit().expect("commit failed");
+        }
+        let segment_metas = index.segments().unwrap();
+        let segment_ids: Vec<SegmentId> = segment_metas.iter().map(|segment_meta| segment_meta.id()).collect();
+        // the segment cannot be opened for the merge.
+        let mut directory = index.directory().box_clone();
+        let postings_path = segment_metas[0].relative_path(SegmentComponent::POSTINGS);
+        let postings_data = directory.atomic_read(&postings_path).unwrap();
+        directory.delete(&postings_path).unwrap();
+        assert!(index_writer.merge(&segment_ids).wait().is_err());
+        for segment_id in &segment_ids {
+            assert!(index_writer.segment_states().contains(&(*segment_id, SegmentState::Ready)));
+        }
+        directory.atomic_write(&postings_path, &postings_data).unwrap();
+        let segment_entries = index_writer.merge(&segment_ids).wait().unwrap();
+        assert_eq!(segment_entries.len(), 1);
+        assert_eq!(segment_entries[0].meta().num_docs(), 2);
+        index_writer.wait_merging_threads().unwrap();
+    }
+
+    /// Never suggests any merge, and blocks the merges
+    /// until `resume` receives a message, once `started` did.
+    #[derive(Debug, Clone)]
+    struct BlockingMergePolicy {
+        started: Arc<Mutex<mpsc::Sender<()>>>,
+        resume: Arc<Mutex<mpsc::Receiver<()>>>,
+    }
+
+    impl MergePolicy for BlockingMergePolicy {
+        fn compute_merge_candidates(&self, _segments: &[SegmentMeta]) -> Vec<MergeCandidate> {
+            Vec::new()
+        }
+
+        fn box_clone(&self) -> Box<MergePolicy> {
+            box self.clone()
+        }
+
+        // called by the merging thread.
+        fn dedup_field(&self) -> Option<Field> {
+            self.started.lock().unwrap().send(()).unwrap();
+            self.resume.lock().unwrap().recv().unwrap();
+            None
+        }
+    }
+
+    #[test]
+    fn test_failed_merged_deletes_segments_ready() {
+        use indexer::SegmentState;
+        let mut schema_builder = schema::SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", schema::TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        let (started_send, started_recv) = mpsc::channel();
+        let (resume_send, resume_recv) = mpsc::channel();
+        index_writer.set_merge_policy(box BlockingMergePolicy {
+            started: Arc::new(Mutex::new(started_send)),
+            resume: Arc::new(Mutex::new(resume_recv)),
+        });
+        for text in &["a", "b"] {
+            index_writer.add_document(doc!(text_field=>*text));
+            index_writer.commit().expect("commit failed");
+        }
+        let segment_ids = index.searchable_segment_ids().unwrap();
+        let merge = index_writer.merge(&segment_ids);
+        started_recv.recv().unwrap();
+        // the delete is committed while the merge is running, and its
+        // delete file cannot be read back when the merge ends.
+        index_writer.delete_term(Term::from_field_text(text_field, "a"));
+        index_writer.commit().expect("commit failed");
+        let mut directory = index.directory().box_clone();
+        let delete_path = index
+            .segments()
+            .unwrap()
+            .into_iter()
+            .find(|segment_meta| segment_meta.num_deleted_docs() == 1)
+            .unwrap()
+            .relative_path(SegmentComponent::DELETE);
+        let delete_data = directory.atomic_read(&delete_path).unwrap();
+        directory.delete(&delete_path).unwrap();
+        resume_send.send(()).unwrap();
+        assert!(merge.wait().is_err());
+        for segment_id in &segment_ids {
+            assert!(index_writer.segment_states().contains(&(*segment_id, SegmentState::Ready)));
+        }
+        // the segments are merged once the delete file is back.
+        directory.atomic_write(&delete_path, &delete_data).unwrap();
+        let merge = index_writer.merge(&segment_ids);
+        started_recv.recv().unwrap();
+        resume_send.send(()).unwrap();
+        let segment_entries = merge.wait().unwrap();
+        assert_eq!(segment_entries.len(), 1);
+        assert_eq!(segment_entries[0].meta().num_docs(), 1);
+        index_writer.wait_merging_threads().unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "There must be at least one merge thread.")]
+    fn test_merge_threads_zero() {
+        let schema_builder = schema::SchemaBuilder::default();
+        let index = Index::create_in_ram(schema_builder.build());
+        let index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        index_writer.set_merge_threads(0);
+    }
+
+    #[test]
+    fn test_merge_all() {
+        let mut schema_builder = schema::SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", schema::TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        index_writer.set_merge_policy(box NoMergePolicy::default());
+        assert!(index_writer.merge_all().wait().unwrap().is_empty());
+        for text in &["a", "b", "c"] {
+            index_writer.add_document(doc!(text_field=>*text));
+            index_writer.commit().expect("commit failed");
+        }
+        index.load_searchers().unwrap();
+        assert_eq!(index.searchable_segment_ids().unwrap().len(), 3);
+        let segment_entries = index_writer.merge_all().wait().unwrap();
+        assert_eq!(segment_entries.len(), 1);
+        let segment_entry = &segment_entries[0];
+        assert_eq!(segment_entry.meta().num_docs(), 3);
+        index.load_searchers().unwrap();
+        assert_eq!(index.searchable_segment_ids().unwrap(), vec!(segment_entry.segment_id()));
+        assert_eq!(index.searcher().num_docs(), 3);
+        assert!(index_writer.merge_all().wait().unwrap().is_empty());
+        // the deleted documents of a single segment are purged.
+        index_writer.delete_term(Term::from_field_text(text_field, "a"));
+        index_writer.commit().expect("commit failed");
+        let segment_entries = index_writer.merge_all().wait().unwrap();
+        assert_eq!(segment_entries.len(), 1);
+        assert_eq!(segment_entries[0].meta().max_doc(), 2);
+        assert_eq!(segment_entries[0].meta().num_deleted_docs(), 0);
+        index.load_searchers().unwrap();
+        assert_eq!(index.searcher().num_docs(), 2);
+        assert!(index_writer.merge_all().wait().unwrap().is_empty());
+        index_writer.wait_merging_threads().unwrap();
+    }
+
+    #[test]
+    fn test_drop_segment() {
+        let mut schema_builder = schema::SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", schema::TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let num_docs_containing = |s: &str| {
+            let searcher = index.searcher();
+            let term_a = Term::from_field_text(text_field, s);
+            searcher.doc_freq(&term_a)
+        };
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        index_writer.set_merge_policy(box NoMergePolicy::default());
+        for text in &["a", "b", "c"] {
+            index_writer.add_document(doc!(text_field=>*text));
+            index_writer.commit().expect("commit failed");
+        }
+        index.load_searchers().unwrap();
+        assert_eq!(index.searchable_segment_ids().unwrap().len(), 3);
+        let segment_id_b = index.searchable_segments()
+            .unwrap()
+            .into_iter()
+            .find(|segment| {
+                let segment_reader = SegmentReader::open(segment.clone()).unwrap();
+                segment_reader.doc_freq(&Term::from_field_text(text_field, "b")) == 1
+            })
+            .map(|segment| segment.id())
+            .unwrap();
+        index_writer.drop_segment(segment_id_b).unwrap();
+        index.load_searchers().unwrap();
+        assert_eq!(index.searchable_segment_ids().unwrap().len(), 2);
+        assert_eq!(num_docs_containing("a"), 1);
+        assert_eq!(num_docs_containing("b"), 0);
+        assert_eq!(num_docs_containing("c"), 1);
+        match index_writer.drop_segment(segment_id_b) {
+            Err(Error::InvalidArgument(_)) => {}
+            _ => panic!("Expected InvalidArgument error"),
+        }
+    }
+
+    #[test]
+    fn test_commit_with_label() {
+        let mut schema_builder = schema::SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", schema::TEXT | schema::STORED);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        index_writer.set_merge_policy(box NoMergePolicy::default());
+        index_writer.add_document(doc!(text_field=>"hello tenant_a"));
+        index_writer.commit_with_label("tenant_a").unwrap();
+        index_writer.add_document(doc!(text_field=>"hello tenant_a"));
+        index_writer.commit_with_label("tenant_a").unwrap();
+        index_writer.add_document(doc!(text_field=>"hello tenant_b"));
+        index_writer.commit_with_label("tenant_b").unwrap();
+        index_writer.add_document(doc!(text_field=>"hello"));
+        index_writer.commit().unwrap();
+        index.load_searchers().unwrap();
+        let search_label = |label: &str| {
+            let searcher = index.searcher();
+            let query = TermQuery::new(Term::from_field_text(text_field, "hello"), SegmentPostingsOption::NoFreq);
+            let mut top_collector = TopCollector::with_limit(10);
+            searcher.search_label(&query, &mut top_collector, label).unwrap();
+            top_collector
+                .docs()
+                .iter()
+                .map(|doc_address| {
+                    let doc = searcher.doc(doc_address).unwrap();
+                    String::from(doc.get_first(text_field).unwrap().text())
+                })
+                .collect::<Vec<String>>()
+        };
+        assert_eq!(search_label("tenant_a"), vec!("hello tenant_a", "hello tenant_a"));
+        assert_eq!(search_label("tenant_b"), vec!("hello tenant_b"));
+        assert!(search_label("tenant_c").is_empty());
+        assert_eq!(index.searcher().num_docs(), 4);
+
+        // the labels are persisted, and kept by merges.
+        let segment_ids_with_label = |label: Option<&str>| -> Vec<SegmentId> {
+            index.segments()
+                .unwrap()
+                .into_iter()
+                .filter(|segment_meta| segment_meta.label() == label)
+                .map(|segment_meta| segment_meta.id())
+                .collect()
+        };
+        assert_eq!(segment_ids_with_label(None).len(), 1);
+        let tenant_a_segment_ids = segment_ids_with_label(Some("tenant_a"));
+        assert_eq!(tenant_a_segment_ids.len(), 2);
+        index_writer.merge(&tenant_a_segment_ids).wait().unwrap();
+        assert_eq!(segment_ids_with_label(Some("tenant_a")).len(), 1);
+        index.load_searchers().unwrap();
+        assert_eq!(search_label("tenant_a").len(), 2);
+
+        // dropping a label.
+        for segment_id in segment_ids_with_label(Some("tenant_b")) {
+            index_writer.drop_segment(segment_id).unwrap();
+        }
+        index.load_searchers().unwrap();
+        assert!(search_label("tenant_b").is_empty());
+        assert_eq!(index.searcher().num_docs(), 3);
+    }
+
+    #[test]
+    fn test_term_hash_functions_produce_identical_segments() {
+        let mut schema_builder = schema::SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", schema::TEXT);
+        let schema = schema_builder.build();
+        let build_index = |term_hash_function: TermHashFunction| {
+            let index = Index::create_in_ram(schema.clone());
+            {
+                let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+                index_writer.set_term_hash_function(term_hash_function);
+                assert_eq!(index_writer.get_term_hash_function(), term_hash_function);
+                for i in 0..1_000 {
+                    index_writer.add_document(doc!(text_field=>format!("a{} b{} c{} a{}", i, i % 7, i % 13, i % 5)));
+                }
+                index_writer.commit().expect("commit failed");
+            }
+            index
+        };
+        let read_components = |index: &Index| {
+            let segments = index.searchable_segments().unwrap();
+            assert_eq!(segments.len(), 1);
+            vec!(SegmentComponent::TERMS, SegmentComponent::POSTINGS, SegmentComponent::POSITIONS)
+                .into_iter()
+                .map(|component| segments[0].open_read(component).unwrap().as_slice().to_vec())
+                .collect::<Vec<Vec<u8>>>()
+        };
+        let fxhash_index = build_index(TermHashFunction::FxHash);
+        let siphash_index = build_index(TermHashFunction::SipHash);
+        assert_eq!(read_components(&fxhash_index), read_components(&siphash_index));
+    }
+
+    #[test]
+    fn test_max_tokens_per_field() {
+        let mut schema_builder = schema::SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", schema::TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            assert_eq!(index_writer.get_max_tokens_per_field(), u32::max_value());
+            index_writer.set_max_tokens_per_field(3);
+            index_writer.add_document(doc!(text_field=>"a b c d e"));
+            index_writer.add_document(doc!(text_field=>"f g", text_field=>"h i"));
+            index_writer.commit().expect("commit failed");
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let doc_freq = |text: &str| searcher.doc_freq(&Term::from_field_text(text_field, text));
+        assert_eq!(doc_freq("c"), 1);
+        assert_eq!(doc_freq("d"), 0);
+        assert_eq!(doc_freq("e"), 0);
+        assert_eq!(doc_freq("h"), 1);
+        assert_eq!(doc_freq("i"), 0);
+        let segment_reader = searcher.segment_reader(0);
+        let fieldnorm_reader = segment_reader.get_fieldnorms_reader(text_field).unwrap();
+        assert_eq!(fieldnorm_reader.get(0), 3);
+        assert_eq!(fieldnorm_reader.get(1), 3);
+    }
+
+    #[test]
+    fn test_interrupted_merge_is_garbage_collected() {
+        let mut schema_builder = schema::SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", schema::TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.set_merge_policy(box NoMergePolicy);
+            index_writer.add_document(doc!(text_field=>"a"));
+            index_writer.commit().unwrap();
+            index_writer.add_document(doc!(text_field=>"b"));
+            index_writer.commit().unwrap();
+        }
+        let segment_ids = index.searchable_segment_ids().unwrap();
+        assert_eq!(segment_ids.len(), 2);
+
+        // simulates a crash in the middle of a merge: the merged
+        // segment is partially written, and was never added to the meta file.
+        let mut merged_segment = index.new_segment();
+        {
+            let mut postings_write = merged_segment.open_write(SegmentComponent::POSTINGS).unwrap();
+            postings_write.write_all(b"partial").unwrap();
+            postings_write.flush().unwrap();
+        }
+        let postings_path = merged_segment.relative_path(SegmentComponent::POSTINGS);
+        assert!(index.directory().exists(&postings_path));
+
+        let _index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        assert!(!index.directory().exists(&postings_path));
+        assert_eq!(index.searchable_segment_ids().unwrap(), segment_ids);
+        assert!(index.check_integrity().unwrap().is_ok());
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        assert_eq!(searcher.num_docs(), 2);
+        assert_eq!(searcher.doc_freq(&Term::from_field_text(text_field, "a")), 1);
+    }
+
+    #[test]
+    fn test_max_term_length() {
+        let mut schema_builder = schema::SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", schema::TEXT);
+        let truncated_field = schema_builder.add_text_field("truncated", schema::TEXT);
+        let keyword_field = schema_builder.add_text_field("keyword", schema::STRING);
+        schema_builder.set_max_term_length(truncated_field, MaxTermLength::new(5, TermLengthPolicy::Truncate));
+        schema_builder.set_max_term_length(keyword_field, MaxTermLength::new(5, TermLengthPolicy::Drop));
+        let schema = schema_builder.build();
+        assert_eq!(schema.get_field_entry(text_field).max_term_length(), MaxTermLength::new(255, TermLengthPolicy::Drop));
+        let index = Index::create_in_ram(schema);
+        let long_token: String = ::std::iter::repeat('a').take(300).collect();
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.add_document(doc!(text_field=>format!("short {} word", long_token)));
+            index_writer.add_document(doc!(truncated_field=>"short abcdefgh"));
+            index_writer.add_document(doc!(keyword_field=>"abcdefgh", keyword_field=>"abc"));
+            index_writer.commit().expect("commit failed");
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let doc_freq = |field: Field, text: &str| searcher.doc_freq(&Term::from_field_text(field, text));
+        assert_eq!(doc_freq(text_field, "short"), 1);
+        assert_eq!(doc_freq(text_field, "word"), 1);
+        assert_eq!(doc_freq(text_field, &long_token), 0);
+        assert_eq!(doc_freq(text_field, &long_token[..255]), 0);
+        assert_eq!(doc_freq(truncated_field, "short"), 1);
+        assert_eq!(doc_freq(truncated_field, "abcde"), 1);
+        assert_eq!(doc_freq(truncated_field, "abcdefgh"), 0);
+        assert_eq!(doc_freq(keyword_field, "abc"), 1);
+        assert_eq!(doc_freq(keyword_field, "abcde"), 0);
+        assert_eq!(doc_freq(keyword_field, "abcdefgh"), 0);
+        let segment_reader = searcher.segment_reader(0);
+        let fieldnorm_reader = segment_reader.get_fieldnorms_reader(text_field).unwrap();
+        assert_eq!(fieldnorm_reader.get(0), 2);
+
+        // the query parser truncates over-long tokens the same way.
+        let query_parser = QueryParser::new(index.schema(), vec!(truncated_field));
+        let query = query_parser.parse_query("abcdefghij").unwrap();
+        let mut collector = TestCollector::default();
+        searcher.search(&*query, &mut collector).unwrap();
+        assert_eq!(collector.docs(), vec!(1));
+    }
+
+    #[test]
+    fn test_computed_field() {
+        let mut schema_builder = schema::SchemaBuilder::default();
+        let popularity_field = schema_builder.add_u32_field("popularity", schema::FAST);
+        let recency_field = schema_builder.add_u32_field("recency", schema::FAST);
+        let score_field = schema_builder.add_u32_field("score", schema::FAST | schema::U32_INDEXED);
+        let text_field = schema_builder.add_text_field("text", schema::TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            let computed_field = ComputedField::new(score_field, move |doc: &Document| {
+                let popularity = doc.get_first(popularity_field).map(|val| val.u32_value()).unwrap_or(0);
+                let recency = doc.get_first(recency_field).map(|val| val.u32_value()).unwrap_or(0);
+                popularity * 2 + recency
+            });
+            index_writer.add_computed_field(computed_field).unwrap();
+            match index_writer.add_computed_field(ComputedField::new(text_field, |_: &Document| 0)) {
+                Err(Error::SchemaError(_)) => {}
+                _ => panic!("Expected SchemaError"),
+            }
+            index_writer.add_document(doc!(popularity_field=>5u32, recency_field=>1u32));
+            index_writer.add_document(doc!(popularity_field=>1u32, recency_field=>2u32));
+            index_writer.add_document(doc!(recency_field=>7u32));
+            index_writer.commit().expect("commit failed");
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let score_reader = searcher.segment_reader(0).get_fast_field_reader(score_field).unwrap();
+        assert_eq!(score_reader.get(0), 11);
+        assert_eq!(score_reader.get(1), 4);
+        assert_eq!(score_reader.get(2), 7);
+
+        let term_query = TermQuery::new(Term::from_field_u32(score_field, 7), SegmentPostingsOption::NoFreq);
+        let mut collector = TestCollector::default();
+        searcher.search(&term_query, &mut collector).unwrap();
+        assert_eq!(collector.docs(), vec!(2));
+
+        let mut doc_addresses = vec!(DocAddress(0, 0), DocAddress(0, 1), DocAddress(0, 2));
+        searcher.sort_by_fast_field(&mut doc_addresses, score_field).unwrap();
+        assert_eq!(doc_addresses, vec!(DocAddress(0, 1), DocAddress(0, 2), DocAddress(0, 0)));
+    }
+
+    #[test]
+    fn test_flush() {
+        let mut schema_builder = schema::SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", schema::TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        assert!(index_writer.flush().unwrap().is_none());
+        index_writer.add_document(doc!(text_field=>"a"));
+        index_writer.add_document(doc!(text_field=>"b"));
+        index_writer.add_document(doc!(text_field=>"c"));
+        let segment_meta = index_writer.flush().unwrap().unwrap();
+        assert_eq!(segment_meta.max_doc(), 3);
+        assert!(index_writer.flush().unwrap().is_none());
+        // flushed segments are not searchable before the commit.
+        assert!(index.searchable_segment_ids().unwrap().is_empty());
+        index_writer.commit().unwrap();
+        assert_eq!(index.searchable_segment_ids().unwrap(), vec!(segment_meta.id()));
+    }
+
+    #[test]
+    fn test_flush_several_threads() {
+        let mut schema_builder = schema::SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", schema::TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_with_num_threads(2, 2 * 40_000_000).unwrap();
+        index_writer.set_merge_policy(box NoMergePolicy);
+        let num_docs = 1_000;
+        for i in 0..num_docs {
+            index_writer.add_document(doc!(text_field=>format!("a{}", i % 10)));
+        }
+        index_writer.delete_term(Term::from_field_text(text_field, "a0"));
+        index_writer.add_document(doc!(text_field=>"a0"));
+        // the segments of the indexing threads end up in a single segment.
+        let segment_meta = index_writer.flush().unwrap().unwrap();
+        assert_eq!(segment_meta.max_doc(), num_docs + 1);
+        let nrt_searcher = index_writer.nrt_searcher().unwrap();
+        assert_eq!(nrt_searcher.segment_readers().len(), 1);
+        assert_eq!(nrt_searcher.segment_reader(0).segment_id(), segment_meta.id());
+        // the documents keep their opstamp: the delete only
+        // affects the documents added before it.
+        index_writer.commit().unwrap();
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        assert_eq!(searcher.num_docs(), num_docs + 1 - num_docs / 10);
+        assert_eq!(searcher.doc_freq(&Term::from_field_text(text_field, "a1")), num_docs / 10);
+        assert_eq!(index.searchable_segment_ids().unwrap(), vec!(segment_meta.id()));
+    }
+
+    #[test]
+    fn test_memory_budget() {
+        let mut schema_builder = schema::SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", schema::TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let memory_budget = 2 * (HEAP_SIZE_LIMIT as usize + 1_000_000);
+        let mut index_writer = index.writer_with_memory_budget(2, memory_budget).unwrap();
+        index_writer.set_merge_policy(box NoMergePolicy);
+        assert_eq!(index_writer.memory_budget(), memory_budget);
+        // far more distinct terms than the budget can hold.
+        let num_docs = 40_000;
+        for i in 0..num_docs {
+            let text: Vec<String> = (0..50).map(|j| format!("t{}x{}", i, j)).collect();
+            index_writer.add_document(doc!(text_field=>text.join(" ")));
+        }
+        assert!(index_writer.flush().unwrap().is_some());
+        // the flushed segments are not searchable until the commit.
+        assert!(index.searchable_segment_ids().unwrap().is_empty());
+        index_writer.commit().unwrap();
+        let segment_metas = index.segments().unwrap();
+        assert!(segment_metas.len() > 2);
+        let max_doc: u32 = segment_metas.iter().map(|segment_meta| segment_meta.max_doc()).sum();
+        assert_eq!(max_doc, num_docs);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_memory_budget_too_small() {
+        let schema_builder = schema::SchemaBuilder::default();
+        let index = Index::create_in_ram(schema_builder.build());
+        let _ = index.writer_with_memory_budget(4, 40_000_000);
+    }
+
+    #[test]
+    fn test_pending_deletes() {
+        let mut schema_builder = schema::SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", schema::TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        assert_eq!(index_writer.pending_deletes(), 0);
+        index_writer.add_document(doc!(text_field=>"a"));
+        index_writer.delete_term(Term::from_field_text(text_field, "a"));
+        assert_eq!(index_writer.pending_deletes(), 1);
+        index_writer.delete_term(Term::from_field_text(text_field, "b"));
+        assert_eq!(index_writer.pending_deletes(), 2);
+        index_writer.commit().expect("commit failed");
+        assert_eq!(index_writer.pending_deletes(), 0);
+        index_writer.delete_term(Term::from_field_text(text_field, "a"));
+        assert_eq!(index_writer.pending_deletes(), 1);
+        index_writer.rollback().unwrap();
+        assert_eq!(index_writer.pending_deletes(), 0);
+    }
+
+    #[test]
+    fn test_delete_and_rollback() {
+        let mut schema_builder = schema::SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", schema::TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let num_live_docs_containing = |s: &str| {
+            index.load_searchers().unwrap();
+            let searcher = index.searcher();
+            let term_query = TermQuery::new(Term::from_field_text(text_field, s), SegmentPostingsOption::NoFreq);
+            let mut test_collector = TestCollector::default();
+            searcher.search(&term_query, &mut test_collector).unwrap();
+            test_collector.docs().len()
+        };
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        index_writer.set_merge_policy(box NoMergePolicy::default());
+        index_writer.add_document(doc!(text_field=>"a"));
+        index_writer.commit().unwrap();
+        // the delete is rollbacked alongside the uncommitted segment.
+        index_writer.add_document(doc!(text_field=>"a"));
+        index_writer.delete_term(Term::from_field_text(text_field, "a"));
+        index_writer.rollback().unwrap();
+        index_writer.commit().unwrap();
+        assert_eq!(num_live_docs_containing("a"), 1);
+        assert!(index.segments().unwrap().iter().all(|segment_meta| !segment_meta.has_deletes()));
+        // the delete applies to both committed and uncommitted segments,
+        // but not to the documents added afterwards.
+        index_writer.add_document(doc!(text_field=>"a"));
+        index_writer.delete_term(Term::from_field_text(text_field, "a"));
+        index_writer.add_document(doc!(text_field=>"a"));
+        index_writer.commit().unwrap();
+        assert_eq!(num_live_docs_containing("a"), 1);
+        let segment_metas = index.segments().unwrap();
+        assert!(segment_metas.iter().any(|segment_meta| segment_meta.has_deletes()));
+        for segment_meta in segment_metas.iter().filter(|segment_meta| segment_meta.has_deletes()) {
+            let delete_file = segment_meta.relative_path(SegmentComponent::DELETE);
+            assert!(segment_meta.list_files().contains(&delete_file));
+            assert!(index.directory().exists(&delete_file));
+        }
+    }
+
+    #[test]
+    fn test_replace_documents() {
+        let mut schema_builder = schema::SchemaBuilder::default();
+        let category_field = schema_builder.add_text_field("category", schema::STRING);
+        let title_field = schema_builder.add_text_field("title", schema::TEXT | schema::STORED);
+        let index = Index::create_in_ram(schema_builder.build());
+        let titles = |query: &str| {
+            index.load_searchers().unwrap();
+            let searcher = index.searcher();
+            let query = QueryParser::for_index(&index, vec![title_field]).parse_query(query).unwrap();
+            let mut top_collector = TopCollector::with_limit(10);
+            searcher.search(&*query, &mut top_collector).unwrap();
+            let mut titles: Vec<String> = top_collector
+                .docs()
+                .iter()
+                .map(|doc_address| {
+                    let doc = searcher.doc(doc_address).unwrap();
+                    String::from(doc.get_first(title_field).unwrap().text())
+                })
+                .collect();
+            titles.sort();
+            titles
+        };
+        let fruit_query = || QueryParser::for_index(&index, vec![title_field]).parse_query("category:fruit").unwrap();
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        index_writer.set_merge_policy(box NoMergePolicy::default());
+        index_writer.add_document(doc!(category_field=>"fruit", title_field=>"apple"));
+        index_writer.add_document(doc!(category_field=>"fruit", title_field=>"banana"));
+        index_writer.add_document(doc!(category_field=>"vegetable", title_field=>"carrot"));
+        index_writer.commit().unwrap();
+        // a document of the category added in the same commit is replaced too.
+        let delete_opstamp = index_writer.add_document(doc!(category_field=>"fruit", title_field=>"cherry")) + 1;
+        let opstamp = index_writer.replace_documents(fruit_query(), vec!(
+            doc!(category_field=>"fruit", title_field=>"mango"),
+            doc!(category_field=>"fruit", title_field=>"peach"),
+        )).unwrap();
+        assert_eq!(opstamp, delete_opstamp + 2);
+        // nothing changes before the commit.
+        assert_eq!(titles("category:fruit"), vec!("apple", "banana"));
+        index_writer.commit().unwrap();
+        assert_eq!(titles("category:fruit"), vec!("mango", "peach"));
+        assert_eq!(titles("category:vegetable"), vec!("carrot"));
+        assert!(titles("apple banana cherry").is_empty());
+        assert_eq!(index.searcher().num_docs(), 3);
+        let operations: Vec<Operation> = index.operation_log(delete_opstamp).unwrap().collect();
+        assert_eq!(operations, vec!(
+            Operation::DeleteQuery { opstamp: delete_opstamp },
+            Operation::Add { opstamp: delete_opstamp + 1 },
+            Operation::Add { opstamp: delete_opstamp + 2 },
+            Operation::Commit { opstamp: delete_opstamp + 3 },
+        ));
+    }
+
+    #[test]
+    fn test_rollback_deletes_uncommitted_files() {
+        let mut schema_builder = schema::SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", schema::TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        index_writer.set_merge_policy(box NoMergePolicy::default());
+        index_writer.add_document(doc!(text_field=>"a"));
+        index_writer.commit().unwrap();
+        index_writer.add_document(doc!(text_field=>"b"));
+        let uncommitted_segment_meta = index_writer.flush().unwrap().unwrap();
+        let uncommitted_files = uncommitted_segment_meta.list_files();
+        assert!(uncommitted_files.iter().all(|path| index.directory().exists(path)));
+        index_writer.rollback().unwrap();
+        assert!(uncommitted_files.iter().all(|path| !index.directory().exists(path)));
+        let committed_segment_metas = index.segments().unwrap();
+        assert_eq!(committed_segment_metas.len(), 1);
+        assert!(committed_segment_metas[0].list_files().iter().all(|path| index.directory().exists(path)));
+    }
+
+    #[test]
+    fn test_garbage_collect_files() {
+        let mut schema_builder = schema::SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", schema::TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        index_writer.set_merge_policy(box NoMergePolicy::default());
+        for text in &["a", "b", "c"] {
+            index_writer.add_document(doc!(text_field=>*text));
+            index_writer.commit().expect("commit failed");
+        }
+        index.load_searchers().unwrap();
+        let former_files: Vec<PathBuf> = index.segments()
+            .unwrap()
+            .iter()
+            .flat_map(|segment_meta| segment_meta.list_files())
+            .collect();
+        let former_searcher = index.open_searcher().unwrap();
+        let segment_ids = index.searchable_segment_ids().unwrap();
+        let merged_segment_entry = index_writer.merge(&segment_ids).wait().unwrap().remove(0);
+        index.load_searchers().unwrap();
+        index_writer.garbage_collect_files().unwrap();
+        // the former segments are still read by `former_searcher`.
+        assert!(former_files.iter().all(|path| index.directory().exists(path)));
+        assert_eq!(former_searcher.num_docs(), 3);
+        drop(former_searcher);
+        index_writer.garbage_collect_files().unwrap();
+        assert!(former_files.iter().all(|path| !index.directory().exists(path)));
+        assert!(merged_segment_entry.meta().list_files().iter().all(|path| index.directory().exists(path)));
+        assert!(index.directory().exists(&*META_FILEPATH));
+        assert_eq!(index.searcher().num_docs(), 3);
+    }
+
+    #[test]
+    fn test_nrt_searcher() {
+        let mut schema_builder = schema::SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", schema::TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        index_writer.set_merge_policy(box NoMergePolicy::default());
+        index_writer.add_document(doc!(text_field=>"a"));
+        index_writer.commit().unwrap();
+        index_writer.add_document(doc!(text_field=>"b"));
+        index_writer.flush().unwrap();
+        index.load_searchers().unwrap();
+        assert_eq!(index.searcher().num_docs(), 1);
+        let nrt_searcher = index_writer.nrt_searcher().unwrap();
+        assert_eq!(nrt_searcher.num_docs(), 2);
+        assert_eq!(nrt_searcher.doc_freq(&Term::from_field_text(text_field, "b")), 1);
+        let former_segment_ids: Vec<SegmentId> = nrt_searcher
+            .segment_readers()
+            .iter()
+            .map(|segment_reader| segment_reader.segment_id())
+            .collect();
+
+        // reopening only picks up the new segment.
+        index_writer.add_document(doc!(text_field=>"c"));
+        index_writer.flush().unwrap();
+        let nrt_searcher = index_writer.reopen_nrt_searcher(nrt_searcher).unwrap();
+        assert_eq!(nrt_searcher.num_docs(), 3);
+        assert_eq!(nrt_searcher.segment_readers().len(), 3);
+        for segment_id in &former_segment_ids {
+            assert!(nrt_searcher
+                .segment_readers()
+                .iter()
+                .any(|segment_reader| segment_reader.segment_id() == *segment_id));
+        }
+
+        // a searcher opened before the rollback keeps its snapshot.
+        index_writer.rollback().unwrap();
+        index_writer.garbage_collect_files().unwrap();
+        assert_eq!(nrt_searcher.num_docs(), 3);
+        assert_eq!(nrt_searcher.doc_freq(&Term::from_field_text(text_field, "c")), 1);
+        assert_eq!(nrt_searcher.doc_freq(&Term::from_field_text(text_field, "a")), 1);
+        let nrt_searcher = index_writer.reopen_nrt_searcher(nrt_searcher).unwrap();
+        assert_eq!(nrt_searcher.num_docs(), 1);
+        assert_eq!(nrt_searcher.doc_freq(&Term::from_field_text(text_field, "c")), 0);
+    }
+
+    #[test]
+    fn test_segment_access_counts() {
+        let mut schema_builder = schema::SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", schema::TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        index_writer.set_merge_policy(box NoMergePolicy::default());
+        index_writer.add_document(doc!(text_field=>"a"));
+        index_writer.commit().unwrap();
+        index_writer.add_document(doc!(text_field=>"b"));
+        index_writer.commit().unwrap();
+        index.load_searchers().unwrap();
+        let access_counts = index_writer.segment_access_counts();
+        assert_eq!(access_counts.len(), 2);
+        assert!(access_counts.iter().all(|&(_, access_count)| access_count == 0));
+        let searcher = index.searcher();
+        let query = TermQuery::new(Term::from_field_text(text_field, "a"), SegmentPostingsOption::NoFreq);
+        for _ in 0..3 {
+            let mut collector = TestCollector::default();
+            searcher.search(&query, &mut collector).unwrap();
+        }
+        let fresh_searcher = index.open_searcher().unwrap();
+        let mut collector = TestCollector::default();
+        fresh_searcher.search(&query, &mut collector).unwrap();
+        for segment_reader in searcher.segment_readers() {
+            assert_eq!(segment_reader.access_count(), 4);
+        }
+        assert!(index_writer.segment_access_counts().iter().all(|&(_, access_count)| access_count == 4));
+        // segments that are not searched are not touched.
+        index_writer.add_document(doc!(text_field=>"c"));
+        let uncommitted_segment_id = index_writer.flush().unwrap().unwrap().id();
+        let access_counts = index_writer.segment_access_counts();
+        assert_eq!(access_counts.len(), 3);
+        for (segment_id, access_count) in access_counts {
+            assert_eq!(access_count, if segment_id == uncommitted_segment_id { 0 } else { 4 });
+        }
+        // the counters of the merged segments are removed.
+        index_writer.commit().unwrap();
+        let segment_ids = index.searchable_segment_ids().unwrap();
+        index_writer.merge(&segment_ids).wait().unwrap();
+        let counters = index.segment_access_counters();
+        for segment_id in &segment_ids {
+            assert_eq!(counters.access_count(*segment_id), 0);
+        }
+        assert_eq!(searcher.segment_readers()[0].access_count(), 4);
+        // as well as those of the segments unknown to the writer,
+        // on garbage collection.
+        counters.counter(segment_ids[0]).fetch_add(1, Ordering::SeqCst);
+        assert_eq!(counters.access_count(segment_ids[0]), 1);
+        index_writer.garbage_collect_files().unwrap();
+        assert_eq!(counters.access_count(segment_ids[0]), 0);
+    }
+
+    #[test]
+    fn test_fragmentation_report() {
+        let mut schema_builder = schema::SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", schema::TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        index_writer.set_merge_policy(box NoMergePolicy::default());
+        assert_eq!(index_writer.fragmentation_report().num_segments(), 0);
+        index_writer.add_document(doc!(text_field=>"a"));
+        index_writer.add_document(doc!(text_field=>"b"));
+        index_writer.commit().expect("commit failed");
+        let report = index_writer.fragmentation_report();
+        assert_eq!(report.num_segments(), 1);
+        assert_eq!(report.fragmentation_score(), 0f32);
+        index_writer.add_document(doc!(text_field=>"c"));
+        index_writer.delete_term(Term::from_field_text(text_field, "a"));
+        index_writer.commit().expect("commit failed");
+        let report = index_writer.fragmentation_report();
+        assert_eq!(report.num_segments(), 2);
+        assert!(report.fragmentation_score() > 0.5f32);
+    }
+
+
+    #[test]
+    fn test_store_block_size() {
+        let mut schema_builder = schema::SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", schema::TEXT | schema::STORED);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        assert_eq!(index_writer.get_store_block_size(), 16_384);
+        index_writer.set_store_block_size(64);
+        index_writer.set_store_compressor(Compressor::Lz4High);
+        for i in 0..100 {
+            index_writer.add_document(doc!(text_field=>format!("doc {}", i)));
+        }
+        index_writer.commit().unwrap();
+        let segment_metas = index.segments().unwrap();
+        assert_eq!(segment_metas.len(), 1);
+        assert_eq!(segment_metas[0].store_block_size(), 64);
+        assert_eq!(segment_metas[0].store_compressor(), Compressor::Lz4High);
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let segment_reader = searcher.segment_reader(0);
+        assert!(segment_reader.get_store_reader().raw_blocks().count() > 10);
+        for i in 0..100 {
+            let doc = segment_reader.doc(i).unwrap();
+            assert_eq!(doc.get_first(text_field).unwrap().text(), format!("doc {}", i));
+        }
+    }
+
+    #[test]
+    fn test_store_compressor() {
+        let mut schema_builder = schema::SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", schema::TEXT | schema::STORED);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        index_writer.set_merge_policy(box NoMergePolicy::default());
+        assert_eq!(index_writer.get_store_compressor(), Compressor::Lz4);
+        let compressors = [Compressor::Snappy, Compressor::None, Compressor::Lz4];
+        for (i, &compressor) in compressors.iter().enumerate() {
+            index_writer.set_store_compressor(compressor);
+            index_writer.add_document(doc!(text_field=>format!("doc {}", i)));
+            index_writer.commit().unwrap();
+        }
+        let mut segment_compressors: Vec<Compressor> = index
+            .segments()
+            .unwrap()
+            .iter()
+            .map(|segment_meta| segment_meta.store_compressor())
+            .collect();
+        segment_compressors.sort_by_key(|compressor| format!("{:?}", compressor));
+        assert_eq!(segment_compressors, vec!(Compressor::Lz4, Compressor::None, Compressor::Snappy));
+        index.load_searchers().unwrap();
+        {
+            let searcher = index.searcher();
+            let mut texts: Vec<String> = searcher
+                .segment_readers()
+                .iter()
+                .map(|segment_reader| {
+                    let doc = segment_reader.doc(0).unwrap();
+                    doc.get_first(text_field).unwrap().text().to_string()
+                })
+                .collect();
+            texts.sort();
+            assert_eq!(texts, vec!("doc 0", "doc 1", "doc 2"));
+        }
+
+        // segments compressed with different codecs can be merged.
+        index_writer.set_store_compressor(Compressor::Snappy);
+        let segment_ids = index.searchable_segment_ids().unwrap();
+        index_writer.merge(&segment_ids).wait().unwrap();
+        let segment_metas = index.segments().unwrap();
+        assert_eq!(segment_metas.len(), 1);
+        assert_eq!(segment_metas[0].store_compressor(), Compressor::Snappy);
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let mut texts: Vec<String> = (0..3)
+            .map(|doc| {
+                let doc = searcher.segment_reader(0).doc(doc).unwrap();
+                doc.get_first(text_field).unwrap().text().to_string()
+            })
+            .collect();
+        texts.sort();
+        assert_eq!(texts, vec!("doc 0", "doc 1", "doc 2"));
+    }
+
+    #[test]
+    fn test_add_index() {
+        let mut schema_builder = schema::SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", schema::TEXT);
+        let schema = schema_builder.build();
+        let source_index = Index::create_in_ram(schema.clone());
+        {
+            let mut index_writer = source_index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.add_document(doc!(text_field=>"a"));
+            index_writer.add_document(doc!(text_field=>"b"));
+            index_writer.commit().unwrap();
+            index_writer.delete_term(Term::from_field_text(text_field, "b"));
+            index_writer.commit().unwrap();
+        }
+        let index = Index::create_in_ram(schema);
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        index_writer.add_document(doc!(text_field=>"c"));
+        index_writer.commit().unwrap();
+        // this delete should not affect the imported documents.
+        index_writer.delete_term(Term::from_field_text(text_field, "a"));
+        
+        // importing the same index twice results in 
+        // two segments with the same id.
+        index_writer.add_index(&source_index).unwrap();
+        index_writer.add_index(&source_index).unwrap();
+        index_writer.commit().unwrap();
+        index.load_searchers().unwrap();
+        
+        let segment_metas = index.segments().unwrap();
+        assert_eq!(segment_metas.len(), 3);
+        let source_segment_id = source_index.segments().unwrap()[0].id();
+        let num_source_ids = segment_metas
+            .iter()
+            .filter(|segment_meta| segment_meta.id() == source_segment_id)
+            .count();
+        assert_eq!(num_source_ids, 1);
+        for segment_meta in &segment_metas {
+            let segment = index.segment(segment_meta.clone());
+            assert!(SegmentReader::open(segment).is_ok());
+        }
+        assert_eq!(index.searcher().num_docs(), 3);
+        
+        index_writer.delete_term(Term::from_field_text(text_field, "a"));
+        index_writer.commit().unwrap();
+        index.load_searchers().unwrap();
+        assert_eq!(index.searcher().num_docs(), 1);
+    }
+
+    #[test]
+    fn test_add_index_schema_mismatch() {
+        let mut schema_builder = schema::SchemaBuilder::default();
+        schema_builder.add_text_field("text", schema::TEXT);
+        let source_index = Index::create_in_ram(schema_builder.build());
+        let mut schema_builder = schema::SchemaBuilder::default();
+        schema_builder.add_text_field("title", schema::TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        match index_writer.add_index(&source_index) {
+            Err(Error::SchemaError(_)) => {}
+            _ => panic!("Expected a schema error"),
+        }
+    }
+
+    /// Returns the content of each of the files of the
+    /// single segment of `index`.
+    fn segment_files(index: &Index) -> Vec<Option<Vec<u8>>> {
+        let segment_metas = index.segments().unwrap();
+        assert_eq!(segment_metas.len(), 1);
+        let segment = index.segment(segment_metas[0].clone());
+        SegmentComponent::iterator()
+            .map(|component| segment.open_read(component.clone()).ok().map(|source| source.as_slice().to_vec()))
+            .collect()
+    }
+
+    #[test]
+    fn test_build_segment() {
+        let mut schema_builder = schema::SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", schema::TEXT | schema::STORED);
+        let tag_field = schema_builder.add_text_field("tag", schema::STRING);
+        let count_field = schema_builder.add_u32_field("count", schema::U32_INDEXED | schema::FAST);
+        let schema = schema_builder.build();
+        let texts: Vec<String> = (0..100).map(|i| format!("a{} b{} c{}", i, i % 7, i % 3)).collect();
+        let tags: Vec<String> = (0..100).map(|i| format!("tag{}", i % 5)).collect();
+        let counts: Vec<u32> = (0..100).map(|i| i % 11).collect();
+
+        let document_index = Index::create_in_ram(schema.clone());
+        {
+            let mut index_writer = document_index.writer_with_num_threads(1, 40_000_000).unwrap();
+            for i in 0..100 {
+                index_writer.add_document(doc!(
+                    text_field=>texts[i].clone(),
+                    tag_field=>tags[i].clone(),
+                    count_field=>counts[i]));
+            }
+            index_writer.commit().unwrap();
+        }
+
+        let row_index = Index::create_in_ram(schema.clone());
+        {
+            let mut index_writer = row_index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.build_segment(20_000_000, |segment_builder| {
+                for i in 0..100 {
+                    try!(segment_builder.add_row(|row| {
+                        row.add_text(text_field, &texts[i]);
+                        row.add_text(tag_field, &tags[i]);
+                        row.add_u32(count_field, counts[i]);
+                    }));
+                }
+                assert_eq!(segment_builder.max_doc(), 100);
+                Ok(())
+            }).unwrap();
+            index_writer.commit().unwrap();
+        }
+
+        let column_index = Index::create_in_ram(schema);
+        {
+            let mut index_writer = column_index.writer_with_num_threads(1, 40_000_000).unwrap();
+            let text_column: Vec<&str> = texts.iter().map(|text| &text[..]).collect();
+            let tag_column: Vec<&str> = tags.iter().map(|tag| &tag[..]).collect();
+            index_writer.build_segment(20_000_000, |segment_builder| {
+                segment_builder.add_columns(&[
+                    Column::U32(count_field, &counts),
+                    Column::Text(text_field, &text_column),
+                    Column::Text(tag_field, &tag_column),
+                ])
+            }).unwrap();
+            index_writer.commit().unwrap();
+        }
+
+        let document_segment_files = segment_files(&document_index);
+        assert!(document_segment_files.iter().filter(|content| content.is_some()).count() >= 7);
+        assert_eq!(segment_files(&row_index), document_segment_files);
+        assert_eq!(segment_files(&column_index), document_segment_files);
+
+        column_index.load_searchers().unwrap();
+        let searcher = column_index.searcher();
+        assert_eq!(searcher.num_docs(), 100);
+        assert_eq!(searcher.doc_freq(&Term::from_field_text(tag_field, "tag3")), 20);
+        let stored_doc = searcher.doc(&DocAddress(0, 42)).unwrap();
+        assert_eq!(stored_doc.get_first(text_field).unwrap().text(), "a42 b0 c0");
+    }
+
+    #[test]
+    fn test_build_segment_errors() {
+        let mut schema_builder = schema::SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", schema::TEXT);
+        let count_field = schema_builder.add_u32_field("count", schema::FAST);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        let result = index_writer.build_segment(20_000_000, |segment_builder| {
+            segment_builder.add_columns(&[
+                Column::Text(text_field, &["a", "b"]),
+                Column::U32(count_field, &[1]),
+            ])
+        });
+        match result {
+            Err(Error::InvalidArgument(_)) => {}
+            _ => panic!("Expected an invalid argument error"),
+        }
+        let result = index_writer.build_segment(20_000_000, |segment_builder| {
+            segment_builder.add_row(|row| row.add_u32(text_field, 1))
+        });
+        match result {
+            Err(Error::InvalidArgument(_)) => {}
+            _ => panic!("Expected an invalid argument error"),
+        }
+        // the rows do not fit in the heap.
+        let text: String = (0..1_000).map(|i| format!("t{} ", i)).collect();
+        let mut num_rows = 0;
+        let result = index_writer.build_segment(MARGIN_IN_BYTES as usize + 100_000, |segment_builder| {
+            loop {
+                try!(segment_builder.add_row(|row| row.add_text(text_field, &text)));
+                num_rows += 1;
+            }
+        });
+        match result {
+            Err(Error::InvalidArgument(_)) => {}
+            _ => panic!("Expected an invalid argument error"),
+        }
+        assert!(num_rows > 0);
+        match index_writer.build_segment(MARGIN_IN_BYTES as usize, |_| Ok(())) {
+            Err(Error::InvalidArgument(_)) => {}
+            _ => panic!("Expected an invalid argument error"),
+        }
+        // empty segments are not added.
+        index_writer.build_segment(20_000_000, |_| Ok(())).unwrap();
+        index_writer.commit().unwrap();
+        assert!(index.segments().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_operation_log() {
+        let mut schema_builder = schema::SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", schema::TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        assert_eq!(index.operation_log(0).unwrap().count(), 0);
+        let term_a = Term::from_field_text(text_field, "a");
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        assert_eq!(index_writer.add_document(doc!(text_field=>"a")), 0);
+        assert_eq!(index_writer.add_document(doc!(text_field=>"b")), 1);
+        assert_eq!(index_writer.commit().unwrap(), 2);
+        // each commit only writes its own operations.
+        let first_commit_path = PathBuf::from("operations.2.log");
+        let first_commit_data = index.directory().atomic_read(&first_commit_path).unwrap();
+        assert_eq!(index_writer.delete_term(term_a.clone()), 3);
+        assert_eq!(index_writer.add_document(doc!(text_field=>"d")), 4);
+        // uncommitted operations are not logged yet.
+        assert_eq!(index.operation_log(0).unwrap().count(), 3);
+        assert_eq!(index_writer.commit().unwrap(), 5);
+        assert_eq!(index.directory().atomic_read(&first_commit_path).unwrap(), first_commit_data);
+        // rolled back operations are not logged.
+        index_writer.add_document(doc!(text_field=>"c"));
+        index_writer.rollback().unwrap();
+        let operations: Vec<Operation> = index.operation_log(0).unwrap().collect();
+        assert_eq!(operations, vec!(
+            Operation::Add { opstamp: 0 },
+            Operation::Add { opstamp: 1 },
+            Operation::Commit { opstamp: 2 },
+            Operation::Delete { opstamp: 3, term: term_a.clone() },
+            Operation::Add { opstamp: 4 },
+            Operation::Commit { opstamp: 5 },
+        ));
+        let opstamps: Vec<u64> = index.operation_log(3)
+            .unwrap()
+            .map(|operation| operation.opstamp())
+            .collect();
+        assert_eq!(opstamps, vec!(3, 4, 5));
+        assert_eq!(index.operation_log(6).unwrap().count(), 0);
+
+        // the log survives the garbage collection of the files.
+        index_writer.garbage_collect_files().unwrap();
+        assert_eq!(index.operation_log(0).unwrap().count(), 6);
+
+        // compaction happens at commit boundaries.
+        assert_eq!(index_writer.compact_operation_log(1).unwrap(), 0);
+        assert_eq!(index_writer.compact_operation_log(4).unwrap(), 3);
+        assert!(!index.directory().exists(&first_commit_path));
+        let operations: Vec<Operation> = index.operation_log(0).unwrap().collect();
+        assert_eq!(operations, vec!(
+            Operation::Delete { opstamp: 3, term: term_a },
+            Operation::Add { opstamp: 4 },
+            Operation::Commit { opstamp: 5 },
+        ));
+    }
+
+    /// Matches the documents of `query`, after
+    /// failing to be run the first time.
+    #[derive(Debug)]
+    struct FailOnceQuery {
+        query: TermQuery,
+        fail_once: AtomicBool,
+    }
+
+    impl Query for FailOnceQuery {
+        fn as_any(&self) -> &Any {
+            self
+        }
+
+        fn weight(&self, searcher: &Searcher) -> Result<Box<Weight>> {
+            if self.fail_once.swap(false, Ordering::SeqCst) {
+                return Err(Error::InvalidArgument(String::from("The query failed")));
+            }
+            self.query.weight(searcher)
+        }
+    }
+
+    #[test]
+    fn test_failed_commit_keeps_operations() {
+        let mut schema_builder = schema::SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", schema::TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        index_writer.add_document(doc!(text_field=>"a"));
+        assert_eq!(index_writer.commit().unwrap(), 1);
+        index_writer.delete_query(box FailOnceQuery {
+            query: TermQuery::new(Term::from_field_text(text_field, "a"), SegmentPostingsOption::NoFreq),
+            fail_once: AtomicBool::new(true),
+        });
+        index_writer.add_document(doc!(text_field=>"b"));
+        assert!(index_writer.commit().is_err());
+        // the operations of the failed commit are logged by the next
+        // one, which follows the last successful commit in the log.
+        assert_eq!(index_writer.pending_deletes(), 1);
+        assert_eq!(index_writer.commit().unwrap(), 5);
+        let operations: Vec<Operation> = index.operation_log(0).unwrap().collect();
+        assert_eq!(operations, vec!(
+            Operation::Add { opstamp: 0 },
+            Operation::Commit { opstamp: 1 },
+            Operation::DeleteQuery { opstamp: 2 },
+            Operation::Add { opstamp: 3 },
+            Operation::Commit { opstamp: 5 },
+        ));
+        index.load_searchers().unwrap();
+        assert_eq!(index.searcher().num_docs(), 1);
+        assert_eq!(index.searcher().doc_freq(&Term::from_field_text(text_field, "b")), 1);
+    }
+
+    #[test]
+    fn test_operation_log_segment_adds() {
+        let mut schema_builder = schema::SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", schema::TEXT);
+        let schema = schema_builder.build();
+        let source_index = Index::create_in_ram(schema.clone());
+        {
+            let mut index_writer = source_index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.add_document(doc!(text_field=>"a"));
+            index_writer.add_document(doc!(text_field=>"b"));
+            index_writer.commit().unwrap();
+        }
+        let index = Index::create_in_ram(schema);
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        // each imported or built document is logged with the opstamp of the call.
+        assert_eq!(index_writer.add_index(&source_index).unwrap(), 0);
+        let opstamp = index_writer.build_segment(20_000_000, |segment_builder| {
+            for text in &["c", "d", "e"] {
+                try!(segment_builder.add_row(|row| row.add_text(text_field, text)));
+            }
+            Ok(())
+        }).unwrap();
+        assert_eq!(opstamp, 1);
+        assert_eq!(index_writer.commit().unwrap(), 2);
+        let operations: Vec<Operation> = index.operation_log(0).unwrap().collect();
+        assert_eq!(operations, vec!(
+            Operation::Add { opstamp: 0 },
+            Operation::Add { opstamp: 0 },
+            Operation::Add { opstamp: 1 },
+            Operation::Add { opstamp: 1 },
+            Operation::Add { opstamp: 1 },
+            Operation::Commit { opstamp: 2 },
+        ));
+    }
+
+    #[test]
+    fn test_segment_delete_opstamp() {
+        let mut schema_builder = schema::SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", schema::TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        index_writer.set_merge_policy(box NoMergePolicy::default());
+        index_writer.add_document(doc!(text_field=>"a"));
+        index_writer.add_document(doc!(text_field=>"b"));
+        index_writer.commit().unwrap();
+        let segment_id = index.searchable_segment_ids().unwrap()[0];
+        assert_eq!(index_writer.segment_delete_opstamp(&segment_id), None);
+        assert_eq!(index_writer.segment_delete_opstamp(&SegmentId::generate_random()), None);
+
+        let delete_opstamp = index_writer.delete_term(Term::from_field_text(text_field, "a"));
+        // the delete is pending until the commit.
+        assert_eq!(index_writer.segment_delete_opstamp(&segment_id), None);
+        index_writer.commit().unwrap();
+        assert_eq!(index_writer.segment_delete_opstamp(&segment_id), Some(delete_opstamp));
+        assert_eq!(index.segments().unwrap()[0].delete_opstamp(), Some(delete_opstamp));
+
+        // deletes are applied even if they do not match any document.
+        let delete_opstamp = index_writer.delete_term(Term::from_field_text(text_field, "c"));
+        index_writer.commit().unwrap();
+        assert_eq!(index_writer.segment_delete_opstamp(&segment_id), Some(delete_opstamp));
+        index.load_searchers().unwrap();
+        assert_eq!(index.searcher().num_docs(), 1);
+    }
+
+    #[test]
+    fn test_oversized_document_policy() {
+        use postings::term_table_capacity;
+        let mut schema_builder = schema::SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", schema::TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let heap_size = HEAP_SIZE_LIMIT as usize + 1;
+        // the text is far smaller than the heap, but has more
+        // distinct tokens than the term table can hold.
+        let num_terms = term_table_capacity(heap_size as u32) + 1;
+        let enormous_text: String = (0..num_terms)
+            .map(|i| format!("t{} ", i))
+            .collect();
+        assert!(enormous_text.len() < heap_size / 4);
+        let mut index_writer = index.writer_with_num_threads(1, heap_size).unwrap();
+        index_writer.set_merge_policy(box NoMergePolicy::default());
+        assert_eq!(index_writer.get_oversized_document_policy(), OversizedDocumentPolicy::OwnSegment);
+        index_writer.add_document(doc!(text_field=>"before"));
+        index_writer.try_add_document(doc!(text_field=>enormous_text.clone())).unwrap();
+        index_writer.add_document(doc!(text_field=>"after"));
+        index_writer.commit().unwrap();
+        let mut max_docs: Vec<u32> = index
+            .segments()
+            .unwrap()
+            .iter()
+            .map(|segment_meta| segment_meta.max_doc())
+            .collect();
+        max_docs.sort();
+        // the segment is flushed right after the enormous document.
+        assert_eq!(max_docs, vec!(1, 2));
+
+        index_writer.set_oversized_document_policy(OversizedDocumentPolicy::Reject);
+        match index_writer.try_add_document(doc!(text_field=>enormous_text.clone())) {
+            Err(Error::InvalidArgument(_)) => {}
+            _ => panic!("Expected the oversized document to be rejected"),
+        }
+        index_writer.add_document(doc!(text_field=>enormous_text));
+        index_writer.add_document(doc!(text_field=>"small"));
+        index_writer.commit().unwrap();
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        assert_eq!(searcher.num_docs(), 4);
+        assert_eq!(searcher.doc_freq(&Term::from_field_text(text_field, "small")), 1);
+    }
+
+    #[test]
+    fn test_replace_documents_rejected() {
+        use postings::term_table_capacity;
+        let mut schema_builder = schema::SchemaBuilder::default();
+        let category_field = schema_builder.add_text_field("category", schema::STRING);
+        let text_field = schema_builder.add_text_field("text", schema::TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let heap_size = HEAP_SIZE_LIMIT as usize + 1;
+        let num_terms = term_table_capacity(heap_size as u32) + 1;
+        let enormous_text: String = (0..num_terms)
+            .map(|i| format!("t{} ", i))
+            .collect();
+        let mut index_writer = index.writer_with_num_threads(1, heap_size).unwrap();
+        index_writer.set_merge_policy(box NoMergePolicy::default());
+        index_writer.set_oversized_document_policy(OversizedDocumentPolicy::Reject);
+        index_writer.add_document(doc!(category_field=>"fruit", text_field=>"apple"));
+        index_writer.commit().unwrap();
+        let fruit_query = || QueryParser::for_index(&index, vec![text_field]).parse_query("category:fruit").unwrap();
+        // the first document fits, but not the whole block.
+        match index_writer.replace_documents(fruit_query(), vec!(
+            doc!(category_field=>"fruit", text_field=>"mango"),
+            doc!(category_field=>"fruit", text_field=>enormous_text),
+        )) {
+            Err(Error::InvalidArgument(_)) => {}
+            _ => panic!("Expected the oversized documents to be rejected"),
+        }
+        // neither the delete nor the documents were queued.
+        assert_eq!(index_writer.pending_deletes(), 0);
+        let commit_opstamp = index_writer.commit().unwrap();
+        assert!(index.operation_log(0).unwrap().all(|operation| match operation {
+            Operation::DeleteQuery { .. } => false,
+            Operation::Add { opstamp } => opstamp == 0,
+            _ => true,
+        }));
+        index.load_searchers().unwrap();
+        assert_eq!(index.searcher().num_docs(), 1);
+        assert_eq!(index.searcher().doc_freq(&Term::from_field_text(text_field, "apple")), 1);
+        // a block that fits replaces the documents.
+        let opstamp = index_writer.replace_documents(fruit_query(), vec!(
+            doc!(category_field=>"fruit", text_field=>"mango"),
+        )).unwrap();
+        assert!(opstamp > commit_opstamp);
+        assert_eq!(index_writer.pending_deletes(), 1);
+        index_writer.commit().unwrap();
+        index.load_searchers().unwrap();
+        assert_eq!(index.searcher().num_docs(), 1);
+        assert_eq!(index.searcher().doc_freq(&Term::from_field_text(text_field, "mango")), 1);
+    }
+
+    #[test]
+    fn test_delete_merge_delete() {
+        use postings::DocSet;
+        let mut schema_builder = schema::SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", schema::TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        index_writer.set_merge_policy(box NoMergePolicy::default());
+        for _ in 0..2 {
+            index_writer.add_document(doc!(text_field=>"a"));
+            index_writer.add_document(doc!(text_field=>"b"));
+            index_writer.add_document(doc!(text_field=>"c"));
+            index_writer.commit().unwrap();
+        }
+        index_writer.delete_term(Term::from_field_text(text_field, "a"));
+        index_writer.commit().unwrap();
+        let segment_ids = index.searchable_segment_ids().unwrap();
+        let segment_entries = index_writer.merge(&segment_ids).wait().unwrap();
+        assert_eq!(segment_entries.len(), 1);
+        assert_eq!(segment_entries[0].meta().num_docs(), 4);
+
+        let delete_opstamp = index_writer.delete_term(Term::from_field_text(text_field, "b"));
+        // added after the delete, and therefore not deleted.
+        index_writer.add_document(doc!(text_field=>"b"));
+        index_writer.commit().unwrap();
+        let merged_segment_id = segment_entries[0].segment_id();
+        assert_eq!(index_writer.segment_delete_opstamp(&merged_segment_id), Some(delete_opstamp));
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        assert_eq!(searcher.num_docs(), 3);
+        let term_b = Term::from_field_text(text_field, "b");
+        let num_live_b: usize = searcher
+            .segment_readers()
+            .iter()
+            .map(|segment_reader| {
+                let mut postings = segment_reader.read_postings(&term_b, SegmentPostingsOption::NoFreq).unwrap();
+                let mut num_live = 0;
+                while postings.advance() {
+                    if !segment_reader.is_deleted(postings.doc()) {
+                        num_live += 1;
+                    }
+                }
+                num_live
+            })
+            .sum();
+        assert_eq!(num_live_b, 1);
+    }
+
+    #[test]
+    fn test_delete_committed_during_merge() {
+        use indexer::MergeProgress;
+        use std::sync::Mutex;
+        use std::sync::mpsc;
+        let mut schema_builder = schema::SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", schema::TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        index_writer.set_merge_policy(box NoMergePolicy::default());
+        for _ in 0..2 {
+            index_writer.add_document(doc!(text_field=>"a"));
+            index_writer.add_document(doc!(text_field=>"b"));
+            index_writer.commit().unwrap();
+        }
+        // the merge waits for the delete to be committed.
+        let (started_send, started_recv) = mpsc::channel();
+        let (resume_send, resume_recv) = mpsc::channel::<()>();
+        let channels = Mutex::new(Some((started_send, resume_recv)));
+        index_writer.set_merge_progress(Some(MergeProgress::new(move |_, _, _, _| {
+            if let Some((started_send, resume_recv)) = channels.lock().unwrap().take() {
+                started_send.send(()).unwrap();
+                resume_recv.recv().unwrap();
+            }
+        })));
+        let segment_ids = index.searchable_segment_ids().unwrap();
+        let merge_future = index_writer.merge(&segment_ids);
+        started_recv.recv().unwrap();
+        let delete_opstamp = index_writer.delete_term(Term::from_field_text(text_field, "a"));
+        index_writer.commit().unwrap();
+        resume_send.send(()).unwrap();
+        let segment_entries = merge_future.wait().unwrap();
+        assert_eq!(segment_entries.len(), 1);
+        assert_eq!(segment_entries[0].meta().max_doc(), 4);
+        assert_eq!(segment_entries[0].meta().num_deleted_docs(), 2);
+        assert_eq!(segment_entries[0].meta().delete_opstamp(), Some(delete_opstamp));
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        assert_eq!(searcher.segment_readers().len(), 1);
+        assert_eq!(searcher.num_docs(), 2);
+        assert_eq!(searcher.doc_freq(&Term::from_field_text(text_field, "b")), 2);
+    }
+
+    #[test]
+    fn test_rollback_discards_uncommitted_deletes() {
+        let mut schema_builder = schema::SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", schema::TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        index_writer.set_merge_policy(box NoMergePolicy::default());
+        index_writer.add_document(doc!(text_field=>"a"));
+        index_writer.commit().unwrap();
+        index_writer.add_document(doc!(text_field=>"a"));
+        index_writer.add_document(doc!(text_field=>"b"));
+        index_writer.delete_term(Term::from_field_text(text_field, "a"));
+        index_writer.rollback().unwrap();
+        index_writer.add_document(doc!(text_field=>"c"));
+        index_writer.commit().unwrap();
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        assert_eq!(searcher.num_docs(), 2);
+        assert!(index.segments().unwrap().iter().all(|segment_meta| !segment_meta.has_deletes()));
+    }
+
+    #[test]
+    fn test_segment_created_at() {
+        use std::time::{SystemTime, UNIX_EPOCH};
+        use tempdir::TempDir;
+        let now = || SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+        let tempdir = TempDir::new("index").unwrap();
+        let mut schema_builder = schema::SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", schema::TEXT);
+        let index = Index::create(tempdir.path(), schema_builder.build()).unwrap();
+        let before = now();
+        let created_at = {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.set_merge_policy(box NoMergePolicy::default());
+            index_writer.add_document(doc!(text_field=>"a"));
+            index_writer.commit().unwrap();
+            let segment_metas = index.segments().unwrap();
+            assert_eq!(segment_metas.len(), 1);
+            let created_at = segment_metas[0].created_at().unwrap();
+            assert!(before <= created_at && created_at <= now());
+            assert_eq!(index_writer.segment_created_at(&segment_metas[0].id()), Some(created_at));
+            assert_eq!(index_writer.segment_created_at(&SegmentId::generate_random()), None);
+            created_at
+        };
+        // the timestamp survives reopening the index.
+        let index = Index::open(tempdir.path()).unwrap();
+        assert_eq!(index.segments().unwrap()[0].created_at(), Some(created_at));
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        index_writer.add_document(doc!(text_field=>"b"));
+        index_writer.commit().unwrap();
+        // the merged segment gets a fresh timestamp.
+        let segment_ids = index.searchable_segment_ids().unwrap();
+        let merged_segment_entry = index_writer.merge(&segment_ids).wait().unwrap().remove(0);
+        let merged_created_at = merged_segment_entry.meta().created_at().unwrap();
+        assert!(created_at <= merged_created_at && merged_created_at <= now());
+        assert_eq!(index_writer.segment_created_at(&merged_segment_entry.segment_id()), Some(merged_created_at));
+    }
+
+    #[test]
+    fn test_commit_opstamps() {
+        let mut schema_builder = schema::SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", schema::TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        index_writer.set_merge_policy(box NoMergePolicy::default());
+        assert!(index_writer.committed_segment_metas().is_empty());
+        index_writer.add_document(doc!(text_field=>"a"));
+        index_writer.add_document(doc!(text_field=>"b"));
+        let first_opstamp = index_writer.commit().unwrap();
+        assert_eq!(first_opstamp, 2);
+        assert_eq!(index.opstamp(), first_opstamp);
+        let committed_segment_ids: Vec<SegmentId> = index_writer
+            .committed_segment_metas()
+            .iter()
+            .map(|segment_meta| segment_meta.id())
+            .collect();
+        assert_eq!(committed_segment_ids, index.searchable_segment_ids().unwrap());
+        assert_eq!(index_writer.committed_segment_metas()[0].num_docs(), 2);
+        // an empty commit still gets a new opstamp.
+        let second_opstamp = index_writer.commit().unwrap();
+        assert!(second_opstamp > first_opstamp);
+        assert_eq!(index.opstamp(), second_opstamp);
+        assert_eq!(index_writer.committed_segment_metas().len(), 1);
+        index_writer.add_document(doc!(text_field=>"c"));
+        let third_opstamp = index_writer.commit().unwrap();
+        assert!(third_opstamp > second_opstamp);
+        assert_eq!(index_writer.committed_segment_metas().len(), 2);
+        // flushed segments are not committed.
+        index_writer.add_document(doc!(text_field=>"d"));
+        let flushed_segment_meta = index_writer.flush().unwrap().unwrap();
+        assert_eq!(index_writer.committed_segment_metas().len(), 2);
+        assert!(index_writer.committed_segment_metas()
+            .iter()
+            .all(|segment_meta| segment_meta.id() != flushed_segment_meta.id()));
+        index_writer.rollback().unwrap();
+        assert_eq!(index_writer.committed_segment_metas().len(), 2);
+        // a new writer starts from the last commit.
+        drop(index_writer);
+        let index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        assert_eq!(index_writer.committed_segment_metas().len(), 2);
+    }
 }
@@ -16,6 +16,7 @@ pub struct LogMergePolicy {
     min_merge_size: usize,
     min_layer_size: u32,
     level_log_size: f64,
+    max_docs_per_segment: Option<u32>,
 }
 
 impl LogMergePolicy {
@@ -44,10 +45,28 @@ impl LogMergePolicy {
     pub fn set_level_log_size(&mut self, level_log_size: f64) {
         self.level_log_size = level_log_size;
     }
+
+    /// Set the maximum number of documents of a merged segment.
+    ///
+    /// Merges exceeding it are split across several segments.
+    /// The segments that already reach it are not merged anymore.
+    /// By default, merged segments are not capped.
+    pub fn set_max_docs_per_segment(&mut self, max_docs_per_segment: Option<u32>) {
+        self.max_docs_per_segment = max_docs_per_segment;
+    }
 }
 
 impl MergePolicy for LogMergePolicy {
     fn compute_merge_candidates(&self, segments: &[SegmentMeta]) -> Vec<MergeCandidate> {
+        // merging full segments would only split them again.
+        let segments: Vec<&SegmentMeta> = segments
+            .iter()
+            .filter(|segment_meta| {
+                self.max_docs_per_segment
+                    .map(|max_docs_per_segment| segment_meta.num_docs() < max_docs_per_segment)
+                    .unwrap_or(true)
+            })
+            .collect();
         if segments.is_empty() {
             return Vec::new();
         }
@@ -88,6 +107,10 @@ impl MergePolicy for LogMergePolicy {
     fn box_clone(&self) -> Box<MergePolicy> {
         box self.clone()
     }
+
+    fn max_docs_per_segment(&self) -> Option<u32> {
+        self.max_docs_per_segment
+    }
 }
 
 impl Default for LogMergePolicy {
@@ -96,6 +119,7 @@ impl Default for LogMergePolicy {
             min_merge_size: DEFAULT_MIN_MERGE_SIZE,
             min_layer_size: DEFAULT_MIN_LAYER_SIZE,
             level_log_size: DEFAULT_LEVEL_LOG_SIZE,
+            max_docs_per_segment: None,
         }
     }
 }
@@ -172,4 +196,25 @@ mod tests {
         let result_list = test_merge_policy().compute_merge_candidates(&test_input);
         assert_eq!(result_list.len(), 1);
     }
+
+    #[test]
+    fn test_log_merge_policy_max_docs_per_segment() {
+        let mut merge_policy = test_merge_policy();
+        merge_policy.set_max_docs_per_segment(Some(10));
+        let test_input = vec![seg_meta(10),
+                              seg_meta(10),
+                              seg_meta(10),
+                              seg_meta(9),
+                              seg_meta(9)];
+        // the full segments are left out.
+        assert!(merge_policy.compute_merge_candidates(&test_input).is_empty());
+        let test_input = vec![seg_meta(10),
+                              seg_meta(9),
+                              seg_meta(9),
+                              seg_meta(9)];
+        let result_list = merge_policy.compute_merge_candidates(&test_input);
+        assert_eq!(result_list.len(), 1);
+        assert_eq!(result_list[0].0.len(), 3);
+        assert!(!result_list[0].0.contains(&test_input[0].id()));
+    }
 }
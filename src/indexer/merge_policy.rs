@@ -1,5 +1,6 @@
 use core::SegmentId;
 use core::SegmentMeta;
+use schema::Field;
 use std::marker;
 use std::fmt::Debug;
 
@@ -21,6 +22,21 @@ pub trait MergePolicy: marker::Send + marker::Sync + Debug {
     fn compute_merge_candidates(&self, segments: &[SegmentMeta]) -> Vec<MergeCandidate>;
     /// Returns a boxed clone of the MergePolicy.
     fn box_clone(&self) -> Box<MergePolicy>;
+    /// Returns the field used to deduplicate documents
+    /// when merging segments, if any.
+    ///
+    /// See `DedupMergePolicy`.
+    fn dedup_field(&self) -> Option<Field> {
+        None
+    }
+    /// Returns the maximum number of documents of the segments
+    /// resulting from a merge, if any.
+    ///
+    /// A merge of more documents than that produces several
+    /// segments instead of a single one.
+    fn max_docs_per_segment(&self) -> Option<u32> {
+        None
+    }
 }
 
 /// Never merge segments. 
@@ -43,3 +59,63 @@ impl MergePolicy for NoMergePolicy {
     }
 }
 
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use Index;
+    use schema;
+    use schema::{Document, FieldValue};
+    use std::sync::{Arc, Mutex};
+
+    /// Records the segments it is consulted with,
+    /// and never suggests any merge.
+    #[derive(Debug, Clone, Default)]
+    struct RecordingMergePolicy {
+        calls: Arc<Mutex<Vec<Vec<SegmentId>>>>,
+    }
+
+    impl MergePolicy for RecordingMergePolicy {
+        fn compute_merge_candidates(&self, segments: &[SegmentMeta]) -> Vec<MergeCandidate> {
+            let segment_ids = segments.iter().map(|segment_meta| segment_meta.id()).collect();
+            self.calls.lock().unwrap().push(segment_ids);
+            Vec::new()
+        }
+
+        fn box_clone(&self) -> Box<MergePolicy> {
+            box self.clone()
+        }
+    }
+
+    #[test]
+    fn test_merge_policy_without_candidates() {
+        let mut schema_builder = schema::SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", schema::TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let merge_policy = RecordingMergePolicy::default();
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        index_writer.set_merge_policy(box merge_policy.clone());
+        for _ in 0..4 {
+            index_writer.add_document(doc!(text_field=>"a"));
+            index_writer.commit().unwrap();
+        }
+        let segment_ids = index.searchable_segment_ids().unwrap();
+        assert_eq!(segment_ids.len(), 4);
+        let calls = merge_policy.calls.lock().unwrap().clone();
+        assert!(!calls.is_empty());
+        // the policy is only ever given existing segments.
+        for call in &calls {
+            assert!(call.iter().all(|segment_id| segment_ids.contains(segment_id)));
+        }
+        // the last call was given all of the committed segments.
+        let mut last_call = calls.iter().filter(|call| !call.is_empty()).last().unwrap().clone();
+        last_call.sort();
+        let mut expected_segment_ids = segment_ids.clone();
+        expected_segment_ids.sort();
+        assert_eq!(last_call, expected_segment_ids);
+        let report = index_writer.fragmentation_report();
+        assert_eq!(report.num_segments(), 4);
+    }
+}
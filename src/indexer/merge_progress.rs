@@ -0,0 +1,63 @@
+use core::SegmentId;
+use std::sync::Arc;
+
+
+/// Phase of a merge.
+///
+/// The postings of all of the merged segments are merged first.
+/// Then, for each segment resulting from the merge, its field norms,
+/// fast fields and stored fields are written, in this order.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MergePhase {
+    /// Merging the postings, counted in terms.
+    ///
+    /// The total is the sum of the number of terms of the
+    /// merged segments. The terms shared by several segments
+    /// are only merged once, so the count jumps to the total
+    /// at the end of the phase.
+    Postings,
+    /// Writing the field norms, counted in documents.
+    /// The documents count as processed as their fields get written.
+    FieldNorms,
+    /// Writing the fast fields, counted in documents.
+    /// The documents count as processed as their fields get written.
+    FastFields,
+    /// Writing the stored fields, counted in documents.
+    Store,
+}
+
+/// Callback observing the progress of the merges.
+///
+/// It is called with the ids of the segments being merged,
+/// the current phase of the merge, the number of units of the phase
+/// processed so far, and the total number of units of the phase.
+/// See `MergePhase` for the units of each phase. The documents
+/// are those of the whole merge, even when it results in several
+/// segments.
+/// The last call, for `MergePhase::Store` and with `processed == total`,
+/// happens once the merged segments have been entirely written.
+///
+/// The callback runs on the merging thread, and should be cheap.
+///
+/// See `IndexWriter::set_merge_progress`.
+#[derive(Clone)]
+pub struct MergeProgress {
+    callback: Arc<Fn(&[SegmentId], MergePhase, u32, u32) + Send + Sync>,
+}
+
+impl MergeProgress {
+
+    /// Creates a `MergeProgress` calling `callback`
+    /// with `(segment_ids, phase, processed, total)`.
+    pub fn new<F>(callback: F) -> MergeProgress
+        where F: Fn(&[SegmentId], MergePhase, u32, u32) + Send + Sync + 'static {
+        MergeProgress {
+            callback: Arc::new(callback),
+        }
+    }
+
+    /// Reports the progress of the merge of `segment_ids`.
+    pub fn report(&self, segment_ids: &[SegmentId], phase: MergePhase, processed: u32, total: u32) {
+        (self.callback)(segment_ids, phase, processed, total);
+    }
+}
@@ -5,26 +5,166 @@ use DocId;
 use core::SerializableSegment;
 use schema::FieldValue;
 use indexer::SegmentSerializer;
+use indexer::{MergePhase, MergeProgress};
+use core::SegmentId;
 use postings::PostingsSerializer;
 use fastfield::U32FastFieldReader;
+use fastfield::{MultiValuedFastFieldReader, MultiValuedFastFieldWriter};
 use itertools::Itertools;
 use postings::Postings;
 use postings::DocSet;
+use postings::ReadAhead;
+use postings::SegmentPostingsOption;
 use core::TermIterator;
-use fastfield::delete::DeleteBitSet;
-use schema::{Schema, Field};
+use schema::{Schema, Field, FieldEntry, Term, Value};
 use fastfield::FastFieldSerializer;
 use store::StoreWriter;
+use termvector::TermVectorWriter;
 use core::SegmentInfo;
 use std::cmp::{min, max};
 use std::iter;
+use std::collections::{HashMap, HashSet};
+use bit_set::BitSet;
 
 
 
 pub struct IndexMerger {
     schema: Schema,
     readers: Vec<SegmentReader>,
+    // documents dropped because a newer version
+    // of them exists, one bitset per reader.
+    duplicates: Vec<BitSet>,
+    doc_id_mapping: DocIdMapping,
     segment_info: SegmentInfo,
+    max_docs_per_segment: Option<u32>,
+    merge_progress: Option<(Vec<SegmentId>, MergeProgress)>,
+}
+
+// number of documents, or terms, between two reports of the progress of the merge.
+const MERGE_PROGRESS_INTERVAL: u32 = 1_000;
+
+// the postings are scanned sequentially when merging.
+const MERGE_READ_AHEAD: ReadAhead = ReadAhead::Blocks(8);
+
+
+// Maps the documents of the merged segment to
+// the documents of the segments being merged, and back.
+struct DocIdMapping {
+    // segment ordinal and doc id of each document of the merged segment.
+    new_to_old: Vec<(usize, DocId)>,
+    // doc id in the merged segment of each document of
+    // each segment, or None if the document is removed.
+    old_to_new: Vec<Vec<Option<DocId>>>,
+    // true if the documents are sorted by a field, in which case
+    // the merged doc ids do not follow the order of the segments.
+    is_sorted: bool,
+    // the ranges of the blocks of more than one document
+    // of the merged segment.
+    blocks: Vec<(DocId, DocId)>,
+}
+
+impl DocIdMapping {
+
+    // The documents of the segments are stacked, or sorted
+    // by the value of `sort_field` if any.
+    //
+    // The documents of a block are kept together, in their order:
+    // a block is sorted by the value of its last document, its parent.
+    fn compute(readers: &[SegmentReader],
+               duplicates: &[BitSet],
+               sort_field: Option<Field>) -> Result<DocIdMapping> {
+        // the remaining documents of each block, a document
+        // which does not belong to a block being a block of its own.
+        let mut blocks: Vec<Vec<(usize, DocId)>> = Vec::new();
+        for (segment_ord, reader) in readers.iter().enumerate() {
+            let mut doc_blocks = reader.doc_blocks().iter().peekable();
+            let mut doc_id = 0;
+            while doc_id < reader.max_doc() {
+                let block_end = match doc_blocks.peek() {
+                    Some(&&(block_start, block_end)) if block_start == doc_id => block_end,
+                    _ => doc_id + 1,
+                };
+                if block_end > doc_id + 1 {
+                    doc_blocks.next();
+                }
+                let block: Vec<(usize, DocId)> = (doc_id..block_end)
+                    .filter(|&doc_id| !reader.is_deleted(doc_id) && !duplicates[segment_ord].contains(doc_id as usize))
+                    .map(|doc_id| (segment_ord, doc_id))
+                    .collect();
+                if !block.is_empty() {
+                    blocks.push(block);
+                }
+                doc_id = block_end;
+            }
+        }
+        if let Some(sort_field) = sort_field {
+            let sort_field_readers: Vec<U32FastFieldReader> = try!(readers
+                .iter()
+                .map(|reader| {
+                    reader
+                        .get_fast_field_reader(sort_field)
+                        .ok_or_else(|| Error::SchemaError(format!("Failed to find a u32_reader for field {:?}", sort_field)))
+                })
+                .collect());
+            // The sort is stable, so that when the segments are
+            // sorted themselves, this is a k-way merge of the segments.
+            blocks.sort_by_key(|block| {
+                let (segment_ord, doc_id) = block[block.len() - 1];
+                sort_field_readers[segment_ord].get(doc_id)
+            });
+        }
+        let mut new_to_old: Vec<(usize, DocId)> = Vec::new();
+        let mut merged_blocks: Vec<(DocId, DocId)> = Vec::new();
+        for block in blocks {
+            let block_start = new_to_old.len() as DocId;
+            new_to_old.extend(block);
+            let block_end = new_to_old.len() as DocId;
+            if block_end - block_start > 1 {
+                merged_blocks.push((block_start, block_end));
+            }
+        }
+        let max_docs: Vec<usize> = readers
+            .iter()
+            .map(|reader| reader.max_doc() as usize)
+            .collect();
+        Ok(DocIdMapping::from_new_to_old(new_to_old, merged_blocks, &max_docs, sort_field.is_some()))
+    }
+
+    fn from_new_to_old(new_to_old: Vec<(usize, DocId)>,
+                       blocks: Vec<(DocId, DocId)>,
+                       max_docs: &[usize],
+                       is_sorted: bool) -> DocIdMapping {
+        let mut old_to_new: Vec<Vec<Option<DocId>>> = max_docs
+            .iter()
+            .map(|&max_doc| vec![None; max_doc])
+            .collect();
+        for (new_doc_id, &(segment_ord, doc_id)) in new_to_old.iter().enumerate() {
+            old_to_new[segment_ord][doc_id as usize] = Some(new_doc_id as DocId);
+        }
+        DocIdMapping {
+            new_to_old: new_to_old,
+            old_to_new: old_to_new,
+            is_sorted: is_sorted,
+            blocks: blocks,
+        }
+    }
+
+    // Restricts the mapping to the documents `start..end`
+    // of the merged segment, which become the documents `0..end - start`.
+    //
+    // The window must not cut a block.
+    fn window(&self, start: usize, end: usize) -> DocIdMapping {
+        let max_docs: Vec<usize> = self.old_to_new
+            .iter()
+            .map(|segment_old_to_new| segment_old_to_new.len())
+            .collect();
+        let blocks: Vec<(DocId, DocId)> = self.blocks
+            .iter()
+            .filter(|&&(block_start, _)| start <= block_start as usize && (block_start as usize) < end)
+            .map(|&(block_start, block_end)| (block_start - start as DocId, block_end - start as DocId))
+            .collect();
+        DocIdMapping::from_new_to_old(self.new_to_old[start..end].to_vec(), blocks, &max_docs, self.is_sorted)
+    }
 }
 
 
@@ -51,11 +191,11 @@ impl DeltaPositionComputer {
 }
 
 
-fn compute_min_max_val(u32_reader: &U32FastFieldReader, max_doc: DocId, delete_bitset: &DeleteBitSet) -> Option<(u32, u32)> {
+fn compute_min_max_val<F: Fn(DocId) -> bool>(u32_reader: &U32FastFieldReader, max_doc: DocId, has_removed_docs: bool, is_removed: F) -> Option<(u32, u32)> {
     if max_doc == 0 {
         None
     }
-    else if !delete_bitset.has_deletes() {
+    else if !has_removed_docs {
         // no deleted documents, 
         // we can use the previous min_val, max_val.
         Some((u32_reader.min_val(), u32_reader.max_val()))
@@ -64,7 +204,7 @@ fn compute_min_max_val(u32_reader: &U32FastFieldReader, max_doc: DocId, delete_b
         // some deleted documents,
         // we need to recompute the max / min
         (0..max_doc)
-            .filter(|doc_id| !delete_bitset.is_deleted(*doc_id))
+            .filter(|doc_id| !is_removed(*doc_id))
             .map(|doc_id| u32_reader.get(doc_id))
             .minmax()
             .into_option()
@@ -79,8 +219,123 @@ fn extract_fast_field_reader(segment_reader: &SegmentReader, field: Field) -> Op
     segment_reader.get_fast_field_reader(field)
 }
 
+/// Returns the stored key of a document, or `None`
+/// if the document has no value for `key_field`.
+fn stored_key(reader: &SegmentReader, key_field: Field, doc_id: DocId) -> Result<Option<Term>> {
+    let doc = try!(reader.doc(doc_id));
+    Ok(doc.get_first(key_field)
+        .map(|value| {
+            match *value {
+                Value::Str(ref text) => Term::from_field_text(key_field, text),
+                Value::U32(val) => Term::from_field_u32(key_field, val),
+                Value::Date(date_time) => Term::from_field_date(key_field, date_time),
+                Value::Json(ref json_text) => Term::from_field_text(key_field, json_text),
+            }
+        }))
+}
+
+/// Identifies the documents that have a newer version, 
+/// according to the value of their `key_field`.
+///
+/// The documents without a value for `key_field` are never duplicates.
+/// As a fast field reads `0` for them, the documents of key `0` are
+/// told apart through the postings of the key if it is indexed,
+/// or through the doc store.
+///
+/// Readers are expected to be sorted from the oldest to the newest. 
+fn find_duplicates(readers: &[SegmentReader], schema: &Schema, key_field: Field) -> Result<Vec<BitSet>> {
+    let field_entry = schema.get_field_entry(key_field);
+    let is_fast = field_entry.is_u32_fast();
+    if !(is_fast && field_entry.is_indexed()) && !field_entry.is_stored() {
+        let error_msg = format!("Field {:?} cannot be used for deduplication as it is neither an indexed fast field nor stored.", field_entry.name());
+        return Err(Error::SchemaError(error_msg));
+    }
+    let mut seen_keys: HashSet<Term> = HashSet::new();
+    let mut duplicates: Vec<BitSet> = readers
+        .iter()
+        .map(|reader| BitSet::with_capacity(reader.max_doc() as usize))
+        .collect();
+    // we go through the documents from the newest to the oldest,
+    // so that the first document encountered for a given key
+    // is the one kept.
+    for (segment_ord, reader) in readers.iter().enumerate().rev() {
+        let fast_field_reader_opt = 
+            if is_fast {
+                reader.get_fast_field_reader(key_field)
+            }
+            else {
+                None
+            };
+        let zero_key = Term::from_field_u32(key_field, 0);
+        let zero_key_docs_opt: Option<BitSet> =
+            if fast_field_reader_opt.is_some() && field_entry.is_indexed() {
+                let mut zero_key_docs = BitSet::with_capacity(reader.max_doc() as usize);
+                if let Some(mut postings) = reader.read_postings(&zero_key, SegmentPostingsOption::NoFreq) {
+                    while postings.advance() {
+                        zero_key_docs.insert(postings.doc() as usize);
+                    }
+                }
+                Some(zero_key_docs)
+            }
+            else {
+                None
+            };
+        for doc_id in (0..reader.max_doc()).rev() {
+            if reader.is_deleted(doc_id) {
+                continue;
+            }
+            let key_opt: Option<Term> = match fast_field_reader_opt {
+                Some(ref fast_field_reader) => {
+                    match fast_field_reader.get(doc_id) {
+                        0 => {
+                            match zero_key_docs_opt {
+                                Some(ref zero_key_docs) => {
+                                    if zero_key_docs.contains(doc_id as usize) {
+                                        Some(zero_key.clone())
+                                    }
+                                    else {
+                                        None
+                                    }
+                                }
+                                None => try!(stored_key(reader, key_field, doc_id)),
+                            }
+                        }
+                        val => Some(Term::from_field_u32(key_field, val)),
+                    }
+                }
+                None => try!(stored_key(reader, key_field, doc_id)),
+            };
+            if let Some(key) = key_opt {
+                if !seen_keys.insert(key) {
+                    duplicates[segment_ord].insert(doc_id as usize);
+                }
+            }
+        }
+    }
+    Ok(duplicates)
+}
+
 impl IndexMerger {
     pub fn open(schema: Schema, segments: &[Segment]) -> Result<IndexMerger> {
+        IndexMerger::open_with_dedup(schema, segments, None)
+    }
+
+    /// Opens an `IndexMerger` that drops the documents 
+    /// for which a newer document with the same `dedup_field` value exists.
+    ///
+    /// The merged segments are then stacked from the oldest 
+    /// to the newest, according to their opstamp.
+    ///
+    /// If `dedup_field` is `None`, this is equivalent to `open`.
+    ///
+    /// In both cases, if the schema defines an index sort field,
+    /// the documents of the merged segment are sorted by its value.
+    /// See `SchemaBuilder::set_index_sort`.
+    pub fn open_with_dedup(schema: Schema, segments: &[Segment], dedup_field: Option<Field>) -> Result<IndexMerger> {
+        let mut segments: Vec<&Segment> = segments.iter().collect();
+        if dedup_field.is_some() {
+            segments.sort_by_key(|segment| segment.meta().opstamp());
+        }
         let mut readers = vec!();
         let mut max_doc = 0;
         for segment in segments {
@@ -90,58 +345,271 @@ impl IndexMerger {
                 readers.push(reader);
             }
         }
+        let duplicates = match dedup_field {
+            Some(key_field) => try!(find_duplicates(&readers, &schema, key_field)),
+            None => readers.iter().map(|_| BitSet::new()).collect(),
+        };
+        for segment_duplicates in &duplicates {
+            max_doc -= segment_duplicates.len() as u32;
+        }
+        let doc_id_mapping = try!(DocIdMapping::compute(&readers, &duplicates, schema.index_sort_field()));
         Ok(IndexMerger {
             schema: schema,
             readers: readers,
-            segment_info: SegmentInfo { max_doc: max_doc },
+            duplicates: duplicates,
+            doc_id_mapping: doc_id_mapping,
+            segment_info: SegmentInfo { max_doc: max_doc, blocks: None },
+            max_docs_per_segment: None,
+            merge_progress: None,
         })
     }
 
+    /// Caps the number of documents of the merged segments.
+    ///
+    /// If the merge results in more than `max_docs_per_segment` documents,
+    /// they are split across several segments. See `.write_merged_segments(...)`.
+    ///
+    /// A block of documents is never split across segments: a merged
+    /// segment may end before the cap to leave a block to the next one,
+    /// or exceed it if a single block does not fit.
+    ///
+    /// # Panics
+    ///
+    /// If `max_docs_per_segment` is 0.
+    pub fn set_max_docs_per_segment(&mut self, max_docs_per_segment: u32) {
+        assert!(max_docs_per_segment > 0, "A merged segment needs to have room for at least one document.");
+        self.max_docs_per_segment = Some(max_docs_per_segment);
+    }
+
+    /// Returns the number of segments resulting from the merge.
+    ///
+    /// This is 1 unless the merged segments are capped,
+    /// and the merge results in more documents than the cap.
+    pub fn num_merged_segments(&self) -> usize {
+        self.merged_segment_starts().len()
+    }
+
+    // Returns the doc id, in the merge, of the first document
+    // of each of the merged segments.
+    //
+    // The documents are laid out as if a single segment was written,
+    // and the merged segments get consecutive slices of them.
+    // Each slice has `max_docs_per_segment` documents, unless this would
+    // cut a block, in which case the slice ends before the block,
+    // or after it if the block starts the slice.
+    fn merged_segment_starts(&self) -> Vec<DocId> {
+        let num_docs = self.segment_info.max_doc;
+        let max_docs_per_segment = match self.max_docs_per_segment {
+            Some(max_docs_per_segment) if num_docs > max_docs_per_segment => max_docs_per_segment,
+            _ => { return vec!(0); }
+        };
+        let blocks = &self.doc_id_mapping.blocks;
+        let mut segment_starts = vec!(0);
+        let mut start = 0;
+        loop {
+            let mut end = start + max_docs_per_segment;
+            if let Err(ord) = blocks.binary_search_by_key(&end, |&(block_start, _)| block_start) {
+                if ord > 0 {
+                    let (block_start, block_end) = blocks[ord - 1];
+                    if end < block_end {
+                        end = if block_start > start { block_start } else { block_end };
+                    }
+                }
+            }
+            if end >= num_docs {
+                return segment_starts;
+            }
+            segment_starts.push(end);
+            start = end;
+        }
+    }
+
+    /// Returns where the documents of the segments being merged
+    /// end up in the merged segments.
+    pub fn merged_doc_ids(&self) -> MergedDocIds {
+        let old_to_new = self.readers
+            .iter()
+            .map(|reader| reader.segment_id())
+            .zip(self.doc_id_mapping.old_to_new.iter().cloned())
+            .collect();
+        MergedDocIds {
+            segment_starts: self.merged_segment_starts(),
+            old_to_new: old_to_new,
+        }
+    }
+
+    /// Writes the segments resulting from the merge, one per serializer,
+    /// and returns their number of documents.
+    ///
+    /// The postings of all of the merged segments are
+    /// written in a single pass over the terms.
+    ///
+    /// # Panics
+    ///
+    /// If the number of serializers is not `.num_merged_segments()`.
+    pub fn write_merged_segments(&self, mut serializers: Vec<SegmentSerializer>) -> Result<Vec<u32>> {
+        let segment_starts = self.merged_segment_starts();
+        assert_eq!(serializers.len(), segment_starts.len(), "One serializer is required per merged segment.");
+        {
+            let mut postings_serializers: Vec<&mut PostingsSerializer> = serializers
+                .iter_mut()
+                .map(|serializer| serializer.get_postings_serializer())
+                .collect();
+            try!(self.write_postings(&segment_starts, &mut postings_serializers));
+        }
+        let mut merged_num_docs = Vec::with_capacity(serializers.len());
+        for (ord, serializer) in serializers.into_iter().enumerate() {
+            let start = segment_starts[ord];
+            let end = segment_starts.get(ord + 1).cloned().unwrap_or(self.segment_info.max_doc);
+            let window;
+            let doc_id_mapping = if segment_starts.len() == 1 {
+                &self.doc_id_mapping
+            }
+            else {
+                window = self.doc_id_mapping.window(start as usize, end as usize);
+                &window
+            };
+            merged_num_docs.push(try!(self.write_segment(doc_id_mapping, start, serializer)));
+        }
+        Ok(merged_num_docs)
+    }
+
+    /// Reports the progress of the merge to `merge_progress`,
+    /// on behalf of the merge of `segment_ids`.
+    pub fn set_merge_progress(&mut self, segment_ids: Vec<SegmentId>, merge_progress: MergeProgress) {
+        self.merge_progress = Some((segment_ids, merge_progress));
+    }
+
+    fn report_progress(&self, phase: MergePhase, processed: u32, total: u32) {
+        if let Some((ref segment_ids, ref merge_progress)) = self.merge_progress {
+            merge_progress.report(segment_ids, phase, processed, total);
+        }
+    }
+
+    // Reports the progress of a phase writing the fields of the documents
+    // of `doc_id_mapping`, once `num_written_fields` of its `num_fields`
+    // fields are written.
+    fn report_fields_progress(&self,
+        phase: MergePhase,
+        doc_id_mapping: &DocIdMapping,
+        docs_offset: u32,
+        num_written_fields: usize,
+        num_fields: usize) {
+        let num_docs = doc_id_mapping.new_to_old.len() as u64;
+        let docs_processed = if num_fields == 0 {
+            num_docs
+        }
+        else {
+            num_docs * num_written_fields as u64 / num_fields as u64
+        };
+        self.report_progress(phase, docs_offset + docs_processed as u32, self.segment_info.max_doc);
+    }
+
+    // Returns the fields of the schema whose entry satisfies `predicate`.
+    fn fields_where(&self, predicate: fn(&FieldEntry) -> bool) -> Vec<Field> {
+        self.schema
+            .fields()
+            .iter()
+            .enumerate()
+            .filter(|&(_, field_entry)| predicate(field_entry))
+            .map(|(field_id, _)| Field(field_id as u8))
+            .collect()
+    }
+
+    /// Returns true if the document should not be 
+    /// part of the merged segment.
+    fn is_removed(&self, segment_ord: usize, doc_id: DocId) -> bool {
+        self.readers[segment_ord].is_deleted(doc_id) ||
+        self.duplicates[segment_ord].contains(doc_id as usize)
+    }
+
     fn write_fieldnorms(&self,
+        doc_id_mapping: &DocIdMapping,
+        docs_offset: u32,
+        fast_field_serializer: &mut FastFieldSerializer) -> Result<()> {
+        let fieldnorm_fastfields: Vec<Field> = self.fields_where(FieldEntry::has_norms);
+        let num_fields = fieldnorm_fastfields.len();
+        let report_fields = |num_written_fields: usize| {
+            self.report_fields_progress(MergePhase::FieldNorms, doc_id_mapping, docs_offset, num_written_fields, num_fields);
+        };
+        report_fields(0);
+        self.generic_write_fast_field(doc_id_mapping, fieldnorm_fastfields, &extract_fieldnorm_reader, &report_fields, fast_field_serializer)
+    }
+
+    fn write_fast_fields(&self,
+        doc_id_mapping: &DocIdMapping,
+        docs_offset: u32,
         fast_field_serializer: &mut FastFieldSerializer) -> Result<()> {
-        let fieldnorm_fastfields: Vec<Field> = self.schema
-                         .fields()
-                         .iter()
-                         .enumerate()
-                         .filter(|&(_, field_entry)| field_entry.is_indexed())
-                         .map(|(field_id, _)| Field(field_id as u8))
-                         .collect();
-        self.generic_write_fast_field(fieldnorm_fastfields, &extract_fieldnorm_reader, fast_field_serializer)
+        let fast_fields: Vec<Field> = self.fields_where(FieldEntry::is_u32_fast);
+        let multivalued_fast_fields: Vec<Field> = self.fields_where(FieldEntry::is_u32_multivalued_fast);
+        let num_single_valued_fields = fast_fields.len();
+        let num_fields = num_single_valued_fields + multivalued_fast_fields.len();
+        let report_fields = |num_written_fields: usize| {
+            self.report_fields_progress(MergePhase::FastFields, doc_id_mapping, docs_offset, num_written_fields, num_fields);
+        };
+        report_fields(0);
+        try!(self.generic_write_fast_field(doc_id_mapping, fast_fields, &extract_fast_field_reader, &report_fields, fast_field_serializer));
+        self.write_multivalued_fast_fields(doc_id_mapping,
+                                           multivalued_fast_fields,
+                                           &|num_written_fields| report_fields(num_single_valued_fields + num_written_fields),
+                                           fast_field_serializer)
     }
 
-    fn write_fast_fields(&self, fast_field_serializer: &mut FastFieldSerializer) -> Result<()> {
-        let fast_fields: Vec<Field> = self.schema
-                         .fields()
-                         .iter()
-                         .enumerate()
-                         .filter(|&(_, field_entry)| field_entry.is_u32_fast())
-                         .map(|(field_id, _)| Field(field_id as u8))
-                         .collect();
-        self.generic_write_fast_field(fast_fields, &extract_fast_field_reader, fast_field_serializer)
+    fn write_multivalued_fast_fields(&self,
+        doc_id_mapping: &DocIdMapping,
+        multivalued_fast_fields: Vec<Field>,
+        report_fields: &Fn(usize),
+        fast_field_serializer: &mut FastFieldSerializer) -> Result<()> {
+        let mut vals: Vec<u32> = Vec::new();
+        for (field_ord, field) in multivalued_fast_fields.into_iter().enumerate() {
+            let multivalued_readers: Vec<MultiValuedFastFieldReader> = try!(self.readers
+                .iter()
+                .map(|reader| {
+                    reader
+                        .get_multivalued_fast_field_reader(field)
+                        .ok_or_else(|| Error::SchemaError(format!("Failed to find a multivalued reader for field {:?}", field)))
+                })
+                .collect());
+            let mut multivalued_field_writer = MultiValuedFastFieldWriter::new(field);
+            for &(segment_ord, doc_id) in &doc_id_mapping.new_to_old {
+                multivalued_readers[segment_ord].get_vals(doc_id, &mut vals);
+                multivalued_field_writer.add_vals(&vals);
+            }
+            try!(multivalued_field_writer.serialize(fast_field_serializer));
+            report_fields(field_ord + 1);
+        }
+        Ok(())
     }
 
 
     // used both to merge field norms and regular u32 fast fields.
+    // `report_fields` is called with the number of fields written so far.
     fn generic_write_fast_field(&self,
+        doc_id_mapping: &DocIdMapping,
         fields: Vec<Field>,
         field_reader_extractor: &Fn(&SegmentReader, Field) -> Option<U32FastFieldReader>,
+        report_fields: &Fn(usize),
         fast_field_serializer: &mut FastFieldSerializer) -> Result<()> {
         
-        for field in fields {
+        for (field_ord, field) in fields.into_iter().enumerate() {
             
-            let mut u32_readers = vec!();
+            let mut u32_readers: Vec<Option<U32FastFieldReader>> = vec!();
             let mut min_val = u32::max_value();
             let mut max_val = u32::min_value();
             
-            for reader in &self.readers {
+            for (segment_ord, reader) in self.readers.iter().enumerate() {
                 match field_reader_extractor(reader, field) {
                     Some(u32_reader) => {
-                        if let Some((seg_min_val, seg_max_val)) = compute_min_max_val(&u32_reader, reader.max_doc(), reader.delete_bitset()) {
+                        let has_removed_docs = reader.delete_bitset().has_deletes() || !self.duplicates[segment_ord].is_empty();
+                        if let Some((seg_min_val, seg_max_val)) = compute_min_max_val(&u32_reader, reader.max_doc(), has_removed_docs, |doc_id| self.is_removed(segment_ord, doc_id)) {
                             // the segment has some non-deleted documents
                             min_val = min(min_val, seg_min_val);
                             max_val = max(max_val, seg_max_val);
-                            u32_readers.push((reader.max_doc(), u32_reader, reader.delete_bitset()));
-                        }        
+                            u32_readers.push(Some(u32_reader));
+                        }
+                        else {
+                            u32_readers.push(None);
+                        }
                     }
                     None => {
                         let error_msg = format!("Failed to find a u32_reader for field {:?}", field);
@@ -152,7 +620,7 @@ impl IndexMerger {
                 
             }
 
-            if u32_readers.is_empty() {
+            if u32_readers.iter().all(Option::is_none) {
                 // we have actually zero documents.
                 min_val = 0;
                 max_val = 0;
@@ -161,47 +629,52 @@ impl IndexMerger {
             assert!(min_val <= max_val);
             
             try!(fast_field_serializer.new_u32_fast_field(field, min_val, max_val));
-            for (max_doc, u32_reader, delete_bitset) in u32_readers {
-                for doc_id in 0..max_doc {
-                    if !delete_bitset.is_deleted(doc_id) {
-                        let val = u32_reader.get(doc_id);
-                        try!(fast_field_serializer.add_val(val));
-                    }
-                }
+            for &(segment_ord, doc_id) in &doc_id_mapping.new_to_old {
+                let u32_reader = u32_readers[segment_ord]
+                    .as_ref()
+                    .expect("Segments with a remaining document have a u32_reader");
+                try!(fast_field_serializer.add_val(u32_reader.get(doc_id)));
             }
 
             try!(fast_field_serializer.close_field());
+            report_fields(field_ord + 1);
         }
         Ok(())
     }
 
+    // Writes the postings of each of the merged segments starting at
+    // `segment_starts` to the postings serializer of the same ordinal.
     fn write_postings(&self,
-
-        postings_serializer: &mut PostingsSerializer) -> Result<()> {
+        segment_starts: &[DocId],
+        postings_serializers: &mut [&mut PostingsSerializer]) -> Result<()> {
         
+        let doc_id_mapping = &self.doc_id_mapping;
         let mut merged_terms = TermIterator::from(&self.readers[..]);
         let mut delta_position_computer = DeltaPositionComputer::new();
         
-        let mut max_doc = 0;
+        // map from segment doc ids to the doc id in the merge.
+        let merged_doc_id_map = &doc_id_mapping.old_to_new;
+        // the merged segments in which the current term was written.
+        let mut terms_written: Vec<bool> = vec![false; postings_serializers.len()];
 
-        // map from segment doc ids to the resulting merged segment doc id.
-        let mut merged_doc_id_map: Vec<Vec<Option<DocId>>> = Vec::with_capacity(self.readers.len());
-        
-        for reader in &self.readers {
-            let mut segment_local_map = Vec::with_capacity(reader.max_doc() as usize);
-            for doc_id in 0..reader.max_doc() {
-                if reader.is_deleted(doc_id) {
-                    segment_local_map.push(None);
-                }
-                else {
-                    segment_local_map.push(Some(max_doc));
-                    max_doc += 1u32;
-                }
-            }
-            merged_doc_id_map.push(segment_local_map);
-        }
+        // when the documents are sorted, the documents of a term
+        // are gathered and sorted before being written:
+        // (doc id, term freq, payload, and range of their positions in `sorted_positions`).
+        let mut sorted_docs: Vec<(DocId, u32, u32, usize, usize)> = Vec::new();
+        let mut sorted_positions: Vec<u32> = Vec::new();
+
+        let num_terms_total: u32 = self.readers
+            .iter()
+            .map(|reader| reader.term_infos().len() as u32)
+            .sum();
+        let mut num_terms_processed = 0u32;
+        self.report_progress(MergePhase::Postings, 0, num_terms_total);
         
         while merged_terms.advance() {
+            num_terms_processed += 1;
+            if num_terms_processed % MERGE_PROGRESS_INTERVAL == 0 && num_terms_processed < num_terms_total {
+                self.report_progress(MergePhase::Postings, num_terms_processed, num_terms_total);
+            }
             // Create the total list of doc ids
             // by stacking the doc ids from the different segment.
             //
@@ -211,8 +684,11 @@ impl IndexMerger {
             // - Segment 1's doc ids become  [seg0.max_doc, seg0.max_doc + seg.max_doc]
             // - Segment 2's doc ids become  [seg0.max_doc + seg1.max_doc, seg0.max_doc + seg1.max_doc + seg2.max_doc]
             // ...
+            // unless the index is sorted.
             let term = merged_terms.term();
-            let mut term_written = false;
+            for term_written in &mut terms_written {
+                *term_written = false;
+            }
             let segment_postings = merged_terms
                     .segment_ords()
                     .iter()
@@ -220,7 +696,7 @@ impl IndexMerger {
                     .flat_map(|segment_ord| {
                         self.readers[segment_ord]
                             .read_postings_all_info(&term)
-                            .map(|segment_postings| (segment_ord, segment_postings))
+                            .map(|segment_postings| (segment_ord, segment_postings.with_read_ahead(MERGE_READ_AHEAD)))
                     })
                     .collect::<Vec<_>>();
 
@@ -231,64 +707,176 @@ impl IndexMerger {
                 // We can now serialize this postings, by pushing each document to the
                 // postings serializer.                
                 
+                sorted_docs.clear();
+                sorted_positions.clear();
                 for (segment_ord, mut segment_postings) in segment_postings {
                     let old_to_new_doc_id = &merged_doc_id_map[segment_ord];
                     while segment_postings.advance() {
                         if let Some(remapped_doc_id) = old_to_new_doc_id[segment_postings.doc() as usize] {
-                            if !term_written {
+                            if doc_id_mapping.is_sorted {
+                                let positions_start = sorted_positions.len();
+                                sorted_positions.extend_from_slice(segment_postings.positions());
+                                sorted_docs.push((remapped_doc_id,
+                                                  segment_postings.term_freq(),
+                                                  segment_postings.payload(),
+                                                  positions_start,
+                                                  sorted_positions.len()));
+                                continue;
+                            }
+                            let (ord, merged_doc_id) = merged_segment_doc_id(segment_starts, remapped_doc_id);
+                            let postings_serializer = &mut postings_serializers[ord];
+                            if !terms_written[ord] {
                                 // we make sure to only write the term iff
                                 // there is at least one document.
                                 postings_serializer.new_term(&term)?;
-                                term_written = true;
+                                terms_written[ord] = true;
                             }
                             let delta_positions: &[u32] =
                                 delta_position_computer.compute_delta_positions(segment_postings.positions());
-                            try!(postings_serializer.write_doc(
-                                    remapped_doc_id,
+                            try!(postings_serializer.write_doc_with_payload(
+                                    merged_doc_id,
                                     segment_postings.term_freq(),
-                                    delta_positions));
+                                    delta_positions,
+                                    segment_postings.payload()));
                         }
                     }
                 }
 
-                if term_written {
-                    try!(postings_serializer.close_term());
+                sorted_docs.sort_by_key(|&(doc_id, _, _, _, _)| doc_id);
+                for &(doc_id, term_freq, payload, positions_start, positions_end) in &sorted_docs {
+                    let (ord, merged_doc_id) = merged_segment_doc_id(segment_starts, doc_id);
+                    let postings_serializer = &mut postings_serializers[ord];
+                    if !terms_written[ord] {
+                        postings_serializer.new_term(&term)?;
+                        terms_written[ord] = true;
+                    }
+                    let delta_positions: &[u32] =
+                        delta_position_computer.compute_delta_positions(&sorted_positions[positions_start..positions_end]);
+                    try!(postings_serializer.write_doc_with_payload(
+                            merged_doc_id,
+                            term_freq,
+                            delta_positions,
+                            payload));
+                }
+
+                for (postings_serializer, &term_written) in postings_serializers.iter_mut().zip(&terms_written) {
+                    if term_written {
+                        try!(postings_serializer.close_term());
+                    }
                 }
             }
             
         }
+        self.report_progress(MergePhase::Postings, num_terms_total, num_terms_total);
         Ok(())
     }
 
-    fn write_storable_fields(&self, store_writer: &mut StoreWriter) -> Result<()> {
-        for reader in &self.readers {
-            let store_reader = reader.get_store_reader();
-            for doc_id in 0..reader.max_doc() {
-                if !reader.is_deleted(doc_id) {
-                    let doc = try!(store_reader.get(doc_id));
-                    let field_values: Vec<&FieldValue> = doc.field_values()
-                        .iter()
-                        .collect();
-                    try!(store_writer.store(&field_values));
-                }
-            }   
+    // The term vectors of the documents are copied as is,
+    // in the order of their new doc ids.
+    fn write_term_vectors(&self,
+        doc_id_mapping: &DocIdMapping,
+        term_vector_writer: &mut TermVectorWriter) -> Result<()> {
+        for &(segment_ord, doc_id) in &doc_id_mapping.new_to_old {
+            let term_vector = try!(self.readers[segment_ord].get_term_vector_reader().get(doc_id));
+            try!(term_vector_writer.write(&term_vector));
         }
         Ok(())
     }
+
+    // `docs_offset` is the number of documents of the merge
+    // written before those of `doc_id_mapping`.
+    fn write_storable_fields(&self,
+        doc_id_mapping: &DocIdMapping,
+        docs_offset: u32,
+        store_writer: &mut StoreWriter) -> Result<()> {
+        let mut docs_processed = docs_offset;
+        self.report_progress(MergePhase::Store, docs_processed, self.segment_info.max_doc);
+        for &(segment_ord, doc_id) in &doc_id_mapping.new_to_old {
+            let store_reader = self.readers[segment_ord].get_store_reader();
+            let doc = try!(store_reader.get(doc_id));
+            let field_values: Vec<&FieldValue> = doc.field_values()
+                .iter()
+                .collect();
+            try!(store_writer.store(&field_values));
+            docs_processed += 1;
+            if docs_processed % MERGE_PROGRESS_INTERVAL == 0 && docs_processed < self.segment_info.max_doc {
+                self.report_progress(MergePhase::Store, docs_processed, self.segment_info.max_doc);
+            }
+        }
+        Ok(())
+    }
+
+    // Writes all but the postings of a merged segment.
+    fn write_segment(&self,
+        doc_id_mapping: &DocIdMapping,
+        docs_offset: u32,
+        mut serializer: SegmentSerializer) -> Result<u32> {
+        let segment_info = SegmentInfo {
+            max_doc: doc_id_mapping.new_to_old.len() as u32,
+            blocks: Some(doc_id_mapping.blocks.clone()),
+        };
+        if let Some(fieldnorms_serializer) = serializer.get_fieldnorms_serializer() {
+            try!(self.write_fieldnorms(doc_id_mapping, docs_offset, fieldnorms_serializer));
+        }
+        try!(self.write_fast_fields(doc_id_mapping, docs_offset, serializer.get_fast_field_serializer()));
+        if let Some(term_vector_writer) = serializer.get_term_vector_writer() {
+            try!(self.write_term_vectors(doc_id_mapping, term_vector_writer));
+        }
+        try!(self.write_storable_fields(doc_id_mapping, docs_offset, serializer.get_store_writer()));
+        try!(serializer.write_segment_info(&segment_info));
+        try!(serializer.close());
+        self.report_progress(MergePhase::Store, docs_offset + segment_info.max_doc, self.segment_info.max_doc);
+        Ok(segment_info.max_doc)
+    }
 }
 
 impl SerializableSegment for IndexMerger {
+    /// Writes all of the merged documents in a single segment,
+    /// regardless of `max_docs_per_segment`.
     fn write(&self, mut serializer: SegmentSerializer) -> Result<u32> {
-        try!(self.write_postings(serializer.get_postings_serializer()));
-        try!(self.write_fieldnorms(serializer.get_fieldnorms_serializer()));
-        try!(self.write_fast_fields(serializer.get_fast_field_serializer()));
-        try!(self.write_storable_fields(serializer.get_store_writer()));
-        try!(serializer.write_segment_info(&self.segment_info));
-        try!(serializer.close());
-        Ok(self.segment_info.max_doc)
+        try!(self.write_postings(&[0], &mut [serializer.get_postings_serializer()]));
+        self.write_segment(&self.doc_id_mapping, 0, serializer)
+    }
+}
+
+/// Maps the documents of the segments being merged
+/// to the documents of the merged segments.
+///
+/// See `IndexMerger::merged_doc_ids`.
+pub struct MergedDocIds {
+    // doc id in the merge of the first document of each merged segment.
+    segment_starts: Vec<DocId>,
+    // doc id in the merge of each document of each segment,
+    // as if a single segment was written.
+    old_to_new: HashMap<SegmentId, Vec<Option<DocId>>>,
+}
+
+impl MergedDocIds {
+    /// Returns the ordinal of the merged segment the document `doc_id`
+    /// of the segment `segment_id` ends up in, and its doc id there.
+    ///
+    /// Returns `None` if the document is not part of the merge:
+    /// it was deleted or deduplicated, or its segment was not merged.
+    pub fn get(&self, segment_id: &SegmentId, doc_id: DocId) -> Option<(usize, DocId)> {
+        self.old_to_new
+            .get(segment_id)
+            .and_then(|segment_old_to_new| segment_old_to_new.get(doc_id as usize).cloned())
+            .and_then(|new_doc_id_opt| new_doc_id_opt)
+            .map(|new_doc_id| merged_segment_doc_id(&self.segment_starts, new_doc_id))
     }
 }
 
+// Returns the ordinal of the merged segment of the document `new_doc_id`
+// of the merge, given the first document of each merged segment,
+// and its doc id in this segment.
+fn merged_segment_doc_id(segment_starts: &[DocId], new_doc_id: DocId) -> (usize, DocId) {
+    let ord = match segment_starts.binary_search(&new_doc_id) {
+        Ok(ord) => ord,
+        Err(ord) => ord - 1,
+    };
+    (ord, new_doc_id - segment_starts[ord])
+}
+
 #[cfg(test)]
 mod tests {
     use schema;
@@ -303,8 +891,15 @@ mod tests {
     use collector::tests::TestCollector;
     use query::BooleanQuery;
     use postings::SegmentPostingsOption;
+    use postings::DocSet;
     use schema::TextIndexingOptions;
     use futures::Future;
+    use indexer::{DedupMergePolicy, LogMergePolicy, NoMergePolicy};
+    use indexer::{MergePhase, MergeProgress};
+    use core::SegmentId;
+    use super::IndexMerger;
+    use Error;
+    use std::sync::{Arc, Mutex};
 
     #[test]
     fn test_index_merger() {
@@ -597,4 +1192,615 @@ mod tests {
         
         
     }
+
+    fn search_doc_ids(searcher: &Searcher, term: Term) -> Vec<u32> {
+        let mut collector = TestCollector::default();
+        let term_query = TermQuery::new(term, SegmentPostingsOption::NoFreq);
+        searcher.search(&term_query, &mut collector).unwrap();
+        collector.docs()
+    }
+
+    #[test]
+    fn test_index_merger_dedup() {
+        let mut schema_builder = schema::SchemaBuilder::default();
+        let id_field = schema_builder.add_u32_field("id", schema::U32_INDEXED | schema::FAST);
+        let text_field = schema_builder.add_text_field("text", schema::STRING | schema::STORED);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        index_writer.set_merge_policy(box DedupMergePolicy::wrap(id_field, box NoMergePolicy::default()));
+        {
+            index_writer.add_document(doc!(id_field => 1, text_field => "1 v1"));
+            index_writer.add_document(doc!(id_field => 2, text_field => "2 v1"));
+            index_writer.add_document(doc!(id_field => 3, text_field => "3 v1"));
+            index_writer.commit().expect("committed");
+        }
+        {
+            index_writer.add_document(doc!(id_field => 3, text_field => "3 v2"));
+            index_writer.add_document(doc!(id_field => 4, text_field => "4 v2"));
+            index_writer.add_document(doc!(id_field => 2, text_field => "2 v2"));
+            index_writer.add_document(doc!(id_field => 4, text_field => "4 v3"));
+            index_writer.commit().expect("committed");
+        }
+        let segment_ids = index.searchable_segment_ids().expect("Searchable segments failed.");
+        assert_eq!(segment_ids.len(), 2);
+        index_writer.merge(&segment_ids)
+                    .wait()
+                    .expect("Merging failed");
+        index.load_searchers().unwrap();
+        let ref searcher = *index.searcher();
+        assert_eq!(searcher.segment_readers().len(), 1);
+        assert_eq!(searcher.num_docs(), 4);
+        let mut texts: Vec<String> = (0..4)
+            .map(|doc_id| {
+                let doc = searcher.doc(&DocAddress(0, doc_id)).unwrap();
+                doc.get_first(text_field).unwrap().text().to_string()
+            })
+            .collect();
+        texts.sort();
+        assert_eq!(texts, vec!("1 v1", "2 v2", "3 v2", "4 v3"));
+        assert_eq!(search_doc_ids(&searcher, Term::from_field_text(text_field, "2 v1")), vec!());
+        assert_eq!(search_doc_ids(&searcher, Term::from_field_text(text_field, "4 v2")), vec!());
+        let id_reader = searcher.segment_reader(0).get_fast_field_reader(id_field).unwrap();
+        assert_eq!(id_reader.min_val(), 1);
+        assert_eq!(id_reader.max_val(), 4);
+    }
+
+    #[test]
+    fn test_index_merger_compacts_deletes() {
+        let mut schema_builder = schema::SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", schema::TEXT | schema::STORED);
+        let score_fieldtype = schema::U32Options::default().set_fast();
+        let score_field = schema_builder.add_u32_field("score", score_fieldtype);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        index_writer.set_merge_policy(box NoMergePolicy::default());
+        let segments = [
+            vec!("keep", "drop", "keep", "drop"),
+            vec!("drop", "keep", "drop", "keep"),
+            // entirely deleted
+            vec!("drop", "drop"),
+        ];
+        let mut score = 0u32;
+        for segment_texts in &segments {
+            for text in segment_texts {
+                score += 1;
+                index_writer.add_document(doc!(text_field=>format!("{} {}", text, score), score_field=>score));
+            }
+            index_writer.commit().expect("committed");
+        }
+        index_writer.delete_term(Term::from_field_text(text_field, "drop"));
+        index_writer.commit().expect("committed");
+        let segment_ids = index.searchable_segment_ids().expect("Searchable segments failed.");
+        assert_eq!(segment_ids.len(), 3);
+        index_writer.merge(&segment_ids)
+                    .wait()
+                    .expect("Merging failed");
+        let segment_metas = index.segments().unwrap();
+        assert_eq!(segment_metas.len(), 1);
+        assert_eq!(segment_metas[0].max_doc(), 4);
+        assert!(!segment_metas[0].has_deletes());
+        assert!(segment_metas[0]
+            .list_files()
+            .iter()
+            .all(|path| path.extension().map(|ext| ext != "del").unwrap_or(true)));
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let segment_reader = searcher.segment_reader(0);
+        assert_eq!(segment_reader.num_docs(), 4);
+        let score_reader = segment_reader.get_fast_field_reader(score_field).unwrap();
+        let scores: Vec<u32> = (0..4).map(|doc| score_reader.get(doc)).collect();
+        assert_eq!(scores, vec!(1, 3, 6, 8));
+        for doc in 0..4 {
+            let stored_doc = searcher.doc(&DocAddress(0, doc)).unwrap();
+            let expected_text = format!("keep {}", scores[doc as usize]);
+            assert_eq!(stored_doc.get_first(text_field).unwrap().text(), &expected_text[..]);
+        }
+        assert_eq!(search_term(&searcher, Term::from_field_text(text_field, "drop")), vec!());
+        assert_eq!(search_term(&searcher, Term::from_field_text(text_field, "keep")), vec!(1, 3, 6, 8));
+    }
+
+    #[test]
+    fn test_index_merger_term_vectors() {
+        let mut schema_builder = schema::SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", schema::TEXT.set_term_vectors());
+        let tag_field = schema_builder.add_text_field("tag", schema::STRING);
+        let score_fieldtype = schema::U32Options::default().set_fast();
+        let score_field = schema_builder.add_u32_field("score", score_fieldtype);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        index_writer.set_merge_policy(box NoMergePolicy::default());
+        let segments = [
+            vec!("a b a", "drop c", "c b c c"),
+            vec!("b d", "drop", "a d a d a"),
+        ];
+        let mut score = 0u32;
+        for segment_texts in &segments {
+            for text in segment_texts {
+                score += 1;
+                index_writer.add_document(doc!(text_field=>*text, tag_field=>"tag", score_field=>score));
+            }
+            index_writer.commit().expect("committed");
+        }
+        index.load_searchers().unwrap();
+        // the term vectors of the documents, by score.
+        let mut original_term_vectors: Vec<(u32, Vec<(Term, u32)>)> = Vec::new();
+        for segment_reader in index.searcher().segment_readers() {
+            assert!(segment_reader.get_term_vector_reader().max_doc() == 3);
+            let score_reader = segment_reader.get_fast_field_reader(score_field).unwrap();
+            for doc in 0..segment_reader.max_doc() {
+                original_term_vectors.push((score_reader.get(doc), segment_reader.term_vector(doc, text_field).unwrap()));
+            }
+        }
+        original_term_vectors.sort();
+        assert_eq!(original_term_vectors[0], (1, vec!((Term::from_field_text(text_field, "a"), 2),
+                                                      (Term::from_field_text(text_field, "b"), 1))));
+        index_writer.delete_term(Term::from_field_text(text_field, "drop"));
+        index_writer.commit().expect("committed");
+        let segment_ids = index.searchable_segment_ids().expect("Searchable segments failed.");
+        assert_eq!(segment_ids.len(), 2);
+        index_writer.merge(&segment_ids)
+                    .wait()
+                    .expect("Merging failed");
+        let segment_metas = index.segments().unwrap();
+        assert_eq!(segment_metas.len(), 1);
+        assert!(segment_metas[0].has_term_vectors());
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let segment_reader = searcher.segment_reader(0);
+        assert_eq!(segment_reader.max_doc(), 4);
+        let score_reader = segment_reader.get_fast_field_reader(score_field).unwrap();
+        let mut merged_term_vectors: Vec<(u32, Vec<(Term, u32)>)> = Vec::new();
+        for doc in 0..segment_reader.max_doc() {
+            merged_term_vectors.push((score_reader.get(doc), segment_reader.term_vector(doc, text_field).unwrap()));
+            assert!(segment_reader.term_vector(doc, tag_field).unwrap().is_empty());
+        }
+        let expected_term_vectors: Vec<(u32, Vec<(Term, u32)>)> = original_term_vectors
+            .into_iter()
+            .filter(|&(score, _)| score != 2 && score != 5)
+            .collect();
+        assert_eq!(merged_term_vectors, expected_term_vectors);
+    }
+
+    #[test]
+    fn test_index_merger_removes_dead_terms() {
+        let mut schema_builder = schema::SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", schema::TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        index_writer.set_merge_policy(box NoMergePolicy::default());
+        index_writer.add_document(doc!(text_field=>"common unique"));
+        index_writer.add_document(doc!(text_field=>"common"));
+        index_writer.commit().expect("committed");
+        index_writer.add_document(doc!(text_field=>"common other"));
+        index_writer.commit().expect("committed");
+        index_writer.delete_term(Term::from_field_text(text_field, "unique"));
+        index_writer.commit().expect("committed");
+        let segment_ids = index.searchable_segment_ids().expect("Searchable segments failed.");
+        index_writer.merge(&segment_ids)
+                    .wait()
+                    .expect("Merging failed");
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        assert_eq!(searcher.segment_readers().len(), 1);
+        let segment_reader = searcher.segment_reader(0);
+        let term_infos = segment_reader.term_infos();
+        assert!(term_infos.get(Term::from_field_text(text_field, "unique").as_slice()).is_none());
+        assert!(term_infos.get(Term::from_field_text(text_field, "other").as_slice()).is_some());
+        assert_eq!(segment_reader.doc_freq(&Term::from_field_text(text_field, "common")), 2);
+    }
+
+    #[test]
+    fn test_merged_doc_ids() {
+        let mut schema_builder = schema::SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", schema::STRING);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        index_writer.set_merge_policy(box NoMergePolicy::default());
+        for segment_ord in 0..2 {
+            for doc_ord in 0..3 {
+                index_writer.add_document(doc!(text_field=>format!("doc{}", segment_ord * 3 + doc_ord)));
+            }
+            index_writer.commit().expect("committed");
+        }
+        index_writer.delete_term(Term::from_field_text(text_field, "doc1"));
+        index_writer.commit().expect("committed");
+        let mut segments = index.searchable_segments().unwrap();
+        segments.sort_by_key(|segment| segment.meta().opstamp());
+        let mut merger = IndexMerger::open(index.schema(), &segments[..]).unwrap();
+        merger.set_max_docs_per_segment(3);
+        let merged_doc_ids = merger.merged_doc_ids();
+        let (first_id, second_id) = (segments[0].id(), segments[1].id());
+        assert_eq!(merged_doc_ids.get(&first_id, 0), Some((0, 0)));
+        assert_eq!(merged_doc_ids.get(&first_id, 1), None);
+        assert_eq!(merged_doc_ids.get(&first_id, 2), Some((0, 1)));
+        assert_eq!(merged_doc_ids.get(&second_id, 0), Some((0, 2)));
+        assert_eq!(merged_doc_ids.get(&second_id, 1), Some((1, 0)));
+        assert_eq!(merged_doc_ids.get(&second_id, 2), Some((1, 1)));
+        assert_eq!(merged_doc_ids.get(&second_id, 3), None);
+        assert_eq!(merged_doc_ids.get(&SegmentId::generate_random(), 0), None);
+    }
+
+    #[test]
+    fn test_index_merger_max_docs_per_segment() {
+        let mut schema_builder = schema::SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", schema::STRING | schema::STORED);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        let mut merge_policy = LogMergePolicy::default();
+        // only explicit merges.
+        merge_policy.set_min_merge_size(100);
+        merge_policy.set_max_docs_per_segment(Some(12));
+        index_writer.set_merge_policy(box merge_policy);
+        for segment_ord in 0..3 {
+            for doc_ord in 0..10 {
+                index_writer.add_document(doc!(text_field=>format!("doc{}", segment_ord * 10 + doc_ord)));
+            }
+            index_writer.commit().expect("committed");
+        }
+        let segment_ids = index.searchable_segment_ids().expect("Searchable segments failed.");
+        let segment_entries = index_writer.merge(&segment_ids)
+                    .wait()
+                    .expect("Merging failed");
+        let num_docs: Vec<u32> = segment_entries
+            .iter()
+            .map(|segment_entry| segment_entry.meta().num_docs())
+            .collect();
+        assert_eq!(num_docs, vec!(12, 12, 6));
+        index.load_searchers().unwrap();
+        let mut merged_segment_ids: Vec<SegmentId> = segment_entries
+            .iter()
+            .map(|segment_entry| segment_entry.segment_id())
+            .collect();
+        merged_segment_ids.sort();
+        let mut searchable_segment_ids = index.searchable_segment_ids().unwrap();
+        searchable_segment_ids.sort();
+        assert_eq!(searchable_segment_ids, merged_segment_ids);
+        let searcher = index.searcher();
+        assert_eq!(searcher.num_docs(), 30);
+        let mut stored_texts = Vec::new();
+        for (segment_ord, segment_reader) in searcher.segment_readers().iter().enumerate() {
+            for doc_id in 0..segment_reader.max_doc() {
+                let doc = searcher.doc(&DocAddress(segment_ord as u32, doc_id)).unwrap();
+                let text = String::from(doc.get_first(text_field).unwrap().text());
+                // the postings of the document follow it in its new segment.
+                assert_eq!(search_doc_ids(&searcher, Term::from_field_text(text_field, &text)).len(), 1);
+                assert_eq!(segment_reader.doc_freq(&Term::from_field_text(text_field, &text)), 1);
+                stored_texts.push(text);
+            }
+        }
+        stored_texts.sort();
+        let mut expected_texts: Vec<String> = (0..30).map(|doc_ord| format!("doc{}", doc_ord)).collect();
+        expected_texts.sort();
+        assert_eq!(stored_texts, expected_texts);
+    }
+
+    #[test]
+    fn test_index_merger_progress() {
+        let mut schema_builder = schema::SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", schema::STRING | schema::STORED);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        index_writer.set_merge_policy(box NoMergePolicy::default());
+        let reports: Arc<Mutex<Vec<(Vec<SegmentId>, MergePhase, u32, u32)>>> = Arc::default();
+        {
+            let reports = reports.clone();
+            index_writer.set_merge_progress(Some(MergeProgress::new(move |segment_ids, phase, processed, total| {
+                reports.lock().unwrap().push((segment_ids.to_vec(), phase, processed, total));
+            })));
+        }
+        for &num_docs in &[1_500, 1_200] {
+            for doc_ord in 0..num_docs {
+                index_writer.add_document(doc!(text_field=>format!("a{}", doc_ord)));
+            }
+            index_writer.commit().expect("committed");
+        }
+        index_writer.add_document(doc!(text_field=>"deleted"));
+        index_writer.commit().expect("committed");
+        index_writer.delete_term(Term::from_field_text(text_field, "deleted"));
+        index_writer.commit().expect("committed");
+        assert!(reports.lock().unwrap().is_empty());
+
+        let mut segment_ids = index.searchable_segment_ids().expect("Searchable segments failed.");
+        index_writer.merge(&segment_ids).wait().expect("Merging failed");
+        index_writer.wait_merging_threads().unwrap();
+
+        let reports = reports.lock().unwrap();
+        segment_ids.sort();
+        for &(ref report_segment_ids, _, _, _) in reports.iter() {
+            let mut report_segment_ids = report_segment_ids.clone();
+            report_segment_ids.sort();
+            assert_eq!(report_segment_ids, segment_ids);
+        }
+        let mut phases: Vec<MergePhase> = reports
+            .iter()
+            .map(|&(_, phase, _, _)| phase)
+            .collect();
+        phases.dedup();
+        assert_eq!(phases, vec!(MergePhase::Postings, MergePhase::FieldNorms, MergePhase::FastFields, MergePhase::Store));
+        let phase_progress = |phase: MergePhase| -> Vec<(u32, u32)> {
+            reports
+                .iter()
+                .filter(|&&(_, report_phase, _, _)| report_phase == phase)
+                .map(|&(_, _, processed, total)| (processed, total))
+                .collect()
+        };
+        // the 1_500 distinct terms are merged, the 1_200 terms
+        // shared by the two segments being merged once.
+        assert_eq!(phase_progress(MergePhase::Postings), vec!((0, 2_700), (1_000, 2_700), (2_700, 2_700)));
+        // the documents count as processed as their fields get written,
+        // and the schema has one field with norms and no fast field.
+        assert_eq!(phase_progress(MergePhase::FieldNorms), vec!((0, 2_700), (2_700, 2_700)));
+        assert_eq!(phase_progress(MergePhase::FastFields), vec!((2_700, 2_700)));
+        let store_progress = phase_progress(MergePhase::Store);
+        assert!(store_progress.len() > 2);
+        assert!(store_progress.iter().all(|&(_, total)| total == 2_700));
+        assert_eq!(store_progress[0].0, 0);
+        assert!(store_progress.windows(2).all(|w| w[0].0 < w[1].0));
+        assert_eq!(store_progress.last().unwrap().0, 2_700);
+    }
+
+    #[test]
+    fn test_index_merger_sorted() {
+        let mut schema_builder = schema::SchemaBuilder::default();
+        let date_field = schema_builder.add_u32_field("date", schema::FAST.set_stored());
+        let text_field = schema_builder.add_text_field("text", schema::TEXT);
+        schema_builder.set_index_sort(date_field);
+        let index = Index::create_in_ram(schema_builder.build());
+        assert_eq!(index.schema().index_sort_field(), Some(date_field));
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        index_writer.set_merge_policy(box NoMergePolicy::default());
+        let segments_dates: Vec<Vec<u32>> = vec!(vec!(3, 10, 12, 20), vec!(1, 11, 12, 30, 2));
+        for dates in &segments_dates {
+            for &date in dates {
+                let text = if date % 2 == 0 { format!("even d{}", date) } else { format!("odd d{}", date) };
+                index_writer.add_document(doc!(date_field => date, text_field => text));
+            }
+            index_writer.commit().expect("committed");
+        }
+        index_writer.delete_term(Term::from_field_text(text_field, "d20"));
+        index_writer.commit().expect("committed");
+        let segment_ids = index.searchable_segment_ids().expect("Searchable segments failed.");
+        assert_eq!(segment_ids.len(), 2);
+        index_writer.merge(&segment_ids)
+                    .wait()
+                    .expect("Merging failed");
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        assert_eq!(searcher.segment_readers().len(), 1);
+        let segment_reader = searcher.segment_reader(0);
+        assert_eq!(segment_reader.max_doc(), 8);
+        let date_reader = segment_reader.get_fast_field_reader(date_field).unwrap();
+        let dates: Vec<u32> = (0..8).map(|doc| date_reader.get(doc)).collect();
+        assert_eq!(dates, vec!(1, 2, 3, 10, 11, 12, 12, 30));
+        // the postings and the doc store follow the documents.
+        for doc in 0..8 {
+            let date = dates[doc as usize];
+            let stored_doc = segment_reader.doc(doc).unwrap();
+            assert_eq!(stored_doc.get_first(date_field).unwrap().u32_value(), date);
+            let date_term = Term::from_field_text(text_field, &format!("d{}", date));
+            assert_eq!(segment_reader.term_positions(doc, &date_term), vec!(1));
+        }
+        let even_term = Term::from_field_text(text_field, "even");
+        let mut even_docs = Vec::new();
+        let mut postings = segment_reader.read_postings_all_info(&even_term).unwrap();
+        while postings.advance() {
+            even_docs.push(postings.doc());
+        }
+        assert_eq!(even_docs, vec!(1, 3, 5, 6, 7));
+        let mut collector = TestCollector::default();
+        let query = TermQuery::new(Term::from_field_text(text_field, "odd"), SegmentPostingsOption::NoFreq);
+        searcher.search(&query, &mut collector).unwrap();
+        assert_eq!(collector.docs(), vec!(0, 2, 4));
+    }
+
+    #[test]
+    fn test_index_merger_sorted_blocks() {
+        let mut schema_builder = schema::SchemaBuilder::default();
+        let date_field = schema_builder.add_u32_field("date", schema::FAST);
+        let text_field = schema_builder.add_text_field("text", schema::STRING);
+        schema_builder.set_index_sort(date_field);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        index_writer.set_merge_policy(box NoMergePolicy::default());
+        let block = |dates: &[u32]| -> Vec<Document> {
+            dates
+                .iter()
+                .map(|&date| doc!(date_field => date, text_field => format!("d{}", date)))
+                .collect()
+        };
+        index_writer.add_document_block(block(&[5, 6, 3]));
+        index_writer.add_document(doc!(date_field => 4u32));
+        index_writer.add_document_block(block(&[9, 2]));
+        index_writer.commit().expect("committed");
+        index_writer.add_document_block(block(&[0, 1]));
+        index_writer.add_document_block(block(&[7, 8, 10]));
+        index_writer.commit().expect("committed");
+        index_writer.delete_term(Term::from_field_text(text_field, "d6"));
+        index_writer.delete_term(Term::from_field_text(text_field, "d7"));
+        index_writer.delete_term(Term::from_field_text(text_field, "d8"));
+        index_writer.commit().expect("committed");
+        index.load_searchers().unwrap();
+        {
+            let searcher = index.searcher();
+            let mut doc_blocks: Vec<Vec<(u32, u32)>> = searcher
+                .segment_readers()
+                .iter()
+                .map(|segment_reader| segment_reader.doc_blocks().to_vec())
+                .collect();
+            doc_blocks.sort();
+            assert_eq!(doc_blocks, vec!(vec!((0, 2), (2, 5)), vec!((0, 3), (4, 6))));
+        }
+        let segment_ids = index.searchable_segment_ids().expect("Searchable segments failed.");
+        index_writer.merge(&segment_ids)
+                    .wait()
+                    .expect("Merging failed");
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        assert_eq!(searcher.segment_readers().len(), 1);
+        let segment_reader = searcher.segment_reader(0);
+        let date_reader = segment_reader.get_fast_field_reader(date_field).unwrap();
+        let dates: Vec<u32> = (0..segment_reader.max_doc()).map(|doc| date_reader.get(doc)).collect();
+        // the blocks are sorted by their last document, and stay contiguous.
+        // The block left with a single document is not a block anymore.
+        assert_eq!(dates, vec!(0, 1, 9, 2, 5, 3, 4, 10));
+        assert_eq!(segment_reader.doc_blocks(), &[(0, 2), (2, 4), (4, 6)]);
+    }
+
+    #[test]
+    fn test_index_merger_max_docs_per_segment_blocks() {
+        let mut schema_builder = schema::SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", schema::STRING);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        let mut merge_policy = LogMergePolicy::default();
+        // only explicit merges.
+        merge_policy.set_min_merge_size(100);
+        merge_policy.set_max_docs_per_segment(Some(3));
+        index_writer.set_merge_policy(box merge_policy);
+        let block = |num_docs: usize| -> Vec<Document> {
+            (0..num_docs).map(|_| doc!(text_field => "a")).collect()
+        };
+        index_writer.add_document_block(block(1));
+        index_writer.add_document_block(block(3));
+        index_writer.commit().expect("committed");
+        index_writer.add_document_block(block(1));
+        index_writer.add_document_block(block(4));
+        index_writer.add_document_block(block(1));
+        index_writer.commit().expect("committed");
+        let segment_ids = index.searchable_segment_ids().expect("Searchable segments failed.");
+        let segment_entries = index_writer.merge(&segment_ids)
+                    .wait()
+                    .expect("Merging failed");
+        let num_docs: Vec<u32> = segment_entries
+            .iter()
+            .map(|segment_entry| segment_entry.meta().num_docs())
+            .collect();
+        // a segment ends before a block that does not fit,
+        // and a block larger than the cap gets a segment of its own.
+        assert_eq!(num_docs, vec!(1, 3, 1, 4, 1));
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let mut doc_blocks: Vec<Vec<(u32, u32)>> = searcher
+            .segment_readers()
+            .iter()
+            .map(|segment_reader| segment_reader.doc_blocks().to_vec())
+            .collect();
+        doc_blocks.sort();
+        assert_eq!(doc_blocks, vec!(vec!(), vec!(), vec!(), vec!((0, 3)), vec!((0, 4))));
+    }
+
+    #[test]
+    fn test_index_merger_fast_field_bit_widths() {
+        let mut schema_builder = schema::SchemaBuilder::default();
+        let num_field = schema_builder.add_u32_field("num", schema::FAST);
+        let text_field = schema_builder.add_text_field("text", schema::STRING);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        index_writer.set_merge_policy(box NoMergePolicy::default());
+        let small_vals: Vec<u32> = (100..108).collect();
+        let large_vals: Vec<u32> = vec!(5_000, 6_023, 5_500, 5_001);
+        {
+            // 3 bits per value
+            for &val in &small_vals {
+                index_writer.add_document(doc!(num_field => val, text_field => "small"));
+            }
+            index_writer.commit().expect("committed");
+        }
+        {
+            // 10 bits per value
+            for &val in &large_vals {
+                index_writer.add_document(doc!(num_field => val, text_field => "large"));
+            }
+            // deleted before the merge, it should not widen the merged column.
+            index_writer.add_document(doc!(num_field => 100_000, text_field => "deleted"));
+            index_writer.commit().expect("committed");
+            index_writer.delete_term(Term::from_field_text(text_field, "deleted"));
+            index_writer.commit().expect("committed");
+        }
+        let segment_ids = index.searchable_segment_ids().expect("Searchable segments failed.");
+        assert_eq!(segment_ids.len(), 2);
+        index_writer.merge(&segment_ids)
+                    .wait()
+                    .expect("Merging failed");
+        index.load_searchers().unwrap();
+        let ref searcher = *index.searcher();
+        assert_eq!(searcher.segment_readers().len(), 1);
+        let segment_reader = searcher.segment_reader(0);
+        assert_eq!(segment_reader.max_doc(), 12);
+        let num_reader = segment_reader.get_fast_field_reader(num_field).unwrap();
+        assert_eq!(num_reader.min_val(), 100);
+        assert_eq!(num_reader.max_val(), 6_023);
+        let mut expected_vals: Vec<u32> = small_vals.clone();
+        expected_vals.extend_from_slice(&large_vals);
+        let mut vals: Vec<u32> = (0..12).map(|doc| num_reader.get(doc)).collect();
+        // the order of the segments within the merged segment is not specified.
+        if vals[0] != 100 {
+            vals = vals[4..].iter().chain(vals[..4].iter()).cloned().collect();
+        }
+        assert_eq!(vals, expected_vals);
+    }
+
+    #[test]
+    fn test_index_merger_dedup_stored_key() {
+        let mut schema_builder = schema::SchemaBuilder::default();
+        let key_field = schema_builder.add_text_field("key", schema::STORED);
+        let text_field = schema_builder.add_text_field("text", schema::STRING | schema::STORED);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        index_writer.set_merge_policy(box DedupMergePolicy::wrap(key_field, box NoMergePolicy::default()));
+        index_writer.add_document(doc!(key_field => "a", text_field => "old"));
+        index_writer.add_document(doc!(text_field => "nokey"));
+        index_writer.commit().expect("committed");
+        index_writer.add_document(doc!(key_field => "a", text_field => "new"));
+        index_writer.add_document(doc!(text_field => "nokey"));
+        index_writer.commit().expect("committed");
+        let segment_ids = index.searchable_segment_ids().expect("Searchable segments failed.");
+        index_writer.merge(&segment_ids)
+                    .wait()
+                    .expect("Merging failed");
+        index.load_searchers().unwrap();
+        let ref searcher = *index.searcher();
+        assert_eq!(searcher.num_docs(), 3);
+        assert_eq!(search_doc_ids(&searcher, Term::from_field_text(text_field, "old")), vec!());
+        assert_eq!(search_doc_ids(&searcher, Term::from_field_text(text_field, "new")).len(), 1);
+        assert_eq!(search_doc_ids(&searcher, Term::from_field_text(text_field, "nokey")).len(), 2);
+    }
+
+    #[test]
+    fn test_index_merger_dedup_missing_fast_key() {
+        let mut schema_builder = schema::SchemaBuilder::default();
+        let id_field = schema_builder.add_u32_field("id", schema::U32_INDEXED | schema::FAST);
+        let fast_only_field = schema_builder.add_u32_field("fast_only", schema::FAST);
+        let text_field = schema_builder.add_text_field("text", schema::STRING | schema::STORED);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        index_writer.set_merge_policy(box DedupMergePolicy::wrap(id_field, box NoMergePolicy::default()));
+        index_writer.add_document(doc!(id_field => 0, text_field => "zero old"));
+        index_writer.add_document(doc!(text_field => "nokey"));
+        index_writer.commit().expect("committed");
+        index_writer.add_document(doc!(id_field => 0, text_field => "zero new"));
+        index_writer.add_document(doc!(text_field => "nokey"));
+        index_writer.add_document(doc!(text_field => "nokey"));
+        index_writer.commit().expect("committed");
+
+        // the documents without a key cannot be told apart
+        // from the key 0 in a fast field that is not indexed.
+        let segments = index.searchable_segments().unwrap();
+        match IndexMerger::open_with_dedup(index.schema(), &segments, Some(fast_only_field)) {
+            Err(Error::SchemaError(_)) => {}
+            _ => panic!("Expected a schema error"),
+        }
+
+        let segment_ids = index.searchable_segment_ids().expect("Searchable segments failed.");
+        index_writer.merge(&segment_ids)
+                    .wait()
+                    .expect("Merging failed");
+        index.load_searchers().unwrap();
+        let ref searcher = *index.searcher();
+        assert_eq!(searcher.num_docs(), 4);
+        assert_eq!(search_doc_ids(&searcher, Term::from_field_text(text_field, "zero old")), vec!());
+        assert_eq!(search_doc_ids(&searcher, Term::from_field_text(text_field, "zero new")).len(), 1);
+        assert_eq!(search_doc_ids(&searcher, Term::from_field_text(text_field, "nokey")).len(), 3);
+    }
+
 }
@@ -3,26 +3,44 @@ pub mod segment_serializer;
 pub mod merger;
 mod merge_policy;
 mod log_merge_policy;
+mod dedup_merge_policy;
 mod segment_register;
 mod segment_writer;
+mod segment_builder;
 mod segment_manager;
+mod fragmentation_report;
+mod segment_stats;
 pub mod delete_queue;
 pub mod segment_updater;
 mod directory_lock;
 mod segment_entry;
 mod doc_opstamp_mapping;
+mod computed_field;
+mod merge_progress;
 pub mod operation;
+mod operation_log;
 
 
 // TODO avoid exposing SegmentState / SegmentEntry if it does not have to be public API
 
 pub use self::segment_entry::{SegmentEntry, SegmentState};
 pub use self::segment_serializer::SegmentSerializer;
-pub use self::segment_writer::SegmentWriter;
-pub use self::index_writer::IndexWriter;
+pub use self::segment_writer::{SegmentWriter, resolve_tokenizers, exceeds_heap};
+pub use self::segment_builder::{SegmentBuilder, Row, Column};
+pub use self::index_writer::{IndexWriter, OversizedDocumentPolicy};
 pub use self::log_merge_policy::LogMergePolicy;
+pub use self::dedup_merge_policy::DedupMergePolicy;
 pub use self::merge_policy::{NoMergePolicy, MergeCandidate, MergePolicy};
 pub use self::segment_manager::SegmentManager;
+pub use self::fragmentation_report::FragmentationReport;
+pub use self::segment_stats::SegmentStats;
+pub use self::computed_field::ComputedField;
+pub use self::merge_progress::{MergePhase, MergeProgress};
+pub use self::operation::Operation;
+#[doc(hidden)]
+pub use self::operation_log::{load_operation_log, operation_log_files};
+#[doc(hidden)]
+pub use self::directory_lock::LOCKFILE_NAME;
 
 /// Alias for the default merge policy, which is the LogMergePolicy.
 pub type DefaultMergePolicy = LogMergePolicy;
@@ -1,12 +1,25 @@
 use schema::Document;
 use schema::Term;
+use common::BinarySerializable;
+use query::Query;
+use std::io::{self, Read, Write};
+use std::sync::Arc;
 
 
+/// Documents deleted by a delete operation.
+#[derive(Clone, Debug)]
+pub enum DeleteTarget {
+    /// The documents containing the term.
+    Term(Term),
+    /// The documents matching the query.
+    Query(Arc<Query>),
+}
+
 /// Timestamped Delete operation.
-#[derive(Clone, Eq, PartialEq, Debug)]
+#[derive(Clone, Debug)]
 pub struct DeleteOperation {
     pub opstamp: u64,
-    pub term: Term,
+    pub target: DeleteTarget,
 }
 
 /// Timestamped Add operation.
@@ -15,3 +28,101 @@ pub struct AddOperation {
     pub opstamp: u64,
     pub document: Document,
 }
+
+
+const ADD_CODE: u8 = 0;
+const DELETE_CODE: u8 = 1;
+const COMMIT_CODE: u8 = 2;
+const DELETE_QUERY_CODE: u8 = 3;
+
+/// Operation recorded in the operation log of an index.
+///
+/// See `Index::operation_log`.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub enum Operation {
+    /// A document was added.
+    ///
+    /// The documents imported by `IndexWriter::add_index`, or built
+    /// by `IndexWriter::build_segment`, share the opstamp of the call.
+    Add {
+        /// Opstamp of the document.
+        opstamp: u64,
+    },
+    /// The documents containing `term` were deleted.
+    Delete {
+        /// Opstamp of the delete operation.
+        opstamp: u64,
+        /// Term of the deleted documents.
+        term: Term,
+    },
+    /// The documents matching a query were deleted.
+    ///
+    /// The query itself is not recorded.
+    DeleteQuery {
+        /// Opstamp of the delete operation.
+        opstamp: u64,
+    },
+    /// The operations preceding this one were committed.
+    Commit {
+        /// Opstamp of the commit, as returned by `IndexWriter::commit`.
+        opstamp: u64,
+    },
+}
+
+impl Operation {
+    /// Returns the opstamp of the operation.
+    pub fn opstamp(&self) -> u64 {
+        match *self {
+            Operation::Add { opstamp } |
+            Operation::Delete { opstamp, .. } |
+            Operation::DeleteQuery { opstamp } |
+            Operation::Commit { opstamp } => opstamp,
+        }
+    }
+}
+
+impl BinarySerializable for Operation {
+    fn serialize(&self, writer: &mut Write) -> io::Result<usize> {
+        let mut written_size = 0;
+        match *self {
+            Operation::Add { opstamp } => {
+                written_size += try!(ADD_CODE.serialize(writer));
+                written_size += try!(opstamp.serialize(writer));
+            }
+            Operation::Delete { opstamp, ref term } => {
+                written_size += try!(DELETE_CODE.serialize(writer));
+                written_size += try!(opstamp.serialize(writer));
+                written_size += try!(term.as_slice().to_vec().serialize(writer));
+            }
+            Operation::DeleteQuery { opstamp } => {
+                written_size += try!(DELETE_QUERY_CODE.serialize(writer));
+                written_size += try!(opstamp.serialize(writer));
+            }
+            Operation::Commit { opstamp } => {
+                written_size += try!(COMMIT_CODE.serialize(writer));
+                written_size += try!(opstamp.serialize(writer));
+            }
+        }
+        Ok(written_size)
+    }
+
+    fn deserialize(reader: &mut Read) -> io::Result<Self> {
+        let type_code = try!(u8::deserialize(reader));
+        let opstamp = try!(u64::deserialize(reader));
+        match type_code {
+            ADD_CODE => Ok(Operation::Add { opstamp: opstamp }),
+            DELETE_CODE => {
+                let term_bytes: Vec<u8> = try!(Vec::deserialize(reader));
+                Ok(Operation::Delete {
+                    opstamp: opstamp,
+                    term: Term::from_bytes(&term_bytes),
+                })
+            }
+            DELETE_QUERY_CODE => Ok(Operation::DeleteQuery { opstamp: opstamp }),
+            COMMIT_CODE => Ok(Operation::Commit { opstamp: opstamp }),
+            _ => {
+                Err(io::Error::new(io::ErrorKind::InvalidData, format!("No operation is associated with code {:?}", type_code)))
+            }
+        }
+    }
+}
@@ -0,0 +1,128 @@
+use Result;
+use Error;
+use Directory;
+use common::BinarySerializable;
+use directory::ManagedDirectory;
+use directory::error::FileError;
+use indexer::operation::Operation;
+use std::path::PathBuf;
+
+
+/// Returns the path of the file holding the operations
+/// published by the commit of opstamp `commit_opstamp`.
+///
+/// The operation log is made of one file per commit, so that
+/// a commit only writes its own operations. Each file starts
+/// with the opstamp of the previous commit, which chains the
+/// files from the last commit to the first one.
+fn operation_log_filepath(commit_opstamp: u64) -> PathBuf {
+    PathBuf::from(format!("operations.{}.log", commit_opstamp))
+}
+
+/// Reads the file of the commit `commit_opstamp`, and returns the
+/// opstamp of the previous commit along with its operations,
+/// or `None` if the file does not exist.
+fn read_commit_operations(directory: &Directory, commit_opstamp: u64) -> Result<Option<(u64, Vec<Operation>)>> {
+    let path = operation_log_filepath(commit_opstamp);
+    let data = match directory.atomic_read(&path) {
+        Ok(data) => data,
+        Err(FileError::FileDoesNotExist(_)) => {
+            return Ok(None);
+        }
+        Err(e) => {
+            return Err(From::from(e));
+        }
+    };
+    let mut cursor = &data[..];
+    let previous_commit_opstamp = u64::deserialize(&mut cursor)
+        .map_err(|e| Error::CorruptedFile(path.clone(), Box::new(e)))?;
+    let mut operations = Vec::new();
+    while !cursor.is_empty() {
+        let operation = Operation::deserialize(&mut cursor)
+            .map_err(|e| Error::CorruptedFile(path.clone(), Box::new(e)))?;
+        operations.push(operation);
+    }
+    Ok(Some((previous_commit_opstamp, operations)))
+}
+
+/// Reads the files of the operation log, from the commit of
+/// opstamp `last_commit_opstamp` back to the first commit logged.
+///
+/// Returns the opstamp of each commit with its operations,
+/// from the oldest to the newest commit.
+fn read_operation_log(directory: &Directory, last_commit_opstamp: u64) -> Result<Vec<(u64, Vec<Operation>)>> {
+    let mut commits = Vec::new();
+    let mut commit_opstamp = last_commit_opstamp;
+    while let Some((previous_commit_opstamp, operations)) = read_commit_operations(directory, commit_opstamp)? {
+        commits.push((commit_opstamp, operations));
+        if previous_commit_opstamp >= commit_opstamp {
+            break;
+        }
+        commit_opstamp = previous_commit_opstamp;
+    }
+    commits.reverse();
+    Ok(commits)
+}
+
+/// Reads all of the operations of the operation log,
+/// in the order in which they were appended.
+///
+/// `last_commit_opstamp` is the opstamp of the last commit, as saved
+/// in the meta of the index: a commit that did not make it to the meta
+/// is not part of the log. The log is empty if nothing was ever committed.
+pub fn load_operation_log(directory: &Directory, last_commit_opstamp: u64) -> Result<Vec<Operation>> {
+    let commits = read_operation_log(directory, last_commit_opstamp)?;
+    Ok(commits
+        .into_iter()
+        .flat_map(|(_, operations)| operations)
+        .collect())
+}
+
+/// Returns the files of the operation log, up to the
+/// commit of opstamp `last_commit_opstamp`.
+pub fn operation_log_files(directory: &Directory, last_commit_opstamp: u64) -> Result<Vec<PathBuf>> {
+    let commits = read_operation_log(directory, last_commit_opstamp)?;
+    Ok(commits
+        .into_iter()
+        .map(|(commit_opstamp, _)| operation_log_filepath(commit_opstamp))
+        .collect())
+}
+
+/// Appends the operations published by the commit of opstamp
+/// `commit_opstamp`, the last of them being the commit itself.
+///
+/// Only the operations of the commit are written, atomically, in a file
+/// of their own, so that a crash cannot leave a partially written operation.
+/// The file is not managed by the directory, so that it is not garbage
+/// collected: it is only removed by `compact_operation_log`.
+///
+/// The log must be appended before the meta of the commit is saved.
+pub fn append_operation_log(directory: &mut ManagedDirectory,
+                            previous_commit_opstamp: u64,
+                            commit_opstamp: u64,
+                            operations: &[Operation]) -> Result<()> {
+    let mut data = Vec::new();
+    previous_commit_opstamp.serialize(&mut data)?;
+    for operation in operations {
+        operation.serialize(&mut data)?;
+    }
+    directory.atomic_write_unmanaged(&operation_log_filepath(commit_opstamp), &data)?;
+    Ok(())
+}
+
+/// Removes the operations of the log up to the last commit
+/// whose opstamp is lower or equal to `opstamp`, included.
+///
+/// The files of the oldest commits are removed first, so that
+/// the remaining files still chain up if the compaction fails.
+///
+/// Returns the number of operations removed.
+pub fn compact_operation_log(directory: &mut Directory, last_commit_opstamp: u64, opstamp: u64) -> Result<usize> {
+    let commits = read_operation_log(directory, last_commit_opstamp)?;
+    let mut num_removed = 0;
+    for &(commit_opstamp, ref operations) in commits.iter().take_while(|&&(commit_opstamp, _)| commit_opstamp <= opstamp) {
+        directory.delete(&operation_log_filepath(commit_opstamp))?;
+        num_removed += operations.len();
+    }
+    Ok(num_removed)
+}
@@ -0,0 +1,182 @@
+use Result;
+use Error;
+use core::Segment;
+use core::SegmentId;
+use core::SegmentMeta;
+use datastruct::stacker::{Heap, TermHashFunction};
+use indexer::ComputedField;
+use indexer::SegmentWriter;
+use indexer::segment_writer::RowValues;
+use schema::Field;
+use schema::Schema;
+use store::Compressor;
+use super::operation::AddOperation;
+
+
+/// Values of a field, one per row, for `SegmentBuilder::add_columns`.
+pub enum Column<'b> {
+    /// Values of a text field.
+    Text(Field, &'b [&'b str]),
+    /// Values of a u32 field.
+    U32(Field, &'b [u32]),
+}
+
+impl<'b> Column<'b> {
+    fn len(&self) -> usize {
+        match *self {
+            Column::Text(_, values) => values.len(),
+            Column::U32(_, values) => values.len(),
+        }
+    }
+}
+
+/// Values of the row being added to a `SegmentBuilder`.
+///
+/// See `SegmentBuilder::add_row`.
+pub struct Row<'b> {
+    values: &'b mut RowValues,
+}
+
+impl<'b> Row<'b> {
+
+    /// Adds a value to a text field.
+    pub fn add_text(&mut self, field: Field, text: &str) {
+        self.values.add_text(field, text);
+    }
+
+    /// Adds a value to a u32 field.
+    pub fn add_u32(&mut self, field: Field, value: u32) {
+        self.values.add_u32(field, value);
+    }
+}
+
+/// Writes a segment directly from rows or columns of values,
+/// without building a `Document` per row.
+///
+/// The values of a row are staged in buffers reused from one
+/// row to another. A `Document` is only built when the index
+/// writer has computed fields, which are populated from it.
+///
+/// The segment is the same as the one the `IndexWriter` would
+/// write for the same documents, added in the same order.
+///
+/// See `IndexWriter::build_segment`.
+pub struct SegmentBuilder<'a> {
+    segment_writer: SegmentWriter<'a>,
+    segment_id: SegmentId,
+    store_compressor: Compressor,
+    store_block_size: usize,
+    schema: Schema,
+    computed_fields: &'a [ComputedField],
+    opstamp: u64,
+    // reused for all of the rows.
+    row: RowValues,
+}
+
+impl<'a> SegmentBuilder<'a> {
+
+    #[doc(hidden)]
+    pub fn for_segment(heap: &'a Heap,
+                       segment: Segment,
+                       schema: Schema,
+                       term_hash_function: TermHashFunction,
+                       computed_fields: &'a [ComputedField],
+                       opstamp: u64) -> Result<SegmentBuilder<'a>> {
+        let segment_id = segment.id();
+        let store_compressor = segment.meta().store_compressor();
+        let store_block_size = segment.meta().store_block_size();
+        let segment_writer = try!(SegmentWriter::for_segment(heap, segment, &schema, term_hash_function));
+        Ok(SegmentBuilder {
+            segment_writer: segment_writer,
+            segment_id: segment_id,
+            store_compressor: store_compressor,
+            store_block_size: store_block_size,
+            schema: schema,
+            computed_fields: computed_fields,
+            opstamp: opstamp,
+            row: RowValues::default(),
+        })
+    }
+
+    /// Sets the maximum number of tokens indexed for each
+    /// field of a row. The following tokens are dropped.
+    pub fn set_max_tokens_per_field(&mut self, max_tokens_per_field: u32) {
+        self.segment_writer.set_max_tokens_per_field(max_tokens_per_field);
+    }
+
+    /// Returns the number of rows added so far.
+    pub fn max_doc(&self) -> u32 {
+        self.segment_writer.max_doc()
+    }
+
+    /// Adds a row, whose values are added by `fill_row`.
+    ///
+    /// # Errors
+    /// If the heap of the builder is full, returns `Error::InvalidArgument`
+    /// and adds no row: the segment cannot hold more rows, and should be
+    /// built with a larger heap. If a value does not match the type of
+    /// its field, returns `Error::InvalidArgument` and adds no row.
+    pub fn add_row<F: FnOnce(&mut Row)>(&mut self, fill_row: F) -> Result<()> {
+        if self.segment_writer.is_buffer_full() {
+            return Err(Error::InvalidArgument(format!("The heap of the segment builder is full after {} rows.",
+                self.segment_writer.max_doc())));
+        }
+        self.row.clear();
+        fill_row(&mut Row { values: &mut self.row });
+        try!(self.row.check_types(&self.schema));
+        if self.computed_fields.is_empty() {
+            try!(self.segment_writer.add_row(self.opstamp, &mut self.row, &self.schema));
+        }
+        else {
+            let mut document = self.row.to_document();
+            for computed_field in self.computed_fields {
+                computed_field.populate(&mut document);
+            }
+            let add_operation = AddOperation {
+                opstamp: self.opstamp,
+                document: document,
+            };
+            try!(self.segment_writer.add_document(&add_operation, &self.schema));
+        }
+        Ok(())
+    }
+
+    /// Adds one row per value of the columns.
+    ///
+    /// # Errors
+    /// If the columns do not have the same number
+    /// of values, returns `Error::InvalidArgument`
+    /// and adds no row.
+    pub fn add_columns(&mut self, columns: &[Column]) -> Result<()> {
+        let num_rows = columns.first().map(Column::len).unwrap_or(0);
+        if columns.iter().any(|column| column.len() != num_rows) {
+            return Err(Error::InvalidArgument(String::from("All of the columns must have the same number of values.")));
+        }
+        for row_id in 0..num_rows {
+            try!(self.add_row(|row| {
+                for column in columns {
+                    match *column {
+                        Column::Text(field, values) => row.add_text(field, values[row_id]),
+                        Column::U32(field, values) => row.add_u32(field, values[row_id]),
+                    }
+                }
+            }));
+        }
+        Ok(())
+    }
+
+    /// Writes the segment, and returns its meta.
+    #[doc(hidden)]
+    pub fn finalize(self) -> Result<SegmentMeta> {
+        let max_doc = self.segment_writer.max_doc();
+        try!(self.segment_writer.finalize());
+        let mut segment_meta = SegmentMeta::new(self.segment_id);
+        segment_meta.set_max_doc(max_doc);
+        segment_meta.set_store_compressor(self.store_compressor);
+        segment_meta.set_store_block_size(self.store_block_size);
+        segment_meta.set_has_fieldnorms(self.schema.has_fieldnorms());
+        segment_meta.set_has_term_vectors(self.schema.has_term_vectors());
+        segment_meta.set_opstamp(self.opstamp);
+        Ok(segment_meta)
+    }
+}
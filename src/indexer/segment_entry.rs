@@ -3,17 +3,25 @@ use core::SegmentMeta;
 use core::SegmentId;
 use std::fmt;
 
+/// State of a segment, as seen by the `IndexWriter`.
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub enum SegmentState {
+    /// The segment is available for merges.
     Ready,
+    /// The segment is being merged.
     InMerge,    
+    /// The segment is being written, by an indexing
+    /// worker or by a merge, and is not registered yet.
+    Writing,
 }
 
 impl SegmentState {
+    /// Returns a one letter code for the state, used in logs.
     pub fn letter_code(&self,) -> char {
         match *self {
             SegmentState::InMerge => 'M',
             SegmentState::Ready => 'R',
+            SegmentState::Writing => 'W',
         }
     }
 }
@@ -59,10 +67,20 @@ impl SegmentEntry {
         &self.meta
     }
 
+    pub fn set_meta(&mut self, segment_meta: SegmentMeta) {
+        self.meta = segment_meta;
+    }
+
     pub fn start_merge(&mut self,) {
         self.state = SegmentState::InMerge;
     }
 
+    /// Makes the segment available for merges again,
+    /// after the merge it was part of failed.
+    pub fn cancel_merge(&mut self,) {
+        self.state = SegmentState::Ready;
+    }
+
     pub fn is_ready(&self,) -> bool {
         self.state == SegmentState::Ready
     }
@@ -3,11 +3,18 @@ use std::sync::RwLock;
 use core::SegmentMeta;
 use core::META_FILEPATH;
 use core::SegmentId;
+use core::SegmentAccessCounters;
 use indexer::{SegmentEntry, SegmentState};
+use indexer::FragmentationReport;
+use indexer::SegmentStats;
+use directory::Directory;
+use indexer::directory_lock::LOCKFILE_NAME;
 use std::path::PathBuf;
 use std::collections::hash_set::HashSet;
 use std::sync::{RwLockReadGuard, RwLockWriteGuard};
 use std::fmt::{self, Debug, Formatter};
+use Error;
+use Result;
 
 #[derive(Default)]
 struct SegmentRegisters {
@@ -26,10 +33,11 @@ struct SegmentRegisters {
 #[derive(Default)]
 pub struct SegmentManager {
     registers: RwLock<SegmentRegisters>,
+    access_counters: SegmentAccessCounters,
 }
 
 impl Debug for SegmentManager {
-    fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
         let lock = self.read();
         write!(f, "{{ uncommitted: {:?}, committed: {:?} }}", lock.uncommitted, lock.committed)
     }
@@ -49,39 +57,56 @@ pub fn get_segments(segment_manager: &SegmentManager,) -> (Vec<SegmentMeta>, Vec
 
 impl SegmentManager {
     
-    pub fn from_segments(segment_metas: Vec<SegmentMeta>) -> SegmentManager {
+    pub fn from_segments(segment_metas: Vec<SegmentMeta>, access_counters: SegmentAccessCounters) -> SegmentManager {
         SegmentManager {
             registers: RwLock::new(SegmentRegisters {
                 uncommitted: SegmentRegister::default(),
                 committed: SegmentRegister::new(segment_metas),
                 writing: HashSet::new(),
             }),
+            access_counters: access_counters,
         }
     }
 
     pub fn segment_entries(&self,) -> Vec<SegmentEntry> {
-        let mut segment_entries = self.read()
+        self.read_segment_entries(|segment_entries| segment_entries)
+    }
+
+    /// Calls `f` on the entries of the uncommitted and committed
+    /// segments, while holding the read lock.
+    ///
+    /// The segment set cannot change while `f` is running:
+    /// a concurrent commit, rollback or the end of a merge
+    /// waits for `f` to return.
+    pub fn read_segment_entries<F, T>(&self, f: F) -> T
+        where F: FnOnce(Vec<SegmentEntry>) -> T {
+        let registers_lock = self.read();
+        let mut segment_entries = registers_lock
             .uncommitted
             .segment_entries();
         segment_entries.extend(
-            self.read()
+            registers_lock
             .committed
             .segment_entries()
         );
-        segment_entries
+        f(segment_entries)
     }
 
     pub fn list_files(&self) -> HashSet<PathBuf> {
         let registers_lock = self.read();
         let mut files = HashSet::new();
         files.insert(META_FILEPATH.clone());
+        // the lock file is held by the `IndexWriter` itself.
+        files.insert(PathBuf::from(LOCKFILE_NAME));
         
+        // the segments being merged are listed as well,
+        // as deletes may be committed during the merge.
         let segment_metas =
             registers_lock.committed
-                .get_segments()
+                .segment_metas()
                 .into_iter()
                 .chain(registers_lock.uncommitted
-                    .get_segments()
+                    .segment_metas()
                     .into_iter())
                 .chain(registers_lock.writing
                     .iter()
@@ -94,6 +119,77 @@ impl SegmentManager {
         files
     }
 
+    /// Returns the state of all of the segments known
+    /// by the `SegmentManager`: the committed segments, the uncommitted
+    /// segments, and the segments being written, reported
+    /// as `SegmentState::Writing`.
+    ///
+    /// The result is consistent with other transactions, and
+    /// a segment appears at most once.
+    pub fn segment_states(&self) -> Vec<(SegmentId, SegmentState)> {
+        let registers_lock = self.read();
+        let mut segment_ids = HashSet::new();
+        let mut segment_states = Vec::new();
+        let segment_entries = registers_lock.committed
+            .segment_entries()
+            .into_iter()
+            .chain(registers_lock.uncommitted.segment_entries().into_iter());
+        for segment_entry in segment_entries {
+            if segment_ids.insert(segment_entry.segment_id()) {
+                segment_states.push((segment_entry.segment_id(), segment_entry.state()));
+            }
+        }
+        for segment_id in &registers_lock.writing {
+            if segment_ids.insert(*segment_id) {
+                segment_states.push((*segment_id, SegmentState::Writing));
+            }
+        }
+        segment_states
+    }
+
+    /// Returns the stats of all of the segments known by the `SegmentManager`,
+    /// in the same order as `segment_states`. The segments being written
+    /// are reported without any document, and without a size.
+    ///
+    /// The segments are listed under a single read lock, so that the list is
+    /// consistent with other transactions, and a segment appears at most once.
+    /// The size of a segment is the total length of the files of `SegmentMeta::list_files`
+    /// that exist in `directory`. It is measured once the lock is released.
+    pub fn segment_stats(&self, directory: &Directory) -> Vec<SegmentStats> {
+        let (segment_entries, writing_segment_ids) = {
+            let registers_lock = self.read();
+            let segment_entries: Vec<SegmentEntry> = registers_lock.committed
+                .segment_entries()
+                .into_iter()
+                .chain(registers_lock.uncommitted.segment_entries().into_iter())
+                .collect();
+            let writing_segment_ids: Vec<SegmentId> = registers_lock.writing
+                .iter()
+                .cloned()
+                .collect();
+            (segment_entries, writing_segment_ids)
+        };
+        let mut segment_ids = HashSet::new();
+        let mut segment_stats = Vec::new();
+        for segment_entry in segment_entries {
+            if segment_ids.insert(segment_entry.segment_id()) {
+                let num_bytes: u64 = segment_entry
+                    .meta()
+                    .list_files()
+                    .iter()
+                    .filter_map(|path| directory.file_len(path).ok())
+                    .sum();
+                segment_stats.push(SegmentStats::for_entry(&segment_entry, num_bytes));
+            }
+        }
+        for segment_id in writing_segment_ids {
+            if segment_ids.insert(segment_id) {
+                segment_stats.push(SegmentStats::writing(segment_id));
+            }
+        }
+        segment_stats
+    }
+
     pub fn segment_state(&self, segment_id: &SegmentId) -> Option<SegmentState> {
         self.segment_entry(segment_id)
             .map(|segment_entry| segment_entry.state())
@@ -107,31 +203,84 @@ impl SegmentManager {
             .or_else(|| registers.uncommitted.segment_entry(segment_id))        
     }
 
-    // Lock poisoning should never happen :
+    /// Returns the time at which the segment was created,
+    /// in seconds since the UNIX epoch.
+    ///
+    /// Returns `None` if the segment is unknown, or if its
+    /// meta was written by an older version of tantivy.
+    pub fn segment_created_at(&self, segment_id: &SegmentId) -> Option<u64> {
+        self.segment_entry(segment_id)
+            .and_then(|segment_entry| segment_entry.meta().created_at())
+    }
+
+    /// Returns the opstamp of the last delete operation
+    /// taken into account by the delete set of the segment.
+    ///
+    /// Deletes with a greater opstamp have not been applied
+    /// to the segment yet.
+    ///
+    /// Returns `None` if the segment is unknown, or if
+    /// no delete was ever applied to it.
+    pub fn segment_delete_opstamp(&self, segment_id: &SegmentId) -> Option<u64> {
+        self.segment_entry(segment_id)
+            .and_then(|segment_entry| segment_entry.meta().delete_opstamp())
+    }
+
+    // Lock poisoning should not happen :
     // The lock is acquired and released within this class,
-    // and the operations cannot panic. 
+    // and the operations should not panic.
+    //
+    // If it does happen anyway, the registers are still
+    // self-consistent, as they are only modified through 
+    // operations that cannot be interrupted half-way.
+    // We therefore recover the lock rather than
+    // making the `SegmentManager` unusable.
     fn read(&self,) -> RwLockReadGuard<SegmentRegisters> { 
-        self.registers.read().expect("Failed to acquire read lock on SegmentManager.")
+        self.registers.read().unwrap_or_else(|poisoned| {
+            warn!("Recovering the poisoned read lock of the SegmentManager.");
+            poisoned.into_inner()
+        })
     }
 
     fn write(&self,) -> RwLockWriteGuard<SegmentRegisters> {
-        self.registers.write().expect("Failed to acquire write lock on SegmentManager.")
+        self.registers.write().unwrap_or_else(|poisoned| {
+            warn!("Recovering the poisoned write lock of the SegmentManager.");
+            poisoned.into_inner()
+        })
     }
 
     /// Removes all of the uncommitted segments
-    /// and returns them.
-    pub fn rollback(&self,) -> Vec<SegmentId> {
+    /// and returns their metas.
+    ///
+    /// The metas are read under the same lock as the one
+    /// clearing the uncommitted segments, so that the files
+    /// of all of the removed segments can be listed.
+    pub fn rollback(&self,) -> Vec<SegmentMeta> {
         let mut registers_lock = self.write();
-        let segment_ids = registers_lock.uncommitted.segment_ids();
+        let segment_metas = registers_lock.uncommitted.segment_metas();
         registers_lock.uncommitted.clear();
-        segment_ids
+        segment_metas
     }
 
-    pub fn commit(&self, segment_metas: Vec<SegmentMeta>) {
+    /// Makes `segment_metas` the committed segments.
+    ///
+    /// The formerly uncommitted segments are labelled with `label`.
+    pub fn commit(&self, segment_metas: Vec<SegmentMeta>, label: Option<&str>) {
+         let uncommitted_segment_ids: HashSet<SegmentId> = self.read()
+                .uncommitted
+                .segment_metas()
+                .iter()
+                .map(|segment_meta| segment_meta.id())
+                .collect();
          let committed_segment_entries = segment_metas
                 .into_iter()
-                .map(|segment_meta| {
+                .map(|mut segment_meta| {
                     let segment_id = segment_meta.id();
+                    if let Some(label) = label {
+                        if uncommitted_segment_ids.contains(&segment_id) {
+                            segment_meta.set_label(label);
+                        }
+                    }
                     let mut segment_entry = SegmentEntry::new(segment_meta);
                     if let Some(state) = self.segment_state(&segment_id) {
                         segment_entry.set_state(state);
@@ -147,6 +296,22 @@ impl SegmentManager {
         }
     }
     
+    /// Removes a committed segment.
+    ///
+    /// Returns false if the segment is not committed,
+    /// or if it is currently being merged.
+    pub fn remove_committed_segment(&self, segment_id: &SegmentId) -> bool {
+        let mut registers_lock = self.write();
+        let is_ready = registers_lock.committed
+            .segment_entry(segment_id)
+            .map(|segment_entry| segment_entry.is_ready())
+            .unwrap_or(false);
+        if is_ready {
+            registers_lock.committed.remove_segment(segment_id);
+        }
+        is_ready
+    }
+    
     pub fn start_merge(&self, segment_ids: &[SegmentId]) {
         let mut registers_lock = self.write();
         if registers_lock.uncommitted.contains_all(segment_ids) {
@@ -164,38 +329,324 @@ impl SegmentManager {
         }
     }
 
+    /// Puts the segments of a merge that failed back
+    /// in the `Ready` state, so that they can be merged again.
+    ///
+    /// The segments that are no longer registered,
+    /// e.g. after a rollback, are ignored.
+    pub fn cancel_merge(&self, segment_ids: &[SegmentId]) {
+        let mut registers_lock = self.write();
+        for segment_id in segment_ids {
+            registers_lock.uncommitted.cancel_merge(segment_id);
+            registers_lock.committed.cancel_merge(segment_id);
+        }
+    }
+
     pub fn write_segment(&self, segment_id: SegmentId) {
         let mut registers_lock = self.write();
         registers_lock.writing.insert(segment_id);
     }
 
+    /// Forgets about a segment that was being written,
+    /// so that its files may be garbage collected.
+    pub fn abort_write(&self, segment_id: &SegmentId) {
+        let mut registers_lock = self.write();
+        registers_lock.writing.remove(segment_id);
+    }
+
     pub fn add_segment(&self, segment_entry: SegmentEntry) {
         let mut registers_lock = self.write();
         registers_lock.writing.remove(&segment_entry.segment_id());
         registers_lock.uncommitted.add_segment_entry(segment_entry);
     }
     
-    pub fn end_merge(&self, merged_segment_metas: &[SegmentMeta], merged_segment_entry: SegmentEntry) {
+    pub fn end_merge(&self, merged_segment_metas: &[SegmentMeta], merged_segment_entries: Vec<SegmentEntry>) {
         let mut registers_lock = self.write();
+        for merged_segment_entry in &merged_segment_entries {
+            registers_lock.writing.remove(&merged_segment_entry.segment_id());
+        }
         let merged_segment_ids: Vec<SegmentId> = merged_segment_metas.iter().map(|meta| meta.id()).collect();
         if registers_lock.uncommitted.contains_all(&merged_segment_ids) {
             for segment_id in &merged_segment_ids {
                 registers_lock.uncommitted.remove_segment(segment_id);
             }
-            registers_lock.uncommitted.add_segment_entry(merged_segment_entry);
+            for merged_segment_entry in merged_segment_entries {
+                registers_lock.uncommitted.add_segment_entry(merged_segment_entry);
+            }
         }
         else if registers_lock.committed.contains_all(&merged_segment_ids) {
             for segment_id in &merged_segment_ids {
                 registers_lock.committed.remove_segment(segment_id);
             }
-            registers_lock.committed.add_segment_entry(merged_segment_entry);
+            for merged_segment_entry in merged_segment_entries {
+                registers_lock.committed.add_segment_entry(merged_segment_entry);
+            }
         } else {
             warn!("couldn't find segment in SegmentManager");
+            return;
+        }
+        for segment_id in &merged_segment_ids {
+            self.access_counters.remove(segment_id);
+        }
+    }
+
+    /// Replaces the segments `old_segment_ids` by `new_segment_entry`,
+    /// e.g. the result of their merge by another process.
+    ///
+    /// As with `end_merge`, all of the old segments need to be
+    /// either committed or uncommitted, and the new segment
+    /// takes their place in the same register.
+    ///
+    /// # Errors
+    /// If some of the old segments are not in the same register,
+    /// returns `Error::InvalidArgument`, leaving the segments untouched.
+    pub fn replace_segments(&self, old_segment_ids: &[SegmentId], new_segment_entry: SegmentEntry) -> Result<()> {
+        let mut registers_lock = self.write();
+        let registers = &mut *registers_lock;
+        let register = if registers.uncommitted.contains_all(old_segment_ids) {
+            &mut registers.uncommitted
+        }
+        else if registers.committed.contains_all(old_segment_ids) {
+            &mut registers.committed
         }
+        else {
+            return Err(Error::InvalidArgument(format!("Segments {:?} are not all committed or all uncommitted.", old_segment_ids)));
+        };
+        for segment_id in old_segment_ids {
+            register.remove_segment(segment_id);
+        }
+        registers.writing.remove(&new_segment_entry.segment_id());
+        register.add_segment_entry(new_segment_entry);
+        Ok(())
     }
 
     pub fn committed_segment_metas(&self,) -> Vec<SegmentMeta> {
         let registers_lock = self.read();
         registers_lock.committed.segment_metas()
     }
+
+    /// Returns the committed segments, from the largest to the smallest
+    /// in number of (non-deleted) documents.
+    ///
+    /// Segments with the same number of documents are sorted by segment id.
+    pub fn committed_by_size(&self,) -> Vec<SegmentMeta> {
+        let mut segment_metas = self.committed_segment_metas();
+        segment_metas.sort_by(|left, right| {
+            right.num_docs()
+                .cmp(&left.num_docs())
+                .then_with(|| left.id().cmp(&right.id()))
+        });
+        segment_metas
+    }
+
+    /// Computes the fragmentation metrics of the committed segments.
+    pub fn fragmentation_report(&self,) -> FragmentationReport {
+        let segment_metas = self.committed_segment_metas();
+        FragmentationReport::from(&segment_metas[..])
+    }
+
+    /// Returns the number of scorers created on each of the
+    /// uncommitted and committed segments, by the searchers of the index.
+    ///
+    /// The hot segments are the ones with the highest counts.
+    pub fn segment_access_counts(&self,) -> Vec<(SegmentId, usize)> {
+        self.segment_entries()
+            .into_iter()
+            .map(|segment_entry| {
+                let segment_id = segment_entry.segment_id();
+                (segment_id, self.access_counters.access_count(segment_id))
+            })
+            .collect()
+    }
+
+    /// Removes the access counters of the segments that are
+    /// neither uncommitted nor committed, such as the segments
+    /// that were merged, dropped or rolled back.
+    pub fn prune_access_counters(&self) {
+        let segment_ids: HashSet<SegmentId> = self.segment_entries()
+            .iter()
+            .map(|segment_entry| segment_entry.segment_id())
+            .collect();
+        self.access_counters.retain(|segment_id| segment_ids.contains(segment_id));
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use directory::RAMDirectory;
+    use core::SegmentId;
+    use core::SegmentMeta;
+    use indexer::SegmentEntry;
+    use super::*;
+
+    fn segment_meta(max_doc: u32, num_deleted_docs: u32) -> SegmentMeta {
+        let mut segment_meta = SegmentMeta::new(SegmentId::generate_random());
+        segment_meta.set_max_doc(max_doc);
+        if num_deleted_docs > 0 {
+            segment_meta.set_delete_meta(num_deleted_docs, 0u64);
+        }
+        segment_meta
+    }
+
+    #[test]
+    fn test_poisoned_lock() {
+        use std::sync::Arc;
+        use std::thread;
+        let segment_manager = Arc::new(SegmentManager::from_segments(vec!(segment_meta(10, 0)), SegmentAccessCounters::default()));
+        {
+            let segment_manager = segment_manager.clone();
+            let join_result = thread::spawn(move || {
+                let _registers_lock = segment_manager.write();
+                panic!("panicking while holding the lock");
+            }).join();
+            assert!(join_result.is_err());
+        }
+        assert!(segment_manager.registers.read().is_err());
+        segment_manager.add_segment(SegmentEntry::new(segment_meta(5, 0)));
+        assert_eq!(segment_manager.segment_entries().len(), 2);
+        let (committed, uncommitted) = get_segments(&segment_manager);
+        assert_eq!(committed.len(), 1);
+        assert_eq!(uncommitted.len(), 1);
+    }
+
+    #[test]
+    fn test_segment_states() {
+        let committed = segment_meta(10, 0);
+        let in_merge = segment_meta(10, 0);
+        let uncommitted = segment_meta(10, 0);
+        let segment_manager = SegmentManager::from_segments(vec!(committed.clone(), in_merge.clone()), SegmentAccessCounters::default());
+        segment_manager.start_merge(&[in_merge.id()]);
+        segment_manager.add_segment(SegmentEntry::new(uncommitted.clone()));
+        let writing_id = SegmentId::generate_random();
+        segment_manager.write_segment(writing_id);
+        let mut segment_states = segment_manager.segment_states();
+        segment_states.sort_by_key(|&(segment_id, _)| segment_id);
+        let mut expected_states = vec!(
+            (committed.id(), SegmentState::Ready),
+            (in_merge.id(), SegmentState::InMerge),
+            (uncommitted.id(), SegmentState::Ready),
+            (writing_id, SegmentState::Writing));
+        expected_states.sort_by_key(|&(segment_id, _)| segment_id);
+        assert_eq!(segment_states, expected_states);
+        // once written, the segment is no longer reported as being written.
+        segment_manager.add_segment(SegmentEntry::new(SegmentMeta::new(writing_id)));
+        let segment_states = segment_manager.segment_states();
+        assert_eq!(segment_states.len(), 4);
+        assert!(segment_states.contains(&(writing_id, SegmentState::Ready)));
+        // a failed merge makes its segments ready again.
+        segment_manager.cancel_merge(&[in_merge.id(), SegmentId::generate_random()]);
+        assert_eq!(segment_manager.segment_state(&in_merge.id()), Some(SegmentState::Ready));
+        assert_eq!(segment_manager.segment_states().len(), 4);
+    }
+
+    #[test]
+    fn test_segment_stats() {
+        use core::SegmentComponent;
+        use std::io::Write;
+        let mut directory = RAMDirectory::create();
+        let committed = segment_meta(10, 2);
+        let uncommitted = segment_meta(5, 0);
+        for &(ref segment_meta, num_bytes) in &[(&committed, 3), (&uncommitted, 7)] {
+            for &component in &[SegmentComponent::POSTINGS, SegmentComponent::STORE] {
+                let mut write = directory.open_write(&segment_meta.relative_path(component)).unwrap();
+                write.write_all(&vec!(0u8; num_bytes)).unwrap();
+                write.flush().unwrap();
+            }
+        }
+        let segment_manager = SegmentManager::from_segments(vec!(committed.clone()), SegmentAccessCounters::default());
+        segment_manager.add_segment(SegmentEntry::new(uncommitted.clone()));
+        let writing_id = SegmentId::generate_random();
+        segment_manager.write_segment(writing_id);
+        let segment_stats = segment_manager.segment_stats(&directory);
+        assert_eq!(segment_stats.len(), 3);
+        let stats = |segment_id: SegmentId| segment_stats
+            .iter()
+            .find(|segment_stats| segment_stats.segment_id() == segment_id)
+            .unwrap()
+            .clone();
+        let committed_stats = stats(committed.id());
+        assert_eq!(committed_stats.state(), SegmentState::Ready);
+        assert_eq!(committed_stats.num_docs(), 8);
+        assert_eq!(committed_stats.num_deleted_docs(), 2);
+        // the missing files, such as the delete file, are not counted.
+        assert_eq!(committed_stats.num_bytes(), Some(6));
+        assert_eq!(stats(uncommitted.id()).num_docs(), 5);
+        assert_eq!(stats(uncommitted.id()).num_bytes(), Some(14));
+        let writing_stats = stats(writing_id);
+        assert_eq!(writing_stats.state(), SegmentState::Writing);
+        assert_eq!(writing_stats.num_docs(), 0);
+        assert_eq!(writing_stats.num_bytes(), None);
+        let segment_states: Vec<(SegmentId, SegmentState)> = segment_stats
+            .iter()
+            .map(|segment_stats| (segment_stats.segment_id(), segment_stats.state()))
+            .collect();
+        assert_eq!(segment_states, segment_manager.segment_states());
+    }
+
+    #[test]
+    fn test_rollback() {
+        let committed = segment_meta(10, 0);
+        let uncommitted = segment_meta(20, 5);
+        let segment_manager = SegmentManager::from_segments(vec!(committed.clone()), SegmentAccessCounters::default());
+        segment_manager.add_segment(SegmentEntry::new(uncommitted.clone()));
+        let rolled_back_metas = segment_manager.rollback();
+        assert_eq!(rolled_back_metas.len(), 1);
+        assert_eq!(rolled_back_metas[0].id(), uncommitted.id());
+        assert_eq!(rolled_back_metas[0].list_files(), uncommitted.list_files());
+        let (committed_metas, uncommitted_metas) = get_segments(&segment_manager);
+        assert_eq!(committed_metas.len(), 1);
+        assert_eq!(committed_metas[0].id(), committed.id());
+        assert!(uncommitted_metas.is_empty());
+        assert!(segment_manager.rollback().is_empty());
+    }
+
+    #[test]
+    fn test_replace_segments() {
+        let committed_a = segment_meta(10, 0);
+        let committed_b = segment_meta(20, 0);
+        let uncommitted = segment_meta(5, 0);
+        let segment_manager = SegmentManager::from_segments(vec!(committed_a.clone(), committed_b.clone()), SegmentAccessCounters::default());
+        segment_manager.add_segment(SegmentEntry::new(uncommitted.clone()));
+        let sorted_ids = |segment_metas: Vec<SegmentMeta>| {
+            let mut segment_ids: Vec<SegmentId> = segment_metas.iter().map(|segment_meta| segment_meta.id()).collect();
+            segment_ids.sort();
+            segment_ids
+        };
+
+        // one of the old segments is missing.
+        let missing = segment_meta(10, 0);
+        assert!(segment_manager.replace_segments(&[committed_a.id(), missing.id()], SegmentEntry::new(segment_meta(20, 0))).is_err());
+        // the old segments are not in the same register.
+        assert!(segment_manager.replace_segments(&[committed_a.id(), uncommitted.id()], SegmentEntry::new(segment_meta(15, 0))).is_err());
+        let (committed_metas, uncommitted_metas) = get_segments(&segment_manager);
+        assert_eq!(sorted_ids(committed_metas), sorted_ids(vec!(committed_a.clone(), committed_b.clone())));
+        assert_eq!(sorted_ids(uncommitted_metas), vec!(uncommitted.id()));
+
+        let merged = segment_meta(30, 0);
+        segment_manager.write_segment(merged.id());
+        segment_manager.replace_segments(&[committed_a.id(), committed_b.id()], SegmentEntry::new(merged.clone())).unwrap();
+        let (committed_metas, uncommitted_metas) = get_segments(&segment_manager);
+        assert_eq!(sorted_ids(committed_metas), vec!(merged.id()));
+        assert_eq!(sorted_ids(uncommitted_metas), vec!(uncommitted.id()));
+        assert!(segment_manager.segment_entry(&merged.id()).is_some());
+        assert!(segment_manager.segment_entry(&committed_a.id()).is_none());
+    }
+
+    #[test]
+    fn test_committed_by_size() {
+        let small = segment_meta(10, 0);
+        let large = segment_meta(1_000, 0);
+        // 100 documents once deletes are accounted for.
+        let medium = segment_meta(150, 50);
+        let uncommitted = segment_meta(5_000, 0);
+        let segment_manager = SegmentManager::from_segments(vec!(small.clone(), large.clone(), medium.clone()), SegmentAccessCounters::default());
+        segment_manager.add_segment(SegmentEntry::new(uncommitted));
+        let segment_ids: Vec<SegmentId> = segment_manager
+            .committed_by_size()
+            .iter()
+            .map(|segment_meta| segment_meta.id())
+            .collect();
+        assert_eq!(segment_ids, vec!(large.id(), medium.id(), small.id()));
+    }
 }
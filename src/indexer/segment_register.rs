@@ -94,6 +94,13 @@ impl SegmentRegister {
             .expect("Received a merge notification for a segment that is not registered")
             .start_merge();
     } 
+
+    /// Does nothing if the segment is not registered.
+    pub fn cancel_merge(&mut self, segment_id: &SegmentId) {
+        if let Some(segment_entry) = self.segment_states.get_mut(segment_id) {
+            segment_entry.cancel_merge();
+        }
+    }
     
     pub fn new(segment_metas: Vec<SegmentMeta>) -> SegmentRegister {
         SegmentRegister {
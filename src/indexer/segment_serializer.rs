@@ -7,6 +7,7 @@ use core::SegmentInfo;
 use core::SegmentComponent;
 use fastfield::FastFieldSerializer;
 use store::StoreWriter;
+use termvector::TermVectorWriter;
 use postings::PostingsSerializer;
 
 
@@ -16,7 +17,10 @@ pub struct SegmentSerializer {
     segment: Segment,
     store_writer: StoreWriter,
     fast_field_serializer: FastFieldSerializer,
-    fieldnorms_serializer: FastFieldSerializer,
+    // `None` if none of the fields has field norms.
+    fieldnorms_serializer: Option<FastFieldSerializer>,
+    // `None` if none of the fields has term vectors.
+    term_vector_writer: Option<TermVectorWriter>,
     postings_serializer: PostingsSerializer,
 }
 
@@ -28,16 +32,34 @@ impl SegmentSerializer {
         let fast_field_write = try!(segment.open_write(SegmentComponent::FASTFIELDS));
         let fast_field_serializer = try!(FastFieldSerializer::new(fast_field_write));
 
-        let fieldnorms_write = try!(segment.open_write(SegmentComponent::FIELDNORMS));
-        let fieldnorms_serializer = try!(FastFieldSerializer::new(fieldnorms_write));
+        let fieldnorms_serializer =
+            if segment.schema().has_fieldnorms() {
+                let fieldnorms_write = try!(segment.open_write(SegmentComponent::FIELDNORMS));
+                Some(try!(FastFieldSerializer::new(fieldnorms_write)))
+            }
+            else {
+                None
+            };
+
+        let term_vector_writer =
+            if segment.schema().has_term_vectors() {
+                let term_vector_write = try!(segment.open_write(SegmentComponent::TERMVECTORS));
+                Some(TermVectorWriter::new(term_vector_write))
+            }
+            else {
+                None
+            };
 
         let postings_serializer = try!(PostingsSerializer::open(segment));
         Ok(SegmentSerializer {
             segment: segment.clone(),
             postings_serializer: postings_serializer,
-            store_writer: StoreWriter::new(store_write),
+            store_writer: StoreWriter::with_block_size(store_write,
+                                                       segment.meta().store_compressor(),
+                                                       segment.meta().store_block_size()),
             fast_field_serializer: fast_field_serializer,
             fieldnorms_serializer: fieldnorms_serializer,
+            term_vector_writer: term_vector_writer,
         })
     }
 
@@ -52,8 +74,19 @@ impl SegmentSerializer {
     }
 
     /// Accessor to the field norm serializer.
-    pub fn get_fieldnorms_serializer(&mut self) -> &mut FastFieldSerializer {
-        &mut self.fieldnorms_serializer
+    ///
+    /// Returns `None` if none of the fields has field norms,
+    /// in which case no field norm file is written.
+    pub fn get_fieldnorms_serializer(&mut self) -> Option<&mut FastFieldSerializer> {
+        self.fieldnorms_serializer.as_mut()
+    }
+
+    /// Accessor to the `TermVectorWriter`.
+    ///
+    /// Returns `None` if none of the fields has term vectors,
+    /// in which case no term vector file is written.
+    pub fn get_term_vector_writer(&mut self) -> Option<&mut TermVectorWriter> {
+        self.term_vector_writer.as_mut()
     }
 
     /// Accessor to the `StoreWriter`.
@@ -76,7 +109,12 @@ impl SegmentSerializer {
         try!(self.fast_field_serializer.close());
         try!(self.postings_serializer.close());
         try!(self.store_writer.close());
-        try!(self.fieldnorms_serializer.close());
+        if let Some(fieldnorms_serializer) = self.fieldnorms_serializer {
+            try!(fieldnorms_serializer.close());
+        }
+        if let Some(term_vector_writer) = self.term_vector_writer {
+            try!(term_vector_writer.close());
+        }
         Ok(())
     }
 }
@@ -0,0 +1,75 @@
+use core::SegmentId;
+use indexer::{SegmentEntry, SegmentState};
+
+/// Metrics about a segment, as reported by `SegmentManager::segment_stats`.
+///
+/// They are meant to help capacity planning, e.g. deciding
+/// when to merge segments, or when to scale out.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SegmentStats {
+    segment_id: SegmentId,
+    state: SegmentState,
+    num_docs: u32,
+    num_deleted_docs: u32,
+    num_bytes: Option<u64>,
+}
+
+impl SegmentStats {
+
+    /// Creates the stats of a segment that has been written,
+    /// given the total size of its files.
+    #[doc(hidden)]
+    pub fn for_entry(segment_entry: &SegmentEntry, num_bytes: u64) -> SegmentStats {
+        let segment_meta = segment_entry.meta();
+        SegmentStats {
+            segment_id: segment_meta.id(),
+            state: segment_entry.state(),
+            num_docs: segment_meta.num_docs(),
+            num_deleted_docs: segment_meta.num_deleted_docs(),
+            num_bytes: Some(num_bytes),
+        }
+    }
+
+    /// Creates the stats of a segment being written,
+    /// of which nothing is known yet.
+    #[doc(hidden)]
+    pub fn writing(segment_id: SegmentId) -> SegmentStats {
+        SegmentStats {
+            segment_id: segment_id,
+            state: SegmentState::Writing,
+            num_docs: 0,
+            num_deleted_docs: 0,
+            num_bytes: None,
+        }
+    }
+
+    /// Returns the id of the segment.
+    pub fn segment_id(&self) -> SegmentId {
+        self.segment_id
+    }
+
+    /// Returns the state of the segment.
+    pub fn state(&self) -> SegmentState {
+        self.state
+    }
+
+    /// Returns the number of (non-deleted) documents of the segment.
+    ///
+    /// It is 0 for a segment being written.
+    pub fn num_docs(&self) -> u32 {
+        self.num_docs
+    }
+
+    /// Returns the number of deleted documents of the segment.
+    pub fn num_deleted_docs(&self) -> u32 {
+        self.num_deleted_docs
+    }
+
+    /// Returns the total size of the files of the segment, in bytes.
+    ///
+    /// It is `None` for a segment being written, as its
+    /// files are not complete yet.
+    pub fn num_bytes(&self) -> Option<u64> {
+        self.num_bytes
+    }
+}
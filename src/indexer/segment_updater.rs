@@ -6,32 +6,47 @@ use core::META_FILEPATH;
 use core::Segment;
 use core::SegmentId;
 use core::SegmentMeta;
-use core::SerializableSegment;
+use core::SegmentReader;
+use core::SegmentComponent;
+use core::rebuild_term_dict;
 use directory::Directory;
+use common::HasLen;
 use Error;
 use std::result;
 use futures_cpupool::CpuPool;
 use futures::Future;
 use futures::Canceled;
 use futures::oneshot;
+use futures::sync::oneshot::Sender;
 use indexer::{MergePolicy, DefaultMergePolicy};
-use indexer::delete_queue::DeleteQueue;
+use indexer::SegmentState;
+use indexer::delete_queue::{DeleteQueue, DeleteQueueSnapshot};
 use indexer::index_writer::advance_deletes;
 use indexer::MergeCandidate;
-use indexer::merger::IndexMerger;
+use indexer::MergeProgress;
+use indexer::merger::{IndexMerger, MergedDocIds};
+use indexer::doc_opstamp_mapping::DocToOpstampMapping;
+use fastfield::delete::{DeleteSet, write_delete_set};
 use indexer::SegmentEntry;
+use indexer::FragmentationReport;
+use indexer::SegmentStats;
 use indexer::SegmentSerializer;
 use Result;
 use futures_cpupool::CpuFuture;
 use rustc_serialize::json;
 use schema::Schema;
+use store::{Compressor, DEFAULT_BLOCK_SIZE};
 use std::borrow::BorrowMut;
+use std::cmp::max;
 use std::collections::HashMap;
+use std::collections::VecDeque;
 use std::io::Write;
+use std::path::PathBuf;
 use std::mem;
 use std::ops::DerefMut;
 use std::sync::Arc;
-use std::sync::atomic::AtomicUsize;
+use std::sync::{Condvar, Mutex, MutexGuard};
+use std::sync::atomic::{AtomicBool, AtomicUsize};
 use std::sync::atomic::Ordering;
 use std::sync::RwLock;
 use std::thread;
@@ -52,7 +67,7 @@ pub fn save_new_metas(schema: Schema,
                   opstamp: u64,
                   directory: &mut Directory)
                   -> Result<()> {
-    save_metas(vec!(), schema, opstamp, directory)
+    save_metas(vec!(), schema, opstamp, false, directory)
 }
 
 
@@ -69,12 +84,14 @@ pub fn save_new_metas(schema: Schema,
 pub fn save_metas(segment_metas: Vec<SegmentMeta>,
                   schema: Schema,
                   opstamp: u64,
+                  frozen: bool,
                   directory: &mut Directory)
                   -> Result<()> {
     let metas = IndexMeta {
         segments: segment_metas,
         schema: schema,
         opstamp: opstamp,
+        frozen: Some(frozen),
     };
     let mut w = vec!();
     try!(write!(&mut w, "{}\n", json::as_pretty_json(&metas)));
@@ -85,6 +102,59 @@ pub fn save_metas(segment_metas: Vec<SegmentMeta>,
 }
 
 
+// A merge waiting for a merge thread to be available.
+struct PendingMerge {
+    segment_ids: Vec<SegmentId>,
+    delete_operations: DeleteQueueSnapshot,
+    merging_future_send: Sender<Vec<SegmentEntry>>,
+}
+
+// The merges are run on at most `max_merge_threads` threads
+// at a time. The other ones are queued, and started in order
+// as the running merges end.
+#[derive(Default)]
+struct MergeQueue {
+    max_merge_threads: Option<usize>,
+    num_running_merges: usize,
+    pending_merges: VecDeque<PendingMerge>,
+}
+
+// Ends a merging thread as it is dropped, when the merge
+// returns as well as when it panics, so that its merge thread
+// is always made available to the pending merges.
+struct MergingThreadGuard {
+    segment_updater: SegmentUpdater,
+    merging_thread_id: usize,
+}
+
+impl Drop for MergingThreadGuard {
+    fn drop(&mut self) {
+        self.segment_updater.end_merging_thread(self.merging_thread_id);
+    }
+}
+
+impl MergeQueue {
+    fn has_free_merge_thread(&self) -> bool {
+        self.max_merge_threads
+            .map(|max_merge_threads| self.num_running_merges < max_merge_threads)
+            .unwrap_or(true)
+    }
+
+    // Pops the next pending merge if a merge thread is available,
+    // counting it as running.
+    fn pop_startable_merge(&mut self) -> Option<PendingMerge> {
+        if !self.has_free_merge_thread() {
+            return None;
+        }
+        let pending_merge_opt = self.pending_merges.pop_front();
+        if pending_merge_opt.is_some() {
+            self.num_running_merges += 1;
+        }
+        pending_merge_opt
+    }
+}
+
+
 // The segment update runner is in charge of processing all
 //  of the `SegmentUpdate`s.
 //
@@ -99,25 +169,42 @@ struct InnerSegmentUpdater {
     index: Index,
     segment_manager: SegmentManager,
     merge_policy: RwLock<Box<MergePolicy>>,
+    store_compressor: RwLock<Compressor>,
+    store_block_size: RwLock<usize>,
+    merge_progress: RwLock<Option<MergeProgress>>,
     merging_thread_id: AtomicUsize,
-    merging_threads: RwLock<HashMap<usize, JoinHandle<Result<SegmentEntry>>>>,
+    merging_threads: RwLock<HashMap<usize, JoinHandle<Result<Vec<SegmentEntry>>>>>,
+    merge_queue: Mutex<MergeQueue>,
+    merge_queue_drained: Condvar,
+    merge_backpressure: AtomicBool,
     generation: AtomicUsize,
     delete_queue: DeleteQueue,
 }
 
 impl SegmentUpdater {
 
-    pub fn new(index: Index, delete_queue: DeleteQueue) -> Result<SegmentUpdater> {
+    pub fn new(mut index: Index, delete_queue: DeleteQueue) -> Result<SegmentUpdater> {
         let segments = index.segments()?;
-        let segment_manager = SegmentManager::from_segments(segments);
+        let segment_manager = SegmentManager::from_segments(segments,
+                                                            index.segment_access_counters().clone());
+        // Removes the files left behind by a previous `IndexWriter`
+        // that did not terminate properly, like a partially
+        // written merged segment.
+        index.directory_mut().garbage_collect(segment_manager.list_files());
         Ok(
             SegmentUpdater(Arc::new(InnerSegmentUpdater {
                 pool: CpuPool::new(1),
                 index: index,
                 segment_manager: segment_manager,
                 merge_policy: RwLock::new(box DefaultMergePolicy::default()),
+                store_compressor: RwLock::new(Compressor::default()),
+                store_block_size: RwLock::new(DEFAULT_BLOCK_SIZE),
+                merge_progress: RwLock::new(None),
                 merging_thread_id: AtomicUsize::default(),
                 merging_threads: RwLock::new(HashMap::new()),
+                merge_queue: Mutex::new(MergeQueue::default()),
+                merge_queue_drained: Condvar::new(),
+                merge_backpressure: AtomicBool::new(false),
                 generation: AtomicUsize::default(),
                 delete_queue: delete_queue,
             }))
@@ -125,12 +212,36 @@ impl SegmentUpdater {
     }
 
     pub fn new_segment(&self) -> Segment {
-        let new_segment = self.0.index.new_segment();
+        let mut new_segment = self.0.index.new_segment();
+        new_segment.set_store_compressor(self.get_store_compressor());
+        new_segment.set_store_block_size(self.get_store_block_size());
         let segment_id = new_segment.id();
         self.0.segment_manager.write_segment(segment_id);
         new_segment
     }
 
+    /// Forgets about a segment returned by `.new_segment()`
+    /// that will not be added.
+    pub fn abort_write(&self, segment_id: &SegmentId) {
+        self.0.segment_manager.abort_write(segment_id);
+    }
+
+    pub fn get_store_compressor(&self) -> Compressor {
+        *self.0.store_compressor.read().unwrap()
+    }
+
+    pub fn set_store_compressor(&self, store_compressor: Compressor) {
+        *self.0.store_compressor.write().unwrap() = store_compressor;
+    }
+
+    pub fn get_store_block_size(&self) -> usize {
+        *self.0.store_block_size.read().unwrap()
+    }
+
+    pub fn set_store_block_size(&self, store_block_size: usize) {
+        *self.0.store_block_size.write().unwrap() = store_block_size;
+    }
+
     pub fn get_merge_policy(&self) -> Box<MergePolicy> {
         self.0.merge_policy.read().unwrap().box_clone()
     }
@@ -139,6 +250,105 @@ impl SegmentUpdater {
         *self.0.merge_policy.write().unwrap()= merge_policy;
     }
 
+    pub fn get_merge_progress(&self) -> Option<MergeProgress> {
+        self.0.merge_progress.read().unwrap().clone()
+    }
+
+    pub fn set_merge_progress(&self, merge_progress: Option<MergeProgress>) {
+        *self.0.merge_progress.write().unwrap() = merge_progress;
+    }
+
+    pub fn get_merge_threads(&self) -> Option<usize> {
+        self.lock_merge_queue().max_merge_threads
+    }
+
+    /// Bounds the number of merges running at the same time
+    /// to `max_merge_threads`, or removes the bound if `None`.
+    ///
+    /// The pending merges for which there is now
+    /// a free merge thread are started right away.
+    pub fn set_merge_threads(&self, max_merge_threads: Option<usize>) {
+        let startable_merges: Vec<PendingMerge> = {
+            let mut merge_queue = self.lock_merge_queue();
+            merge_queue.max_merge_threads = max_merge_threads;
+            let mut startable_merges = Vec::new();
+            while let Some(pending_merge) = merge_queue.pop_startable_merge() {
+                startable_merges.push(pending_merge);
+            }
+            startable_merges
+        };
+        if !startable_merges.is_empty() {
+            self.0.merge_queue_drained.notify_all();
+        }
+        for pending_merge in startable_merges {
+            self.spawn_merge(pending_merge);
+        }
+    }
+
+    pub fn get_merge_backpressure(&self) -> bool {
+        self.0.merge_backpressure.load(Ordering::Acquire)
+    }
+
+    pub fn set_merge_backpressure(&self, merge_backpressure: bool) {
+        self.0.merge_backpressure.store(merge_backpressure, Ordering::Release);
+    }
+
+    pub fn num_pending_merges(&self) -> usize {
+        self.lock_merge_queue().pending_merges.len()
+    }
+
+    fn lock_merge_queue(&self) -> MutexGuard<MergeQueue> {
+        self.0.merge_queue.lock().expect("Merge queue lock poisoned")
+    }
+
+    // Blocks until all of the pending merges have been started.
+    //
+    // This must not be called from the segment updater thread,
+    // as the merges need it to end.
+    fn wait_merge_queue_drained(&self) {
+        let mut merge_queue = self.lock_merge_queue();
+        while !merge_queue.pending_merges.is_empty() {
+            merge_queue = self.0.merge_queue_drained
+                .wait(merge_queue)
+                .expect("Merge queue lock poisoned");
+        }
+    }
+
+    pub fn fragmentation_report(&self) -> FragmentationReport {
+        self.0.segment_manager.fragmentation_report()
+    }
+
+    pub fn segment_access_counts(&self) -> Vec<(SegmentId, usize)> {
+        self.0.segment_manager.segment_access_counts()
+    }
+
+    pub fn segment_states(&self) -> Vec<(SegmentId, SegmentState)> {
+        self.0.segment_manager.segment_states()
+    }
+
+    pub fn segment_stats(&self) -> Vec<SegmentStats> {
+        self.0.segment_manager.segment_stats(self.0.index.directory())
+    }
+
+    pub fn segment_created_at(&self, segment_id: &SegmentId) -> Option<u64> {
+        self.0.segment_manager.segment_created_at(segment_id)
+    }
+
+    pub fn segment_delete_opstamp(&self, segment_id: &SegmentId) -> Option<u64> {
+        self.0.segment_manager.segment_delete_opstamp(segment_id)
+    }
+
+    pub fn committed_segments_by_size(&self) -> Vec<SegmentMeta> {
+        self.0.segment_manager.committed_by_size()
+    }
+
+    /// Returns the committed segments that are
+    /// not currently being merged.
+    pub fn mergeable_committed_segments(&self) -> Vec<SegmentMeta> {
+        let (committed_segments, _) = get_segments(&self.0.segment_manager);
+        committed_segments
+    }
+
     fn get_merging_thread_id(&self) -> usize {
         self.0.merging_thread_id.fetch_add(1, Ordering::SeqCst)
     }
@@ -150,10 +360,26 @@ impl SegmentUpdater {
         })
     }
 
+    // Removes the files of the segments that are neither uncommitted,
+    // committed nor being written, and forgets their access counters.
+    fn garbage_collect(&self, index: &mut Index) {
+        let living_files = self.0.segment_manager.list_files();
+        index.directory_mut().garbage_collect(living_files);
+        self.0.segment_manager.prune_access_counters();
+    }
+
     pub fn rollback(&mut self, generation: usize) -> result::Result<(), Error> {
         self.0.generation.store(generation, Ordering::Release);
         self.run_async(|segment_updater| {
-            segment_updater.0.segment_manager.rollback();
+            let rolled_back_segment_metas = segment_updater.0.segment_manager.rollback();
+            // the files of the uncommitted segments can be removed
+            // right away, rather than at the next garbage collection.
+            let rolled_back_files: Vec<PathBuf> = rolled_back_segment_metas
+                .iter()
+                .flat_map(|segment_meta| segment_meta.list_files())
+                .collect();
+            let mut index = segment_updater.0.index.clone();
+            index.directory_mut().delete_managed_files(&rolled_back_files);
         }).wait()
     }
 
@@ -171,6 +397,21 @@ impl SegmentUpdater {
         }
     }
 
+    /// Adds a segment like `add_segment`, but only returns once
+    /// the segment is part of the uncommitted segments.
+    pub fn add_segment_and_wait(&self, generation: usize, segment_entry: SegmentEntry) -> Result<bool> {
+        if generation >= self.0.generation.load(Ordering::Acquire) {
+            self.run_async(|segment_updater| {
+                segment_updater.0.segment_manager.add_segment(segment_entry);
+                segment_updater.consider_merge_options();
+            }).wait()?;
+            Ok(true)
+        }
+        else {
+            Ok(false)
+        }
+    }
+
     fn purge_deletes(&self) -> Result<Vec<SegmentMeta>> {
         self.0.segment_manager
             .segment_entries()
@@ -182,115 +423,442 @@ impl SegmentUpdater {
             .collect()
     }
 
-    pub fn commit(&self, opstamp: u64) -> Result<()> {
-        self.run_async(move |segment_updater| {
-            let segment_metas = segment_updater.purge_deletes().expect("Failed purge deletes");
-            segment_updater.0.segment_manager.commit(segment_metas);
+    /// Commits the segments, and returns the metas
+    /// of the committed segments, as saved in the meta file.
+    ///
+    /// With merge backpressure, the commit first waits for
+    /// the pending merges to be started.
+    pub fn commit(&self, opstamp: u64, label: Option<String>) -> Result<Vec<SegmentMeta>> {
+        if self.get_merge_backpressure() {
+            self.wait_merge_queue_drained();
+        }
+        self.run_async(move |segment_updater| -> Result<Vec<SegmentMeta>> {
+            // on error, the segments are left uncommitted.
+            let segment_metas = segment_updater.purge_deletes()?;
+            segment_updater.0.segment_manager.commit(segment_metas, label.as_ref().map(|label| label.as_str()));
+            let committed_segment_metas = segment_updater.0.segment_manager.committed_segment_metas();
             let mut index = segment_updater.0.index.clone();
             {
                 let directory = index.directory();
                 save_metas(
-                    segment_updater.0.segment_manager.committed_segment_metas(),
+                    committed_segment_metas.clone(),
                     index.schema(),
                     opstamp,
+                    index.is_frozen(),
                     directory.box_clone().borrow_mut()).expect("Could not save metas.");
             }
-            let living_files = segment_updater.0.segment_manager.list_files();
-            index.directory_mut().garbage_collect(living_files);
+            segment_updater.garbage_collect(&mut index);
             segment_updater.consider_merge_options();
+            Ok(committed_segment_metas)
+        }).wait()?
+    }
+
+    /// Opens readers on the uncommitted and committed segments.
+    ///
+    /// The segment set is snapshotted, and the readers are opened,
+    /// under the read lock of the `SegmentManager`, so that
+    /// the files of the segments cannot be garbage collected
+    /// by a concurrent merge or rollback before they are
+    /// protected by the readers.
+    ///
+    /// The readers of `previous_segment_readers` that are still
+    /// up to date are reused, the other ones are dropped.
+    pub fn open_nrt_segment_readers(&self, previous_segment_readers: Vec<SegmentReader>) -> Result<Vec<SegmentReader>> {
+        let mut previous_segment_readers: HashMap<SegmentId, SegmentReader> = previous_segment_readers
+            .into_iter()
+            .map(|segment_reader| (segment_reader.segment_id(), segment_reader))
+            .collect();
+        let index = &self.0.index;
+        self.0.segment_manager.read_segment_entries(|segment_entries| {
+            segment_entries
+                .into_iter()
+                .map(|segment_entry| {
+                    let segment_meta = segment_entry.meta();
+                    match previous_segment_readers.remove(&segment_meta.id()) {
+                        Some(segment_reader) if segment_reader.num_deleted_docs() == segment_meta.num_deleted_docs() => {
+                            Ok(segment_reader)
+                        }
+                        _ => SegmentReader::open(index.segment(segment_meta.clone()))
+                    }
+                })
+                .collect()
+        })
+    }
+
+    pub fn garbage_collect_files(&self) -> Result<()> {
+        self.run_async(|segment_updater| {
+            let mut index = segment_updater.0.index.clone();
+            segment_updater.garbage_collect(&mut index);
         }).wait()
     }
 
+    pub fn drop_segment(&self, segment_id: SegmentId) -> Result<()> {
+        self.run_async(move |segment_updater| {
+            if !segment_updater.0.segment_manager.remove_committed_segment(&segment_id) {
+                return Err(Error::InvalidArgument(format!("Segment {:?} is not a committed segment, or is being merged.", segment_id)));
+            }
+            let mut index = segment_updater.0.index.clone();
+            {
+                let directory = index.directory();
+                save_metas(
+                    segment_updater.0.segment_manager.committed_segment_metas(),
+                    index.schema(),
+                    index.opstamp(),
+                    index.is_frozen(),
+                    directory.box_clone().borrow_mut())?;
+            }
+            segment_updater.garbage_collect(&mut index);
+            Ok(())
+        }).wait()?
+    }
 
-    pub fn start_merge(&self, segment_ids: &[SegmentId]) -> impl Future<Item=SegmentEntry, Error=Canceled> {
+    /// Rebuilds the term dictionary of a committed segment into a new
+    /// segment, which takes its place, like the result of a merge.
+    ///
+    /// The rebuild runs on the thread of the segment updater, so that
+    /// the segment cannot be merged or dropped meanwhile.
+    ///
+    /// See `IndexWriter::rebuild_term_dict`.
+    pub fn rebuild_term_dict(&self, segment_id: SegmentId) -> Result<()> {
+        self.run_async(move |segment_updater| {
+            let is_committed = segment_updater.0.segment_manager
+                .committed_segment_metas()
+                .iter()
+                .any(|segment_meta| segment_meta.id() == segment_id);
+            let segment_entry = match segment_updater.0.segment_manager.segment_entry(&segment_id) {
+                Some(ref segment_entry) if is_committed && segment_entry.is_ready() => segment_entry.clone(),
+                _ => {
+                    return Err(Error::InvalidArgument(format!("Segment {:?} is not a committed segment, or is being merged.", segment_id)));
+                }
+            };
+            let mut index = segment_updater.0.index.clone();
+            let segment = index.segment(segment_entry.meta().clone());
+            let rebuilt_segment_id = segment_updater.new_segment().id();
+            let rebuilt_segment_meta = match rebuild_term_dict(&index, &segment, rebuilt_segment_id) {
+                Ok(rebuilt_segment_meta) => rebuilt_segment_meta,
+                Err(e) => {
+                    segment_updater.abort_write(&rebuilt_segment_id);
+                    return Err(e);
+                }
+            };
+            let mut rebuilt_segment_entry = SegmentEntry::new(rebuilt_segment_meta);
+            rebuilt_segment_entry.set_doc_to_opstamp(segment_entry.doc_to_opstamp().clone());
+            segment_updater.0.segment_manager.replace_segments(&[segment_id], rebuilt_segment_entry)?;
+            {
+                let directory = index.directory();
+                save_metas(
+                    segment_updater.0.segment_manager.committed_segment_metas(),
+                    index.schema(),
+                    index.opstamp(),
+                    index.is_frozen(),
+                    directory.box_clone().borrow_mut())?;
+            }
+            segment_updater.garbage_collect(&mut index);
+            Ok(())
+        }).wait()?
+    }
+
+    /// Marks the segments as being merged, and starts their merge
+    /// on a new thread.
+    ///
+    /// If the maximum number of merge threads is reached, the merge
+    /// is queued until one of the running merges ends.
+    pub fn start_merge(&self, segment_ids: &[SegmentId]) -> impl Future<Item=Vec<SegmentEntry>, Error=Canceled> {
         
         self.0.segment_manager.start_merge(segment_ids);
-        let segment_updater_clone = self.clone();
-        
-        let segment_ids_vec = segment_ids.to_vec(); 
         
-        let merging_thread_id = self.get_merging_thread_id();
         let (merging_future_send, merging_future_recv) = oneshot();
         
-        let delete_operations = self.0.delete_queue.snapshot();
-
         if segment_ids.is_empty() {
             return merging_future_recv;
         }
-        
-        let merging_join_handle = thread::spawn(move || {
-            
-            // first we need to apply deletes to our segment.
-            info!("Start merge: {:?}", segment_ids_vec);
-            
-            let ref index = segment_updater_clone.0.index;
-            let schema = index.schema();
-
-            let mut segment_metas = vec!();
-            for segment_id in &segment_ids_vec {
-                if let Some(segment_entry) = segment_updater_clone.0
-                    .segment_manager
-                    .segment_entry(segment_id) {
-                    let mut segment = index.segment(segment_entry.meta().clone());
-                    let segment_meta = advance_deletes(
-                         &mut segment,
-                         &delete_operations,
-                         segment_entry.doc_to_opstamp())?;
-                    segment_metas.push(segment_meta);
-                }
-                else {
-                    error!("Error, had to abort merge as some of the segment is not managed anymore.a");
-                    return Err(Error::InvalidArgument(format!("Segment {:?} requested for merge is not managed.", segment_id)));
-                }
+
+        let pending_merge = PendingMerge {
+            segment_ids: segment_ids.to_vec(),
+            delete_operations: self.0.delete_queue.snapshot(),
+            merging_future_send: merging_future_send,
+        };
+        {
+            let mut merge_queue = self.lock_merge_queue();
+            if !merge_queue.has_free_merge_thread() {
+                debug!("Queue merge: {:?}", pending_merge.segment_ids);
+                merge_queue.pending_merges.push_back(pending_merge);
+                return merging_future_recv;
             }
-            
-            let segments: Vec<Segment> = segment_metas
-                .iter()
-                .cloned()
-                .map(|segment_meta| index.segment(segment_meta))
-                .collect();
-            
-            // An IndexMerger is like a "view" of our merged segments.
-            let merger: IndexMerger = IndexMerger::open(schema, &segments[..])?;
-            let mut merged_segment = index.new_segment(); 
-            
-            // ... we just serialize this index merger in our new segment
-            // to merge the two segments.
+            merge_queue.num_running_merges += 1;
+        }
+        self.spawn_merge(pending_merge);
+        merging_future_recv
+    }
+
+    // Called by a merging thread as it ends, through its `MergingThreadGuard`.
+    //
+    // The next pending merge is started before the thread
+    // is removed from the merging threads, so that
+    // `wait_merging_thread` also waits for it.
+    // A thread that panicked is not removed, so that
+    // `wait_merging_thread` reports its failure.
+    fn end_merging_thread(&self, merging_thread_id: usize) {
+        let next_merge_opt = {
+            let mut merge_queue = self.lock_merge_queue();
+            merge_queue.num_running_merges -= 1;
+            merge_queue.pop_startable_merge()
+        };
+        if let Some(next_merge) = next_merge_opt {
+            self.0.merge_queue_drained.notify_all();
+            self.spawn_merge(next_merge);
+        }
+        if !thread::panicking() {
+            self.0.merging_threads.write().unwrap().remove(&merging_thread_id);
+        }
+    }
 
-            let segment_serializer = SegmentSerializer::for_segment(&mut merged_segment).expect("Creating index serializer failed");
+    fn spawn_merge(&self, pending_merge: PendingMerge) {
+        let segment_updater_clone = self.clone();
+        let merging_thread_id = self.get_merging_thread_id();
+        let PendingMerge { segment_ids: segment_ids_vec, delete_operations, merging_future_send } = pending_merge;
 
-            let num_docs = merger.write(segment_serializer).expect("Serializing merged index failed");
-            let mut segment_meta = SegmentMeta::new(merged_segment.id());
-            segment_meta.set_max_doc(num_docs);
-            
-            let segment_entry = SegmentEntry::new(segment_meta);
-            segment_updater_clone
-                .end_merge(segment_metas.clone(), segment_entry.clone())
-                .unwrap();
+        let merging_join_handle = thread::spawn(move || {
+            let _merging_thread_guard = MergingThreadGuard {
+                segment_updater: segment_updater_clone.clone(),
+                merging_thread_id: merging_thread_id,
+            };
+
+            info!("Start merge: {:?}", segment_ids_vec);
+
+            // The merged segments are registered as being written, so that
+            // their files are not garbage collected while the merge is running.
+            // They only become part of the index once they are all fully written,
+            // in `end_merge`. If we crash before that, their files are
+            // garbage collected when the next `IndexWriter` is opened.
+            let mut merged_segment_ids = Vec::new();
+            let merge_res = segment_updater_clone
+                .merge_segments(&segment_ids_vec, &delete_operations, &mut merged_segment_ids)
+                .and_then(|(segment_metas, segment_entries, merged_doc_ids)| {
+                    segment_updater_clone.end_merge(segment_metas, segment_entries, merged_doc_ids)
+                });
+            let segment_entries = match merge_res {
+                Ok(segment_entries) => segment_entries,
+                Err(e) => {
+                    // the merged segments are left out, and
+                    // the segments to merge can be merged again.
+                    for merged_segment_id in &merged_segment_ids {
+                        segment_updater_clone.0.segment_manager.abort_write(merged_segment_id);
+                    }
+                    segment_updater_clone.0.segment_manager.cancel_merge(&segment_ids_vec);
+                    return Err(e);
+                }
+            };
             
             // Send will fail if nobody is waiting for the result and
             // the receiver side got destroyed.
             //
             // This is not a problem.
             let _send_result = merging_future_send
-                .send(segment_entry.clone());
-            
-            segment_updater_clone.0.merging_threads.write().unwrap().remove(&merging_thread_id);
-            Ok(segment_entry)
+                .send(segment_entries.clone());
+
+            Ok(segment_entries)
         });
         self.0.merging_threads.write().unwrap().insert(merging_thread_id, merging_join_handle);
-        merging_future_recv
     }
 
 
+    /// Applies the pending deletes to the segments to merge,
+    /// and writes the result of their merge in new segments.
+    ///
+    /// There is a single new segment, unless the merge policy caps
+    /// the number of documents per segment. The ids of the new segments
+    /// are pushed to `merged_segment_ids` as they are created.
+    ///
+    /// All of the files of the merged segments are flushed
+    /// by the time this method returns. Their delete file is
+    /// only written in `end_merge`, as it needs to account for
+    /// the deletes committed in the meantime.
+    ///
+    /// The merged segments keep the opstamps of their documents,
+    /// so that the pending deletes do not affect the documents
+    /// added after them.
+    fn merge_segments(&self,
+                      segment_ids: &[SegmentId],
+                      delete_operations: &DeleteQueueSnapshot,
+                      merged_segment_ids: &mut Vec<SegmentId>) -> Result<(Vec<SegmentMeta>, Vec<SegmentEntry>, MergedDocIds)> {
+        let index = &self.0.index;
+        let schema = index.schema();
+
+        // first we need to apply deletes to our segment.
+        let mut segment_metas = vec!();
+        let mut doc_to_opstamps = vec!();
+        for segment_id in segment_ids {
+            if let Some(segment_entry) = self.0
+                .segment_manager
+                .segment_entry(segment_id) {
+                let mut segment = index.segment(segment_entry.meta().clone());
+                let segment_meta = advance_deletes(
+                     &mut segment,
+                     delete_operations,
+                     segment_entry.doc_to_opstamp())?;
+                segment_metas.push(segment_meta);
+                doc_to_opstamps.push(segment_entry.doc_to_opstamp().clone());
+            }
+            else {
+                error!("Error, had to abort merge as some of the segment is not managed anymore.a");
+                return Err(Error::InvalidArgument(format!("Segment {:?} requested for merge is not managed.", segment_id)));
+            }
+        }
+        
+        let segments: Vec<Segment> = segment_metas
+            .iter()
+            .cloned()
+            .map(|segment_meta| index.segment(segment_meta))
+            .collect();
+        
+        // An IndexMerger is like a "view" of our merged segments.
+        let merge_policy = self.get_merge_policy();
+        let mut merger: IndexMerger = IndexMerger::open_with_dedup(schema, &segments[..], merge_policy.dedup_field())?;
+        if let Some(max_docs_per_segment) = merge_policy.max_docs_per_segment() {
+            merger.set_max_docs_per_segment(max_docs_per_segment);
+        }
+        if let Some(merge_progress) = self.get_merge_progress() {
+            merger.set_merge_progress(segment_ids.to_vec(), merge_progress);
+        }
+        
+        let opstamp = segment_metas.iter().filter_map(|segment_meta| segment_meta.opstamp()).max();
+        // the merged segments keep the label their segments have in common.
+        let label = match segment_metas.first().and_then(|segment_meta| segment_meta.label()) {
+            Some(label) if segment_metas.iter().all(|segment_meta| segment_meta.label() == Some(label)) => Some(label),
+            _ => None,
+        };
+
+        // ... we just serialize this index merger in our new segments
+        // to merge the segments.
+        let mut merged_segments = Vec::with_capacity(merger.num_merged_segments());
+        let mut segment_serializers = Vec::with_capacity(merger.num_merged_segments());
+        for _ in 0..merger.num_merged_segments() {
+            let mut merged_segment = self.new_segment();
+            merged_segment_ids.push(merged_segment.id());
+            segment_serializers.push(SegmentSerializer::for_segment(&mut merged_segment)?);
+            merged_segments.push(merged_segment);
+        }
+        let merged_num_docs = merger.write_merged_segments(segment_serializers)?;
+        let mut segment_entries = Vec::with_capacity(merged_segments.len());
+        for (merged_segment, num_docs) in merged_segments.into_iter().zip(merged_num_docs) {
+            let mut segment_meta = SegmentMeta::new(merged_segment.id());
+            segment_meta.set_max_doc(num_docs);
+            segment_meta.set_store_compressor(merged_segment.meta().store_compressor());
+            segment_meta.set_store_block_size(merged_segment.meta().store_block_size());
+            segment_meta.set_has_fieldnorms(merged_segment.schema().has_fieldnorms());
+            segment_meta.set_has_term_vectors(merged_segment.schema().has_term_vectors());
+            if let Some(opstamp) = opstamp {
+                segment_meta.set_opstamp(opstamp);
+            }
+            if let Some(label) = label {
+                segment_meta.set_label(label);
+            }
+            segment_entries.push(SegmentEntry::new(segment_meta));
+        }
+
+        // the documents of segments without opstamps were committed,
+        // and are older than any pending delete.
+        let merged_doc_ids = merger.merged_doc_ids();
+        let has_doc_opstamps = doc_to_opstamps
+            .iter()
+            .any(|doc_to_opstamp| match *doc_to_opstamp {
+                DocToOpstampMapping::WithMap(_) => true,
+                DocToOpstampMapping::None => false,
+            });
+        if has_doc_opstamps {
+            let mut merged_doc_opstamps: Vec<Vec<u64>> = segment_entries
+                .iter()
+                .map(|segment_entry| vec![0u64; segment_entry.meta().max_doc() as usize])
+                .collect();
+            for (segment_meta, doc_to_opstamp) in segment_metas.iter().zip(doc_to_opstamps.iter()) {
+                for doc_id in 0..segment_meta.max_doc() {
+                    if let Some((ord, merged_doc_id)) = merged_doc_ids.get(&segment_meta.id(), doc_id) {
+                        merged_doc_opstamps[ord][merged_doc_id as usize] = doc_to_opstamp.opstamp(doc_id).unwrap_or(0u64);
+                    }
+                }
+            }
+            for (segment_entry, doc_opstamps) in segment_entries.iter_mut().zip(merged_doc_opstamps) {
+                segment_entry.set_doc_to_opstamp(DocToOpstampMapping::from(doc_opstamps));
+            }
+        }
+        Ok((segment_metas, segment_entries, merged_doc_ids))
+    }
+
+    /// Writes the delete files of the merged segments.
+    ///
+    /// The deletes applied to the segments being merged,
+    /// be it before the merge or by a commit during the merge,
+    /// are carried over to the merged segments,
+    /// along with the opstamp of the last of them.
+    ///
+    /// The merged segments do not get a delete file if none of their
+    /// documents is deleted and the deletes were all committed, since
+    /// the pending deletes are the only ones that may be applied again.
+    fn write_merged_deletes(&self,
+                            merged_segment_metas: &[SegmentMeta],
+                            segment_entries: &mut [SegmentEntry],
+                            merged_doc_ids: &MergedDocIds) -> Result<()> {
+        let index = &self.0.index;
+        let mut delete_sets: Vec<DeleteSet> = segment_entries
+            .iter()
+            .map(|_| DeleteSet::new())
+            .collect();
+        let mut delete_opstamp_opt: Option<u64> = None;
+        for merged_segment_meta in merged_segment_metas {
+            let segment_meta = self.0.segment_manager
+                .segment_entry(&merged_segment_meta.id())
+                .map(|segment_entry| segment_entry.meta().clone())
+                .unwrap_or_else(|| merged_segment_meta.clone());
+            delete_opstamp_opt = max(delete_opstamp_opt, segment_meta.delete_opstamp());
+            // the deletes known when the merge started are already
+            // left out of the merged segments.
+            if segment_meta.delete_opstamp() == merged_segment_meta.delete_opstamp() {
+                continue;
+            }
+            let segment_reader = SegmentReader::open(index.segment(segment_meta.clone()))?;
+            for doc_id in 0..segment_reader.max_doc() {
+                if segment_reader.is_deleted(doc_id) {
+                    if let Some((ord, merged_doc_id)) = merged_doc_ids.get(&segment_meta.id(), doc_id) {
+                        delete_sets[ord].insert(merged_doc_id);
+                    }
+                }
+            }
+        }
+        if let Some(delete_opstamp) = delete_opstamp_opt {
+            if delete_opstamp <= index.opstamp() && delete_sets.iter().all(|delete_set| delete_set.len() == 0) {
+                return Ok(());
+            }
+            for (segment_entry, delete_set) in segment_entries.iter_mut().zip(delete_sets) {
+                let mut segment = index.segment(segment_entry.meta().clone());
+                segment.set_delete_meta(delete_set.len() as u32, delete_opstamp);
+                let mut delete_file = segment.open_write(SegmentComponent::DELETE)?;
+                write_delete_set(&delete_set, &mut delete_file)?;
+                segment_entry.set_meta(segment.meta().clone());
+            }
+        }
+        Ok(())
+    }
+
     fn consider_merge_options(&self) {
         let (committed_segments, uncommitted_segments) = get_segments(&self.0.segment_manager);
         // Committed segments cannot be merged with uncommitted_segments.
         // We therefore consider merges using these two sets of segments independently.
         let merge_policy = self.get_merge_policy();
         let mut merge_candidates = merge_policy.compute_merge_candidates(&uncommitted_segments);
-        let committed_merge_candidates = merge_policy.compute_merge_candidates(&committed_segments);
-        merge_candidates.extend_from_slice(&committed_merge_candidates[..]);
+        // Segments committed with different labels are not merged together either,
+        // so that each label keeps its own segments.
+        let mut committed_segments_by_label: HashMap<Option<String>, Vec<SegmentMeta>> = HashMap::new();
+        for segment_meta in committed_segments {
+            committed_segments_by_label
+                .entry(segment_meta.label().map(String::from))
+                .or_insert_with(Vec::new)
+                .push(segment_meta);
+        }
+        for segment_metas in committed_segments_by_label.values() {
+            let committed_merge_candidates = merge_policy.compute_merge_candidates(segment_metas);
+            merge_candidates.extend_from_slice(&committed_merge_candidates[..]);
+        }
         for MergeCandidate(segment_metas) in merge_candidates {
             self.start_merge(&segment_metas);
         }
@@ -299,37 +867,58 @@ impl SegmentUpdater {
     
     fn end_merge(&self, 
         merged_segment_metas: Vec<SegmentMeta>,
-        resulting_segment_entry: SegmentEntry) -> Result<()> {
+        mut resulting_segment_entries: Vec<SegmentEntry>,
+        merged_doc_ids: MergedDocIds) -> Result<Vec<SegmentEntry>> {
         
-        self.run_async(move |segment_updater| {
+        self.run_async(move |segment_updater| -> Result<Vec<SegmentEntry>> {
             debug!("End merge {:?}", merged_segment_metas);
-            segment_updater.0.segment_manager.end_merge(&merged_segment_metas, resulting_segment_entry);
+            // on error, the merged segments are left out of the index,
+            // and the segments to merge can be merged again.
+            if let Err(e) = segment_updater.write_merged_deletes(&merged_segment_metas, &mut resulting_segment_entries, &merged_doc_ids) {
+                let segment_ids: Vec<SegmentId> = merged_segment_metas
+                    .iter()
+                    .map(|segment_meta| segment_meta.id())
+                    .collect();
+                segment_updater.0.segment_manager.cancel_merge(&segment_ids);
+                return Err(e);
+            }
+            segment_updater.0.segment_manager.end_merge(&merged_segment_metas, resulting_segment_entries.clone());
             let mut directory = segment_updater.0.index.directory().box_clone();
             let segment_metas = segment_updater.0.segment_manager.committed_segment_metas();
             save_metas(
                 segment_metas,
                 segment_updater.0.index.schema(),
                 segment_updater.0.index.opstamp(),
+                segment_updater.0.index.is_frozen(),
                 directory.borrow_mut()).expect("Could not save metas.");
-        }).wait()
+            Ok(resulting_segment_entries)
+        }).wait()?
     }
 
+    /// Waits for the running merges, as well as for the pending
+    /// merges they start as they end.
     pub fn wait_merging_thread(&self) -> Result<()> {
-        let mut new_merging_threads = HashMap::new();
-        {
-            let mut merging_threads = self.0.merging_threads.write().unwrap();
-            mem::swap(&mut new_merging_threads, merging_threads.deref_mut());
-        }
-        for (_, merging_thread_handle) in new_merging_threads {
-            merging_thread_handle
-                .join()
-                .map(|_| ())
-                .map_err(|_| {
-                    Error::ErrorInThread("Merging thread failed.".to_string())
-                })?
+        loop {
+            // Our merging thread may have queued their completed,
+            // and the segment updater may still be starting merges.
+            self.run_async(move |_| {}).wait()?;
+            let mut new_merging_threads = HashMap::new();
+            {
+                let mut merging_threads = self.0.merging_threads.write().unwrap();
+                mem::swap(&mut new_merging_threads, merging_threads.deref_mut());
+            }
+            if new_merging_threads.is_empty() {
+                return Ok(());
+            }
+            for (_, merging_thread_handle) in new_merging_threads {
+                merging_thread_handle
+                    .join()
+                    .map(|_| ())
+                    .map_err(|_| {
+                        Error::ErrorInThread("Merging thread failed.".to_string())
+                    })?
+            }
         }
-        // Our merging thread may have queued their completed
-        self.run_async(move |_| {}).wait()
     }
 
 }
@@ -11,14 +11,27 @@ use fastfield::U32FastFieldsWriter;
 use schema::Field;
 use schema::FieldEntry;
 use schema::FieldValue;
+use schema::{Document, Value, serialize_text_value};
+use common::BinarySerializable;
 use schema::FieldType;
 use schema::TextIndexingOptions;
 use postings::SpecializedPostingsWriter;
 use postings::{NothingRecorder, TermFrequencyRecorder, TFAndPositionRecorder};
+use postings::term_table_capacity;
 use indexer::segment_serializer::SegmentSerializer;
-use datastruct::stacker::Heap;
+use analyzer::keyword_token;
+use analyzer::{Tokenizer, TokenizerManager};
+use Error;
+use std::sync::Arc;
+use datastruct::stacker::{Heap, TermHashFunction, FxBuildHasher};
+use std::collections::hash_map::RandomState;
+use std::collections::HashSet;
+use std::mem;
+use std::hash::BuildHasher;
 use indexer::index_writer::MARGIN_IN_BYTES;
 use super::operation::AddOperation;
+use schema::MaxTermLength;
+use rustc_serialize::json::Json;
 
 
 /// A `SegmentWriter` is in charge of creating segment index from a
@@ -34,6 +47,100 @@ pub struct SegmentWriter<'a> {
 	fast_field_writers: U32FastFieldsWriter,
 	fieldnorms_writer: U32FastFieldsWriter,
 	doc_opstamps: Vec<u64>,
+	// tokenizer of each of the tokenized fields.
+	tokenizers: Vec<Option<Arc<Tokenizer>>>,
+	// reused across documents by the tokenizers.
+	token_buffer: String,
+	// reused across documents to build the terms
+	// that are suscribed.
+	term_buffer: Term,
+	// reused across rows to gather the u32 values of a field.
+	u32_buffer: Vec<u32>,
+	max_tokens_per_field: u32,
+	// the ranges of the blocks of more than one document.
+	blocks: Vec<(DocId, DocId)>,
+	// the terms of the fields with term vectors
+	// of the document being indexed.
+	term_vector_terms: Vec<Term>,
+}
+
+
+#[derive(Clone, Copy)]
+enum RowValue {
+	// start and end of the text in the text buffer of the row.
+	Text(usize, usize),
+	U32(u32),
+}
+
+/// Values of a row, indexed by `SegmentWriter::add_row`
+/// without building a `Document`.
+///
+/// The texts of all of the values share a single buffer,
+/// which is reused from one row to another.
+#[doc(hidden)]
+#[derive(Default)]
+pub struct RowValues {
+	text_buffer: String,
+	values: Vec<(Field, RowValue)>,
+}
+
+impl RowValues {
+
+	/// Removes all of the values.
+	pub fn clear(&mut self) {
+		self.text_buffer.clear();
+		self.values.clear();
+	}
+
+	/// Adds a value to a text field.
+	pub fn add_text(&mut self, field: Field, text: &str) {
+		let start = self.text_buffer.len();
+		self.text_buffer.push_str(text);
+		self.values.push((field, RowValue::Text(start, self.text_buffer.len())));
+	}
+
+	/// Adds a value to a u32 field.
+	pub fn add_u32(&mut self, field: Field, value: u32) {
+		self.values.push((field, RowValue::U32(value)));
+	}
+
+	/// Returns a `Document` holding the values, in the order they were added.
+	pub fn to_document(&self) -> Document {
+		let mut document = Document::default();
+		for &(field, value) in &self.values {
+			match value {
+				RowValue::Text(start, end) => document.add_text(field, &self.text_buffer[start..end]),
+				RowValue::U32(val) => document.add_u32(field, val),
+			}
+		}
+		document
+	}
+
+	/// Checks that each of the values matches the type of its field.
+	///
+	/// # Errors
+	/// If a value is not a text of a text field or a u32
+	/// of a u32 field, returns `Error::InvalidArgument`.
+	pub fn check_types(&self, schema: &Schema) -> Result<()> {
+		for &(field, value) in &self.values {
+			let field_entry = schema.get_field_entry(field);
+			match (field_entry.field_type(), value) {
+				(&FieldType::Str(_), RowValue::Text(..)) | (&FieldType::U32(_), RowValue::U32(_)) => {}
+				_ => {
+					return Err(Error::InvalidArgument(format!("The field <{}> cannot hold the value {:?} of the row.",
+						field_entry.name(), self.value(value))));
+				}
+			}
+		}
+		Ok(())
+	}
+
+	fn value(&self, value: RowValue) -> Value {
+		match value {
+			RowValue::Text(start, end) => Value::from(&self.text_buffer[start..end]),
+			RowValue::U32(val) => Value::U32(val),
+		}
+	}
 }
 
 
@@ -41,35 +148,262 @@ fn create_fieldnorms_writer(schema: &Schema) -> U32FastFieldsWriter {
 	let u32_fields: Vec<Field> = schema.fields()
 		.iter()
 		.enumerate()
-		.filter(|&(_, field_entry)| field_entry.is_indexed()) 
+		.filter(|&(_, field_entry)| field_entry.has_norms())
 		.map(|(field_id, _)| Field(field_id as u8))
 		.collect();
 	U32FastFieldsWriter::new(u32_fields)
 }
 
 
-fn posting_from_field_entry<'a>(field_entry: &FieldEntry, heap: &'a Heap) -> Box<PostingsWriter + 'a> {
+fn posting_from_field_entry<'a, H: BuildHasher + 'a>(field_entry: &FieldEntry, heap: &'a Heap, hash_builder: H) -> Box<PostingsWriter + 'a> {
 	match *field_entry.field_type() {
 		FieldType::Str(ref text_options) => {
 			match text_options.get_indexing_options() {
 				TextIndexingOptions::TokenizedWithFreq => {
-					SpecializedPostingsWriter::<TermFrequencyRecorder>::new_boxed(heap)
+					SpecializedPostingsWriter::<TermFrequencyRecorder, H>::new_boxed_with_hasher(heap, hash_builder)
 				}
 				TextIndexingOptions::TokenizedWithFreqAndPosition => {
-					SpecializedPostingsWriter::<TFAndPositionRecorder>::new_boxed(heap)
+					SpecializedPostingsWriter::<TFAndPositionRecorder, H>::new_boxed_with_hasher(heap, hash_builder)
 				}
 				_ => {
-					SpecializedPostingsWriter::<NothingRecorder>::new_boxed(heap)
+					SpecializedPostingsWriter::<NothingRecorder, H>::new_boxed_with_hasher(heap, hash_builder)
 				}
 			}
 		} 
-		FieldType::U32(_) => {
-			SpecializedPostingsWriter::<NothingRecorder>::new_boxed(heap)
+		FieldType::U32(_) | FieldType::Date(_) => {
+			SpecializedPostingsWriter::<NothingRecorder, H>::new_boxed_with_hasher(heap, hash_builder)
+		}
+		FieldType::Json(_) => {
+			SpecializedPostingsWriter::<TermFrequencyRecorder, H>::new_boxed_with_hasher(heap, hash_builder)
 		}
 	}
 }
 
 
+// Appends the terms of the leaves of a JSON value to `terms`.
+//
+// The keys of the nested objects are joined with a `.` to form the path of
+// their leaves, and the elements of an array are indexed under the path of the array.
+// Returns the number of leaf tokens, excluding the terms for range queries.
+fn json_leaf_terms(field: Field,
+				   path: &mut String,
+				   json: &Json,
+				   tokenizer: &Tokenizer,
+				   max_term_length: MaxTermLength,
+				   fast: bool,
+				   terms: &mut Vec<Term>) -> u32 {
+	match *json {
+		Json::Object(ref object) => {
+			let mut num_tokens = 0u32;
+			let path_len = path.len();
+			for (key, child) in object {
+				if path_len > 0 {
+					path.push('.');
+				}
+				path.push_str(key);
+				num_tokens += json_leaf_terms(field, path, child, tokenizer, max_term_length, fast, terms);
+				path.truncate(path_len);
+			}
+			num_tokens
+		}
+		Json::Array(ref elements) => {
+			elements
+				.iter()
+				.map(|element| json_leaf_terms(field, path, element, tokenizer, max_term_length, fast, terms))
+				.sum()
+		}
+		Json::String(ref text) => {
+			let mut num_tokens = 0u32;
+			let mut token_stream = tokenizer.token_stream(text);
+			while token_stream.advance() {
+				if let Some(term_text) = max_term_length.apply(&token_stream.token().text) {
+					terms.push(Term::from_field_json_text(field, path, term_text));
+					num_tokens += 1;
+				}
+			}
+			num_tokens
+		}
+		Json::I64(val) => {
+			terms.push(Term::from_field_json_i64(field, path, val));
+			if fast {
+				terms.push(Term::from_field_json_number(field, path, val as f64));
+			}
+			1
+		}
+		Json::U64(val) => {
+			if val <= i64::max_value() as u64 {
+				terms.push(Term::from_field_json_i64(field, path, val as i64));
+			}
+			else {
+				terms.push(Term::from_field_json_f64(field, path, val as f64));
+			}
+			if fast {
+				terms.push(Term::from_field_json_number(field, path, val as f64));
+			}
+			1
+		}
+		Json::F64(val) => {
+			terms.push(Term::from_field_json_f64(field, path, val));
+			if fast {
+				terms.push(Term::from_field_json_number(field, path, val));
+			}
+			1
+		}
+		Json::Boolean(val) => {
+			terms.push(Term::from_field_json_bool(field, path, val));
+			1
+		}
+		Json::Null => 0,
+	}
+}
+
+
+/// Returns the tokenizer of each of the tokenized fields of the schema.
+///
+/// # Errors
+/// If the tokenizer of a field is not registered, returns `Error::SchemaError`.
+pub fn resolve_tokenizers(schema: &Schema, tokenizer_manager: &TokenizerManager) -> Result<Vec<Option<Arc<Tokenizer>>>> {
+	schema.fields()
+		.iter()
+		.map(|field_entry| {
+			match *field_entry.field_type() {
+				FieldType::Str(ref text_options) if text_options.get_indexing_options().is_tokenized() => {
+					tokenizer_manager
+						.get(field_entry.tokenizer())
+						.map(Some)
+						.ok_or_else(|| Error::SchemaError(format!("The tokenizer {:?} of field <{}> is not registered.",
+							field_entry.tokenizer(), field_entry.name())))
+				}
+				FieldType::Json(ref json_options) if json_options.is_indexed() => {
+					tokenizer_manager
+						.get(field_entry.tokenizer())
+						.map(Some)
+						.ok_or_else(|| Error::SchemaError(format!("The tokenizer {:?} of field <{}> is not registered.",
+							field_entry.tokenizer(), field_entry.name())))
+				}
+				_ => Ok(None),
+			}
+		})
+		.collect()
+}
+
+
+// Appends the terms indexed for the texts of a text field to `terms`.
+//
+// Like `PostingsWriter::index_text`, at most `max_num_tokens`
+// tokens of a tokenized field are indexed.
+fn text_terms<'a, I: Iterator<Item=&'a str>>(field: Field,
+			  texts: I,
+			  indexing_options: TextIndexingOptions,
+			  tokenizer: Option<&Tokenizer>,
+			  max_term_length: MaxTermLength,
+			  max_num_tokens: u32,
+			  terms: &mut Vec<Term>) {
+	let mut num_tokens = 0u32;
+	for text in texts {
+		if let Some(tokenizer) = tokenizer {
+			let mut token_stream = tokenizer.token_stream(text);
+			while num_tokens < max_num_tokens && token_stream.advance() {
+				if let Some(term_text) = max_term_length.apply(&token_stream.token().text) {
+					terms.push(Term::from_field_text(field, term_text));
+					num_tokens += 1;
+				}
+			}
+		}
+		else {
+			let token = keyword_token(text, indexing_options.is_lowercased_keyword());
+			if let Some(term_text) = max_term_length.apply(&token) {
+				terms.push(Term::from_field_text(field, term_text));
+			}
+		}
+	}
+}
+
+
+// Appends the terms indexed for the values of a field to `terms`.
+fn field_terms(field: Field,
+			   field_values: &[&FieldValue],
+			   schema: &Schema,
+			   tokenizer: Option<&Tokenizer>,
+			   terms: &mut Vec<Term>) {
+	let field_entry = schema.get_field_entry(field);
+	let max_term_length = field_entry.max_term_length();
+	match *field_entry.field_type() {
+		FieldType::Str(ref text_options) => {
+			let indexing_options = text_options.get_indexing_options();
+			if !indexing_options.is_indexed() {
+				return;
+			}
+			let texts = field_values.iter().map(|field_value| field_value.value().text());
+			text_terms(field, texts, indexing_options, tokenizer, max_term_length, u32::max_value(), terms);
+		}
+		FieldType::U32(ref u32_options) => {
+			if u32_options.is_indexed() {
+				for field_value in field_values {
+					terms.push(Term::from_field_u32(field, field_value.value().u32_value()));
+				}
+			}
+		}
+		FieldType::Date(ref date_options) => {
+			if date_options.is_indexed() {
+				for field_value in field_values {
+					terms.push(Term::from_field_date(field, field_value.value().date_value()));
+				}
+			}
+		}
+		FieldType::Json(ref json_options) => {
+			if let (true, Some(tokenizer)) = (json_options.is_indexed(), tokenizer) {
+				for field_value in field_values {
+					// invalid JSON is reported when the document gets indexed.
+					if let Ok(json) = Json::from_str(field_value.value().json_text()) {
+						let mut path = String::new();
+						json_leaf_terms(field, &mut path, &json, tokenizer, max_term_length, json_options.is_fast(), terms);
+					}
+				}
+			}
+		}
+	}
+}
+
+/// Returns true iff indexing the documents would exhaust an empty heap of
+/// `heap_capacity` bytes, or the term table of one of their fields.
+///
+/// The documents are tokenized to count their terms and their occurrences.
+/// A term takes at most its bytes and its recorder in the heap, and each
+/// of its occurrences at most 16 bytes for its doc id, its term frequency
+/// and its position.
+pub fn exceeds_heap(schema: &Schema,
+					tokenizers: &[Option<Arc<Tokenizer>>],
+					add_operations: &[AddOperation],
+					heap_capacity: u32) -> bool {
+	let max_num_terms = term_table_capacity(heap_capacity);
+	let mut per_field_terms: Vec<HashSet<Term>> = schema.fields()
+		.iter()
+		.map(|_| HashSet::new())
+		.collect();
+	let mut num_bytes = 0usize;
+	let mut terms = Vec::new();
+	for add_operation in add_operations {
+		for (field, field_values) in add_operation.document.get_sorted_field_values() {
+			let tokenizer = tokenizers[field.0 as usize].as_ref().map(|tokenizer| &**tokenizer);
+			field_terms(field, &field_values, schema, tokenizer, &mut terms);
+			let field_terms = &mut per_field_terms[field.0 as usize];
+			for term in terms.drain(..) {
+				num_bytes += 16;
+				if !field_terms.contains(&term) {
+					num_bytes += term.as_slice().len() + mem::size_of::<TFAndPositionRecorder>();
+					field_terms.insert(term);
+				}
+			}
+			if num_bytes > heap_capacity as usize || field_terms.len() > max_num_terms {
+				return true;
+			}
+		}
+	}
+	false
+}
+
+
 impl<'a> SegmentWriter<'a> {
 	
 	/// Creates a new `SegmentWriter`
@@ -81,13 +415,23 @@ impl<'a> SegmentWriter<'a> {
 	/// the flushing behavior as a buffer limit
 	/// - segment: The segment being written  
 	/// - schema
+	/// - term_hash_function: hash function used to index the terms in the heap.
 	pub fn for_segment(heap: &'a Heap,
 					   mut segment: Segment,
-					   schema: &Schema) -> Result<SegmentWriter<'a>> {
+					   schema: &Schema,
+					   term_hash_function: TermHashFunction) -> Result<SegmentWriter<'a>> {
+		let tokenizers = try!(resolve_tokenizers(schema, segment.index().tokenizers()));
 		let segment_serializer = try!(SegmentSerializer::for_segment(&mut segment));
 		let mut per_field_postings_writers: Vec<Box<PostingsWriter + 'a>> = Vec::new();
 		for field_entry in schema.fields() {
-			let postings_writer: Box<PostingsWriter + 'a> = posting_from_field_entry(field_entry, heap);
+			let postings_writer: Box<PostingsWriter + 'a> = match term_hash_function {
+				TermHashFunction::FxHash => {
+					posting_from_field_entry(field_entry, heap, FxBuildHasher::default())
+				}
+				TermHashFunction::SipHash => {
+					posting_from_field_entry(field_entry, heap, RandomState::new())
+				}
+			};
 			per_field_postings_writers.push(postings_writer);
 		}
 		Ok(SegmentWriter {
@@ -98,8 +442,21 @@ impl<'a> SegmentWriter<'a> {
 			segment_serializer: segment_serializer,
 			fast_field_writers: U32FastFieldsWriter::from_schema(schema),
 			doc_opstamps: Vec::with_capacity(1_000),
+			tokenizers: tokenizers,
+			token_buffer: String::new(),
+			term_buffer: Term::allocate(Field(0), 100),
+			u32_buffer: Vec::new(),
+			max_tokens_per_field: u32::max_value(),
+			blocks: Vec::new(),
+			term_vector_terms: Vec::new(),
 		})
 	}
+
+	/// Sets the maximum number of tokens indexed for each
+	/// field of a document. The following tokens are dropped.
+	pub fn set_max_tokens_per_field(&mut self, max_tokens_per_field: u32) {
+		self.max_tokens_per_field = max_tokens_per_field;
+	}
 	
 	/// Lay on disk the current content of the `SegmentWriter`
 	/// 
@@ -126,8 +483,27 @@ impl<'a> SegmentWriter<'a> {
 	///
 	/// Because, we cannot cut through a document, the margin is there to ensure that we rarely
 	/// exceeds the heap size.  
+	///
+	/// The heap holds the terms and the recorders of the postings, including
+	/// the term frequencies and the positions, so that position heavy
+	/// documents fill the buffer faster.
+	///
+	/// The buffer is also full when the term table of a field
+	/// holds as many terms as it was sized for.
 	pub fn is_buffer_full(&self,) -> bool {
-		self.heap.num_free_bytes() <= MARGIN_IN_BYTES
+		self.heap.num_free_bytes() <= MARGIN_IN_BYTES || self.is_term_table_full()
+	}
+
+	/// Returns true iff the documents indexed so far exhausted the heap,
+	/// overflowing it, or exhausted the term table of a field.
+	pub fn is_heap_exhausted(&self,) -> bool {
+		self.heap.num_free_bytes() == 0 || self.is_term_table_full()
+	}
+
+	fn is_term_table_full(&self,) -> bool {
+		self.per_field_postings_writers
+			.iter()
+			.any(|postings_writer| postings_writer.is_term_table_full())
 	}
 	
 	/// Indexes a new document
@@ -140,17 +516,32 @@ impl<'a> SegmentWriter<'a> {
         for (field, field_values) in doc.get_sorted_field_values() {
 			let field_posting_writer: &mut Box<PostingsWriter> = &mut self.per_field_postings_writers[field.0 as usize];
 			let field_options = schema.get_field_entry(field);
+			let max_term_length = field_options.max_term_length();
 			match *field_options.field_type() {
 				FieldType::Str(ref text_options) => {
+					if !text_options.get_indexing_options().is_indexed() {
+						// stored only fields are not written to the inverted index.
+						continue;
+					}
 					let num_tokens: u32 =
 						if text_options.get_indexing_options().is_tokenized() {
-							field_posting_writer.index_text(doc_id, field, &field_values, self.heap)
+							let tokenizer = self.tokenizers[field.0 as usize]
+								.as_ref()
+								.expect("Tokenized fields have a tokenizer");
+							let mut texts = field_values.iter().map(|field_value| field_value.value().text());
+							field_posting_writer.index_text(doc_id, field, &mut texts, &**tokenizer, &mut self.token_buffer, &mut self.term_buffer, self.max_tokens_per_field, max_term_length, self.heap)
 						}
 						else {
-							let num_field_values = field_values.len() as u32;
-							for field_value in field_values {
-								let term = Term::from_field_text(field, field_value.value().text());
-								field_posting_writer.suscribe(doc_id, 0, &term, self.heap);
+							let mut num_field_values = 0u32;
+							let lowercase = text_options.get_indexing_options().is_lowercased_keyword();
+							for field_value in &field_values {
+								let token = keyword_token(field_value.value().text(), lowercase);
+								if let Some(term_text) = max_term_length.apply(&token) {
+									self.term_buffer.set_field(field);
+									self.term_buffer.set_text(term_text);
+									field_posting_writer.suscribe(doc_id, 0, &self.term_buffer, self.heap);
+									num_field_values += 1;
+								}
 							}
 							num_field_values
 						};
@@ -159,35 +550,241 @@ impl<'a> SegmentWriter<'a> {
 						.map(|field_norms_writer| {
 							field_norms_writer.add_val(num_tokens as u32)
 						});
+					if field_options.has_term_vectors() {
+						let tokenizer = self.tokenizers[field.0 as usize].as_ref().map(|tokenizer| &**tokenizer);
+						let texts = field_values.iter().map(|field_value| field_value.value().text());
+						text_terms(field, texts, text_options.get_indexing_options(), tokenizer, max_term_length, self.max_tokens_per_field, &mut self.term_vector_terms);
+					}
 				}
 				FieldType::U32(ref u32_options) => {
 					if u32_options.is_indexed() {
 						for field_value in field_values {
-							let term = Term::from_field_u32(field_value.field(), field_value.value().u32_value());
+							self.term_buffer.set_field(field_value.field());
+							self.term_buffer.set_u32(field_value.value().u32_value());
+							field_posting_writer.suscribe(doc_id, 0, &self.term_buffer, self.heap);
+						}
+					}
+				}
+				FieldType::Date(ref date_options) => {
+					if date_options.is_indexed() {
+						for field_value in field_values {
+							let term = Term::from_field_date(field_value.field(), field_value.value().date_value());
 							field_posting_writer.suscribe(doc_id, 0, &term, self.heap);
 						}
 					}
 				}
+				FieldType::Json(ref json_options) => {
+					if !json_options.is_indexed() {
+						continue;
+					}
+					let tokenizer = self.tokenizers[field.0 as usize]
+						.as_ref()
+						.expect("Indexed JSON fields have a tokenizer");
+					let mut terms = Vec::new();
+					let mut num_tokens = 0u32;
+					for field_value in field_values {
+						let json = try!(Json::from_str(field_value.value().json_text())
+							.map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err)));
+						let mut path = String::new();
+						num_tokens += json_leaf_terms(field, &mut path, &json, &**tokenizer, max_term_length, json_options.is_fast(), &mut terms);
+					}
+					for term in &terms {
+						field_posting_writer.suscribe(doc_id, 0, term, self.heap);
+					}
+					self.fieldnorms_writer
+						.get_field_writer(field)
+						.map(|field_norms_writer| {
+							field_norms_writer.add_val(num_tokens)
+						});
+				}
+			}
+		}
+		for &(field, ref term_text, payload) in doc.payloads() {
+			if schema.get_field_entry(field).has_payloads() {
+				let term = Term::from_field_text(field, term_text);
+				self.per_field_postings_writers[field.0 as usize].set_payload(doc_id, &term, payload);
 			}
 		}
 		self.fieldnorms_writer.fill_val_up_to(doc_id);
 		self.fast_field_writers.add_document(&doc);
-		let stored_fieldvalues: Vec<&FieldValue> = doc
+		let mut stored_fieldvalues: Vec<&FieldValue> = doc
 			.field_values()
 			.iter()
 			.filter(|field_value| schema.get_field_entry(field_value.field()).is_stored())
 			.collect();
+		schema.sort_stored_field_values(&mut stored_fieldvalues);
 		let doc_writer = self.segment_serializer.get_store_writer();
 		try!(doc_writer.store(&stored_fieldvalues));
+		try!(self.write_term_vector());
         self.max_doc += 1;
 		Ok(())
     }
+
+	/// Indexes a new document from the values of a row.
+	///
+	/// The document is indexed as if the values had been added to
+	/// a `Document`, in the same order, and passed to `add_document`.
+	/// The values must match the type of their field,
+	/// see `RowValues::check_types`.
+	pub fn add_row(&mut self, opstamp: u64, row: &mut RowValues, schema: &Schema) -> io::Result<()> {
+		let doc_id = self.max_doc;
+		self.doc_opstamps.push(opstamp);
+		// the values of a field stay contiguous and keep their relative order,
+		// and the fields are in the order of the doc store.
+		row.values.sort_by_key(|&(field, _)| schema.stored_field_sort_key(field));
+		let text_buffer = &row.text_buffer[..];
+		let mut start = 0;
+		while start < row.values.len() {
+			let field = row.values[start].0;
+			let end = start + row.values[start..]
+				.iter()
+				.take_while(|&&(value_field, _)| value_field == field)
+				.count();
+			let field_values = &row.values[start..end];
+			start = end;
+			let field_posting_writer: &mut Box<PostingsWriter> = &mut self.per_field_postings_writers[field.0 as usize];
+			let field_options = schema.get_field_entry(field);
+			let max_term_length = field_options.max_term_length();
+			match *field_options.field_type() {
+				FieldType::Str(ref text_options) => {
+					if !text_options.get_indexing_options().is_indexed() {
+						continue;
+					}
+					let mut texts = field_values
+						.iter()
+						.filter_map(|&(_, value)| match value {
+							RowValue::Text(start, end) => Some(&text_buffer[start..end]),
+							RowValue::U32(_) => None,
+						});
+					let num_tokens: u32 =
+						if text_options.get_indexing_options().is_tokenized() {
+							let tokenizer = self.tokenizers[field.0 as usize]
+								.as_ref()
+								.expect("Tokenized fields have a tokenizer");
+							field_posting_writer.index_text(doc_id, field, &mut texts, &**tokenizer, &mut self.token_buffer, &mut self.term_buffer, self.max_tokens_per_field, max_term_length, self.heap)
+						}
+						else {
+							let mut num_field_values = 0u32;
+							let lowercase = text_options.get_indexing_options().is_lowercased_keyword();
+							for text in texts {
+								let token = keyword_token(text, lowercase);
+								if let Some(term_text) = max_term_length.apply(&token) {
+									self.term_buffer.set_field(field);
+									self.term_buffer.set_text(term_text);
+									field_posting_writer.suscribe(doc_id, 0, &self.term_buffer, self.heap);
+									num_field_values += 1;
+								}
+							}
+							num_field_values
+						};
+					self.fieldnorms_writer
+						.get_field_writer(field)
+						.map(|field_norms_writer| {
+							field_norms_writer.add_val(num_tokens as u32)
+						});
+					if field_options.has_term_vectors() {
+						let tokenizer = self.tokenizers[field.0 as usize].as_ref().map(|tokenizer| &**tokenizer);
+						let texts = field_values
+							.iter()
+							.filter_map(|&(_, value)| match value {
+								RowValue::Text(start, end) => Some(&text_buffer[start..end]),
+								RowValue::U32(_) => None,
+							});
+						text_terms(field, texts, text_options.get_indexing_options(), tokenizer, max_term_length, self.max_tokens_per_field, &mut self.term_vector_terms);
+					}
+				}
+				FieldType::U32(ref u32_options) => {
+					self.u32_buffer.clear();
+					self.u32_buffer.extend(field_values
+						.iter()
+						.filter_map(|&(_, value)| match value {
+							RowValue::U32(val) => Some(val),
+							RowValue::Text(..) => None,
+						}));
+					if u32_options.is_indexed() {
+						for &val in &self.u32_buffer {
+							self.term_buffer.set_field(field);
+							self.term_buffer.set_u32(val);
+							field_posting_writer.suscribe(doc_id, 0, &self.term_buffer, self.heap);
+						}
+					}
+					if let Some(&first_val) = self.u32_buffer.first() {
+						self.fast_field_writers
+							.get_field_writer(field)
+							.map(|field_writer| field_writer.add_val(first_val));
+						let u32_buffer = &self.u32_buffer;
+						self.fast_field_writers
+							.get_multivalued_field_writer(field)
+							.map(|field_writer| field_writer.add_vals(u32_buffer));
+					}
+				}
+				_ => {}
+			}
+		}
+		self.fieldnorms_writer.fill_val_up_to(doc_id);
+		self.fast_field_writers.fill_val_up_to(doc_id);
+		let stored_values: Vec<&(Field, RowValue)> = row.values
+			.iter()
+			.filter(|&&(field, _)| schema.get_field_entry(field).is_stored())
+			.collect();
+		let doc_writer = self.segment_serializer.get_store_writer();
+		try!(doc_writer.store_with(stored_values.len() as u32, |buffer| {
+			for &&(field, value) in &stored_values {
+				try!(field.serialize(buffer));
+				match value {
+					RowValue::Text(start, end) => try!(serialize_text_value(&text_buffer[start..end], buffer)),
+					RowValue::U32(val) => try!(Value::U32(val).serialize(buffer)),
+				};
+			}
+			Ok(())
+		}));
+		try!(self.write_term_vector());
+		self.max_doc += 1;
+		Ok(())
+	}
+
+	// Writes the term vector of the document being indexed,
+	// from the terms gathered for its fields with term vectors.
+	fn write_term_vector(&mut self) -> io::Result<()> {
+		if let Some(term_vector_writer) = self.segment_serializer.get_term_vector_writer() {
+			self.term_vector_terms.sort();
+			let mut term_vector: Vec<(Term, u32)> = Vec::new();
+			for term in self.term_vector_terms.drain(..) {
+				if let Some(&mut (ref last_term, ref mut term_freq)) = term_vector.last_mut() {
+					if *last_term == term {
+						*term_freq += 1;
+						continue;
+					}
+				}
+				term_vector.push((term, 1));
+			}
+			try!(term_vector_writer.write(&term_vector));
+		}
+		Ok(())
+	}
 	
+	/// Indexes a block of documents, whose documents
+	/// get contiguous doc ids.
+	///
+	/// The block is recorded in the `SegmentInfo`, so that
+	/// the merges keep its documents together.
+	pub fn add_document_block(&mut self, add_operations: &[AddOperation], schema: &Schema) -> io::Result<()> {
+		let start = self.max_doc;
+		for add_operation in add_operations {
+			try!(self.add_document(add_operation, schema));
+		}
+		if self.max_doc - start > 1 {
+			self.blocks.push((start, self.max_doc));
+		}
+		Ok(())
+	}
+
 	/// Creates the `SegmentInfo` that will be serialized along
 	/// with the index in JSON format.  
  	fn segment_info(&self,) -> SegmentInfo {
 		SegmentInfo {
-			max_doc: self.max_doc
+			max_doc: self.max_doc,
+			blocks: Some(self.blocks.clone()),
 		}
 	}
 	
@@ -225,7 +822,9 @@ fn write<'a>(per_field_postings_writers: &[Box<PostingsWriter + 'a>],
 			try!(per_field_postings_writer.serialize(serializer.get_postings_serializer(), heap));
 		}
 		try!(fast_field_writers.serialize(serializer.get_fast_field_serializer()));
-		try!(fieldnorms_writer.serialize(serializer.get_fieldnorms_serializer()));
+		if let Some(fieldnorms_serializer) = serializer.get_fieldnorms_serializer() {
+			try!(fieldnorms_writer.serialize(fieldnorms_serializer));
+		}
 		try!(serializer.write_segment_info(&segment_info));
 		try!(serializer.close());
 		Ok(segment_info.max_doc)
@@ -241,3 +840,145 @@ impl<'a> SerializableSegment for SegmentWriter<'a> {
 			  self.heap)
 	}
 }
+
+
+#[cfg(test)]
+mod tests {
+
+	use super::*;
+	use core::{Index, SegmentComponent, SegmentMeta};
+	use schema::{self, Document, SchemaBuilder, FieldValue, TextOptions, TextIndexingOptions};
+	use test::Bencher;
+
+	fn build_schema() -> Schema {
+		let mut schema_builder = SchemaBuilder::default();
+		schema_builder.add_text_field("text", schema::TEXT);
+		schema_builder.add_text_field("tag", schema::STRING);
+		schema_builder.add_u32_field("count", schema::U32_INDEXED);
+		schema_builder.build()
+	}
+
+	fn build_docs(schema: &Schema, num_docs: u32, salt: u32) -> Vec<Document> {
+		let text_field = schema.get_field("text").unwrap();
+		let tag_field = schema.get_field("tag").unwrap();
+		let count_field = schema.get_field("count").unwrap();
+		(0..num_docs)
+			.map(|i| {
+				let mut doc = Document::default();
+				doc.add_text(text_field, &format!("a{} b{} c{} a{}", i * salt, i % 7, i % 13, i % 5));
+				doc.add_text(tag_field, &format!("tag{}", (i + salt) % 3));
+				doc.add_u32(count_field, i % 11);
+				doc
+			})
+			.collect()
+	}
+
+	/// Writes `docs` into a new segment of `index`, and
+	/// returns the content of its postings files.
+	fn write_segment(index: &Index, heap: &Heap, docs: Vec<Document>) -> Vec<Vec<u8>> {
+		let schema = index.schema();
+		heap.clear();
+		let segment = index.new_segment();
+		let segment_id = segment.id();
+		let num_docs = {
+			let mut segment_writer = SegmentWriter::for_segment(heap, segment, &schema, TermHashFunction::FxHash).unwrap();
+			for (opstamp, doc) in docs.into_iter().enumerate() {
+				let add_operation = AddOperation { opstamp: opstamp as u64, document: doc };
+				segment_writer.add_document(&add_operation, &schema).unwrap();
+			}
+			let num_docs = segment_writer.max_doc();
+			segment_writer.finalize().unwrap();
+			num_docs
+		};
+		let mut segment_meta = SegmentMeta::new(segment_id);
+		segment_meta.set_max_doc(num_docs);
+		let segment = index.segment(segment_meta);
+		vec!(SegmentComponent::TERMS, SegmentComponent::POSTINGS, SegmentComponent::POSITIONS)
+			.into_iter()
+			.map(|component| segment.open_read(component).unwrap().as_slice().to_vec())
+			.collect()
+	}
+
+	#[test]
+	fn test_reused_heap_produces_identical_segments() {
+		let schema = build_schema();
+		let index = Index::create_in_ram(schema.clone());
+		let fresh_segment = write_segment(&index, &Heap::with_capacity(10_000_000), build_docs(&schema, 1_000, 1));
+		let heap = Heap::with_capacity(10_000_000);
+		write_segment(&index, &heap, build_docs(&schema, 3_000, 17));
+		assert!(heap.len() > 0);
+		let reused_segment = write_segment(&index, &heap, build_docs(&schema, 1_000, 1));
+		assert_eq!(fresh_segment, reused_segment);
+	}
+
+	const NUM_TOKENS_PER_DOC: usize = 1_000;
+
+	/// Indexes up to `max_docs` position heavy documents, until the
+	/// buffer is full, and returns the number of documents
+	/// indexed and the number of bytes used in the heap.
+	fn fill_buffer(indexing_options: TextIndexingOptions, heap_budget: usize, max_docs: usize) -> (u32, u32) {
+		let mut schema_builder = SchemaBuilder::default();
+		let text_options = TextOptions::default().set_indexing_options(indexing_options);
+		let text_field = schema_builder.add_text_field("text", text_options);
+		let schema = schema_builder.build();
+		let index = Index::create_in_ram(schema.clone());
+		let heap = Heap::with_capacity(MARGIN_IN_BYTES as usize + heap_budget);
+		let text: Vec<&str> = (0..NUM_TOKENS_PER_DOC)
+			.map(|i| if i % 2 == 0 { "a" } else { "b" })
+			.collect();
+		let text = text.join(" ");
+		let mut segment_writer = SegmentWriter::for_segment(&heap, index.new_segment(), &schema, TermHashFunction::FxHash).unwrap();
+		for opstamp in 0..max_docs {
+			let add_operation = AddOperation { opstamp: opstamp as u64, document: doc!(text_field=>text.clone()) };
+			segment_writer.add_document(&add_operation, &schema).unwrap();
+			if segment_writer.is_buffer_full() {
+				break;
+			}
+		}
+		let num_docs = segment_writer.max_doc();
+		let heap_len = heap.len();
+		segment_writer.finalize().unwrap();
+		(num_docs, heap_len)
+	}
+
+	#[test]
+	fn test_positions_count_in_heap_usage() {
+		let num_docs = 100;
+		let (_, heap_len_with_positions) = fill_buffer(TextIndexingOptions::TokenizedWithFreqAndPosition, 1_000_000, num_docs);
+		let (_, heap_len_with_freq) = fill_buffer(TextIndexingOptions::TokenizedWithFreq, 1_000_000, num_docs);
+		// each position takes 4 bytes.
+		assert!(heap_len_with_positions - heap_len_with_freq >= (4 * NUM_TOKENS_PER_DOC * num_docs) as u32);
+	}
+
+	#[test]
+	fn test_positions_trigger_flush() {
+		let heap_budget = 1_000_000;
+		let (num_docs, heap_len) = fill_buffer(TextIndexingOptions::TokenizedWithFreqAndPosition, heap_budget, 10_000);
+		assert!(num_docs < 10_000);
+		assert!(heap_len as usize >= heap_budget);
+		// the same documents without positions fit in the budget.
+		let (num_docs, heap_len) = fill_buffer(TextIndexingOptions::TokenizedWithFreq, heap_budget, 10_000);
+		assert_eq!(num_docs, 10_000);
+		assert!((heap_len as usize) < heap_budget);
+	}
+
+	#[bench]
+	fn bench_index_documents_fresh_heap(b: &mut Bencher) {
+		let schema = build_schema();
+		let index = Index::create_in_ram(schema.clone());
+		b.iter(|| {
+			let heap = Heap::with_capacity(10_000_000);
+			write_segment(&index, &heap, build_docs(&schema, 1_000, 1))
+		});
+	}
+
+	#[bench]
+	fn bench_index_documents_reused_heap(b: &mut Bencher) {
+		let schema = build_schema();
+		let index = Index::create_in_ram(schema.clone());
+		let heap = Heap::with_capacity(10_000_000);
+		b.iter(|| {
+			write_segment(&index, &heap, build_docs(&schema, 1_000, 1))
+		});
+	}
+}
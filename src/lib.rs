@@ -91,11 +91,13 @@ mod core;
 mod compression;
 mod fastfield;
 mod store;
+mod termvector;
 mod indexer;
 mod common;
 mod error;
 mod analyzer;
 mod datastruct;
+mod snippet;
 
 
 
@@ -113,10 +115,25 @@ pub mod schema;
 
 pub use directory::Directory;
 pub use core::{Index, Segment, SegmentId, SegmentMeta, Searcher};
-pub use indexer::IndexWriter;
+pub use core::{IndexReader, ReloadPolicy};
+pub use core::{BackupGuard, Snapshot};
+pub use indexer::{IndexWriter, OversizedDocumentPolicy};
+pub use indexer::{SegmentBuilder, Row, Column};
+pub use indexer::FragmentationReport;
+pub use indexer::SegmentStats;
+pub use indexer::ComputedField;
+pub use indexer::{MergePhase, MergeProgress};
+pub use indexer::SegmentState;
+pub use datastruct::stacker::TermHashFunction;
 pub use schema::{Term, Document};
 pub use core::SegmentReader;
+pub use core::rename_segment_files;
 pub use self::common::TimerTree;
+pub use analyzer::{CjkTokenizer, CjkTokenIter, Token};
+pub use analyzer::{Tokenizer, TokenStream, TokenizerManager};
+pub use analyzer::{SimpleTokenizer, RawTokenizer, NgramTokenizer, UrlEmailTokenizer};
+pub use analyzer::{TextAnalyzer, TokenFilter, LowerCaser, StopWordFilter, Stemmer};
+pub use snippet::{SnippetGenerator, MultiFieldSnippetGenerator, Snippet};
 
 
 pub use postings::DocSet;
@@ -124,6 +141,15 @@ pub use postings::Postings;
 pub use postings::SegmentPostingsOption;
 
 pub use core::TermIterator;
+pub use core::RankedStream;
+pub use core::ResultCache;
+pub use core::{IntegrityReport, IntegrityProblem};
+pub use core::CollectionStatistics;
+pub use core::FacetDrillDown;
+pub use core::{MultiIndexSearcher, IndexDocAddress};
+pub use core::BudgetedSearcher;
+pub use fastfield::FastFieldWidth;
+pub use fastfield::MultiValuedFastFieldReader;
 
 
 /// Expose the current version of tantivy, as well
@@ -142,10 +168,20 @@ pub fn version() -> &'static str {
 pub mod merge_policy {
     pub use indexer::MergePolicy;
     pub use indexer::LogMergePolicy;
+    pub use indexer::DedupMergePolicy;
     pub use indexer::NoMergePolicy;
     pub use indexer::DefaultMergePolicy;
 }
 
+/// Codecs used to compress the stored fields.
+///
+/// The codec of the segments being written is defined
+/// by `IndexWriter::set_store_compressor`.
+pub mod store_compression {
+    pub use store::{Compressor, StoreCompressor};
+    pub use store::{Lz4Compressor, SnappyCompressor, NoCompressor};
+}
+
 /// u32 identifying a document within a segment.
 /// Documents have their doc id assigned incrementally,
 /// as they are added in the segment.
@@ -183,7 +219,7 @@ impl DocAddress {
 /// 
 /// The id used for the segment is actually an ordinal
 /// in the list of segment hold by a `Searcher`.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct DocAddress(pub SegmentLocalId, pub DocId);
 
 
@@ -265,6 +301,37 @@ mod tests {
             assert_eq!(searcher.doc_freq(&term_d), 0);
         }
     }
+
+    #[test]
+    fn test_term_doc_freqs() {
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let other_field = schema_builder.add_text_field("other", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.add_document(doc!(text_field=>"b a c", other_field=>"a z"));
+            index_writer.add_document(doc!(text_field=>"a a"));
+            index_writer.add_document(doc!(text_field=>"ab c", other_field=>"y"));
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let segment_reader: &SegmentReader = searcher.segment_reader(0);
+        let term_doc_freqs: Vec<(Vec<u8>, u32)> = segment_reader.term_doc_freqs(text_field).collect();
+        assert_eq!(term_doc_freqs, vec!(
+            (b"a".to_vec(), 2),
+            (b"ab".to_vec(), 1),
+            (b"b".to_vec(), 1),
+            (b"c".to_vec(), 2),
+        ));
+        let term_doc_freqs: Vec<(Vec<u8>, u32)> = segment_reader.term_doc_freqs(other_field).collect();
+        assert_eq!(term_doc_freqs, vec!(
+            (b"a".to_vec(), 1),
+            (b"y".to_vec(), 1),
+            (b"z".to_vec(), 1),
+        ));
+    }
     
     
     #[test]
@@ -299,6 +366,79 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_fieldnorm_disabled() {
+        use collector::TopCollector;
+        use core::SegmentComponent;
+        use query::{PhraseQuery, Query, TermQuery};
+        use std::fs;
+        use std::path::Path;
+        use tempdir::TempDir;
+
+        fn dir_num_bytes(path: &Path) -> u64 {
+            fs::read_dir(path)
+                .unwrap()
+                .map(|entry| entry.unwrap().metadata().unwrap().len())
+                .sum()
+        }
+
+        // returns the matching docs, with their scores,
+        // and the size of the index on disk.
+        fn search(text_options: TextOptions) -> (Vec<(u32, f32)>, Vec<(u32, f32)>, u64) {
+            let tempdir = TempDir::new("index").unwrap();
+            let mut schema_builder = SchemaBuilder::default();
+            let has_norms = text_options.has_norms();
+            let text_field = schema_builder.add_text_field("text", text_options);
+            let index = Index::create(tempdir.path(), schema_builder.build()).unwrap();
+            {
+                let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+                for i in 0..1_000 {
+                    let mut text = String::from(if i % 3 == 0 { "a b" } else { "b a" });
+                    for _ in 0..(i % 10) {
+                        text.push_str(" c");
+                    }
+                    index_writer.add_document(doc!(text_field=>text));
+                }
+                index_writer.commit().unwrap();
+            }
+            index.load_searchers().unwrap();
+            let searcher = index.searcher();
+            let segment_reader = searcher.segment_reader(0);
+            assert_eq!(segment_reader.get_fieldnorms_reader(text_field).is_some(), has_norms);
+            let segment = index.segment(index.segments().unwrap()[0].clone());
+            assert_eq!(segment.open_read(SegmentComponent::FIELDNORMS).is_ok(), has_norms);
+            let search_query = |query: &Query| {
+                let mut top_collector = TopCollector::with_limit(1_000);
+                searcher.search(query, &mut top_collector).unwrap();
+                let mut score_docs: Vec<(u32, f32)> = top_collector
+                    .score_docs()
+                    .into_iter()
+                    .map(|(score, doc_address)| (doc_address.doc(), score))
+                    .collect();
+                score_docs.sort_by_key(|&(doc, _)| doc);
+                score_docs
+            };
+            let term_score_docs = search_query(&TermQuery::new(Term::from_field_text(text_field, "a"), SegmentPostingsOption::Freq));
+            let phrase_score_docs = search_query(&PhraseQuery::from(vec!(Term::from_field_text(text_field, "a"),
+                                                                         Term::from_field_text(text_field, "b"))));
+            (term_score_docs, phrase_score_docs, dir_num_bytes(tempdir.path()))
+        }
+
+        let (term_score_docs, phrase_score_docs, num_bytes) = search(TEXT);
+        let (no_norms_term_score_docs, no_norms_phrase_score_docs, no_norms_num_bytes) = search(TEXT.set_norms(false));
+        let docs = |score_docs: &[(u32, f32)]| score_docs.iter().map(|&(doc, _)| doc).collect::<Vec<u32>>();
+        assert_eq!(term_score_docs.len(), 1_000);
+        assert_eq!(docs(&term_score_docs), docs(&no_norms_term_score_docs));
+        assert_eq!(phrase_score_docs.len(), 334);
+        assert_eq!(docs(&phrase_score_docs), docs(&no_norms_phrase_score_docs));
+        // the scores only depend on the length of the field with norms.
+        assert!(term_score_docs[0].1 != term_score_docs[1].1);
+        assert_eq!(no_norms_term_score_docs[0].1, no_norms_term_score_docs[1].1);
+        assert!(term_score_docs[1].1 != no_norms_term_score_docs[1].1);
+        assert!(phrase_score_docs[1].1 != no_norms_phrase_score_docs[1].1);
+        assert!(no_norms_num_bytes < num_bytes);
+    }
+
 
     #[test]
     fn test_delete_postings1() {
@@ -613,6 +753,39 @@ mod tests {
         index.searcher();
     }
 
+    #[test]
+    fn test_max_position() {
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let string_field = schema_builder.add_text_field("string", STRING);
+        let other_text_field = schema_builder.add_text_field("other", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.add_document(doc!(text_field=>"a b c", string_field=>"a b c"));
+            index_writer.add_document(doc!(text_field=>"a b c d e"));
+            index_writer.add_document(doc!(text_field=>"b"));
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let segment_reader = searcher.segment_reader(0);
+        assert_eq!(segment_reader.max_position(text_field), Some(4));
+        assert_eq!(segment_reader.max_position(string_field), None);
+        assert_eq!(segment_reader.max_position(other_text_field), None);
+
+        let multivalued_index = Index::create_in_ram(index.schema());
+        {
+            let mut index_writer = multivalued_index.writer_with_num_threads(1, 40_000_000).unwrap();
+            // the values of a multivalued field are separated by a gap of one position.
+            index_writer.add_document(doc!(text_field=>"a b c", text_field=>"d e"));
+            index_writer.commit().unwrap();
+        }
+        multivalued_index.load_searchers().unwrap();
+        let searcher = multivalued_index.searcher();
+        assert_eq!(searcher.segment_reader(0).max_position(text_field), Some(5));
+    }
+
     #[test]
     fn test_doc_macro() {
         let mut schema_builder = SchemaBuilder::default();
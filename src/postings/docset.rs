@@ -55,6 +55,25 @@ pub trait DocSet {
     /// Returns the current document
     fn doc(&self) -> DocId;
 
+    /// Returns the documents following the current one that are already
+    /// decoded, typically the rest of the current block of a `SegmentPostings`.
+    ///
+    /// They may include deleted documents, and are only a hint: an
+    /// `IntersectionDocSet` intersects them with `simd_intersect`
+    /// to find its next candidate. The default implementation
+    /// returns an empty slice.
+    fn buffered_docs(&self) -> &[DocId] {
+        &[]
+    }
+
+    /// Returns an upper bound of the number of documents
+    /// in the `DocSet`, or `u32::max_value()` if it is unknown.
+    ///
+    /// A `DocSet` whose size hint is 0 is guaranteed to be empty.
+    fn size_hint(&self) -> u32 {
+        u32::max_value()
+    }
+
     /// Advances the cursor to the next document
     /// None is returned if the iterator has `DocSet`
     /// has already been entirely consumed.
@@ -65,6 +84,29 @@ pub trait DocSet {
             None
         }
     }
+
+    /// Consumes the remaining documents of the `DocSet`,
+    /// and returns them as a sorted vector.
+    ///
+    /// The vector can be replayed with a `VecDocSet`,
+    /// without evaluating the `DocSet` again.
+    fn collect_into_vec(&mut self) -> Vec<DocId> {
+        let mut doc_ids = Vec::new();
+        while self.advance() {
+            doc_ids.push(self.doc());
+        }
+        doc_ids
+    }
+
+    /// Consumes the remaining documents of the `DocSet`,
+    /// and returns their number.
+    fn count(&mut self) -> u32 {
+        let mut count = 0u32;
+        while self.advance() {
+            count += 1;
+        }
+        count
+    }
 }
 
 
@@ -83,6 +125,16 @@ impl<TDocSet: DocSet + ?Sized> DocSet for Box<TDocSet> {
         let unboxed: &TDocSet = self.borrow();
         unboxed.doc()
     }
+
+    fn buffered_docs(&self) -> &[DocId] {
+        let unboxed: &TDocSet = self.borrow();
+        unboxed.buffered_docs()
+    }
+
+    fn size_hint(&self) -> u32 {
+        let unboxed: &TDocSet = self.borrow();
+        unboxed.size_hint()
+    }
 }
 
 impl<'a, TDocSet: DocSet> DocSet for &'a mut TDocSet {
@@ -100,4 +152,14 @@ impl<'a, TDocSet: DocSet> DocSet for &'a mut TDocSet {
         let unref: &TDocSet = *self;
         unref.doc()
     }
+
+    fn buffered_docs(&self) -> &[DocId] {
+        let unref: &TDocSet = *self;
+        unref.buffered_docs()
+    }
+
+    fn size_hint(&self) -> u32 {
+        let unref: &TDocSet = *self;
+        unref.size_hint()
+    }
 }
@@ -59,6 +59,18 @@ impl FreqHandler {
         }
     }
 
+    /// Returns true iff the positions are decoded.
+    ///
+    /// The positions of the documents are offset by the term
+    /// frequencies of all of the previous blocks, so that
+    /// no block can then be skipped.
+    pub fn has_positions(&self) -> bool {
+        match self.option {
+            SegmentPostingsOption::FreqAndPositions => true,
+            _ => false,
+        }
+    }
+
     fn fill_positions_offset(&mut self) {
         let mut cur_position: usize = self.positions_offsets[NUM_DOCS_PER_BLOCK];
         let mut i: usize = 0;
@@ -111,15 +123,16 @@ impl FreqHandler {
     }
 
     /// Decompresses an incomplete frequency block
-    pub fn read_freq_vint(&mut self, data: &[u8], num_els: usize) {
+    pub fn read_freq_vint<'a>(&mut self, data: &'a [u8], num_els: usize) -> &'a [u8] {
         match self.option {
-            SegmentPostingsOption::NoFreq => {}
+            SegmentPostingsOption::NoFreq => data,
             SegmentPostingsOption::Freq => {
-                self.freq_decoder.uncompress_vint_unsorted(data, num_els);
+                self.freq_decoder.uncompress_vint_unsorted(data, num_els)
             }
             SegmentPostingsOption::FreqAndPositions => {
-                self.freq_decoder.uncompress_vint_unsorted(data, num_els);
+                let remaining: &'a [u8] = self.freq_decoder.uncompress_vint_unsorted(data, num_els);
                 self.fill_positions_offset();
+                remaining
             }
         }
     }
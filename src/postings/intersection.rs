@@ -1,21 +1,31 @@
 use postings::DocSet;
 use postings::SkipResult;
+use compression::{simd_intersect, NUM_DOCS_PER_BLOCK};
+use std::cmp;
 use DocId;
 
 /// Creates a `DocSet` that iterator through the intersection of two `DocSet`s.
+///
+/// The intersection of a single `DocSet` is the `DocSet` itself.
+///
+/// The documents already decoded by the first two `DocSet`s
+/// (see `DocSet::buffered_docs`) are intersected with `simd_intersect`
+/// to jump directly to the next candidate.
 pub struct IntersectionDocSet<TDocSet: DocSet> {
     docsets: Vec<TDocSet>,
     finished: bool,
     doc: DocId,
+    intersection_buffer: Vec<DocId>,
 }
 
 impl<TDocSet: DocSet> From<Vec<TDocSet>> for IntersectionDocSet<TDocSet> {
     fn from(docsets: Vec<TDocSet>) -> IntersectionDocSet<TDocSet> {
-        assert!(docsets.len() >= 2);
+        assert!(docsets.len() >= 1);
         IntersectionDocSet {
             docsets: docsets,
             finished: false,
             doc: DocId::max_value(),
+            intersection_buffer: vec!(0u32; NUM_DOCS_PER_BLOCK),
         }
     }
 }
@@ -27,6 +37,29 @@ impl<TDocSet: DocSet> IntersectionDocSet<TDocSet> {
     pub fn docsets(&self) -> &[TDocSet] {
         &self.docsets[..]
     }
+
+    // returns a lower bound of the next document of the intersection.
+    //
+    // The docsets are positioned on the same document, and any document
+    // of the intersection lower than the last document buffered by the
+    // first two docsets is in the intersection of their buffers.
+    fn first_candidate(&mut self) -> DocId {
+        let left = self.docsets[0].buffered_docs();
+        let right = self.docsets[1].buffered_docs();
+        if left.is_empty() || right.is_empty() {
+            return 0;
+        }
+        let max_len = cmp::min(left.len(), right.len());
+        if self.intersection_buffer.len() < max_len {
+            self.intersection_buffer.resize(max_len, 0u32);
+        }
+        if simd_intersect(left, right, &mut self.intersection_buffer) > 0 {
+            self.intersection_buffer[0]
+        }
+        else {
+            cmp::min(left[left.len() - 1], right[right.len() - 1]) + 1
+        }
+    }
 }
 
 
@@ -35,9 +68,19 @@ impl<TDocSet: DocSet> DocSet for IntersectionDocSet<TDocSet> {
         if self.finished {
             return false;
         }
+        if self.docsets.len() == 1 {
+            // `skip_next` always advances, and cannot be
+            // called on an unique docset positioned on the candidate.
+            if !self.docsets[0].advance() {
+                self.finished = true;
+                return false;
+            }
+            self.doc = self.docsets[0].doc();
+            return true;
+        }
         let num_docsets = self.docsets.len();
         let mut count_matching = 0;
-        let mut doc_candidate = 0;
+        let mut doc_candidate = self.first_candidate();
         let mut ord = 0;
         loop {
             let mut doc_set = &mut self.docsets[ord];
@@ -68,4 +111,12 @@ impl<TDocSet: DocSet> DocSet for IntersectionDocSet<TDocSet> {
     fn doc(&self) -> DocId {
         self.doc
     }
+
+    fn size_hint(&self) -> u32 {
+        self.docsets
+            .iter()
+            .map(|docset| docset.size_hint())
+            .min()
+            .unwrap_or(0)
+    }
 }
@@ -10,44 +10,57 @@ mod serializer;
 mod postings_writer;
 mod term_info;
 mod vec_postings;
+mod vec_docset;
 mod segment_postings;
 mod intersection;
 mod freq_handler;
 mod docset;
 mod segment_postings_option;
+mod read_ahead;
 
 pub use self::docset::{SkipResult, DocSet};
 pub use self::recorder::{Recorder, NothingRecorder, TermFrequencyRecorder, TFAndPositionRecorder};
 pub use self::serializer::PostingsSerializer;
 pub use self::postings_writer::PostingsWriter;
 pub use self::postings_writer::SpecializedPostingsWriter;
+pub use self::postings_writer::term_table_capacity;
 pub use self::term_info::TermInfo;
 pub use self::postings::Postings;
 
 #[cfg(test)]
 pub use self::vec_postings::VecPostings;
+pub use self::vec_docset::VecDocSet;
 pub use self::segment_postings::SegmentPostings;
 pub use self::intersection::IntersectionDocSet;
 pub use self::freq_handler::FreqHandler;
 pub use self::segment_postings_option::SegmentPostingsOption;
+pub use self::read_ahead::ReadAhead;
 pub use common::HasLen;
 
 #[cfg(test)]
 mod tests {
     
     use super::*;
-    use schema::{Document, TEXT, STRING, SchemaBuilder, Term};
+    use schema::{Document, FieldValue, TEXT, STRING, SchemaBuilder, Term};
+    use DocId;
     use core::SegmentComponent;
     use indexer::SegmentWriter;
     use core::SegmentReader;
     use core::Index;
     use std::iter;
-    use datastruct::stacker::Heap;
+    use datastruct::stacker::{Heap, TermHashFunction};
     use query::TermQuery;
     use schema::Field;
     use test::Bencher;
     use indexer::operation::AddOperation;
     use rand::{XorShiftRng, Rng, SeedableRng};
+    use fst::Streamer;
+    use datastruct::FstMap;
+    use fastfield::delete::DeleteBitSet;
+    use indexer::NoMergePolicy;
+    use compression::NUM_DOCS_PER_BLOCK;
+    use futures::Future;
+    use tempdir::TempDir;
     
         
     #[test]
@@ -79,7 +92,7 @@ mod tests {
         let segment = index.new_segment();
         let heap = Heap::with_capacity(10_000_000);
         {
-            let mut segment_writer = SegmentWriter::for_segment(&heap, segment.clone(), &schema).unwrap();
+            let mut segment_writer = SegmentWriter::for_segment(&heap, segment.clone(), &schema, TermHashFunction::default()).unwrap();
             {
                 let mut doc = Document::default();
                 doc.add_text(text_field, "a b a c a d a a.");
@@ -196,6 +209,323 @@ mod tests {
         assert_eq!(term_scorer.postings().positions(), &[1u32, 4]);
     }
     
+    #[test]
+    pub fn test_no_token_bleed_through() {
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let schema = schema_builder.build();
+        let index = Index::create_in_ram(schema);
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            {
+                let mut doc = Document::default();
+                doc.add_text(text_field, "supercalifragilistic");
+                index_writer.add_document(doc);
+            }
+            {
+                let mut doc = Document::default();
+                doc.add_text(text_field, "a");
+                doc.add_text(text_field, "bc");
+                index_writer.add_document(doc);
+            }
+            assert!(index_writer.commit().is_ok());
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let segment_reader = &searcher.segment_readers()[0];
+        let mut terms: Vec<Term> = Vec::new();
+        let mut keys = segment_reader.term_infos().keys();
+        while let Some(term_bytes) = keys.next() {
+            terms.push(Term::from_bytes(term_bytes));
+        }
+        let expected_terms: Vec<Term> = vec!("a", "bc", "supercalifragilistic")
+            .into_iter()
+            .map(|text| Term::from_field_text(text_field, text))
+            .collect();
+        assert_eq!(terms, expected_terms);
+    }
+    
+    #[test]
+    pub fn test_raw_postings_bytes() {
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let schema = schema_builder.build();
+        let index = Index::create_in_ram(schema);
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            for i in 0..300 {
+                let mut doc = Document::default();
+                if i % 3 == 0 {
+                    doc.add_text(text_field, "a b a");
+                }
+                else {
+                    doc.add_text(text_field, "b c a c");
+                }
+                index_writer.add_document(doc);
+            }
+            assert!(index_writer.commit().is_ok());
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let segment_reader = &searcher.segment_readers()[0];
+        let term_a = Term::from_field_text(text_field, "a");
+        assert!(segment_reader.raw_postings_bytes(&Term::from_field_text(text_field, "z")).is_none());
+        let doc_freq = segment_reader.get_term_info(&term_a).unwrap().doc_freq;
+        let postings_bytes = segment_reader.raw_postings_bytes(&term_a).unwrap();
+        let positions_bytes = segment_reader.raw_positions_bytes(&term_a).unwrap();
+
+        let mut segment = index.new_segment();
+        {
+            let mut posting_serializer = PostingsSerializer::open(&mut segment).unwrap();
+            posting_serializer.write_raw_postings(&term_a, doc_freq, postings_bytes, positions_bytes).unwrap();
+            posting_serializer.close().unwrap();
+        }
+        let term_infos: FstMap<TermInfo> = FstMap::from_source(segment.open_read(SegmentComponent::TERMS).unwrap()).unwrap();
+        let postings_data = segment.open_read(SegmentComponent::POSTINGS).unwrap();
+        let positions_data = segment.open_read(SegmentComponent::POSITIONS).unwrap();
+        let term_info = term_infos.get(term_a.as_slice()).unwrap();
+        let delete_bitset = DeleteBitSet::empty();
+        let mut shipped_postings = SegmentPostings::from_data(
+            term_info.doc_freq,
+            &postings_data.as_slice()[term_info.postings_offset as usize..],
+            &delete_bitset,
+            FreqHandler::new_with_freq_and_position(&positions_data.as_slice()[term_info.positions_offset as usize..]));
+        let mut original_postings = segment_reader
+            .read_postings(&term_a, SegmentPostingsOption::FreqAndPositions)
+            .unwrap();
+        for _ in 0..300 {
+            assert!(original_postings.advance());
+            assert!(shipped_postings.advance());
+            assert_eq!(shipped_postings.doc(), original_postings.doc());
+            assert_eq!(shipped_postings.term_freq(), original_postings.term_freq());
+            assert_eq!(shipped_postings.positions(), original_postings.positions());
+        }
+        assert!(!original_postings.advance());
+        assert!(!shipped_postings.advance());
+    }
+    
+    #[test]
+    pub fn test_payloads() {
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let tag_field = schema_builder.add_text_field("tag", STRING);
+        schema_builder.enable_payloads(text_field);
+        schema_builder.enable_payloads(tag_field);
+        let index = Index::create_in_ram(schema_builder.build());
+        // documents without a payload get 0.
+        let expected_payload = |doc: u32| if doc % 7 == 0 { 0 } else { doc * 1_000 + 17 };
+        let expected_tag_payload = |doc: u32| if doc % 7 == 0 { 0 } else { doc + 1 };
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.set_merge_policy(box NoMergePolicy);
+            for i in 0..300u32 {
+                let mut doc = Document::default();
+                doc.add_text(text_field, "a b a");
+                doc.add_text(tag_field, "Tag");
+                if i % 7 != 0 {
+                    doc.set_payload(text_field, "a", expected_payload(i));
+                    doc.set_payload(tag_field, "Tag", expected_tag_payload(i));
+                }
+                // the document does not contain the term.
+                doc.set_payload(text_field, "z", 3);
+                index_writer.add_document(doc);
+                if i == 199 {
+                    index_writer.commit().unwrap();
+                }
+            }
+            index_writer.commit().unwrap();
+            let segment_ids = index.searchable_segment_ids().unwrap();
+            assert_eq!(segment_ids.len(), 2);
+            index_writer.merge(&segment_ids).wait().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        assert_eq!(searcher.segment_readers().len(), 1);
+        let segment_reader = searcher.segment_reader(0);
+        let tag_term = Term::from_field_text(tag_field, "Tag");
+        // merged segments are stacked in an unspecified order.
+        let first_payload = {
+            let mut postings_tag = segment_reader.read_postings_all_info(&tag_term).unwrap();
+            assert!(postings_tag.advance());
+            postings_tag.payload()
+        };
+        let docs: Vec<u32> = if first_payload == expected_tag_payload(0) {
+            (0..300).collect()
+        } else {
+            (200..300).chain(0..200).collect()
+        };
+        {
+            let term_a = Term::from_field_text(text_field, "a");
+            let mut postings_a = segment_reader.read_postings(&term_a, SegmentPostingsOption::FreqAndPositions).unwrap();
+            let mut postings_a_nofreq = segment_reader.read_postings(&term_a, SegmentPostingsOption::NoFreq).unwrap();
+            let mut postings_b = segment_reader.read_postings_all_info(&Term::from_field_text(text_field, "b")).unwrap();
+            let mut postings_tag = segment_reader.read_postings_all_info(&tag_term).unwrap();
+            for (merged_doc, &doc) in docs.iter().enumerate() {
+                assert!(postings_a.advance());
+                assert_eq!(postings_a.doc(), merged_doc as u32);
+                assert_eq!(postings_a.term_freq(), 2);
+                assert_eq!(postings_a.positions(), [0, 2]);
+                assert_eq!(postings_a.payload(), expected_payload(doc));
+                assert!(postings_a_nofreq.advance());
+                assert_eq!(postings_a_nofreq.doc(), merged_doc as u32);
+                assert_eq!(postings_a_nofreq.payload(), expected_payload(doc));
+                assert!(postings_b.advance());
+                assert_eq!(postings_b.payload(), 0);
+                assert!(postings_tag.advance());
+                assert_eq!(postings_tag.payload(), expected_tag_payload(doc));
+            }
+            assert!(!postings_a.advance());
+            assert!(!postings_tag.advance());
+        }
+        assert!(segment_reader.read_postings_all_info(&Term::from_field_text(text_field, "z")).is_none());
+    }
+
+    #[test]
+    fn test_postings_partial_last_block() {
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            for i in 0..300 {
+                let text = if i % 2 == 0 { "a b" } else { "b" };
+                index_writer.add_document(doc!(text_field=>text));
+            }
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let segment_reader = searcher.segment_reader(0);
+        // 150 docs: a full block followed by a partial one.
+        let expected_docs: Vec<DocId> = (0..150).map(|i| i * 2).collect();
+        {
+            let mut postings = segment_reader
+                .read_postings(&Term::from_field_text(text_field, "a"), SegmentPostingsOption::Freq)
+                .unwrap();
+            let mut docs = Vec::new();
+            while postings.advance() {
+                assert_eq!(postings.term_freq(), 1);
+                docs.push(postings.doc());
+            }
+            assert_eq!(docs, expected_docs);
+            assert!(!postings.advance());
+        }
+        {
+            let mut postings = segment_reader
+                .read_postings(&Term::from_field_text(text_field, "a"), SegmentPostingsOption::NoFreq)
+                .unwrap();
+            assert_eq!(postings.skip_next(298), SkipResult::Reached);
+            assert_eq!(postings.skip_next(299), SkipResult::End);
+        }
+    }
+
+    #[test]
+    fn test_postings_skip_next() {
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            for i in 0..3_000 {
+                let mut text = "b ".repeat(i % 4 + 1);
+                if i % 3 == 0 {
+                    text.push_str("a");
+                }
+                if i % 7 == 0 {
+                    text.push_str(" c");
+                }
+                index_writer.add_document(doc!(text_field=>text));
+            }
+            index_writer.commit().unwrap();
+            index_writer.delete_term(Term::from_field_text(text_field, "c"));
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let segment_reader = searcher.segment_reader(0);
+        assert!(segment_reader.num_deleted_docs() > 0);
+        for text in &["a", "b"] {
+            let term = Term::from_field_text(text_field, text);
+            let mut expected = Vec::new();
+            {
+                let mut postings = segment_reader.read_postings(&term, SegmentPostingsOption::Freq).unwrap();
+                while postings.advance() {
+                    expected.push((postings.doc(), postings.term_freq()));
+                }
+            }
+            // the targets skip blocks, stay within a block, go backwards,
+            // hit deleted documents and end up in the tail of the list.
+            for targets in &[vec!(0u32, 1, 700, 701, 702, 1_500, 900, 2_990, 3_000),
+                             vec!(1_000, 1_003, 2_001, 2_999),
+                             vec!(2_950)] {
+                let mut postings = segment_reader.read_postings(&term, SegmentPostingsOption::Freq).unwrap();
+                let mut cursor: Option<usize> = None;
+                for &target in targets {
+                    let start = cursor.map(|ord| ord + 1).unwrap_or(0);
+                    let expected_ord = (start..expected.len()).find(|&ord| expected[ord].0 >= target);
+                    let skip_result = postings.skip_next(target);
+                    match expected_ord {
+                        Some(ord) => {
+                            let expected_result = if expected[ord].0 == target { SkipResult::Reached } else { SkipResult::OverStep };
+                            assert_eq!(skip_result, expected_result);
+                            assert_eq!((postings.doc(), postings.term_freq()), expected[ord]);
+                            cursor = Some(ord);
+                        }
+                        None => {
+                            assert_eq!(skip_result, SkipResult::End);
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn read_postings_with_read_ahead(segment_reader: &SegmentReader, term: &Term, read_ahead: ReadAhead) -> Vec<(DocId, u32, Vec<u32>)> {
+        let mut postings = segment_reader
+            .read_postings(term, SegmentPostingsOption::FreqAndPositions)
+            .unwrap()
+            .with_read_ahead(read_ahead);
+        let mut decoded = Vec::new();
+        while postings.advance() {
+            decoded.push((postings.doc(), postings.term_freq(), postings.positions().to_vec()));
+        }
+        decoded
+    }
+
+    #[test]
+    fn test_read_ahead_same_postings() {
+        let tempdir = TempDir::new("index").unwrap();
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create(tempdir.path(), schema_builder.build()).unwrap();
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            for i in 0..5_000 {
+                let mut text = "a ".repeat(i % 5 + 1);
+                if i % 3 == 0 {
+                    text.push_str("b c b");
+                }
+                if i % 1_000 == 0 {
+                    text.push_str(" d");
+                }
+                index_writer.add_document(doc!(text_field=>text));
+            }
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let segment_reader = searcher.segment_reader(0);
+        for text in &["a", "b", "c", "d"] {
+            let term = Term::from_field_text(text_field, text);
+            let expected = read_postings_with_read_ahead(&segment_reader, &term, ReadAhead::Disabled);
+            assert!(!expected.is_empty());
+            for &read_ahead in &[ReadAhead::Blocks(0), ReadAhead::Blocks(1), ReadAhead::Blocks(8), ReadAhead::Blocks(1_000)] {
+                assert_eq!(read_postings_with_read_ahead(&segment_reader, &term, read_ahead), expected);
+            }
+        }
+    }
+
     #[test]
     fn test_intersection() {
         {
@@ -218,6 +548,60 @@ mod tests {
             assert!(!intersection.advance());
         }
     }
+
+    #[test]
+    fn test_intersection_segment_postings() {
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            for i in 0..2_000 {
+                let mut text = String::new();
+                if i % 3 == 0 {
+                    text.push_str("a ");
+                }
+                if i % 5 == 0 || (i > 1_000 && i < 1_100) {
+                    text.push_str("b ");
+                }
+                if i % 11 == 0 {
+                    text.push_str("c");
+                }
+                index_writer.add_document(doc!(text_field=>text));
+            }
+            index_writer.commit().unwrap();
+            index_writer.delete_term(Term::from_field_text(text_field, "c"));
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let segment_reader = searcher.segment_reader(0);
+        let read_postings = |text: &str| {
+            segment_reader
+                .read_postings(&Term::from_field_text(text_field, text), SegmentPostingsOption::Freq)
+                .unwrap()
+        };
+        let expected: Vec<DocId> = (0..2_000u32)
+            .filter(|&i| i % 3 == 0 && (i % 5 == 0 || (i > 1_000 && i < 1_100)) && i % 11 != 0)
+            .collect();
+        {
+            let mut postings = read_postings("a");
+            assert!(postings.buffered_docs().is_empty());
+            // the document 0 is deleted.
+            assert!(postings.advance());
+            assert_eq!(postings.doc(), 3);
+            assert_eq!(postings.buffered_docs().len(), NUM_DOCS_PER_BLOCK - 2);
+            assert_eq!(postings.buffered_docs()[0], 6);
+        }
+        let mut intersection = IntersectionDocSet::from(vec!(read_postings("a"), read_postings("b")));
+        let mut docs = Vec::new();
+        while intersection.advance() {
+            // the underlying postings are positioned on the document.
+            assert!(intersection.docsets().iter().all(|postings| postings.doc() == intersection.doc()));
+            docs.push(intersection.doc());
+        }
+        assert_eq!(docs, expected);
+    }
     
     
     lazy_static! {
@@ -277,6 +661,41 @@ mod tests {
         });
     }    
     
+    fn bench_segment_postings_scan(b: &mut Bencher, read_ahead: ReadAhead) {
+        let tempdir = TempDir::new("index").unwrap();
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create(tempdir.path(), schema_builder.build()).unwrap();
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 100_000_000).unwrap();
+            for i in 0..200_000 {
+                index_writer.add_document(doc!(text_field=>"a ".repeat(i % 4 + 1)));
+            }
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let segment_reader = searcher.segment_reader(0);
+        let term = Term::from_field_text(text_field, "a");
+        b.iter(|| {
+            let mut segment_postings = segment_reader
+                .read_postings(&term, SegmentPostingsOption::Freq)
+                .unwrap()
+                .with_read_ahead(read_ahead);
+            while segment_postings.advance() {}
+        });
+    }
+
+    #[bench]
+    fn bench_segment_postings_scan_no_read_ahead(b: &mut Bencher) {
+        bench_segment_postings_scan(b, ReadAhead::Disabled);
+    }
+
+    #[bench]
+    fn bench_segment_postings_scan_read_ahead(b: &mut Bencher) {
+        bench_segment_postings_scan(b, ReadAhead::Blocks(8));
+    }
+
     #[bench]
     fn bench_segment_intersection(b: &mut Bencher) {
         let searcher = INDEX.searcher();
@@ -17,6 +17,9 @@ pub trait Postings: DocSet {
     /// Returns the list of positions of the term, expressed as a list of
     /// token ordinals.
     fn positions(&self) -> &[u32];
+    /// Returns the payload of the current document,
+    /// or `0` if the field does not have payloads.
+    fn payload(&self) -> u32;
 }
 
 impl<TPostings: Postings> Postings for Box<TPostings> {
@@ -29,6 +32,11 @@ impl<TPostings: Postings> Postings for Box<TPostings> {
         let unboxed: &TPostings = self.borrow();
         unboxed.positions()
     }
+
+    fn payload(&self) -> u32 {
+        let unboxed: &TPostings = self.borrow();
+        unboxed.payload()
+    }
 }
 
 impl<'a, TPostings: Postings> Postings for &'a mut TPostings {
@@ -41,4 +49,9 @@ impl<'a, TPostings: Postings> Postings for &'a mut TPostings {
         let unref: &TPostings = *self;
         unref.positions()
     }
+
+    fn payload(&self) -> u32 {
+        let unref: &TPostings = *self;
+        unref.payload()
+    }
 }
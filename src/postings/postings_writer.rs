@@ -1,13 +1,16 @@
 use DocId;
 use schema::Term;
-use schema::FieldValue;
 use postings::PostingsSerializer;
 use std::io;
 use postings::Recorder;
-use analyzer::SimpleTokenizer;
+use analyzer::Tokenizer;
 use schema::Field;
-use analyzer::StreamingIterator;
+use schema::MaxTermLength;
 use datastruct::stacker::{HashMap, Heap};
+use datastruct::stacker::FxBuildHasher;
+use std::hash::BuildHasher;
+use std::collections::BTreeMap;
+use std::mem;
 
 /// The `PostingsWriter` is in charge of receiving documenting
 /// and building a `Segment` in anonymous memory.
@@ -22,6 +25,13 @@ pub trait PostingsWriter {
     /// * heap - heap used to store the postings informations as well as the terms
     /// in the hashmap.
     fn suscribe(&mut self, doc: DocId, pos: u32, term: &Term, heap: &Heap);
+
+    /// Sets the payload of a term for a document.
+    ///
+    /// Documents have to be pushed in increasing order.
+    /// The payload is only written if the document
+    /// contains the term.
+    fn set_payload(&mut self, doc: DocId, term: &Term, payload: u32);
     
     /// Serializes the postings on disk.
     /// The actual serialization format is handled by the `PostingsSerializer`.
@@ -30,25 +40,61 @@ pub trait PostingsWriter {
     /// Closes all of the currently open `Recorder`'s.
     fn close(&mut self, heap: &Heap);
 
-    /// Tokenize a text and suscribe all of its token.
+    /// Returns true iff the term table holds as many terms
+    /// as it was sized for, given the capacity of the heap.
+    fn is_term_table_full(&self) -> bool;
+
+    /// Tokenize the `texts` of a field with `tokenizer` and suscribe all of their tokens.
+    ///
+    /// `token_buffer` is used by the tokenizer, and `term_buffer`
+    /// holds the terms being suscribed. Both are kept
+    /// by the caller to be reused from one document to another.
+    ///
+    /// At most `max_num_tokens` tokens are indexed. The following
+    /// tokens are dropped, and a warning is logged.
+    ///
+    /// Tokens longer than `max_term_length` are truncated or
+    /// dropped depending on its policy. Dropped tokens still
+    /// take a position, but are not counted as tokens.
     fn index_text<'a>(&mut self,
                       doc_id: DocId,
                       field: Field,
-                      field_values: &[&'a FieldValue],
+                      texts: &mut Iterator<Item=&'a str>,
+                      tokenizer: &Tokenizer,
+                      token_buffer: &mut String,
+                      term_buffer: &mut Term,
+                      max_num_tokens: u32,
+                      max_term_length: MaxTermLength,
                       heap: &Heap)
                       -> u32 {
         let mut pos = 0u32;
         let mut num_tokens: u32 = 0u32;
-        let mut term = Term::allocate(field, 100);
-        for field_value in field_values {
-            let mut tokens = SimpleTokenizer.tokenize(field_value.value().text());
-            // right now num_tokens and pos are redundant, but it should
-            // change when we get proper analyzers
-            while let Some(token) = tokens.next() {
-                term.set_text(token);
-                self.suscribe(doc_id, pos, &term, heap);
-                pos += 1u32;
-                num_tokens += 1u32;
+        term_buffer.set_field(field);
+        let mut truncated = false;
+        for text in texts {
+            let buffer = mem::replace(token_buffer, String::new());
+            let mut token_stream = tokenizer.token_stream_with_buffer(text, buffer);
+            // number of positions taken by the tokens of the field value.
+            let mut num_positions = 0u32;
+            while token_stream.advance() {
+                if num_tokens == max_num_tokens {
+                    warn!("Field {:?} of doc {} has more than {} tokens. The remaining tokens are not indexed.",
+                          field, doc_id, max_num_tokens);
+                    truncated = true;
+                    break;
+                }
+                let token = token_stream.token();
+                if let Some(term_text) = max_term_length.apply(&token.text) {
+                    term_buffer.set_text(term_text);
+                    self.suscribe(doc_id, pos + token.position, term_buffer, heap);
+                    num_tokens += 1u32;
+                }
+                num_positions = token.position + 1;
+            }
+            *token_buffer = token_stream.into_buffer();
+            pos += num_positions;
+            if truncated {
+                break;
             }
             pos += 1;
             // THIS is to avoid phrase query accross field repetition.
@@ -60,8 +106,20 @@ pub trait PostingsWriter {
 
 /// The `SpecializedPostingsWriter` is just here to remove dynamic
 /// dispatch to the recorder information.
-pub struct SpecializedPostingsWriter<'a, Rec: Recorder + 'static> {
-    term_index: HashMap<'a, Rec>,
+///
+/// `H` defines the hash function used to index the terms.
+pub struct SpecializedPostingsWriter<'a, Rec: Recorder + 'static, H: BuildHasher = FxBuildHasher> {
+    term_index: HashMap<'a, Rec, H>,
+    // payloads are rare enough to be kept
+    // out of the heap.
+    payloads: BTreeMap<Term, Vec<(DocId, u32)>>,
+}
+
+/// Returns the number of terms the term table of a field
+/// can hold when its postings are written in a heap of
+/// `heap_capacity` bytes.
+pub fn term_table_capacity(heap_capacity: u32) -> usize {
+    (1 << hashmap_size_in_bits(heap_capacity)) / 2
 }
 
 /// Given a `Heap` size, computes a relevant size for the `HashMap`.
@@ -81,9 +139,7 @@ fn hashmap_size_in_bits(heap_capacity: u32) -> usize {
 impl<'a, Rec: Recorder + 'static> SpecializedPostingsWriter<'a, Rec> {
     /// constructor
     pub fn new(heap: &'a Heap) -> SpecializedPostingsWriter<'a, Rec> {
-        let capacity = heap.capacity();
-        let hashmap_size = hashmap_size_in_bits(capacity);
-        SpecializedPostingsWriter { term_index: HashMap::new(hashmap_size, heap) }
+        SpecializedPostingsWriter::with_hasher(heap, FxBuildHasher::default())
     }
 
     /// Builds a `SpecializedPostingsWriter` storing its data in a heap.
@@ -92,13 +148,35 @@ impl<'a, Rec: Recorder + 'static> SpecializedPostingsWriter<'a, Rec> {
     }
 }
 
-impl<'a, Rec: Recorder + 'static> PostingsWriter for SpecializedPostingsWriter<'a, Rec> {
+impl<'a, Rec: Recorder + 'static, H: BuildHasher + 'a> SpecializedPostingsWriter<'a, Rec, H> {
+    /// Creates a `SpecializedPostingsWriter` hashing its
+    /// terms with the given `BuildHasher`.
+    pub fn with_hasher(heap: &'a Heap, hash_builder: H) -> SpecializedPostingsWriter<'a, Rec, H> {
+        let capacity = heap.capacity();
+        let hashmap_size = hashmap_size_in_bits(capacity);
+        SpecializedPostingsWriter {
+            term_index: HashMap::with_hasher(hashmap_size, heap, hash_builder),
+            payloads: BTreeMap::new(),
+        }
+    }
+
+    /// Builds a boxed `SpecializedPostingsWriter` hashing its
+    /// terms with the given `BuildHasher`.
+    pub fn new_boxed_with_hasher(heap: &'a Heap, hash_builder: H) -> Box<PostingsWriter + 'a> {
+        Box::new(SpecializedPostingsWriter::<Rec, H>::with_hasher(heap, hash_builder))
+    }
+}
+
+impl<'a, Rec: Recorder + 'static, H: BuildHasher> PostingsWriter for SpecializedPostingsWriter<'a, Rec, H> {
     fn close(&mut self, heap: &Heap) {
         for recorder in self.term_index.values_mut() {
             recorder.close_doc(heap);
         }
     }
 
+    fn is_term_table_full(&self) -> bool {
+        self.term_index.len() >= self.term_index.capacity()
+    }
 
     #[inline]
     fn suscribe(&mut self, doc: DocId, position: u32, term: &Term, heap: &Heap) {
@@ -113,6 +191,16 @@ impl<'a, Rec: Recorder + 'static> PostingsWriter for SpecializedPostingsWriter<'
         recorder.record_position(position, heap);
     }
 
+    fn set_payload(&mut self, doc: DocId, term: &Term, payload: u32) {
+        let term_payloads = self.payloads
+            .entry(term.clone())
+            .or_insert_with(Vec::new);
+        if term_payloads.last().map(|&(last_doc, _)| last_doc == doc).unwrap_or(false) {
+            term_payloads.pop();
+        }
+        term_payloads.push((doc, payload));
+    }
+
     fn serialize(&self, serializer: &mut PostingsSerializer, heap: &Heap) -> io::Result<()> {
         let mut term_offsets: Vec<(&[u8], (u32, &Rec))> = self.term_index
             .iter()
@@ -123,6 +211,9 @@ impl<'a, Rec: Recorder + 'static> PostingsWriter for SpecializedPostingsWriter<'
             // sadly we are required to copy the data
             term.set_content(term_bytes);
             try!(serializer.new_term(&term));
+            if let Some(term_payloads) = self.payloads.get(&term) {
+                serializer.set_term_payloads(term_payloads);
+            }
             try!(recorder.serialize(addr, serializer, heap));
             try!(serializer.close_term());
         }
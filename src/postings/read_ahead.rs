@@ -0,0 +1,51 @@
+/// Object describing how far ahead of the block being decoded
+/// a `SegmentPostings` asks for its data to be loaded.
+///
+/// Sequential scans of long postings, as during merges,
+/// otherwise stall on each page of an mmapped segment
+/// that is not in the page cache yet.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReadAhead {
+    /// The data is loaded as it is decoded.
+    Disabled,
+    /// The data of the next `N` blocks is prefetched
+    /// while the current block is decoded.
+    Blocks(usize),
+}
+
+impl Default for ReadAhead {
+    fn default() -> ReadAhead {
+        ReadAhead::Disabled
+    }
+}
+
+#[cfg(all(unix, feature="simdcompression"))]
+const PAGE_SIZE: usize = 4_096;
+
+/// Advises the OS that `data` is about to be read,
+/// so that it loads its pages in the background.
+///
+/// This is a hint: it is a no-op on the platforms
+/// without `madvise`, and its errors are ignored.
+#[cfg(all(unix, feature="simdcompression"))]
+pub fn advise_will_need(data: &[u8]) {
+    use libc;
+    if data.is_empty() {
+        return;
+    }
+    // madvise requires an address aligned on a page.
+    let start = data.as_ptr() as usize;
+    let aligned_start = start - start % PAGE_SIZE;
+    let len = data.len() + (start - aligned_start);
+    unsafe {
+        libc::madvise(aligned_start as *mut libc::c_void, len, libc::MADV_WILLNEED);
+    }
+}
+
+/// Advises the OS that `data` is about to be read,
+/// so that it loads its pages in the background.
+///
+/// This is a hint: it is a no-op on the platforms
+/// without `madvise`, and its errors are ignored.
+#[cfg(not(all(unix, feature="simdcompression")))]
+pub fn advise_will_need(_data: &[u8]) {}
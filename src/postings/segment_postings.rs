@@ -1,7 +1,10 @@
 use compression::{NUM_DOCS_PER_BLOCK, BlockDecoder, VIntDecoder};
+use compression::{BlockSkipEntry, read_skip_entries, compressed_block_len};
 use DocId;
-use postings::{Postings, FreqHandler, DocSet, HasLen};
+use postings::{Postings, FreqHandler, DocSet, HasLen, ReadAhead, SkipResult};
+use super::read_ahead::advise_will_need;
 use std::num::Wrapping;
+use std::cmp::{self, Ordering};
 use fastfield::delete::DeleteBitSet;
 
 
@@ -12,28 +15,108 @@ const EMPTY_DATA: [u8; 0] = [0u8; 0];
 ///
 /// As we iterate through the `SegmentPostings`, the frequencies are optionally decoded.
 /// Positions on the other hand, are optionally entirely decoded upfront.
+///
+/// Given the skip entries of its full blocks, `.skip_next(...)` jumps
+/// directly to the block containing its target.
 pub struct SegmentPostings<'a> {
     len: usize,
     doc_offset: u32,
     block_decoder: BlockDecoder,
     freq_handler: FreqHandler,
+    payload_decoder: Option<BlockDecoder>,
+    data: &'a [u8],
     remaining_data: &'a [u8],
+    skip_data: &'a [u8],
+    skip_entries: Vec<BlockSkipEntry>,
     cur: Wrapping<usize>,
     delete_bitset: DeleteBitSet,
+    read_ahead: ReadAhead,
+    // length of the remaining data once the prefetched data is consumed.
+    prefetched_until: usize,
 }
 
 impl<'a> SegmentPostings<'a> {
     fn load_next_block(&mut self) {
         let num_remaining_docs = self.len - self.cur.0;
         if num_remaining_docs >= NUM_DOCS_PER_BLOCK {
+            let block_start_len = self.remaining_data.len();
             self.remaining_data = self.block_decoder
                 .uncompress_block_sorted(self.remaining_data, self.doc_offset);
-            self.remaining_data = self.freq_handler.read_freq_block(self.remaining_data);
-            self.doc_offset = self.block_decoder.output(NUM_DOCS_PER_BLOCK - 1);
+            self.load_block_freqs_and_payloads(block_start_len);
         } else {
             self.remaining_data = self.block_decoder
                 .uncompress_vint_sorted(self.remaining_data, self.doc_offset, num_remaining_docs);
-            self.freq_handler.read_freq_vint(self.remaining_data, num_remaining_docs);
+            self.remaining_data = self.freq_handler.read_freq_vint(self.remaining_data, num_remaining_docs);
+            if let Some(ref mut payload_decoder) = self.payload_decoder {
+                self.remaining_data = payload_decoder.uncompress_vint_unsorted(self.remaining_data, num_remaining_docs);
+            }
+        }
+    }
+
+    // decodes the blocks of term frequencies and payloads following
+    // the block of doc ids that was just decoded, `block_start_len`
+    // being the length of the remaining data before the latter.
+    fn load_block_freqs_and_payloads(&mut self, block_start_len: usize) {
+        self.remaining_data = self.freq_handler.read_freq_block(self.remaining_data);
+        if let Some(ref mut payload_decoder) = self.payload_decoder {
+            self.remaining_data = payload_decoder.uncompress_block_unsorted(self.remaining_data);
+        }
+        self.doc_offset = self.block_decoder.output(NUM_DOCS_PER_BLOCK - 1);
+        let num_block_bytes = block_start_len - self.remaining_data.len();
+        self.read_ahead(num_block_bytes);
+    }
+
+    // positions the postings on the first document greater or equal
+    // to `target` (or on the last document of the full blocks if there
+    // is none) using the skip entries, if this skips at least a block.
+    //
+    // Returns false if the postings were left untouched.
+    fn seek(&mut self, target: DocId) -> bool {
+        if self.skip_data.is_empty() || self.freq_handler.has_positions() {
+            return false;
+        }
+        if self.skip_entries.is_empty() {
+            self.skip_entries = read_skip_entries(self.skip_data);
+        }
+        if self.cur.0 == usize::max_value() {
+            if target <= self.skip_entries[0].last_val {
+                return false;
+            }
+        } else if self.cur.0 / NUM_DOCS_PER_BLOCK >= self.skip_entries.len() || target <= self.doc_offset {
+            // within the tail, or the target is within the current block.
+            return false;
+        }
+        let last_val = self.skip_entries[self.skip_entries.len() - 1].last_val;
+        let seek_target = cmp::min(target, last_val);
+        let block_ord = match self.skip_entries.binary_search_by(|skip_entry| skip_entry.last_val.cmp(&seek_target)) {
+            Ok(block_ord) | Err(block_ord) => block_ord,
+        };
+        let block_offset = self.skip_entries[block_ord].offset;
+        if block_offset >= self.data.len() {
+            return false;
+        }
+        let idx = match self.block_decoder.seek(self.data, &self.skip_entries, seek_target) {
+            Some((_, idx)) => idx,
+            None => return false,
+        };
+        let block_data = &self.data[block_offset..];
+        self.remaining_data = &block_data[compressed_block_len(block_data)..];
+        self.load_block_freqs_and_payloads(block_data.len());
+        self.cur = Wrapping(block_ord * NUM_DOCS_PER_BLOCK + idx);
+        true
+    }
+
+    // prefetches the next blocks, estimating their size with
+    // the size of the last block, once less than a block
+    // of prefetched data is left.
+    fn read_ahead(&mut self, num_block_bytes: usize) {
+        if let ReadAhead::Blocks(num_blocks) = self.read_ahead {
+            let remaining_len = self.remaining_data.len();
+            if remaining_len < self.prefetched_until + num_block_bytes {
+                let num_prefetched_bytes = cmp::min(remaining_len, num_blocks * num_block_bytes);
+                advise_will_need(&self.remaining_data[..num_prefetched_bytes]);
+                self.prefetched_until = remaining_len - num_prefetched_bytes;
+            }
         }
     }
 
@@ -52,12 +135,47 @@ impl<'a> SegmentPostings<'a> {
             doc_offset: 0,
             block_decoder: BlockDecoder::new(),
             freq_handler: freq_handler,
+            payload_decoder: None,
+            data: data,
             remaining_data: data,
+            skip_data: &EMPTY_DATA,
+            skip_entries: Vec::new(),
             cur: Wrapping(usize::max_value()),
             delete_bitset: delete_bitset.clone(),
+            read_ahead: ReadAhead::Disabled,
+            prefetched_until: data.len(),
         }
     }
 
+    /// Decodes the payloads of the documents, which are
+    /// expected to follow their term frequencies.
+    ///
+    /// The `FreqHandler` must then decode the term frequencies
+    /// if the field has some.
+    pub fn with_payloads(mut self) -> SegmentPostings<'a> {
+        self.payload_decoder = Some(BlockDecoder::new());
+        self
+    }
+
+    /// Lets `.skip_next(...)` seek through the skip entries
+    /// of the full blocks, written right after the posting list.
+    ///
+    /// Seeking is disabled if the positions are decoded.
+    pub fn with_skip_data(mut self, skip_data: &'a [u8]) -> SegmentPostings<'a> {
+        self.skip_data = skip_data;
+        self
+    }
+
+    /// Prefetches the data of the blocks following the
+    /// one being decoded, see `ReadAhead`.
+    ///
+    /// This only pays off for long sequential scans,
+    /// as the decoded postings are the same.
+    pub fn with_read_ahead(mut self, read_ahead: ReadAhead) -> SegmentPostings<'a> {
+        self.read_ahead = read_ahead;
+        self
+    }
+
     /// Returns an empty segment postings object
     pub fn empty() -> SegmentPostings<'static> {
         SegmentPostings {
@@ -65,9 +183,15 @@ impl<'a> SegmentPostings<'a> {
             doc_offset: 0,
             block_decoder: BlockDecoder::new(),
             freq_handler: FreqHandler::new_without_freq(),
+            payload_decoder: None,
+            data: &EMPTY_DATA,
             remaining_data: &EMPTY_DATA,
+            skip_data: &EMPTY_DATA,
+            skip_entries: Vec::new(),
             delete_bitset: DeleteBitSet::empty(),
             cur: Wrapping(usize::max_value()),
+            read_ahead: ReadAhead::Disabled,
+            prefetched_until: 0,
         }
     }
 
@@ -98,10 +222,43 @@ impl<'a> DocSet for SegmentPostings<'a> {
         }
     }
 
+    fn skip_next(&mut self, target: DocId) -> SkipResult {
+        if self.seek(target) {
+            if self.delete_bitset.is_deleted(self.doc()) && !self.advance() {
+                return SkipResult::End;
+            }
+        } else if !self.advance() {
+            return SkipResult::End;
+        }
+        loop {
+            match self.doc().cmp(&target) {
+                Ordering::Less => {
+                    if !self.advance() {
+                        return SkipResult::End;
+                    }
+                }
+                Ordering::Equal => return SkipResult::Reached,
+                Ordering::Greater => return SkipResult::OverStep,
+            }
+        }
+    }
+
     #[inline]
     fn doc(&self) -> DocId {
         self.block_decoder.output(self.index_within_block())
     }
+
+    fn buffered_docs(&self) -> &[DocId] {
+        if self.cur.0 >= self.len {
+            // not started, or entirely consumed.
+            return &[];
+        }
+        &self.block_decoder.output_array()[self.index_within_block() + 1..]
+    }
+
+    fn size_hint(&self) -> u32 {
+        self.len as u32
+    }
 }
 
 impl<'a> HasLen for SegmentPostings<'a> {
@@ -118,4 +275,11 @@ impl<'a> Postings for SegmentPostings<'a> {
     fn positions(&self) -> &[u32] {
         self.freq_handler.positions(self.index_within_block())
     }
+
+    fn payload(&self) -> u32 {
+        self.payload_decoder
+            .as_ref()
+            .map(|payload_decoder| payload_decoder.output(self.index_within_block()))
+            .unwrap_or(0u32)
+    }
 }
@@ -8,13 +8,12 @@ use schema::FieldType;
 use schema::Schema;
 use schema::TextIndexingOptions;
 use directory::WritePtr;
-use compression::{NUM_DOCS_PER_BLOCK, BlockEncoder, CompositeEncoder};
+use compression::{BlockPostingsWriter, CompositeEncoder};
 use DocId;
 use core::Segment;
 use std::io;
 use core::SegmentComponent;
 use std::io::Write;
-use compression::VIntEncoder;
 use common::VInt;
 use common::BinarySerializable;
 
@@ -50,18 +49,17 @@ use common::BinarySerializable;
 /// [available here](https://fulmicoton.gitbooks.io/tantivy-doc/content/inverted-index.html).
 pub struct PostingsSerializer {
     terms_fst_builder: FstMapBuilder<WritePtr, TermInfo>,
-    postings_write: WritePtr,
+    postings_writer: BlockPostingsWriter<WritePtr>,
     positions_write: WritePtr,
-    written_bytes_postings: usize,
     written_bytes_positions: usize,
-    last_doc_id_encoded: u32,
     positions_encoder: CompositeEncoder,
-    block_encoder: BlockEncoder,
-    doc_ids: Vec<DocId>,
-    term_freqs: Vec<u32>,
     position_deltas: Vec<u32>,
+    // payloads of the current term, given by the postings writer.
+    term_payloads: Vec<(DocId, u32)>,
+    term_payloads_cursor: usize,
     schema: Schema,
     text_indexing_options: TextIndexingOptions,
+    payloads_enabled: bool,
     term_open: bool,
     current_term_info: TermInfo,
 }
@@ -76,18 +74,16 @@ impl PostingsSerializer {
         let terms_fst_builder = try!(FstMapBuilder::new(terms_write));
         Ok(PostingsSerializer {
             terms_fst_builder: terms_fst_builder,
-            postings_write: postings_write,
+            postings_writer: BlockPostingsWriter::new(postings_write),
             positions_write: positions_write,
-            written_bytes_postings: 0,
             written_bytes_positions: 0,
-            last_doc_id_encoded: 0u32,
             positions_encoder: CompositeEncoder::new(),
-            block_encoder: BlockEncoder::new(),
-            doc_ids: Vec::new(),
-            term_freqs: Vec::new(),
             position_deltas: Vec::new(),
+            term_payloads: Vec::new(),
+            term_payloads_cursor: 0,
             schema: schema,
             text_indexing_options: TextIndexingOptions::Unindexed,
+            payloads_enabled: false,
             term_open: false,
             current_term_info: TermInfo::default(),
         })
@@ -107,6 +103,7 @@ impl PostingsSerializer {
 
     fn load_indexing_options(&mut self, field: Field) {
         let field_entry: &FieldEntry = self.schema.get_field_entry(field);
+        self.payloads_enabled = field_entry.has_payloads();
         self.text_indexing_options = match *field_entry.field_type() {
             FieldType::Str(ref text_options) => text_options.get_indexing_options(),
             FieldType::U32(ref u32_options) => {
@@ -116,6 +113,20 @@ impl PostingsSerializer {
                     TextIndexingOptions::Untokenized
                 }
             }
+            FieldType::Date(ref date_options) => {
+                if date_options.is_indexed() {
+                    TextIndexingOptions::Unindexed
+                } else {
+                    TextIndexingOptions::Untokenized
+                }
+            }
+            FieldType::Json(ref json_options) => {
+                if json_options.is_indexed() {
+                    TextIndexingOptions::TokenizedWithFreq
+                } else {
+                    TextIndexingOptions::Unindexed
+                }
+            }
         };
     }
 
@@ -129,13 +140,12 @@ impl PostingsSerializer {
         }
         self.term_open = true;
         self.load_indexing_options(term.field());
-        self.doc_ids.clear();
-        self.last_doc_id_encoded = 0;
-        self.term_freqs.clear();
         self.position_deltas.clear();
+        self.term_payloads.clear();
+        self.term_payloads_cursor = 0;
         self.current_term_info = TermInfo {
             doc_freq: 0,
-            postings_offset: self.written_bytes_postings as u32,
+            postings_offset: self.postings_writer.written_bytes() as u32,
             positions_offset: self.written_bytes_positions as u32,
         };
         self.terms_fst_builder.insert_key(term.as_slice())
@@ -143,38 +153,18 @@ impl PostingsSerializer {
 
     /// Finish the serialization for this term postings.
     ///
-    /// If the current block is incomplete, it need to be encoded
-    /// using `VInt` encoding.
+    /// If the current block is incomplete, the doc ids, term
+    /// frequencies and payloads waiting to be written are
+    /// encoded using `VInt` encoding.
     pub fn close_term(&mut self) -> io::Result<()> {
         if self.term_open {
 
             self.terms_fst_builder.insert_value(&self.current_term_info)?;
 
-            if !self.doc_ids.is_empty() {
-                // we have doc ids waiting to be written
-                // this happens when the number of doc ids is
-                // not a perfect multiple of our block size.
-                //
-                // In that case, the remaining part is encoded
-                // using variable int encoding.
-                {
-                    let block_encoded = self.block_encoder
-                        .compress_vint_sorted(&self.doc_ids, self.last_doc_id_encoded);
-                    self.written_bytes_postings += block_encoded.len();
-                    try!(self.postings_write.write_all(block_encoded));
-                    self.doc_ids.clear();
-                }
-                // ... Idem for term frequencies
-                if self.text_indexing_options.is_termfreq_enabled() {
-                    let block_encoded = self.block_encoder
-                        .compress_vint_unsorted(&self.term_freqs[..]);
-                    for num in block_encoded {
-                        self.written_bytes_postings +=
-                            try!(num.serialize(&mut self.postings_write));
-                    }
-                    self.term_freqs.clear();
-                }
-            }
+            try!(self.postings_writer.finish());
+            // the skip entries of the full blocks are written right after
+            // the list, so that `SegmentPostings` can seek through them.
+            try!(self.postings_writer.write_skip_entries());
             // On the other hand, positions are entirely buffered until the
             // end of the term, at which point they are compressed and written.
             if self.text_indexing_options.is_position_enabled() {
@@ -192,6 +182,59 @@ impl PostingsSerializer {
     }
 
 
+    /// Writes the postings of a new term from raw, already
+    /// compressed bytes.
+    ///
+    /// The bytes are written verbatim. They are typically obtained from 
+    /// another segment via `SegmentReader::raw_postings_bytes` and
+    /// `SegmentReader::raw_positions_bytes`, and the field of the term
+    /// must be indexed with the same options in both segments.
+    ///
+    /// As for `new_term(...)`, terms need to be pushed in lexicographical order.
+    pub fn write_raw_postings(&mut self,
+                              term: &Term,
+                              doc_freq: u32,
+                              postings_bytes: &[u8],
+                              positions_bytes: &[u8])
+                              -> io::Result<()> {
+        try!(self.new_term(term));
+        self.current_term_info.doc_freq = doc_freq;
+        try!(self.terms_fst_builder.insert_value(&self.current_term_info));
+        try!(self.postings_writer.write_raw(postings_bytes));
+        try!(self.positions_write.write_all(positions_bytes));
+        self.written_bytes_positions += positions_bytes.len();
+        self.term_open = false;
+        Ok(())
+    }
+
+    /// Sets the payloads of the documents of the current term,
+    /// as `(doc, payload)` sorted by doc.
+    ///
+    /// They are used by `write_doc`. Documents without
+    /// a payload get a payload of `0`.
+    pub fn set_term_payloads(&mut self, term_payloads: &[(DocId, u32)]) {
+        self.term_payloads.clear();
+        self.term_payloads.extend_from_slice(term_payloads);
+        self.term_payloads_cursor = 0;
+    }
+
+    /// Returns the payload of `doc_id` set with `set_term_payloads`.
+    ///
+    /// Documents are looked up in increasing order.
+    fn term_payload(&mut self, doc_id: DocId) -> u32 {
+        while self.term_payloads_cursor < self.term_payloads.len() {
+            let (doc, payload) = self.term_payloads[self.term_payloads_cursor];
+            if doc > doc_id {
+                break;
+            }
+            self.term_payloads_cursor += 1;
+            if doc == doc_id {
+                return payload;
+            }
+        }
+        0u32
+    }
+
     /// Serialize the information that a document contains the current term,
     /// its term frequency, and the position deltas.
     ///
@@ -201,46 +244,43 @@ impl PostingsSerializer {
     ///
     /// Term frequencies and positions may be ignored by the serializer depending
     /// on the configuration of the field in the `Schema`.
+    ///
+    /// If the field has payloads, the payload of the document is
+    /// the one given to `set_term_payloads`.
     pub fn write_doc(&mut self,
                      doc_id: DocId,
                      term_freq: u32,
                      position_deltas: &[u32])
                      -> io::Result<()> {
+        let payload = self.term_payload(doc_id);
+        self.write_doc_with_payload(doc_id, term_freq, position_deltas, payload)
+    }
+
+    /// Same as `write_doc`, with an explicit payload.
+    ///
+    /// The payload is ignored if the field does not have payloads.
+    pub fn write_doc_with_payload(&mut self,
+                                  doc_id: DocId,
+                                  term_freq: u32,
+                                  position_deltas: &[u32],
+                                  payload: u32)
+                                  -> io::Result<()> {
         self.current_term_info.doc_freq += 1;
-        self.doc_ids.push(doc_id);
-        if self.text_indexing_options.is_termfreq_enabled() {
-            self.term_freqs.push(term_freq as u32);
-        }
         if self.text_indexing_options.is_position_enabled() {
             self.position_deltas.extend_from_slice(position_deltas);
         }
-        if self.doc_ids.len() == NUM_DOCS_PER_BLOCK {
-            {
-                // encode the doc ids
-                let block_encoded: &[u8] = self.block_encoder
-                    .compress_block_sorted(&self.doc_ids, self.last_doc_id_encoded);
-                self.last_doc_id_encoded = self.doc_ids[self.doc_ids.len() - 1];
-                try!(self.postings_write.write_all(block_encoded));
-                self.written_bytes_postings += block_encoded.len();
-            }
-            if self.text_indexing_options.is_termfreq_enabled() {
-                // encode the term_freqs
-                let block_encoded: &[u8] = self.block_encoder
-                    .compress_block_unsorted(&self.term_freqs);
-                try!(self.postings_write.write_all(block_encoded));
-                self.written_bytes_postings += block_encoded.len();
-                self.term_freqs.clear();
-            }
-            self.doc_ids.clear();
-        }
-        Ok(())
+        // the blocks of doc ids are interleaved with the blocks
+        // of term frequencies and payloads by the postings writer.
+        let term_freq_opt = if self.text_indexing_options.is_termfreq_enabled() { Some(term_freq) } else { None };
+        let payload_opt = if self.payloads_enabled { Some(payload) } else { None };
+        self.postings_writer.record_with(doc_id, term_freq_opt, payload_opt)
     }
 
     /// Closes the serializer.
     pub fn close(mut self) -> io::Result<()> {
         try!(self.close_term());
         try!(self.terms_fst_builder.finish());
-        try!(self.postings_write.flush());
+        try!(self.postings_writer.flush());
         try!(self.positions_write.flush());
         Ok(())
     }
@@ -0,0 +1,148 @@
+use DocId;
+use postings::{DocSet, HasLen, SkipResult};
+use std::sync::Arc;
+use std::num::Wrapping;
+
+
+/// `DocSet` over a sorted vector of doc ids,
+/// typically materialized with `DocSet::collect_into_vec`.
+///
+/// The vector is shared between the `VecDocSet` and its replays,
+/// so that an expensive `DocSet` can be iterated
+/// several times at the cost of a single evaluation.
+pub struct VecDocSet {
+    doc_ids: Arc<Vec<DocId>>,
+    cursor: Wrapping<usize>,
+}
+
+impl From<Vec<DocId>> for VecDocSet {
+    /// Creates a `VecDocSet` from a sorted vector of doc ids.
+    ///
+    /// # Panics
+    /// Panics if the doc ids are not strictly increasing.
+    fn from(doc_ids: Vec<DocId>) -> VecDocSet {
+        assert!(doc_ids.windows(2).all(|pair| pair[0] < pair[1]),
+                "The doc ids of a VecDocSet must be sorted and unique.");
+        VecDocSet {
+            doc_ids: Arc::new(doc_ids),
+            cursor: Wrapping(usize::max_value()),
+        }
+    }
+}
+
+impl VecDocSet {
+
+    /// Returns a new `VecDocSet` over the same doc ids,
+    /// positioned before the first document.
+    ///
+    /// The doc ids are not copied.
+    pub fn replay(&self) -> VecDocSet {
+        VecDocSet {
+            doc_ids: self.doc_ids.clone(),
+            cursor: Wrapping(usize::max_value()),
+        }
+    }
+
+    /// Returns the doc ids of the `DocSet`.
+    pub fn doc_ids(&self) -> &[DocId] {
+        &self.doc_ids
+    }
+}
+
+impl DocSet for VecDocSet {
+    fn advance(&mut self) -> bool {
+        self.cursor += Wrapping(1);
+        self.doc_ids.len() > self.cursor.0
+    }
+
+    fn skip_next(&mut self, target: DocId) -> SkipResult {
+        let start = (self.cursor + Wrapping(1)).0;
+        if start >= self.doc_ids.len() {
+            self.cursor = Wrapping(self.doc_ids.len());
+            return SkipResult::End;
+        }
+        match self.doc_ids[start..].binary_search(&target) {
+            Ok(offset) => {
+                self.cursor = Wrapping(start + offset);
+                SkipResult::Reached
+            }
+            Err(offset) => {
+                self.cursor = Wrapping(start + offset);
+                if self.cursor.0 == self.doc_ids.len() {
+                    SkipResult::End
+                } else {
+                    SkipResult::OverStep
+                }
+            }
+        }
+    }
+
+    fn doc(&self) -> DocId {
+        self.doc_ids[self.cursor.0]
+    }
+
+    fn size_hint(&self) -> u32 {
+        self.doc_ids.len() as u32
+    }
+}
+
+impl HasLen for VecDocSet {
+    fn len(&self) -> usize {
+        self.doc_ids.len()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use DocId;
+    use postings::{DocSet, IntersectionDocSet, SkipResult, VecPostings};
+
+    #[test]
+    fn test_vec_docset() {
+        let make_filter = || {
+            let left = VecPostings::from((0u32..1000u32).map(|doc| doc * 2).collect::<Vec<DocId>>());
+            let right = VecPostings::from((0u32..1000u32).map(|doc| doc * 3).collect::<Vec<DocId>>());
+            IntersectionDocSet::from(vec!(left, right))
+        };
+        let mut live_docs = Vec::new();
+        let mut filter = make_filter();
+        while let Some(doc) = filter.next() {
+            live_docs.push(doc);
+        }
+        let doc_ids = make_filter().collect_into_vec();
+        assert_eq!(doc_ids, live_docs);
+        assert_eq!(doc_ids.len(), 334);
+
+        let mut vec_docset = VecDocSet::from(doc_ids);
+        assert_eq!(vec_docset.size_hint(), 334);
+        let mut replay = vec_docset.replay();
+        assert_eq!(vec_docset.collect_into_vec(), live_docs);
+        assert!(!vec_docset.advance());
+        assert_eq!(replay.collect_into_vec(), live_docs);
+        assert_eq!(vec_docset.replay().doc_ids(), &live_docs[..]);
+
+        let mut replay = vec_docset.replay();
+        assert_eq!(replay.skip_next(0), SkipResult::Reached);
+        assert_eq!(replay.doc(), 0);
+        // skipping always advances.
+        assert_eq!(replay.skip_next(0), SkipResult::OverStep);
+        assert_eq!(replay.doc(), 6);
+        assert_eq!(replay.skip_next(13), SkipResult::OverStep);
+        assert_eq!(replay.doc(), 18);
+        assert_eq!(replay.skip_next(600), SkipResult::Reached);
+        assert!(replay.advance());
+        assert_eq!(replay.doc(), 606);
+        assert_eq!(replay.skip_next(2000), SkipResult::End);
+        assert!(!replay.advance());
+        assert!(VecDocSet::from(Vec::new()).collect_into_vec().is_empty());
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_vec_docset_unsorted() {
+        VecDocSet::from(vec!(3, 1));
+    }
+}
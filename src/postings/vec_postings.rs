@@ -10,7 +10,7 @@ const EMPTY_ARRAY: [u32; 0] = [];
 /// `VecPostings` only exist for testing purposes.
 ///
 /// Term frequencies always return 1.
-/// No positions are returned.
+/// No positions are returned, and payloads are 0.
 pub struct VecPostings {
     doc_ids: Vec<DocId>,
     cursor: Wrapping<usize>,
@@ -34,6 +34,13 @@ impl DocSet for VecPostings {
     fn doc(&self) -> DocId {
         self.doc_ids[self.cursor.0]
     }
+
+    fn buffered_docs(&self) -> &[DocId] {
+        if self.cursor.0 >= self.doc_ids.len() {
+            return &[];
+        }
+        &self.doc_ids[self.cursor.0 + 1..]
+    }
 }
 
 impl HasLen for VecPostings {
@@ -50,6 +57,10 @@ impl Postings for VecPostings {
     fn positions(&self) -> &[u32] {
         &EMPTY_ARRAY
     }
+
+    fn payload(&self) -> u32 {
+        0u32
+    }
 }
 
 #[cfg(test)]
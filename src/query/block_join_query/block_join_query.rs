@@ -0,0 +1,72 @@
+use query::Query;
+use query::Weight;
+use query::QueryCost;
+use core::searcher::Searcher;
+use super::ToParentBlockJoinWeight;
+use std::any::Any;
+use Result;
+use schema::Term;
+
+
+/// `ToParentBlockJoinQuery` matches the parents of
+/// the documents matching a child query.
+///
+/// It relies on the documents being indexed in blocks,
+/// with `IndexWriter::add_document_block`: each block consists of
+/// child documents, followed by their parent.
+/// The parents are marked at index time, with a term that only
+/// parent documents contain. The parent query matches
+/// these documents, typically with a `TermQuery` on this term.
+///
+/// A parent matches if at least one of its children
+/// matches the child query. Its score is the highest score
+/// of its matching children.
+/// Parents matching the child query are ignored, and so are
+/// the children that do not belong to any parent.
+#[derive(Debug)]
+pub struct ToParentBlockJoinQuery {
+    child_query: Box<Query>,
+    parent_query: Box<Query>,
+}
+
+impl ToParentBlockJoinQuery {
+
+    /// Creates a new `ToParentBlockJoinQuery`.
+    ///
+    /// * child_query - the query matching the child documents
+    /// * parent_query - the query matching all of the parent documents
+    pub fn new(child_query: Box<Query>, parent_query: Box<Query>) -> ToParentBlockJoinQuery {
+        ToParentBlockJoinQuery {
+            child_query: child_query,
+            parent_query: parent_query,
+        }
+    }
+}
+
+impl Query for ToParentBlockJoinQuery {
+
+    fn as_any(&self) -> &Any {
+        self
+    }
+
+    fn query_terms(&self, terms: &mut Vec<Term>) {
+        self.child_query.query_terms(terms);
+        self.parent_query.query_terms(terms);
+    }
+
+    fn estimate_cost(&self, searcher: &Searcher) -> QueryCost {
+        // both of the queries go through all of their documents.
+        self.child_query
+            .estimate_cost(searcher)
+            .union(self.parent_query.estimate_cost(searcher))
+    }
+
+    fn weight(&self, searcher: &Searcher) -> Result<Box<Weight>> {
+        let child_weight = try!(self.child_query.weight(searcher));
+        let parent_weight = try!(self.parent_query.weight(searcher));
+        Ok(box ToParentBlockJoinWeight {
+            child_weight: child_weight,
+            parent_weight: parent_weight,
+        })
+    }
+}
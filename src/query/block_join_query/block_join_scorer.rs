@@ -0,0 +1,90 @@
+use query::Scorer;
+use postings::DocSet;
+use DocId;
+use Score;
+
+/// Scorer rolling up the documents matched by a child scorer
+/// to their parent.
+///
+/// The children of a parent are the documents located
+/// between the previous parent and itself.
+pub struct ToParentBlockJoinScorer<'a> {
+    child_scorer: Box<Scorer + 'a>,
+    // sorted doc ids of the parents of the segment.
+    parents: Vec<DocId>,
+    parent_ord: usize,
+    // true if the child scorer is positioned on a
+    // child that has not been rolled up yet.
+    child_pending: bool,
+    child_exhausted: bool,
+    doc: DocId,
+    score: Score,
+}
+
+impl<'a> ToParentBlockJoinScorer<'a> {
+    pub fn new(child_scorer: Box<Scorer + 'a>, parents: Vec<DocId>) -> ToParentBlockJoinScorer<'a> {
+        ToParentBlockJoinScorer {
+            child_scorer: child_scorer,
+            parents: parents,
+            parent_ord: 0,
+            child_pending: false,
+            child_exhausted: false,
+            doc: 0,
+            score: 0f32,
+        }
+    }
+
+    fn advance_child(&mut self) -> bool {
+        if !self.child_exhausted && !self.child_scorer.advance() {
+            self.child_exhausted = true;
+        }
+        !self.child_exhausted
+    }
+}
+
+impl<'a> DocSet for ToParentBlockJoinScorer<'a> {
+    fn advance(&mut self) -> bool {
+        loop {
+            if !self.child_pending && !self.advance_child() {
+                return false;
+            }
+            self.child_pending = false;
+            let child_doc = self.child_scorer.doc();
+            while self.parent_ord < self.parents.len() && self.parents[self.parent_ord] < child_doc {
+                self.parent_ord += 1;
+            }
+            if self.parent_ord == self.parents.len() {
+                // the remaining children do not have any parent.
+                return false;
+            }
+            let parent = self.parents[self.parent_ord];
+            if child_doc == parent {
+                continue;
+            }
+            let mut score = self.child_scorer.score();
+            while self.advance_child() {
+                let child_doc = self.child_scorer.doc();
+                if child_doc > parent {
+                    self.child_pending = true;
+                    break;
+                }
+                if child_doc < parent {
+                    score = score.max(self.child_scorer.score());
+                }
+            }
+            self.doc = parent;
+            self.score = score;
+            return true;
+        }
+    }
+
+    fn doc(&self) -> DocId {
+        self.doc
+    }
+}
+
+impl<'a> Scorer for ToParentBlockJoinScorer<'a> {
+    fn score(&self) -> Score {
+        self.score
+    }
+}
@@ -0,0 +1,23 @@
+use query::Weight;
+use query::Scorer;
+use core::SegmentReader;
+use postings::DocSet;
+use super::ToParentBlockJoinScorer;
+use Result;
+
+pub struct ToParentBlockJoinWeight {
+    pub child_weight: Box<Weight>,
+    pub parent_weight: Box<Weight>,
+}
+
+impl Weight for ToParentBlockJoinWeight {
+    fn scorer<'a>(&'a self, reader: &'a SegmentReader) -> Result<Box<Scorer + 'a>> {
+        let mut parent_scorer = try!(self.parent_weight.scorer(reader));
+        let mut parents = Vec::new();
+        while parent_scorer.advance() {
+            parents.push(parent_scorer.doc());
+        }
+        let child_scorer = try!(self.child_weight.scorer(reader));
+        Ok(box ToParentBlockJoinScorer::new(child_scorer, parents))
+    }
+}
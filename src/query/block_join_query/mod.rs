@@ -0,0 +1,70 @@
+mod block_join_query;
+mod block_join_weight;
+mod block_join_scorer;
+
+pub use self::block_join_query::ToParentBlockJoinQuery;
+pub use self::block_join_weight::ToParentBlockJoinWeight;
+pub use self::block_join_scorer::ToParentBlockJoinScorer;
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use core::Index;
+    use schema::FieldValue;
+    use schema::{Document, Term, SchemaBuilder, TEXT, STRING};
+    use collector::tests::TestCollector;
+    use query::{Query, TermQuery};
+    use postings::SegmentPostingsOption;
+    use DocId;
+
+    #[test]
+    pub fn test_to_parent_block_join_query() {
+        let mut schema_builder = SchemaBuilder::default();
+        let kind_field = schema_builder.add_text_field("kind", STRING);
+        let color_field = schema_builder.add_text_field("color", TEXT);
+        let schema = schema_builder.build();
+        let index = Index::create_in_ram(schema);
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            // 0, 1, 2
+            index_writer.add_document_block(vec!(
+                doc!(color_field=>"red"),
+                doc!(color_field=>"blue"),
+                doc!(kind_field=>"product", color_field=>"green")));
+            // 3, 4, 5
+            index_writer.add_document_block(vec!(
+                doc!(color_field=>"green"),
+                doc!(color_field=>"blue"),
+                doc!(kind_field=>"product")));
+            index_writer.commit().unwrap();
+            // 6, 7 in a second segment
+            index_writer.add_document_block(vec!(
+                doc!(color_field=>"red"),
+                doc!(kind_field=>"product")));
+            // 8, a child without any parent
+            index_writer.add_document(doc!(color_field=>"red"));
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let search_parents = |color: &str| -> Vec<DocId> {
+            let child_query = box TermQuery::new(
+                Term::from_field_text(color_field, color),
+                SegmentPostingsOption::NoFreq);
+            let parent_query = box TermQuery::new(
+                Term::from_field_text(kind_field, "product"),
+                SegmentPostingsOption::NoFreq);
+            let block_join_query = ToParentBlockJoinQuery::new(child_query, parent_query);
+            let mut test_collector = TestCollector::default();
+            block_join_query.search(&*searcher, &mut test_collector).unwrap();
+            test_collector.docs()
+        };
+        assert_eq!(search_parents("red"), vec!(2, 7));
+        assert_eq!(search_parents("blue"), vec!(2, 5));
+        // the parent matching the child query is ignored.
+        assert_eq!(search_parents("green"), vec!(5));
+        assert!(search_parents("purple").is_empty());
+    }
+}
@@ -2,6 +2,7 @@ use Result;
 use std::any::Any;
 use super::boolean_weight::BooleanWeight;
 use query::Weight;
+use query::QueryCost;
 use Searcher;
 use query::Query;
 use schema::Term;
@@ -15,19 +16,28 @@ use query::OccurFilter;
 /// The documents matched by the boolean query are
 /// those which
 /// * match all of the sub queries associated with the
-/// `Must` occurence
+/// `Must` or the `Filter` occurence
 /// * match none of the sub queries associated with the
 /// `MustNot` occurence.
 /// * match at least one of the subqueries that is not
 /// a `MustNot` occurence.
+/// * match at least `minimum_should_match` of the sub queries
+/// associated with the `Should` occurence. (0 by default)
+///
+/// The sub queries associated with the `Filter` occurence
+/// do not contribute to the score.
 #[derive(Debug)]
 pub struct BooleanQuery {
     subqueries: Vec<(Occur, Box<Query>)>,
+    minimum_should_match: usize,
 }
 
 impl From<Vec<(Occur, Box<Query>)>> for BooleanQuery {
     fn from(subqueries: Vec<(Occur, Box<Query>)>) -> BooleanQuery {
-        BooleanQuery { subqueries: subqueries }
+        BooleanQuery {
+            subqueries: subqueries,
+            minimum_should_match: 0,
+        }
     }
 }
 
@@ -36,21 +46,89 @@ impl Query for BooleanQuery {
         self
     }
 
+    fn query_terms(&self, terms: &mut Vec<Term>) {
+        // the terms of the excluded clauses do not match.
+        for &(occur, ref subquery) in &self.subqueries {
+            if occur != Occur::MustNot {
+                subquery.query_terms(terms);
+            }
+        }
+    }
+
+    fn estimate_cost(&self, searcher: &Searcher) -> QueryCost {
+        let mut required_cost: Option<QueryCost> = None;
+        let mut optional_cost = QueryCost::default();
+        let mut num_excluded_blocks = 0u64;
+        for &(occur, ref subquery) in &self.subqueries {
+            let cost = subquery.estimate_cost(searcher);
+            match occur {
+                Occur::Must | Occur::Filter => {
+                    required_cost = Some(required_cost.map_or(cost, |required_cost| required_cost.intersection(cost)));
+                }
+                Occur::Should => {
+                    optional_cost = optional_cost.union(cost);
+                }
+                Occur::MustNot => {
+                    // the excluded documents are skipped, but their blocks are decoded.
+                    num_excluded_blocks += cost.num_blocks;
+                }
+            }
+        }
+        let num_docs = required_cost.map_or(optional_cost.num_docs, |required_cost| required_cost.num_docs);
+        let num_required_blocks = required_cost.map_or(0, |required_cost| required_cost.num_blocks);
+        QueryCost {
+            num_docs: num_docs,
+            num_blocks: num_required_blocks + optional_cost.num_blocks + num_excluded_blocks,
+        }
+    }
+
     fn weight(&self, searcher: &Searcher) -> Result<Box<Weight>> {
         let sub_weights = try!(self.subqueries
             .iter()
             .map(|&(ref _occur, ref subquery)| subquery.weight(searcher))
             .collect());
+        Ok(box BooleanWeight::new(sub_weights, self.occur_filter()))
+    }
+
+    fn matching_weight(&self, searcher: &Searcher) -> Result<Box<Weight>> {
+        let sub_weights = try!(self.subqueries
+            .iter()
+            .map(|&(ref _occur, ref subquery)| subquery.matching_weight(searcher))
+            .collect());
+        Ok(box BooleanWeight::new(sub_weights, self.occur_filter()))
+    }
+}
+
+impl BooleanQuery {
+
+    fn occur_filter(&self) -> OccurFilter {
         let occurs: Vec<Occur> = self.subqueries
             .iter()
             .map(|&(ref occur, ref _subquery)| *occur)
             .collect();
-        let filter = OccurFilter::new(&occurs);
-        Ok(box BooleanWeight::new(sub_weights, filter))
+        let mut filter = OccurFilter::new(&occurs);
+        filter.set_minimum_should_match(self.minimum_should_match);
+        filter
+    }
+
+    /// Sets the number of `Should` sub queries a document
+    /// must match, in addition to the `Must` and `Filter` sub queries.
+    ///
+    /// The score of a document still sums the scores of all
+    /// of the `Should` sub queries it matches.
+    /// If `minimum_should_match` is greater than the number of
+    /// `Should` sub queries, the query matches no document.
+    pub fn set_minimum_should_match(mut self, minimum_should_match: usize) -> BooleanQuery {
+        self.minimum_should_match = minimum_should_match;
+        self
+    }
+
+    /// Returns the number of `Should` sub queries
+    /// a document must match.
+    pub fn minimum_should_match(&self,) -> usize {
+        self.minimum_should_match
     }
-}
 
-impl BooleanQuery {
     /// Helper method to create a boolean query matching a given list of terms.
     /// The resulting query is a disjunction of the terms.
     pub fn new_multiterms_query(terms: Vec<Term>) -> BooleanQuery {
@@ -44,27 +44,27 @@ impl<TScorer: Scorer> BooleanScorer<TScorer> {
     
     pub fn new(scorers: Vec<TScorer>,
                occur_filter: OccurFilter) -> BooleanScorer<TScorer> {
-        let score_combiner = ScoreCombiner::default_for_num_scorers(scorers.len());
-        let mut non_empty_scorers: Vec<TScorer> = Vec::new();
-        for mut posting in scorers {
-            let non_empty = posting.advance();
-            if non_empty {
-                non_empty_scorers.push(posting);
-            }
-        }
-        let heap_items: Vec<HeapItem> = non_empty_scorers
-            .iter()
-            .map(|posting| posting.doc())
+        let score_combiner = ScoreCombiner::default_for_num_scorers(occur_filter.num_scoring());
+        let mut scorers = scorers;
+        // the ordinals of the scorers are those of the occur filter,
+        // so empty scorers are kept, but never enter the heap.
+        let heap_items: Vec<HeapItem> = scorers
+            .iter_mut()
             .enumerate()
-            .map(|(ord, doc)| {
-                HeapItem {
-                    doc: doc,
-                    ord: ord as u32
+            .filter_map(|(ord, scorer)| {
+                if scorer.advance() {
+                    Some(HeapItem {
+                        doc: scorer.doc(),
+                        ord: ord as u32
+                    })
+                }
+                else {
+                    None
                 }
             })
             .collect();
         BooleanScorer {
-            scorers: non_empty_scorers,
+            scorers: scorers,
             queue: BinaryHeap::from(heap_items),
             doc: 0u32,
             score_combiner: score_combiner,
@@ -104,8 +104,10 @@ impl<TScorer: Scorer> DocSet for BooleanScorer<TScorer> {
                 Some(heap_item) => {
                     let ord = heap_item.ord as usize;
                     self.doc = heap_item.doc;
-                    let score = self.scorers[ord].score();
-                    self.score_combiner.update(score);
+                    if self.occur_filter.is_scoring(ord) {
+                        let score = self.scorers[ord].score();
+                        self.score_combiner.update(score);
+                    }
                     ord_bitset |= 1 << ord;  
                 }
                 None => {
@@ -116,8 +118,10 @@ impl<TScorer: Scorer> DocSet for BooleanScorer<TScorer> {
             while let Some(&HeapItem {doc, ord}) = self.queue.peek() {
                 if doc == self.doc {
                     let ord = ord as usize;
-                    let score = self.scorers[ord].score();
-                    self.score_combiner.update(score);
+                    if self.occur_filter.is_scoring(ord) {
+                        let score = self.scorers[ord].score();
+                        self.score_combiner.update(score);
+                    }
                     ord_bitset |= 1 << ord;
                 }
                 else  {
@@ -1,9 +1,13 @@
 use query::Weight;
 use core::SegmentReader;
 use query::Scorer;
+use query::EmptyScorer;
+use query::Explanation;
+use postings::{DocSet, SkipResult};
 use super::BooleanScorer;
 use query::OccurFilter;
 use Result;
+use DocId;
 
 pub struct BooleanWeight {
     weights: Vec<Box<Weight>>,
@@ -22,11 +26,48 @@ impl BooleanWeight {
 
 impl Weight for BooleanWeight {
     fn scorer<'a>(&'a self, reader: &'a SegmentReader) -> Result<Box<Scorer + 'a>> {
-        let sub_scorers: Vec<Box<Scorer + 'a>> = try!(self.weights
-            .iter()
-            .map(|weight| weight.scorer(reader))
-            .collect());
+        let mut sub_scorers: Vec<Box<Scorer + 'a>> = Vec::with_capacity(self.weights.len());
+        for (ord, weight) in self.weights.iter().enumerate() {
+            let sub_scorer = try!(weight.scorer(reader));
+            // the intersection with an empty required clause is empty,
+            // the remaining clauses do not need to be opened.
+            if self.occur_filter.is_required(ord) && sub_scorer.size_hint() == 0 {
+                return Ok(box EmptyScorer);
+            }
+            sub_scorers.push(sub_scorer);
+        }
         let boolean_scorer = BooleanScorer::new(sub_scorers, self.occur_filter);
         Ok(box boolean_scorer)
     }
+
+    fn explain(&self, reader: &SegmentReader, doc: DocId) -> Result<Option<Explanation>> {
+        // the score is taken from the scorer, as the order in which
+        // it sums the scores of the clauses is that of its heap.
+        let mut boolean_scorer = try!(self.scorer(reader));
+        if boolean_scorer.skip_next(doc) != SkipResult::Reached {
+            return Ok(None);
+        }
+        let mut clause_explanations = Vec::new();
+        for (ord, weight) in self.weights.iter().enumerate() {
+            if self.occur_filter.is_scoring(ord) {
+                if let Some(clause_explanation) = try!(weight.explain(reader, doc)) {
+                    clause_explanations.push(clause_explanation);
+                }
+            }
+        }
+        let num_matching = clause_explanations.len();
+        let num_scoring = self.occur_filter.num_scoring();
+        let mut explanation = Explanation::new(
+            format!("sum of the {} matching scoring clauses, times coord", num_matching),
+            boolean_scorer.score());
+        for clause_explanation in clause_explanations {
+            explanation.add_detail(clause_explanation);
+        }
+        if num_scoring > 0 {
+            explanation.add_detail(Explanation::new(
+                format!("coord, {} out of {} scoring clauses", num_matching, num_scoring),
+                num_matching as f32 / num_scoring as f32));
+        }
+        Ok(Some(explanation))
+    }
 }
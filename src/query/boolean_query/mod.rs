@@ -25,6 +25,9 @@ mod tests {
     use schema::*;
     use fastfield::{U32FastFieldReader};
     use postings::SegmentPostingsOption;
+    use query::PhraseQuery;
+    use collector::TopCollector;
+    use {DocId, Score};
 
     fn abs_diff(left: f32, right: f32) -> f32 {
         (right - left).abs()
@@ -104,6 +107,125 @@ mod tests {
             let boolean_query = BooleanQuery::from(vec![(Occur::MustNot, make_term_query("d")),]);
             assert_eq!(matching_docs(&boolean_query), Vec::new());
         }
+        {
+            // a `Must` clause matching no document.
+            let boolean_query = BooleanQuery::from(vec![(Occur::Must, make_term_query("z")),  (Occur::Should, make_term_query("b"))]);
+            assert_eq!(matching_docs(&boolean_query), Vec::new());
+        }
+    }
+
+    #[test]
+    pub fn test_boolean_query_filter() {
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let category_field = schema_builder.add_text_field("category", STRING);
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.add_document(doc!(text_field => "a b c", category_field => "x"));
+            index_writer.add_document(doc!(text_field => "a b", category_field => "y"));
+            index_writer.add_document(doc!(text_field => "a b a b d", category_field => "x"));
+            index_writer.add_document(doc!(text_field => "b a", category_field => "x"));
+            assert!(index_writer.commit().is_ok());
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let make_phrase_query = || {
+            let terms = vec!(Term::from_field_text(text_field, "a"), Term::from_field_text(text_field, "b"));
+            let query: Box<Query> = box PhraseQuery::from(terms);
+            query
+        };
+        let make_category_query = |category: &str| {
+            let term_query = TermQuery::new(Term::from_field_text(category_field, category), SegmentPostingsOption::NoFreq);
+            let query: Box<Query> = box term_query;
+            query
+        };
+        let score_docs = |query: &Query| {
+            let mut top_collector = TopCollector::with_limit(10);
+            searcher.search(query, &mut top_collector).unwrap();
+            let mut score_docs: Vec<(DocId, Score)> = top_collector
+                .score_docs()
+                .into_iter()
+                .map(|(score, doc_address)| (doc_address.doc(), score))
+                .collect();
+            score_docs.sort_by_key(|&(doc, _)| doc);
+            score_docs
+        };
+        let phrase_score_docs = score_docs(&BooleanQuery::from(vec![(Occur::Must, make_phrase_query())]));
+        assert_eq!(phrase_score_docs.iter().map(|&(doc, _)| doc).collect::<Vec<DocId>>(), vec!(0, 1, 2));
+        let filtered_score_docs = score_docs(&BooleanQuery::from(vec![(Occur::Must, make_phrase_query()),
+                                                                      (Occur::Filter, make_category_query("x"))]));
+        assert_eq!(filtered_score_docs, vec!(phrase_score_docs[0], phrase_score_docs[2]));
+        // as a `Must` clause, the category changes the scores.
+        let must_score_docs = score_docs(&BooleanQuery::from(vec![(Occur::Must, make_phrase_query()),
+                                                                  (Occur::Must, make_category_query("x"))]));
+        assert_eq!(must_score_docs.iter().map(|&(doc, _)| doc).collect::<Vec<DocId>>(), vec!(0, 2));
+        assert!(must_score_docs[0].1 != phrase_score_docs[0].1);
+        // a filter alone matches its documents, with a score of 0.
+        let filter_score_docs = score_docs(&BooleanQuery::from(vec![(Occur::Filter, make_category_query("y"))]));
+        assert_eq!(filter_score_docs, vec!((1, 0f32)));
+    }
+
+    #[test]
+    pub fn test_boolean_query_minimum_should_match() {
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.add_document(doc!(text_field => "d"));
+            index_writer.add_document(doc!(text_field => "a d"));
+            index_writer.add_document(doc!(text_field => "a b"));
+            index_writer.add_document(doc!(text_field => "a b c d"));
+            index_writer.add_document(doc!(text_field => "b c"));
+            assert!(index_writer.commit().is_ok());
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let make_term_query = |text: &str| {
+            let query: Box<Query> = box TermQuery::new(Term::from_field_text(text_field, text), SegmentPostingsOption::Freq);
+            query
+        };
+        let score_docs = |query: &Query| {
+            let mut top_collector = TopCollector::with_limit(10);
+            searcher.search(query, &mut top_collector).unwrap();
+            let mut score_docs: Vec<(DocId, Score)> = top_collector
+                .score_docs()
+                .into_iter()
+                .map(|(score, doc_address)| (doc_address.doc(), score))
+                .collect();
+            score_docs.sort_by_key(|&(doc, _)| doc);
+            score_docs
+        };
+        let docs = |score_docs: &[(DocId, Score)]| {
+            score_docs.iter().map(|&(doc, _)| doc).collect::<Vec<DocId>>()
+        };
+        let optional_query = |minimum_should_match: usize| {
+            BooleanQuery::from(vec![(Occur::Should, make_term_query("a")),
+                                    (Occur::Should, make_term_query("b")),
+                                    (Occur::Should, make_term_query("c"))])
+                .set_minimum_should_match(minimum_should_match)
+        };
+        let all_score_docs = score_docs(&optional_query(0));
+        assert_eq!(docs(&all_score_docs), vec!(1, 2, 3, 4));
+        assert_eq!(score_docs(&optional_query(1)), all_score_docs);
+        // the scores sum all of the matching clauses.
+        let score_docs_2 = score_docs(&optional_query(2));
+        assert_eq!(score_docs_2, vec!(all_score_docs[1], all_score_docs[2], all_score_docs[3]));
+        assert_eq!(docs(&score_docs(&optional_query(3))), vec!(3));
+        assert!(score_docs(&optional_query(4)).is_empty());
+
+        let with_required_clauses = BooleanQuery::from(vec![(Occur::Must, make_term_query("a")),
+                                                            (Occur::Should, make_term_query("b")),
+                                                            (Occur::Should, make_term_query("c")),
+                                                            (Occur::MustNot, make_term_query("d"))]);
+        assert_eq!(docs(&score_docs(&with_required_clauses)), vec!(2));
+        assert_eq!(docs(&score_docs(&with_required_clauses.set_minimum_should_match(1))), vec!(2));
+        let with_required_clauses = BooleanQuery::from(vec![(Occur::Must, make_term_query("a")),
+                                                            (Occur::Should, make_term_query("b")),
+                                                            (Occur::Should, make_term_query("c"))]);
+        assert_eq!(docs(&score_docs(&with_required_clauses)), vec!(1, 2, 3));
+        assert_eq!(docs(&score_docs(&with_required_clauses.set_minimum_should_match(2))), vec!(3));
     }
 
     #[test]
@@ -118,6 +240,8 @@ mod tests {
             idf: 1f32,
             fieldnorm_reader_opt: Some(left_fieldnorms),
             postings: left,
+            similarity: Similarity::TfIdf,
+            average_fieldnorm: 0f32,
         };
         
         let right_fieldnorms = U32FastFieldReader::from(vec!(15,25,35));
@@ -127,6 +251,8 @@ mod tests {
             idf: 4f32,
             fieldnorm_reader_opt: Some(right_fieldnorms),
             postings: right,
+            similarity: Similarity::TfIdf,
+            average_fieldnorm: 0f32,
         };
 
         let mut boolean_scorer = BooleanScorer::new(vec!(left_scorer, right_scorer), occur_filter);
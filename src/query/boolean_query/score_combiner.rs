@@ -28,6 +28,10 @@ impl ScoreCombiner {
     }
     
     pub fn default_for_num_scorers(num_scorers: usize) -> ScoreCombiner {
+        if num_scorers == 0 {
+            // e.g. a boolean query made of filters only.
+            return ScoreCombiner::from(vec!(0f32));
+        }
         let query_coords: Vec<Score> = (0..num_scorers + 1)
             .map(|i| (i as Score) / (num_scorers as Score))
             .collect();
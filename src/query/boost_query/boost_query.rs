@@ -0,0 +1,68 @@
+use query::Query;
+use query::Weight;
+use query::QueryCost;
+use core::searcher::Searcher;
+use super::BoostWeight;
+use std::any::Any;
+use Result;
+use schema::Term;
+use Score;
+
+
+/// `BoostQuery` multiplies the score of the documents
+/// matching its inner query by a constant factor.
+///
+/// The set of matching documents is left untouched.
+#[derive(Debug)]
+pub struct BoostQuery {
+    inner: Box<Query>,
+    boost: Score,
+}
+
+impl BoostQuery {
+
+    /// Creates a new `BoostQuery`.
+    pub fn new(inner: Box<Query>, boost: Score) -> BoostQuery {
+        BoostQuery {
+            inner: inner,
+            boost: boost,
+        }
+    }
+
+    /// Returns the inner query.
+    pub fn inner(&self) -> &Query {
+        &*self.inner
+    }
+
+    /// Returns the factor applied to the score of the inner query.
+    pub fn boost(&self) -> Score {
+        self.boost
+    }
+}
+
+impl Query for BoostQuery {
+
+    fn as_any(&self) -> &Any {
+        self
+    }
+
+    fn query_terms(&self, terms: &mut Vec<Term>) {
+        self.inner.query_terms(terms);
+    }
+
+    fn estimate_cost(&self, searcher: &Searcher) -> QueryCost {
+        self.inner.estimate_cost(searcher)
+    }
+
+    fn weight(&self, searcher: &Searcher) -> Result<Box<Weight>> {
+        let inner_weight = try!(self.inner.weight(searcher));
+        Ok(box BoostWeight {
+            inner: inner_weight,
+            boost: self.boost,
+        })
+    }
+
+    fn matching_weight(&self, searcher: &Searcher) -> Result<Box<Weight>> {
+        self.inner.matching_weight(searcher)
+    }
+}
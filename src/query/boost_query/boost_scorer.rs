@@ -0,0 +1,31 @@
+use query::Scorer;
+use postings::{DocSet, SkipResult};
+use DocId;
+use Score;
+
+/// Scorer multiplying the score of its inner scorer
+/// by a constant factor.
+pub struct BoostScorer<'a> {
+    pub inner: Box<Scorer + 'a>,
+    pub boost: Score,
+}
+
+impl<'a> DocSet for BoostScorer<'a> {
+    fn advance(&mut self) -> bool {
+        self.inner.advance()
+    }
+
+    fn skip_next(&mut self, target: DocId) -> SkipResult {
+        self.inner.skip_next(target)
+    }
+
+    fn doc(&self) -> DocId {
+        self.inner.doc()
+    }
+}
+
+impl<'a> Scorer for BoostScorer<'a> {
+    fn score(&self) -> Score {
+        self.inner.score() * self.boost
+    }
+}
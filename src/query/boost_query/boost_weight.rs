@@ -0,0 +1,36 @@
+use query::Weight;
+use query::Scorer;
+use query::Explanation;
+use core::SegmentReader;
+use super::BoostScorer;
+use Result;
+use Score;
+use DocId;
+
+pub struct BoostWeight {
+    pub inner: Box<Weight>,
+    pub boost: Score,
+}
+
+impl Weight for BoostWeight {
+    fn scorer<'a>(&'a self, reader: &'a SegmentReader) -> Result<Box<Scorer + 'a>> {
+        let inner_scorer = try!(self.inner.scorer(reader));
+        Ok(box BoostScorer {
+            inner: inner_scorer,
+            boost: self.boost,
+        })
+    }
+
+    fn explain(&self, reader: &SegmentReader, doc: DocId) -> Result<Option<Explanation>> {
+        let inner_explanation = match try!(self.inner.explain(reader, doc)) {
+            Some(inner_explanation) => inner_explanation,
+            None => {
+                return Ok(None);
+            }
+        };
+        let mut explanation = Explanation::new("boosted score", inner_explanation.value() * self.boost);
+        explanation.add_detail(inner_explanation);
+        explanation.add_detail(Explanation::new("boost", self.boost));
+        Ok(Some(explanation))
+    }
+}
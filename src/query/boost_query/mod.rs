@@ -0,0 +1,7 @@
+mod boost_query;
+mod boost_weight;
+mod boost_scorer;
+
+pub use self::boost_query::BoostQuery;
+pub use self::boost_weight::BoostWeight;
+pub use self::boost_scorer::BoostScorer;
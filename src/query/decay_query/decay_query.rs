@@ -0,0 +1,129 @@
+use query::Query;
+use query::Weight;
+use query::QueryCost;
+use core::searcher::Searcher;
+use schema::Field;
+use super::DecayWeight;
+use std::any::Any;
+use std::time::{SystemTime, UNIX_EPOCH};
+use Result;
+use schema::Term;
+
+
+/// Function used by the `DecayQuery` to compute the
+/// multiplier of a document's score given its age.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DecayFunction {
+    /// The multiplier is divided by 2 every `half_life`.
+    Exponential,
+    /// The multiplier decreases linearly, reaching
+    /// 0.5 at `half_life`, and 0 at twice the `half_life`.
+    Linear,
+}
+
+impl DecayFunction {
+    /// Returns the score multiplier for a document of age `age`.
+    pub fn multiplier(&self, age: u32, half_life: u32) -> f32 {
+        let relative_age = age as f32 / half_life as f32;
+        match *self {
+            DecayFunction::Exponential => 0.5f32.powf(relative_age),
+            DecayFunction::Linear => (1f32 - relative_age / 2f32).max(0f32),
+        }
+    }
+}
+
+
+/// `DecayQuery` boosts recent documents.
+///
+/// The score of the inner query is multiplied by a
+/// decreasing function of the age of the document.
+/// The age is computed from a u32 fast field holding
+/// the date of the document as a timestamp in seconds, 
+/// relative to the current time.
+///
+/// Documents with a date of `0` are considered as not 
+/// having any date, and their score is left untouched.
+/// Documents with a date in the future are considered 
+/// as having an age of `0`.
+#[derive(Debug)]
+pub struct DecayQuery {
+    inner: Box<Query>,
+    date_field: Field,
+    half_life: u32,
+    decay_function: DecayFunction,
+    now: Option<u32>,
+}
+
+impl DecayQuery {
+
+    /// Creates a new `DecayQuery`.
+    ///
+    /// * inner - the query defining the matching documents and their score
+    /// * date_field - u32 fast field holding the timestamp of the documents
+    /// * half_life - age, in seconds, at which the score is divided by 2. 
+    ///
+    /// The decay is exponential by default.
+    ///
+    /// # Panics
+    /// Panics if `half_life` is `0`.
+    pub fn new(inner: Box<Query>, date_field: Field, half_life: u32) -> DecayQuery {
+        assert!(half_life > 0, "The half life must be strictly positive.");
+        DecayQuery {
+            inner: inner,
+            date_field: date_field,
+            half_life: half_life,
+            decay_function: DecayFunction::Exponential,
+            now: None,
+        }
+    }
+
+    /// Sets the decay function.
+    pub fn set_decay_function(mut self, decay_function: DecayFunction) -> DecayQuery {
+        self.decay_function = decay_function;
+        self
+    }
+
+    /// Sets the timestamp used as the current time.
+    ///
+    /// By default, the current time is read from the
+    /// system clock every time the query is run.
+    pub fn set_now(mut self, now: u32) -> DecayQuery {
+        self.now = Some(now);
+        self
+    }
+
+    fn now(&self) -> u32 {
+        self.now.unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|duration| duration.as_secs() as u32)
+                .unwrap_or(0u32)
+        })
+    }
+}
+
+impl Query for DecayQuery {
+
+    fn as_any(&self) -> &Any {
+        self
+    }
+
+    fn query_terms(&self, terms: &mut Vec<Term>) {
+        self.inner.query_terms(terms);
+    }
+
+    fn estimate_cost(&self, searcher: &Searcher) -> QueryCost {
+        self.inner.estimate_cost(searcher)
+    }
+
+    fn weight(&self, searcher: &Searcher) -> Result<Box<Weight>> {
+        let inner_weight = try!(self.inner.weight(searcher));
+        Ok(box DecayWeight {
+            inner: inner_weight,
+            date_field: self.date_field,
+            half_life: self.half_life,
+            decay_function: self.decay_function,
+            now: self.now(),
+        })
+    }
+}
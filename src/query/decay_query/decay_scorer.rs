@@ -0,0 +1,48 @@
+use query::Scorer;
+use query::DecayFunction;
+use fastfield::U32FastFieldReader;
+use postings::{DocSet, SkipResult};
+use DocId;
+use Score;
+
+/// Scorer multiplying the score of its inner scorer
+/// by a decreasing function of the age of the documents.
+pub struct DecayScorer<'a> {
+    pub inner: Box<Scorer + 'a>,
+    pub date_reader: U32FastFieldReader,
+    pub half_life: u32,
+    pub decay_function: DecayFunction,
+    pub now: u32,
+}
+
+impl<'a> DecayScorer<'a> {
+    fn multiplier(&self) -> f32 {
+        let date = self.date_reader.get(self.inner.doc());
+        if date == 0u32 {
+            // documents without a date are neither boosted nor penalized.
+            return 1f32;
+        }
+        let age = self.now.saturating_sub(date);
+        self.decay_function.multiplier(age, self.half_life)
+    }
+}
+
+impl<'a> DocSet for DecayScorer<'a> {
+    fn advance(&mut self) -> bool {
+        self.inner.advance()
+    }
+
+    fn skip_next(&mut self, target: DocId) -> SkipResult {
+        self.inner.skip_next(target)
+    }
+
+    fn doc(&self) -> DocId {
+        self.inner.doc()
+    }
+}
+
+impl<'a> Scorer for DecayScorer<'a> {
+    fn score(&self) -> Score {
+        self.inner.score() * self.multiplier()
+    }
+}
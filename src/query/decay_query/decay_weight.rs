@@ -0,0 +1,32 @@
+use query::Weight;
+use query::Scorer;
+use query::DecayFunction;
+use core::SegmentReader;
+use schema::Field;
+use super::DecayScorer;
+use Error;
+use Result;
+
+pub struct DecayWeight {
+    pub inner: Box<Weight>,
+    pub date_field: Field,
+    pub half_life: u32,
+    pub decay_function: DecayFunction,
+    pub now: u32,
+}
+
+impl Weight for DecayWeight {
+    fn scorer<'a>(&'a self, reader: &'a SegmentReader) -> Result<Box<Scorer + 'a>> {
+        let date_reader = try!(reader
+            .get_fast_field_reader(self.date_field)
+            .ok_or_else(|| Error::SchemaError(format!("Field {:?} is not a u32 fast field.", self.date_field))));
+        let inner_scorer = try!(self.inner.scorer(reader));
+        Ok(box DecayScorer {
+            inner: inner_scorer,
+            date_reader: date_reader,
+            half_life: self.half_life,
+            decay_function: self.decay_function,
+            now: self.now,
+        })
+    }
+}
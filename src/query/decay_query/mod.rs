@@ -0,0 +1,96 @@
+mod decay_query;
+mod decay_weight;
+mod decay_scorer;
+
+pub use self::decay_query::{DecayQuery, DecayFunction};
+pub use self::decay_weight::DecayWeight;
+pub use self::decay_scorer::DecayScorer;
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use core::Index;
+    use schema::FieldValue;
+    use schema::{Document, Term, SchemaBuilder, TEXT, FAST};
+    use collector::TopCollector;
+    use query::TermQuery;
+    use postings::SegmentPostingsOption;
+    use DocAddress;
+
+    const DAY: u32 = 86_400;
+    const NOW: u32 = 1_500_000_000;
+
+    #[test]
+    pub fn test_decay_function() {
+        assert_eq!(DecayFunction::Exponential.multiplier(0, DAY), 1f32);
+        assert_eq!(DecayFunction::Exponential.multiplier(DAY, DAY), 0.5f32);
+        assert_eq!(DecayFunction::Exponential.multiplier(2 * DAY, DAY), 0.25f32);
+        assert_eq!(DecayFunction::Linear.multiplier(0, DAY), 1f32);
+        assert_eq!(DecayFunction::Linear.multiplier(DAY, DAY), 0.5f32);
+        assert_eq!(DecayFunction::Linear.multiplier(3 * DAY, DAY), 0f32);
+    }
+
+    #[test]
+    pub fn test_decay_query() {
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let date_field = schema_builder.add_u32_field("date", FAST);
+        let schema = schema_builder.build();
+        let index = Index::create_in_ram(schema);
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            // 0
+            index_writer.add_document(doc!(text_field=>"a", date_field=>NOW - 10 * DAY));
+            // 1
+            index_writer.add_document(doc!(text_field=>"a", date_field=>NOW - DAY));
+            // 2
+            index_writer.add_document(doc!(text_field=>"a", date_field=>NOW - 3 * DAY));
+            // 3, without any date
+            index_writer.add_document(doc!(text_field=>"a"));
+            // 4
+            index_writer.add_document(doc!(text_field=>"b", date_field=>NOW));
+            assert!(index_writer.commit().is_ok());
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let term_query = || {
+            let term = Term::from_field_text(text_field, "a");
+            box TermQuery::new(term, SegmentPostingsOption::Freq)
+        };
+        let mut top_collector = TopCollector::with_limit(5);
+        searcher.search(&*term_query(), &mut top_collector).unwrap();
+        let inner_score = top_collector.score_docs()[0].0;
+        {
+            let decay_query = DecayQuery::new(term_query(), date_field, DAY).set_now(NOW);
+            let mut top_collector = TopCollector::with_limit(5);
+            searcher.search(&decay_query, &mut top_collector).unwrap();
+            let score_docs = top_collector.score_docs();
+            let docs: Vec<DocAddress> = score_docs
+                .iter()
+                .map(|&(_, doc_address)| doc_address)
+                .collect();
+            assert_eq!(docs, vec!(DocAddress(0, 3), DocAddress(0, 1), DocAddress(0, 2), DocAddress(0, 0)));
+            assert_eq!(score_docs[0].0, inner_score);
+            assert_eq!(score_docs[1].0, inner_score * 0.5f32);
+        }
+        {
+            let decay_query = DecayQuery::new(term_query(), date_field, DAY)
+                .set_decay_function(DecayFunction::Linear)
+                .set_now(NOW);
+            let mut top_collector = TopCollector::with_limit(5);
+            searcher.search(&decay_query, &mut top_collector).unwrap();
+            let score_docs = top_collector.score_docs();
+            assert_eq!(score_docs[0].1, DocAddress(0, 3));
+            assert_eq!(score_docs[1].1, DocAddress(0, 1));
+            assert_eq!(score_docs[2].0, 0f32);
+            assert_eq!(score_docs[3].0, 0f32);
+        }
+        {
+            let decay_query = DecayQuery::new(term_query(), text_field, DAY);
+            let mut top_collector = TopCollector::with_limit(5);
+            assert!(searcher.search(&decay_query, &mut top_collector).is_err());
+        }
+    }
+}
@@ -0,0 +1,197 @@
+use std::fmt;
+use rustc_serialize::json;
+use Score;
+
+
+/// Describes how the score of a document was computed.
+///
+/// An `Explanation` is a tree: each node holds a value and
+/// a description of what it stands for, and its details are the
+/// values it was computed from. For instance, the explanation of
+/// a `TermQuery` details the idf of the term, its term frequency
+/// and the norm of the field.
+///
+/// The value of the root is the score the collectors are given
+/// for the document. See `Searcher::explain`.
+#[derive(Clone, Debug, PartialEq, RustcEncodable, RustcDecodable)]
+pub struct Explanation {
+    value: Score,
+    description: String,
+    details: Vec<Explanation>,
+}
+
+impl Explanation {
+
+    /// Creates an explanation without any detail.
+    pub fn new<T: ToString>(description: T, value: Score) -> Explanation {
+        Explanation {
+            value: value,
+            description: description.to_string(),
+            details: Vec::new(),
+        }
+    }
+
+    /// Returns the value being explained.
+    pub fn value(&self) -> Score {
+        self.value
+    }
+
+    /// Returns the description of the value.
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+
+    /// Returns the explanations of the values
+    /// this value was computed from.
+    pub fn details(&self) -> &[Explanation] {
+        &self.details
+    }
+
+    /// Appends the explanation of a value
+    /// this value was computed from.
+    pub fn add_detail(&mut self, detail: Explanation) {
+        self.details.push(detail);
+    }
+
+    /// Serializes the explanation as a JSON tree.
+    ///
+    /// Each node is an object with a `value`, a `description`,
+    /// and the array of its `details`. The values are serialized
+    /// with enough digits to be parsed back exactly.
+    pub fn to_json(&self) -> String {
+        json::encode(self).expect("Explanations are always serializable")
+    }
+
+    fn fmt_indented(&self, f: &mut fmt::Formatter, depth: usize) -> fmt::Result {
+        for _ in 0..depth {
+            try!(write!(f, "  "));
+        }
+        try!(writeln!(f, "{} = {}", self.value, self.description));
+        for detail in &self.details {
+            try!(detail.fmt_indented(f, depth + 1));
+        }
+        Ok(())
+    }
+}
+
+/// Displays the tree of the explanation, one value per line,
+/// the details being indented under the value they explain.
+impl fmt::Display for Explanation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.fmt_indented(f, 0)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use core::Index;
+    use collector::TopCollector;
+    use query::{BooleanQuery, BoostQuery, Occur, Query, TermQuery};
+    use postings::SegmentPostingsOption;
+    use schema::{Document, FieldValue, SchemaBuilder, Similarity, Term, TEXT};
+    use DocAddress;
+    use super::Explanation;
+    use rustc_serialize::json::{self, Json};
+
+    #[test]
+    fn test_explain() {
+        let mut schema_builder = SchemaBuilder::default();
+        let title_field = schema_builder.add_text_field("title", TEXT);
+        let body_field = schema_builder.add_text_field("body", TEXT);
+        schema_builder.set_similarity(body_field, Similarity::bm25());
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.add_document(doc!(title_field=>"rust", body_field=>"a systems language"));
+            index_writer.add_document(doc!(title_field=>"a language", body_field=>"rust rust and c"));
+            index_writer.add_document(doc!(title_field=>"c", body_field=>"another systems language"));
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let term_query = |field, text: &str| -> Box<Query> {
+            box TermQuery::new(Term::from_field_text(field, text), SegmentPostingsOption::FreqAndPositions)
+        };
+        let boosted_query: Box<Query> = box BoostQuery::new(term_query(title_field, "rust"), 3f32);
+        let query = BooleanQuery::from(vec!(
+            (Occur::Should, boosted_query),
+            (Occur::Should, term_query(body_field, "rust")),
+            (Occur::Should, term_query(body_field, "systems")),
+            (Occur::MustNot, term_query(body_field, "another")),
+        ));
+        let mut top_collector = TopCollector::with_limit(10);
+        searcher.search(&query, &mut top_collector).unwrap();
+        let score_docs = top_collector.score_docs();
+        assert_eq!(score_docs.len(), 2);
+        for &(score, doc_address) in &score_docs {
+            assert_eq!(searcher.explain(&query, &doc_address).unwrap().value(), score);
+        }
+        // the boost on the title makes the first document rank first.
+        assert_eq!(score_docs[0].1, DocAddress(0, 0));
+
+        let explanation = searcher.explain(&query, &DocAddress(0, 0)).unwrap();
+        let details = explanation.details();
+        assert_eq!(details.len(), 3);
+        assert_eq!(details[0].description(), "boosted score");
+        assert_eq!(details[0].details()[1].value(), 3f32);
+        let title_explanation = &details[0].details()[0];
+        assert_eq!(title_explanation.value() * 3f32, details[0].value());
+        assert_eq!(title_explanation.details()[1].value(), 1f32);
+        assert_eq!(title_explanation.details()[2].value(), 1f32);
+        // the excluded clause counts in the coord.
+        assert_eq!(details[2].value(), 2f32 / 4f32);
+        assert_eq!(explanation.value(), (details[0].value() + details[1].value()) * details[2].value());
+
+        let explanation = searcher.explain(&query, &DocAddress(0, 1)).unwrap();
+        let bm25_explanation = &explanation.details()[0];
+        assert_eq!(bm25_explanation.details().len(), 4);
+        assert_eq!(bm25_explanation.details()[1].value(), 2f32);
+        assert_eq!(bm25_explanation.details()[2].value(), 4f32);
+        assert_eq!(bm25_explanation.details()[3].value(), 10f32 / 3f32);
+        assert!(format!("{}", explanation).contains("term freq"));
+
+        assert!(searcher.explain(&query, &DocAddress(0, 2)).is_err());
+    }
+
+    #[test]
+    fn test_explanation_to_json() {
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.add_document(doc!(text_field=>"a b"));
+            index_writer.add_document(doc!(text_field=>"a c c"));
+            index_writer.add_document(doc!(text_field=>"d"));
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let term_query = |text: &str| -> Box<Query> {
+            box TermQuery::new(Term::from_field_text(text_field, text), SegmentPostingsOption::Freq)
+        };
+        let query = BooleanQuery::from(vec!(
+            (Occur::Should, term_query("a")),
+            (Occur::Should, term_query("c")),
+        ));
+        let explanation = searcher.explain(&query, &DocAddress(0, 1)).unwrap();
+        let explanation_json = Json::from_str(&explanation.to_json()).unwrap();
+        assert_eq!(explanation_json.find("value").and_then(Json::as_f64), Some(explanation.value() as f64));
+        let clauses = explanation_json.find("details").and_then(Json::as_array).unwrap();
+        // the two matching clauses, and the coord.
+        assert_eq!(clauses.len(), 3);
+        assert!(clauses[0].find("description").and_then(Json::as_string).unwrap().contains("score of"));
+        assert_eq!(clauses[2].find("value").and_then(Json::as_f64), Some(1f64));
+        let term_freq = clauses[1]
+            .find("details")
+            .and_then(Json::as_array)
+            .and_then(|details| details[1].find("value"))
+            .and_then(Json::as_f64);
+        assert_eq!(term_freq, Some(2f64));
+        // the values are parsed back exactly.
+        let decoded_explanation: Explanation = json::decode(&explanation.to_json()).unwrap();
+        assert_eq!(decoded_explanation, explanation);
+    }
+}
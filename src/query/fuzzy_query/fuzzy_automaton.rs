@@ -0,0 +1,328 @@
+use std::cmp;
+use std::str;
+use std::collections::BTreeMap;
+use fst::{Automaton, IntoStreamer};
+use schema::Term;
+use core::searcher::Searcher;
+
+/// Returns the number of bytes of the UTF-8 encoded character
+/// starting with a given leading byte.
+fn utf8_len(leading_byte: u8) -> usize {
+    if leading_byte < 0xC0 {
+        1
+    } else if leading_byte < 0xE0 {
+        2
+    } else if leading_byte < 0xF0 {
+        3
+    } else {
+        4
+    }
+}
+
+/// State of the `FuzzyAutomaton`.
+///
+/// Once the exact prefix is matched, the state holds the last row
+/// of the edit distance matrix between the text read so far and the
+/// fuzzy part of the automaton. Values greater than the maximum distance
+/// are all equivalent, and are capped to `distance + 1`.
+#[derive(Clone, Debug)]
+pub struct FuzzyState {
+    num_prefix_bytes: usize,
+    // bytes of the character being read.
+    pending_bytes: Vec<u8>,
+    last_char: Option<char>,
+    // row before `last_char` was read, for transpositions.
+    previous_row: Vec<u32>,
+    row: Vec<u32>,
+    // smallest distance between a prefix of the text read so far
+    // and the reference text, for prefix automata.
+    prefix_distance: u32,
+}
+
+/// Automaton matching the keys made of a prefix followed by a text
+/// within a given edit distance of a reference text.
+///
+/// The edit distance is the Levenshtein distance, counted in characters.
+/// Optionally, the transposition of two adjacent characters can count
+/// as a single edit, (the optimal string alignment distance)
+/// rather than as two substitutions.
+///
+/// A prefix automaton matches the keys whose text starts with
+/// a text within the edit distance of the reference text.
+pub struct FuzzyAutomaton {
+    prefix: Vec<u8>,
+    chars: Vec<char>,
+    distance: u32,
+    transposition_cost_one: bool,
+    prefix_match: bool,
+}
+
+impl FuzzyAutomaton {
+
+    /// Creates an automaton matching the keys starting with `prefix`,
+    /// and followed by a text within `distance` edits of `text`.
+    pub fn new(prefix: &[u8], text: &str, distance: u32, transposition_cost_one: bool) -> FuzzyAutomaton {
+        FuzzyAutomaton {
+            prefix: prefix.to_vec(),
+            chars: text.chars().collect(),
+            distance: distance,
+            transposition_cost_one: transposition_cost_one,
+            prefix_match: false,
+        }
+    }
+
+    /// Creates an automaton matching the keys starting with `prefix`,
+    /// and followed by a text starting with a text within
+    /// `distance` edits of `text`.
+    pub fn new_prefix(prefix: &[u8], text: &str, distance: u32, transposition_cost_one: bool) -> FuzzyAutomaton {
+        let mut automaton = FuzzyAutomaton::new(prefix, text, distance, transposition_cost_one);
+        automaton.prefix_match = true;
+        automaton
+    }
+
+    /// Returns the edit distance between the text following the
+    /// prefix of `key` and the reference text, or `None`
+    /// if `key` is not matched by the automaton.
+    ///
+    /// For a prefix automaton, it is the smallest edit distance
+    /// between a prefix of the text and the reference text.
+    pub fn distance(&self, key: &[u8]) -> Option<u32> {
+        let mut state = self.start();
+        for &byte in key {
+            state = self.accept(&state, byte);
+        }
+        if self.is_match(&state) {
+            state.map(|state| self.state_distance(&state))
+        } else {
+            None
+        }
+    }
+
+    /// Returns the terms of the index matched by the automaton,
+    /// closest first, with their distance.
+    ///
+    /// At most `max_terms` terms are returned.
+    pub fn closest_terms(&self, searcher: &Searcher, max_terms: usize) -> Vec<(Term, u32)> {
+        use fst::Streamer;
+        let mut terms: BTreeMap<Term, u32> = BTreeMap::new();
+        for segment_reader in searcher.segment_readers() {
+            let mut stream = segment_reader
+                .term_infos()
+                .search(self)
+                .into_stream();
+            while let Some((term_bytes, _)) = stream.next() {
+                if let Some(distance) = self.distance(term_bytes) {
+                    terms.insert(Term::from_bytes(term_bytes), distance);
+                }
+            }
+        }
+        let mut terms: Vec<(Term, u32)> = terms.into_iter().collect();
+        terms.sort_by_key(|&(_, distance)| distance);
+        terms.truncate(max_terms);
+        terms
+    }
+
+    fn state_distance(&self, state: &FuzzyState) -> u32 {
+        if self.prefix_match {
+            state.prefix_distance
+        } else {
+            state.row[self.chars.len()]
+        }
+    }
+
+    /// Returns true if all of the keys starting
+    /// with the text read so far are matched.
+    fn is_prefix_matched(&self, state: &FuzzyState) -> bool {
+        self.prefix_match &&
+            state.num_prefix_bytes == self.prefix.len() &&
+            state.prefix_distance <= self.distance
+    }
+
+    /// Returns true if reading more characters may
+    /// lower the prefix distance of the state.
+    fn can_lower_prefix_distance(&self, state: &FuzzyState) -> bool {
+        state.row
+            .iter()
+            .chain(state.previous_row.iter())
+            .any(|&value| value < state.prefix_distance)
+    }
+
+    fn read_char(&self, state: &FuzzyState, c: char) -> FuzzyState {
+        let max_value = self.distance + 1;
+        let row = &state.row;
+        let mut next_row: Vec<u32> = Vec::with_capacity(row.len());
+        next_row.push(cmp::min(row[0] + 1, max_value));
+        for (i, &expected_char) in self.chars.iter().enumerate() {
+            let j = i + 1;
+            let substitution_cost = if expected_char == c { 0 } else { 1 };
+            let mut value = cmp::min(
+                row[j - 1] + substitution_cost,
+                cmp::min(row[j] + 1, next_row[j - 1] + 1));
+            if self.transposition_cost_one && j > 1 &&
+                c == self.chars[j - 2] && state.last_char == Some(expected_char) {
+                value = cmp::min(value, state.previous_row[j - 2] + 1);
+            }
+            next_row.push(cmp::min(value, max_value));
+        }
+        FuzzyState {
+            num_prefix_bytes: state.num_prefix_bytes,
+            pending_bytes: Vec::new(),
+            last_char: Some(c),
+            previous_row: state.row.clone(),
+            prefix_distance: cmp::min(state.prefix_distance, next_row[self.chars.len()]),
+            row: next_row,
+        }
+    }
+}
+
+impl Automaton for FuzzyAutomaton {
+
+    // `None` is the dead state.
+    type State = Option<FuzzyState>;
+
+    fn start(&self) -> Option<FuzzyState> {
+        let max_value = self.distance + 1;
+        Some(FuzzyState {
+            num_prefix_bytes: 0,
+            pending_bytes: Vec::new(),
+            last_char: None,
+            previous_row: Vec::new(),
+            row: (0..self.chars.len() as u32 + 1)
+                .map(|value| cmp::min(value, max_value))
+                .collect(),
+            prefix_distance: cmp::min(self.chars.len() as u32, max_value),
+        })
+    }
+
+    fn is_match(&self, state: &Option<FuzzyState>) -> bool {
+        match *state {
+            Some(ref state) => {
+                state.num_prefix_bytes == self.prefix.len() &&
+                    state.pending_bytes.is_empty() &&
+                    self.state_distance(state) <= self.distance
+            }
+            None => false,
+        }
+    }
+
+    fn can_match(&self, state: &Option<FuzzyState>) -> bool {
+        match *state {
+            Some(ref state) => {
+                self.is_prefix_matched(state) ||
+                    state.row.iter().any(|&value| value <= self.distance)
+            }
+            None => false,
+        }
+    }
+
+    fn will_always_match(&self, state: &Option<FuzzyState>) -> bool {
+        match *state {
+            Some(ref state) => self.is_prefix_matched(state) && state.pending_bytes.is_empty(),
+            None => false,
+        }
+    }
+
+    fn accept(&self, state: &Option<FuzzyState>, byte: u8) -> Option<FuzzyState> {
+        let state = match *state {
+            Some(ref state) => state,
+            None => {
+                return None;
+            }
+        };
+        if state.num_prefix_bytes < self.prefix.len() {
+            if self.prefix[state.num_prefix_bytes] != byte {
+                return None;
+            }
+            let mut next_state = state.clone();
+            next_state.num_prefix_bytes += 1;
+            return Some(next_state);
+        }
+        if self.is_prefix_matched(state) && !self.can_lower_prefix_distance(state) {
+            // the following characters are not read.
+            return Some(state.clone());
+        }
+        let mut pending_bytes = state.pending_bytes.clone();
+        pending_bytes.push(byte);
+        if pending_bytes.len() < utf8_len(pending_bytes[0]) {
+            let mut next_state = state.clone();
+            next_state.pending_bytes = pending_bytes;
+            return Some(next_state);
+        }
+        let c = match str::from_utf8(&pending_bytes) {
+            Ok(text) => text.chars().next().expect("A complete character was read"),
+            Err(_) => {
+                return None;
+            }
+        };
+        let next_state = self.read_char(state, c);
+        if self.can_match(&Some(next_state.clone())) {
+            Some(next_state)
+        } else {
+            None
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn distance(text: &str, key: &str, distance: u32, transposition_cost_one: bool) -> Option<u32> {
+        let automaton = FuzzyAutomaton::new(b"\x01", text, distance, transposition_cost_one);
+        let mut prefixed_key = vec!(1u8);
+        prefixed_key.extend_from_slice(key.as_bytes());
+        automaton.distance(&prefixed_key)
+    }
+
+    #[test]
+    fn test_fuzzy_automaton() {
+        assert_eq!(distance("research", "research", 0, false), Some(0));
+        assert_eq!(distance("research", "reserch", 0, false), None);
+        assert_eq!(distance("reserch", "research", 1, false), Some(1));
+        assert_eq!(distance("research", "researcher", 1, false), None);
+        assert_eq!(distance("research", "researcher", 2, false), Some(2));
+        assert_eq!(distance("kitten", "sitting", 2, false), None);
+        assert_eq!(distance("", "ab", 2, false), Some(2));
+        assert_eq!(distance("ab", "", 2, false), Some(2));
+        assert_eq!(distance("café", "cafe", 1, false), Some(1));
+        assert_eq!(distance("北京", "南京", 1, false), Some(1));
+        assert_eq!(distance("北京", "京北", 1, false), None);
+        assert_eq!(distance("北京", "京北", 1, true), Some(1));
+        assert_eq!(distance("search", "saerch", 1, false), None);
+        assert_eq!(distance("search", "saerch", 2, false), Some(2));
+        assert_eq!(distance("search", "saerch", 1, true), Some(1));
+    }
+
+    #[test]
+    fn test_fuzzy_prefix_automaton() {
+        let prefix_distance = |text: &str, key: &str, distance: u32| {
+            let automaton = FuzzyAutomaton::new_prefix(b"\x01", text, distance, true);
+            let mut prefixed_key = vec!(1u8);
+            prefixed_key.extend_from_slice(key.as_bytes());
+            automaton.distance(&prefixed_key)
+        };
+        assert_eq!(prefix_distance("sea", "search", 0), Some(0));
+        assert_eq!(prefix_distance("sea", "se", 0), None);
+        assert_eq!(prefix_distance("sea", "se", 1), Some(1));
+        assert_eq!(prefix_distance("serch", "search", 1), Some(1));
+        assert_eq!(prefix_distance("serch", "searching", 1), Some(1));
+        assert_eq!(prefix_distance("saerch", "searching", 1), Some(1));
+        assert_eq!(prefix_distance("serch", "seaarch", 1), None);
+        assert_eq!(prefix_distance("北京", "南京大学", 1), Some(1));
+        assert_eq!(prefix_distance("", "anything", 0), Some(0));
+        assert_eq!(prefix_distance("sear", "searching", 1), Some(0));
+        assert_eq!(prefix_distance("sear", "sea", 1), Some(1));
+    }
+
+    #[test]
+    fn test_fuzzy_automaton_prefix() {
+        let automaton = FuzzyAutomaton::new(b"\x01re", "search", 1, false);
+        assert_eq!(automaton.distance(b"\x01research"), Some(0));
+        assert_eq!(automaton.distance(b"\x01reserch"), Some(1));
+        assert_eq!(automaton.distance(b"\x01rsearch"), None);
+        assert_eq!(automaton.distance(b"\x01besearch"), None);
+        assert_eq!(automaton.distance(b"\x02research"), None);
+    }
+}
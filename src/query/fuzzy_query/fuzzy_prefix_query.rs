@@ -0,0 +1,127 @@
+use schema::Field;
+use schema::Term;
+use query::Query;
+use query::Weight;
+use query::BooleanQuery;
+use query::QueryCost;
+use query::term_expansion::{self, DEFAULT_MAX_EXPANSIONS};
+use core::searcher::Searcher;
+use super::FuzzyAutomaton;
+use std::any::Any;
+use Result;
+
+/// Upper bound for the edit distance.
+const MAX_DISTANCE: u8 = 2;
+
+/// `FuzzyPrefixQuery` matches the documents containing a term
+/// starting with a prefix within a given edit distance of a text.
+///
+/// It is meant for search-as-you-type, forgiving the typos
+/// of the user: with a distance of 1, `serch` matches `search`
+/// and `searching`.
+/// By default, the transposition of two adjacent characters counts
+/// as a single edit. See `FuzzyTermQuery` for the edit distance.
+///
+/// The matching terms are searched as a disjunction. The number of expansions
+/// is capped by `max_expansions`, (50 by default) and the terms closest
+/// to the text are kept, in lexicographic order.
+#[derive(Debug)]
+pub struct FuzzyPrefixQuery {
+    field: Field,
+    text: String,
+    distance: u8,
+    transposition_cost_one: bool,
+    max_expansions: usize,
+}
+
+impl FuzzyPrefixQuery {
+
+    /// Creates a new `FuzzyPrefixQuery`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `distance` is greater than 2.
+    pub fn new(field: Field, text: &str, distance: u8) -> FuzzyPrefixQuery {
+        assert!(distance <= MAX_DISTANCE, "The distance of a fuzzy query is at most 2.");
+        FuzzyPrefixQuery {
+            field: field,
+            text: String::from(text),
+            distance: distance,
+            transposition_cost_one: true,
+            max_expansions: DEFAULT_MAX_EXPANSIONS,
+        }
+    }
+
+    /// Sets whether the transposition of two adjacent
+    /// characters counts as a single edit. (true by default)
+    pub fn set_transposition_cost_one(mut self, transposition_cost_one: bool) -> FuzzyPrefixQuery {
+        self.transposition_cost_one = transposition_cost_one;
+        self
+    }
+
+    /// Sets the maximum number of terms the prefix
+    /// may be expanded into.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max_expansions` is 0 or greater than 64.
+    pub fn set_max_expansions(mut self, max_expansions: usize) -> FuzzyPrefixQuery {
+        term_expansion::check_max_expansions(max_expansions);
+        self.max_expansions = max_expansions;
+        self
+    }
+
+    /// Returns the field targeted by the query.
+    pub fn field(&self,) -> Field {
+        self.field
+    }
+
+    /// Returns the searched prefix.
+    pub fn text(&self,) -> &str {
+        &self.text
+    }
+
+    /// Returns the maximum edit distance.
+    pub fn distance(&self,) -> u8 {
+        self.distance
+    }
+
+    /// Returns the terms of the index matching the prefix,
+    /// closest first.
+    fn expand(&self, searcher: &Searcher) -> Vec<Term> {
+        let field_prefix = Term::from_field_text(self.field, "");
+        let automaton = FuzzyAutomaton::new_prefix(field_prefix.as_slice(),
+                                                   &self.text,
+                                                   self.distance as u32,
+                                                   self.transposition_cost_one);
+        automaton
+            .closest_terms(searcher, self.max_expansions)
+            .into_iter()
+            .map(|(term, _)| term)
+            .collect()
+    }
+}
+
+impl Query for FuzzyPrefixQuery {
+
+    /// Used to make it possible to cast Box<Query>
+    /// into a specific type. This is mostly useful for unit tests.
+    fn as_any(&self) -> &Any {
+        self
+    }
+
+    /// Create the weight associated to a query.
+    ///
+    /// The prefix is expanded into the matching terms of the index,
+    /// which are then searched as a disjunction.
+    ///
+    /// See [Weight](./trait.Weight.html).
+    fn weight(&self, searcher: &Searcher) -> Result<Box<Weight>> {
+        let expanded_terms = self.expand(searcher);
+        BooleanQuery::new_multiterms_query(expanded_terms).weight(searcher)
+    }
+
+    fn estimate_cost(&self, searcher: &Searcher) -> QueryCost {
+        term_expansion::union_cost(searcher, self.expand(searcher).iter())
+    }
+}
@@ -0,0 +1,162 @@
+use schema::Term;
+use query::Query;
+use query::Weight;
+use query::BooleanQuery;
+use query::BoostQuery;
+use query::TermQuery;
+use query::Occur;
+use query::QueryCost;
+use query::term_expansion::{self, DEFAULT_MAX_EXPANSIONS};
+use postings::SegmentPostingsOption;
+use core::searcher::Searcher;
+use super::FuzzyAutomaton;
+use std::any::Any;
+use Result;
+use Score;
+
+/// Upper bound for the edit distance, as the number of
+/// matching terms explodes with the distance.
+const MAX_DISTANCE: u8 = 2;
+
+/// `FuzzyTermQuery` matches the documents containing a term
+/// within a given edit distance of a term.
+///
+/// The edit distance is the Levenshtein distance, counted in characters:
+/// `reserch` is within a distance of 1 of `research`.
+/// If `transposition_cost_one` is true, the transposition of two adjacent
+/// characters counts as a single edit, so that `saerch` is within a
+/// distance of 1 of `search`.
+///
+/// An automaton is run over the term dictionary of each segment.
+/// Requiring the first characters to match exactly, with `set_prefix_length`,
+/// drastically reduces the number of terms the automaton goes through.
+///
+/// The matching terms are searched as a disjunction. The number of expansions
+/// is capped by `max_expansions`, (50 by default) and the terms closest
+/// to the searched term are kept.
+/// With a distance of 0, the query is equivalent to a `TermQuery`.
+#[derive(Debug)]
+pub struct FuzzyTermQuery {
+    term: Term,
+    distance: u8,
+    transposition_cost_one: bool,
+    prefix_length: usize,
+    max_expansions: usize,
+    distance_penalty: bool,
+}
+
+impl FuzzyTermQuery {
+
+    /// Creates a new `FuzzyTermQuery`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `distance` is greater than 2.
+    pub fn new(term: Term, distance: u8, transposition_cost_one: bool) -> FuzzyTermQuery {
+        assert!(distance <= MAX_DISTANCE, "The distance of a fuzzy query is at most 2.");
+        FuzzyTermQuery {
+            term: term,
+            distance: distance,
+            transposition_cost_one: transposition_cost_one,
+            prefix_length: 0,
+            max_expansions: DEFAULT_MAX_EXPANSIONS,
+            distance_penalty: false,
+        }
+    }
+
+    /// Sets the number of leading characters of the term
+    /// that must match exactly.
+    pub fn set_prefix_length(mut self, prefix_length: usize) -> FuzzyTermQuery {
+        self.prefix_length = prefix_length;
+        self
+    }
+
+    /// Sets the maximum number of terms the term
+    /// may be expanded into.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max_expansions` is 0 or greater than 64.
+    pub fn set_max_expansions(mut self, max_expansions: usize) -> FuzzyTermQuery {
+        term_expansion::check_max_expansions(max_expansions);
+        self.max_expansions = max_expansions;
+        self
+    }
+
+    /// If `distance_penalty` is true, the score of a matching term
+    /// at a distance `d` of the searched term is divided by `1 + d`,
+    /// so that the exact matches rank first.
+    pub fn set_distance_penalty(mut self, distance_penalty: bool) -> FuzzyTermQuery {
+        self.distance_penalty = distance_penalty;
+        self
+    }
+
+    /// Returns the searched term.
+    pub fn term(&self,) -> &Term {
+        &self.term
+    }
+
+    /// Returns the maximum edit distance.
+    pub fn distance(&self,) -> u8 {
+        self.distance
+    }
+
+    fn automaton(&self) -> FuzzyAutomaton {
+        let text = String::from_utf8_lossy(self.term.value());
+        let prefix_len = text
+            .char_indices()
+            .nth(self.prefix_length)
+            .map(|(offset, _)| offset)
+            .unwrap_or(text.len());
+        let prefix = Term::from_field_text(self.term.field(), &text[..prefix_len]);
+        FuzzyAutomaton::new(prefix.as_slice(),
+                            &text[prefix_len..],
+                            self.distance as u32,
+                            self.transposition_cost_one)
+    }
+
+    /// Returns the terms of the index within the distance
+    /// of the searched term, closest first, with their distance.
+    fn expand(&self, searcher: &Searcher) -> Vec<(Term, u32)> {
+        self.automaton().closest_terms(searcher, self.max_expansions)
+    }
+}
+
+impl Query for FuzzyTermQuery {
+
+    /// Used to make it possible to cast Box<Query>
+    /// into a specific type. This is mostly useful for unit tests.
+    fn as_any(&self) -> &Any {
+        self
+    }
+
+    /// Create the weight associated to a query.
+    ///
+    /// The term is expanded into the matching terms of the index,
+    /// which are then searched as a disjunction.
+    ///
+    /// See [Weight](./trait.Weight.html).
+    fn weight(&self, searcher: &Searcher) -> Result<Box<Weight>> {
+        let subqueries: Vec<(Occur, Box<Query>)> = self.expand(searcher)
+            .into_iter()
+            .map(|(term, distance)| {
+                let mut term_query: Box<Query> = box TermQuery::new(term, SegmentPostingsOption::Freq);
+                if self.distance_penalty && distance > 0 {
+                    let boost = 1f32 / (1 + distance) as Score;
+                    term_query = box BoostQuery::new(term_query, boost);
+                }
+                (Occur::Should, term_query)
+            })
+            .collect();
+        BooleanQuery::from(subqueries).weight(searcher)
+    }
+
+    fn query_terms(&self, terms: &mut Vec<Term>) {
+        terms.push(self.term.clone());
+    }
+
+    fn estimate_cost(&self, searcher: &Searcher) -> QueryCost {
+        let expanded_terms = self.expand(searcher);
+        term_expansion::union_cost(searcher, expanded_terms.iter().map(|&(ref term, _)| term))
+    }
+}
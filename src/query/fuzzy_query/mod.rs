@@ -0,0 +1,127 @@
+mod fuzzy_automaton;
+mod fuzzy_term_query;
+mod fuzzy_prefix_query;
+
+pub use self::fuzzy_automaton::FuzzyAutomaton;
+pub use self::fuzzy_term_query::FuzzyTermQuery;
+pub use self::fuzzy_prefix_query::FuzzyPrefixQuery;
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use core::Index;
+    use schema::FieldValue;
+    use schema::{Document, SchemaBuilder, Term, TEXT};
+    use collector::tests::TestCollector;
+    use collector::TopCollector;
+    use query::{Query, TermQuery};
+    use postings::SegmentPostingsOption;
+
+    #[test]
+    pub fn test_fuzzy_term_query() {
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let schema = schema_builder.build();
+        let index = Index::create_in_ram(schema);
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.add_document(doc!(text_field=>"research papers"));  // 0
+            index_writer.add_document(doc!(text_field=>"searching"));  // 1
+            index_writer.add_document(doc!(text_field=>"a researcher"));  // 2
+            assert!(index_writer.commit().is_ok());
+            index_writer.add_document(doc!(text_field=>"reserch"));  // 3
+            index_writer.add_document(doc!(text_field=>"saerch"));  // 4
+            assert!(index_writer.commit().is_ok());
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let term = |text: &str| Term::from_field_text(text_field, text);
+        let test_query = |query: FuzzyTermQuery| {
+            let mut test_collector = TestCollector::default();
+            searcher.search(&query, &mut test_collector).expect("search should succeed");
+            test_collector.docs()
+        };
+        assert_eq!(test_query(FuzzyTermQuery::new(term("reserch"), 1, false)), vec!(0, 3));
+        assert_eq!(test_query(FuzzyTermQuery::new(term("reserch"), 0, false)), vec!(3));
+        assert_eq!(test_query(FuzzyTermQuery::new(term("researche"), 2, false)), vec!(0, 2, 3));
+        assert_eq!(test_query(FuzzyTermQuery::new(term("search"), 1, false)), Vec::<u32>::new());
+        assert_eq!(test_query(FuzzyTermQuery::new(term("search"), 1, true)), vec!(4));
+        assert_eq!(test_query(FuzzyTermQuery::new(term("raserch"), 2, false)), vec!(0, 3, 4));
+        assert_eq!(test_query(FuzzyTermQuery::new(term("raserch"), 2, false).set_prefix_length(2)), Vec::<u32>::new());
+        assert_eq!(test_query(FuzzyTermQuery::new(term("researh"), 2, false).set_max_expansions(1)), vec!(0));
+    }
+
+    #[test]
+    pub fn test_fuzzy_term_query_scores() {
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.add_document(doc!(text_field=>"research"));
+            index_writer.add_document(doc!(text_field=>"reserch"));
+            index_writer.add_document(doc!(text_field=>"research and more research"));
+            assert!(index_writer.commit().is_ok());
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let score_docs = |query: &Query| {
+            let mut top_collector = TopCollector::with_limit(10);
+            searcher.search(query, &mut top_collector).unwrap();
+            top_collector.score_docs()
+        };
+        let term = Term::from_field_text(text_field, "research");
+        // a distance of 0 is a term query.
+        assert_eq!(score_docs(&FuzzyTermQuery::new(term.clone(), 0, true)),
+                   score_docs(&TermQuery::new(term.clone(), SegmentPostingsOption::Freq)));
+        let without_penalty = score_docs(&FuzzyTermQuery::new(term.clone(), 1, false));
+        let with_penalty = score_docs(&FuzzyTermQuery::new(term.clone(), 1, false).set_distance_penalty(true));
+        assert_eq!(without_penalty.len(), 3);
+        assert_eq!(with_penalty.len(), 3);
+        let score = |score_docs: &[(f32, ::DocAddress)], doc: u32| {
+            score_docs.iter().find(|&&(_, doc_address)| doc_address.1 == doc).unwrap().0
+        };
+        assert_eq!(score(&with_penalty, 0), score(&without_penalty, 0));
+        assert_eq!(score(&with_penalty, 1), score(&without_penalty, 1) / 2f32);
+    }
+
+    #[test]
+    pub fn test_fuzzy_prefix_query() {
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.add_document(doc!(text_field=>"search engine"));  // 0
+            index_writer.add_document(doc!(text_field=>"searching"));  // 1
+            index_writer.add_document(doc!(text_field=>"a serious engine"));  // 2
+            assert!(index_writer.commit().is_ok());
+            index_writer.add_document(doc!(text_field=>"sea"));  // 3
+            index_writer.add_document(doc!(text_field=>"research"));  // 4
+            assert!(index_writer.commit().is_ok());
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let test_query = |query: FuzzyPrefixQuery| {
+            let mut test_collector = TestCollector::default();
+            searcher.search(&query, &mut test_collector).expect("search should succeed");
+            test_collector.docs()
+        };
+        assert_eq!(test_query(FuzzyPrefixQuery::new(text_field, "serch", 1)), vec!(0, 1));
+        assert_eq!(test_query(FuzzyPrefixQuery::new(text_field, "serch", 0)), Vec::<u32>::new());
+        assert_eq!(test_query(FuzzyPrefixQuery::new(text_field, "search", 0)), vec!(0, 1));
+        assert_eq!(test_query(FuzzyPrefixQuery::new(text_field, "saerch", 1)), vec!(0, 1));
+        assert_eq!(test_query(FuzzyPrefixQuery::new(text_field, "saerch", 1).set_transposition_cost_one(false)), Vec::<u32>::new());
+        assert_eq!(test_query(FuzzyPrefixQuery::new(text_field, "sear", 1)), vec!(0, 1, 2, 3));
+        // the closest terms are kept.
+        assert_eq!(test_query(FuzzyPrefixQuery::new(text_field, "sear", 1).set_max_expansions(2)), vec!(0, 1));
+    }
+
+    #[test]
+    #[should_panic]
+    pub fn test_fuzzy_term_query_max_distance() {
+        FuzzyTermQuery::new(Term::from_field_text(::schema::Field(0), "a"), 3, false);
+    }
+}
@@ -0,0 +1,65 @@
+use query::Query;
+use query::Weight;
+use core::searcher::Searcher;
+use schema::GeoField;
+use super::GeoBoundingBoxWeight;
+use std::any::Any;
+use Result;
+
+
+/// `GeoBoundingBoxQuery` matches the documents whose geo-point
+/// lies within a bounding box.
+///
+/// The edges of the box are inclusive. All of the matching
+/// documents get the same score, `1`.
+///
+/// If `min_lon` is greater than `max_lon`, the box is considered as
+/// crossing the antimeridian : it is split in two boxes,
+/// `[min_lon, 180]` and `[-180, max_lon]`.
+///
+/// The query scans the latitude and longitude fast fields of
+/// all of the documents of the segments.
+#[derive(Debug)]
+pub struct GeoBoundingBoxQuery {
+    geo_field: GeoField,
+    min_lat: u32,
+    max_lat: u32,
+    min_lon: u32,
+    max_lon: u32,
+}
+
+impl GeoBoundingBoxQuery {
+
+    /// Creates a new `GeoBoundingBoxQuery`. Coordinates are in degrees.
+    ///
+    /// # Panics
+    /// Panics if the coordinates are out of range, or
+    /// if `min_lat` is greater than `max_lat`.
+    pub fn new(geo_field: GeoField, min_lat: f64, max_lat: f64, min_lon: f64, max_lon: f64) -> GeoBoundingBoxQuery {
+        assert!(min_lat <= max_lat, "min_lat must not be greater than max_lat.");
+        GeoBoundingBoxQuery {
+            geo_field: geo_field,
+            min_lat: GeoField::encode_lat(min_lat),
+            max_lat: GeoField::encode_lat(max_lat),
+            min_lon: GeoField::encode_lon(min_lon),
+            max_lon: GeoField::encode_lon(max_lon),
+        }
+    }
+}
+
+impl Query for GeoBoundingBoxQuery {
+
+    fn as_any(&self) -> &Any {
+        self
+    }
+
+    fn weight(&self, _searcher: &Searcher) -> Result<Box<Weight>> {
+        Ok(box GeoBoundingBoxWeight {
+            geo_field: self.geo_field,
+            min_lat: self.min_lat,
+            max_lat: self.max_lat,
+            min_lon: self.min_lon,
+            max_lon: self.max_lon,
+        })
+    }
+}
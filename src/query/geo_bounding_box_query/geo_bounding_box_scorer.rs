@@ -0,0 +1,48 @@
+use query::Scorer;
+use core::SegmentReader;
+use fastfield::U32FastFieldReader;
+use postings::DocSet;
+use super::GeoBoundingBoxWeight;
+use DocId;
+use Score;
+
+/// Scorer going through all of the documents of the segment,
+/// and keeping the ones whose geo-point lies within the box.
+pub struct GeoBoundingBoxScorer<'a> {
+    pub reader: &'a SegmentReader,
+    pub lat_reader: U32FastFieldReader,
+    pub lon_reader: U32FastFieldReader,
+    pub weight: &'a GeoBoundingBoxWeight,
+    pub doc: DocId,
+    pub started: bool,
+}
+
+impl<'a> DocSet for GeoBoundingBoxScorer<'a> {
+    fn advance(&mut self) -> bool {
+        let max_doc = self.reader.max_doc();
+        if self.started {
+            self.doc += 1;
+        }
+        else {
+            self.started = true;
+        }
+        while self.doc < max_doc {
+            if !self.reader.is_deleted(self.doc) &&
+               self.weight.contains(self.lat_reader.get(self.doc), self.lon_reader.get(self.doc)) {
+                return true;
+            }
+            self.doc += 1;
+        }
+        false
+    }
+
+    fn doc(&self) -> DocId {
+        self.doc
+    }
+}
+
+impl<'a> Scorer for GeoBoundingBoxScorer<'a> {
+    fn score(&self) -> Score {
+        1f32
+    }
+}
@@ -0,0 +1,57 @@
+use query::Weight;
+use query::Scorer;
+use core::SegmentReader;
+use schema::{Field, GeoField};
+use fastfield::U32FastFieldReader;
+use super::GeoBoundingBoxScorer;
+use Error;
+use Result;
+
+pub struct GeoBoundingBoxWeight {
+    pub geo_field: GeoField,
+    pub min_lat: u32,
+    pub max_lat: u32,
+    pub min_lon: u32,
+    pub max_lon: u32,
+}
+
+fn fast_field_reader(reader: &SegmentReader, field: Field) -> Result<U32FastFieldReader> {
+    reader
+        .get_fast_field_reader(field)
+        .ok_or_else(|| Error::SchemaError(format!("Field {:?} is not a u32 fast field.", field)))
+}
+
+impl Weight for GeoBoundingBoxWeight {
+    fn scorer<'a>(&'a self, reader: &'a SegmentReader) -> Result<Box<Scorer + 'a>> {
+        let lat_reader = try!(fast_field_reader(reader, self.geo_field.lat_field()));
+        let lon_reader = try!(fast_field_reader(reader, self.geo_field.lon_field()));
+        Ok(box GeoBoundingBoxScorer {
+            reader: reader,
+            lat_reader: lat_reader,
+            lon_reader: lon_reader,
+            weight: self,
+            doc: 0,
+            started: false,
+        })
+    }
+}
+
+impl GeoBoundingBoxWeight {
+    /// Returns true iff the encoded geo-point lies within the box.
+    pub fn contains(&self, lat: u32, lon: u32) -> bool {
+        if lat == 0u32 || lon == 0u32 {
+            // no geo-point.
+            return false;
+        }
+        if lat < self.min_lat || lat > self.max_lat {
+            return false;
+        }
+        if self.min_lon <= self.max_lon {
+            self.min_lon <= lon && lon <= self.max_lon
+        }
+        else {
+            // the box crosses the antimeridian.
+            self.min_lon <= lon || lon <= self.max_lon
+        }
+    }
+}
@@ -0,0 +1,63 @@
+mod geo_bounding_box_query;
+mod geo_bounding_box_weight;
+mod geo_bounding_box_scorer;
+
+pub use self::geo_bounding_box_query::GeoBoundingBoxQuery;
+pub use self::geo_bounding_box_weight::GeoBoundingBoxWeight;
+pub use self::geo_bounding_box_scorer::GeoBoundingBoxScorer;
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use core::Index;
+    use schema::{Document, SchemaBuilder, TEXT};
+    use collector::tests::TestCollector;
+    use query::Query;
+
+    #[test]
+    fn test_geo_bounding_box_query() {
+        let mut schema_builder = SchemaBuilder::default();
+        let geo_field = schema_builder.add_geo_field("location");
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let points = vec!(
+            (48.8566, 2.3522), // 0 : Paris
+            (51.5074, -0.1278), // 1 : London
+            (40.7128, -74.0060), // 2 : New York
+            (45.0, 0.0), // 3 : on the south-west corner of the box
+            (52.0, 5.0), // 4 : on the north-east corner of the box
+            (44.9999, 2.0), // 5 : just south of the box
+            (48.0, 5.0001), // 6 : just east of the box
+            (-16.5, 179.5), // 7 : Fiji, east of the antimeridian
+            (-16.5, -179.5), // 8 : Fiji, west of the antimeridian
+        );
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            for &(lat, lon) in &points {
+                let mut doc = Document::default();
+                doc.add_geo_point(geo_field, lat, lon);
+                index_writer.add_document(doc);
+            }
+            // a document without any geo-point.
+            let mut doc = Document::default();
+            doc.add_text(text_field, "nowhere");
+            index_writer.add_document(doc);
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let matching_docs = |query: GeoBoundingBoxQuery| {
+            let mut collector = TestCollector::default();
+            query.search(&*searcher, &mut collector).unwrap();
+            collector.docs()
+        };
+        assert_eq!(matching_docs(GeoBoundingBoxQuery::new(geo_field, 45.0, 52.0, 0.0, 5.0)), vec!(0, 3, 4));
+        assert_eq!(matching_docs(GeoBoundingBoxQuery::new(geo_field, 40.0, 55.0, -80.0, 5.0)), vec!(0, 1, 2, 3, 4, 5));
+        // crossing the antimeridian.
+        assert_eq!(matching_docs(GeoBoundingBoxQuery::new(geo_field, -20.0, -10.0, 179.0, -179.0)), vec!(7, 8));
+        assert_eq!(matching_docs(GeoBoundingBoxQuery::new(geo_field, -20.0, -10.0, -179.0, 179.0)), Vec::<u32>::new());
+        assert_eq!(matching_docs(GeoBoundingBoxQuery::new(geo_field, -90.0, 90.0, -180.0, 180.0)), (0..9).collect::<Vec<u32>>());
+    }
+}
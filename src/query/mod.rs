@@ -4,7 +4,11 @@
 ///
 
 mod query;
+mod query_cost;
+mod explanation;
 mod boolean_query;
+mod block_join_query;
+mod boost_query;
 mod scorer;
 mod occur;
 mod weight;
@@ -12,15 +16,44 @@ mod occur_filter;
 mod term_query;
 mod query_parser;
 mod phrase_query;
+mod phrase_prefix_query;
+mod proximity_query;
+mod decay_query;
+mod position_boost_query;
+mod geo_bounding_box_query;
+mod wildcard_query;
+mod fuzzy_query;
+mod regex_query;
+mod range_query;
+mod term_range_query;
+mod more_like_this_query;
+mod term_expansion;
 
 pub use self::boolean_query::BooleanQuery;
+pub use self::block_join_query::ToParentBlockJoinQuery;
+pub use self::boost_query::BoostQuery;
 pub use self::occur_filter::OccurFilter;
 pub use self::occur::Occur;
+pub use self::decay_query::{DecayQuery, DecayFunction};
+pub use self::explanation::Explanation;
+pub use self::fuzzy_query::{FuzzyPrefixQuery, FuzzyTermQuery};
+pub use self::regex_query::RegexQuery;
+pub use self::geo_bounding_box_query::GeoBoundingBoxQuery;
+pub use self::more_like_this_query::MoreLikeThisQuery;
 pub use self::phrase_query::PhraseQuery;
+pub use self::position_boost_query::PositionBoostQuery;
+pub use self::phrase_prefix_query::PhrasePrefixQuery;
+pub use self::proximity_query::ProximityQuery;
+pub use self::range_query::RangeQuery;
+pub use self::term_range_query::TermRangeQuery;
 pub use self::query_parser::QueryParserError;
 pub use self::query_parser::QueryParser;
+pub use self::query_parser::SynonymMap;
+pub use self::query_parser::FieldAliasMap;
 pub use self::query::Query;
+pub use self::query_cost::QueryCost;
 pub use self::scorer::EmptyScorer;
 pub use self::scorer::Scorer;
 pub use self::term_query::TermQuery;
 pub use self::weight::Weight;
+pub use self::wildcard_query::WildcardQuery;
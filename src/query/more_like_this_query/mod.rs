@@ -0,0 +1,7 @@
+mod more_like_this_query;
+mod more_like_this_weight;
+mod more_like_this_scorer;
+
+pub use self::more_like_this_query::MoreLikeThisQuery;
+pub use self::more_like_this_weight::MoreLikeThisWeight;
+pub use self::more_like_this_scorer::MoreLikeThisScorer;
@@ -0,0 +1,385 @@
+use schema::{Field, FieldType, Schema, Term, Value};
+use query::Query;
+use query::Weight;
+use query::BooleanQuery;
+use query::BoostQuery;
+use query::TermQuery;
+use query::Occur;
+use query::term_expansion::MAX_MAX_EXPANSIONS;
+use postings::SegmentPostingsOption;
+use analyzer::TokenizerManager;
+use core::searcher::Searcher;
+use core::Index;
+use super::MoreLikeThisWeight;
+use std::any::Any;
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use DocAddress;
+use Error;
+use Result;
+use Score;
+
+/// Number of terms of the query, by default.
+const DEFAULT_MAX_QUERY_TERMS: usize = 25;
+
+#[derive(Debug)]
+enum Seed {
+    Document(DocAddress),
+    Text(String),
+}
+
+/// `MoreLikeThisQuery` matches the documents similar to a seed document,
+/// or to a seed text, for instance to display related items.
+///
+/// The text of the configured fields of the seed is tokenized
+/// with the tokenizer of each field, and its terms are ranked by TF-IDF:
+/// their frequency in the seed, times their inverse document
+/// frequency in the index. The best `max_query_terms` terms
+/// (25 by default) are searched as a disjunction, each of them
+/// boosted by its IDF relative to the highest IDF of the query.
+///
+/// The terms occurring less than `min_term_freq` times in the seed
+/// (1 by default) and the stop words are left out.
+///
+/// A seed document must be stored, as its text is read from the
+/// doc store. It is excluded from the results, unless
+/// `set_exclude_seed(false)` is called.
+pub struct MoreLikeThisQuery {
+    seed: Seed,
+    fields: Vec<Field>,
+    schema: Schema,
+    tokenizers: TokenizerManager,
+    min_term_freq: u32,
+    max_query_terms: usize,
+    stop_words: HashSet<String>,
+    exclude_seed: bool,
+}
+
+impl MoreLikeThisQuery {
+
+    fn new(index: &Index, seed: Seed, fields: Vec<Field>) -> MoreLikeThisQuery {
+        MoreLikeThisQuery {
+            seed: seed,
+            fields: fields,
+            schema: index.schema(),
+            tokenizers: index.tokenizers().clone(),
+            min_term_freq: 1,
+            max_query_terms: DEFAULT_MAX_QUERY_TERMS,
+            stop_words: HashSet::new(),
+            exclude_seed: true,
+        }
+    }
+
+    /// Creates a `MoreLikeThisQuery` for the documents similar
+    /// to the stored document `doc_address`, in `fields`.
+    ///
+    /// The tokenizers registered on `index` are used.
+    pub fn for_document(index: &Index, doc_address: DocAddress, fields: Vec<Field>) -> MoreLikeThisQuery {
+        MoreLikeThisQuery::new(index, Seed::Document(doc_address), fields)
+    }
+
+    /// Creates a `MoreLikeThisQuery` for the documents whose
+    /// `fields` are similar to `text`.
+    ///
+    /// The tokenizers registered on `index` are used.
+    pub fn for_text(index: &Index, text: &str, fields: Vec<Field>) -> MoreLikeThisQuery {
+        MoreLikeThisQuery::new(index, Seed::Text(String::from(text)), fields)
+    }
+
+    /// Sets the number of occurrences of a term in
+    /// the seed required for the term to be searched.
+    pub fn set_min_term_freq(mut self, min_term_freq: u32) -> MoreLikeThisQuery {
+        self.min_term_freq = min_term_freq;
+        self
+    }
+
+    /// Sets the maximum number of terms of the query.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max_query_terms` is 0 or greater than 64.
+    pub fn set_max_query_terms(mut self, max_query_terms: usize) -> MoreLikeThisQuery {
+        assert!(max_query_terms > 0 && max_query_terms <= MAX_MAX_EXPANSIONS);
+        self.max_query_terms = max_query_terms;
+        self
+    }
+
+    /// Sets the words that are never searched.
+    ///
+    /// They are compared to the text of the tokens, as
+    /// output by the tokenizers of the fields.
+    pub fn set_stop_words<S: Into<String>>(mut self, stop_words: Vec<S>) -> MoreLikeThisQuery {
+        self.stop_words = stop_words.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// If `exclude_seed` is true, (the default) the seed
+    /// document does not match the query.
+    ///
+    /// This has no effect on a seed text.
+    pub fn set_exclude_seed(mut self, exclude_seed: bool) -> MoreLikeThisQuery {
+        self.exclude_seed = exclude_seed;
+        self
+    }
+
+    // counts the terms of the seed, field by field.
+    fn seed_term_freqs(&self, searcher: &Searcher) -> Result<HashMap<Term, u32>> {
+        let seed_doc = match self.seed {
+            Seed::Document(ref doc_address) => {
+                let DocAddress(segment_ord, doc) = *doc_address;
+                if segment_ord as usize >= searcher.num_segments() ||
+                    doc >= searcher.segment_reader(segment_ord as usize).max_doc() {
+                    return Err(Error::InvalidArgument(format!("{:?} is not a document of the searcher.", doc_address)));
+                }
+                Some(try!(searcher.doc_fields(doc_address, &self.fields)))
+            }
+            Seed::Text(_) => None,
+        };
+        let mut term_freqs: HashMap<Term, u32> = HashMap::new();
+        for &field in &self.fields {
+            let field_entry = self.schema.get_field_entry(field);
+            let is_tokenized = match *field_entry.field_type() {
+                FieldType::Str(ref text_options) => text_options.get_indexing_options().is_tokenized(),
+                _ => false,
+            };
+            if !is_tokenized {
+                return Err(Error::SchemaError(format!("Field <{}> is not a tokenized text field.", field_entry.name())));
+            }
+            let tokenizer = try!(self.tokenizers
+                .get(field_entry.tokenizer())
+                .ok_or_else(|| Error::SchemaError(format!("The tokenizer {:?} of field <{}> is not registered.",
+                                                          field_entry.tokenizer(), field_entry.name()))));
+            let texts: Vec<&str> = match self.seed {
+                Seed::Text(ref text) => vec![&text[..]],
+                Seed::Document(_) => seed_doc
+                    .as_ref()
+                    .map(|doc| doc.get_all(field))
+                    .unwrap_or(Vec::new())
+                    .into_iter()
+                    .filter_map(|value| match *value {
+                        Value::Str(ref text) => Some(&text[..]),
+                        _ => None,
+                    })
+                    .collect(),
+            };
+            for text in texts {
+                let mut token_stream = tokenizer.token_stream(text);
+                while token_stream.advance() {
+                    let token_text = &token_stream.token().text;
+                    if self.stop_words.contains(token_text) {
+                        continue;
+                    }
+                    *term_freqs.entry(Term::from_field_text(field, token_text)).or_insert(0) += 1;
+                }
+            }
+        }
+        Ok(term_freqs)
+    }
+
+    // returns the best terms, with their TF-IDF and their IDF.
+    fn ranked_terms(&self, searcher: &Searcher) -> Result<Vec<(Term, Score, Score)>> {
+        let mut ranked_terms: Vec<(Term, Score, Score)> = try!(self.seed_term_freqs(searcher))
+            .into_iter()
+            .filter(|&(_, term_freq)| term_freq >= self.min_term_freq)
+            .filter_map(|(term, term_freq)| {
+                let doc_freq = searcher.doc_freq(&term);
+                if doc_freq == 0 {
+                    return None;
+                }
+                let field = term.field();
+                let idf = searcher.similarity(field).idf(searcher.idf_num_docs(field), searcher.idf_doc_freq(&term));
+                Some((term, term_freq as Score * idf, idf))
+            })
+            .collect();
+        ranked_terms.sort_by(|&(ref left_term, left_score, _), &(ref right_term, right_score, _)| {
+            match right_score.partial_cmp(&left_score) {
+                Some(Ordering::Equal) | None => left_term.cmp(right_term),
+                Some(ordering) => ordering,
+            }
+        });
+        ranked_terms.truncate(self.max_query_terms);
+        Ok(ranked_terms)
+    }
+
+    /// Returns the terms the query searches for, best first,
+    /// with their TF-IDF in the seed.
+    pub fn interesting_terms(&self, searcher: &Searcher) -> Result<Vec<(Term, Score)>> {
+        Ok(try!(self.ranked_terms(searcher))
+            .into_iter()
+            .map(|(term, score, _)| (term, score))
+            .collect())
+    }
+}
+
+impl fmt::Debug for MoreLikeThisQuery {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "MoreLikeThisQuery({:?}, fields={:?}, min_term_freq={}, max_query_terms={}, exclude_seed={})",
+               self.seed, self.fields, self.min_term_freq, self.max_query_terms, self.exclude_seed)
+    }
+}
+
+impl Query for MoreLikeThisQuery {
+
+    /// Used to make it possible to cast Box<Query>
+    /// into a specific type. This is mostly useful for unit tests.
+    fn as_any(&self) -> &Any {
+        self
+    }
+
+    /// Create the weight associated to a query.
+    ///
+    /// The best terms of the seed are extracted,
+    /// and searched as a disjunction.
+    ///
+    /// See [Weight](./trait.Weight.html).
+    fn weight(&self, searcher: &Searcher) -> Result<Box<Weight>> {
+        let ranked_terms = try!(self.ranked_terms(searcher));
+        let max_idf = ranked_terms
+            .iter()
+            .map(|&(_, _, idf)| idf)
+            .fold(0f32, |max_idf, idf| if idf > max_idf { idf } else { max_idf });
+        let subqueries: Vec<(Occur, Box<Query>)> = ranked_terms
+            .into_iter()
+            .map(|(term, _, idf)| {
+                let term_query: Box<Query> = box TermQuery::new(term, SegmentPostingsOption::Freq);
+                // the idf is not positive for a collection without documents.
+                let boost = if max_idf > 0f32 { idf / max_idf } else { 1f32 };
+                let boosted_query: Box<Query> = box BoostQuery::new(term_query, boost);
+                (Occur::Should, boosted_query)
+            })
+            .collect();
+        let inner = try!(BooleanQuery::from(subqueries).weight(searcher));
+        let excluded_doc = match self.seed {
+            Seed::Document(DocAddress(segment_ord, doc)) if self.exclude_seed => {
+                Some((searcher.segment_reader(segment_ord as usize).segment_id(), doc))
+            }
+            _ => None,
+        };
+        Ok(box MoreLikeThisWeight {
+            inner: inner,
+            excluded_doc: excluded_doc,
+        })
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::MoreLikeThisQuery;
+    use core::Index;
+    use schema::{Document, FieldValue, SchemaBuilder, Similarity, Term, TEXT, STORED};
+    use collector::TopCollector;
+    use query::Query;
+    use CollectionStatistics;
+    use DocAddress;
+    use Error;
+
+    #[test]
+    fn test_more_like_this() {
+        let mut schema_builder = SchemaBuilder::default();
+        let title_field = schema_builder.add_text_field("title", TEXT | STORED);
+        let body_field = schema_builder.add_text_field("body", TEXT | STORED);
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.add_document(doc!(title_field=>"Rust programming",
+                                           body_field=>"the rust programming language, ownership and borrowing in rust"));
+            index_writer.add_document(doc!(title_field=>"Cooking pasta",
+                                           body_field=>"the pasta is cooked in salted water for ten minutes"));
+            index_writer.add_document(doc!(title_field=>"Borrowing in rust",
+                                           body_field=>"the borrow checker of rust enforces ownership"));
+            index_writer.add_document(doc!(title_field=>"Gardening",
+                                           body_field=>"the roses are watered in the morning"));
+            index_writer.add_document(doc!(title_field=>"Systems programming",
+                                           body_field=>"programming a language without a garbage collector, as in rust"));
+            index_writer.add_document(doc!(title_field=>"Travel",
+                                           body_field=>"the language spoken in the city is not the one of the guide"));
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let query = MoreLikeThisQuery::for_document(&index, DocAddress(0, 0), vec!(title_field, body_field))
+            .set_stop_words(vec!("the", "in", "and", "a", "of", "as", "is"));
+        let interesting_terms = query.interesting_terms(&searcher).unwrap();
+        assert_eq!(interesting_terms[0].0, Term::from_field_text(body_field, "rust"));
+        assert!(!interesting_terms.iter().any(|&(ref term, _)| term.value() == b"the"));
+
+        let mut top_collector = TopCollector::with_limit(10);
+        query.search(&searcher, &mut top_collector).unwrap();
+        let docs: Vec<u32> = top_collector.docs().iter().map(|doc_address| doc_address.doc()).collect();
+        // the rust documents come first, and the seed is excluded.
+        let mut first_docs = docs[..2].to_vec();
+        first_docs.sort();
+        assert_eq!(first_docs, vec!(2, 4));
+        assert!(!docs.contains(&0));
+        assert!(!docs.contains(&3));
+
+        let mut top_collector = TopCollector::with_limit(10);
+        query.set_exclude_seed(false).search(&searcher, &mut top_collector).unwrap();
+        assert_eq!(top_collector.docs()[0], DocAddress(0, 0));
+
+        for &doc_address in &[DocAddress(1, 0), DocAddress(0, 6)] {
+            let query = MoreLikeThisQuery::for_document(&index, doc_address, vec!(title_field, body_field));
+            let mut top_collector = TopCollector::with_limit(10);
+            match query.search(&searcher, &mut top_collector) {
+                Err(Error::InvalidArgument(_)) => {}
+                _ => panic!("{:?} is out of range", doc_address),
+            }
+        }
+    }
+
+    #[test]
+    fn test_more_like_this_empty_collection() {
+        let mut schema_builder = SchemaBuilder::default();
+        let body_field = schema_builder.add_text_field("body", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.add_document(doc!(body_field=>"a b"));
+            index_writer.add_document(doc!(body_field=>"b c"));
+            index_writer.commit().unwrap();
+        }
+        // the BM25 idf of the terms is negative.
+        let mut searcher = index.open_searcher().unwrap();
+        searcher.set_similarity(Some(Similarity::bm25()));
+        searcher.set_collection_statistics(Some(CollectionStatistics::new(0)));
+        let query = MoreLikeThisQuery::for_text(&index, "a b", vec!(body_field));
+        let mut top_collector = TopCollector::with_limit(10);
+        query.search(&searcher, &mut top_collector).unwrap();
+        let score_docs = top_collector.score_docs();
+        assert_eq!(score_docs.len(), 2);
+        assert!(score_docs.iter().all(|&(score, _)| score.is_finite()));
+    }
+
+    #[test]
+    fn test_more_like_this_text() {
+        let mut schema_builder = SchemaBuilder::default();
+        let body_field = schema_builder.add_text_field("body", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.add_document(doc!(body_field=>"a b"));
+            index_writer.add_document(doc!(body_field=>"b c"));
+            index_writer.add_document(doc!(body_field=>"c c d"));
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let interesting_texts = |query: &MoreLikeThisQuery| -> Vec<String> {
+            query.interesting_terms(&searcher)
+                .unwrap()
+                .into_iter()
+                .map(|(term, _)| String::from_utf8_lossy(term.value()).into_owned())
+                .collect()
+        };
+        let query = MoreLikeThisQuery::for_text(&index, "d d b z", vec!(body_field));
+        // `z` is not in the index.
+        assert_eq!(interesting_texts(&query), vec!("d", "b"));
+        let query = query.set_min_term_freq(2);
+        assert_eq!(interesting_texts(&query), vec!("d"));
+        let query = MoreLikeThisQuery::for_text(&index, "a b c d", vec!(body_field)).set_max_query_terms(2);
+        assert_eq!(interesting_texts(&query), vec!("a", "d"));
+    }
+}
@@ -0,0 +1,44 @@
+use query::Scorer;
+use postings::{DocSet, SkipResult};
+use DocId;
+use Score;
+
+/// Scorer of the documents similar to the seed document,
+/// skipping the seed document itself.
+pub struct MoreLikeThisScorer<'a> {
+    pub inner: Box<Scorer + 'a>,
+    pub excluded_doc: DocId,
+}
+
+impl<'a> DocSet for MoreLikeThisScorer<'a> {
+    fn advance(&mut self) -> bool {
+        while self.inner.advance() {
+            if self.inner.doc() != self.excluded_doc {
+                return true;
+            }
+        }
+        false
+    }
+
+    fn skip_next(&mut self, target: DocId) -> SkipResult {
+        let skip_result = self.inner.skip_next(target);
+        if skip_result == SkipResult::End || self.inner.doc() != self.excluded_doc {
+            return skip_result;
+        }
+        if self.advance() {
+            SkipResult::OverStep
+        } else {
+            SkipResult::End
+        }
+    }
+
+    fn doc(&self) -> DocId {
+        self.inner.doc()
+    }
+}
+
+impl<'a> Scorer for MoreLikeThisScorer<'a> {
+    fn score(&self) -> Score {
+        self.inner.score()
+    }
+}
@@ -0,0 +1,28 @@
+use query::Weight;
+use query::Scorer;
+use core::SegmentReader;
+use core::SegmentId;
+use super::MoreLikeThisScorer;
+use Result;
+use DocId;
+
+pub struct MoreLikeThisWeight {
+    pub inner: Box<Weight>,
+    // the seed document, if it is excluded from the results.
+    pub excluded_doc: Option<(SegmentId, DocId)>,
+}
+
+impl Weight for MoreLikeThisWeight {
+    fn scorer<'a>(&'a self, reader: &'a SegmentReader) -> Result<Box<Scorer + 'a>> {
+        let inner_scorer = try!(self.inner.scorer(reader));
+        match self.excluded_doc {
+            Some((segment_id, excluded_doc)) if segment_id == reader.segment_id() => {
+                Ok(box MoreLikeThisScorer {
+                    inner: inner_scorer,
+                    excluded_doc: excluded_doc,
+                })
+            }
+            _ => Ok(inner_scorer),
+        }
+    }
+}
@@ -11,4 +11,8 @@ pub enum Occur {
     /// Document that contain the term are excluded from the
     /// search.
     MustNot,
+    /// Like `Must`, document without the term are excluded
+    /// from the search, but the term does not contribute
+    /// to the score of the documents.
+    Filter,
 }
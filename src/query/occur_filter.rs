@@ -10,35 +10,77 @@ use query::Occur;
 pub struct OccurFilter {
     and_mask: u64,
     result: u64,    
+    scoring_mask: u64,
+    should_mask: u64,
+    minimum_should_match: u32,
 }
 
 impl OccurFilter {
 
     /// Returns true if the bitset is matching the occur list.
     pub fn accept(&self, ord_set: u64) -> bool {
-        (self.and_mask & ord_set) == self.result
+        (self.and_mask & ord_set) == self.result &&
+            (self.should_mask & ord_set).count_ones() >= self.minimum_should_match
+    }
+
+    /// Sets the number of `Should` scorers that must
+    /// match for a bitset to be accepted.
+    pub fn set_minimum_should_match(&mut self, minimum_should_match: usize) {
+        self.minimum_should_match = minimum_should_match as u32;
+    }
+
+    /// Returns true if the scorer of ordinal `ord` contributes
+    /// to the score, i.e. if it is not a `Filter`.
+    pub fn is_scoring(&self, ord: usize) -> bool {
+        (self.scoring_mask & (1 << ord)) != 0
+    }
+
+    /// Returns true if the scorer of ordinal `ord` must match
+    /// for the document to match, i.e. if it is a `Must`
+    /// or a `Filter`.
+    pub fn is_required(&self, ord: usize) -> bool {
+        (self.result & (1 << ord)) != 0
+    }
+
+    /// Returns the number of scorers contributing to the score.
+    pub fn num_scoring(&self) -> usize {
+        self.scoring_mask.count_ones() as usize
     }
     
     /// Builds an `OccurFilter` from a list of `Occur`. 
     pub fn new(occurs: &[Occur]) -> OccurFilter {
         let mut and_mask = 0u64;
         let mut result = 0u64;
+        let mut scoring_mask = 0u64;
+        let mut should_mask = 0u64;
         for (i, occur) in occurs.iter().enumerate() {
             let shift = 1 << i;
             match *occur {
                 Occur::Must => {
                     and_mask |= shift;
                     result |= shift;
+                    scoring_mask |= shift;
                 },
                 Occur::MustNot => {
                     and_mask |= shift;
+                    scoring_mask |= shift;
+                },
+                Occur::Should => {
+                    scoring_mask |= shift;
+                    should_mask |= shift;
+                },
+                Occur::Filter => {
+                    and_mask |= shift;
+                    result |= shift;
                 },
-                Occur::Should => {},
             }
         }
         OccurFilter {
             and_mask: and_mask,
-            result: result
+            result: result,
+            scoring_mask: scoring_mask,
+            should_mask: should_mask,
+            minimum_should_match: 0u32,
         }
     }
 }
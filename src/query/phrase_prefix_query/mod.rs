@@ -0,0 +1,59 @@
+mod phrase_prefix_query;
+mod phrase_prefix_weight;
+
+pub use self::phrase_prefix_query::PhrasePrefixQuery;
+pub use self::phrase_prefix_weight::PhrasePrefixWeight;
+
+
+#[cfg(test)]
+mod tests {
+        
+    use super::*;
+    use core::Index;
+    use schema::FieldValue;
+    use schema::{Document, SchemaBuilder, TEXT};
+    use collector::tests::TestCollector;
+
+    #[test]
+    pub fn test_phrase_prefix_query() {
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let schema = schema_builder.build();
+        let index = Index::create_in_ram(schema);
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            {   // 0
+                let doc = doc!(text_field=>"the quick brown fox");
+                index_writer.add_document(doc);
+            }
+            {   // 1
+                let doc = doc!(text_field=>"the quick green fox");
+                index_writer.add_document(doc);
+            }
+            {   // 2
+                let doc = doc!(text_field=>"brown and quick");
+                index_writer.add_document(doc);
+            }
+            {   // 3
+                let doc = doc!(text_field=>"quick bread quick broom");
+                index_writer.add_document(doc);
+            }
+            assert!(index_writer.commit().is_ok());
+        }
+
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let test_query = |query: PhrasePrefixQuery| {
+            let mut test_collector = TestCollector::default();
+            searcher.search(&query, &mut test_collector).expect("search should succeed");
+            test_collector.docs()
+        };
+        assert_eq!(test_query(PhrasePrefixQuery::new(text_field, &["quick", "br"])), vec!(0, 3));
+        assert_eq!(test_query(PhrasePrefixQuery::new(text_field, &["quick", "brown"])), vec!(0));
+        assert_eq!(test_query(PhrasePrefixQuery::new(text_field, &["quick", "gr"])), vec!(1));
+        assert_eq!(test_query(PhrasePrefixQuery::new(text_field, &["the", "quick", "b"])), vec!(0));
+        assert_eq!(test_query(PhrasePrefixQuery::new(text_field, &["quick", "z"])), vec!());
+        assert_eq!(test_query(PhrasePrefixQuery::new(text_field, &["quick", "br"]).set_max_expansions(1)), vec!(3));
+    }
+    
+}
@@ -0,0 +1,120 @@
+use schema::Field;
+use schema::Term;
+use query::Query;
+use core::searcher::Searcher;
+use super::PhrasePrefixWeight;
+use std::any::Any;
+use query::Weight;
+use query::QueryCost;
+use query::term_expansion::{self, DEFAULT_MAX_EXPANSIONS};
+use Result;
+
+/// `PhrasePrefixQuery` matches a sequence of words,
+/// the last of which being only a prefix.
+///
+/// It is typically used for search-as-you-type.
+/// For instance the phrase prefix query for `"new yor"` 
+/// will match the sentence
+///
+/// **I love new york.**
+///
+/// The last term is expanded into the terms of the segment's
+/// dictionary that start with it. The number of expansions
+/// is capped by `max_expansions`, (50 by default) and only the 
+/// first terms in lexicographic order are kept.
+///
+/// Using a `PhrasePrefixQuery` on a field requires positions
+/// to be indexed for this field.
+#[derive(Debug)]
+pub struct PhrasePrefixQuery {
+    field: Field,
+    phrase_terms: Vec<Term>,
+    prefix: Term,
+    max_expansions: usize,
+}
+
+impl PhrasePrefixQuery {
+    
+    /// Creates a new `PhrasePrefixQuery`.
+    ///
+    /// All of the terms but the last are matched exactly, 
+    /// while the last one is handled as a prefix.
+    ///
+    /// # Panics
+    ///
+    /// Panics if less than two terms are given.
+    pub fn new(field: Field, terms: &[&str]) -> PhrasePrefixQuery {
+        assert!(terms.len() > 1);
+        let (prefix, phrase_terms) = terms.split_last().unwrap();
+        PhrasePrefixQuery {
+            field: field,
+            phrase_terms: phrase_terms
+                .iter()
+                .map(|text| Term::from_field_text(field, text))
+                .collect(),
+            prefix: Term::from_field_text(field, prefix),
+            max_expansions: DEFAULT_MAX_EXPANSIONS,
+        }
+    }
+
+    /// Sets the maximum number of terms the prefix
+    /// may be expanded into, within each segment.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max_expansions` is 0 or greater than 64.
+    pub fn set_max_expansions(mut self, max_expansions: usize) -> PhrasePrefixQuery {
+        term_expansion::check_max_expansions(max_expansions);
+        self.max_expansions = max_expansions;
+        self
+    }
+
+    /// Returns the field targeted by the query.
+    pub fn field(&self,) -> Field {
+        self.field
+    }
+}
+
+impl Query for PhrasePrefixQuery {
+
+    /// Used to make it possible to cast Box<Query>
+    /// into a specific type. This is mostly useful for unit tests.
+    fn as_any(&self) -> &Any {
+        self
+    }
+
+    fn query_terms(&self, terms: &mut Vec<Term>) {
+        // the terms the prefix expands to depend on the index.
+        terms.extend_from_slice(&self.phrase_terms);
+    }
+
+    fn estimate_cost(&self, searcher: &Searcher) -> QueryCost {
+        // the phrase terms are intersected with each of the expansions.
+        let weight = PhrasePrefixWeight::new(self.phrase_terms.clone(), self.prefix.clone(), self.max_expansions);
+        let mut cost = QueryCost::default();
+        for segment_reader in searcher.segment_readers() {
+            let phrase_cost = self.phrase_terms
+                .iter()
+                .map(|term| QueryCost::for_segment_term(segment_reader, term))
+                .fold(None, |cost: Option<QueryCost>, term_cost| {
+                    Some(cost.map_or(term_cost, |cost| cost.intersection(term_cost)))
+                })
+                .expect("A phrase prefix query has at least one phrase term.");
+            for expanded_term in weight.expand_prefix(segment_reader) {
+                let expanded_term_cost = QueryCost::for_segment_term(segment_reader, &expanded_term);
+                cost = cost.union(phrase_cost.intersection(expanded_term_cost));
+            }
+        }
+        cost
+    }
+
+    /// Create the weight associated to a query.
+    ///
+    /// See [Weight](./trait.Weight.html).
+    fn weight(&self, _searcher: &Searcher) -> Result<Box<Weight>> {
+        Ok(box PhrasePrefixWeight::new(
+            self.phrase_terms.clone(),
+            self.prefix.clone(),
+            self.max_expansions))
+    }
+}
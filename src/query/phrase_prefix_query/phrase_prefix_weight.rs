@@ -0,0 +1,79 @@
+use query::Weight;
+use query::Scorer;
+use query::Occur;
+use query::OccurFilter;
+use query::EmptyScorer;
+use query::phrase_query::PhraseScorer;
+use query::boolean_query::BooleanScorer;
+use schema::{Term, Similarity};
+use postings::SegmentPostingsOption;
+use core::SegmentReader;
+use fst::{IntoStreamer, Streamer};
+use Result;
+
+pub struct PhrasePrefixWeight {
+    phrase_terms: Vec<Term>,
+    prefix: Term,
+    max_expansions: usize,
+}
+
+impl PhrasePrefixWeight {
+    pub fn new(phrase_terms: Vec<Term>, prefix: Term, max_expansions: usize) -> PhrasePrefixWeight {
+        PhrasePrefixWeight {
+            phrase_terms: phrase_terms,
+            prefix: prefix,
+            max_expansions: max_expansions,
+        }
+    }
+
+    /// Returns the terms of the segment starting with the prefix,
+    /// in lexicographic order.
+    pub fn expand_prefix(&self, reader: &SegmentReader) -> Vec<Term> {
+        let prefix_bytes = self.prefix.as_slice();
+        let mut stream = reader
+            .term_infos()
+            .range()
+            .ge(prefix_bytes)
+            .into_stream();
+        let mut expanded_terms = Vec::new();
+        while let Some((term_bytes, _)) = stream.next() {
+            if !term_bytes.starts_with(prefix_bytes) {
+                break;
+            }
+            expanded_terms.push(Term::from_bytes(term_bytes));
+            if expanded_terms.len() == self.max_expansions {
+                break;
+            }
+        }
+        expanded_terms
+    }
+}
+
+impl Weight for PhrasePrefixWeight {
+    fn scorer<'a>(&'a self, reader: &'a SegmentReader) -> Result<Box<Scorer + 'a>> {
+        let expanded_terms = self.expand_prefix(reader);
+        if expanded_terms.is_empty() {
+            return Ok(box EmptyScorer);
+        }
+        let mut phrase_scorers = Vec::new();
+        'expansions: for expanded_term in &expanded_terms {
+            let mut term_postings_list = Vec::new();
+            for (offset, term) in self.phrase_terms.iter().chain(Some(expanded_term)).enumerate() {
+                let term_postings_option = reader.read_postings(term, SegmentPostingsOption::FreqAndPositions);
+                if let Some(term_postings) = term_postings_option {
+                    term_postings_list.push((offset, term_postings));
+                }
+                else {
+                    continue 'expansions;
+                }
+            }
+            phrase_scorers.push(PhraseScorer::new(term_postings_list, 1f32, None, Similarity::TfIdf, 0f32));
+        }
+        if phrase_scorers.is_empty() {
+            return Ok(box EmptyScorer);
+        }
+        let occurs = vec!(Occur::Should; phrase_scorers.len());
+        let occur_filter = OccurFilter::new(&occurs);
+        Ok(box BooleanScorer::new(phrase_scorers, occur_filter))
+    }
+}
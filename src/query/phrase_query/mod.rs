@@ -13,8 +13,12 @@ mod tests {
     use super::*;
     use core::Index;
     use schema::FieldValue;
-    use schema::{Document, Term, SchemaBuilder, TEXT};
+    use schema::{Document, Term, SchemaBuilder, TEXT, STRING};
     use collector::tests::TestCollector;
+    use collector::TopCollector;
+    use query::{Query, TermQuery};
+    use postings::SegmentPostingsOption;
+    use DocAddress;
 
     #[test]
     pub fn test_phrase_query() {
@@ -65,6 +69,103 @@ mod tests {
         assert_eq!(test_query(vec!("b", "b")), vec!(0, 1));
         assert_eq!(test_query(vec!("g", "ewrwer")), vec!());
         assert_eq!(test_query(vec!("g", "a")), vec!());
+        assert_eq!(test_query(vec!("d")), vec!(0, 1, 3));
+        assert_eq!(test_query(vec!("ewrwer")), vec!());
+    }
+
+    #[test]
+    pub fn test_phrase_query_phrase_freq() {
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let schema = schema_builder.build();
+        let index = Index::create_in_ram(schema);
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            // 0, the phrase appears once
+            index_writer.add_document(doc!(text_field=>"quick brown fox c quick d"));
+            // 1, the phrase appears twice
+            index_writer.add_document(doc!(text_field=>"quick brown fox quick brown fox"));
+            // 2, the terms are not consecutive
+            index_writer.add_document(doc!(text_field=>"quick c brown fox d e"));
+            assert!(index_writer.commit().is_ok());
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let terms = |texts: &[&str]| -> Vec<Term> {
+            texts
+                .iter()
+                .map(|text| Term::from_field_text(text_field, text))
+                .collect()
+        };
+        let search = |query: &Query| -> Vec<(f32, DocAddress)> {
+            let mut top_collector = TopCollector::with_limit(10);
+            searcher.search(query, &mut top_collector).unwrap();
+            top_collector.score_docs()
+        };
+        let score_docs = search(&PhraseQuery::from(terms(&["quick", "brown", "fox"])));
+        assert_eq!(score_docs.len(), 2);
+        assert_eq!(score_docs[0].1, DocAddress(0, 1));
+        assert_eq!(score_docs[1].1, DocAddress(0, 0));
+        assert!((score_docs[0].0 / score_docs[1].0 - 2f32.sqrt()).abs() < 0.0001f32);
+        let score_docs = search(&PhraseQuery::from(terms(&["brown", "fox"])));
+        assert_eq!(score_docs.len(), 3);
+        assert_eq!(score_docs[0].1, DocAddress(0, 1));
+        // a single term phrase query behaves like a term query.
+        let term_query = TermQuery::new(Term::from_field_text(text_field, "quick"), SegmentPostingsOption::Freq);
+        assert_eq!(search(&PhraseQuery::from(terms(&["quick"]))), search(&term_query));
+    }
+
+    #[test]
+    pub fn test_phrase_query_requires_positions() {
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", STRING);
+        let schema = schema_builder.build();
+        let index = Index::create_in_ram(schema);
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.add_document(doc!(text_field=>"a"));
+            assert!(index_writer.commit().is_ok());
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let phrase_query = PhraseQuery::from(vec!(Term::from_field_text(text_field, "a")));
+        let mut test_collector = TestCollector::default();
+        assert!(searcher.search(&phrase_query, &mut test_collector).is_err());
+    }
+
+    #[test]
+    pub fn test_phrase_query_with_offsets() {
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.add_document(doc!(text_field=>"a b c"));
+            index_writer.add_document(doc!(text_field=>"a c b"));
+            index_writer.add_document(doc!(text_field=>"a c"));
+            assert!(index_writer.commit().is_ok());
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let test_query = |phrase_terms: Vec<(usize, &str)>| {
+            let terms: Vec<(usize, Term)> = phrase_terms
+                .into_iter()
+                .map(|(offset, text)| (offset, Term::from_field_text(text_field, text)))
+                .collect();
+            let mut test_collector = TestCollector::default();
+            searcher.search(&PhraseQuery::with_offsets(terms), &mut test_collector).unwrap();
+            test_collector.docs()
+        };
+        assert_eq!(test_query(vec!((0, "a"), (2, "c"))), vec!(0));
+        assert_eq!(test_query(vec!((3, "a"), (5, "c"))), vec!(0));
+        // the terms are sorted by offset.
+        assert_eq!(test_query(vec!((2, "b"), (0, "a"))), vec!(1));
+        assert_eq!(test_query(vec!((0, "a"), (1, "c"))), vec!(1, 2));
+    }
+
+    #[test]
+    #[should_panic]
+    pub fn test_phrase_query_empty() {
+        PhraseQuery::from(Vec::new());
     }
-    
 }
@@ -4,7 +4,9 @@ use core::searcher::Searcher;
 use super::PhraseWeight;
 use std::any::Any;
 use query::Weight;
+use query::QueryCost;
 use Result;
+use schema::Similarity;
 
 
 /// `PhraseQuery` matches a specific sequence of word.
@@ -20,9 +22,20 @@ use Result;
 /// Using a `PhraseQuery` on a field requires positions
 /// to be indexed for this field.
 ///
+/// Documents are scored like for a `TermQuery`, using the number
+/// of occurrences of the phrase in the document as term frequency,
+/// and the sum of the idfs of the terms as idf.
+///
+/// A phrase of a single term matches the same documents,
+/// with the same scores, as a `TermQuery` on this term.
+///
+/// The terms may be given with their offsets in the phrase,
+/// so that the phrase can have gaps, e.g. where the stop words were
+/// removed: `"part of time"` then matches `"part and time"`.
 #[derive(Debug)]
 pub struct PhraseQuery {
-    phrase_terms: Vec<Term>,    
+    // sorted by offset, the first offset being 0.
+    phrase_terms: Vec<(usize, Term)>,
 }
 
 impl Query for PhraseQuery {
@@ -34,19 +47,93 @@ impl Query for PhraseQuery {
         self
     }
 
+    fn query_terms(&self, terms: &mut Vec<Term>) {
+        terms.extend(self.phrase_terms.iter().map(|&(_, ref term)| term.clone()));
+    }
+
+    fn estimate_cost(&self, searcher: &Searcher) -> QueryCost {
+        self.phrase_terms
+            .iter()
+            .map(|&(_, ref term)| QueryCost::for_term(searcher, term))
+            .fold(None, |cost: Option<QueryCost>, term_cost| {
+                Some(cost.map_or(term_cost, |cost| cost.intersection(term_cost)))
+            })
+            .expect("A phrase query has at least one term.")
+    }
+
     /// Create the weight associated to a query.
     ///
     /// See [Weight](./trait.Weight.html).
-    fn weight(&self, _searcher: &Searcher) -> Result<Box<Weight>> {
-        Ok(box PhraseWeight::from(self.phrase_terms.clone()))
+    fn weight(&self, searcher: &Searcher) -> Result<Box<Weight>> {
+        let field = self.phrase_terms[0].1.field();
+        try!(searcher.check_indexed(field));
+        let similarity = searcher.similarity(field);
+        let num_docs = searcher.idf_num_docs(field);
+        let idf = self.phrase_terms
+            .iter()
+            .map(|&(_, ref term)| similarity.idf(num_docs, searcher.idf_doc_freq(term)))
+            .sum();
+        let average_fieldnorm = if similarity.requires_average_fieldnorm() {
+            searcher.average_fieldnorm(field)
+        }
+        else {
+            0f32
+        };
+        Ok(box PhraseWeight {
+            phrase_terms: self.phrase_terms.clone(),
+            idf: idf,
+            similarity: similarity,
+            average_fieldnorm: average_fieldnorm,
+        })
+    }
+
+    fn matching_weight(&self, searcher: &Searcher) -> Result<Box<Weight>> {
+        try!(searcher.check_indexed(self.phrase_terms[0].1.field()));
+        Ok(box PhraseWeight {
+            phrase_terms: self.phrase_terms.clone(),
+            idf: 0f32,
+            similarity: Similarity::Constant,
+            average_fieldnorm: 0f32,
+        })
     }
 
 }
 
 
+/// Creates a `PhraseQuery` from its terms, in order.
+///
+/// # Panics
+/// Panics if `phrase_terms` is empty, or if the terms
+/// do not all belong to the same field.
 impl From<Vec<Term>> for PhraseQuery {
     fn from(phrase_terms: Vec<Term>) -> PhraseQuery {
-        assert!(phrase_terms.len() > 1);
+        PhraseQuery::with_offsets(phrase_terms
+            .into_iter()
+            .enumerate()
+            .collect())
+    }
+}
+
+impl PhraseQuery {
+    /// Creates a `PhraseQuery` from its terms, along with
+    /// their offsets in the phrase.
+    ///
+    /// Only the differences between the offsets matter:
+    /// `(3, a), (5, b)` matches the same documents as `(0, a), (2, b)`.
+    ///
+    /// # Panics
+    /// Panics if `phrase_terms` is empty, or if the terms
+    /// do not all belong to the same field.
+    pub fn with_offsets(mut phrase_terms: Vec<(usize, Term)>) -> PhraseQuery {
+        assert!(!phrase_terms.is_empty(), "A phrase query requires at least one term.");
+        let field = phrase_terms[0].1.field();
+        assert!(phrase_terms.iter().all(|&(_, ref term)| term.field() == field),
+                "The terms of a phrase query must belong to the same field.");
+        phrase_terms.sort_by_key(|&(offset, _)| offset);
+        let first_offset = phrase_terms[0].0;
+        for phrase_term in &mut phrase_terms {
+            phrase_term.0 -= first_offset;
+        }
         PhraseQuery {
             phrase_terms: phrase_terms,
         }
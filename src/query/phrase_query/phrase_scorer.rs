@@ -3,57 +3,93 @@ use DocSet;
 use postings::SegmentPostings;
 use postings::Postings;
 use postings::IntersectionDocSet;
+use fastfield::U32FastFieldReader;
+use schema::Similarity;
 use DocId;
+use Score;
 
 pub struct PhraseScorer<'a> {
     pub intersection_docset: IntersectionDocSet<SegmentPostings<'a>>,
+    idf: Score,
+    fieldnorm_reader_opt: Option<U32FastFieldReader>,
+    similarity: Similarity,
+    average_fieldnorm: f32,
+    // offsets of the terms in the phrase, in the order of the docsets.
+    offsets: Vec<u32>,
+    // positions at which the phrase starts in the current document.
+    phrase_positions: Vec<u32>,
 }
 
 
 impl<'a> PhraseScorer<'a> {
-    fn phrase_match(&self) -> bool {
-        let mut positions_arr: Vec<&[u32]> = self.intersection_docset
-            .docsets()
+
+    /// Creates a `PhraseScorer` over the postings of the terms
+    /// of the phrase, along with their offsets in the phrase.
+    ///
+    /// The offsets must be increasing, the first one being 0,
+    /// and the postings must have their positions.
+    pub fn new(term_postings_list: Vec<(usize, SegmentPostings<'a>)>,
+               idf: Score,
+               fieldnorm_reader_opt: Option<U32FastFieldReader>,
+               similarity: Similarity,
+               average_fieldnorm: f32) -> PhraseScorer<'a> {
+        let offsets: Vec<u32> = term_postings_list
             .iter()
-            .map(|posting| {
-                posting.positions()
-            })
+            .map(|&(offset, _)| offset as u32)
             .collect();
-        
-        let num_postings = positions_arr.len() as u32;
-        
-        let mut ord = 1u32;
-        let mut pos_candidate =  positions_arr[0][0];
-        positions_arr[0] = &(positions_arr[0])[1..];
-        let mut count_matching = 1;
-        
-        'outer: loop {
-            let target = pos_candidate + ord;
-            let positions = positions_arr[ord as usize];
-            for i in 0..positions.len() {
-                let pos_i = positions[i];
-                if pos_i < target {
-                    continue;
-                }
-                if pos_i == target {
-                    count_matching += 1;
-                    if count_matching == num_postings {
-                        return true;
-                    }
-                }
-                else if pos_i > target {
-                    count_matching = 1;
-                    pos_candidate = positions[i] - ord;
-                    positions_arr[ord as usize] = &(positions_arr[ord as usize])[(i+1)..];
+        let postings_list: Vec<SegmentPostings<'a>> = term_postings_list
+            .into_iter()
+            .map(|(_, postings)| postings)
+            .collect();
+        PhraseScorer {
+            intersection_docset: IntersectionDocSet::from(postings_list),
+            idf: idf,
+            fieldnorm_reader_opt: fieldnorm_reader_opt,
+            similarity: similarity,
+            average_fieldnorm: average_fieldnorm,
+            offsets: offsets,
+            phrase_positions: Vec::new(),
+        }
+    }
+
+    /// Computes the positions at which the phrase starts
+    /// in the current document.
+    ///
+    /// Returns true iff there is at least one.
+    fn phrase_match(&mut self) -> bool {
+        let docsets = self.intersection_docset.docsets();
+        self.phrase_positions.clear();
+        self.phrase_positions.extend_from_slice(docsets[0].positions());
+        for (ord, postings) in docsets.iter().enumerate().skip(1) {
+            // only keep the candidates followed by the term
+            // at its offset.
+            let offset = self.offsets[ord];
+            let positions = postings.positions();
+            let mut cursor = 0;
+            let mut num_kept = 0;
+            for i in 0..self.phrase_positions.len() {
+                let phrase_position = self.phrase_positions[i];
+                let target = phrase_position + offset;
+                while cursor < positions.len() && positions[cursor] < target {
+                    cursor += 1;
                 }
-                ord += 1;
-                if ord == num_postings {
-                    ord = 0;
+                if cursor < positions.len() && positions[cursor] == target {
+                    self.phrase_positions[num_kept] = phrase_position;
+                    num_kept += 1;
                 }
-                continue 'outer;
             }
-            return false;
+            self.phrase_positions.truncate(num_kept);
+            if num_kept == 0 {
+                return false;
+            }
         }
+        !self.phrase_positions.is_empty()
+    }
+
+    /// Returns the number of occurrences of the phrase
+    /// in the current document.
+    pub fn phrase_freq(&self) -> u32 {
+        self.phrase_positions.len() as u32
     }
 }
 
@@ -74,8 +110,10 @@ impl<'a> DocSet for PhraseScorer<'a> {
 
 
 impl<'a> Scorer for PhraseScorer<'a> {
-    fn score(&self,) -> f32 {
-        1f32
+    fn score(&self,) -> Score {
+        let fieldnorm_opt = self.fieldnorm_reader_opt
+            .as_ref()
+            .map(|fieldnorm_reader| fieldnorm_reader.get(self.doc()));
+        self.similarity.score(self.idf, self.phrase_freq(), fieldnorm_opt, self.average_fieldnorm)
     }
-    
 }
@@ -1,39 +1,46 @@
 use query::Weight;
 use query::Scorer;
-use schema::Term;
+use schema::{Term, FieldType, Similarity};
 use postings::SegmentPostingsOption;
 use core::SegmentReader;
 use super::PhraseScorer;
-use postings::IntersectionDocSet;
 use query::EmptyScorer;
+use Error;
 use Result;
 
 pub struct PhraseWeight {
-    phrase_terms: Vec<Term>,
-}
-
-impl From<Vec<Term>> for PhraseWeight {
-    fn from(phrase_terms: Vec<Term>) -> PhraseWeight {
-        PhraseWeight {
-            phrase_terms: phrase_terms
-        }
-    }
+    // sorted by offset, the first offset being 0.
+    pub phrase_terms: Vec<(usize, Term)>,
+    pub idf: f32,
+    pub similarity: Similarity,
+    pub average_fieldnorm: f32,
 }
 
 impl Weight for PhraseWeight {
     fn scorer<'a>(&'a self, reader: &'a SegmentReader) -> Result<Box<Scorer + 'a>> {
+        let field = self.phrase_terms[0].1.field();
+        let field_entry = reader.schema().get_field_entry(field);
+        let has_positions = match *field_entry.field_type() {
+            FieldType::Str(ref text_options) => text_options.get_indexing_options().is_position_enabled(),
+            FieldType::U32(_) | FieldType::Date(_) | FieldType::Json(_) => false,
+        };
+        if !has_positions {
+            return Err(Error::SchemaError(format!("Field {:?} does not have positions indexed.", field_entry.name())));
+        }
         let mut term_postings_list = Vec::new();
-        for term in &self.phrase_terms {            
+        for &(offset, ref term) in &self.phrase_terms {
             let term_postings_option = reader.read_postings(term, SegmentPostingsOption::FreqAndPositions);
             if let Some(term_postings) = term_postings_option {
-                term_postings_list.push(term_postings);
+                term_postings_list.push((offset, term_postings));
             }
             else {
                 return Ok(box EmptyScorer);
             }
         }
-        Ok(box PhraseScorer {
-            intersection_docset: IntersectionDocSet::from(term_postings_list),
-        })
+        Ok(box PhraseScorer::new(term_postings_list,
+                                 self.idf,
+                                 reader.get_fieldnorms_reader(field),
+                                 self.similarity,
+                                 self.average_fieldnorm))
     }
 }
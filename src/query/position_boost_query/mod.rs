@@ -0,0 +1,69 @@
+mod position_boost_query;
+mod position_boost_weight;
+mod position_boost_scorer;
+
+pub use self::position_boost_query::PositionBoostQuery;
+pub use self::position_boost_weight::PositionBoostWeight;
+pub use self::position_boost_scorer::PositionBoostScorer;
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use core::Index;
+    use schema::FieldValue;
+    use schema::{Document, Term, SchemaBuilder, TEXT};
+    use collector::TopCollector;
+    use query::{Query, TermQuery};
+    use postings::SegmentPostingsOption;
+    use DocAddress;
+
+    #[test]
+    pub fn test_position_boost_query() {
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let schema = schema_builder.build();
+        let index = Index::create_in_ram(schema);
+        let padding = vec!("x"; 50).join(" ");
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            // 0, with `a` at position 50
+            index_writer.add_document(doc!(text_field=>format!("{} a", padding)));
+            // 1, with `a` at position 0
+            index_writer.add_document(doc!(text_field=>format!("a {}", padding)));
+            // 2
+            index_writer.add_document(doc!(text_field=>format!("b {}", padding)));
+            assert!(index_writer.commit().is_ok());
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let term_a = Term::from_field_text(text_field, "a");
+        let term_query = || -> Box<Query> {
+            box TermQuery::new(term_a.clone(), SegmentPostingsOption::Freq)
+        };
+        let mut top_collector = TopCollector::with_limit(3);
+        searcher.search(&*term_query(), &mut top_collector).unwrap();
+        let inner_score = top_collector.score_docs()[0].0;
+        {
+            let position_boost_query = PositionBoostQuery::new(term_query(), vec!(term_a.clone()), 10);
+            let mut top_collector = TopCollector::with_limit(3);
+            searcher.search(&position_boost_query, &mut top_collector).unwrap();
+            let score_docs = top_collector.score_docs();
+            assert_eq!(score_docs.len(), 2);
+            assert_eq!(score_docs[0].1, DocAddress(0, 1));
+            assert_eq!(score_docs[1].1, DocAddress(0, 0));
+            assert_eq!(score_docs[0].0, inner_score + 1f32);
+            assert_eq!(score_docs[1].0, inner_score + 0.5f32.powi(5));
+        }
+        {
+            let terms = vec!(term_a.clone(), Term::from_field_text(text_field, "b"));
+            let position_boost_query = PositionBoostQuery::new(term_query(), terms, 10).set_boost(2f32);
+            let mut top_collector = TopCollector::with_limit(3);
+            searcher.search(&position_boost_query, &mut top_collector).unwrap();
+            let score_docs = top_collector.score_docs();
+            assert_eq!(score_docs.len(), 2);
+            assert_eq!(score_docs[0].0, inner_score + 2f32);
+        }
+    }
+}
@@ -0,0 +1,97 @@
+use query::Query;
+use query::Weight;
+use query::QueryCost;
+use query::DecayFunction;
+use core::searcher::Searcher;
+use schema::Term;
+use super::PositionBoostWeight;
+use std::any::Any;
+use Result;
+use Score;
+
+
+/// `PositionBoostQuery` boosts the documents in which
+/// its terms appear near the beginning of the field.
+///
+/// For each of the terms, a bonus is added to the score of the
+/// inner query. The bonus is worth `boost` when the first
+/// occurrence of the term is the first token of the field,
+/// and decreases with the position of this first occurrence.
+///
+/// This is typically useful on title-like fields.
+///
+/// The set of matching documents is left untouched.
+/// Using a `PositionBoostQuery` on a field requires positions
+/// to be indexed for this field. Terms whose positions
+/// are not available do not get any bonus.
+#[derive(Debug)]
+pub struct PositionBoostQuery {
+    inner: Box<Query>,
+    terms: Vec<Term>,
+    half_life: u32,
+    boost: Score,
+    decay_function: DecayFunction,
+}
+
+impl PositionBoostQuery {
+
+    /// Creates a new `PositionBoostQuery`.
+    ///
+    /// * inner - the query defining the matching documents and their base score
+    /// * terms - the terms whose positions are boosted
+    /// * half_life - position, in tokens, at which the bonus is divided by 2.
+    ///
+    /// The bonus is worth `1` at position `0` by default,
+    /// and its decay is exponential.
+    ///
+    /// # Panics
+    /// Panics if `half_life` is `0`.
+    pub fn new(inner: Box<Query>, terms: Vec<Term>, half_life: u32) -> PositionBoostQuery {
+        assert!(half_life > 0, "The half life must be strictly positive.");
+        PositionBoostQuery {
+            inner: inner,
+            terms: terms,
+            half_life: half_life,
+            boost: 1f32,
+            decay_function: DecayFunction::Exponential,
+        }
+    }
+
+    /// Sets the bonus given to a term appearing at position `0`.
+    pub fn set_boost(mut self, boost: Score) -> PositionBoostQuery {
+        self.boost = boost;
+        self
+    }
+
+    /// Sets the decay function.
+    pub fn set_decay_function(mut self, decay_function: DecayFunction) -> PositionBoostQuery {
+        self.decay_function = decay_function;
+        self
+    }
+}
+
+impl Query for PositionBoostQuery {
+
+    fn as_any(&self) -> &Any {
+        self
+    }
+
+    fn query_terms(&self, terms: &mut Vec<Term>) {
+        self.inner.query_terms(terms);
+    }
+
+    fn estimate_cost(&self, searcher: &Searcher) -> QueryCost {
+        self.inner.estimate_cost(searcher)
+    }
+
+    fn weight(&self, searcher: &Searcher) -> Result<Box<Weight>> {
+        let inner_weight = try!(self.inner.weight(searcher));
+        Ok(box PositionBoostWeight {
+            inner: inner_weight,
+            terms: self.terms.clone(),
+            half_life: self.half_life,
+            boost: self.boost,
+            decay_function: self.decay_function,
+        })
+    }
+}
@@ -0,0 +1,109 @@
+use query::Scorer;
+use query::DecayFunction;
+use postings::{DocSet, SkipResult};
+use postings::Postings;
+use postings::SegmentPostings;
+use DocId;
+use Score;
+
+/// Scorer adding to the score of its inner scorer a bonus
+/// for each term appearing near the beginning of the field.
+pub struct PositionBoostScorer<'a> {
+    inner: Box<Scorer + 'a>,
+    term_postings: Vec<SegmentPostings<'a>>,
+    // current doc of each of the term postings,
+    // or `None` if the postings are entirely consumed.
+    term_docs: Vec<Option<DocId>>,
+    half_life: u32,
+    boost: Score,
+    decay_function: DecayFunction,
+}
+
+impl<'a> PositionBoostScorer<'a> {
+
+    /// Creates a new `PositionBoostScorer`.
+    ///
+    /// `term_postings` are expected to have been
+    /// opened with positions, and not to have been advanced yet.
+    pub fn new(inner: Box<Scorer + 'a>,
+               mut term_postings: Vec<SegmentPostings<'a>>,
+               half_life: u32,
+               boost: Score,
+               decay_function: DecayFunction) -> PositionBoostScorer<'a> {
+        let term_docs = term_postings
+            .iter_mut()
+            .map(|postings| {
+                if postings.advance() {
+                    Some(postings.doc())
+                } else {
+                    None
+                }
+            })
+            .collect();
+        PositionBoostScorer {
+            inner: inner,
+            term_postings: term_postings,
+            term_docs: term_docs,
+            half_life: half_life,
+            boost: boost,
+            decay_function: decay_function,
+        }
+    }
+
+    // Positions the term postings on the first doc
+    // greater or equal to `target`.
+    fn sync_term_postings(&mut self, target: DocId) {
+        for (postings, term_doc) in self.term_postings.iter_mut().zip(self.term_docs.iter_mut()) {
+            if let Some(doc) = *term_doc {
+                if doc < target {
+                    *term_doc = match postings.skip_next(target) {
+                        SkipResult::End => None,
+                        SkipResult::Reached | SkipResult::OverStep => Some(postings.doc()),
+                    };
+                }
+            }
+        }
+    }
+
+    fn position_bonus(&self) -> Score {
+        let doc = self.inner.doc();
+        self.term_postings
+            .iter()
+            .zip(self.term_docs.iter())
+            .filter(|&(_, term_doc)| *term_doc == Some(doc))
+            .filter_map(|(postings, _)| postings.positions().first())
+            .map(|&position| self.boost * self.decay_function.multiplier(position, self.half_life))
+            .sum()
+    }
+}
+
+impl<'a> DocSet for PositionBoostScorer<'a> {
+    fn advance(&mut self) -> bool {
+        if self.inner.advance() {
+            let doc = self.inner.doc();
+            self.sync_term_postings(doc);
+            true
+        } else {
+            false
+        }
+    }
+
+    fn skip_next(&mut self, target: DocId) -> SkipResult {
+        let skip_result = self.inner.skip_next(target);
+        if skip_result != SkipResult::End {
+            let doc = self.inner.doc();
+            self.sync_term_postings(doc);
+        }
+        skip_result
+    }
+
+    fn doc(&self) -> DocId {
+        self.inner.doc()
+    }
+}
+
+impl<'a> Scorer for PositionBoostScorer<'a> {
+    fn score(&self) -> Score {
+        self.inner.score() + self.position_bonus()
+    }
+}
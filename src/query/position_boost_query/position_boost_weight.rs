@@ -0,0 +1,33 @@
+use query::Weight;
+use query::Scorer;
+use query::DecayFunction;
+use core::SegmentReader;
+use schema::Term;
+use postings::{SegmentPostings, SegmentPostingsOption};
+use super::PositionBoostScorer;
+use Result;
+use Score;
+
+pub struct PositionBoostWeight {
+    pub inner: Box<Weight>,
+    pub terms: Vec<Term>,
+    pub half_life: u32,
+    pub boost: Score,
+    pub decay_function: DecayFunction,
+}
+
+impl Weight for PositionBoostWeight {
+    fn scorer<'a>(&'a self, reader: &'a SegmentReader) -> Result<Box<Scorer + 'a>> {
+        let inner_scorer = try!(self.inner.scorer(reader));
+        let term_postings: Vec<SegmentPostings<'a>> = self.terms
+            .iter()
+            .filter_map(|term| reader.read_postings(term, SegmentPostingsOption::FreqAndPositions))
+            .collect();
+        Ok(box PositionBoostScorer::new(
+            inner_scorer,
+            term_postings,
+            self.half_life,
+            self.boost,
+            self.decay_function))
+    }
+}
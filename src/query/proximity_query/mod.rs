@@ -0,0 +1,57 @@
+mod proximity_query;
+mod proximity_weight;
+mod proximity_scorer;
+
+pub use self::proximity_query::ProximityQuery;
+pub use self::proximity_weight::ProximityWeight;
+pub use self::proximity_scorer::ProximityScorer;
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use super::proximity_scorer::min_span;
+    use core::Index;
+    use schema::FieldValue;
+    use schema::{Document, Term, SchemaBuilder, TEXT};
+    use collector::TopCollector;
+    use DocAddress;
+
+    #[test]
+    pub fn test_min_span() {
+        assert_eq!(min_span(&[&[3], &[4]]), 2);
+        assert_eq!(min_span(&[&[4], &[3]]), 2);
+        assert_eq!(min_span(&[&[0, 10], &[6, 12], &[2, 11]]), 3);
+        assert_eq!(min_span(&[&[0], &[7]]), 8);
+    }
+
+    #[test]
+    pub fn test_proximity_query() {
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let schema = schema_builder.build();
+        let index = Index::create_in_ram(schema);
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            // 0
+            index_writer.add_document(doc!(text_field=>"a x x x x x x b"));
+            // 1
+            index_writer.add_document(doc!(text_field=>"x x x a b x x x"));
+            // 2
+            index_writer.add_document(doc!(text_field=>"a x x x x x x x"));
+            assert!(index_writer.commit().is_ok());
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let terms = vec!(
+            Term::from_field_text(text_field, "a"),
+            Term::from_field_text(text_field, "b"));
+        let proximity_query = ProximityQuery::from(terms);
+        let mut top_collector = TopCollector::with_limit(3);
+        searcher.search(&proximity_query, &mut top_collector).expect("search should succeed");
+        let docs: Vec<DocAddress> = top_collector.docs();
+        assert_eq!(docs, vec!(DocAddress(0, 1), DocAddress(0, 0)));
+    }
+
+}
@@ -0,0 +1,74 @@
+use schema::Term;
+use query::Query;
+use query::TermQuery;
+use query::term_query::TermWeight;
+use core::searcher::Searcher;
+use super::ProximityWeight;
+use postings::SegmentPostingsOption;
+use std::any::Any;
+use query::Weight;
+use query::QueryCost;
+use Result;
+
+
+/// `ProximityQuery` matches the documents containing all of its terms,
+/// in any order.
+///
+/// Each document is scored as the sum of the `TermQuery`
+/// scores of its terms, plus a bonus that is inversely proportional
+/// to the length of the smallest window of tokens containing
+/// all of the terms.
+///
+/// For instance, for the terms `part` and `time`,
+/// **Alan just got a part time job.** will score higher than
+/// **This part of the job takes time.**
+///
+/// Using a `ProximityQuery` on a field requires positions
+/// to be indexed for this field.
+#[derive(Debug)]
+pub struct ProximityQuery {
+    terms: Vec<Term>,
+}
+
+impl Query for ProximityQuery {
+
+    fn as_any(&self) -> &Any {
+        self
+    }
+
+    fn query_terms(&self, terms: &mut Vec<Term>) {
+        terms.extend_from_slice(&self.terms);
+    }
+
+    fn estimate_cost(&self, searcher: &Searcher) -> QueryCost {
+        self.terms
+            .iter()
+            .map(|term| QueryCost::for_term(searcher, term))
+            .fold(None, |cost: Option<QueryCost>, term_cost| {
+                Some(cost.map_or(term_cost, |cost| cost.intersection(term_cost)))
+            })
+            .expect("A proximity query has at least two terms.")
+    }
+
+    fn weight(&self, searcher: &Searcher) -> Result<Box<Weight>> {
+        let term_weights: Vec<TermWeight> = self.terms
+            .iter()
+            .map(|term| {
+                TermQuery::new(term.clone(), SegmentPostingsOption::FreqAndPositions)
+                    .specialized_weight(searcher)
+            })
+            .collect();
+        Ok(box ProximityWeight::from(term_weights))
+    }
+
+}
+
+
+impl From<Vec<Term>> for ProximityQuery {
+    fn from(terms: Vec<Term>) -> ProximityQuery {
+        assert!(terms.len() > 1);
+        ProximityQuery {
+            terms: terms,
+        }
+    }
+}
@@ -0,0 +1,88 @@
+use query::Scorer;
+use query::term_query::TermScorer;
+use DocSet;
+use postings::SegmentPostings;
+use postings::Postings;
+use postings::IntersectionDocSet;
+use DocId;
+use Score;
+
+
+/// Returns the length, in tokens, of the smallest window
+/// containing at least one position of each of the position lists.
+///
+/// All position lists are expected to be sorted and non-empty.
+pub fn min_span(positions_arr: &[&[u32]]) -> u32 {
+    let mut cursors: Vec<usize> = vec!(0; positions_arr.len());
+    let mut best_span = u32::max_value();
+    loop {
+        let mut min_ord = 0;
+        let mut min_pos = u32::max_value();
+        let mut max_pos = 0u32;
+        for (ord, positions) in positions_arr.iter().enumerate() {
+            let pos = positions[cursors[ord]];
+            if pos < min_pos {
+                min_pos = pos;
+                min_ord = ord;
+            }
+            if pos > max_pos {
+                max_pos = pos;
+            }
+        }
+        let span = max_pos - min_pos + 1;
+        if span < best_span {
+            best_span = span;
+        }
+        cursors[min_ord] += 1;
+        if cursors[min_ord] == positions_arr[min_ord].len() {
+            return best_span;
+        }
+    }
+}
+
+
+/// `ProximityScorer` scores the documents containing
+/// all of the terms.
+///
+/// The score is the sum of the term scores, plus a
+/// proximity bonus equal to `num_terms / min_span`.
+/// The bonus is worth `1` when the terms are adjacent.
+pub struct ProximityScorer<'a> {
+    pub intersection_docset: IntersectionDocSet<TermScorer<SegmentPostings<'a>>>,
+}
+
+impl<'a> ProximityScorer<'a> {
+    fn proximity_bonus(&self) -> Score {
+        let term_scorers = self.intersection_docset.docsets();
+        let positions_arr: Vec<&[u32]> = term_scorers
+            .iter()
+            .map(|term_scorer| term_scorer.postings().positions())
+            .collect();
+        if positions_arr.iter().any(|positions| positions.is_empty()) {
+            return 0f32;
+        }
+        let span = min_span(&positions_arr);
+        (term_scorers.len() as Score) / (span as Score)
+    }
+}
+
+impl<'a> DocSet for ProximityScorer<'a> {
+    fn advance(&mut self,) -> bool {
+        self.intersection_docset.advance()
+    }
+
+    fn doc(&self,) -> DocId {
+        self.intersection_docset.doc()
+    }
+}
+
+impl<'a> Scorer for ProximityScorer<'a> {
+    fn score(&self,) -> Score {
+        let terms_score: Score = self.intersection_docset
+            .docsets()
+            .iter()
+            .map(|term_scorer| term_scorer.score())
+            .sum();
+        terms_score + self.proximity_bonus()
+    }
+}
@@ -0,0 +1,33 @@
+use query::Weight;
+use query::Scorer;
+use query::term_query::TermWeight;
+use core::SegmentReader;
+use super::ProximityScorer;
+use postings::IntersectionDocSet;
+use postings::SegmentPostings;
+use query::term_query::TermScorer;
+use Result;
+
+pub struct ProximityWeight {
+    term_weights: Vec<TermWeight>,
+}
+
+impl From<Vec<TermWeight>> for ProximityWeight {
+    fn from(term_weights: Vec<TermWeight>) -> ProximityWeight {
+        ProximityWeight {
+            term_weights: term_weights
+        }
+    }
+}
+
+impl Weight for ProximityWeight {
+    fn scorer<'a>(&'a self, reader: &'a SegmentReader) -> Result<Box<Scorer + 'a>> {
+        let term_scorers: Vec<TermScorer<SegmentPostings<'a>>> = try!(self.term_weights
+            .iter()
+            .map(|term_weight| term_weight.specialized_scorer(reader))
+            .collect());
+        Ok(box ProximityScorer {
+            intersection_docset: IntersectionDocSet::from(term_scorers),
+        })
+    }
+}
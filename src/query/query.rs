@@ -3,9 +3,11 @@ use collector::Collector;
 use core::searcher::Searcher;
 use common::TimerTree;
 use SegmentLocalId;
-use super::Weight;
+use SegmentReader;
+use super::{Weight, QueryCost};
 use std::fmt;
 use std::any::Any;
+use schema::Term;
 
 
 /// Query trait are in charge of defining :
@@ -37,7 +39,7 @@ use std::any::Any;
 ///
 /// When implementing a new type of `Query`, it is normal to implement a
 /// dedicated `Query`, `Weight` and `Scorer`.
-pub trait Query: fmt::Debug {
+pub trait Query: fmt::Debug + Send + Sync {
     
     /// Used to make it possible to cast Box<Query>
     /// into a specific type. This is mostly useful for unit tests.
@@ -47,6 +49,36 @@ pub trait Query: fmt::Debug {
     ///
     /// See [Weight](./trait.Weight.html).
     fn weight(&self, searcher: &Searcher) -> Result<Box<Weight>>;
+
+    /// Creates a weight matching the same documents as `.weight(...)`,
+    /// for uses that do not need their score, such as counting them.
+    ///
+    /// Queries computing statistics of the searcher to score the
+    /// documents (idf, average field norm...) skip them here.
+    /// By default, this is the weight of `.weight(...)`.
+    fn matching_weight(&self, searcher: &Searcher) -> Result<Box<Weight>> {
+        self.weight(searcher)
+    }
+
+    /// Appends the terms the query searches for to `terms`.
+    ///
+    /// This is used to highlight the matched terms,
+    /// see `SnippetGenerator`. The terms of the excluded
+    /// clauses of a `BooleanQuery` are not included, nor are the
+    /// terms of the queries that depend on the index, such as
+    /// the `WildcardQuery`, which appends nothing.
+    fn query_terms(&self, _terms: &mut Vec<Term>) {}
+
+    /// Estimates the cost of executing the query,
+    /// without executing it.
+    ///
+    /// By default, the query is assumed to go through all
+    /// of the documents of the searcher.
+    ///
+    /// See `QueryCost`.
+    fn estimate_cost(&self, searcher: &Searcher) -> QueryCost {
+        QueryCost::full_scan(searcher)
+    }
     
     /// Search works as follows :
     ///
@@ -57,10 +89,25 @@ pub trait Query: fmt::Debug {
     /// - creates a `Scorer` object associated for this segment
     /// - iterate throw the matched documents and push them to the collector.
     ///
+    /// The loop stops as soon as the collector gets aborted.
+    ///
     fn search(
         &self,
         searcher: &Searcher,
         collector: &mut Collector) -> Result<TimerTree> {
+        self.search_segments(searcher, collector, &|_| true)
+    }
+
+    /// Searches the segments accepted by `segment_filter` only,
+    /// as [search](#method.search) does.
+    ///
+    /// The documents keep their address within the searcher, and
+    /// their scores are computed with the statistics of all of the segments.
+    fn search_segments(
+        &self,
+        searcher: &Searcher,
+        collector: &mut Collector,
+        segment_filter: &Fn(&SegmentReader) -> bool) -> Result<TimerTree> {
             
         let mut timer_tree = TimerTree::default();    
         let weight = try!(self.weight(searcher));
@@ -68,12 +115,19 @@ pub trait Query: fmt::Debug {
         {
             let mut search_timer = timer_tree.open("search");
             for (segment_ord, segment_reader) in searcher.segment_readers().iter().enumerate() {
+                if collector.is_aborted() {
+                    break;
+                }
+                if !segment_filter(segment_reader) {
+                    continue;
+                }
                 let mut segment_search_timer = search_timer.open("segment_search");
                 {
                     let _ = segment_search_timer.open("set_segment");
                     try!(collector.set_segment(segment_ord as SegmentLocalId, &segment_reader));
                 }
                 let mut scorer = try!(weight.scorer(segment_reader));
+                segment_reader.record_access();
                 {
                     let _collection_timer = segment_search_timer.open("collection");
                     scorer.collect(collector);
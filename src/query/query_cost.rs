@@ -0,0 +1,151 @@
+use std::cmp;
+use core::searcher::Searcher;
+use core::SegmentReader;
+use compression::NUM_DOCS_PER_BLOCK;
+use schema::Term;
+
+
+/// Estimate of the work required to execute a query,
+/// computed without executing it.
+///
+/// The estimate is derived from the document frequency
+/// of the terms of the query, and from the structure of the query:
+/// - a union goes through the documents of all of its clauses,
+/// - an intersection goes through the documents of its rarest clause,
+/// but still decodes the blocks of all of them.
+///
+/// It is meant to compare queries with each other, for instance
+/// to reject or queue the expensive ones before executing them.
+/// See `Searcher::estimate_cost`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct QueryCost {
+    /// Number of documents the query is expected to go through.
+    pub num_docs: u64,
+    /// Number of blocks of postings the query is expected to decode.
+    pub num_blocks: u64,
+}
+
+impl QueryCost {
+
+    /// Returns the cost of going through the postings of `term`,
+    /// in all of the segments of the searcher.
+    pub fn for_term(searcher: &Searcher, term: &Term) -> QueryCost {
+        searcher
+            .segment_readers()
+            .iter()
+            .map(|segment_reader| QueryCost::for_segment_term(segment_reader, term))
+            .fold(QueryCost::default(), QueryCost::union)
+    }
+
+    /// Returns the cost of going through the postings of `term`,
+    /// in a single segment.
+    pub fn for_segment_term(segment_reader: &SegmentReader, term: &Term) -> QueryCost {
+        let doc_freq = segment_reader.doc_freq(term) as usize;
+        QueryCost {
+            num_docs: doc_freq as u64,
+            num_blocks: ((doc_freq + NUM_DOCS_PER_BLOCK - 1) / NUM_DOCS_PER_BLOCK) as u64,
+        }
+    }
+
+    /// Returns the cost of going through all of the documents
+    /// of the searcher, without decoding any postings.
+    ///
+    /// This is the cost of the queries that do not rely on
+    /// the postings, such as the `GeoBoundingBoxQuery`.
+    pub fn full_scan(searcher: &Searcher) -> QueryCost {
+        QueryCost {
+            num_docs: searcher
+                .segment_readers()
+                .iter()
+                .map(|segment_reader| segment_reader.max_doc() as u64)
+                .sum(),
+            num_blocks: 0,
+        }
+    }
+
+    /// Returns the cost of the union of two queries.
+    pub fn union(self, other: QueryCost) -> QueryCost {
+        QueryCost {
+            num_docs: self.num_docs + other.num_docs,
+            num_blocks: self.num_blocks + other.num_blocks,
+        }
+    }
+
+    /// Returns the cost of the intersection of two queries.
+    pub fn intersection(self, other: QueryCost) -> QueryCost {
+        QueryCost {
+            num_docs: cmp::min(self.num_docs, other.num_docs),
+            num_blocks: self.num_blocks + other.num_blocks,
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use core::Index;
+    use query::{BooleanQuery, Occur, PhraseQuery, Query, TermQuery, WildcardQuery};
+    use postings::SegmentPostingsOption;
+    use schema::{Document, FieldValue, SchemaBuilder, TEXT};
+
+    #[test]
+    fn test_estimate_cost() {
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            for i in 0..1_000 {
+                let text = if i % 100 == 0 {
+                    format!("common rare a{}", i)
+                }
+                else {
+                    format!("common a{}", i)
+                };
+                index_writer.add_document(doc!(text_field=>text));
+            }
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let term_query = |text: &str| -> Box<Query> {
+            box TermQuery::new(Term::from_field_text(text_field, text), SegmentPostingsOption::NoFreq)
+        };
+        let rare_cost = searcher.estimate_cost(&*term_query("rare"));
+        assert_eq!(rare_cost, QueryCost { num_docs: 10, num_blocks: 1 });
+        let common_cost = searcher.estimate_cost(&*term_query("common"));
+        assert_eq!(common_cost, QueryCost { num_docs: 1_000, num_blocks: 8 });
+        assert_eq!(searcher.estimate_cost(&*term_query("missing")), QueryCost::default());
+
+        // the prefix expands to `a0`, `a1`, `a10`, `a100`...
+        let prefix_cost = searcher.estimate_cost(&WildcardQuery::new(text_field, "a*"));
+        assert_eq!(prefix_cost, QueryCost { num_docs: 50, num_blocks: 50 });
+        assert!(rare_cost.num_docs < prefix_cost.num_docs);
+        assert!(rare_cost.num_blocks < prefix_cost.num_blocks);
+        assert!(rare_cost.num_docs < common_cost.num_docs);
+        assert!(rare_cost.num_blocks < common_cost.num_blocks);
+
+        let intersection = BooleanQuery::from(vec!(
+            (Occur::Must, term_query("rare")),
+            (Occur::Must, term_query("common")),
+        ));
+        assert_eq!(searcher.estimate_cost(&intersection), QueryCost { num_docs: 10, num_blocks: 9 });
+        let union = BooleanQuery::from(vec!(
+            (Occur::Should, term_query("rare")),
+            (Occur::Should, term_query("common")),
+        ));
+        assert_eq!(searcher.estimate_cost(&union), QueryCost { num_docs: 1_010, num_blocks: 9 });
+        let exclusion = BooleanQuery::from(vec!(
+            (Occur::Should, term_query("rare")),
+            (Occur::MustNot, term_query("common")),
+        ));
+        assert_eq!(searcher.estimate_cost(&exclusion), QueryCost { num_docs: 10, num_blocks: 9 });
+        let phrase_query = PhraseQuery::from(vec!(
+            Term::from_field_text(text_field, "common"),
+            Term::from_field_text(text_field, "rare"),
+        ));
+        assert_eq!(searcher.estimate_cost(&phrase_query), QueryCost { num_docs: 10, num_blocks: 9 });
+    }
+}
@@ -0,0 +1,36 @@
+use std::collections::HashMap;
+use schema::Field;
+
+
+/// Map from alternative field names to fields,
+/// used by the `QueryParser` to resolve the field names of a query.
+///
+/// This makes it possible to rename a field of the schema while
+/// keeping the queries referencing its former name valid.
+///
+/// The fields of the schema take precedence over the aliases:
+/// an alias named after an existing field is ignored.
+#[derive(Default, Clone)]
+pub struct FieldAliasMap {
+    aliases: HashMap<String, Field>,
+}
+
+impl FieldAliasMap {
+
+    /// Registers `alias` as an alternative name for `field`.
+    ///
+    /// Registering the same alias again replaces its field.
+    pub fn add_alias(&mut self, alias: &str, field: Field) {
+        self.aliases.insert(String::from(alias), field);
+    }
+
+    /// Returns the field registered for `alias`.
+    pub fn field(&self, alias: &str) -> Option<Field> {
+        self.aliases.get(alias).cloned()
+    }
+
+    /// Returns true iff no alias has been registered.
+    pub fn is_empty(&self) -> bool {
+        self.aliases.is_empty()
+    }
+}
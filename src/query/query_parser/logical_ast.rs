@@ -1,17 +1,23 @@
 use std::fmt;
+use std::collections::Bound;
 use schema::Term;
+use schema::Field;
 use query::Occur;
 
 #[derive(Clone)]
 pub enum LogicalLiteral {
     Term(Term),
-    Phrase(Vec<Term>),
+    // the terms along with their positions in the phrase.
+    Phrase(Vec<(usize, Term)>),
+    Wildcard(Field, String),
+    Range(Field, Bound<Term>, Bound<Term>),
 }
 
 #[derive(Clone)]
 pub enum LogicalAST{
     Clause(Vec<(Occur, LogicalAST)>),
-    Leaf(Box<LogicalLiteral>)  
+    Leaf(Box<LogicalLiteral>),
+    Boost(Box<LogicalAST>, f32),
 }
 
 fn occur_letter(occur: Occur) -> &'static str {
@@ -19,6 +25,7 @@ fn occur_letter(occur: Occur) -> &'static str {
         Occur::Must => "+",
         Occur::MustNot => "-",
         Occur::Should => "",
+        Occur::Filter => "#",
     }
 }
 
@@ -42,6 +49,9 @@ impl fmt::Debug for LogicalAST {
             LogicalAST::Leaf(ref literal) => {
                 write!(formatter, "{:?}", literal)
             }
+            LogicalAST::Boost(ref subquery, boost) => {
+                write!(formatter, "({:?})^{}", subquery, boost)
+            }
         }
     }
 }
@@ -59,8 +69,15 @@ impl fmt::Debug for LogicalLiteral {
                 write!(formatter, "{:?}", term)
             },
             LogicalLiteral::Phrase(ref terms) => {
+                let terms: Vec<&Term> = terms.iter().map(|&(_, ref term)| term).collect();
                 write!(formatter, "\"{:?}\"", terms)
             }
+            LogicalLiteral::Wildcard(field, ref pattern) => {
+                write!(formatter, "Wildcard({:?}, {:?})", field, pattern)
+            }
+            LogicalLiteral::Range(field, ref lower, ref upper) => {
+                write!(formatter, "Range({:?}, {:?}, {:?})", field, lower, upper)
+            }
         }
     }
 }
@@ -1,7 +1,11 @@
 mod query_parser;
 mod query_grammar;
 mod user_input_ast;
+mod synonym_map;
+mod field_alias_map;
 
 pub mod logical_ast;
 pub use self::query_parser::QueryParser;
-pub use self::query_parser::QueryParserError;
\ No newline at end of file
+pub use self::query_parser::QueryParserError;
+pub use self::synonym_map::SynonymMap;
+pub use self::field_alias_map::FieldAliasMap;
\ No newline at end of file
@@ -2,30 +2,124 @@ use combine::*;
 use combine::char::*;
 use super::user_input_ast::*;
 
+/// A field name, e.g. `title`.
+/// Dots separate the field of a JSON field from the path
+/// within its objects, e.g. `attributes.size.width`.
+fn field_name<I>(input: I) -> ParseResult<String, I>
+    where I: Stream<Item = char>
+{
+    (letter(), many(satisfy(|c: char| c.is_alphanumeric() || c == '_' || c == '.')))
+        .map(|(first, rest): (char, String)| format!("{}{}", first, rest))
+        .parse_stream(input)
+}
+
 fn literal<I>(input: I) -> ParseResult<UserInputAST, I>
     where I: Stream<Item = char>
 {
+    // the boolean tells whether the value is a word
+    // that contains wildcards.
     let term_val = || {
-        let word = many1(satisfy(|c: char| c.is_alphanumeric()));
-        let phrase = (char('"'), many1(satisfy(|c| c != '"')), char('"')).map(|(_, s, _)| s);
-        phrase.or(word)
+        let word = many1(satisfy(|c: char| c.is_alphanumeric() || c == '.' || c == '*' || c == '?'))
+            .map(|s: String| {
+                let is_wildcard = s.contains(|c| c == '*' || c == '?');
+                (s, is_wildcard)
+            });
+        let phrase = (char('"'), many1(satisfy(|c| c != '"')), char('"')).map(|(_, s, _)| (s, false));
+        phrase.or(try(parser(date))).or(word)
     };
-    let field = many1(letter());
-    let term_query = (field, char(':'), term_val()).map(|(field_name, _, phrase)| {
-        UserInputLiteral {
+    let field = parser(field_name);
+    let term_query = (field, char(':'), term_val()).map(|(field_name, _, (phrase, is_wildcard))| {
+        (UserInputLiteral {
             field_name: Some(field_name),
             phrase: phrase,
-        }
+        }, is_wildcard)
     });
-    let term_default_field = term_val().map(|phrase| {
-        UserInputLiteral {
+    let term_default_field = term_val().map(|(phrase, is_wildcard)| {
+        (UserInputLiteral {
             field_name: None,
             phrase: phrase,
-        }
+        }, is_wildcard)
     });
-    try(term_query)
+    let term = try(term_query)
         .or(term_default_field)
-        .map(|query_literal| UserInputAST::from(query_literal))
+        .map(|(query_literal, is_wildcard)| {
+            if is_wildcard {
+                UserInputAST::Wildcard(box query_literal)
+            } else {
+                UserInputAST::from(query_literal)
+            }
+        });
+    // the boost is validated by the query parser.
+    let boost = (char('^'), many1(satisfy(|c: char| c.is_digit(10) || c == '.')))
+        .map(|(_, boost)| boost);
+    (try(parser(range)).or(try(parser(group))).or(term), optional(boost))
+        .map(|(ast, boost_opt): (UserInputAST, Option<String>)| {
+            match boost_opt {
+                Some(boost) => UserInputAST::Boost(box ast, boost),
+                None => ast,
+            }
+        })
+        .parse_stream(input)
+}
+
+
+/// A datetime, e.g. `2017-03-12T09:30:00Z`.
+/// It is only validated by the query parser, once the type
+/// of its field is known.
+fn date<I>(input: I) -> ParseResult<(String, bool), I>
+    where I: Stream<Item = char>
+{
+    let year = (digit(), digit(), digit(), digit())
+        .map(|(d0, d1, d2, d3)| [d0, d1, d2, d3].iter().cloned().collect::<String>());
+    let month = (digit(), digit()).map(|(d0, d1)| [d0, d1].iter().cloned().collect::<String>());
+    let time = many1(satisfy(|c: char| c.is_alphanumeric() || c == ':' || c == '.' || c == '+' || c == '-'));
+    (year, char('-'), month, char('-'), time)
+        .map(|(year, _, month, _, time): (String, char, String, char, String)| {
+            (format!("{}-{}-{}", year, month, time), false)
+        })
+        .parse_stream(input)
+}
+
+
+/// A range of values, optionally prefixed by a field.
+/// `[` and `]` delimit inclusive bounds, `{` and `}` exclusive bounds,
+/// and `*` stands for an unbounded side.
+/// e.g. `created:[2017-01-01T00:00:00Z TO *}`
+fn range<I>(input: I) -> ParseResult<UserInputAST, I>
+    where I: Stream<Item = char>
+{
+    let field = (parser(field_name), char(':')).map(|(field_name, _): (String, _)| field_name);
+    let bound_value = || many1(satisfy(|c: char| !c.is_whitespace() && c != ']' && c != '}'));
+    let lower = (one_of("[{".chars()), spaces(), bound_value())
+        .map(|(bracket, _, value): (char, _, String)| UserInputBound::new(value, bracket == '['));
+    let upper = (bound_value(), spaces(), one_of("]}".chars()))
+        .map(|(value, _, bracket): (String, _, char)| UserInputBound::new(value, bracket == ']'));
+    (optional(try(field)), lower, skip_many1(space()), string("TO"), skip_many1(space()), upper)
+        .map(|(field_name, lower, _, _, _, upper)| {
+            UserInputAST::Range {
+                field_name: field_name,
+                lower: lower,
+                upper: upper,
+            }
+        })
+        .parse_stream(input)
+}
+
+
+/// Subqueries within parentheses, optionally prefixed by a field
+/// which is then the field of the literals that do not have one.
+/// e.g. `title:(rust "search engine")`
+fn group<I>(input: I) -> ParseResult<UserInputAST, I>
+    where I: Stream<Item = char>
+{
+    let field = (parser(field_name), char(':')).map(|(field_name, _): (String, _)| field_name);
+    (optional(try(field)), between(char('('), char(')'), parser(parse_to_ast)))
+        .map(|(field_name_opt, ast): (Option<String>, UserInputAST)| {
+            match field_name_opt {
+                Some(field_name) => ast.with_default_field(&field_name),
+                None => ast,
+            }
+        })
         .parse_stream(input)
 }
 
@@ -79,6 +173,28 @@ mod test {
         test_parse_query_to_ast_helper("-abc:toto", "-(abc:\"toto\")");
         test_parse_query_to_ast_helper("abc:a b", "abc:\"a\" \"b\"");
         test_parse_query_to_ast_helper("abc:\"a b\"", "abc:\"a b\"");
+        test_parse_query_to_ast_helper("abc:toto^2.5", "(abc:\"toto\")^2.5");
+        test_parse_query_to_ast_helper("+\"a b\"^2 c", "+((\"a b\")^2) \"c\"");
+        test_parse_query_to_ast_helper("abc:te?t", "abc:te?t");
+        test_parse_query_to_ast_helper("+sea* \"te?t\"", "+(sea*) \"te?t\"");
+        test_parse_query_to_ast_helper("*arch^2", "(*arch)^2");
+        test_parse_query_to_ast_helper("title:(a b)", "title:\"a\" title:\"b\"");
+        test_parse_query_to_ast_helper("title:(a body:\"b c\")^2 d", "(title:\"a\" body:\"b c\")^2 \"d\"");
+        test_parse_query_to_ast_helper("+(a te?t)", "+(\"a\" te?t)");
+        test_parse_query_to_ast_helper("title:((a) b)", "title:\"a\" title:\"b\"");
+        test_parse_query_to_ast_helper("created:2017-03-12T09:30:00.250+02:00", "created:\"2017-03-12T09:30:00.250+02:00\"");
+        test_parse_query_to_ast_helper("2017-03-12T09:30:00Z", "\"2017-03-12T09:30:00Z\"");
+        test_parse_query_to_ast_helper("created:[2017-01-01T00:00:00Z TO 2017-12-31T23:59:59Z]",
+                                       "created:[2017-01-01T00:00:00Z TO 2017-12-31T23:59:59Z]");
+        test_parse_query_to_ast_helper("created:{a TO *]^2 b", "(created:{a TO *])^2 \"b\"");
+        test_parse_query_to_ast_helper("+[ a TO b }", "+([a TO b})");
+        test_parse_query_to_ast_helper("title:([a TO b] c)", "title:[a TO b] title:\"c\"");
+        test_parse_query_to_ast_helper("attributes.size_2.width:3.5", "attributes.size_2.width:\"3.5\"");
+        test_parse_query_to_ast_helper("attributes.width:[-2.5 TO 10}", "attributes.width:[-2.5 TO 10}");
+        test_parse_query_to_ast_helper("attributes.color:(red blue)", "attributes.color:\"red\" attributes.color:\"blue\"");
+        test_is_parse_err("title:(a b)^");
         test_is_parse_err("abc +    ");
+        test_is_parse_err("abc^");
+        test_is_parse_err("abc^x");
     }
 }
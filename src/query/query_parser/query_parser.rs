@@ -1,6 +1,7 @@
 use schema::{Schema, Field};
 use query::Query;
 use query::BooleanQuery;
+use query::BoostQuery;
 use super::logical_ast::*;
 use super::user_input_ast::*;
 use super::query_grammar::parse_to_ast;
@@ -8,9 +9,21 @@ use query::Occur;
 use query::TermQuery;
 use postings::SegmentPostingsOption;
 use query::PhraseQuery;
+use query::WildcardQuery;
+use query::TermRangeQuery;
 use analyzer::SimpleTokenizer;
 use analyzer::StreamingIterator;
+use analyzer::TokenizerManager;
+use analyzer::keyword_token;
+use schema::FieldType;
 use schema::Term;
+use schema::DateTime;
+use super::SynonymMap;
+use super::FieldAliasMap;
+use Index;
+use std::ascii::AsciiExt;
+use std::collections::Bound;
+use std::f64;
 
 
 
@@ -22,15 +35,45 @@ pub enum QueryParserError {
     /// `FieldDoesNotExist(field_name: String)`
     /// The query references a field that is not in the schema
     FieldDoesNotExist(String),
+    /// `FieldNotIndexed(field_name: String)`
+    /// The query searches a field that is not indexed.
+    FieldNotIndexed(String),
     /// `ExpectedU32(field_name: String, field_value: String)`
     /// The query contains a term for a `u32`-field, but the value
     /// is not a u32.
     ExpectedU32(String, String),
+    /// `ExpectedDate(field_name: String, field_value: String)`
+    /// The query contains a term for a date field, but the value
+    /// is not an RFC 3339 datetime.
+    ExpectedDate(String, String),
+    /// `ExpectedNumber(field_name: String, field_value: String)`
+    /// The query contains a range over the numeric leaves of a JSON field,
+    /// but a bound is not a number.
+    ExpectedNumber(String, String),
+    /// `JsonPathRequired(field_name: String)`
+    /// The query searches a JSON field without a path
+    /// within its objects. e.g. `attributes:red` instead of `attributes.color:red`.
+    JsonPathRequired(String),
     /// It is forbidden queries that are only "excluding". (e.g. -title:pop)
     AllButQueryForbidden,
     /// If no default field is declared, running a query without any
     /// field specified is forbbidden.
     NoDefaultFieldDeclared,
+    /// `InvalidBoost(boost: String)`
+    /// The boost of a term or phrase is not a valid number.
+    InvalidBoost(String),
+    /// `UnsupportedWildcard(field_name: String)`
+    /// The query contains a wildcard pattern for a field
+    /// that is not a text field.
+    UnsupportedWildcard(String),
+    /// `UnsupportedRange(field_name: String)`
+    /// The query contains a range over a JSON path
+    /// of a field that is not fast.
+    UnsupportedRange(String),
+    /// `UnknownTokenizer(tokenizer_name: String)`
+    /// The tokenizer of a field is not registered
+    /// in the `TokenizerManager` of the query parser.
+    UnknownTokenizer(String),
 }
 
 
@@ -63,11 +106,61 @@ pub enum QueryParserError {
 ///
 /// * must terms: By prepending a term by a `+`, a term can be made required for the search.
 ///
+/// * untokenized fields: The value of a field indexed without tokenization
+///   (e.g. `STRING` or `STRING_CI`) is searched as a whole. e.g. `company:"Apple Inc."`
+///
+/// * synonyms: If a `SynonymMap` is set, each literal of the query that has synonyms
+///   is replaced by a disjunction of the literal and its synonyms.
+///   e.g. `tv` becomes `tv OR television`. Multi-word synonyms are searched
+///   as phrases.
+///
+/// * field aliases: If a `FieldAliasMap` is set, the aliases it contains can be
+///   used in place of the names of the fields. e.g. `headline:rust` searches
+///   the `title` field if `headline` is an alias of `title`.
+///
+/// * boosts: Appending `^` and a number to a term, a phrase or a group multiplies
+///   its score by this number. e.g. `rust^2 search` or `"tantivy search"^1.5`.
+///   A boost of `0` keeps the matches, but cancels their contribution to the score.
+///
+/// * groups: Subqueries can be grouped within parentheses. A field prefixing
+///   the group applies to all of the subqueries that do not specify one.
+///   e.g. `title:(rust "search engine")^2 body:rust`
+///
+/// * wildcards: Within a term, `?` matches any single character and `*` matches
+///   any sequence of characters. e.g. `te?t` or `sea*`.
+///   The term is then expanded into the matching terms of the index,
+///   (see `WildcardQuery`). Wildcards are not supported within phrases.
+///   Patterns starting with a wildcard are allowed, but they require to
+///   scan all of the terms of the field.
+///
+/// * dates: The values of date fields are written in RFC 3339, and are
+///   normalized to UTC. e.g. `created:2017-03-12T11:30:00+02:00`.
+///
+/// * ranges: `[` and `]` delimit inclusive bounds, `{` and `}` exclusive bounds,
+///   and `*` stands for an unbounded side.
+///   e.g. `created:[2017-01-01T00:00:00Z TO 2018-01-01T00:00:00Z}` or `title:[a TO m]`.
+///   e.g. `count:[10 TO 20}`.
+///   Ranges are supported on u32 and date fields, and on text fields where the terms
+///   are compared in lexicographic order. (see `TermRangeQuery`)
+///
+/// * JSON fields: The leaves of a JSON field are searched by appending their path
+///   to the name of the field. e.g. `attributes.color:red` or `attributes.size.width:12`.
+///   The value matches the text, integer, floating point and boolean leaves it can be
+///   parsed as. Ranges over the numeric leaves require the field to be `JSON_FAST`.
+///   e.g. `attributes.size.width:[10 TO 20}`.
+///
+/// * tokenizers: The text of tokenized fields is split by the tokenizer
+///   of the field (see `SchemaBuilder::set_tokenizer`). Use `QueryParser::for_index`
+///   so that the tokenizers registered on the index are available.
+///
 pub struct QueryParser {
     schema: Schema,
     default_fields: Vec<Field>,
     conjunction_by_default: bool,
     analyzer: Box<SimpleTokenizer>,
+    tokenizers: TokenizerManager,
+    synonym_map: SynonymMap,
+    field_alias_map: FieldAliasMap,
 }
 
 impl QueryParser {
@@ -81,14 +174,44 @@ impl QueryParser {
             default_fields: default_fields,
             conjunction_by_default: false,
             analyzer: box SimpleTokenizer,
+            tokenizers: TokenizerManager::default(),
+            synonym_map: SynonymMap::default(),
+            field_alias_map: FieldAliasMap::default(),
         }
     }
+
+    /// Creates a `QueryParser` for an index, using its schema
+    /// and the tokenizers registered on it.
+    /// * default_fields - fields used to search if no field is specifically defined
+    ///   in the query.
+    pub fn for_index(index: &Index, default_fields: Vec<Field>) -> QueryParser {
+        let mut query_parser = QueryParser::new(index.schema(), default_fields);
+        query_parser.tokenizers = index.tokenizers().clone();
+        query_parser
+    }
+
+    /// Sets the synonyms used to expand the query.
+    pub fn set_synonym_map(&mut self, synonym_map: SynonymMap) {
+        self.synonym_map = synonym_map;
+    }
+
+    /// Sets the alternative field names accepted in the queries.
+    pub fn set_field_alias_map(&mut self, field_alias_map: FieldAliasMap) {
+        self.field_alias_map = field_alias_map;
+    }
     
-    /// Set the default way to compose queries to a conjunction.
+    /// Sets the default way to compose the terms of a query.
+    ///
+    /// By default, terms without an explicit `+` or `-` operator
+    /// are composed as a disjunction: `rust search` is interpreted
+    /// as `rust OR search`.
+    /// If `conjunction_by_default` is true, they are composed as a
+    /// conjunction instead, and `rust search` is interpreted as
+    /// `+rust +search`.
     ///
-    /// By default a , 
-    pub fn set_conjunction_by_default(&mut self) {
-        self.conjunction_by_default = true;
+    /// Explicit operators always take precedence over this setting.
+    pub fn set_conjunction_by_default(&mut self, conjunction_by_default: bool) {
+        self.conjunction_by_default = conjunction_by_default;
     }
 
     /// Parse a query
@@ -116,8 +239,39 @@ impl QueryParser {
     fn resolve_field_name(&self, field_name: &str) -> Result<Field, QueryParserError> {
         self.schema
             .get_field(field_name)
+            .or_else(|| self.field_alias_map.field(field_name))
             .ok_or_else(|| QueryParserError::FieldDoesNotExist(String::from(field_name)))
     }
+
+    /// Splits a field name such as `attributes.size.width` into
+    /// a JSON field and the path of the leaves within its objects.
+    ///
+    /// Returns `None` if the field name is not that of a JSON path.
+    fn resolve_json_path(&self, field_name_opt: &Option<String>) -> Result<Option<(Field, String)>, QueryParserError> {
+        let field_name = match *field_name_opt {
+            Some(ref field_name) => field_name,
+            None => { return Ok(None); }
+        };
+        if self.resolve_field_name(field_name).is_ok() {
+            return Ok(None);
+        }
+        for (dot_pos, _) in field_name.match_indices('.') {
+            if let Ok(field) = self.resolve_field_name(&field_name[..dot_pos]) {
+                let field_entry = self.schema.get_field_entry(field);
+                if let FieldType::Json(ref json_options) = *field_entry.field_type() {
+                    if !json_options.is_indexed() {
+                        return Err(QueryParserError::FieldNotIndexed(field_entry.name().to_string()));
+                    }
+                    return Ok(Some((field, field_name[dot_pos + 1..].to_string())));
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    fn json_field_name(&self, field: Field, path: &str) -> String {
+        format!("{}.{}", self.schema.get_field_entry(field).name(), path)
+    }
     
     fn compute_logical_ast(&self,
                                user_input_ast: UserInputAST)
@@ -132,26 +286,258 @@ impl QueryParser {
     fn compute_logical_ast_for_leaf(&self,
                                     field: Field,
                                     phrase: &str)
-                                    -> Result<Option<LogicalLiteral>, QueryParserError> {
-        let mut token_iter = self.analyzer.tokenize(phrase);
-        let mut tokens: Vec<Term> = Vec::new();
-        loop {
-            if let Some(token) = token_iter.next() {
-                let text = token.to_string();
-                // TODO Handle u32
-                let term = Term::from_field_text(field, &text);
-                tokens.push(term);
-            } else {
-                break;
+                                    -> Result<Option<LogicalAST>, QueryParserError> {
+        let field_entry = self.schema.get_field_entry(field);
+        match *field_entry.field_type() {
+            FieldType::Date(_) => {
+                let term = try!(self.compute_date_term(field, phrase));
+                return Ok(Some(LogicalAST::from(LogicalLiteral::Term(term))));
+            }
+            FieldType::Json(_) => {
+                return Err(QueryParserError::JsonPathRequired(field_entry.name().clone()));
             }
+            _ => {}
         }
-        if tokens.is_empty() {
+        // over-long tokens are truncated the way they were at indexing time.
+        // Dropped tokens are kept as is: they simply match no document.
+        let max_term_length = field_entry.max_term_length();
+        let mut tokens: Vec<String> = Vec::new();
+        // the positions of the tokens, which leave gaps where
+        // the token filters removed tokens, e.g. stop words.
+        let mut positions: Vec<usize> = Vec::new();
+        if let FieldType::Str(ref text_options) = *field_entry.field_type() {
+            let indexing_options = text_options.get_indexing_options();
+            if indexing_options.is_indexed() && !indexing_options.is_tokenized() {
+                // untokenized fields are searched as a whole,
+                // the way they were indexed.
+                let token = keyword_token(phrase, indexing_options.is_lowercased_keyword());
+                let term = Term::from_field_text(field, max_term_length.apply(&token).unwrap_or(&token));
+                return Ok(Some(LogicalAST::from(LogicalLiteral::Term(term))));
+            }
+            let tokenizer_name = field_entry.tokenizer();
+            let tokenizer = self.tokenizers
+                .get(tokenizer_name)
+                .ok_or_else(|| QueryParserError::UnknownTokenizer(String::from(tokenizer_name)))?;
+            let mut token_stream = tokenizer.token_stream(phrase);
+            while token_stream.advance() {
+                let token = &token_stream.token().text;
+                tokens.push(max_term_length.apply(token).unwrap_or(token).to_string());
+                positions.push(token_stream.token().position as usize);
+            }
+        } else {
+            let mut token_iter = self.analyzer.tokenize(phrase);
+            loop {
+                if let Some(token) = token_iter.next() {
+                    positions.push(tokens.len());
+                    tokens.push(token.to_string());
+                } else {
+                    break;
+                }
+            }
+        }
+        let literal = match compute_literal(field, &tokens, &positions) {
+            Some(literal) => literal,
+            None => { return Ok(None); }
+        };
+        let synonyms = self.synonym_map.synonyms(&tokens);
+        if synonyms.is_empty() {
+            Ok(Some(LogicalAST::from(literal)))
+        } else {
+            // each synonym is a separate literal, so that the positions 
+            // of a multi-word synonym are matched independently
+            // from those of the original phrase.
+            let mut alternatives = vec![(Occur::Should, LogicalAST::from(literal))];
+            for synonym in synonyms {
+                let synonym_positions: Vec<usize> = (0..synonym.len()).collect();
+                if let Some(synonym_literal) = compute_literal(field, synonym, &synonym_positions) {
+                    alternatives.push((Occur::Should, LogicalAST::from(synonym_literal)));
+                }
+            }
+            Ok(Some(LogicalAST::Clause(alternatives)))
+        }
+    }
+
+    /// The value of a JSON leaf may be of any type: it is searched
+    /// as each of the types it can be parsed as.
+    fn compute_logical_ast_for_json_leaf(&self,
+                                         field: Field,
+                                         path: &str,
+                                         phrase: &str)
+                                         -> Result<Option<LogicalAST>, QueryParserError> {
+        let field_entry = self.schema.get_field_entry(field);
+        let mut asts: Vec<LogicalAST> = Vec::new();
+        if let Ok(val) = phrase.parse::<i64>() {
+            asts.push(LogicalAST::from(LogicalLiteral::Term(Term::from_field_json_i64(field, path, val))));
+        }
+        if let Ok(val) = phrase.parse::<f64>() {
+            asts.push(LogicalAST::from(LogicalLiteral::Term(Term::from_field_json_f64(field, path, val))));
+        }
+        if let Ok(val) = phrase.parse::<bool>() {
+            asts.push(LogicalAST::from(LogicalLiteral::Term(Term::from_field_json_bool(field, path, val))));
+        }
+        let max_term_length = field_entry.max_term_length();
+        let tokenizer_name = field_entry.tokenizer();
+        let tokenizer = self.tokenizers
+            .get(tokenizer_name)
+            .ok_or_else(|| QueryParserError::UnknownTokenizer(String::from(tokenizer_name)))?;
+        let mut token_stream = tokenizer.token_stream(phrase);
+        // the positions of the leaves are not indexed: the tokens
+        // of a phrase are searched as a conjunction.
+        let mut token_asts: Vec<(Occur, LogicalAST)> = Vec::new();
+        while token_stream.advance() {
+            let token = &token_stream.token().text;
+            let term = Term::from_field_json_text(field, path, max_term_length.apply(token).unwrap_or(token));
+            token_asts.push((Occur::Must, LogicalAST::from(LogicalLiteral::Term(term))));
+        }
+        if token_asts.len() == 1 {
+            asts.push(token_asts.pop().unwrap().1);
+        } else if token_asts.len() > 1 {
+            asts.push(LogicalAST::Clause(token_asts));
+        }
+        if asts.is_empty() {
             Ok(None)
-        } else if tokens.len() == 1 {
-            Ok(Some(LogicalLiteral::Term(tokens.into_iter().next().unwrap())))
         } else {
-            Ok(Some(LogicalLiteral::Phrase(tokens)))
+            compute_disjunction(asts).map(Some)
+        }
+    }
+
+    fn compute_logical_ast_for_wildcard(&self,
+                                        field: Field,
+                                        pattern: &str)
+                                        -> Result<LogicalAST, QueryParserError> {
+        let field_entry = self.schema.get_field_entry(field);
+        let pattern = match *field_entry.field_type() {
+            FieldType::Str(ref text_options) => {
+                let indexing_options = text_options.get_indexing_options();
+                if indexing_options.is_tokenized() {
+                    // the tokenizer lowercases the indexed terms.
+                    pattern.to_ascii_lowercase()
+                } else {
+                    keyword_token(pattern, indexing_options.is_lowercased_keyword()).into_owned()
+                }
+            }
+            _ => {
+                return Err(QueryParserError::UnsupportedWildcard(String::from(field_entry.name())));
+            }
+        };
+        if pattern.starts_with(|c| c == '*' || c == '?') {
+            warn!("Wildcard pattern {:?} starts with a wildcard. All of the terms of field <{}> will be scanned.",
+                  pattern, field_entry.name());
         }
+        Ok(LogicalAST::from(LogicalLiteral::Wildcard(field, pattern)))
+    }
+
+    fn compute_date_term(&self, field: Field, value: &str) -> Result<Term, QueryParserError> {
+        DateTime::from_rfc3339(value)
+            .map(|date_time| Term::from_field_date(field, date_time))
+            .ok_or_else(|| {
+                let field_name = self.schema.get_field_entry(field).name();
+                QueryParserError::ExpectedDate(field_name.clone(), String::from(value))
+            })
+    }
+
+    fn compute_logical_ast_for_range(&self,
+                                     field: Field,
+                                     lower: &UserInputBound,
+                                     upper: &UserInputBound)
+                                     -> Result<LogicalAST, QueryParserError> {
+        let field_entry = self.schema.get_field_entry(field);
+        if let FieldType::Json(_) = *field_entry.field_type() {
+            return Err(QueryParserError::JsonPathRequired(field_entry.name().clone()));
+        }
+        let compute_term = |value: &str| -> Result<Term, QueryParserError> {
+            match *field_entry.field_type() {
+                FieldType::Str(ref text_options) => {
+                    // the bounds are normalized the way the wildcard patterns are.
+                    let indexing_options = text_options.get_indexing_options();
+                    if indexing_options.is_tokenized() {
+                        Ok(Term::from_field_text(field, &value.to_ascii_lowercase()))
+                    } else {
+                        Ok(Term::from_field_text(field, &keyword_token(value, indexing_options.is_lowercased_keyword())))
+                    }
+                }
+                // u32 terms are big endian, so that their
+                // lexicographic order is the order of the values.
+                FieldType::U32(_) => {
+                    value
+                        .parse::<u32>()
+                        .map(|val| Term::from_field_u32(field, val))
+                        .map_err(|_| QueryParserError::ExpectedU32(field_entry.name().clone(), String::from(value)))
+                }
+                FieldType::Date(_) => self.compute_date_term(field, value),
+                FieldType::Json(_) => unreachable!(),
+            }
+        };
+        let lower_bound = match *lower {
+            UserInputBound::Inclusive(ref value) => Bound::Included(try!(compute_term(value))),
+            UserInputBound::Exclusive(ref value) => Bound::Excluded(try!(compute_term(value))),
+            UserInputBound::Unbounded => Bound::Unbounded,
+        };
+        let upper_bound = match *upper {
+            UserInputBound::Inclusive(ref value) => Bound::Included(try!(compute_term(value))),
+            UserInputBound::Exclusive(ref value) => Bound::Excluded(try!(compute_term(value))),
+            UserInputBound::Unbounded => Bound::Unbounded,
+        };
+        Ok(LogicalAST::from(LogicalLiteral::Range(field, lower_bound, upper_bound)))
+    }
+
+    /// Ranges over a JSON path search the numeric leaves of the path,
+    /// which are only indexed for range queries if the field is fast.
+    fn compute_logical_ast_for_json_range(&self,
+                                          field: Field,
+                                          path: &str,
+                                          lower: &UserInputBound,
+                                          upper: &UserInputBound)
+                                          -> Result<LogicalAST, QueryParserError> {
+        let is_fast = match *self.schema.get_field_entry(field).field_type() {
+            FieldType::Json(ref json_options) => json_options.is_fast(),
+            _ => false,
+        };
+        if !is_fast {
+            return Err(QueryParserError::UnsupportedRange(self.json_field_name(field, path)));
+        }
+        let compute_term = |value: &str| -> Result<Term, QueryParserError> {
+            value
+                .parse::<f64>()
+                .map(|val| Term::from_field_json_number(field, path, val))
+                .map_err(|_| QueryParserError::ExpectedNumber(self.json_field_name(field, path), String::from(value)))
+        };
+        // the unbounded sides are bounded by infinities,
+        // so that the range does not leak out of the path.
+        let lower_bound = match *lower {
+            UserInputBound::Inclusive(ref value) => Bound::Included(try!(compute_term(value))),
+            UserInputBound::Exclusive(ref value) => Bound::Excluded(try!(compute_term(value))),
+            UserInputBound::Unbounded => Bound::Included(Term::from_field_json_number(field, path, f64::NEG_INFINITY)),
+        };
+        let upper_bound = match *upper {
+            UserInputBound::Inclusive(ref value) => Bound::Included(try!(compute_term(value))),
+            UserInputBound::Exclusive(ref value) => Bound::Excluded(try!(compute_term(value))),
+            UserInputBound::Unbounded => Bound::Included(Term::from_field_json_number(field, path, f64::INFINITY)),
+        };
+        Ok(LogicalAST::from(LogicalLiteral::Range(field, lower_bound, upper_bound)))
+    }
+
+    /// Returns the fields a literal or a range should be searched in.
+    fn searched_fields(&self, field_name: &Option<String>) -> Result<Vec<Field>, QueryParserError> {
+        let fields = match *field_name {
+            Some(ref field_name) => {
+                let field = try!(self.resolve_field_name(&field_name));
+                vec![field]
+            }
+            None => {
+                if self.default_fields.len() == 0 {
+                    return Err(QueryParserError::NoDefaultFieldDeclared);
+                }
+                self.default_fields.clone()
+            }
+        };
+        for &field in &fields {
+            let field_entry = self.schema.get_field_entry(field);
+            if !field_entry.is_indexed() {
+                return Err(QueryParserError::FieldNotIndexed(field_entry.name().to_string()));
+            }
+        }
+        Ok(fields)
     }
 
     fn default_occur(&self) -> Occur {
@@ -186,45 +572,86 @@ impl QueryParser {
                     try!(self.compute_logical_ast_with_occur(*subquery));
                 Ok((compose_occur(Occur::Must, occur), logical_sub_queries))
             }
+            UserInputAST::Boost(subquery, boost_str) => {
+                let boost: f32 = boost_str
+                    .parse()
+                    .map_err(|_| QueryParserError::InvalidBoost(boost_str.clone()))?;
+                if !boost.is_finite() || boost < 0f32 {
+                    return Err(QueryParserError::InvalidBoost(boost_str));
+                }
+                let (occur, logical_sub_query) =
+                    try!(self.compute_logical_ast_with_occur(*subquery));
+                if boost == 1f32 {
+                    return Ok((occur, logical_sub_query));
+                }
+                Ok((occur, LogicalAST::Boost(box logical_sub_query, boost)))
+            }
             UserInputAST::Leaf(literal) => {
-                let term_phrases: Vec<(Field, String)> = match literal.field_name {
-                    Some(ref field_name) => {
-                        let field = try!(self.resolve_field_name(&field_name));
-                        vec![(field, literal.phrase.clone())]
-                    }
-                    None => {
-                        if self.default_fields.len() == 0 {
-                            return Err(QueryParserError::NoDefaultFieldDeclared);
-                        } else {
-                            self.default_fields
-                                .iter()
-                                .map(|default_field| (*default_field, literal.phrase.clone()))
-                                .collect::<Vec<(Field, String)>>()
-                        }
-                    }
-                };
+                if let Some((field, path)) = try!(self.resolve_json_path(&literal.field_name)) {
+                    let ast_opt = try!(self.compute_logical_ast_for_json_leaf(field, &path, &literal.phrase));
+                    return Ok((Occur::Should, try!(compute_disjunction(ast_opt.into_iter().collect()))));
+                }
                 let mut asts: Vec<LogicalAST> = Vec::new();
-                for (field, phrase) in term_phrases {
-                    if let Some(ast) = self.compute_logical_ast_for_leaf(field, &phrase)? {
-                        asts.push(LogicalAST::Leaf(box ast));
+                for field in try!(self.searched_fields(&literal.field_name)) {
+                    if let Some(ast) = self.compute_logical_ast_for_leaf(field, &literal.phrase)? {
+                        asts.push(ast);
                     }
                 }
-                let result_ast = if asts.len() == 0 {
-                    // this should never happen
-                    return Err(QueryParserError::SyntaxError); 
-                } else if asts.len() == 1 {
-                    asts[0].clone()
-                } else {
-                    LogicalAST::Clause(asts.into_iter()
-                        .map(|ast| (Occur::Should, ast))
-                        .collect())
-                };
-                Ok((Occur::Should, result_ast))
+                Ok((Occur::Should, try!(compute_disjunction(asts))))
+            }
+            UserInputAST::Wildcard(literal) => {
+                if let Some((field, path)) = try!(self.resolve_json_path(&literal.field_name)) {
+                    return Err(QueryParserError::UnsupportedWildcard(self.json_field_name(field, &path)));
+                }
+                let mut asts: Vec<LogicalAST> = Vec::new();
+                for field in try!(self.searched_fields(&literal.field_name)) {
+                    asts.push(try!(self.compute_logical_ast_for_wildcard(field, &literal.phrase)));
+                }
+                Ok((Occur::Should, try!(compute_disjunction(asts))))
+            }
+            UserInputAST::Range { field_name, lower, upper } => {
+                if let Some((field, path)) = try!(self.resolve_json_path(&field_name)) {
+                    return Ok((Occur::Should, try!(self.compute_logical_ast_for_json_range(field, &path, &lower, &upper))));
+                }
+                let mut asts: Vec<LogicalAST> = Vec::new();
+                for field in try!(self.searched_fields(&field_name)) {
+                    asts.push(try!(self.compute_logical_ast_for_range(field, &lower, &upper)));
+                }
+                Ok((Occur::Should, try!(compute_disjunction(asts))))
             }
         }
     }
 }
 
+/// Returns the union of the asts, one per searched field.
+fn compute_disjunction(mut asts: Vec<LogicalAST>) -> Result<LogicalAST, QueryParserError> {
+    if asts.len() == 0 {
+        // this should never happen
+        Err(QueryParserError::SyntaxError)
+    } else if asts.len() == 1 {
+        Ok(asts.pop().unwrap())
+    } else {
+        Ok(LogicalAST::Clause(asts.into_iter()
+            .map(|ast| (Occur::Should, ast))
+            .collect()))
+    }
+}
+
+fn compute_literal(field: Field, tokens: &[String], positions: &[usize]) -> Option<LogicalLiteral> {
+    // TODO Handle u32
+    let mut terms: Vec<(usize, Term)> = positions
+        .iter()
+        .cloned()
+        .zip(tokens.iter().map(|token| Term::from_field_text(field, token)))
+        .collect();
+    if terms.is_empty() {
+        None
+    } else if terms.len() == 1 {
+        Some(LogicalLiteral::Term(terms.pop().unwrap().1))
+    } else {
+        Some(LogicalLiteral::Phrase(terms))
+    }
+}
 
 /// Compose two occur values.
 fn compose_occur(left: Occur, right: Occur) -> Occur {
@@ -244,13 +671,22 @@ fn compose_occur(left: Occur, right: Occur) -> Occur {
                 Occur::MustNot
             }
         }  
+        Occur::Filter => {
+            if right == Occur::MustNot {
+                Occur::MustNot
+            } else {
+                Occur::Filter
+            }
+        }
     }
 }
 
 fn convert_literal_to_query(logical_literal: LogicalLiteral) -> Box<Query> {
     match logical_literal {
         LogicalLiteral::Term(term) => box TermQuery::new(term, SegmentPostingsOption::Freq),
-        LogicalLiteral::Phrase(terms) => box PhraseQuery::from(terms),
+        LogicalLiteral::Phrase(terms) => box PhraseQuery::with_offsets(terms),
+        LogicalLiteral::Wildcard(field, pattern) => box WildcardQuery::new(field, &pattern),
+        LogicalLiteral::Range(field, lower, upper) => box TermRangeQuery::new(field, lower, upper),
     }
 }
 
@@ -263,6 +699,9 @@ fn convert_to_query(logical_ast: LogicalAST) -> Box<Query> {
             box BooleanQuery::from(occur_subqueries)
         }
         LogicalAST::Leaf(logical_literal) => convert_literal_to_query(*logical_literal),
+        LogicalAST::Boost(logical_sub_query, boost) => {
+            box BoostQuery::new(convert_to_query(*logical_sub_query), boost)
+        }
     }
 }
 
@@ -270,10 +709,22 @@ fn convert_to_query(logical_ast: LogicalAST) -> Box<Query> {
 
 #[cfg(test)]
 mod test {
-    use schema::{SchemaBuilder, TEXT};
+    use schema::{SchemaBuilder, TEXT, STORED, STRING, STRING_CI, U32_INDEXED, DATE_INDEXED, DATE_STORED};
+    use schema::{JSON_INDEXED, JSON_STORED, JSON_FAST};
     use super::QueryParser;
     use super::QueryParserError;
     use super::super::logical_ast::*;
+    use super::super::SynonymMap;
+    use super::super::FieldAliasMap;
+    use query::Occur;
+    use core::Index;
+    use schema::{Document, FieldValue};
+    use collector::tests::TestCollector;
+    use collector::TopCollector;
+    use analyzer::{Tokenizer, TokenStream, Token};
+    use schema::{Field, Term};
+    use DocAddress;
+    use Error;
     
     
     fn make_query_parser() -> QueryParser {
@@ -289,10 +740,8 @@ mod test {
     fn parse_query_to_logical_ast(query: &str,
                                   default_conjunction: bool)
                                   -> Result<LogicalAST, QueryParserError> {
-        let mut query_parser = make_query_parser();                       
-        if default_conjunction {
-            query_parser.set_conjunction_by_default();
-        }
+        let mut query_parser = make_query_parser();
+        query_parser.set_conjunction_by_default(default_conjunction);
         query_parser.parse_query_to_logical_ast(query)
     }
 
@@ -353,4 +802,598 @@ mod test {
                                                "\"[Term([0, 97]), Term([0, 98])]\"",
                                                true);
     }
+
+    fn clause_occurs(query: &str, default_conjunction: bool) -> Vec<Occur> {
+        match parse_query_to_logical_ast(query, default_conjunction).unwrap() {
+            LogicalAST::Clause(sub_queries) => {
+                sub_queries.into_iter().map(|(occur, _)| occur).collect()
+            }
+            _ => panic!("Expected a clause"),
+        }
+    }
+
+    #[test]
+    pub fn test_parse_query_default_occur() {
+        assert_eq!(clause_occurs("title:rust title:search", false),
+                   vec![Occur::Should, Occur::Should]);
+        assert_eq!(clause_occurs("title:rust title:search", true),
+                   vec![Occur::Must, Occur::Must]);
+        // explicit operators override the default occur.
+        assert_eq!(clause_occurs("title:rust +title:search", false),
+                   vec![Occur::Should, Occur::Must]);
+        assert_eq!(clause_occurs("title:rust -title:search", true),
+                   vec![Occur::Must, Occur::MustNot]);
+    }
+
+    #[test]
+    pub fn test_parse_query_to_ast_synonyms() {
+        let mut query_parser = make_query_parser();
+        let mut synonym_map = SynonymMap::default();
+        synonym_map.add_synonym("tv", "television");
+        synonym_map.add_synonym("tv show", "series");
+        synonym_map.add_synonym("tube", "idiot box");
+        query_parser.set_synonym_map(synonym_map);
+        let ast_str = |query: &str| {
+            format!("{:?}", query_parser.parse_query_to_logical_ast(query).unwrap())
+        };
+        assert_eq!(ast_str("title:tv"),
+                   "(Term([0, 116, 118]) Term([0, 116, 101, 108, 101, 118, 105, 115, 105, 111, 110]))");
+        assert_eq!(ast_str("title:tube"),
+                   "(Term([0, 116, 117, 98, 101]) \"[Term([0, 105, 100, 105, 111, 116]), Term([0, 98, 111, 120])]\")");
+        // words within a phrase are not expanded
+        assert_eq!(ast_str("title:\"tv set\""),
+                   "\"[Term([0, 116, 118]), Term([0, 115, 101, 116])]\"");
+        // ... but the phrase itself is.
+        assert_eq!(ast_str("title:\"tv show\""),
+                   "(\"[Term([0, 116, 118]), Term([0, 115, 104, 111, 119])]\" Term([0, 115, 101, 114, 105, 101, 115]))");
+    }
+
+    #[test]
+    pub fn test_query_synonyms() {
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let schema = schema_builder.build();
+        let index = Index::create_in_ram(schema.clone());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.add_document(doc!(text_field=>"a brand new television"));
+            index_writer.add_document(doc!(text_field=>"a radio"));
+            index_writer.add_document(doc!(text_field=>"watching the idiot box"));
+            index_writer.add_document(doc!(text_field=>"a box for an idiot"));
+            assert!(index_writer.commit().is_ok());
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let mut query_parser = QueryParser::new(schema, vec![text_field]);
+        let search = |query_parser: &QueryParser, query: &str| {
+            let query = query_parser.parse_query(query).unwrap();
+            let mut test_collector = TestCollector::default();
+            searcher.search(&*query, &mut test_collector).unwrap();
+            test_collector.docs()
+        };
+        assert!(search(&query_parser, "tv").is_empty());
+        let mut synonym_map = SynonymMap::default();
+        synonym_map.add_synonym("tv", "television");
+        synonym_map.add_synonym("tv", "idiot box");
+        query_parser.set_synonym_map(synonym_map);
+        assert_eq!(search(&query_parser, "tv"), vec![0, 2]);
+        assert_eq!(search(&query_parser, "tv radio"), vec![0, 1, 2]);
+        assert_eq!(search(&query_parser, "television"), vec![0]);
+    }
+
+
+    #[test]
+    pub fn test_query_field_alias() {
+        let mut schema_builder = SchemaBuilder::default();
+        let title_field = schema_builder.add_text_field("title", TEXT);
+        let body_field = schema_builder.add_text_field("body", TEXT);
+        let schema = schema_builder.build();
+        let index = Index::create_in_ram(schema.clone());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.add_document(doc!(title_field=>"rust", body_field=>"a language"));
+            index_writer.add_document(doc!(title_field=>"a language", body_field=>"rust"));
+            assert!(index_writer.commit().is_ok());
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let mut query_parser = QueryParser::new(schema, vec![body_field]);
+        let search = |query_parser: &QueryParser, query: &str| {
+            let query = query_parser.parse_query(query).unwrap();
+            let mut test_collector = TestCollector::default();
+            searcher.search(&*query, &mut test_collector).unwrap();
+            test_collector.docs()
+        };
+        assert_eq!(query_parser.parse_query("headline:rust").err().unwrap(),
+                   QueryParserError::FieldDoesNotExist(String::from("headline")));
+        let mut field_alias_map = FieldAliasMap::default();
+        assert!(field_alias_map.is_empty());
+        field_alias_map.add_alias("headline", title_field);
+        // the fields of the schema take precedence over the aliases.
+        field_alias_map.add_alias("body", title_field);
+        query_parser.set_field_alias_map(field_alias_map);
+        assert_eq!(search(&query_parser, "headline:rust"), vec![0]);
+        assert_eq!(search(&query_parser, "headline:(rust language)"), vec![0, 1]);
+        assert_eq!(search(&query_parser, "body:rust"), vec![1]);
+        assert_eq!(query_parser.parse_query("subtitle:rust").err().unwrap(),
+                   QueryParserError::FieldDoesNotExist(String::from("subtitle")));
+    }
+
+    #[test]
+    pub fn test_query_field_not_indexed() {
+        let mut schema_builder = SchemaBuilder::default();
+        let title = schema_builder.add_text_field("title", TEXT);
+        let archive = schema_builder.add_text_field("archive", STORED);
+        let schema = schema_builder.build();
+        let query_parser = QueryParser::new(schema.clone(), vec![title]);
+        assert!(query_parser.parse_query("title:rust").is_ok());
+        assert_eq!(query_parser.parse_query("archive:rust").err().unwrap(),
+                   QueryParserError::FieldNotIndexed(String::from("archive")));
+        assert_eq!(query_parser.parse_query("archive:\"rust language\"").err().unwrap(),
+                   QueryParserError::FieldNotIndexed(String::from("archive")));
+        let query_parser = QueryParser::new(schema, vec![title, archive]);
+        assert_eq!(query_parser.parse_query("rust").err().unwrap(),
+                   QueryParserError::FieldNotIndexed(String::from("archive")));
+    }
+
+
+    #[test]
+    pub fn test_query_keyword_case_insensitive() {
+        let mut schema_builder = SchemaBuilder::default();
+        let company_field = schema_builder.add_text_field("company", STRING_CI);
+        let schema = schema_builder.build();
+        let index = Index::create_in_ram(schema.clone());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.add_document(doc!(company_field=>"Apple Inc."));
+            index_writer.add_document(doc!(company_field=>"Apple"));
+            assert!(index_writer.commit().is_ok());
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let query_parser = QueryParser::new(schema, vec![company_field]);
+        let search = |query: &str| {
+            let query = query_parser.parse_query(query).unwrap();
+            let mut test_collector = TestCollector::default();
+            searcher.search(&*query, &mut test_collector).unwrap();
+            test_collector.docs()
+        };
+        assert_eq!(search("\"apple inc.\""), vec![0]);
+        assert_eq!(search("company:\"APPLE INC.\""), vec![0]);
+        assert_eq!(search("apple"), vec![1]);
+        assert!(search("inc").is_empty());
+    }
+
+
+    #[test]
+    pub fn test_parse_query_boost() {
+        test_parse_query_to_logical_ast_helper("+title:a^2 +title:b",
+                                               "(+(Term([0, 97]))^2 +Term([0, 98]))",
+                                               false);
+        test_parse_query_to_logical_ast_helper("title:\"a b\"^0.5",
+                                               "(\"[Term([0, 97]), Term([0, 98])]\")^0.5",
+                                               false);
+        assert_eq!(parse_query_to_logical_ast("title:a^1.2.3", false).err().unwrap(),
+                   QueryParserError::InvalidBoost(String::from("1.2.3")));
+        test_parse_query_to_logical_ast_helper("title:a^0",
+                                               "(Term([0, 97]))^0",
+                                               false);
+        // a boost of 1 is dropped.
+        test_parse_query_to_logical_ast_helper("title:a^1.0",
+                                               "Term([0, 97])",
+                                               false);
+        test_parse_query_to_logical_ast_helper("title:(a \"b c\")^2",
+                                               "((Term([0, 97]) \"[Term([0, 98]), Term([0, 99])]\"))^2",
+                                               false);
+        assert_eq!(parse_query_to_logical_ast("title:a^", false).err().unwrap(),
+                   QueryParserError::SyntaxError);
+        assert_eq!(parse_query_to_logical_ast("title:(a b)^", false).err().unwrap(),
+                   QueryParserError::SyntaxError);
+        assert_eq!(parse_query_to_logical_ast("title:a^^2", false).err().unwrap(),
+                   QueryParserError::SyntaxError);
+        assert_eq!(parse_query_to_logical_ast("title:a^.", false).err().unwrap(),
+                   QueryParserError::InvalidBoost(String::from(".")));
+    }
+
+    #[test]
+    pub fn test_query_boost() {
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let schema = schema_builder.build();
+        let index = Index::create_in_ram(schema.clone());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.add_document(doc!(text_field=>"rust search"));
+            index_writer.add_document(doc!(text_field=>"rust"));
+            index_writer.add_document(doc!(text_field=>"search engine"));
+            assert!(index_writer.commit().is_ok());
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let query_parser = QueryParser::new(schema, vec![text_field]);
+        // score of the first document.
+        let score = |query: &str| {
+            let query = query_parser.parse_query(query).unwrap();
+            let mut top_collector = TopCollector::with_limit(3);
+            searcher.search(&*query, &mut top_collector).unwrap();
+            top_collector
+                .score_docs()
+                .into_iter()
+                .find(|&(_, doc_address)| doc_address == DocAddress(0, 0))
+                .map(|(score, _)| score)
+                .unwrap()
+        };
+        let rust_score = score("rust");
+        assert!(rust_score > 0f32);
+        // the score of the boolean query is the sum of the scores of its clauses.
+        let boosted_score = score("+rust^2 +search");
+        assert!((boosted_score - score("+rust +search") - rust_score).abs() < 1e-5f32);
+        assert!((score("rust^3") - 3f32 * rust_score).abs() < 1e-5f32);
+    }
+
+
+    #[test]
+    pub fn test_query_field_boost() {
+        let mut schema_builder = SchemaBuilder::default();
+        let title_field = schema_builder.add_text_field("title", TEXT);
+        let body_field = schema_builder.add_text_field("body", TEXT);
+        let schema = schema_builder.build();
+        let index = Index::create_in_ram(schema.clone());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.add_document(doc!(title_field=>"rust", body_field=>"a language"));
+            index_writer.add_document(doc!(title_field=>"a language", body_field=>"rust"));
+            index_writer.add_document(doc!(title_field=>"c", body_field=>"c"));
+            assert!(index_writer.commit().is_ok());
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let query_parser = QueryParser::new(schema, vec![title_field, body_field]);
+        let score_docs = |query: &str| {
+            let query = query_parser.parse_query(query).unwrap();
+            let mut top_collector = TopCollector::with_limit(3);
+            searcher.search(&*query, &mut top_collector).unwrap();
+            top_collector.score_docs()
+        };
+        let unboosted = score_docs("title:rust body:rust");
+        assert_eq!(unboosted, score_docs("title:rust^1.0 body:rust"));
+        assert_eq!(score_docs("title:rust^2.0 body:rust")[0].1, DocAddress(0, 0));
+        assert_eq!(score_docs("title:rust body:rust^2.0")[0].1, DocAddress(0, 1));
+        assert_eq!(score_docs("(title:rust body:c)^2.0 body:rust")[0].1, DocAddress(0, 0));
+        let zero_boosted = score_docs("title:rust^0 body:rust");
+        assert_eq!(zero_boosted.len(), 2);
+        assert_eq!(zero_boosted[0].1, DocAddress(0, 1));
+        assert_eq!(zero_boosted[1], (0f32, DocAddress(0, 0)));
+
+        let query = query_parser.parse_query("title:rust^2.5 body:rust").unwrap();
+        let explanation = searcher.explain(&*query, &DocAddress(0, 0)).unwrap();
+        let boost_explanation = &explanation.details()[0];
+        assert_eq!(boost_explanation.description(), "boosted score");
+        assert_eq!(boost_explanation.details()[1].value(), 2.5f32);
+    }
+
+
+    #[test]
+    pub fn test_parse_query_wildcard() {
+        test_parse_query_to_logical_ast_helper("title:Te?t",
+                                               "Wildcard(Field(0), \"te?t\")",
+                                               false);
+        test_parse_query_to_logical_ast_helper("+sea* \"te?t\"",
+                                               "(+(Wildcard(Field(0), \"sea*\") Wildcard(Field(1), \"sea*\")) \
+                                                (\"[Term([0, 116, 101]), Term([0, 116])]\" \"[Term([1, 116, 101]), Term([1, 116])]\"))",
+                                               false);
+        let mut schema_builder = SchemaBuilder::default();
+        let count_field = schema_builder.add_u32_field("count", U32_INDEXED);
+        let id_field = schema_builder.add_text_field("id", STRING);
+        let query_parser = QueryParser::new(schema_builder.build(), vec![count_field]);
+        assert_eq!(query_parser.parse_query("count:1*").err().unwrap(),
+                   QueryParserError::UnsupportedWildcard(String::from("count")));
+        let logical_ast = query_parser.parse_query_to_logical_ast("id:AB?").unwrap();
+        assert_eq!(format!("{:?}", logical_ast), format!("Wildcard({:?}, \"AB?\")", id_field));
+    }
+
+    #[test]
+    pub fn test_query_wildcard() {
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let schema = schema_builder.build();
+        let index = Index::create_in_ram(schema.clone());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.add_document(doc!(text_field=>"test"));
+            index_writer.add_document(doc!(text_field=>"text"));
+            index_writer.add_document(doc!(text_field=>"tent"));
+            index_writer.add_document(doc!(text_field=>"toast"));
+            assert!(index_writer.commit().is_ok());
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let query_parser = QueryParser::new(schema, vec![text_field]);
+        let search = |query: &str| {
+            let query = query_parser.parse_query(query).unwrap();
+            let mut test_collector = TestCollector::default();
+            searcher.search(&*query, &mut test_collector).unwrap();
+            test_collector.docs()
+        };
+        assert_eq!(search("te?t"), vec!(0, 1, 2));
+        assert_eq!(search("TE?T"), vec!(0, 1, 2));
+        assert_eq!(search("*t"), vec!(0, 1, 2, 3));
+        assert_eq!(search("to*"), vec!(3));
+        assert_eq!(search("te?t -tent"), vec!(0, 1));
+        assert!(search("\"te?t\"").is_empty());
+    }
+
+    // splits identifiers such as `getUserName` into `get`, `user`, `name`.
+    struct CodeTokenizer;
+
+    struct CodeTokenStream {
+        tokens: Vec<Token>,
+        cursor: usize,
+    }
+
+    impl TokenStream for CodeTokenStream {
+        fn advance(&mut self) -> bool {
+            self.cursor += 1;
+            self.cursor <= self.tokens.len()
+        }
+
+        fn token(&self) -> &Token {
+            &self.tokens[self.cursor - 1]
+        }
+
+        fn token_mut(&mut self) -> &mut Token {
+            &mut self.tokens[self.cursor - 1]
+        }
+    }
+
+    impl Tokenizer for CodeTokenizer {
+        fn token_stream<'a>(&self, text: &'a str) -> Box<TokenStream + 'a> {
+            let mut tokens: Vec<Token> = Vec::new();
+            let mut previous_is_lowercase = false;
+            for (offset, c) in text.char_indices() {
+                if !c.is_alphanumeric() {
+                    previous_is_lowercase = false;
+                    continue;
+                }
+                let starts_token = match tokens.last() {
+                    Some(token) => token.offset_to != offset || (c.is_uppercase() && previous_is_lowercase),
+                    None => true,
+                };
+                if starts_token {
+                    let position = tokens.len() as u32;
+                    tokens.push(Token {
+                        text: String::new(),
+                        offset_from: offset,
+                        offset_to: offset,
+                        position: position,
+                    });
+                }
+                let token = tokens.last_mut().unwrap();
+                token.text.extend(c.to_lowercase());
+                token.offset_to = offset + c.len_utf8();
+                previous_is_lowercase = c.is_lowercase();
+            }
+            box CodeTokenStream {
+                tokens: tokens,
+                cursor: 0,
+            }
+        }
+    }
+
+    #[test]
+    pub fn test_custom_tokenizer() {
+        let mut schema_builder = SchemaBuilder::default();
+        let code_field = schema_builder.add_text_field("code", TEXT);
+        schema_builder.set_tokenizer(code_field, "code");
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        match index.writer_with_num_threads(1, 40_000_000) {
+            Err(Error::SchemaError(_)) => {}
+            _ => panic!("Expected SchemaError"),
+        }
+        assert_eq!(QueryParser::for_index(&index, vec![code_field]).parse_query("getUserName").err().unwrap(),
+                   QueryParserError::UnknownTokenizer(String::from("code")));
+        index.tokenizers().register("code", CodeTokenizer);
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.add_document(doc!(code_field=>"fn getUserName()", text_field=>"getUserName"));
+            index_writer.add_document(doc!(code_field=>"let userId", text_field=>"userId"));
+            assert!(index_writer.commit().is_ok());
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let doc_freq = |field: Field, text: &str| searcher.doc_freq(&Term::from_field_text(field, text));
+        assert_eq!(doc_freq(code_field, "get"), 1);
+        assert_eq!(doc_freq(code_field, "user"), 2);
+        assert_eq!(doc_freq(code_field, "name"), 1);
+        assert_eq!(doc_freq(code_field, "getusername"), 0);
+        assert_eq!(doc_freq(text_field, "getusername"), 1);
+        assert_eq!(doc_freq(text_field, "user"), 0);
+        let query_parser = QueryParser::for_index(&index, vec![code_field, text_field]);
+        let search = |query: &str| {
+            let query = query_parser.parse_query(query).unwrap();
+            let mut test_collector = TestCollector::default();
+            searcher.search(&*query, &mut test_collector).unwrap();
+            test_collector.docs()
+        };
+        assert_eq!(search("code:user"), vec!(0, 1));
+        assert_eq!(search("code:\"userName\""), vec!(0));
+        assert_eq!(search("code:\"nameUser\""), Vec::<u32>::new());
+        assert_eq!(search("text:userId"), vec!(1));
+        assert_eq!(search("text:user"), Vec::<u32>::new());
+    }
+
+    #[test]
+    pub fn test_query_phrase_stop_words() {
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        schema_builder.set_tokenizer(text_field, "en_stem");
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.add_document(doc!(text_field=>"a part of the job"));
+            index_writer.add_document(doc!(text_field=>"a part job"));
+            index_writer.add_document(doc!(text_field=>"a part and a job"));
+            assert!(index_writer.commit().is_ok());
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let query_parser = QueryParser::for_index(&index, vec![text_field]);
+        let search = |query: &str| {
+            let query = query_parser.parse_query(query).unwrap();
+            let mut test_collector = TestCollector::default();
+            searcher.search(&*query, &mut test_collector).unwrap();
+            test_collector.docs()
+        };
+        // the removed stop words leave gaps in the positions,
+        // both in the documents and in the phrase.
+        assert_eq!(search("\"part of the job\""), vec!(0, 2));
+        assert_eq!(search("\"part job\""), vec!(1));
+        assert_eq!(search("\"the part of job\""), Vec::<u32>::new());
+    }
+
+    #[test]
+    pub fn test_query_date() {
+        let mut schema_builder = SchemaBuilder::default();
+        let title_field = schema_builder.add_text_field("title", STRING);
+        let created_field = schema_builder.add_date_field("created", DATE_INDEXED | DATE_STORED);
+        schema_builder.add_u32_field("count", U32_INDEXED);
+        let schema = schema_builder.build();
+        let index = Index::create_in_ram(schema.clone());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            for (title, created) in vec!(("a", "2019-12-31T23:59:59.999Z"),
+                                         ("b", "2020-01-01T02:00:00+02:00"),
+                                         ("c", "2020-06-15T12:30:00.250Z"),
+                                         ("d", "2020-12-31T23:59:59Z"),
+                                         ("e", "2021-01-01T00:00:00Z")) {
+                let doc_json = format!("{{\"title\": \"{}\", \"created\": \"{}\"}}", title, created);
+                index_writer.add_document(schema.parse_document(&doc_json).unwrap());
+            }
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        // the offset datetime is normalized to UTC before storage.
+        let doc = searcher.doc(&DocAddress(0, 1)).unwrap();
+        assert_eq!(doc.get_first(created_field).unwrap().date_value().to_rfc3339(), "2020-01-01T00:00:00Z");
+        assert!(schema.to_json(&doc).contains("\"created\":[\"2020-01-01T00:00:00Z\"]"));
+        let doc = searcher.doc(&DocAddress(0, 2)).unwrap();
+        assert!(schema.to_json(&doc).contains("\"created\":[\"2020-06-15T12:30:00.250Z\"]"));
+
+        let query_parser = QueryParser::new(schema, vec![title_field]);
+        let search = |query: &str| {
+            let query = query_parser.parse_query(query).unwrap();
+            let mut test_collector = TestCollector::default();
+            searcher.search(&*query, &mut test_collector).unwrap();
+            test_collector.docs()
+        };
+        assert_eq!(search("created:[2020-01-01T00:00:00Z TO 2020-12-31T23:59:59Z]"), vec!(1, 2, 3));
+        assert_eq!(search("created:{2020-01-01T00:00:00Z TO 2020-12-31T23:59:59Z}"), vec!(2));
+        assert_eq!(search("created:[2020-06-15T12:30:00.250Z TO *]"), vec!(2, 3, 4));
+        assert_eq!(search("created:{2020-06-15T12:30:00.250Z TO *]"), vec!(3, 4));
+        assert_eq!(search("created:[* TO 2020-01-01T01:00:00+01:00}"), vec!(0));
+        assert_eq!(search("created:2020-06-15T14:30:00.250+02:00"), vec!(2));
+        assert_eq!(search("created:2020-06-15T12:30:00Z"), Vec::<u32>::new());
+        assert_eq!(search("+created:[2020-01-01T00:00:00Z TO *] -title:e"), vec!(1, 2, 3));
+        assert_eq!(search("title:[b TO d}"), vec!(1, 2));
+        assert_eq!(query_parser.parse_query("created:2020-06-15").err().unwrap(),
+                   QueryParserError::ExpectedDate(String::from("created"), String::from("2020-06-15")));
+        assert_eq!(query_parser.parse_query("created:[2020 TO *]").err().unwrap(),
+                   QueryParserError::ExpectedDate(String::from("created"), String::from("2020")));
+        assert_eq!(query_parser.parse_query("count:[1 TO -2]").err().unwrap(),
+                   QueryParserError::ExpectedU32(String::from("count"), String::from("-2")));
+    }
+
+    #[test]
+    pub fn test_query_u32_range() {
+        let mut schema_builder = SchemaBuilder::default();
+        let title_field = schema_builder.add_text_field("title", STRING);
+        let count_field = schema_builder.add_u32_field("count", U32_INDEXED);
+        let schema = schema_builder.build();
+        let index = Index::create_in_ram(schema.clone());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            for (title, count) in vec!(("a", 0u32), ("b", 9u32), ("c", 10u32), ("d", 256u32), ("e", u32::max_value())) {
+                index_writer.add_document(doc!(title_field=>title, count_field=>count));
+            }
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let query_parser = QueryParser::new(schema, vec![title_field]);
+        let search = |query: &str| {
+            let query = query_parser.parse_query(query).unwrap();
+            let mut test_collector = TestCollector::default();
+            searcher.search(&*query, &mut test_collector).unwrap();
+            test_collector.docs()
+        };
+        assert_eq!(search("count:[9 TO 256]"), vec!(1, 2, 3));
+        assert_eq!(search("count:{9 TO 256}"), vec!(2));
+        assert_eq!(search("count:[10 TO *]"), vec!(2, 3, 4));
+        assert_eq!(search("count:[* TO 10}"), vec!(0, 1));
+        assert_eq!(search("+count:[1 TO *] -title:e"), vec!(1, 2, 3));
+        assert_eq!(query_parser.parse_query("count:[a TO 2]").err().unwrap(),
+                   QueryParserError::ExpectedU32(String::from("count"), String::from("a")));
+    }
+
+    #[test]
+    pub fn test_query_json() {
+        let mut schema_builder = SchemaBuilder::default();
+        let title_field = schema_builder.add_text_field("title", STRING);
+        let attributes_field = schema_builder.add_json_field("attributes", JSON_FAST | JSON_STORED);
+        schema_builder.add_json_field("extra", JSON_INDEXED);
+        let schema = schema_builder.build();
+        let index = Index::create_in_ram(schema.clone());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            for doc_json in &[
+                r#"{"title": "a", "attributes": {"color": "red", "size": {"width": 12, "unit": "cm"}, "tags": ["new", "sale"]}}"#,
+                r#"{"title": "b", "attributes": {"color": "Dark Red", "size": {"width": 2.5}, "available": true}}"#,
+                r#"{"title": "c", "attributes": {"color": "blue", "size": {"width": -3}, "tags": ["sale"], "rank": 12}}"#,
+                r#"{"title": "d", "attributes": {"size": [{"width": 30}, {"width": 4}]}, "extra": {"size": {"width": 7}}}"#] {
+                index_writer.add_document(schema.parse_document(doc_json).unwrap());
+            }
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let doc = searcher.doc(&DocAddress(0, 1)).unwrap();
+        assert!(doc.get_first(attributes_field).unwrap().json_text().contains("\"width\":2.5"));
+        assert!(schema.to_json(&doc).contains("\"attributes\":[{\"available\":true,\"color\":\"Dark Red\""));
+
+        let query_parser = QueryParser::new(schema, vec![title_field]);
+        let search = |query: &str| {
+            let query = query_parser.parse_query(query).unwrap();
+            let mut test_collector = TestCollector::default();
+            searcher.search(&*query, &mut test_collector).unwrap();
+            test_collector.docs()
+        };
+        assert_eq!(search("attributes.color:red"), vec!(0, 1));
+        assert_eq!(search("attributes.color:\"dark red\""), vec!(1));
+        assert_eq!(search("attributes.tags:sale"), vec!(0, 2));
+        assert_eq!(search("attributes.size.unit:cm"), vec!(0));
+        // the leaves are only searched under their own path.
+        assert_eq!(search("attributes.size:cm"), Vec::<u32>::new());
+        assert_eq!(search("attributes.size.width:12"), vec!(0));
+        assert_eq!(search("attributes.size.width:2.5"), vec!(1));
+        assert_eq!(search("attributes.size.width:\"-3\""), vec!(2));
+        assert_eq!(search("attributes.available:true"), vec!(1));
+        assert_eq!(search("attributes.size.width:[2.5 TO 12]"), vec!(0, 1, 3));
+        assert_eq!(search("attributes.size.width:{2.5 TO 12}"), vec!(3));
+        assert_eq!(search("attributes.size.width:[* TO 0]"), vec!(2));
+        assert_eq!(search("attributes.size.width:[20 TO *]"), vec!(3));
+        assert_eq!(search("+attributes.size.width:[0 TO *] -attributes.tags:sale"), vec!(1, 3));
+        assert_eq!(search("extra.size.width:7"), vec!(3));
+        assert_eq!(query_parser.parse_query("extra.size.width:[0 TO 10]").err().unwrap(),
+                   QueryParserError::UnsupportedRange(String::from("extra.size.width")));
+        assert_eq!(query_parser.parse_query("attributes.size.width:[a TO 10]").err().unwrap(),
+                   QueryParserError::ExpectedNumber(String::from("attributes.size.width"), String::from("a")));
+        assert_eq!(query_parser.parse_query("attributes:red").err().unwrap(),
+                   QueryParserError::JsonPathRequired(String::from("attributes")));
+        assert_eq!(query_parser.parse_query("attributes.color:re*").err().unwrap(),
+                   QueryParserError::UnsupportedWildcard(String::from("attributes.color")));
+        assert_eq!(query_parser.parse_query("nothing.color:red").err().unwrap(),
+                   QueryParserError::FieldDoesNotExist(String::from("nothing.color")));
+    }
 }
@@ -0,0 +1,93 @@
+use std::collections::HashMap;
+use analyzer::SimpleTokenizer;
+use analyzer::StreamingIterator;
+
+
+fn tokenize(text: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut token_iter = SimpleTokenizer.tokenize(text);
+    loop {
+        if let Some(token) = token_iter.next() {
+            tokens.push(String::from(token));
+        } else {
+            break;
+        }
+    }
+    tokens
+}
+
+
+/// Map from a word or a sequence of words to its synonyms,
+/// used by the `QueryParser` to expand queries.
+///
+/// Both the words and their synonyms are tokenized with
+/// the same tokenizer as the query, so that `"TV"` and `"tv"`
+/// are equivalent.
+///
+/// Synonyms are not symmetric : adding `television` as a synonym
+/// of `tv` does not make `tv` a synonym of `television`.
+///
+/// Synonyms are only applied to a whole literal of the query.
+/// The individual words of a phrase query (e.g. `"tv show"`) are never
+/// expanded, as that would mix up the positions of the phrase.
+/// The phrase itself is expanded if it is a key of the map.
+#[derive(Default, Clone)]
+pub struct SynonymMap {
+    synonyms: HashMap<Vec<String>, Vec<Vec<String>>>,
+}
+
+impl SynonymMap {
+
+    /// Registers `synonym` as an alternative for `words`.
+    ///
+    /// Both `words` and `synonym` can contain several words,
+    /// in which case they will be searched as a phrase.
+    pub fn add_synonym(&mut self, words: &str, synonym: &str) {
+        let key = tokenize(words);
+        let synonym_tokens = tokenize(synonym);
+        if key.is_empty() || synonym_tokens.is_empty() || key == synonym_tokens {
+            return;
+        }
+        let synonyms = self.synonyms
+            .entry(key)
+            .or_insert_with(Vec::new);
+        if !synonyms.contains(&synonym_tokens) {
+            synonyms.push(synonym_tokens);
+        }
+    }
+
+    /// Returns the synonyms registered for the given sequence
+    /// of tokens.
+    pub fn synonyms(&self, tokens: &[String]) -> &[Vec<String>] {
+        self.synonyms
+            .get(tokens)
+            .map(|synonyms| &synonyms[..])
+            .unwrap_or(&[])
+    }
+
+    /// Returns true iff no synonym has been registered.
+    pub fn is_empty(&self) -> bool {
+        self.synonyms.is_empty()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::SynonymMap;
+
+    #[test]
+    fn test_synonym_map() {
+        let mut synonym_map = SynonymMap::default();
+        assert!(synonym_map.is_empty());
+        synonym_map.add_synonym("TV", "television");
+        synonym_map.add_synonym("tv", "Television");
+        synonym_map.add_synonym("tv", "tv");
+        synonym_map.add_synonym("tv", "idiot box");
+        assert_eq!(synonym_map.synonyms(&[String::from("tv")]),
+                   &[vec![String::from("television")],
+                     vec![String::from("idiot"), String::from("box")]]);
+        assert!(synonym_map.synonyms(&[String::from("television")]).is_empty());
+    }
+}
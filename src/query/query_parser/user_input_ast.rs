@@ -5,6 +5,15 @@ pub struct UserInputLiteral {
     pub phrase: String, 
 }
 
+impl UserInputLiteral {
+    fn with_default_field(mut self, field_name: &str) -> UserInputLiteral {
+        if self.field_name.is_none() {
+            self.field_name = Some(String::from(field_name));
+        }
+        self
+    }
+}
+
 impl fmt::Debug for UserInputLiteral {
     fn fmt(&self, formatter: &mut fmt::Formatter) -> Result<(), fmt::Error> {
         match self.field_name {
@@ -18,12 +27,73 @@ impl fmt::Debug for UserInputLiteral {
     }
 }
 
+/// Bound of a range, as written by the user.
+pub enum UserInputBound {
+    Inclusive(String),
+    Exclusive(String),
+    Unbounded,
+}
+
+impl UserInputBound {
+    /// `*` stands for an unbounded side.
+    pub fn new(value: String, inclusive: bool) -> UserInputBound {
+        if value == "*" {
+            UserInputBound::Unbounded
+        } else if inclusive {
+            UserInputBound::Inclusive(value)
+        } else {
+            UserInputBound::Exclusive(value)
+        }
+    }
+
+    fn value(&self) -> &str {
+        match *self {
+            UserInputBound::Inclusive(ref value) | UserInputBound::Exclusive(ref value) => value,
+            UserInputBound::Unbounded => "*",
+        }
+    }
+}
+
 pub enum UserInputAST {
     Clause(Vec<Box<UserInputAST>>),
     Not(Box<UserInputAST>),
     Must(Box<UserInputAST>),
-    Leaf(Box<UserInputLiteral>)
-    
+    Leaf(Box<UserInputLiteral>),
+    /// The phrase of the literal is a wildcard pattern.
+    Wildcard(Box<UserInputLiteral>),
+    /// The boost is kept as written by the user.
+    Boost(Box<UserInputAST>, String),
+    Range {
+        field_name: Option<String>,
+        lower: UserInputBound,
+        upper: UserInputBound,
+    },
+}
+
+impl UserInputAST {
+    /// Sets the field of the literals that do not have one.
+    pub fn with_default_field(self, field_name: &str) -> UserInputAST {
+        match self {
+            UserInputAST::Clause(subqueries) => {
+                UserInputAST::Clause(subqueries
+                    .into_iter()
+                    .map(|subquery| box subquery.with_default_field(field_name))
+                    .collect())
+            }
+            UserInputAST::Not(subquery) => UserInputAST::Not(box subquery.with_default_field(field_name)),
+            UserInputAST::Must(subquery) => UserInputAST::Must(box subquery.with_default_field(field_name)),
+            UserInputAST::Leaf(literal) => UserInputAST::Leaf(box literal.with_default_field(field_name)),
+            UserInputAST::Wildcard(literal) => UserInputAST::Wildcard(box literal.with_default_field(field_name)),
+            UserInputAST::Boost(subquery, boost) => UserInputAST::Boost(box subquery.with_default_field(field_name), boost),
+            UserInputAST::Range { field_name: range_field_name, lower, upper } => {
+                UserInputAST::Range {
+                    field_name: range_field_name.or_else(|| Some(String::from(field_name))),
+                    lower: lower,
+                    upper: upper,
+                }
+            }
+        }
+    }
 }
 
 impl From<UserInputLiteral> for UserInputAST {
@@ -57,6 +127,27 @@ impl fmt::Debug for UserInputAST {
             UserInputAST::Leaf(ref subquery) => {
                 write!(formatter, "{:?}", subquery)
             }
+            UserInputAST::Wildcard(ref literal) => {
+                match literal.field_name {
+                    Some(ref field_name) => {
+                        write!(formatter, "{}:{}", field_name, literal.phrase)
+                    }
+                    None => {
+                        write!(formatter, "{}", literal.phrase)
+                    }
+                }
+            }
+            UserInputAST::Boost(ref subquery, ref boost) => {
+                write!(formatter, "({:?})^{}", subquery, boost)
+            }
+            UserInputAST::Range { ref field_name, ref lower, ref upper } => {
+                if let Some(ref field_name) = *field_name {
+                    try!(write!(formatter, "{}:", field_name));
+                }
+                let lower_bracket = if let UserInputBound::Exclusive(_) = *lower { '{' } else { '[' };
+                let upper_bracket = if let UserInputBound::Exclusive(_) = *upper { '}' } else { ']' };
+                write!(formatter, "{}{} TO {}{}", lower_bracket, lower.value(), upper.value(), upper_bracket)
+            }
         }
     }
 }
@@ -0,0 +1,63 @@
+mod range_query;
+mod range_weight;
+mod range_scorer;
+
+pub use self::range_query::RangeQuery;
+pub use self::range_weight::RangeWeight;
+pub use self::range_scorer::RangeScorer;
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use std::collections::Bound;
+    use core::Index;
+    use schema::{Document, SchemaBuilder, TEXT, FAST};
+    use collector::tests::TestCollector;
+    use query::Query;
+
+    #[test]
+    fn test_range_query() {
+        let mut schema_builder = SchemaBuilder::default();
+        let price_field = schema_builder.add_u32_field("price", FAST);
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            for i in 0..1_000 {
+                let mut doc = Document::default();
+                doc.add_u32(price_field, (i * 7) % 1_000);
+                index_writer.add_document(doc);
+            }
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let matching_docs = |query: RangeQuery| {
+            let mut collector = TestCollector::default();
+            query.search(&*searcher, &mut collector).unwrap();
+            collector.docs()
+        };
+        let expected_docs = |contains: &Fn(u32) -> bool| -> Vec<u32> {
+            (0..1_000u32).filter(|&i| contains((i * 7) % 1_000)).collect()
+        };
+        let half_open = matching_docs(RangeQuery::new(price_field, Bound::Included(100), Bound::Excluded(200)));
+        assert_eq!(half_open.len(), 100);
+        assert_eq!(half_open, expected_docs(&|price| price >= 100 && price < 200));
+        assert_eq!(matching_docs(RangeQuery::new(price_field, Bound::Excluded(100), Bound::Included(200))),
+                   expected_docs(&|price| price > 100 && price <= 200));
+        assert_eq!(matching_docs(RangeQuery::new(price_field, Bound::Included(900), Bound::Unbounded)),
+                   expected_docs(&|price| price >= 900));
+        assert_eq!(matching_docs(RangeQuery::new(price_field, Bound::Unbounded, Bound::Excluded(3))),
+                   expected_docs(&|price| price < 3));
+        assert_eq!(matching_docs(RangeQuery::new(price_field, Bound::Unbounded, Bound::Unbounded)).len(), 1_000);
+        assert!(matching_docs(RangeQuery::new(price_field, Bound::Included(200), Bound::Excluded(200))).is_empty());
+        assert!(matching_docs(RangeQuery::new(price_field, Bound::Included(300), Bound::Included(200))).is_empty());
+        assert!(matching_docs(RangeQuery::new(price_field, Bound::Excluded(u32::max_value()), Bound::Unbounded)).is_empty());
+
+        let mut collector = TestCollector::default();
+        let text_range_query = RangeQuery::new(text_field, Bound::Included(0), Bound::Unbounded);
+        assert!(text_range_query.search(&*searcher, &mut collector).is_err());
+    }
+}
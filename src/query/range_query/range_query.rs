@@ -0,0 +1,84 @@
+use std::collections::Bound;
+use query::Query;
+use query::Weight;
+use core::searcher::Searcher;
+use schema::Field;
+use super::RangeWeight;
+use std::any::Any;
+use Result;
+
+
+/// `RangeQuery` matches the documents whose value for
+/// a u32 fast field lies within a range.
+///
+/// Each bound may be inclusive, exclusive, or unbounded.
+/// For instance, the documents with a price greater
+/// than or equal to 100 are matched by
+///
+/// ```rust
+/// # use tantivy::schema::{SchemaBuilder, FAST};
+/// use std::collections::Bound;
+/// use tantivy::query::RangeQuery;
+///
+/// # let mut schema_builder = SchemaBuilder::default();
+/// # let price_field = schema_builder.add_u32_field("price", FAST);
+/// let query = RangeQuery::new(price_field, Bound::Included(100), Bound::Unbounded);
+/// ```
+///
+/// All of the matching documents get the same score, `1`.
+///
+/// The query does not rely on the term dictionary: it scans the
+/// fast field of all of the documents of the segments, in order.
+/// The documents without any value for the field have the value `0`.
+#[derive(Debug)]
+pub struct RangeQuery {
+    field: Field,
+    lower_bound: Bound<u32>,
+    upper_bound: Bound<u32>,
+}
+
+impl RangeQuery {
+
+    /// Creates a new `RangeQuery`.
+    ///
+    /// If the lower bound is greater than the upper bound,
+    /// the query matches no document.
+    pub fn new(field: Field, lower_bound: Bound<u32>, upper_bound: Bound<u32>) -> RangeQuery {
+        RangeQuery {
+            field: field,
+            lower_bound: lower_bound,
+            upper_bound: upper_bound,
+        }
+    }
+
+    /// Returns the field targeted by the query.
+    pub fn field(&self,) -> Field {
+        self.field
+    }
+}
+
+impl Query for RangeQuery {
+
+    fn as_any(&self) -> &Any {
+        self
+    }
+
+    fn weight(&self, _searcher: &Searcher) -> Result<Box<Weight>> {
+        // the bounds are turned into a half-open range of u64,
+        // so that excluding `u32::max_value()` does not overflow.
+        let start = match self.lower_bound {
+            Bound::Included(value) => value as u64,
+            Bound::Excluded(value) => value as u64 + 1,
+            Bound::Unbounded => 0u64,
+        };
+        let end = match self.upper_bound {
+            Bound::Included(value) => value as u64 + 1,
+            Bound::Excluded(value) => value as u64,
+            Bound::Unbounded => u32::max_value() as u64 + 1,
+        };
+        Ok(box RangeWeight {
+            field: self.field,
+            range: start..end,
+        })
+    }
+}
@@ -0,0 +1,47 @@
+use query::Scorer;
+use core::SegmentReader;
+use fastfield::U32FastFieldReader;
+use postings::DocSet;
+use super::RangeWeight;
+use DocId;
+use Score;
+
+/// Scorer going through all of the documents of the segment,
+/// and keeping the ones whose value lies within the range.
+pub struct RangeScorer<'a> {
+    pub reader: &'a SegmentReader,
+    pub fast_field_reader: U32FastFieldReader,
+    pub weight: &'a RangeWeight,
+    pub doc: DocId,
+    pub started: bool,
+}
+
+impl<'a> DocSet for RangeScorer<'a> {
+    fn advance(&mut self) -> bool {
+        let max_doc = self.reader.max_doc();
+        if self.started {
+            self.doc += 1;
+        }
+        else {
+            self.started = true;
+        }
+        while self.doc < max_doc {
+            if !self.reader.is_deleted(self.doc) &&
+               self.weight.contains(self.fast_field_reader.get(self.doc)) {
+                return true;
+            }
+            self.doc += 1;
+        }
+        false
+    }
+
+    fn doc(&self) -> DocId {
+        self.doc
+    }
+}
+
+impl<'a> Scorer for RangeScorer<'a> {
+    fn score(&self) -> Score {
+        1f32
+    }
+}
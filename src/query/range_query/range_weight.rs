@@ -0,0 +1,40 @@
+use std::ops::Range;
+use query::Weight;
+use query::Scorer;
+use query::EmptyScorer;
+use core::SegmentReader;
+use schema::Field;
+use super::RangeScorer;
+use Error;
+use Result;
+
+pub struct RangeWeight {
+    pub field: Field,
+    pub range: Range<u64>,
+}
+
+impl Weight for RangeWeight {
+    fn scorer<'a>(&'a self, reader: &'a SegmentReader) -> Result<Box<Scorer + 'a>> {
+        let fast_field_reader = try!(reader
+            .get_fast_field_reader(self.field)
+            .ok_or_else(|| Error::SchemaError(format!("Field {:?} is not a u32 fast field.", self.field))));
+        if self.range.start >= self.range.end {
+            return Ok(box EmptyScorer);
+        }
+        Ok(box RangeScorer {
+            reader: reader,
+            fast_field_reader: fast_field_reader,
+            weight: self,
+            doc: 0,
+            started: false,
+        })
+    }
+}
+
+impl RangeWeight {
+    /// Returns true iff the value lies within the range.
+    pub fn contains(&self, value: u32) -> bool {
+        let value = value as u64;
+        self.range.start <= value && value < self.range.end
+    }
+}
@@ -0,0 +1,62 @@
+mod regex_automaton;
+mod regex_query;
+
+pub use self::regex_automaton::RegexAutomaton;
+pub use self::regex_query::RegexQuery;
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use core::Index;
+    use schema::FieldValue;
+    use schema::{Document, SchemaBuilder, STRING, TEXT};
+    use collector::tests::TestCollector;
+    use Error;
+
+    #[test]
+    pub fn test_regex_query() {
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let other_field = schema_builder.add_text_field("other", TEXT);
+        let code_field = schema_builder.add_text_field("code", STRING);
+        let schema = schema_builder.build();
+        let index = Index::create_in_ram(schema);
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.add_document(doc!(text_field=>"hallo"));  // 0
+            index_writer.add_document(doc!(text_field=>"hello world"));  // 1
+            index_writer.add_document(doc!(text_field=>"hullo"));  // 2
+            index_writer.add_document(doc!(text_field=>"hellos", other_field=>"hello"));  // 3
+            assert!(index_writer.commit().is_ok());
+            index_writer.add_document(doc!(code_field=>"error-404"));  // 4
+            index_writer.add_document(doc!(code_field=>"error-"));  // 5
+            assert!(index_writer.commit().is_ok());
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let test_query = |query: RegexQuery| {
+            let mut test_collector = TestCollector::default();
+            searcher.search(&query, &mut test_collector).expect("search should succeed");
+            test_collector.docs()
+        };
+        assert_eq!(test_query(RegexQuery::from_pattern(text_field, "h[ae]llo").unwrap()), vec!(0, 1));
+        assert_eq!(test_query(RegexQuery::from_pattern(text_field, "h.llos?").unwrap()), vec!(0, 1, 2, 3));
+        assert_eq!(test_query(RegexQuery::from_pattern(code_field, r"error-\d+").unwrap()), vec!(4));
+        assert_eq!(test_query(RegexQuery::from_pattern(text_field, ".*llo").unwrap()), vec!(0, 1, 2));
+        assert_eq!(test_query(RegexQuery::from_pattern(other_field, "h[ae]llo").unwrap()), vec!(3));
+        assert_eq!(test_query(RegexQuery::from_pattern(text_field, "h.llos?").unwrap().set_max_expansions(2)), vec!(0, 1));
+        assert!(test_query(RegexQuery::from_pattern(text_field, "x.*").unwrap()).is_empty());
+    }
+
+    #[test]
+    pub fn test_regex_query_invalid_pattern() {
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        match RegexQuery::from_pattern(text_field, "h[ae") {
+            Err(Error::InvalidArgument(_)) => {}
+            _ => panic!("An invalid regex should be rejected"),
+        }
+    }
+}
@@ -0,0 +1,128 @@
+use std::fmt;
+use fst::{Automaton, Regex};
+use Error;
+use Result;
+
+/// Automaton matching the keys made of a prefix followed by
+/// a text matching a regular expression.
+///
+/// The regular expression is compiled into a DFA by `fst`,
+/// and must match the whole text following the prefix.
+/// The state is the number of bytes of the prefix read so far,
+/// and the state of the DFA. `None` is the dead state.
+pub struct RegexAutomaton {
+    prefix: Vec<u8>,
+    pattern: String,
+    regex: Regex,
+}
+
+impl RegexAutomaton {
+
+    /// Creates an automaton matching the keys starting with `prefix`,
+    /// and followed by a text matching `pattern`.
+    ///
+    /// # Errors
+    /// If the pattern is not a valid regular expression, or if its
+    /// DFA is too large, returns `Error::InvalidArgument`.
+    pub fn new(prefix: &[u8], pattern: &str) -> Result<RegexAutomaton> {
+        let regex = try!(Regex::new(pattern)
+            .map_err(|err| Error::InvalidArgument(format!("Invalid regex {:?}: {}", pattern, err))));
+        Ok(RegexAutomaton {
+            prefix: prefix.to_vec(),
+            pattern: String::from(pattern),
+            regex: regex,
+        })
+    }
+
+    /// Returns the regular expression.
+    pub fn pattern(&self,) -> &str {
+        &self.pattern
+    }
+}
+
+impl fmt::Debug for RegexAutomaton {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "RegexAutomaton({:?})", self.pattern)
+    }
+}
+
+impl Automaton for RegexAutomaton {
+
+    type State = Option<(usize, usize)>;
+
+    fn start(&self) -> Option<(usize, usize)> {
+        self.regex.start().map(|regex_state| (0, regex_state))
+    }
+
+    fn is_match(&self, state: &Option<(usize, usize)>) -> bool {
+        match *state {
+            Some((num_prefix_bytes, regex_state)) => {
+                num_prefix_bytes == self.prefix.len() &&
+                    self.regex.is_match(&Some(regex_state))
+            }
+            None => false,
+        }
+    }
+
+    fn can_match(&self, state: &Option<(usize, usize)>) -> bool {
+        state.is_some()
+    }
+
+    fn accept(&self, state: &Option<(usize, usize)>, byte: u8) -> Option<(usize, usize)> {
+        let (num_prefix_bytes, regex_state) = match *state {
+            Some(state) => state,
+            None => {
+                return None;
+            }
+        };
+        if num_prefix_bytes < self.prefix.len() {
+            if self.prefix[num_prefix_bytes] == byte {
+                Some((num_prefix_bytes + 1, regex_state))
+            } else {
+                None
+            }
+        } else {
+            self.regex
+                .accept(&Some(regex_state), byte)
+                .map(|regex_state| (num_prefix_bytes, regex_state))
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn matches(pattern: &str, text: &str) -> bool {
+        let automaton = RegexAutomaton::new(b"\x01", pattern).unwrap();
+        let mut state = automaton.start();
+        for &byte in [1u8].iter().chain(text.as_bytes()) {
+            state = automaton.accept(&state, byte);
+        }
+        automaton.is_match(&state)
+    }
+
+    #[test]
+    fn test_regex_automaton() {
+        assert!(matches("h[ae]llo", "hello"));
+        assert!(!matches("h[ae]llo", "hullo"));
+        // the whole text must match.
+        assert!(!matches("h[ae]llo", "hellos"));
+        assert!(!matches("ell", "hello"));
+        assert!(matches(r"error-\d+", "error-404"));
+        assert!(!matches(r"error-\d+", "error-"));
+        assert!(matches("北.", "北京"));
+        assert!(matches(".*", ""));
+        let automaton = RegexAutomaton::new(b"\x01", "a").unwrap();
+        let state = automaton.accept(&automaton.start(), 2u8);
+        assert!(!automaton.can_match(&state));
+    }
+
+    #[test]
+    fn test_regex_automaton_invalid() {
+        assert!(RegexAutomaton::new(b"\x01", "h[ae").is_err());
+        assert!(RegexAutomaton::new(b"\x01", "a)").is_err());
+    }
+}
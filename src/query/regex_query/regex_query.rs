@@ -0,0 +1,125 @@
+use schema::Field;
+use schema::Term;
+use query::Query;
+use query::Weight;
+use query::BooleanQuery;
+use query::QueryCost;
+use query::term_expansion::{self, DEFAULT_MAX_EXPANSIONS};
+use core::searcher::Searcher;
+use super::RegexAutomaton;
+use std::any::Any;
+use std::collections::BTreeSet;
+use fst::IntoStreamer;
+use Result;
+
+/// `RegexQuery` matches the documents containing a term
+/// matching a regular expression.
+///
+/// The regular expression must match the whole term:
+/// `h[ae]llo` matches `hallo` and `hello`, but not `hellos`.
+/// Use `.*hello.*` to match the terms containing `hello`.
+/// See the documentation of `fst::Regex` for the supported syntax.
+///
+/// The regular expression is compiled into a DFA, which is run
+/// over the term dictionary of each segment. The number of expansions
+/// is capped by `max_expansions`, (50 by default) and only the
+/// first terms in lexicographic order are kept. A warning is
+/// logged when some of the matching terms are left out.
+///
+/// A regular expression starting with `.*` cannot make use of
+/// the term dictionary and requires to go through all of
+/// the terms of the field.
+#[derive(Debug)]
+pub struct RegexQuery {
+    field: Field,
+    automaton: RegexAutomaton,
+    max_expansions: usize,
+}
+
+impl RegexQuery {
+
+    /// Creates a new `RegexQuery`.
+    ///
+    /// # Errors
+    /// If the pattern is not a valid regular expression, or if
+    /// it compiles into a DFA that is too large,
+    /// returns `Error::InvalidArgument`.
+    pub fn from_pattern(field: Field, pattern: &str) -> Result<RegexQuery> {
+        let field_prefix = Term::from_field_text(field, "");
+        let automaton = try!(RegexAutomaton::new(field_prefix.as_slice(), pattern));
+        Ok(RegexQuery {
+            field: field,
+            automaton: automaton,
+            max_expansions: DEFAULT_MAX_EXPANSIONS,
+        })
+    }
+
+    /// Sets the maximum number of terms the regular
+    /// expression may be expanded into.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max_expansions` is 0 or greater than 64.
+    pub fn set_max_expansions(mut self, max_expansions: usize) -> RegexQuery {
+        term_expansion::check_max_expansions(max_expansions);
+        self.max_expansions = max_expansions;
+        self
+    }
+
+    /// Returns the field targeted by the query.
+    pub fn field(&self,) -> Field {
+        self.field
+    }
+
+    /// Returns the regular expression.
+    pub fn pattern(&self,) -> &str {
+        self.automaton.pattern()
+    }
+
+    /// Returns the terms of the index matching the
+    /// regular expression, in lexicographic order,
+    /// and whether some of them were left out.
+    fn expand(&self, searcher: &Searcher) -> (Vec<Term>, bool) {
+        let mut expanded_terms: BTreeSet<Term> = BTreeSet::new();
+        let mut is_truncated = false;
+        for segment_reader in searcher.segment_readers() {
+            let stream = segment_reader
+                .term_infos()
+                .search(&self.automaton)
+                .into_stream();
+            is_truncated |= term_expansion::collect_terms(stream,
+                                                          |_| true,
+                                                          self.max_expansions,
+                                                          &mut expanded_terms);
+        }
+        term_expansion::first_terms(expanded_terms, is_truncated, self.max_expansions)
+    }
+}
+
+impl Query for RegexQuery {
+
+    /// Used to make it possible to cast Box<Query>
+    /// into a specific type. This is mostly useful for unit tests.
+    fn as_any(&self) -> &Any {
+        self
+    }
+
+    /// Create the weight associated to a query.
+    ///
+    /// The regular expression is expanded into the matching
+    /// terms of the index, which are then searched as a disjunction.
+    ///
+    /// See [Weight](./trait.Weight.html).
+    fn weight(&self, searcher: &Searcher) -> Result<Box<Weight>> {
+        let (expanded_terms, is_truncated) = self.expand(searcher);
+        if is_truncated {
+            term_expansion::warn_truncated(self, self.max_expansions);
+        }
+        BooleanQuery::new_multiterms_query(expanded_terms).weight(searcher)
+    }
+
+    fn estimate_cost(&self, searcher: &Searcher) -> QueryCost {
+        let (expanded_terms, _) = self.expand(searcher);
+        term_expansion::union_cost(searcher, expanded_terms.iter())
+    }
+}
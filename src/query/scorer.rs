@@ -16,8 +16,10 @@ pub trait Scorer: DocSet {
     
     /// Consumes the complete `DocSet` and
     /// push the scored documents to the collector. 
+    ///
+    /// Collection stops early if the collector gets aborted.
     fn collect(&mut self, collector: &mut Collector) {
-        while self.advance() {
+        while !collector.is_aborted() && self.advance() {
             collector.collect(self.doc(), self.score());
         }
     }
@@ -31,7 +33,7 @@ impl<'a> Scorer for Box<Scorer + 'a> {
     
     fn collect(&mut self, collector: &mut Collector) {
         let scorer = self.deref_mut();
-        while scorer.advance() {
+        while !collector.is_aborted() && scorer.advance() {
             collector.collect(scorer.doc(), scorer.score());
         }
     }
@@ -39,7 +41,9 @@ impl<'a> Scorer for Box<Scorer + 'a> {
 
 /// EmptyScorer is a dummy Scorer in which no document matches.
 /// 
-/// It is useful for tests and handling edge cases.
+/// It is returned by the weights when a segment is guaranteed
+/// not to match, e.g. when a term is absent from the segment,
+/// so that no postings need to be opened.
 pub struct EmptyScorer;
 
 impl DocSet for EmptyScorer {
@@ -50,6 +54,10 @@ impl DocSet for EmptyScorer {
     fn doc(&self,) -> DocId {
         DocId::max_value()
     }
+
+    fn size_hint(&self) -> u32 {
+        0
+    }
 }
 
 impl Scorer for EmptyScorer {
@@ -0,0 +1,110 @@
+use schema::Term;
+use query::QueryCost;
+use core::searcher::Searcher;
+use std::collections::BTreeSet;
+use std::fmt;
+use fst::Streamer;
+
+/// Maximum number of terms a query is expanded into, by default.
+pub const DEFAULT_MAX_EXPANSIONS: usize = 50;
+
+/// Upper bound for the number of terms a query is expanded into.
+/// (the union of the expanded terms is limited to 64 clauses)
+pub const MAX_MAX_EXPANSIONS: usize = 64;
+
+/// Checks the maximum number of terms a query may be expanded into.
+///
+/// # Panics
+///
+/// Panics if `max_expansions` is 0 or greater than 64.
+pub fn check_max_expansions(max_expansions: usize) {
+    assert!(max_expansions > 0 && max_expansions <= MAX_MAX_EXPANSIONS,
+            "A query is expanded into 1 to {} terms.", MAX_MAX_EXPANSIONS);
+}
+
+/// Inserts the terms of a term dictionary `stream` into `terms`,
+/// as long as they satisfy `take_while`, and up to `max_terms` of them.
+///
+/// Returns true iff the stream had more terms than `max_terms`.
+pub fn collect_terms<S, P>(mut stream: S,
+                           take_while: P,
+                           max_terms: usize,
+                           terms: &mut BTreeSet<Term>) -> bool
+    where S: for<'a> Streamer<'a, Item=(&'a [u8], u64)>, P: Fn(&[u8]) -> bool {
+    let mut num_terms = 0;
+    while let Some((term_bytes, _)) = stream.next() {
+        if !take_while(term_bytes) {
+            return false;
+        }
+        if num_terms == max_terms {
+            return true;
+        }
+        terms.insert(Term::from_bytes(term_bytes));
+        num_terms += 1;
+    }
+    false
+}
+
+/// Returns the first `max_expansions` terms in lexicographic order,
+/// and whether the query matches more terms, that is if `terms`
+/// holds more terms or if some of them were not collected.
+pub fn first_terms(terms: BTreeSet<Term>,
+                   is_truncated: bool,
+                   max_expansions: usize) -> (Vec<Term>, bool) {
+    let is_truncated = is_truncated || terms.len() > max_expansions;
+    let first_terms = terms
+        .into_iter()
+        .take(max_expansions)
+        .collect();
+    (first_terms, is_truncated)
+}
+
+/// Logs a warning for a query matching more than `max_expansions`
+/// terms, as the remaining terms are not searched.
+pub fn warn_truncated<Q: fmt::Debug>(query: &Q, max_expansions: usize) {
+    warn!("{:?} matches more than {} terms. Only the first {} of them are searched.",
+          query, max_expansions, max_expansions);
+}
+
+/// Returns the cost of the union of the postings of `terms`.
+pub fn union_cost<'a, I: Iterator<Item=&'a Term>>(searcher: &Searcher, terms: I) -> QueryCost {
+    terms
+        .map(|term| QueryCost::for_term(searcher, term))
+        .fold(QueryCost::default(), QueryCost::union)
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::{collect_terms, first_terms};
+    use schema::{Field, Term};
+    use std::collections::BTreeSet;
+    use fst::{IntoStreamer, Map};
+
+    #[test]
+    fn test_collect_terms() {
+        let term = |text: &str| Term::from_field_text(Field(0), text);
+        let texts = ["rest", "rust", "rust.log", "rusty", "sea"];
+        let map = Map::from_iter(texts.iter().map(|text| (term(text).as_slice().to_vec(), 0u64))).unwrap();
+        let prefix = term("rust");
+        let collect = |max_terms: usize| {
+            let mut terms = BTreeSet::new();
+            let stream = map.range().ge(prefix.as_slice()).into_stream();
+            let is_truncated = collect_terms(stream,
+                                             |term_bytes| term_bytes.starts_with(prefix.as_slice()),
+                                             max_terms,
+                                             &mut terms);
+            (terms, is_truncated)
+        };
+        let (terms, is_truncated) = collect(3);
+        assert_eq!(terms.len(), 3);
+        assert!(!is_truncated);
+        let (terms, is_truncated) = collect(2);
+        assert_eq!(terms.into_iter().collect::<Vec<Term>>(), vec!(term("rust"), term("rust.log")));
+        assert!(is_truncated);
+        let (terms, is_truncated) = first_terms(collect(3).0, false, 2);
+        assert_eq!(terms, vec!(term("rust"), term("rust.log")));
+        assert!(is_truncated);
+    }
+}
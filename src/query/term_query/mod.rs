@@ -16,9 +16,15 @@ mod tests {
     use query::Query;
     use fastfield::U32FastFieldReader;
     use query::TermQuery;
+    use query::{BooleanQuery, Occur};
+    use compression::NUM_BLOCK_DECODERS;
     use Index;
+    use Searcher;
     use schema::*;
     use postings::SegmentPostingsOption;
+    use rustc_serialize::json;
+    use Error;
+    use fst::{IntoStreamer, Streamer};
 
     fn abs_diff(left: f32, right: f32) -> f32 {
         (right - left).abs()
@@ -52,7 +58,77 @@ mod tests {
         assert_eq!(term_scorer.score(), 0.30685282);
     }
 
-       
+    #[test]
+    pub fn test_missing_term_allocates_no_decoder() {
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.add_document(doc!(text_field => "a b"));
+            assert!(index_writer.commit().is_ok());
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let segment_reader = searcher.segment_reader(0);
+        let term_query = |text: &str| -> Box<Query> {
+            box TermQuery::new(Term::from_field_text(text_field, text), SegmentPostingsOption::NoFreq)
+        };
+        let num_block_decoders = |query: &Query| -> usize {
+            let weight = query.weight(&searcher).unwrap();
+            let before = NUM_BLOCK_DECODERS.with(|num_block_decoders| num_block_decoders.get());
+            let mut scorer = weight.scorer(segment_reader).unwrap();
+            let size_hint = scorer.size_hint();
+            assert_eq!(size_hint == 0, !scorer.advance());
+            NUM_BLOCK_DECODERS.with(|num_block_decoders| num_block_decoders.get()) - before
+        };
+        assert!(num_block_decoders(&*term_query("a")) > 0);
+        assert_eq!(num_block_decoders(&*term_query("missing")), 0);
+        let intersection = BooleanQuery::from(vec!(
+            (Occur::Must, term_query("missing")),
+            (Occur::Must, term_query("a")),
+            (Occur::Should, term_query("b")),
+        ));
+        assert_eq!(num_block_decoders(&intersection), 0);
+        let union = BooleanQuery::from(vec!(
+            (Occur::Should, term_query("a")),
+            (Occur::Should, term_query("missing")),
+        ));
+        assert!(num_block_decoders(&union) > 0);
+    }
+
+    #[test]
+    pub fn test_term_query_on_stored_only_field() {
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let archive_field = schema_builder.add_text_field("archive", STORED);
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.add_document(doc!(text_field => "a", archive_field => "archived"));
+            assert!(index_writer.commit().is_ok());
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let segment_reader = searcher.segment_reader(0);
+        // nothing is written to the inverted index for the stored only field.
+        let archive_term = Term::from_field_text(archive_field, "archived");
+        assert!(!segment_reader.contains_term(&archive_term));
+        // the stored only field is the last field, so none of the terms follow it.
+        let field_prefix = Term::from_field_text(archive_field, "");
+        assert!(segment_reader.term_infos().range().ge(field_prefix.as_slice()).into_stream().next().is_none());
+        // the stored value is retrievable.
+        let doc = segment_reader.doc(0).unwrap();
+        assert_eq!(doc.get_first(archive_field).unwrap().text(), "archived");
+        let archive_query = TermQuery::new(archive_term, SegmentPostingsOption::NoFreq);
+        match archive_query.weight(&searcher) {
+            Err(Error::SchemaError(msg)) => assert!(msg.contains("archive")),
+            _ => panic!("Searching a stored only field should fail."),
+        }
+        let text_query = TermQuery::new(Term::from_field_text(text_field, "a"), SegmentPostingsOption::NoFreq);
+        assert!(text_query.weight(&searcher).is_ok());
+    }
+
     #[test]
     pub fn test_term_scorer() {
         let left_fieldnorms = U32FastFieldReader::from(vec!(10, 4));
@@ -63,9 +139,89 @@ mod tests {
             idf: 0.30685282,
             fieldnorm_reader_opt: Some(left_fieldnorms),
             postings: left,
+            similarity: Similarity::TfIdf,
+            average_fieldnorm: 0f32,
         };
         left_scorer.advance();
         assert!(abs_diff(left_scorer.score(), 0.15342641) < 0.001f32);
     }
 
+    #[test]
+    pub fn test_per_field_similarity() {
+        let mut schema_builder = SchemaBuilder::default();
+        let title_field = schema_builder.add_text_field("title", TEXT);
+        let body_field = schema_builder.add_text_field("body", TEXT);
+        schema_builder.set_similarity(title_field, Similarity::BM25 { k1: 1.2, b: 0.0 });
+        schema_builder.set_similarity(body_field, Similarity::BM25 { k1: 1.2, b: 1.0 });
+        let schema = schema_builder.build();
+        let schema_json = json::encode(&schema).unwrap();
+        let schema_decoded: Schema = json::decode(&schema_json).unwrap();
+        assert_eq!(schema_decoded.get_field_entry(body_field).similarity(), Similarity::BM25 { k1: 1.2, b: 1.0 });
+        assert_eq!(schema_decoded.get_field_entry(title_field).similarity(), Similarity::BM25 { k1: 1.2, b: 0.0 });
+        let index = Index::create_in_ram(schema);
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.add_document(doc!(title_field=>"a b c d e f", body_field=>"a b c d e f"));
+            index_writer.add_document(doc!(title_field=>"a", body_field=>"a"));
+            index_writer.add_document(doc!(title_field=>"b", body_field=>"b"));
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let scores = |field: Field| {
+            let term_query = TermQuery::new(Term::from_field_text(field, "a"), SegmentPostingsOption::Freq);
+            let term_weight = term_query.weight(&searcher).unwrap();
+            let mut term_scorer = term_weight.scorer(searcher.segment_reader(0)).unwrap();
+            let mut scores = Vec::new();
+            while term_scorer.advance() {
+                scores.push(term_scorer.score());
+            }
+            scores
+        };
+        let title_scores = scores(title_field);
+        let body_scores = scores(body_field);
+        // with b = 0, the length of the field is ignored.
+        assert!(abs_diff(title_scores[0], title_scores[1]) < 0.0001f32);
+        // with b = 1, the short field scores higher than the long one.
+        assert!(body_scores[1] > body_scores[0]);
+        assert!(body_scores[0] < title_scores[0]);
+        assert!(body_scores[1] > title_scores[1]);
+    }
+
+    #[test]
+    pub fn test_tf_only_similarity() {
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        schema_builder.set_similarity(text_field, Similarity::TfOnly);
+        let schema = schema_builder.build();
+        let schema_json = json::encode(&schema).unwrap();
+        let schema_decoded: Schema = json::decode(&schema_json).unwrap();
+        assert_eq!(schema_decoded.get_field_entry(text_field).similarity(), Similarity::TfOnly);
+        let index = Index::create_in_ram(schema);
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.add_document(doc!(text_field=>"rare common rare common"));
+            index_writer.add_document(doc!(text_field=>"common"));
+            index_writer.add_document(doc!(text_field=>"common"));
+            index_writer.add_document(doc!(text_field=>"common"));
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let mut searcher = index.searcher();
+        let first_score = |searcher: &Searcher, text: &str| {
+            let term_query = TermQuery::new(Term::from_field_text(text_field, text), SegmentPostingsOption::Freq);
+            let term_weight = term_query.weight(searcher).unwrap();
+            let mut term_scorer = term_weight.scorer(searcher.segment_reader(0)).unwrap();
+            assert!(term_scorer.advance());
+            assert_eq!(term_scorer.doc(), 0);
+            term_scorer.score()
+        };
+        // the rare and the common term both appear twice in the first document.
+        assert!(abs_diff(first_score(&searcher, "rare"), first_score(&searcher, "common")) < 0.0001f32);
+        assert!(abs_diff(first_score(&searcher, "rare"), 0.5f32.sqrt()) < 0.0001f32);
+        searcher.set_similarity(Some(Similarity::bm25()));
+        assert!(first_score(&searcher, "rare") > first_score(&searcher, "common"));
+        searcher.set_similarity(None);
+    }
+
 }
\ No newline at end of file
@@ -3,14 +3,17 @@ use Result;
 use super::term_weight::TermWeight;
 use query::Query;
 use query::Weight;
+use query::QueryCost;
 use postings::SegmentPostingsOption;
 use Searcher;
+use schema::Similarity;
 use std::any::Any;
 
 /// A Term query matches all of the documents
 /// containing a specific term.
 ///
-/// The score associated is defined as
+/// The score associated is defined by the `Similarity`
+/// of the term's field. By default, it is
 /// `idf` *  sqrt(`term_freq` / `field norm`)
 /// in which :
 /// * idf        - inverse document frequency. 
@@ -38,11 +41,21 @@ impl TermQuery {
     /// this method return a specific implementation.
     /// This is useful for optimization purpose.
     pub fn specialized_weight(&self, searcher: &Searcher) -> TermWeight {
+        let field = self.term.field();
+        let similarity = searcher.similarity(field);
+        let average_fieldnorm = if similarity.requires_average_fieldnorm() {
+            searcher.average_fieldnorm(field)
+        }
+        else {
+            0f32
+        };
         TermWeight {
-            num_docs: searcher.num_docs(),
-            doc_freq: searcher.doc_freq(&self.term),
+            num_docs: searcher.idf_num_docs(field),
+            doc_freq: searcher.idf_doc_freq(&self.term),
             term: self.term.clone(),
             segment_postings_options: self.segment_postings_options,
+            similarity: similarity,
+            average_fieldnorm: average_fieldnorm,
         }
     }
 }
@@ -52,8 +65,29 @@ impl Query for TermQuery {
         self
     }
 
+    fn query_terms(&self, terms: &mut Vec<Term>) {
+        terms.push(self.term.clone());
+    }
+
+    fn estimate_cost(&self, searcher: &Searcher) -> QueryCost {
+        QueryCost::for_term(searcher, &self.term)
+    }
+
     fn weight(&self, searcher: &Searcher) -> Result<Box<Weight>> {
+        try!(searcher.check_indexed(self.term.field()));
         Ok(box self.specialized_weight(searcher))
     }
+
+    fn matching_weight(&self, searcher: &Searcher) -> Result<Box<Weight>> {
+        try!(searcher.check_indexed(self.term.field()));
+        Ok(box TermWeight {
+            num_docs: 0,
+            doc_freq: 0,
+            term: self.term.clone(),
+            segment_postings_options: SegmentPostingsOption::NoFreq,
+            similarity: Similarity::Constant,
+            average_fieldnorm: 0f32,
+        })
+    }
     
 }
@@ -1,14 +1,17 @@
 use Score;
 use DocId;
 use fastfield::U32FastFieldReader;
-use postings::DocSet;
+use postings::{DocSet, SkipResult};
 use query::Scorer;
 use postings::Postings;
+use schema::Similarity;
 
 pub struct TermScorer<TPostings> where TPostings: Postings {
     pub idf: Score,
     pub fieldnorm_reader_opt: Option<U32FastFieldReader>,
     pub postings: TPostings,
+    pub similarity: Similarity,
+    pub average_fieldnorm: f32,
 }
 
 impl<TPostings> TermScorer<TPostings> where TPostings: Postings {
@@ -21,25 +24,31 @@ impl<TPostings> DocSet for TermScorer<TPostings> where TPostings: Postings {
     fn advance(&mut self,) -> bool {
         self.postings.advance()
     }
+
+    fn skip_next(&mut self, target: DocId) -> SkipResult {
+        self.postings.skip_next(target)
+    }
       
     fn doc(&self,) -> DocId {
         self.postings.doc()
     }
+
+    fn buffered_docs(&self) -> &[DocId] {
+        self.postings.buffered_docs()
+    }
+
+    fn size_hint(&self) -> u32 {
+        self.postings.size_hint()
+    }
 }
 
 impl<TPostings> Scorer for TermScorer<TPostings> where TPostings: Postings {
     fn score(&self,) -> Score {
         let doc = self.postings.doc();
-        let tf = match self.fieldnorm_reader_opt {
-            Some(ref fieldnorm_reader) => {
-                let field_norm = fieldnorm_reader.get(doc);
-                (self.postings.term_freq() as f32 / field_norm as f32)
-            }
-            None => {
-                self.postings.term_freq() as f32
-            }
-        };
-        self.idf * tf.sqrt()
+        let fieldnorm_opt = self.fieldnorm_reader_opt
+            .as_ref()
+            .map(|fieldnorm_reader| fieldnorm_reader.get(doc));
+        self.similarity.score(self.idf, self.postings.term_freq(), fieldnorm_opt, self.average_fieldnorm)
     } 
 }
 
@@ -2,24 +2,65 @@ use Term;
 use query::Weight;
 use core::SegmentReader;
 use query::Scorer;
+use query::EmptyScorer;
+use query::Explanation;
+use postings::{DocSet, Postings, SkipResult};
 use postings::SegmentPostingsOption;
 use postings::SegmentPostings;
 use super::term_scorer::TermScorer;
+use schema::Similarity;
 use Result;
+use DocId;
 
 pub struct TermWeight {
-    pub num_docs: u32,
+    pub num_docs: u64,
     pub doc_freq: u32,
     pub term: Term,
     pub segment_postings_options: SegmentPostingsOption,
+    pub similarity: Similarity,
+    pub average_fieldnorm: f32,
 }
 
 
 impl Weight for TermWeight {
     
     fn scorer<'a>(&'a self, reader: &'a SegmentReader) -> Result<Box<Scorer + 'a>> {
-        let specialized_scorer = try!(self.specialized_scorer(reader));
-        Ok(box specialized_scorer)
+        // a term absent from the segment does not need any postings.
+        match reader.read_postings(&self.term, self.segment_postings_options) {
+            Some(segment_postings) => Ok(box self.term_scorer(reader, segment_postings)),
+            None => Ok(box EmptyScorer),
+        }
+    }
+
+    fn explain(&self, reader: &SegmentReader, doc: DocId) -> Result<Option<Explanation>> {
+        let mut term_scorer = try!(self.specialized_scorer(reader));
+        if term_scorer.skip_next(doc) != SkipResult::Reached {
+            return Ok(None);
+        }
+        let mut explanation = Explanation::new(
+            format!("{:?} score of {:?}", self.similarity, self.term),
+            term_scorer.score());
+        explanation.add_detail(Explanation::new(
+            format!("idf, {} docs out of {} containing the term", self.doc_freq, self.num_docs),
+            term_scorer.idf));
+        explanation.add_detail(Explanation::new("term freq", term_scorer.postings().term_freq() as f32));
+        if let Some(ref fieldnorm_reader) = term_scorer.fieldnorm_reader_opt {
+            explanation.add_detail(Explanation::new("field norm", fieldnorm_reader.get(doc) as f32));
+        }
+        if self.similarity.requires_average_fieldnorm() {
+            explanation.add_detail(Explanation::new("average field norm", self.average_fieldnorm));
+        }
+        Ok(Some(explanation))
+    }
+
+    fn count(&self, reader: &SegmentReader) -> Result<u32> {
+        // without deletes, all of the documents
+        // of the posting list match.
+        if reader.num_deleted_docs() == 0 {
+            return Ok(reader.doc_freq(&self.term));
+        }
+        let mut scorer = try!(self.scorer(reader));
+        Ok(scorer.count())
     }
     
 }
@@ -27,27 +68,36 @@ impl Weight for TermWeight {
 impl TermWeight {
     
     fn idf(&self) -> f32 {
-        1.0 + (self.num_docs as f32 / (self.doc_freq as f32 + 1.0)).ln()
+        self.similarity.idf(self.num_docs, self.doc_freq)
     }
 
+    fn term_scorer<'a>(&'a self, reader: &'a SegmentReader, segment_postings: SegmentPostings<'a>) -> TermScorer<SegmentPostings<'a>> {
+        TermScorer {
+            idf: self.idf(),
+            fieldnorm_reader_opt: reader.get_fieldnorms_reader(self.term.field()),
+            postings: segment_postings,
+            similarity: self.similarity,
+            average_fieldnorm: self.average_fieldnorm,
+        }
+    }
+
+    /// Returns a `TermScorer`, backed by empty postings
+    /// if the term is absent from the segment.
+    ///
+    /// Unlike `.scorer(...)`, the scorer is always a `TermScorer`,
+    /// which is required when its postings are accessed.
     pub fn specialized_scorer<'a>(&'a self, reader: &'a SegmentReader) -> Result<TermScorer<SegmentPostings<'a>>> {
-        let field = self.term.field();
-        let fieldnorm_reader_opt = reader.get_fieldnorms_reader(field);
         Ok(
             reader
                 .read_postings(&self.term, self.segment_postings_options)
-                .map(|segment_postings|
-                    TermScorer {
-                        idf: self.idf(),
-                        fieldnorm_reader_opt: fieldnorm_reader_opt,
-                        postings: segment_postings,
-                    }
-                )
-                .unwrap_or(
+                .map(|segment_postings| self.term_scorer(reader, segment_postings))
+                .unwrap_or_else(||
                     TermScorer {
                         idf: 1f32,
                         fieldnorm_reader_opt: None,
-                        postings: SegmentPostings::empty()
+                        postings: SegmentPostings::empty(),
+                        similarity: self.similarity,
+                        average_fieldnorm: self.average_fieldnorm,
                     })
         )
     }
@@ -0,0 +1,67 @@
+mod term_range_query;
+mod term_range_weight;
+mod term_range_scorer;
+
+pub use self::term_range_query::TermRangeQuery;
+pub use self::term_range_weight::TermRangeWeight;
+pub use self::term_range_scorer::TermRangeScorer;
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use std::collections::Bound;
+    use core::Index;
+    use schema::{Document, SchemaBuilder, Term, DateTime, DATE_INDEXED, STRING};
+    use collector::tests::TestCollector;
+    use query::Query;
+
+    #[test]
+    fn test_term_range_query() {
+        let mut schema_builder = SchemaBuilder::default();
+        let date_field = schema_builder.add_date_field("date", DATE_INDEXED);
+        let text_field = schema_builder.add_text_field("text", STRING);
+        let index = Index::create_in_ram(schema_builder.build());
+        let date = |day: i64| DateTime::from_timestamp_millis((day - 50) * 86_400_000);
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            for i in 0..100 {
+                let mut doc = Document::default();
+                doc.add_date(date_field, date((i * 7) % 100));
+                doc.add_text(text_field, &format!("{:02}", i));
+                index_writer.add_document(doc);
+                if i == 49 {
+                    index_writer.commit().unwrap();
+                }
+            }
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let matching_docs = |query: TermRangeQuery| {
+            let mut collector = TestCollector::default();
+            query.search(&*searcher, &mut collector).unwrap();
+            collector.docs()
+        };
+        let expected_docs = |contains: &Fn(i64) -> bool| -> Vec<u32> {
+            (0..100u32).filter(|&i| contains((i as i64 * 7) % 100)).collect()
+        };
+        let date_term = |day: i64| Term::from_field_date(date_field, date(day));
+        // the range spans negative and positive timestamps.
+        let date_range = TermRangeQuery::new(date_field, Bound::Included(date_term(40)), Bound::Excluded(date_term(60)));
+        assert_eq!(matching_docs(date_range), expected_docs(&|day| day >= 40 && day < 60));
+        let date_range = TermRangeQuery::new(date_field, Bound::Excluded(date_term(40)), Bound::Unbounded);
+        assert_eq!(matching_docs(date_range), expected_docs(&|day| day > 40));
+        let date_range = TermRangeQuery::new(date_field, Bound::Unbounded, Bound::Included(date_term(3)));
+        assert_eq!(matching_docs(date_range), expected_docs(&|day| day <= 3));
+        let all_dates = TermRangeQuery::new(date_field, Bound::Unbounded, Bound::Unbounded);
+        assert_eq!(matching_docs(all_dates).len(), 100);
+        let empty_range = TermRangeQuery::new(date_field, Bound::Included(date_term(60)), Bound::Included(date_term(40)));
+        assert!(matching_docs(empty_range).is_empty());
+
+        let text_term = |text: &str| Term::from_field_text(text_field, text);
+        let text_range = TermRangeQuery::new(text_field, Bound::Included(text_term("10")), Bound::Included(text_term("19")));
+        assert_eq!(matching_docs(text_range), (10..20).collect::<Vec<u32>>());
+    }
+}
@@ -0,0 +1,109 @@
+use std::collections::Bound;
+use query::Query;
+use query::Weight;
+use query::QueryCost;
+use core::searcher::Searcher;
+use schema::Field;
+use schema::Term;
+use super::TermRangeWeight;
+use std::any::Any;
+use Result;
+
+
+/// `TermRangeQuery` matches the documents containing a term
+/// of a field within a range of terms.
+///
+/// The terms are compared in the lexicographic order of their bytes,
+/// which is the order of the values for text fields,
+/// and for dates (see `Term::from_field_date`).
+/// For instance, the documents created during 2017 are matched by
+///
+/// ```rust
+/// # use tantivy::schema::{SchemaBuilder, DateTime, Term, DATE_INDEXED};
+/// use std::collections::Bound;
+/// use tantivy::query::TermRangeQuery;
+///
+/// # let mut schema_builder = SchemaBuilder::default();
+/// # let created_field = schema_builder.add_date_field("created", DATE_INDEXED);
+/// let start = DateTime::from_rfc3339("2017-01-01T00:00:00Z").unwrap();
+/// let end = DateTime::from_rfc3339("2018-01-01T00:00:00Z").unwrap();
+/// let query = TermRangeQuery::new(created_field,
+///                                 Bound::Included(Term::from_field_date(created_field, start)),
+///                                 Bound::Excluded(Term::from_field_date(created_field, end)));
+/// ```
+///
+/// All of the matching documents get the same score, `1`.
+///
+/// Unlike the expansion of a `WildcardQuery`, the number
+/// of terms within the range is not limited: the doc ids of
+/// all of their postings are merged for each segment.
+#[derive(Debug)]
+pub struct TermRangeQuery {
+    field: Field,
+    lower_bound: Bound<Term>,
+    upper_bound: Bound<Term>,
+}
+
+impl TermRangeQuery {
+
+    /// Creates a new `TermRangeQuery`.
+    ///
+    /// If the lower bound is greater than the upper bound,
+    /// the query matches no document.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the terms of the bounds are not terms of `field`.
+    pub fn new(field: Field, lower_bound: Bound<Term>, upper_bound: Bound<Term>) -> TermRangeQuery {
+        for bound in &[&lower_bound, &upper_bound] {
+            match **bound {
+                Bound::Included(ref term) | Bound::Excluded(ref term) => {
+                    assert_eq!(term.field(), field, "The bounds of a TermRangeQuery must be terms of its field.");
+                }
+                Bound::Unbounded => {}
+            }
+        }
+        TermRangeQuery {
+            field: field,
+            lower_bound: lower_bound,
+            upper_bound: upper_bound,
+        }
+    }
+
+    /// Returns the field targeted by the query.
+    pub fn field(&self,) -> Field {
+        self.field
+    }
+
+    fn term_range_weight(&self) -> TermRangeWeight {
+        TermRangeWeight {
+            field: self.field,
+            lower_bound: self.lower_bound.clone(),
+            upper_bound: self.upper_bound.clone(),
+        }
+    }
+}
+
+impl Query for TermRangeQuery {
+
+    fn as_any(&self) -> &Any {
+        self
+    }
+
+    fn weight(&self, _searcher: &Searcher) -> Result<Box<Weight>> {
+        Ok(box self.term_range_weight())
+    }
+
+    fn estimate_cost(&self, searcher: &Searcher) -> QueryCost {
+        let weight = self.term_range_weight();
+        searcher
+            .segment_readers()
+            .iter()
+            .flat_map(|segment_reader| {
+                weight.terms(segment_reader)
+                    .into_iter()
+                    .map(move |term| QueryCost::for_segment_term(segment_reader, &term))
+            })
+            .fold(QueryCost::default(), QueryCost::union)
+    }
+}
@@ -0,0 +1,34 @@
+use query::Scorer;
+use postings::{DocSet, SkipResult, VecDocSet};
+use DocId;
+use Score;
+
+/// Scorer going through the documents containing
+/// any of the terms of the range.
+pub struct TermRangeScorer {
+    pub doc_set: VecDocSet,
+}
+
+impl DocSet for TermRangeScorer {
+    fn advance(&mut self) -> bool {
+        self.doc_set.advance()
+    }
+
+    fn skip_next(&mut self, target: DocId) -> SkipResult {
+        self.doc_set.skip_next(target)
+    }
+
+    fn doc(&self) -> DocId {
+        self.doc_set.doc()
+    }
+
+    fn size_hint(&self) -> u32 {
+        self.doc_set.size_hint()
+    }
+}
+
+impl Scorer for TermRangeScorer {
+    fn score(&self) -> Score {
+        1f32
+    }
+}
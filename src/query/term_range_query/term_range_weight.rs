@@ -0,0 +1,65 @@
+use std::collections::Bound;
+use query::Weight;
+use query::Scorer;
+use core::SegmentReader;
+use postings::{DocSet, SegmentPostingsOption, VecDocSet};
+use schema::Field;
+use schema::Term;
+use super::TermRangeScorer;
+use fst::{IntoStreamer, Streamer};
+use DocId;
+use Result;
+
+pub struct TermRangeWeight {
+    pub field: Field,
+    pub lower_bound: Bound<Term>,
+    pub upper_bound: Bound<Term>,
+}
+
+impl TermRangeWeight {
+    /// Returns the terms of the segment within the range.
+    pub fn terms(&self, reader: &SegmentReader) -> Vec<Term> {
+        let field_start = [self.field.0];
+        let mut stream_builder = reader.term_infos().range();
+        stream_builder = match self.lower_bound {
+            Bound::Included(ref term) => stream_builder.ge(term.as_slice()),
+            Bound::Excluded(ref term) => stream_builder.gt(term.as_slice()),
+            Bound::Unbounded => stream_builder.ge(&field_start[..]),
+        };
+        stream_builder = match self.upper_bound {
+            Bound::Included(ref term) => stream_builder.le(term.as_slice()),
+            Bound::Excluded(ref term) => stream_builder.lt(term.as_slice()),
+            Bound::Unbounded => {
+                // the terms of the field are all prefixed by its id.
+                match self.field.0.checked_add(1) {
+                    Some(next_field_id) => stream_builder.lt(&[next_field_id][..]),
+                    None => stream_builder,
+                }
+            }
+        };
+        let mut terms = Vec::new();
+        let mut stream = stream_builder.into_stream();
+        while let Some((term_bytes, _)) = stream.next() {
+            terms.push(Term::from_bytes(term_bytes));
+        }
+        terms
+    }
+}
+
+impl Weight for TermRangeWeight {
+    fn scorer<'a>(&'a self, reader: &'a SegmentReader) -> Result<Box<Scorer + 'a>> {
+        let mut doc_ids: Vec<DocId> = Vec::new();
+        for term in self.terms(reader) {
+            if let Some(mut postings) = reader.read_postings(&term, SegmentPostingsOption::NoFreq) {
+                while postings.advance() {
+                    doc_ids.push(postings.doc());
+                }
+            }
+        }
+        doc_ids.sort();
+        doc_ids.dedup();
+        Ok(box TermRangeScorer {
+            doc_set: VecDocSet::from(doc_ids),
+        })
+    }
+}
@@ -1,16 +1,42 @@
-use super::Scorer;
+use super::{Scorer, Explanation};
 use Result;
+use DocId;
 use core::SegmentReader;
+use postings::{DocSet, SkipResult};
 
 
 /// A Weight is the specialization of a Query
 /// for a given set of segments.
 ///
 /// See [Query](./trait.Query.html).
-pub trait Weight {
-    
+pub trait Weight: Sync {
+
     /// Returns the scorer for the given segment.
     /// See [Query](./trait.Query.html).
     fn scorer<'a>(&'a self, reader: &'a SegmentReader) -> Result<Box<Scorer + 'a>>;
-    
+
+    /// Explains the score of the document `doc` of the given segment,
+    /// or returns `None` if the document does not match.
+    ///
+    /// By default, the explanation is the score of the scorer,
+    /// without any detail.
+    /// See [Explanation](./struct.Explanation.html).
+    fn explain(&self, reader: &SegmentReader, doc: DocId) -> Result<Option<Explanation>> {
+        let mut scorer = try!(self.scorer(reader));
+        if scorer.skip_next(doc) != SkipResult::Reached {
+            return Ok(None);
+        }
+        Ok(Some(Explanation::new("score", scorer.score())))
+    }
+
+    /// Returns the number of documents of the given segment
+    /// matching the query. Deleted documents are not counted.
+    ///
+    /// By default, the scorer goes through all of the matching
+    /// documents, without computing their score.
+    fn count(&self, reader: &SegmentReader) -> Result<u32> {
+        let mut scorer = try!(self.scorer(reader));
+        Ok(scorer.count())
+    }
+
 }
@@ -0,0 +1,48 @@
+mod wildcard_automaton;
+mod wildcard_query;
+
+pub use self::wildcard_automaton::WildcardAutomaton;
+pub use self::wildcard_query::WildcardQuery;
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use core::Index;
+    use schema::FieldValue;
+    use schema::{Document, SchemaBuilder, TEXT};
+    use collector::tests::TestCollector;
+
+    #[test]
+    pub fn test_wildcard_query() {
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let other_field = schema_builder.add_text_field("other", TEXT);
+        let schema = schema_builder.build();
+        let index = Index::create_in_ram(schema);
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.add_document(doc!(text_field=>"a test"));  // 0
+            index_writer.add_document(doc!(text_field=>"some text"));  // 1
+            index_writer.add_document(doc!(text_field=>"a tent"));  // 2
+            index_writer.add_document(doc!(text_field=>"a toast", other_field=>"test"));  // 3
+            assert!(index_writer.commit().is_ok());
+            index_writer.add_document(doc!(text_field=>"searching the tests"));  // 4
+            assert!(index_writer.commit().is_ok());
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let test_query = |query: WildcardQuery| {
+            let mut test_collector = TestCollector::default();
+            searcher.search(&query, &mut test_collector).expect("search should succeed");
+            test_collector.docs()
+        };
+        assert_eq!(test_query(WildcardQuery::new(text_field, "te?t")), vec!(0, 1, 2));
+        assert_eq!(test_query(WildcardQuery::new(text_field, "te*")), vec!(0, 1, 2, 4));
+        assert_eq!(test_query(WildcardQuery::new(text_field, "*st*")), vec!(0, 3, 4));
+        assert_eq!(test_query(WildcardQuery::new(text_field, "te?t").set_max_expansions(2)), vec!(0, 2));
+        assert_eq!(test_query(WildcardQuery::new(other_field, "te?t")), vec!(3));
+        assert!(test_query(WildcardQuery::new(text_field, "x*")).is_empty());
+    }
+}
@@ -0,0 +1,181 @@
+use fst::Automaton;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Token {
+    /// Matches exactly this byte.
+    Byte(u8),
+    /// `?`, matches any single character.
+    AnyChar,
+    /// `*`, matches any sequence of characters, including the empty one.
+    AnySeq,
+}
+
+/// Returns the number of continuation bytes following
+/// a given leading byte in UTF-8.
+fn num_continuation_bytes(leading_byte: u8) -> u8 {
+    if leading_byte < 0xC0 {
+        0
+    } else if leading_byte < 0xE0 {
+        1
+    } else if leading_byte < 0xF0 {
+        2
+    } else {
+        3
+    }
+}
+
+fn is_continuation_byte(byte: u8) -> bool {
+    byte & 0xC0 == 0x80
+}
+
+/// Automaton matching the keys made of a prefix followed by
+/// a text matching a wildcard pattern.
+///
+/// In the pattern, `?` matches any single character and `*` matches
+/// any sequence of characters. All other characters are matched literally.
+///
+/// The automaton works on bytes: its state is the set of the
+/// positions reached in the pattern, each with the number of UTF-8
+/// continuation bytes still expected for the character matched by a `?`.
+pub struct WildcardAutomaton {
+    tokens: Vec<Token>,
+}
+
+impl WildcardAutomaton {
+
+    /// Creates an automaton matching the keys starting with `prefix`,
+    /// and followed by a text matching `pattern`.
+    pub fn new(prefix: &[u8], pattern: &str) -> WildcardAutomaton {
+        let mut tokens: Vec<Token> = prefix.iter().cloned().map(Token::Byte).collect();
+        let mut buffer = [0u8; 4];
+        for c in pattern.chars() {
+            match c {
+                '?' => tokens.push(Token::AnyChar),
+                '*' => {
+                    // `**` is equivalent to `*`
+                    if tokens.last() != Some(&Token::AnySeq) {
+                        tokens.push(Token::AnySeq);
+                    }
+                }
+                _ => {
+                    let encoded_len = c.encode_utf8(&mut buffer).len();
+                    tokens.extend(buffer[..encoded_len].iter().cloned().map(Token::Byte));
+                }
+            }
+        }
+        WildcardAutomaton {
+            tokens: tokens,
+        }
+    }
+
+    /// Adds a pattern position to the state, as well as the positions
+    /// that can be reached from it without consuming any byte.
+    fn add_position(&self, state: &mut Vec<(usize, u8)>, mut pos: usize) {
+        loop {
+            state.push((pos, 0u8));
+            if self.tokens.get(pos) == Some(&Token::AnySeq) {
+                pos += 1;
+            } else {
+                break;
+            }
+        }
+    }
+}
+
+impl Automaton for WildcardAutomaton {
+
+    type State = Vec<(usize, u8)>;
+
+    fn start(&self) -> Vec<(usize, u8)> {
+        let mut state = Vec::new();
+        self.add_position(&mut state, 0);
+        state
+    }
+
+    fn is_match(&self, state: &Vec<(usize, u8)>) -> bool {
+        state.contains(&(self.tokens.len(), 0u8))
+    }
+
+    fn can_match(&self, state: &Vec<(usize, u8)>) -> bool {
+        !state.is_empty()
+    }
+
+    fn will_always_match(&self, state: &Vec<(usize, u8)>) -> bool {
+        // the pattern ends with a `*` that has been reached.
+        self.tokens.last() == Some(&Token::AnySeq) &&
+            state.contains(&(self.tokens.len() - 1, 0u8))
+    }
+
+    fn accept(&self, state: &Vec<(usize, u8)>, byte: u8) -> Vec<(usize, u8)> {
+        let mut next_state = Vec::new();
+        for &(pos, num_pending_bytes) in state {
+            if num_pending_bytes > 0 {
+                if is_continuation_byte(byte) {
+                    if num_pending_bytes == 1 {
+                        self.add_position(&mut next_state, pos);
+                    } else {
+                        next_state.push((pos, num_pending_bytes - 1));
+                    }
+                }
+                continue;
+            }
+            match self.tokens.get(pos) {
+                Some(&Token::Byte(expected_byte)) => {
+                    if byte == expected_byte {
+                        self.add_position(&mut next_state, pos + 1);
+                    }
+                }
+                Some(&Token::AnyChar) => {
+                    match num_continuation_bytes(byte) {
+                        0 => self.add_position(&mut next_state, pos + 1),
+                        num_bytes => next_state.push((pos + 1, num_bytes)),
+                    }
+                }
+                Some(&Token::AnySeq) => {
+                    // keys are valid UTF-8, and the following tokens can only
+                    // match on a char boundary, so that `*` can consume bytes
+                    // one at a time.
+                    self.add_position(&mut next_state, pos);
+                }
+                None => {}
+            }
+        }
+        next_state.sort();
+        next_state.dedup();
+        next_state
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn matches(pattern: &str, text: &str) -> bool {
+        let automaton = WildcardAutomaton::new(b"\x01", pattern);
+        let mut state = automaton.start();
+        for &byte in [1u8].iter().chain(text.as_bytes()) {
+            state = automaton.accept(&state, byte);
+        }
+        automaton.is_match(&state)
+    }
+
+    #[test]
+    fn test_wildcard_automaton() {
+        assert!(matches("te?t", "test"));
+        assert!(matches("te?t", "teét"));
+        assert!(!matches("te?t", "tet"));
+        assert!(!matches("te?t", "toast"));
+        assert!(matches("sea*", "sea"));
+        assert!(matches("sea*", "search"));
+        assert!(!matches("sea*", "bsea"));
+        assert!(matches("*ch", "search"));
+        assert!(matches("s**r?h", "search"));
+        assert!(matches("*", ""));
+        assert!(matches("*é*", "café crème"));
+        assert!(!matches("*é*", "cafe"));
+        assert!(matches("北?", "北京"));
+        assert!(!matches("北?", "北京大"));
+    }
+}
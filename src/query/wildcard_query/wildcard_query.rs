@@ -0,0 +1,222 @@
+use schema::Field;
+use schema::Term;
+use query::Query;
+use query::Weight;
+use query::BooleanQuery;
+use query::QueryCost;
+use query::term_expansion::{self, DEFAULT_MAX_EXPANSIONS};
+use core::searcher::Searcher;
+use super::WildcardAutomaton;
+use std::any::Any;
+use std::collections::BTreeSet;
+use fst::IntoStreamer;
+use Result;
+
+/// Returns the literal prefix of a pure prefix pattern,
+/// e.g. `rust` for `rust*`, or `None` if the pattern
+/// has wildcards anywhere else than at its end.
+fn literal_prefix(pattern: &str) -> Option<&str> {
+    let prefix = pattern.trim_right_matches('*');
+    if prefix.len() < pattern.len() && !prefix.contains(|c| c == '*' || c == '?') {
+        Some(prefix)
+    } else {
+        None
+    }
+}
+
+/// `WildcardQuery` matches the documents containing a term
+/// matching a wildcard pattern.
+///
+/// In the pattern, `?` matches any single character, and `*`
+/// matches any sequence of characters, including the empty one.
+/// For instance, `te?t` matches `test` and `text`, and `sea*`
+/// matches `sea`, `search` and `seal`.
+///
+/// The pattern is translated into an automaton, which is run
+/// over the term dictionary of each segment. A pure prefix
+/// pattern such as `sea*` skips the automaton, and simply scans
+/// the range of the dictionary starting with the prefix. The number of expansions
+/// is capped by `max_expansions`, (50 by default) and only the
+/// first terms in lexicographic order are kept. A warning is
+/// logged when some of the matching terms are left out.
+///
+/// A pattern starting with a wildcard cannot make use of
+/// the term dictionary and requires to go through all of
+/// the terms of the field.
+#[derive(Debug)]
+pub struct WildcardQuery {
+    field: Field,
+    pattern: String,
+    max_expansions: usize,
+}
+
+impl WildcardQuery {
+
+    /// Creates a new `WildcardQuery`.
+    pub fn new(field: Field, pattern: &str) -> WildcardQuery {
+        WildcardQuery {
+            field: field,
+            pattern: String::from(pattern),
+            max_expansions: DEFAULT_MAX_EXPANSIONS,
+        }
+    }
+
+    /// Sets the maximum number of terms the pattern
+    /// may be expanded into.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max_expansions` is 0 or greater than 64.
+    pub fn set_max_expansions(mut self, max_expansions: usize) -> WildcardQuery {
+        term_expansion::check_max_expansions(max_expansions);
+        self.max_expansions = max_expansions;
+        self
+    }
+
+    /// Returns the field targeted by the query.
+    pub fn field(&self,) -> Field {
+        self.field
+    }
+
+    /// Returns the wildcard pattern.
+    pub fn pattern(&self,) -> &str {
+        &self.pattern
+    }
+
+    /// Returns the terms of the index matching the pattern,
+    /// in lexicographic order, and whether some of them were left out.
+    fn expand(&self, searcher: &Searcher) -> (Vec<Term>, bool) {
+        let mut expanded_terms: BTreeSet<Term> = BTreeSet::new();
+        let mut is_truncated = false;
+        match literal_prefix(&self.pattern) {
+            Some(prefix) => {
+                let prefix_term = Term::from_field_text(self.field, prefix);
+                let prefix_bytes = prefix_term.as_slice();
+                for segment_reader in searcher.segment_readers() {
+                    let stream = segment_reader
+                        .term_infos()
+                        .range()
+                        .ge(prefix_bytes)
+                        .into_stream();
+                    is_truncated |= term_expansion::collect_terms(stream,
+                                                                  |term_bytes| term_bytes.starts_with(prefix_bytes),
+                                                                  self.max_expansions,
+                                                                  &mut expanded_terms);
+                }
+            }
+            None => {
+                let field_prefix = Term::from_field_text(self.field, "");
+                let automaton = WildcardAutomaton::new(field_prefix.as_slice(), &self.pattern);
+                for segment_reader in searcher.segment_readers() {
+                    let stream = segment_reader
+                        .term_infos()
+                        .search(&automaton)
+                        .into_stream();
+                    is_truncated |= term_expansion::collect_terms(stream,
+                                                                  |_| true,
+                                                                  self.max_expansions,
+                                                                  &mut expanded_terms);
+                }
+            }
+        }
+        term_expansion::first_terms(expanded_terms, is_truncated, self.max_expansions)
+    }
+}
+
+impl Query for WildcardQuery {
+
+    /// Used to make it possible to cast Box<Query>
+    /// into a specific type. This is mostly useful for unit tests.
+    fn as_any(&self) -> &Any {
+        self
+    }
+
+    /// Create the weight associated to a query.
+    ///
+    /// The pattern is expanded into the matching terms of the index,
+    /// which are then searched as a disjunction.
+    ///
+    /// See [Weight](./trait.Weight.html).
+    fn weight(&self, searcher: &Searcher) -> Result<Box<Weight>> {
+        let (expanded_terms, is_truncated) = self.expand(searcher);
+        if is_truncated {
+            term_expansion::warn_truncated(self, self.max_expansions);
+        }
+        BooleanQuery::new_multiterms_query(expanded_terms).weight(searcher)
+    }
+
+    fn estimate_cost(&self, searcher: &Searcher) -> QueryCost {
+        let (expanded_terms, _) = self.expand(searcher);
+        term_expansion::union_cost(searcher, expanded_terms.iter())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::{literal_prefix, WildcardQuery};
+    use core::Index;
+    use schema::{Document, FieldValue, SchemaBuilder, STRING};
+    use collector::tests::TestCollector;
+
+    #[test]
+    fn test_literal_prefix() {
+        assert_eq!(literal_prefix("rust*"), Some("rust"));
+        assert_eq!(literal_prefix("rust**"), Some("rust"));
+        assert_eq!(literal_prefix("*"), Some(""));
+        assert_eq!(literal_prefix("rust"), None);
+        assert_eq!(literal_prefix("*.log"), None);
+        assert_eq!(literal_prefix("r?st*"), None);
+        assert_eq!(literal_prefix("r*st*"), None);
+    }
+
+    #[test]
+    fn test_wildcard_query_expand() {
+        let mut schema_builder = SchemaBuilder::default();
+        let file_field = schema_builder.add_text_field("file", STRING);
+        let empty_field = schema_builder.add_text_field("empty", STRING);
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            for file in &["rust", "rusty", "rust.log", "rest"] {
+                index_writer.add_document(doc!(file_field=>*file));
+            }
+            index_writer.commit().unwrap();
+            for file in &["roast", "rst", "app.log", "log", "error.log", "rust"] {
+                index_writer.add_document(doc!(file_field=>*file));
+            }
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        assert_eq!(searcher.num_segments(), 2);
+        let expand = |pattern: &str| -> Vec<String> {
+            WildcardQuery::new(file_field, pattern)
+                .expand(&*searcher)
+                .0
+                .into_iter()
+                .map(|term| unsafe { term.text().to_string() })
+                .collect()
+        };
+        assert_eq!(expand("rust*"), vec!("rust", "rust.log", "rusty"));
+        assert_eq!(expand("*.log"), vec!("app.log", "error.log", "rust.log"));
+        assert_eq!(expand("r?st"), vec!("rest", "rust"));
+        assert_eq!(expand("*"), vec!("app.log", "error.log", "log", "rest", "roast", "rst", "rust", "rust.log", "rusty"));
+        assert!(expand("x*").is_empty());
+        let (terms, is_truncated) = WildcardQuery::new(file_field, "r*")
+            .set_max_expansions(2)
+            .expand(&*searcher);
+        assert_eq!(terms.len(), 2);
+        assert!(is_truncated);
+        assert!(!WildcardQuery::new(file_field, "rust*").expand(&*searcher).1);
+        let search = |field, pattern: &str| {
+            let mut test_collector = TestCollector::default();
+            searcher.search(&WildcardQuery::new(field, pattern), &mut test_collector).unwrap();
+            test_collector.docs()
+        };
+        assert_eq!(search(file_field, "rust*"), vec!(0, 1, 2, 9));
+        assert_eq!(search(file_field, "*"), (0..10).collect::<Vec<u32>>());
+        assert!(search(empty_field, "*").is_empty());
+    }
+}
@@ -0,0 +1,79 @@
+use std::ops::BitOr;
+
+/// Define how a date field should be handled by tantivy.
+#[derive(Clone,Debug,PartialEq,Eq, RustcDecodable, RustcEncodable)]
+pub struct DateOptions {
+    indexed: bool,
+    stored: bool,
+}
+
+impl DateOptions {
+
+    /// Returns true iff the value is stored.
+    pub fn is_stored(&self,) -> bool {
+        self.stored
+    }
+
+    /// Returns true iff the value is indexed.
+    pub fn is_indexed(&self,) -> bool {
+        self.indexed
+    }
+
+    /// Set the date options as stored.
+    ///
+    /// Stored dates are returned by `searcher.doc(doc_address)`,
+    /// and serialized in RFC 3339.
+    pub fn set_stored(mut self,) -> DateOptions {
+        self.stored = true;
+        self
+    }
+
+    /// Set the date options as indexed.
+    ///
+    /// Setting a date as indexed will generate a posting
+    /// list for each value taken by the date, making it possible
+    /// to search for a date, or a range of dates.
+    pub fn set_indexed(mut self,) -> DateOptions {
+        self.indexed = true;
+        self
+    }
+}
+
+impl Default for DateOptions {
+    fn default() -> DateOptions {
+        DateOptions {
+            indexed: false,
+            stored: false,
+        }
+    }
+}
+
+
+/// Shortcut for an indexed date field.
+///
+/// Such a shortcut can be composed as follows `DATE_INDEXED | DATE_STORED`
+pub const DATE_INDEXED: DateOptions = DateOptions {
+    indexed: true,
+    stored: false,
+};
+
+/// Shortcut for a stored date field.
+///
+/// Such a shortcut can be composed as follows `DATE_INDEXED | DATE_STORED`
+pub const DATE_STORED: DateOptions = DateOptions {
+    indexed: false,
+    stored: true,
+};
+
+
+impl BitOr for DateOptions {
+
+    type Output = DateOptions;
+
+    fn bitor(self, other: DateOptions) -> DateOptions {
+        let mut res = DateOptions::default();
+        res.indexed = self.indexed | other.indexed;
+        res.stored = self.stored | other.stored;
+        res
+    }
+}
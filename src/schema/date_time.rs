@@ -0,0 +1,267 @@
+use std::fmt;
+use time::Timespec;
+
+const MILLIS_PER_SECOND: i64 = 1_000;
+const SECONDS_PER_DAY: i64 = 86_400;
+
+/// Returns the number of days between 1970-01-01
+/// and the given date of the proleptic gregorian calendar.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = if year >= 0 { year } else { year - 399 } / 400;
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Inverse of `days_from_civil`. Returns the `(year, month, day)`
+/// of the date, a given number of days after 1970-01-01.
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719_468;
+    let era = if days >= 0 { days } else { days - 146_096 } / 146_097;
+    let day_of_era = days - era * 146_097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+fn is_leap_year(year: i64) -> bool {
+    (year % 4 == 0 && year % 100 != 0) || year % 400 == 0
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 => if is_leap_year(year) { 29 } else { 28 },
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Reads the decimal number made of exactly
+/// `num_digits` digits at the beginning of `bytes`.
+fn read_number(bytes: &[u8], num_digits: usize) -> Option<i64> {
+    if bytes.len() < num_digits {
+        return None;
+    }
+    let mut number = 0i64;
+    for &byte in &bytes[..num_digits] {
+        if byte < b'0' || byte > b'9' {
+            return None;
+        }
+        number = number * 10 + (byte - b'0') as i64;
+    }
+    Some(number)
+}
+
+/// A point in time, with a millisecond precision.
+///
+/// Internally, a `DateTime` is the number of milliseconds
+/// elapsed since 1970-01-01T00:00:00Z. It is parsed from
+/// and formatted to RFC 3339 (e.g. `2017-03-12T09:30:00.250Z`),
+/// the textual representation used in the query parser and
+/// in the JSON documents.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, RustcEncodable, RustcDecodable)]
+pub struct DateTime(i64);
+
+impl DateTime {
+
+    /// Creates a `DateTime` from a number of milliseconds
+    /// since 1970-01-01T00:00:00Z.
+    pub fn from_timestamp_millis(timestamp_millis: i64) -> DateTime {
+        DateTime(timestamp_millis)
+    }
+
+    /// Returns the number of milliseconds
+    /// since 1970-01-01T00:00:00Z.
+    pub fn timestamp_millis(&self,) -> i64 {
+        self.0
+    }
+
+    /// Parses an RFC 3339 datetime, such as
+    /// `2017-03-12T09:30:00Z` or `2017-03-12T11:30:00.250+02:00`.
+    ///
+    /// The datetime is normalized to UTC, and its fraction
+    /// of a second is truncated to the millisecond.
+    ///
+    /// Returns `None` if the text is not a valid RFC 3339 datetime.
+    pub fn from_rfc3339(text: &str) -> Option<DateTime> {
+        let bytes = text.as_bytes();
+        // YYYY-MM-DDTHH:MM:SS
+        if bytes.len() < 20 ||
+            bytes[4] != b'-' || bytes[7] != b'-' ||
+            (bytes[10] != b'T' && bytes[10] != b't' && bytes[10] != b' ') ||
+            bytes[13] != b':' || bytes[16] != b':' {
+            return None;
+        }
+        let numbers: Option<Vec<i64>> = [(0, 4), (5, 2), (8, 2), (11, 2), (14, 2), (17, 2)]
+            .iter()
+            .map(|&(start, num_digits)| read_number(&bytes[start..], num_digits))
+            .collect();
+        let numbers = match numbers {
+            Some(numbers) => numbers,
+            None => {
+                return None;
+            }
+        };
+        let (year, month, day) = (numbers[0], numbers[1], numbers[2]);
+        let (hour, minute, second) = (numbers[3], numbers[4], numbers[5]);
+        if month < 1 || month > 12 || day < 1 || day > days_in_month(year, month) ||
+            hour > 23 || minute > 59 || second > 60 {
+            return None;
+        }
+        let mut offset = 19;
+        let mut millis = 0i64;
+        if bytes[offset] == b'.' {
+            offset += 1;
+            let num_digits = bytes[offset..]
+                .iter()
+                .take_while(|&&byte| byte >= b'0' && byte <= b'9')
+                .count();
+            if num_digits == 0 {
+                return None;
+            }
+            for i in 0..3 {
+                millis *= 10;
+                if i < num_digits {
+                    millis += (bytes[offset + i] - b'0') as i64;
+                }
+            }
+            offset += num_digits;
+        }
+        let zone = &bytes[offset..];
+        let offset_seconds =
+            if zone == b"Z" || zone == b"z" {
+                0
+            }
+            else {
+                // +HH:MM or -HH:MM
+                if zone.len() != 6 || zone[3] != b':' {
+                    return None;
+                }
+                let sign = match zone[0] {
+                    b'+' => 1,
+                    b'-' => -1,
+                    _ => {
+                        return None;
+                    }
+                };
+                match (read_number(&zone[1..], 2), read_number(&zone[4..], 2)) {
+                    (Some(offset_hours), Some(offset_minutes)) if offset_hours <= 23 && offset_minutes <= 59 => {
+                        sign * (offset_hours * 3600 + offset_minutes * 60)
+                    }
+                    _ => {
+                        return None;
+                    }
+                }
+            };
+        let seconds = days_from_civil(year, month, day) * SECONDS_PER_DAY +
+            hour * 3600 + minute * 60 + second - offset_seconds;
+        Some(DateTime(seconds * MILLIS_PER_SECOND + millis))
+    }
+
+    /// Formats the datetime in RFC 3339, in UTC.
+    ///
+    /// The milliseconds are only written if they are not 0,
+    /// e.g. `2017-03-12T09:30:00Z` or `2017-03-12T09:30:00.250Z`.
+    pub fn to_rfc3339(&self,) -> String {
+        let seconds = div_floor(self.0, MILLIS_PER_SECOND);
+        let millis = self.0 - seconds * MILLIS_PER_SECOND;
+        let days = div_floor(seconds, SECONDS_PER_DAY);
+        let seconds_of_day = seconds - days * SECONDS_PER_DAY;
+        let (year, month, day) = civil_from_days(days);
+        let mut text = format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+                               year,
+                               month,
+                               day,
+                               seconds_of_day / 3600,
+                               (seconds_of_day / 60) % 60,
+                               seconds_of_day % 60);
+        if millis != 0 {
+            text.push_str(&format!(".{:03}", millis));
+        }
+        text.push('Z');
+        text
+    }
+}
+
+fn div_floor(numerator: i64, denominator: i64) -> i64 {
+    let quotient = numerator / denominator;
+    if numerator % denominator < 0 { quotient - 1 } else { quotient }
+}
+
+impl From<Timespec> for DateTime {
+    fn from(timespec: Timespec) -> DateTime {
+        DateTime(timespec.sec * MILLIS_PER_SECOND + (timespec.nsec / 1_000_000) as i64)
+    }
+}
+
+impl From<DateTime> for Timespec {
+    fn from(date_time: DateTime) -> Timespec {
+        let seconds = div_floor(date_time.0, MILLIS_PER_SECOND);
+        let millis = date_time.0 - seconds * MILLIS_PER_SECOND;
+        Timespec::new(seconds, (millis * 1_000_000) as i32)
+    }
+}
+
+impl fmt::Display for DateTime {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.to_rfc3339())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::DateTime;
+    use time::Timespec;
+
+    #[test]
+    fn test_date_time_rfc3339() {
+        let date_time = DateTime::from_rfc3339("2017-03-12T09:30:00Z").unwrap();
+        assert_eq!(date_time.timestamp_millis(), 1_489_311_000_000);
+        assert_eq!(date_time.to_rfc3339(), "2017-03-12T09:30:00Z");
+        assert_eq!(DateTime::from_timestamp_millis(0).to_rfc3339(), "1970-01-01T00:00:00Z");
+        assert_eq!(DateTime::from_timestamp_millis(-1).to_rfc3339(), "1969-12-31T23:59:59.999Z");
+        assert_eq!(DateTime::from_rfc3339("1969-12-31T23:59:59.999Z").unwrap().timestamp_millis(), -1);
+        assert_eq!(DateTime::from_rfc3339("2000-02-29T12:00:00Z").unwrap().to_rfc3339(), "2000-02-29T12:00:00Z");
+        // the fraction is truncated to the millisecond.
+        let date_time = DateTime::from_rfc3339("2017-03-12T09:30:00.2509Z").unwrap();
+        assert_eq!(date_time.timestamp_millis(), 1_489_311_000_250);
+        assert_eq!(date_time.to_rfc3339(), "2017-03-12T09:30:00.250Z");
+        assert_eq!(DateTime::from_rfc3339("2017-03-12T09:30:00.5Z").unwrap().to_rfc3339(), "2017-03-12T09:30:00.500Z");
+    }
+
+    #[test]
+    fn test_date_time_offset_normalized_to_utc() {
+        let date_time = DateTime::from_rfc3339("2017-03-12T11:30:00.250+02:00").unwrap();
+        assert_eq!(date_time, DateTime::from_rfc3339("2017-03-12T09:30:00.250Z").unwrap());
+        assert_eq!(date_time.to_rfc3339(), "2017-03-12T09:30:00.250Z");
+        let date_time = DateTime::from_rfc3339("2016-12-31T22:00:00-03:30").unwrap();
+        assert_eq!(date_time.to_rfc3339(), "2017-01-01T01:30:00Z");
+    }
+
+    #[test]
+    fn test_date_time_invalid() {
+        assert!(DateTime::from_rfc3339("2017-03-12").is_none());
+        assert!(DateTime::from_rfc3339("2017-03-12T09:30:00").is_none());
+        assert!(DateTime::from_rfc3339("2017-02-29T09:30:00Z").is_none());
+        assert!(DateTime::from_rfc3339("2017-13-12T09:30:00Z").is_none());
+        assert!(DateTime::from_rfc3339("2017-03-12T24:30:00Z").is_none());
+        assert!(DateTime::from_rfc3339("2017-03-12T09:30:00.Z").is_none());
+        assert!(DateTime::from_rfc3339("2017-03-12T09:30:00+0200").is_none());
+        assert!(DateTime::from_rfc3339("2017-03-12T09:30:00Zx").is_none());
+    }
+
+    #[test]
+    fn test_date_time_timespec() {
+        let date_time = DateTime::from(Timespec::new(-2, 500_000_000));
+        assert_eq!(date_time.timestamp_millis(), -1_500);
+        assert_eq!(Timespec::from(date_time), Timespec::new(-2, 500_000_000));
+    }
+}
@@ -1,5 +1,6 @@
 use super::*;
 use itertools::Itertools;    
+use rustc_serialize::json::{self, Json};
 
 /// Tantivy's Document is the object that can
 /// be indexed and then searched for.  
@@ -14,6 +15,8 @@ use itertools::Itertools;
 #[derive(Debug, RustcEncodable, RustcDecodable, Default)]
 pub struct Document {
     field_values: Vec<FieldValue>,
+    // (field, term text, payload)
+    payloads: Vec<(Field, String, u32)>,
 }
 
 impl PartialEq for Document {
@@ -46,6 +49,13 @@ impl Document {
         self.field_values.is_empty()
     }
     
+    /// Removes all of the fields and payloads of the document,
+    /// keeping its allocated memory, so that it can be reused.
+    pub fn clear(&mut self) {
+        self.field_values.clear();
+        self.payloads.clear();
+    }
+
     /// Add a text field.
     pub fn add_text(&mut self, field: Field, text: &str) {
         let value = Value::Str(String::from(text)); 
@@ -57,6 +67,44 @@ impl Document {
         self.add(FieldValue::new(field, Value::U32(value)));
     }
 
+    /// Add a date field.
+    pub fn add_date(&mut self, field: Field, date_time: DateTime) {
+        self.add(FieldValue::new(field, Value::Date(date_time)));
+    }
+
+    /// Add a JSON field.
+    pub fn add_json(&mut self, field: Field, json_object: json::Object) {
+        self.add(FieldValue::new(field, Value::Json(Json::Object(json_object).to_string())));
+    }
+
+    /// Add a geo-point, given its latitude and longitude in degrees.
+    ///
+    /// # Panics
+    /// Panics if the coordinates are out of range.
+    pub fn add_geo_point(&mut self, geo_field: GeoField, lat: f64, lon: f64) {
+        self.add_u32(geo_field.lat_field(), GeoField::encode_lat(lat));
+        self.add_u32(geo_field.lon_field(), GeoField::encode_lon(lon));
+    }
+
+    /// Sets the payload of a term of the document.
+    ///
+    /// The payload is written in the postings of the term, and is
+    /// available at search time via `Postings::payload`.
+    /// The field must have payloads enabled (see `SchemaBuilder::enable_payloads`),
+    /// and `term_text` must be one of the terms of the field in the document,
+    /// as it is indexed (i.e. lowercased for tokenized fields).
+    /// Otherwise the payload is ignored.
+    ///
+    /// Payloads are not stored, and they are ignored when comparing documents.
+    pub fn set_payload(&mut self, field: Field, term_text: &str, payload: u32) {
+        self.payloads.push((field, String::from(term_text), payload));
+    }
+
+    /// Returns the payloads of the document, as `(field, term text, payload)`.
+    pub fn payloads(&self) -> &[(Field, String, u32)] {
+        &self.payloads
+    }
+
     /// Add a field value
     pub fn add(&mut self, field_value: FieldValue) {
         self.field_values.push(field_value);
@@ -106,7 +154,8 @@ impl Document {
 impl From<Vec<FieldValue>> for Document {
     fn from(field_values: Vec<FieldValue>) -> Document {
         Document {
-            field_values: field_values
+            field_values: field_values,
+            payloads: Vec::new(),
         }
     }
 }
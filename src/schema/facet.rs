@@ -0,0 +1,107 @@
+use std::fmt;
+
+/// Separator of the segments of the path of a facet.
+pub const FACET_SEP: char = '/';
+
+/// A hierarchical facet, such as `/electronics/phones`.
+///
+/// A document belongs to a facet if it belongs to the facet
+/// itself or to one of its descendants: a document with the facet
+/// `/electronics/phones` belongs to `/electronics`, and to the root facet `/`.
+///
+/// Facets are indexed in an untokenized text field (e.g. `STRING`),
+/// under their encoded form, see `Facet::encoded_str`.
+/// As terms are sorted, the facets of a subtree are contiguous
+/// in the term dictionary.
+///
+/// See `FacetCollector` and `FacetDrillDown`.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Facet(String);
+
+impl Facet {
+
+    /// Returns the root facet `/`, to which all documents belong.
+    pub fn root() -> Facet {
+        Facet(String::new())
+    }
+
+    /// Creates a facet from the segments of its path.
+    ///
+    /// # Panics
+    /// Panics if a segment is empty or contains a `/`.
+    pub fn from_path(segments: &[&str]) -> Facet {
+        let mut encoded = String::new();
+        for segment in segments {
+            assert!(!segment.is_empty() && !segment.contains(FACET_SEP),
+                    "Invalid facet segment {:?}", segment);
+            encoded.push(FACET_SEP);
+            encoded.push_str(segment);
+        }
+        Facet(encoded)
+    }
+
+    /// Creates a facet from its textual representation,
+    /// e.g. `/electronics/phones`.
+    ///
+    /// Empty segments are ignored, so that `/electronics/`
+    /// and `electronics` are equivalent to `/electronics`.
+    pub fn from_text(text: &str) -> Facet {
+        let segments: Vec<&str> = text
+            .split(FACET_SEP)
+            .filter(|segment| !segment.is_empty())
+            .collect();
+        Facet::from_path(&segments)
+    }
+
+    /// Returns the encoded form of the facet, as it is indexed.
+    ///
+    /// The root facet is encoded as an empty string.
+    pub fn encoded_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Returns true iff the facet is the root facet.
+    pub fn is_root(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Returns true iff `other` is this facet or one of its descendants.
+    pub fn is_prefix_of(&self, other: &Facet) -> bool {
+        other.0.starts_with(&self.0) &&
+            other.0[self.0.len()..].chars().next().map(|c| c == FACET_SEP).unwrap_or(true)
+    }
+}
+
+impl fmt::Display for Facet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_root() {
+            write!(f, "{}", FACET_SEP)
+        } else {
+            write!(f, "{}", self.0)
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_facet() {
+        let phones = Facet::from_text("/electronics/phones");
+        assert_eq!(phones, Facet::from_path(&["electronics", "phones"]));
+        assert_eq!(phones, Facet::from_text("electronics/phones/"));
+        assert_eq!(phones.encoded_str(), "/electronics/phones");
+        assert_eq!(format!("{}", phones), "/electronics/phones");
+        assert_eq!(format!("{}", Facet::root()), "/");
+        assert_eq!(Facet::from_text("/"), Facet::root());
+        let electronics = Facet::from_text("/electronics");
+        assert!(electronics.is_prefix_of(&phones));
+        assert!(electronics.is_prefix_of(&electronics));
+        assert!(Facet::root().is_prefix_of(&electronics));
+        assert!(!phones.is_prefix_of(&electronics));
+        assert!(!Facet::from_text("/elec").is_prefix_of(&electronics));
+    }
+}
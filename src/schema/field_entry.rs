@@ -1,11 +1,16 @@
 use schema::TextOptions;
 use schema::U32Options;
+use schema::DateOptions;
+use schema::JsonOptions;
+use schema::Similarity;
+use schema::MaxTermLength;
 
 use rustc_serialize::Decodable;
 use rustc_serialize::Decoder;
 use rustc_serialize::Encodable;
 use rustc_serialize::Encoder;
 use schema::FieldType;
+use analyzer::DEFAULT_TOKENIZER;
 
 /// A `FieldEntry` represents a field and its configuration.
 /// `Schema` are a collection of `FieldEntry`
@@ -18,6 +23,13 @@ use schema::FieldType;
 pub struct FieldEntry {
     name: String,
     field_type: FieldType,
+    stored_rank: Option<usize>,
+    similarity: Option<Similarity>,
+    max_term_length: Option<MaxTermLength>,
+    payloads: bool,
+    tokenizer: Option<String>,
+    index_sort: bool,
+    multivalued: bool,
 }
 
 impl FieldEntry {
@@ -28,6 +40,13 @@ impl FieldEntry {
         FieldEntry {
             name: field_name,
             field_type: FieldType::Str(field_type),
+            stored_rank: None,
+            similarity: None,
+            max_term_length: None,
+            payloads: false,
+            tokenizer: None,
+            index_sort: false,
+            multivalued: false,
         }
     }
     
@@ -37,9 +56,48 @@ impl FieldEntry {
         FieldEntry {
             name: field_name,
             field_type: FieldType::U32(field_type),
+            stored_rank: None,
+            similarity: None,
+            max_term_length: None,
+            payloads: false,
+            tokenizer: None,
+            index_sort: false,
+            multivalued: false,
         }
     }
     
+    /// Creates a new date field entry in the schema, given
+    /// a name, and some options.
+    pub fn new_date(field_name: String, field_type: DateOptions) -> FieldEntry {
+        FieldEntry {
+            name: field_name,
+            field_type: FieldType::Date(field_type),
+            stored_rank: None,
+            similarity: None,
+            max_term_length: None,
+            payloads: false,
+            tokenizer: None,
+            index_sort: false,
+            multivalued: false,
+        }
+    }
+
+    /// Creates a new JSON field entry in the schema, given
+    /// a name, and some options.
+    pub fn new_json(field_name: String, field_type: JsonOptions) -> FieldEntry {
+        FieldEntry {
+            name: field_name,
+            field_type: FieldType::Json(field_type),
+            stored_rank: None,
+            similarity: None,
+            max_term_length: None,
+            payloads: false,
+            tokenizer: None,
+            index_sort: false,
+            multivalued: false,
+        }
+    }
+
     /// Returns the name of the field
     pub fn name(&self,) -> &String {
         &self.name
@@ -50,18 +108,134 @@ impl FieldEntry {
         &self.field_type
     }
     
+    /// Returns the position of the field in the
+    /// schema's stored-field order, if any.
+    ///
+    /// See `SchemaBuilder::set_stored_fields_order`.
+    pub fn stored_rank(&self,) -> Option<usize> {
+        self.stored_rank
+    }
+
+    #[doc(hidden)]
+    pub fn set_stored_rank(&mut self, stored_rank: Option<usize>) {
+        self.stored_rank = stored_rank;
+    }
+
+    /// Returns the similarity used to score the terms of the field.
+    ///
+    /// See `SchemaBuilder::set_similarity`.
+    pub fn similarity(&self,) -> Similarity {
+        self.similarity.unwrap_or_default()
+    }
+
+    #[doc(hidden)]
+    pub fn set_similarity(&mut self, similarity: Option<Similarity>) {
+        self.similarity = similarity;
+    }
+
+    /// Returns the maximum length of the terms of the field.
+    ///
+    /// See `SchemaBuilder::set_max_term_length`.
+    pub fn max_term_length(&self,) -> MaxTermLength {
+        self.max_term_length.unwrap_or_default()
+    }
+
+    #[doc(hidden)]
+    pub fn set_max_term_length(&mut self, max_term_length: Option<MaxTermLength>) {
+        self.max_term_length = max_term_length;
+    }
+
+    /// Returns true iff the postings of the field
+    /// store a payload for each document.
+    ///
+    /// See `SchemaBuilder::enable_payloads`.
+    pub fn has_payloads(&self,) -> bool {
+        self.payloads
+    }
+
+    #[doc(hidden)]
+    pub fn set_payloads(&mut self, payloads: bool) {
+        self.payloads = payloads;
+    }
+
+    /// Returns the name of the tokenizer of the field.
+    ///
+    /// See `SchemaBuilder::set_tokenizer`.
+    pub fn tokenizer(&self,) -> &str {
+        self.tokenizer
+            .as_ref()
+            .map(|tokenizer| &tokenizer[..])
+            .unwrap_or(DEFAULT_TOKENIZER)
+    }
+
+    #[doc(hidden)]
+    pub fn set_tokenizer(&mut self, tokenizer: Option<String>) {
+        self.tokenizer = tokenizer;
+    }
+
+    /// Returns true iff the documents of the merged
+    /// segments are sorted by the value of this field.
+    ///
+    /// See `SchemaBuilder::set_index_sort`.
+    pub fn is_index_sort(&self,) -> bool {
+        self.index_sort
+    }
+
+    #[doc(hidden)]
+    pub fn set_index_sort(&mut self, index_sort: bool) {
+        self.index_sort = index_sort;
+    }
+
+    #[doc(hidden)]
+    pub fn set_multivalued(&mut self, multivalued: bool) {
+        self.multivalued = multivalued;
+    }
+
     /// Returns true iff the field is indexed
     pub fn is_indexed(&self,) -> bool {
         match self.field_type {
             FieldType::Str(ref options) => options.get_indexing_options().is_indexed(),
             FieldType::U32(ref options) => options.is_indexed(),
+            FieldType::Date(ref options) => options.is_indexed(),
+            FieldType::Json(ref options) => options.is_indexed(),
         }
     }
     
-    /// Returns true iff the field is a u32 fast field
+    /// Returns true iff the field norms of the field are recorded,
+    /// which is the case of the indexed fields, unless they are disabled
+    /// with `TextOptions::set_norms`.
+    pub fn has_norms(&self,) -> bool {
+        match self.field_type {
+            FieldType::Str(ref options) => options.get_indexing_options().is_indexed() && options.has_norms(),
+            _ => self.is_indexed(),
+        }
+    }
+
+    /// Returns true iff the term vectors of the field are recorded,
+    /// which requires an indexed text field with `TextOptions::set_term_vectors`.
+    pub fn has_term_vectors(&self,) -> bool {
+        match self.field_type {
+            FieldType::Str(ref options) => options.get_indexing_options().is_indexed() && options.has_term_vectors(),
+            _ => false,
+        }
+    }
+
+    /// Returns true iff the field is a u32 fast field,
+    /// holding one value per document.
     pub fn is_u32_fast(&self,) -> bool {
         match self.field_type {
-            FieldType::U32(ref options) => options.is_fast(),
+            FieldType::U32(ref options) => options.is_fast() && !self.multivalued,
+            _ => false,
+        }
+    }
+
+    /// Returns true iff the field is a u32 fast field
+    /// holding any number of values per document.
+    ///
+    /// See `SchemaBuilder::set_multivalued`.
+    pub fn is_u32_multivalued_fast(&self,) -> bool {
+        match self.field_type {
+            FieldType::U32(ref options) => options.is_fast() && self.multivalued,
             _ => false,
         }
     }
@@ -75,6 +249,12 @@ impl FieldEntry {
             FieldType::Str(ref options) => {
                 options.is_stored()
             }
+            FieldType::Date(ref options) => {
+                options.is_stored()
+            }
+            FieldType::Json(ref options) => {
+                options.is_stored()
+            }
         }
     }
 }
@@ -83,7 +263,15 @@ impl FieldEntry {
 
 impl Encodable for FieldEntry {
     fn encode<S: Encoder>(&self, s: &mut S) -> Result<(), S::Error> {
-        s.emit_struct("field_entry", 3, |s| {
+        let num_struct_fields = 3 +
+            if self.stored_rank.is_some() { 1 } else { 0 } +
+            if self.similarity.is_some() { 1 } else { 0 } +
+            if self.max_term_length.is_some() { 1 } else { 0 } +
+            if self.payloads { 1 } else { 0 } +
+            if self.tokenizer.is_some() { 1 } else { 0 } +
+            if self.index_sort { 1 } else { 0 } +
+            if self.multivalued { 1 } else { 0 };
+        s.emit_struct("field_entry", num_struct_fields, |s| {
             try!(s.emit_struct_field("name", 0, |s| {
                 self.name.encode(s)
             }));
@@ -104,8 +292,58 @@ impl Encodable for FieldEntry {
                         options.encode(s)
                     }));
                 }
+                FieldType::Date(ref options) => {
+                    try!(s.emit_struct_field("type", 1, |s| {
+                        s.emit_str("date")
+                    }));
+                    try!(s.emit_struct_field("options", 2, |s| {
+                        options.encode(s)
+                    }));
+                }
+                FieldType::Json(ref options) => {
+                    try!(s.emit_struct_field("type", 1, |s| {
+                        s.emit_str("json")
+                    }));
+                    try!(s.emit_struct_field("options", 2, |s| {
+                        options.encode(s)
+                    }));
+                }
+            }
+            if let Some(stored_rank) = self.stored_rank {
+                try!(s.emit_struct_field("stored_rank", 3, |s| {
+                    s.emit_usize(stored_rank)
+                }));
+            }
+            if let Some(similarity) = self.similarity {
+                try!(s.emit_struct_field("similarity", 4, |s| {
+                    similarity.encode(s)
+                }));
+            }
+            if let Some(max_term_length) = self.max_term_length {
+                try!(s.emit_struct_field("max_term_length", 5, |s| {
+                    max_term_length.encode(s)
+                }));
+            }
+            if self.payloads {
+                try!(s.emit_struct_field("payloads", 6, |s| {
+                    s.emit_bool(true)
+                }));
+            }
+            if let Some(ref tokenizer) = self.tokenizer {
+                try!(s.emit_struct_field("tokenizer", 7, |s| {
+                    s.emit_str(tokenizer)
+                }));
+            }
+            if self.index_sort {
+                try!(s.emit_struct_field("index_sort", 8, |s| {
+                    s.emit_bool(true)
+                }));
+            }
+            if self.multivalued {
+                try!(s.emit_struct_field("multivalued", 9, |s| {
+                    s.emit_bool(true)
+                }));
             }
-            
             Ok(())
         })
     }
@@ -120,7 +358,7 @@ impl Decodable for FieldEntry {
             let field_type: String = try!(d.read_struct_field("type", 1, |d| {
                 d.read_str()
             }));
-            d.read_struct_field("options", 2, |d| {
+            let mut field_entry = try!(d.read_struct_field("options", 2, |d| {
                 match field_type.as_ref() {
                     "u32" => {
                         let u32_options = try!(U32Options::decode(d));
@@ -130,11 +368,62 @@ impl Decodable for FieldEntry {
                         let text_options = try!(TextOptions::decode(d));
                         Ok(FieldEntry::new_text(name, text_options))
                     }
+                    "date" => {
+                        let date_options = try!(DateOptions::decode(d));
+                        Ok(FieldEntry::new_date(name, date_options))
+                    }
+                    "json" => {
+                        let json_options = try!(JsonOptions::decode(d));
+                        Ok(FieldEntry::new_json(name, json_options))
+                    }
                     _ => {
                         Err(d.error(&format!("Field type {:?} unknown", field_type)))
                     }
                 }
-            })
+            }));
+            let stored_rank = try!(d.read_struct_field("stored_rank", 3, |d| {
+                d.read_option(|d, is_some| {
+                    if is_some { d.read_usize().map(Some) } else { Ok(None) }
+                })
+            }));
+            field_entry.set_stored_rank(stored_rank);
+            let similarity = try!(d.read_struct_field("similarity", 4, |d| {
+                d.read_option(|d, is_some| {
+                    if is_some { Similarity::decode(d).map(Some) } else { Ok(None) }
+                })
+            }));
+            field_entry.set_similarity(similarity);
+            let max_term_length = try!(d.read_struct_field("max_term_length", 5, |d| {
+                d.read_option(|d, is_some| {
+                    if is_some { MaxTermLength::decode(d).map(Some) } else { Ok(None) }
+                })
+            }));
+            field_entry.set_max_term_length(max_term_length);
+            let payloads = try!(d.read_struct_field("payloads", 6, |d| {
+                d.read_option(|d, is_some| {
+                    if is_some { d.read_bool().map(Some) } else { Ok(None) }
+                })
+            }));
+            field_entry.set_payloads(payloads.unwrap_or(false));
+            let tokenizer = try!(d.read_struct_field("tokenizer", 7, |d| {
+                d.read_option(|d, is_some| {
+                    if is_some { d.read_str().map(Some) } else { Ok(None) }
+                })
+            }));
+            field_entry.set_tokenizer(tokenizer);
+            let index_sort = try!(d.read_struct_field("index_sort", 8, |d| {
+                d.read_option(|d, is_some| {
+                    if is_some { d.read_bool().map(Some) } else { Ok(None) }
+                })
+            }));
+            field_entry.set_index_sort(index_sort.unwrap_or(false));
+            let multivalued = try!(d.read_struct_field("multivalued", 9, |d| {
+                d.read_option(|d, is_some| {
+                    if is_some { d.read_bool().map(Some) } else { Ok(None) }
+                })
+            }));
+            field_entry.set_multivalued(multivalued.unwrap_or(false));
+            Ok(field_entry)
         })
     }
 }
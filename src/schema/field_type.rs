@@ -1,5 +1,8 @@
 use schema::TextOptions;
 use schema::U32Options;
+use schema::DateOptions;
+use schema::JsonOptions;
+use schema::DateTime;
 
 use rustc_serialize::json::Json;
 use schema::Value;
@@ -14,10 +17,12 @@ pub enum ValueParsingError {
     /// The json node is not of the correct type. (e.g. 3 for a `Str` type or `"abc"` for a u32 type)
     /// Tantivy will try to autocast values.  
     TypeError(String),
+    /// The string of a date field is not a valid RFC 3339 datetime.
+    DateParsingError(String),
 }
 
 
-/// A `FieldType` describes the type (text, u32) of a field as well as 
+/// A `FieldType` describes the type (text, u32, date, json) of a field as well as 
 /// how it should be handled by tantivy.
 #[derive(Clone, Debug, RustcDecodable, RustcEncodable)]
 pub enum FieldType {
@@ -25,6 +30,10 @@ pub enum FieldType {
     Str(TextOptions),
     /// U32 field type configuration
     U32(U32Options),
+    /// Date field type configuration
+    Date(DateOptions),
+    /// JSON field type configuration
+    Json(JsonOptions),
 }
 
 impl FieldType {
@@ -35,15 +44,26 @@ impl FieldType {
     /// For instance, If the json value is the integer `3` and the 
     /// target field is a `Str`, this method will return an Error. 
     pub fn value_from_json(&self, json: &Json) -> Result<Value, ValueParsingError> {
+        if let FieldType::Json(_) = *self {
+            return match *json {
+                Json::Object(_) => Ok(Value::Json(json.to_string())),
+                _ => Err(ValueParsingError::TypeError(format!("Expected a JSON object, got {:?}", json))),
+            };
+        }
         match *json {
             Json::String(ref field_text) => {
                 match *self {
                     FieldType::Str(_) => {
                         Ok(Value::Str(field_text.clone()))
                     }
-                    FieldType::U32(_) => {
+                    FieldType::U32(_) | FieldType::Json(_) => {
                         Err(ValueParsingError::TypeError(format!("Expected a u32 int, got {:?}", json)))
                     }
+                    FieldType::Date(_) => {
+                        DateTime::from_rfc3339(field_text)
+                            .map(Value::Date)
+                            .ok_or_else(|| ValueParsingError::DateParsingError(format!("Expected an RFC 3339 datetime, got {:?}", field_text)))
+                    }
                 }
             }
             Json::U64(ref field_val_u64) => {
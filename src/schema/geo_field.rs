@@ -0,0 +1,65 @@
+use schema::Field;
+
+
+/// Handle to a geo-point field.
+///
+/// A geo-point is stored as two u32 fast fields, one for the
+/// latitude and one for the longitude, named after the geo field
+/// with the suffixes `.lat` and `.lon`.
+///
+/// Coordinates are encoded as fixed-point values, with a
+/// resolution of about 2cm. The value `0` is reserved for
+/// documents without any geo-point.
+///
+/// See `SchemaBuilder::add_geo_field` and `Document::add_geo_point`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GeoField {
+    lat_field: Field,
+    lon_field: Field,
+}
+
+fn encode(val: f64, min_val: f64, max_val: f64) -> u32 {
+    assert!(val >= min_val && val <= max_val,
+            "Coordinate {} is not within [{}, {}].", val, min_val, max_val);
+    // values are kept on 31 bits, as fast fields do not support
+    // an amplitude of 32 bits.
+    let max_encoded = (i32::max_value() - 1i32) as f64;
+    1u32 + ((val - min_val) / (max_val - min_val) * max_encoded).round() as u32
+}
+
+impl GeoField {
+
+    #[doc(hidden)]
+    pub fn new(lat_field: Field, lon_field: Field) -> GeoField {
+        GeoField {
+            lat_field: lat_field,
+            lon_field: lon_field,
+        }
+    }
+
+    /// Returns the fast field holding the encoded latitudes.
+    pub fn lat_field(&self,) -> Field {
+        self.lat_field
+    }
+
+    /// Returns the fast field holding the encoded longitudes.
+    pub fn lon_field(&self,) -> Field {
+        self.lon_field
+    }
+
+    /// Encodes a latitude, in degrees.
+    ///
+    /// # Panics
+    /// Panics if the latitude is not within `[-90, 90]`.
+    pub fn encode_lat(lat: f64) -> u32 {
+        encode(lat, -90f64, 90f64)
+    }
+
+    /// Encodes a longitude, in degrees.
+    ///
+    /// # Panics
+    /// Panics if the longitude is not within `[-180, 180]`.
+    pub fn encode_lon(lon: f64) -> u32 {
+        encode(lon, -180f64, 180f64)
+    }
+}
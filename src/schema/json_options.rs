@@ -0,0 +1,107 @@
+use std::ops::BitOr;
+
+/// Define how a JSON field should be handled by tantivy.
+#[derive(Clone,Debug,PartialEq,Eq, RustcDecodable, RustcEncodable)]
+pub struct JsonOptions {
+    indexed: bool,
+    stored: bool,
+    fast: bool,
+}
+
+impl JsonOptions {
+
+    /// Returns true iff the value is stored.
+    pub fn is_stored(&self,) -> bool {
+        self.stored
+    }
+
+    /// Returns true iff the value is indexed.
+    pub fn is_indexed(&self,) -> bool {
+        self.indexed
+    }
+
+    /// Returns true iff the numeric leaves can be searched by range.
+    pub fn is_fast(&self,) -> bool {
+        self.fast
+    }
+
+    /// Set the JSON options as stored.
+    ///
+    /// Stored JSON objects are returned by `searcher.doc(doc_address)`.
+    pub fn set_stored(mut self,) -> JsonOptions {
+        self.stored = true;
+        self
+    }
+
+    /// Set the JSON options as indexed.
+    ///
+    /// Each leaf of the JSON objects is indexed under its path,
+    /// with a type inferred from its JSON value.
+    pub fn set_indexed(mut self,) -> JsonOptions {
+        self.indexed = true;
+        self
+    }
+
+    /// Set the JSON options as fast.
+    ///
+    /// The numeric leaves of the JSON objects are then
+    /// additionally indexed so as to be searchable by range,
+    /// whether they are integers or floats.
+    pub fn set_fast(mut self,) -> JsonOptions {
+        self.fast = true;
+        self
+    }
+}
+
+impl Default for JsonOptions {
+    fn default() -> JsonOptions {
+        JsonOptions {
+            indexed: false,
+            stored: false,
+            fast: false,
+        }
+    }
+}
+
+
+/// Shortcut for an indexed JSON field.
+///
+/// Such a shortcut can be composed as follows `JSON_INDEXED | JSON_STORED`
+pub const JSON_INDEXED: JsonOptions = JsonOptions {
+    indexed: true,
+    stored: false,
+    fast: false,
+};
+
+/// Shortcut for a stored JSON field.
+///
+/// Such a shortcut can be composed as follows `JSON_INDEXED | JSON_STORED`
+pub const JSON_STORED: JsonOptions = JsonOptions {
+    indexed: false,
+    stored: true,
+    fast: false,
+};
+
+/// Shortcut for a JSON field whose numeric leaves
+/// can be searched by range. It implies `JSON_INDEXED`.
+///
+/// Such a shortcut can be composed as follows `JSON_FAST | JSON_STORED`
+pub const JSON_FAST: JsonOptions = JsonOptions {
+    indexed: true,
+    stored: false,
+    fast: true,
+};
+
+
+impl BitOr for JsonOptions {
+
+    type Output = JsonOptions;
+
+    fn bitor(self, other: JsonOptions) -> JsonOptions {
+        let mut res = JsonOptions::default();
+        res.indexed = self.indexed | other.indexed;
+        res.stored = self.stored | other.stored;
+        res.fast = self.fast | other.fast;
+        res
+    }
+}
@@ -0,0 +1,101 @@
+use rustc_serialize::Decodable;
+use rustc_serialize::Decoder;
+use rustc_serialize::Encodable;
+use rustc_serialize::Encoder;
+
+
+/// Defines what happens to the tokens that are longer
+/// than the maximum term length of their field.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TermLengthPolicy {
+    /// The token is truncated to the maximum term length.
+    ///
+    /// Truncation happens on a char boundary, so that the
+    /// resulting term may be slightly shorter than the limit.
+    Truncate,
+    /// The token is not indexed.
+    Drop,
+}
+
+/// Maximum length, in bytes, of the terms of a text field,
+/// and what to do with the tokens exceeding it.
+///
+/// Extremely long tokens (base64 blobs for instance) bloat the
+/// term dictionary while being very unlikely to be searched for.
+///
+/// The maximum term length is defined per field, see
+/// `SchemaBuilder::set_max_term_length`.
+/// By default, tokens longer than 255 bytes are dropped.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MaxTermLength {
+    /// Maximum number of bytes of a term.
+    pub num_bytes: usize,
+    /// What to do with the longer tokens.
+    pub policy: TermLengthPolicy,
+}
+
+impl Default for MaxTermLength {
+    fn default() -> MaxTermLength {
+        MaxTermLength::new(255, TermLengthPolicy::Drop)
+    }
+}
+
+impl MaxTermLength {
+
+    /// Creates a new `MaxTermLength`.
+    pub fn new(num_bytes: usize, policy: TermLengthPolicy) -> MaxTermLength {
+        MaxTermLength {
+            num_bytes: num_bytes,
+            policy: policy,
+        }
+    }
+
+    /// Returns the text that should be indexed for `token`,
+    /// or `None` if the token should be dropped.
+    pub fn apply<'a>(&self, token: &'a str) -> Option<&'a str> {
+        if token.len() <= self.num_bytes {
+            return Some(token);
+        }
+        match self.policy {
+            TermLengthPolicy::Drop => None,
+            TermLengthPolicy::Truncate => {
+                let mut len = self.num_bytes;
+                while !token.is_char_boundary(len) {
+                    len -= 1;
+                }
+                Some(&token[..len])
+            }
+        }
+    }
+}
+
+impl Encodable for MaxTermLength {
+    fn encode<S: Encoder>(&self, s: &mut S) -> Result<(), S::Error> {
+        s.emit_struct("max_term_length", 2, |s| {
+            try!(s.emit_struct_field("num_bytes", 0, |s| s.emit_usize(self.num_bytes)));
+            s.emit_struct_field("policy", 1, |s| {
+                match self.policy {
+                    TermLengthPolicy::Truncate => s.emit_str("truncate"),
+                    TermLengthPolicy::Drop => s.emit_str("drop"),
+                }
+            })
+        })
+    }
+}
+
+impl Decodable for MaxTermLength {
+    fn decode<D: Decoder>(d: &mut D) -> Result<Self, D::Error> {
+        d.read_struct("max_term_length", 2, |d| {
+            let num_bytes = try!(d.read_struct_field("num_bytes", 0, |d| d.read_usize()));
+            let policy_name = try!(d.read_struct_field("policy", 1, |d| d.read_str()));
+            let policy = match policy_name.as_ref() {
+                "truncate" => TermLengthPolicy::Truncate,
+                "drop" => TermLengthPolicy::Drop,
+                _ => {
+                    return Err(d.error(&format!("Term length policy {:?} unknown", policy_name)));
+                }
+            };
+            Ok(MaxTermLength::new(num_bytes, policy))
+        })
+    }
+}
@@ -7,7 +7,7 @@ Tantivy has a very strict schema.
 The schema defines information about the fields your index contains, that is, for each field :
 
 * the field name (may only contain letters `[a-zA-Z]`, number `[0-9]`, and `_`)
-* the type of the field (currently only  `text` and `u32` are supported)
+* the type of the field (currently `text`, `u32`, `date` and `json` are supported)
 * how the field should be indexed / stored.
     
 This very last point is critical as it will enable / disable some of the functionality
@@ -93,6 +93,55 @@ u32 that are indexed as fast will be stored in a special data structure that wil
 make it possible to access the u32 value given the doc id rapidly. This is useful if the value of
 the field is required during scoring or collection for instance.
 
+
+
+## Setting a date field
+
+### Example
+
+```
+use tantivy::schema::*;
+let mut schema_builder = SchemaBuilder::default();
+let created = schema_builder.add_date_field("created", DATE_INDEXED | DATE_STORED);
+let schema = schema_builder.build();
+let mut doc = Document::default();
+doc.add_date(created, DateTime::from_rfc3339("2017-03-12T11:30:00+02:00").unwrap());
+```
+
+Dates have a millisecond precision, and are normalized to UTC.
+They are written in RFC 3339 in the JSON documents, and in the queries,
+e.g. `created:[2017-01-01T00:00:00Z TO 2017-12-31T23:59:59Z]`.
+
+
+
+## Setting a JSON field
+
+### Example
+
+```
+extern crate rustc_serialize;
+# extern crate tantivy;
+use tantivy::schema::*;
+use rustc_serialize::json::Json;
+# fn main() {
+let mut schema_builder = SchemaBuilder::default();
+let attributes = schema_builder.add_json_field("attributes", JSON_INDEXED | JSON_STORED);
+let schema = schema_builder.build();
+let mut doc = Document::default();
+let json = Json::from_str(r#"{"color": "red", "size": {"width": 12}}"#).unwrap();
+doc.add_json(attributes, json.as_object().unwrap().clone());
+# }
+```
+
+A JSON field accepts arbitrary objects, for which no schema is declared.
+The nested keys are flattened into paths, and each leaf is indexed
+under its path with the type inferred from its value: text, i64, f64 or bool.
+The elements of an array are all indexed under the path of the array.
+
+The query parser targets a path with a dotted field name, e.g. `attributes.color:red`
+or `attributes.size.width:12`. If the field is `JSON_FAST`, the numeric
+leaves can also be queried by range, e.g. `attributes.size.width:[10 TO 20]`.
+
 */ 
 
 mod schema;
@@ -105,18 +154,29 @@ mod field_value;
 
 mod text_options;
 mod u32_options;
+mod date_options;
+mod date_time;
+mod json_options;
 mod field;
 mod value;
 mod named_field_document;
+mod geo_field;
+mod similarity;
+mod max_term_length;
+mod facet;
 
 
 pub use self::named_field_document::NamedFieldDocument;
 pub use self::schema::{Schema, SchemaBuilder};
-pub use self::value::Value;
+pub use self::value::{Value, serialize_text_value};
 pub use self::schema::DocParsingError;
 
 pub use self::document::Document;
 pub use self::field::Field;
+pub use self::geo_field::GeoField;
+pub use self::similarity::Similarity;
+pub use self::max_term_length::{MaxTermLength, TermLengthPolicy};
+pub use self::facet::{Facet, FACET_SEP};
 pub use self::term::Term;
 
 pub use self::field_type::FieldType;
@@ -127,6 +187,7 @@ pub use self::text_options::TextOptions;
 pub use self::text_options::TextIndexingOptions;
 pub use self::text_options::TEXT;
 pub use self::text_options::STRING;
+pub use self::text_options::STRING_CI;
 pub use self::text_options::STORED;
 
 pub use self::u32_options::U32Options;
@@ -134,6 +195,16 @@ pub use self::u32_options::FAST;
 pub use self::u32_options::U32_INDEXED;
 pub use self::u32_options::U32_STORED;
 
+pub use self::date_time::DateTime;
+pub use self::date_options::DateOptions;
+pub use self::date_options::DATE_INDEXED;
+pub use self::date_options::DATE_STORED;
+
+pub use self::json_options::JsonOptions;
+pub use self::json_options::JSON_INDEXED;
+pub use self::json_options::JSON_STORED;
+pub use self::json_options::JSON_FAST;
+
 use regex::Regex;
 
 
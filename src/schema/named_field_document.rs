@@ -1,5 +1,6 @@
 use std::collections::BTreeMap;
 use schema::Value;
+use rustc_serialize::json::Json;
 use rustc_serialize::Encodable;
 use rustc_serialize::Encoder;
 
@@ -29,6 +30,15 @@ impl Encodable for NamedFieldDocument {
                                     Value::U32(ref val) => {
                                         s.emit_u32(*val)
                                     }
+                                    Value::Date(ref date_time) => {
+                                        s.emit_str(&date_time.to_rfc3339())
+                                    }
+                                    Value::Json(ref json_text) => {
+                                        match Json::from_str(json_text) {
+                                            Ok(json) => json.encode(s),
+                                            Err(_) => s.emit_str(json_text),
+                                        }
+                                    }
                                 }
                             })
                         }));
@@ -61,6 +61,54 @@ impl SchemaBuilder {
         self.add_field(field_entry)
     }
 
+    /// Adds a new date field.
+    /// Returns the associated field handle
+    ///
+    /// # Caution
+    ///
+    /// Appending two fields with the same name
+    /// will result in the shadowing of the first
+    /// by the second one.
+    /// The first field will get a field id
+    /// but only the second one will be indexed
+    pub fn add_date_field(
+            &mut self,
+            field_name_str: &str,
+            field_options: DateOptions) -> Field {
+        let field_name = String::from(field_name_str);
+        let field_entry = FieldEntry::new_date(field_name, field_options);
+        self.add_field(field_entry)
+    }
+
+    /// Adds a new JSON field.
+    /// Returns the associated field handle
+    ///
+    /// # Caution
+    ///
+    /// Appending two fields with the same name
+    /// will result in the shadowing of the first
+    /// by the second one.
+    /// The first field will get a field id
+    /// but only the second one will be indexed
+    pub fn add_json_field(
+            &mut self,
+            field_name_str: &str,
+            field_options: JsonOptions) -> Field {
+        let field_name = String::from(field_name_str);
+        let field_entry = FieldEntry::new_json(field_name, field_options);
+        self.add_field(field_entry)
+    }
+
+    /// Adds a new geo-point field.
+    ///
+    /// It is made of two u32 fast fields, `<field_name>.lat` and
+    /// `<field_name>.lon`. See `GeoField`.
+    pub fn add_geo_field(&mut self, field_name_str: &str) -> GeoField {
+        let lat_field = self.add_u32_field(&format!("{}.lat", field_name_str), FAST);
+        let lon_field = self.add_u32_field(&format!("{}.lon", field_name_str), FAST);
+        GeoField::new(lat_field, lon_field)
+    }
+
     /// Adds a new text field.
     /// Returns the associated field handle
     ///
@@ -81,6 +129,129 @@ impl SchemaBuilder {
     }
     
     
+    /// Defines the order in which stored fields are
+    /// written in each document record of the doc store.
+    ///
+    /// Stored fields listed here are written first, in the given order,
+    /// followed by the remaining stored fields in field order.
+    /// Putting the fields that are read most often first makes
+    /// `StoreReader::get_first_fields` cheaper.
+    ///
+    /// # Panics
+    ///
+    /// Panics if one of the fields does not belong to this schema.
+    pub fn set_stored_fields_order(&mut self, fields: &[Field]) {
+        for field_entry in &mut self.fields {
+            field_entry.set_stored_rank(None);
+        }
+        for (rank, field) in fields.iter().enumerate() {
+            self.fields[field.0 as usize].set_stored_rank(Some(rank));
+        }
+    }
+    
+    /// Sets the similarity used to score the terms of `field`.
+    ///
+    /// By default, fields use `Similarity::TfIdf`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the field does not belong to this schema.
+    pub fn set_similarity(&mut self, field: Field, similarity: Similarity) {
+        self.fields[field.0 as usize].set_similarity(Some(similarity));
+    }
+    
+    /// Sets the maximum length of the terms of `field`, and
+    /// what happens to the longer tokens.
+    ///
+    /// By default, tokens longer than 255 bytes are dropped.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the field does not belong to this schema.
+    pub fn set_max_term_length(&mut self, field: Field, max_term_length: MaxTermLength) {
+        self.fields[field.0 as usize].set_max_term_length(Some(max_term_length));
+    }
+    
+    /// Enables payloads for the text field `field`.
+    ///
+    /// The postings of the field then store, for each document,
+    /// a `u32` payload set with `Document::set_payload`, which can
+    /// be read at search time with `Postings::payload`.
+    /// Documents without a payload for a term get a payload of `0`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the field does not belong to this schema,
+    /// or if it is not an indexed text field.
+    pub fn enable_payloads(&mut self, field: Field) {
+        let field_entry = &mut self.fields[field.0 as usize];
+        let is_text = if let FieldType::Str(_) = *field_entry.field_type() { true } else { false };
+        assert!(is_text && field_entry.is_indexed(),
+                "Payloads are only supported by indexed text fields, {:?} is not one.", field_entry.name());
+        field_entry.set_payloads(true);
+    }
+    
+    /// Sets the tokenizer of the text field `field`, by the name
+    /// it is registered under in the `TokenizerManager` of the index.
+    ///
+    /// By default, fields use the tokenizer registered as `default`,
+    /// that is the `SimpleTokenizer`. The tokenizer only applies
+    /// to tokenized fields.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the field does not belong to this schema.
+    pub fn set_tokenizer(&mut self, field: Field, tokenizer: &str) {
+        self.fields[field.0 as usize].set_tokenizer(Some(String::from(tokenizer)));
+    }
+    
+    /// Sorts the documents of the merged segments by
+    /// the value of the u32 fast field `field`, in increasing order.
+    ///
+    /// Documents with the same value keep the order of their segments.
+    /// The blocks of documents, see `IndexWriter::add_document_block`,
+    /// are sorted as a whole, by the value of their last document.
+    /// Only merged segments are sorted: the segments produced by
+    /// the `IndexWriter` keep the documents in the order they were added.
+    ///
+    /// An index can only be sorted by one field, so this replaces
+    /// the field the index was previously sorted by, if any.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the field does not belong to this schema,
+    /// or if it is not a u32 fast field.
+    pub fn set_index_sort(&mut self, field: Field) {
+        assert!(self.fields[field.0 as usize].is_u32_fast(),
+                "The index can only be sorted by a u32 fast field, {:?} is not one.", self.fields[field.0 as usize].name());
+        for field_entry in &mut self.fields {
+            field_entry.set_index_sort(false);
+        }
+        self.fields[field.0 as usize].set_index_sort(true);
+    }
+    
+    /// Lets the u32 fast field `field` hold any number of values
+    /// per document, instead of only its first value.
+    ///
+    /// The values of a document are kept in the order they were
+    /// added to the document, and read with
+    /// `SegmentReader::get_multivalued_fast_field_reader`.
+    /// A multivalued fast field is not accessible through
+    /// `SegmentReader::get_fast_field_reader`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the field does not belong to this schema,
+    /// if it is not a u32 fast field, or if the index is sorted by it.
+    pub fn set_multivalued(&mut self, field: Field) {
+        let field_entry = &mut self.fields[field.0 as usize];
+        assert!(field_entry.is_u32_fast() || field_entry.is_u32_multivalued_fast(),
+                "Only u32 fast fields can be multivalued, {:?} is not one.", field_entry.name());
+        assert!(!field_entry.is_index_sort(),
+                "The index is sorted by {:?}, which cannot be multivalued.", field_entry.name());
+        field_entry.set_multivalued(true);
+    }
+    
     /// Adds a field entry to the schema in build.
     fn add_field(&mut self, field_entry: FieldEntry) -> Field {
         let field = Field(self.fields.len() as u8);
@@ -97,9 +268,23 @@ impl SchemaBuilder {
         if self.fields.len() > MAX_NUM_FIELDS {
             panic!("There may be at most 255 fields.");
         }
+        let mut stored_fields_order: Vec<(usize, Field)> = self.fields
+            .iter()
+            .enumerate()
+            .filter_map(|(field_id, field_entry)| {
+                field_entry
+                    .stored_rank()
+                    .map(|rank| (rank, Field(field_id as u8)))
+            })
+            .collect();
+        stored_fields_order.sort();
         Schema(Arc::new(InnerSchema {
             fields: self.fields,
             fields_map: self.fields_map,
+            stored_fields_order: stored_fields_order
+                .into_iter()
+                .map(|(_, field)| field)
+                .collect(),
         }))
     }
 }
@@ -118,6 +303,7 @@ impl Default for SchemaBuilder {
 struct InnerSchema {
     fields: Vec<FieldEntry>,
     fields_map: HashMap<String, Field>,  // transient
+    stored_fields_order: Vec<Field>, // transient
 }
 
 
@@ -162,6 +348,58 @@ impl Schema {
         &self.0.fields
     }
     
+    /// Return the fields that are written first in the doc store,
+    /// as defined by `SchemaBuilder::set_stored_fields_order`.
+    pub fn stored_fields_order(&self,) -> &[Field] {
+        &self.0.stored_fields_order
+    }
+    
+    /// Returns the field the documents of the merged
+    /// segments are sorted by, if any.
+    ///
+    /// See `SchemaBuilder::set_index_sort`.
+    pub fn index_sort_field(&self,) -> Option<Field> {
+        self.0.fields
+            .iter()
+            .position(|field_entry| field_entry.is_index_sort())
+            .map(|field_id| Field(field_id as u8))
+    }
+    
+    /// Sorts field values in the order in which they
+    /// should be written in the doc store.
+    ///
+    /// Values of a same field stay contiguous and keep their
+    /// relative order.
+    pub fn sort_stored_field_values(&self, field_values: &mut Vec<&FieldValue>) {
+        field_values.sort_by_key(|field_value| self.stored_field_sort_key(field_value.field()));
+    }
+
+    /// Returns the key by which the values of `field` are
+    /// sorted in the doc store.
+    ///
+    /// See `sort_stored_field_values`.
+    #[doc(hidden)]
+    pub fn stored_field_sort_key(&self, field: Field) -> (usize, usize) {
+        match self.get_field_entry(field).stored_rank() {
+            Some(rank) => (0, rank),
+            None => (1, field.0 as usize),
+        }
+    }
+    
+    /// Returns true iff at least one of the fields has field norms.
+    ///
+    /// Otherwise, the segments of the index have no field norm file.
+    pub fn has_fieldnorms(&self,) -> bool {
+        self.fields().iter().any(FieldEntry::has_norms)
+    }
+
+    /// Returns true iff at least one of the fields has term vectors.
+    ///
+    /// Otherwise, the segments of the index have no term vector file.
+    pub fn has_term_vectors(&self,) -> bool {
+        self.fields().iter().any(FieldEntry::has_term_vectors)
+    }
+
     /// Returns the field options associated with a given name.
     ///
     /// # Panics
@@ -483,4 +721,30 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    pub fn test_stored_fields_order() {
+        let mut schema_builder = SchemaBuilder::default();
+        let title_field = schema_builder.add_text_field("title", STORED);
+        let body_field = schema_builder.add_text_field("body", STORED);
+        let count_field = schema_builder.add_u32_field("count", U32Options::default().set_stored());
+        schema_builder.set_stored_fields_order(&[count_field, title_field]);
+        let schema = schema_builder.build();
+        assert_eq!(schema.stored_fields_order(), &[count_field, title_field]);
+        let schema_json = json::encode(&schema).unwrap();
+        let schema_deser: Schema = json::decode(&schema_json).unwrap();
+        assert_eq!(schema_deser.stored_fields_order(), &[count_field, title_field]);
+
+        let field_values = vec!(
+            FieldValue::new(body_field, Value::from("body")),
+            FieldValue::new(title_field, Value::from("title1")),
+            FieldValue::new(count_field, Value::U32(3)),
+            FieldValue::new(title_field, Value::from("title2")),
+        );
+        let mut field_values_refs: Vec<&FieldValue> = field_values.iter().collect();
+        schema.sort_stored_field_values(&mut field_values_refs);
+        let sorted: Vec<&FieldValue> = vec!(&field_values[2], &field_values[1], &field_values[3], &field_values[0]);
+        assert_eq!(field_values_refs, sorted);
+    }
+
 }
@@ -0,0 +1,151 @@
+use rustc_serialize::Decodable;
+use rustc_serialize::Decoder;
+use rustc_serialize::Encodable;
+use rustc_serialize::Encoder;
+
+
+/// Defines how the documents matching a term are scored,
+/// given the term frequency and the length of their field.
+///
+/// The similarity is defined per field, see `SchemaBuilder::set_similarity`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Similarity {
+    /// `idf * sqrt(term_freq / field_norm)`, with
+    /// `idf = 1 + ln(num_docs / (doc_freq + 1))`.
+    ///
+    /// This is the default similarity.
+    TfIdf,
+    /// Okapi BM25.
+    ///
+    /// `k1` controls the saturation of the term frequency, and
+    /// `b` how much the score is normalized by the length
+    /// of the field, relatively to the average length of the field.
+    /// With `b = 0`, the length of the field is ignored.
+    BM25 {
+        /// Term frequency saturation.
+        k1: f32,
+        /// Length normalization.
+        b: f32,
+    },
+    /// `sqrt(term_freq / field_norm)`.
+    ///
+    /// Same as `TfIdf`, with an idf of `1`: the rarity
+    /// of the term is ignored.
+    TfOnly,
+    /// Every document matching a term scores `1`, whatever
+    /// the term frequency, the length of the field, or the rarity
+    /// of the term.
+    Constant,
+}
+
+impl Default for Similarity {
+    fn default() -> Similarity {
+        Similarity::TfIdf
+    }
+}
+
+impl Similarity {
+
+    /// BM25 with the usual parameters, `k1 = 1.2` and `b = 0.75`.
+    pub fn bm25() -> Similarity {
+        Similarity::BM25 {
+            k1: 1.2f32,
+            b: 0.75f32,
+        }
+    }
+
+    /// Returns true iff the similarity requires the
+    /// average field norm of the field.
+    pub fn requires_average_fieldnorm(&self,) -> bool {
+        match *self {
+            Similarity::TfIdf | Similarity::TfOnly | Similarity::Constant => false,
+            Similarity::BM25 { .. } => true,
+        }
+    }
+
+    /// Inverse document frequency of a term.
+    pub fn idf(&self, num_docs: u64, doc_freq: u32) -> f32 {
+        match *self {
+            Similarity::TfIdf => {
+                1.0 + (num_docs as f32 / (doc_freq as f32 + 1.0)).ln()
+            }
+            Similarity::BM25 { .. } => {
+                let doc_freq = doc_freq as f32;
+                (1.0 + (num_docs as f32 - doc_freq + 0.5) / (doc_freq + 0.5)).ln()
+            }
+            Similarity::TfOnly | Similarity::Constant => 1f32,
+        }
+    }
+
+    /// Scores a document given the `idf` of the term, its term frequency,
+    /// and the norm of its field if available.
+    pub fn score(&self, idf: f32, term_freq: u32, fieldnorm_opt: Option<u32>, average_fieldnorm: f32) -> f32 {
+        let term_freq = term_freq as f32;
+        match *self {
+            Similarity::TfIdf | Similarity::TfOnly => {
+                let tf = match fieldnorm_opt {
+                    Some(fieldnorm) => term_freq / fieldnorm as f32,
+                    None => term_freq,
+                };
+                idf * tf.sqrt()
+            }
+            Similarity::BM25 { k1, b } => {
+                let length_ratio = match fieldnorm_opt {
+                    Some(fieldnorm) if average_fieldnorm > 0f32 => fieldnorm as f32 / average_fieldnorm,
+                    _ => 1f32,
+                };
+                let norm = k1 * (1f32 - b + b * length_ratio);
+                idf * term_freq * (k1 + 1f32) / (term_freq + norm)
+            }
+            Similarity::Constant => 1f32,
+        }
+    }
+}
+
+impl Encodable for Similarity {
+    fn encode<S: Encoder>(&self, s: &mut S) -> Result<(), S::Error> {
+        match *self {
+            Similarity::TfIdf => {
+                s.emit_struct("similarity", 1, |s| {
+                    s.emit_struct_field("name", 0, |s| s.emit_str("tfidf"))
+                })
+            }
+            Similarity::BM25 { k1, b } => {
+                s.emit_struct("similarity", 3, |s| {
+                    try!(s.emit_struct_field("name", 0, |s| s.emit_str("bm25")));
+                    try!(s.emit_struct_field("k1", 1, |s| s.emit_f32(k1)));
+                    s.emit_struct_field("b", 2, |s| s.emit_f32(b))
+                })
+            }
+            Similarity::TfOnly => {
+                s.emit_struct("similarity", 1, |s| {
+                    s.emit_struct_field("name", 0, |s| s.emit_str("tf"))
+                })
+            }
+            Similarity::Constant => {
+                s.emit_struct("similarity", 1, |s| {
+                    s.emit_struct_field("name", 0, |s| s.emit_str("constant"))
+                })
+            }
+        }
+    }
+}
+
+impl Decodable for Similarity {
+    fn decode<D: Decoder>(d: &mut D) -> Result<Self, D::Error> {
+        d.read_struct("similarity", 3, |d| {
+            let name = try!(d.read_struct_field("name", 0, |d| d.read_str()));
+            match name.as_ref() {
+                "tfidf" => Ok(Similarity::TfIdf),
+                "bm25" => {
+                    let k1 = try!(d.read_struct_field("k1", 1, |d| d.read_f32()));
+                    let b = try!(d.read_struct_field("b", 2, |d| d.read_f32()));
+                    Ok(Similarity::BM25 { k1: k1, b: b })
+                }
+                "tf" => Ok(Similarity::TfOnly),
+                "constant" => Ok(Similarity::Constant),
+                _ => Err(d.error(&format!("Similarity {:?} unknown", name)))
+            }
+        })
+    }
+}
@@ -3,8 +3,27 @@ use std::fmt;
 use common::BinarySerializable;
 use byteorder::{BigEndian, ByteOrder};
 use super::Field;
+use super::DateTime;
 use std::str;
 
+const JSON_TEXT_CODE: u8 = b's';
+const JSON_I64_CODE: u8 = b'i';
+const JSON_F64_CODE: u8 = b'f';
+const JSON_BOOL_CODE: u8 = b'b';
+const JSON_NUMBER_CODE: u8 = b'n';
+
+// maps a f64 to a u64, so that the order of the u64s
+// matches that of the f64s.
+fn f64_to_ordered_u64(val: f64) -> u64 {
+    let bits = val.to_bits();
+    if bits & (1u64 << 63) != 0 {
+        !bits
+    }
+    else {
+        bits ^ (1u64 << 63)
+    }
+}
+
 
 /// Term represents the value that the token can take.
 ///
@@ -53,6 +72,17 @@ impl Term {
         Term(buffer)
     }
     
+    /// Builds a term given a field, and a date.
+    ///
+    /// The term has 9 bytes: the field id, followed by the
+    /// timestamp in milliseconds, with its sign bit flipped, so that
+    /// the lexicographic order of the terms matches the order of the dates.
+    pub fn from_field_date(field: Field, date_time: DateTime) -> Term {
+        let mut buffer = vec!(field.0; 1 + 8);
+        BigEndian::write_u64(&mut buffer[1..9], (date_time.timestamp_millis() as u64) ^ (1u64 << 63));
+        Term(buffer)
+    }
+
     /// Builds a term given a field, and a string value
     ///
     /// Assuming the term has a field id of 2, and a text value of "abc",
@@ -67,12 +97,86 @@ impl Term {
         Term(buffer)
     }
 
+    // the term of a JSON leaf starts with the field, the utf-8 path
+    // to the leaf, a 0 separator, and the one byte type of the leaf.
+    fn json_leaf(field: Field, path: &str, type_code: u8, num_bytes: usize) -> Term {
+        let mut buffer = Vec::with_capacity(1 + path.len() + 2 + num_bytes);
+        buffer.push(field.0);
+        buffer.extend(path.as_bytes());
+        buffer.push(0u8);
+        buffer.push(type_code);
+        Term(buffer)
+    }
+
+    /// Builds the term of a text token found in a JSON field
+    /// under the given path, e.g. `color` or `size.unit`.
+    ///
+    /// The term consists of the field id, the utf-8 path, a `0` separator,
+    /// the type code `s` and the utf-8 token.
+    pub fn from_field_json_text(field: Field, path: &str, text: &str) -> Term {
+        let mut term = Term::json_leaf(field, path, JSON_TEXT_CODE, text.len());
+        term.0.extend(text.as_bytes());
+        term
+    }
+
+    /// Builds the term of an integer leaf found in a JSON field
+    /// under the given path.
+    ///
+    /// The value is encoded as 8 big endian bytes, with its sign bit flipped.
+    pub fn from_field_json_i64(field: Field, path: &str, val: i64) -> Term {
+        let mut term = Term::json_leaf(field, path, JSON_I64_CODE, 8);
+        let mut bytes = [0u8; 8];
+        BigEndian::write_u64(&mut bytes, (val as u64) ^ (1u64 << 63));
+        term.0.extend(&bytes);
+        term
+    }
+
+    /// Builds the term of a floating point leaf found in a JSON field
+    /// under the given path.
+    pub fn from_field_json_f64(field: Field, path: &str, val: f64) -> Term {
+        let mut term = Term::json_leaf(field, path, JSON_F64_CODE, 8);
+        let mut bytes = [0u8; 8];
+        BigEndian::write_u64(&mut bytes, f64_to_ordered_u64(val));
+        term.0.extend(&bytes);
+        term
+    }
+
+    /// Builds the term of a boolean leaf found in a JSON field
+    /// under the given path.
+    pub fn from_field_json_bool(field: Field, path: &str, val: bool) -> Term {
+        let mut term = Term::json_leaf(field, path, JSON_BOOL_CODE, 1);
+        term.0.push(val as u8);
+        term
+    }
+
+    /// Builds the term of a numeric leaf found in a JSON field,
+    /// as indexed for range queries if the field is fast.
+    ///
+    /// Integers and floating point values are both encoded as f64,
+    /// so that the lexicographic order of the terms sharing a path
+    /// matches the order of their values.
+    pub fn from_field_json_number(field: Field, path: &str, val: f64) -> Term {
+        let mut term = Term::json_leaf(field, path, JSON_NUMBER_CODE, 8);
+        let mut bytes = [0u8; 8];
+        BigEndian::write_u64(&mut bytes, f64_to_ordered_u64(val));
+        term.0.extend(&bytes);
+        term
+    }
+
     /// Assume the term is a u32 field.
     ///
     /// Panics if the term is not a u32 field.
     pub fn get_u32(&self) -> u32 {
         BigEndian::read_u32(&self.0[1..])
     }
+
+    /// Assume the term is a date field.
+    ///
+    /// Panics if the term is not a date field.
+    pub fn get_date(&self) -> DateTime {
+        let timestamp_millis = (BigEndian::read_u64(&self.0[1..]) ^ (1u64 << 63)) as i64;
+        DateTime::from_timestamp_millis(timestamp_millis)
+    }
     
     /// Builds a term from its byte representation.
     ///
@@ -102,6 +206,21 @@ impl Term {
         str::from_utf8_unchecked(self.value())
     }
 
+    /// Sets the field of the term, and clears its value.
+    ///
+    /// The underlying buffer is kept, so that a term
+    /// can be reused without allocating.
+    pub fn set_field(&mut self, field: Field) {
+        self.0.clear();
+        self.0.push(field.0);
+    }
+
+    /// Set the u32 value only, keeping the field untouched.
+    pub fn set_u32(&mut self, val: u32) {
+        self.0.resize(5, 0u8);
+        BigEndian::write_u32(&mut self.0[1..5], val);
+    }
+
     /// Set the texts only, keeping the field untouched. 
     pub fn set_text(&mut self, text: &str) {
         self.0.resize(1, 0u8);
@@ -154,6 +273,38 @@ mod tests {
             assert_eq!(term.as_slice()[3], (933u32 / 256u32) as u8);
             assert_eq!(term.as_slice()[4], (983u32 % 256u32) as u8);
         }
+        {
+            let mut term = Term::allocate(title_field, 100);
+            term.set_text("a rather long text");
+            term.set_field(count_field);
+            term.set_u32(983u32);
+            assert_eq!(term, Term::from_field_u32(count_field, 983u32));
+            term.set_field(title_field);
+            term.set_text("test");
+            assert_eq!(term, Term::from_field_text(title_field, "test"));
+        }
+        {
+            let before_epoch = DateTime::from_timestamp_millis(-1_000);
+            let after_epoch = DateTime::from_timestamp_millis(1_000);
+            let term = Term::from_field_date(count_field, before_epoch);
+            assert_eq!(term.as_slice().len(), 9);
+            assert_eq!(term.get_date(), before_epoch);
+            assert!(term < Term::from_field_date(count_field, after_epoch));
+        }
+        {
+            let term = Term::from_field_json_text(title_field, "size.unit", "cm");
+            assert_eq!(term.field(), title_field);
+            assert_eq!(&term.as_slice()[1..], b"size.unit\0scm");
+            assert_eq!(Term::from_field_json_bool(title_field, "a", true).as_slice(), &[1u8, b'a', 0u8, b'b', 1u8]);
+            assert!(Term::from_field_json_i64(title_field, "a", -3) < Term::from_field_json_i64(title_field, "a", 2));
+            let numbers = [-1e10, -2.5, -0.5, 0.0, 0.5, 3.0, 1e10];
+            for window in numbers.windows(2) {
+                assert!(Term::from_field_json_number(title_field, "a", window[0]) < Term::from_field_json_number(title_field, "a", window[1]));
+                assert!(Term::from_field_json_f64(title_field, "a", window[0]) < Term::from_field_json_f64(title_field, "a", window[1]));
+            }
+            // the typed terms of a path do not collide.
+            assert!(Term::from_field_json_number(title_field, "a", 3.0) != Term::from_field_json_f64(title_field, "a", 3.0));
+        }
                 
     }
 }
\ No newline at end of file
@@ -6,10 +6,12 @@ use rustc_serialize::Encoder;
 
 
 /// Define how a text field should be handled by tantivy.
-#[derive(Clone,Debug,PartialEq,Eq, RustcDecodable, RustcEncodable)]
+#[derive(Clone,Debug,PartialEq,Eq)]
 pub struct TextOptions {
     indexing: TextIndexingOptions,
     stored: bool,
+    norms: bool,
+    term_vectors: bool,
 }
 
 impl TextOptions {
@@ -36,6 +38,40 @@ impl TextOptions {
         self
     }
 
+    /// Returns true iff the field norms of the field are recorded.
+    pub fn has_norms(&self,) -> bool {
+        self.norms
+    }
+
+    /// Enables or disables the field norms of the field. They are enabled by default.
+    ///
+    /// The field norm of a document is the number of tokens of its field,
+    /// and costs a few bits per document.
+    /// Without field norms, the scores are not normalized by
+    /// the length of the field: the norm is considered to be 1.
+    /// Disabling them is worth it for the fields that are only
+    /// used for filtering.
+    pub fn set_norms(mut self, norms: bool) -> TextOptions {
+        self.norms = norms;
+        self
+    }
+
+    /// Returns true iff the term vectors of the field are recorded.
+    pub fn has_term_vectors(&self,) -> bool {
+        self.term_vectors
+    }
+
+    /// Records the term vectors of the field.
+    ///
+    /// The term vector of a document lists the terms of its field,
+    /// along with their frequency in the document. They are written
+    /// in a separate file of the segment, and are only recorded for
+    /// indexed fields.
+    pub fn set_term_vectors(mut self,) -> TextOptions {
+        self.term_vectors = true;
+        self
+    }
+
 }
 
 impl Default for TextOptions {
@@ -43,10 +79,63 @@ impl Default for TextOptions {
         TextOptions {
             indexing: TextIndexingOptions::Unindexed,
             stored: false,
+            norms: true,
+            term_vectors: false,
         }
     }
 }
 
+impl Encodable for TextOptions {
+    fn encode<S: Encoder>(&self, s: &mut S) -> Result<(), S::Error> {
+        let num_struct_fields = 2 + if self.norms { 0 } else { 1 } + if self.term_vectors { 1 } else { 0 };
+        s.emit_struct("text_options", num_struct_fields, |s| {
+            try!(s.emit_struct_field("indexing", 0, |s| {
+                self.indexing.encode(s)
+            }));
+            try!(s.emit_struct_field("stored", 1, |s| {
+                s.emit_bool(self.stored)
+            }));
+            if !self.norms {
+                try!(s.emit_struct_field("norms", 2, |s| {
+                    s.emit_bool(false)
+                }));
+            }
+            if self.term_vectors {
+                try!(s.emit_struct_field("term_vectors", 3, |s| {
+                    s.emit_bool(true)
+                }));
+            }
+            Ok(())
+        })
+    }
+}
+
+impl Decodable for TextOptions {
+    fn decode<D: Decoder>(d: &mut D) -> Result<Self, D::Error> {
+        d.read_struct("text_options", 2, |d| {
+            let indexing = try!(d.read_struct_field("indexing", 0, TextIndexingOptions::decode));
+            let stored = try!(d.read_struct_field("stored", 1, |d| d.read_bool()));
+            // the field norms were always recorded by older versions of tantivy.
+            let norms = try!(d.read_struct_field("norms", 2, |d| {
+                d.read_option(|d, is_some| {
+                    if is_some { d.read_bool().map(Some) } else { Ok(None) }
+                })
+            }));
+            let term_vectors = try!(d.read_struct_field("term_vectors", 3, |d| {
+                d.read_option(|d, is_some| {
+                    if is_some { d.read_bool().map(Some) } else { Ok(None) }
+                })
+            }));
+            Ok(TextOptions {
+                indexing: indexing,
+                stored: stored,
+                norms: norms.unwrap_or(true),
+                term_vectors: term_vectors.unwrap_or(false),
+            })
+        })
+    }
+}
+
 
 
 
@@ -62,6 +151,10 @@ pub enum TextIndexingOptions {
     /// It will **not** be searchable if the user enter "hello" for instance.
     /// This can be useful for tags, or ids for instance.   
     Untokenized,
+    /// Like `Untokenized`, but the field text is lowercased before being indexed.
+    /// A field with the value "Hello world" will only be searchable as a whole, but 
+    /// regardless of its case, e.g. as "hello World".
+    UntokenizedLowercase,
     /// TokenizedNoFreq will tokenize the field value, and append the document doc id 
     /// to the posting lists associated to all of the tokens.
     /// The frequence of appearance of the term in the document however will be lost.
@@ -86,6 +179,9 @@ impl Encodable for TextIndexingOptions {
           TextIndexingOptions::Untokenized => {
               "untokenized"
           }
+          TextIndexingOptions::UntokenizedLowercase => {
+              "keyword_ci"
+          }
           TextIndexingOptions::TokenizedNoFreq => {
               "tokenize"
           }
@@ -107,6 +203,7 @@ impl Decodable for TextIndexingOptions {
         Ok(match option_name.as_ref() {
             "unindexed" => Unindexed,
             "untokenized" => Untokenized,
+            "keyword_ci" => UntokenizedLowercase,
             "tokenize" => TokenizedNoFreq,
             "freq" => TokenizedWithFreq,
             "position" => TokenizedWithFreqAndPosition,
@@ -139,6 +236,12 @@ impl TextIndexingOptions {
     }
     
     
+    /// Returns true iff the term is lowercased before being indexed
+    /// as a single token.
+    pub fn is_lowercased_keyword(&self,) -> bool {
+        *self == TextIndexingOptions::UntokenizedLowercase
+    }
+
     /// Returns true iff the term will generate some posting lists.
     pub fn is_indexed(&self,) -> bool {
         match *self {
@@ -180,13 +283,28 @@ impl BitOr for TextIndexingOptions {
 pub const STRING: TextOptions = TextOptions {
     indexing: TextIndexingOptions::Untokenized,
     stored: false,
+    norms: true,
+    term_vectors: false,
 };
 
 
+/// The field will be untokenized, lowercased and indexed.
+///
+/// Its value can only be matched as a whole, but
+/// matching is case insensitive.
+pub const STRING_CI: TextOptions = TextOptions {
+    indexing: TextIndexingOptions::UntokenizedLowercase,
+    stored: false,
+    norms: true,
+    term_vectors: false,
+};
+
 /// The field will be tokenized and indexed
 pub const TEXT: TextOptions = TextOptions {
     indexing: TextIndexingOptions::TokenizedWithFreqAndPosition,
     stored: false,
+    norms: true,
+    term_vectors: false,
 };
 
 /// A stored fields of a document can be retrieved given its `DocId`.
@@ -196,6 +314,8 @@ pub const TEXT: TextOptions = TextOptions {
 pub const STORED: TextOptions = TextOptions {
     indexing: TextIndexingOptions::Unindexed,
     stored: true,
+    norms: true,
+    term_vectors: false,
 };
 
 
@@ -207,6 +327,8 @@ impl BitOr for TextOptions {
         let mut res = TextOptions::default();
         res.indexing = self.indexing | other.indexing;
         res.stored = self.stored | other.stored;
+        res.norms = self.norms & other.norms;
+        res.term_vectors = self.term_vectors | other.term_vectors;
         res
     }
 }
@@ -215,6 +337,7 @@ impl BitOr for TextOptions {
 #[cfg(test)]
 mod tests {
     use schema::*;
+    use rustc_serialize::json;
     
     #[test]
     fn test_field_options() {
@@ -239,4 +362,44 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn test_keyword_ci_options() {
+        let field_options = STORED | STRING_CI;
+        let indexing_options = field_options.get_indexing_options();
+        assert!(indexing_options.is_indexed());
+        assert!(!indexing_options.is_tokenized());
+        assert!(indexing_options.is_lowercased_keyword());
+        assert!(!STRING.get_indexing_options().is_lowercased_keyword());
+        let json = json::encode(&field_options).unwrap();
+        assert_eq!(json, r#"{"indexing":"keyword_ci","stored":true}"#);
+        let decoded: TextOptions = json::decode(&json).unwrap();
+        assert_eq!(decoded, field_options);
+    }
+
+    #[test]
+    fn test_norms_options() {
+        assert!(TEXT.has_norms());
+        let field_options = TEXT.set_norms(false);
+        assert!(!field_options.has_norms());
+        assert!(!(STORED | field_options.clone()).has_norms());
+        let json = json::encode(&field_options).unwrap();
+        assert_eq!(json, r#"{"indexing":"position","stored":false,"norms":false}"#);
+        let decoded: TextOptions = json::decode(&json).unwrap();
+        assert_eq!(decoded, field_options);
+        assert_eq!(json::encode(&TEXT).unwrap(), r#"{"indexing":"position","stored":false}"#);
+    }
+
+    #[test]
+    fn test_term_vectors_options() {
+        assert!(!TEXT.has_term_vectors());
+        let field_options = TEXT.set_term_vectors();
+        assert!(field_options.has_term_vectors());
+        assert!((STORED | field_options.clone()).has_term_vectors());
+        let json = json::encode(&field_options).unwrap();
+        assert_eq!(json, r#"{"indexing":"position","stored":false,"term_vectors":true}"#);
+        let decoded: TextOptions = json::decode(&json).unwrap();
+        assert_eq!(decoded, field_options);
+    }
+
 }
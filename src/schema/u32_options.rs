@@ -48,8 +48,8 @@ impl U32Options {
     ///
     /// Fast fields are designed for random access.
     /// Access time are similar to a random lookup in an array. 
-    /// If more than one value is associated to a fast field, only the last one is
-    /// kept.
+    /// If more than one value is associated to a fast field, only the first one is
+    /// kept, unless the field is multivalued (see `SchemaBuilder::set_multivalued`).
     pub fn set_fast(mut self,) -> U32Options {
         self.fast = true;
         self
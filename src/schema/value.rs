@@ -1,5 +1,7 @@
 
 use common::BinarySerializable;
+use common::VInt;
+use schema::DateTime;
 use std::io;
 use std::io::Write;
 use std::io::Read;
@@ -12,6 +14,10 @@ pub enum Value {
     Str(String),
     /// Unsigned 32-bits Integer `u32`
     U32(u32),
+    /// Date, with a millisecond precision.
+    Date(DateTime),
+    /// JSON object, in its serialized form.
+    Json(String),
 }
 
 impl Value {
@@ -44,6 +50,36 @@ impl Value {
             }
         }
     }
+
+    /// Returns the date, provided the value is of the `Date` type.
+    ///
+    /// # Panics
+    /// If the value is not of type `Date`
+    pub fn date_value(&self) -> DateTime {
+        match *self {
+            Value::Date(date_time) => {
+               date_time
+            }
+            _ => {
+                panic!("This is not a date field.")
+            }
+        }
+    }
+
+    /// Returns the serialized JSON object, provided the value is of the `Json` type.
+    ///
+    /// # Panics
+    /// If the value is not of type `Json`
+    pub fn json_text(&self) -> &str {
+        match *self {
+            Value::Json(ref json_text) => {
+               json_text
+            }
+            _ => {
+                panic!("This is not a JSON field.")
+            }
+        }
+    }
 }
 
 impl From<String> for Value {
@@ -59,6 +95,12 @@ impl From<u32> for Value {
     }
 }
 
+impl From<DateTime> for Value {
+    fn from(date_time: DateTime) -> Value {
+        Value::Date(date_time)
+    }
+}
+
 impl<'a> From<&'a str> for Value {
     fn from(s: &'a str) -> Value {
         Value::Str(s.to_string())
@@ -67,20 +109,39 @@ impl<'a> From<&'a str> for Value {
 
 const TEXT_CODE: u8 = 0;
 const U32_CODE: u8 = 1;
+const DATE_CODE: u8 = 2;
+const JSON_CODE: u8 = 3;
 
+/// Serializes `text` the way `Value::Str` is serialized,
+/// without having to allocate the value.
+#[doc(hidden)]
+pub fn serialize_text_value(text: &str, writer: &mut Write) -> io::Result<usize> {
+    let data: &[u8] = text.as_bytes();
+    let mut written_size = try!(TEXT_CODE.serialize(writer));
+    written_size += try!(VInt(data.len() as u64).serialize(writer));
+    try!(writer.write_all(data));
+    Ok(written_size + data.len())
+}
 
 impl BinarySerializable for Value {
     fn serialize(&self, writer: &mut Write) -> io::Result<usize> {
         let mut written_size = 0;
         match *self {
             Value::Str(ref text) => {
-                written_size += try!(TEXT_CODE.serialize(writer));
-                written_size += try!(text.serialize(writer));
+                written_size += try!(serialize_text_value(text, writer));
             },
             Value::U32(ref val) => {
                 written_size += try!(U32_CODE.serialize(writer));
                 written_size += try!(val.serialize(writer));
-            },            
+            },
+            Value::Date(ref date_time) => {
+                written_size += try!(DATE_CODE.serialize(writer));
+                written_size += try!((date_time.timestamp_millis() as u64).serialize(writer));
+            },
+            Value::Json(ref json_text) => {
+                written_size += try!(JSON_CODE.serialize(writer));
+                written_size += try!(json_text.serialize(writer));
+            },
         }
         Ok(written_size)
     }
@@ -95,6 +156,14 @@ impl BinarySerializable for Value {
                 let value = try!(u32::deserialize(reader));
                 Ok(Value::U32(value))
             }
+            DATE_CODE => {
+                let timestamp_millis = try!(u64::deserialize(reader)) as i64;
+                Ok(Value::Date(DateTime::from_timestamp_millis(timestamp_millis)))
+            }
+            JSON_CODE => {
+                let json_text = try!(String::deserialize(reader));
+                Ok(Value::Json(json_text))
+            }
             _ => {
                 Err(io::Error::new(io::ErrorKind::InvalidData, format!("No field type is associated with code {:?}", type_code)))
             }
@@ -0,0 +1,614 @@
+use std::cmp;
+use std::collections::BTreeMap;
+use std::ops::Range;
+use std::str;
+use std::sync::Arc;
+use analyzer::{Tokenizer, RawTokenizer};
+use query::Query;
+use schema::{Document, Field, FieldType, Term};
+use Index;
+use Error;
+use Result;
+
+
+/// Maximum number of characters of a snippet, by default.
+const DEFAULT_MAX_NUM_CHARS: usize = 150;
+
+
+/// An excerpt of a text, together with the byte ranges
+/// of the matched terms within this excerpt.
+///
+/// See `SnippetGenerator`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Snippet {
+    fragment: String,
+    highlighted: Vec<Range<usize>>,
+}
+
+impl Snippet {
+
+    /// Returns the excerpt of the text.
+    pub fn fragment(&self) -> &str {
+        &self.fragment
+    }
+
+    /// Returns the byte ranges of the matched terms within
+    /// the fragment, in increasing order.
+    ///
+    /// The ranges do not overlap.
+    pub fn highlighted(&self) -> &[Range<usize>] {
+        &self.highlighted
+    }
+
+    /// Returns true iff the fragment is empty.
+    pub fn is_empty(&self) -> bool {
+        self.fragment.is_empty()
+    }
+
+    /// Returns the fragment as HTML, with the matched terms
+    /// wrapped in `<b>` tags.
+    ///
+    /// The text of the fragment is escaped.
+    pub fn to_html(&self) -> String {
+        let mut html = String::new();
+        let mut start = 0;
+        for range in &self.highlighted {
+            escape_html(&self.fragment[start..range.start], &mut html);
+            html.push_str("<b>");
+            escape_html(&self.fragment[range.clone()], &mut html);
+            html.push_str("</b>");
+            start = range.end;
+        }
+        escape_html(&self.fragment[start..], &mut html);
+        html
+    }
+}
+
+fn escape_html(text: &str, html: &mut String) {
+    for c in text.chars() {
+        match c {
+            '&' => html.push_str("&amp;"),
+            '<' => html.push_str("&lt;"),
+            '>' => html.push_str("&gt;"),
+            '"' => html.push_str("&quot;"),
+            _ => html.push(c),
+        }
+    }
+}
+
+
+/// Computes the snippets of the values of a field,
+/// highlighting the terms of a query.
+///
+/// The text is split with the tokenizer of the field,
+/// so that the highlighted terms are exactly the ones
+/// that were matched in the index.
+///
+/// The snippet is the window of at most `max_num_chars` characters
+/// containing the largest number of distinct terms of the query,
+/// the first one in case of ties. The window is then extended
+/// forward, or backward at the end of the text, to get as close
+/// as possible to `max_num_chars` characters.
+/// If the text does not contain any of the terms, the snippet
+/// is the beginning of the text.
+///
+/// Windows are cut at character boundaries, not at word boundaries.
+///
+/// ```rust
+/// extern crate tantivy;
+/// use tantivy::{Index, SnippetGenerator};
+/// use tantivy::schema::{SchemaBuilder, TEXT, STORED};
+/// use tantivy::query::QueryParser;
+///
+/// # fn main() {
+/// # run().unwrap();
+/// # }
+/// # fn run() -> tantivy::Result<()> {
+/// let mut schema_builder = SchemaBuilder::default();
+/// let body = schema_builder.add_text_field("body", TEXT | STORED);
+/// let index = Index::create_in_ram(schema_builder.build());
+/// let query_parser = QueryParser::for_index(&index, vec![body]);
+/// let query = query_parser.parse_query("sea whale").unwrap();
+/// let snippet_generator = SnippetGenerator::for_field(&index, &*query, body)?;
+/// let snippet = snippet_generator.snippet("The whale swims in the sea.");
+/// assert_eq!(snippet.to_html(), "The <b>whale</b> swims in the <b>sea</b>.");
+/// # Ok(())
+/// # }
+/// ```
+pub struct SnippetGenerator {
+    field: Field,
+    // identifier of each term of the query.
+    terms: BTreeMap<String, usize>,
+    tokenizer: Arc<Tokenizer>,
+    max_num_chars: usize,
+}
+
+impl SnippetGenerator {
+
+    /// Creates a `SnippetGenerator` highlighting the terms
+    /// of `query` which belong to `field`, using `tokenizer`
+    /// to split the text of the field.
+    pub fn new(query: &Query, field: Field, tokenizer: Arc<Tokenizer>) -> SnippetGenerator {
+        let mut query_terms: Vec<Term> = Vec::new();
+        query.query_terms(&mut query_terms);
+        let mut terms: BTreeMap<String, usize> = BTreeMap::new();
+        for term in query_terms {
+            if term.field() != field {
+                continue;
+            }
+            if let Ok(text) = str::from_utf8(term.value()) {
+                let term_id = terms.len();
+                terms.entry(String::from(text)).or_insert(term_id);
+            }
+        }
+        SnippetGenerator {
+            field: field,
+            terms: terms,
+            tokenizer: tokenizer,
+            max_num_chars: DEFAULT_MAX_NUM_CHARS,
+        }
+    }
+
+    /// Creates a `SnippetGenerator` for the text field `field`
+    /// of `index`, using the tokenizer the field is indexed with.
+    ///
+    /// # Errors
+    /// If the field is not a text field, or if its tokenizer
+    /// is not registered, returns `Error::SchemaError`.
+    pub fn for_field(index: &Index, query: &Query, field: Field) -> Result<SnippetGenerator> {
+        let schema = index.schema();
+        let field_entry = schema.get_field_entry(field);
+        let tokenizer: Arc<Tokenizer> = match *field_entry.field_type() {
+            FieldType::Str(ref text_options) => {
+                if text_options.get_indexing_options().is_tokenized() {
+                    try!(index
+                        .tokenizers()
+                        .get(field_entry.tokenizer())
+                        .ok_or_else(|| Error::SchemaError(format!("The tokenizer {:?} of field <{}> is not registered.",
+                            field_entry.tokenizer(), field_entry.name()))))
+                }
+                else {
+                    Arc::new(RawTokenizer)
+                }
+            }
+            _ => {
+                return Err(Error::SchemaError(format!("Field <{}> is not a text field.", field_entry.name())));
+            }
+        };
+        Ok(SnippetGenerator::new(query, field, tokenizer))
+    }
+
+    /// Sets the maximum number of characters of the snippets.
+    ///
+    /// Defaults to 150.
+    pub fn set_max_num_chars(&mut self, max_num_chars: usize) {
+        self.max_num_chars = max_num_chars;
+    }
+
+    /// Returns the snippet of the values of the field in `doc`.
+    ///
+    /// The values are joined by a space.
+    pub fn snippet_from_doc(&self, doc: &Document) -> Snippet {
+        self.snippet(&self.text_from_doc(doc))
+    }
+
+    fn text_from_doc(&self, doc: &Document) -> String {
+        let texts: Vec<&str> = doc.get_all(self.field)
+            .into_iter()
+            .map(|value| value.text())
+            .collect();
+        texts.join(" ")
+    }
+
+    /// Returns the snippet of `text`.
+    pub fn snippet(&self, text: &str) -> Snippet {
+        self.snippet_with_num_terms(text).0
+    }
+
+    // Returns the snippet of `text`, and the number
+    // of distinct terms of the query it contains.
+    fn snippet_with_num_terms(&self, text: &str) -> (Snippet, usize) {
+        // byte range and term identifier of the matched tokens.
+        let mut matches: Vec<(usize, usize, usize)> = Vec::new();
+        let mut token_stream = self.tokenizer.token_stream(text);
+        while token_stream.advance() {
+            let token = token_stream.token();
+            if let Some(&term_id) = self.terms.get(&token.text) {
+                matches.push((token.offset_from, token.offset_to, term_id));
+            }
+        }
+        if matches.is_empty() {
+            let end = advance_chars(text, 0, self.max_num_chars);
+            let snippet = Snippet {
+                fragment: String::from(&text[..end]),
+                highlighted: Vec::new(),
+            };
+            return (snippet, 0);
+        }
+        matches.sort();
+        let char_offsets = CharOffsets::new(text, &matches);
+
+        // sliding window over the matches, keeping the first
+        // window with the largest number of distinct terms.
+        let mut term_counts: Vec<usize> = vec![0; self.terms.len()];
+        let mut num_distinct_terms = 0;
+        let mut best_window: (usize, usize, usize) = (0, 0, 0);
+        let mut first = 0;
+        for (last, &(_, last_to, last_term_id)) in matches.iter().enumerate() {
+            if term_counts[last_term_id] == 0 {
+                num_distinct_terms += 1;
+            }
+            term_counts[last_term_id] += 1;
+            while first < last && char_offsets.get(last_to) - char_offsets.get(matches[first].0) > self.max_num_chars {
+                let first_term_id = matches[first].2;
+                term_counts[first_term_id] -= 1;
+                if term_counts[first_term_id] == 0 {
+                    num_distinct_terms -= 1;
+                }
+                first += 1;
+            }
+            if num_distinct_terms > best_window.0 {
+                best_window = (num_distinct_terms, first, last);
+            }
+        }
+
+        let (num_terms, first, last) = best_window;
+        let window_start = matches[first].0;
+        let window_end = matches[last].1;
+        let window_num_chars = char_offsets.get(window_end) - char_offsets.get(window_start);
+        let (start, end) = if window_num_chars >= self.max_num_chars {
+            (window_start, advance_chars(text, window_start, self.max_num_chars))
+        }
+        else {
+            let slack = self.max_num_chars - window_num_chars;
+            let end = advance_chars(text, window_end, slack);
+            let remaining_slack = slack - text[window_end..end].chars().count();
+            let start = rewind_chars(text, window_start, remaining_slack);
+            (start, end)
+        };
+
+        let mut highlighted: Vec<Range<usize>> = Vec::new();
+        for &(offset_from, offset_to, _) in &matches {
+            if offset_to <= start || offset_from >= end {
+                continue;
+            }
+            let range = (cmp::max(offset_from, start) - start)..(cmp::min(offset_to, end) - start);
+            // overlapping tokens, e.g. n-grams, are highlighted together.
+            if let Some(last_range) = highlighted.last_mut() {
+                if range.start <= last_range.end {
+                    last_range.end = cmp::max(last_range.end, range.end);
+                    continue;
+                }
+            }
+            highlighted.push(range);
+        }
+        let snippet = Snippet {
+            fragment: String::from(&text[start..end]),
+            highlighted: highlighted,
+        };
+        (snippet, num_terms)
+    }
+}
+
+
+/// Computes the snippets of several fields of a document,
+/// e.g. its title and its body, highlighting the terms of a query.
+///
+/// Each field is handled by a `SnippetGenerator`, with
+/// the tokenizer the field is indexed with. The snippet of a field
+/// without any of the terms of the query is empty.
+///
+/// ```rust
+/// extern crate tantivy;
+/// use tantivy::{Document, Index, MultiFieldSnippetGenerator};
+/// use tantivy::schema::{SchemaBuilder, TEXT, STORED};
+/// use tantivy::query::QueryParser;
+///
+/// # fn main() {
+/// # run().unwrap();
+/// # }
+/// # fn run() -> tantivy::Result<()> {
+/// let mut schema_builder = SchemaBuilder::default();
+/// let title = schema_builder.add_text_field("title", TEXT | STORED);
+/// let body = schema_builder.add_text_field("body", TEXT | STORED);
+/// let index = Index::create_in_ram(schema_builder.build());
+/// let query_parser = QueryParser::for_index(&index, vec![title, body]);
+/// let query = query_parser.parse_query("whale").unwrap();
+/// let snippet_generator = MultiFieldSnippetGenerator::for_fields(&index, &*query, vec![title, body])?;
+/// let mut doc = Document::default();
+/// doc.add_text(title, "Moby Dick");
+/// doc.add_text(body, "The whale swims in the sea.");
+/// let snippets = snippet_generator.snippets_from_doc(&doc);
+/// assert!(snippets[0].1.is_empty());
+/// assert_eq!(snippets[1].1.to_html(), "The <b>whale</b> swims in the sea.");
+/// # Ok(())
+/// # }
+/// ```
+pub struct MultiFieldSnippetGenerator {
+    snippet_generators: Vec<SnippetGenerator>,
+}
+
+impl MultiFieldSnippetGenerator {
+
+    /// Creates a `MultiFieldSnippetGenerator` for the text fields `fields`
+    /// of `index`. See `SnippetGenerator::for_field`.
+    ///
+    /// # Errors
+    /// If one of the fields is not a text field, or if its tokenizer
+    /// is not registered, returns `Error::SchemaError`.
+    pub fn for_fields(index: &Index, query: &Query, fields: Vec<Field>) -> Result<MultiFieldSnippetGenerator> {
+        let snippet_generators: Vec<SnippetGenerator> = try!(fields
+            .into_iter()
+            .map(|field| SnippetGenerator::for_field(index, query, field))
+            .collect());
+        Ok(MultiFieldSnippetGenerator {
+            snippet_generators: snippet_generators,
+        })
+    }
+
+    /// Sets the maximum number of characters of the snippets
+    /// of each of the fields.
+    ///
+    /// Defaults to 150.
+    pub fn set_max_num_chars(&mut self, max_num_chars: usize) {
+        for snippet_generator in &mut self.snippet_generators {
+            snippet_generator.set_max_num_chars(max_num_chars);
+        }
+    }
+
+    /// Returns the snippet of each of the fields in `doc`,
+    /// in the order the fields were given.
+    ///
+    /// The values of a field are joined by a space.
+    pub fn snippets_from_doc(&self, doc: &Document) -> Vec<(Field, Snippet)> {
+        self.snippets_with_num_terms(doc)
+            .into_iter()
+            .map(|(field, snippet, _)| (field, snippet))
+            .collect()
+    }
+
+    /// Returns the snippet containing the largest number of
+    /// distinct terms of the query, among the fields of `doc`.
+    ///
+    /// In case of ties, the snippet of the first field is returned.
+    /// Returns `None` if none of the fields contains any of the terms.
+    pub fn best_snippet_from_doc(&self, doc: &Document) -> Option<(Field, Snippet)> {
+        let mut best_snippet: Option<(Field, Snippet, usize)> = None;
+        for (field, snippet, num_terms) in self.snippets_with_num_terms(doc) {
+            let best_num_terms = best_snippet.as_ref().map(|&(_, _, num_terms)| num_terms).unwrap_or(0);
+            if num_terms > best_num_terms {
+                best_snippet = Some((field, snippet, num_terms));
+            }
+        }
+        best_snippet.map(|(field, snippet, _)| (field, snippet))
+    }
+
+    fn snippets_with_num_terms(&self, doc: &Document) -> Vec<(Field, Snippet, usize)> {
+        self.snippet_generators
+            .iter()
+            .map(|snippet_generator| {
+                let (snippet, num_terms) = snippet_generator.snippet_with_num_terms(&snippet_generator.text_from_doc(doc));
+                if num_terms == 0 {
+                    let empty_snippet = Snippet {
+                        fragment: String::new(),
+                        highlighted: Vec::new(),
+                    };
+                    return (snippet_generator.field, empty_snippet, 0);
+                }
+                (snippet_generator.field, snippet, num_terms)
+            })
+            .collect()
+    }
+}
+
+// Returns the byte offset `num_chars` characters after `offset`,
+// or the length of the text.
+fn advance_chars(text: &str, offset: usize, num_chars: usize) -> usize {
+    text[offset..]
+        .char_indices()
+        .nth(num_chars)
+        .map(|(char_offset, _)| offset + char_offset)
+        .unwrap_or(text.len())
+}
+
+// Returns the byte offset `num_chars` characters before `offset`,
+// or 0.
+fn rewind_chars(text: &str, offset: usize, num_chars: usize) -> usize {
+    if num_chars == 0 {
+        return offset;
+    }
+    text[..offset]
+        .char_indices()
+        .rev()
+        .nth(num_chars - 1)
+        .map(|(char_offset, _)| char_offset)
+        .unwrap_or(0)
+}
+
+// Character offset of the boundaries of the matches.
+struct CharOffsets(BTreeMap<usize, usize>);
+
+impl CharOffsets {
+    fn new(text: &str, matches: &[(usize, usize, usize)]) -> CharOffsets {
+        let mut char_offsets: BTreeMap<usize, usize> = BTreeMap::new();
+        for &(offset_from, offset_to, _) in matches {
+            char_offsets.insert(offset_from, 0);
+            char_offsets.insert(offset_to, 0);
+        }
+        let mut byte_offset = 0;
+        let mut char_offset = 0;
+        for (&boundary, boundary_char_offset) in char_offsets.iter_mut() {
+            char_offset += text[byte_offset..boundary].chars().count();
+            byte_offset = boundary;
+            *boundary_char_offset = char_offset;
+        }
+        CharOffsets(char_offsets)
+    }
+
+    fn get(&self, byte_offset: usize) -> usize {
+        self.0[&byte_offset]
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use std::sync::Arc;
+    use analyzer::{SimpleTokenizer, NgramTokenizer};
+    use query::{BooleanQuery, QueryParser, TermQuery};
+    use postings::SegmentPostingsOption;
+    use schema::{FieldValue, SchemaBuilder, TEXT, STORED, STRING, U32_STORED};
+
+    fn make_generator(query: &str, max_num_chars: usize) -> SnippetGenerator {
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let query_parser = QueryParser::new(schema_builder.build(), vec![text_field]);
+        let query = query_parser.parse_query(query).unwrap();
+        let mut snippet_generator = SnippetGenerator::new(&*query, text_field, Arc::new(SimpleTokenizer));
+        snippet_generator.set_max_num_chars(max_num_chars);
+        snippet_generator
+    }
+
+    fn highlighted_texts(snippet: &Snippet) -> Vec<&str> {
+        snippet.highlighted()
+            .iter()
+            .map(|range| &snippet.fragment()[range.clone()])
+            .collect()
+    }
+
+    const RUST_TEXT: &'static str = "Rust is a systems programming language. \
+        Its compiler checks the lifetime of references. \
+        A Rust program has no data race, as the compiler rejects them.";
+
+    #[test]
+    fn test_snippet() {
+        let snippet = make_generator("rust language", 60).snippet(RUST_TEXT);
+        assert_eq!(snippet.fragment(), "Rust is a systems programming language. Its compiler checks ");
+        assert_eq!(highlighted_texts(&snippet), vec!("Rust", "language"));
+        assert_eq!(snippet.to_html(), "<b>Rust</b> is a systems programming <b>language</b>. Its compiler checks ");
+        // the whole text fits in the snippet.
+        let snippet = make_generator("rust -compiler", 1_000).snippet(RUST_TEXT);
+        assert_eq!(snippet.fragment(), RUST_TEXT);
+        assert_eq!(highlighted_texts(&snippet), vec!("Rust", "Rust"));
+    }
+
+    #[test]
+    fn test_snippet_maximizes_distinct_terms() {
+        // `compiler` appears twice, but the second window
+        // contains both `compiler` and `race`.
+        let snippet = make_generator("compiler race", 40).snippet(RUST_TEXT);
+        assert_eq!(highlighted_texts(&snippet), vec!("race", "compiler"));
+        assert!(snippet.fragment().starts_with("data race, as the compiler"));
+        assert_eq!(snippet.fragment().chars().count(), 40);
+    }
+
+    #[test]
+    fn test_snippet_no_match() {
+        let snippet = make_generator("python", 20).snippet(RUST_TEXT);
+        assert_eq!(snippet.fragment(), "Rust is a systems pr");
+        assert!(snippet.highlighted().is_empty());
+        assert_eq!(make_generator("python", 20).snippet("").fragment(), "");
+    }
+
+    #[test]
+    fn test_snippet_match_at_the_end() {
+        let snippet = make_generator("them", 20).snippet(RUST_TEXT);
+        assert_eq!(snippet.fragment(), "mpiler rejects them.");
+        assert_eq!(snippet.fragment().chars().count(), 20);
+        assert_eq!(highlighted_texts(&snippet), vec!("them"));
+        // a single match longer than the snippet is truncated.
+        let snippet = make_generator("programming", 4).snippet(RUST_TEXT);
+        assert_eq!(snippet.fragment(), "prog");
+        assert_eq!(highlighted_texts(&snippet), vec!("prog"));
+    }
+
+    #[test]
+    fn test_snippet_multibyte_chars() {
+        let text = "Über die Brücke läuft ein Bär, über die Straße.";
+        let snippet = make_generator("bär", 12).snippet(text);
+        assert_eq!(snippet.fragment().chars().count(), 12);
+        assert_eq!(highlighted_texts(&snippet), vec!("Bär"));
+        assert!(snippet.fragment().starts_with("Bär"));
+        let snippet = make_generator("straße", 10).snippet(text);
+        assert_eq!(snippet.fragment(), "ie Straße.");
+        assert_eq!(highlighted_texts(&snippet), vec!("Straße"));
+    }
+
+    #[test]
+    fn test_snippet_overlapping_tokens() {
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let query = BooleanQuery::new_multiterms_query(vec!(
+            Term::from_field_text(text_field, "hel"),
+            Term::from_field_text(text_field, "ell"),
+        ));
+        let snippet_generator = SnippetGenerator::new(&query, text_field, Arc::new(NgramTokenizer::new(3, 3, false)));
+        let snippet = snippet_generator.snippet("a hello");
+        assert_eq!(snippet.to_html(), "a <b>hell</b>o");
+    }
+
+    #[test]
+    fn test_snippet_generator_for_field() {
+        let mut schema_builder = SchemaBuilder::default();
+        let title_field = schema_builder.add_text_field("title", TEXT | STORED);
+        schema_builder.set_tokenizer(title_field, "en_stem");
+        let id_field = schema_builder.add_text_field("id", STRING | STORED);
+        let count_field = schema_builder.add_u32_field("count", U32_STORED);
+        let index = Index::create_in_ram(schema_builder.build());
+        let query = QueryParser::for_index(&index, vec![title_field]).parse_query("run").unwrap();
+        let snippet_generator = SnippetGenerator::for_field(&index, &*query, title_field).unwrap();
+        let mut doc = Document::default();
+        doc.add_text(title_field, "The Running Dogs");
+        doc.add_text(title_field, "Dogs run");
+        let snippet = snippet_generator.snippet_from_doc(&doc);
+        assert_eq!(snippet.to_html(), "The <b>Running</b> Dogs Dogs <b>run</b>");
+        let id_query = TermQuery::new(Term::from_field_text(id_field, "A b"), SegmentPostingsOption::NoFreq);
+        let snippet = SnippetGenerator::for_field(&index, &id_query, id_field).unwrap().snippet("A b");
+        assert_eq!(snippet.to_html(), "<b>A b</b>");
+        assert!(SnippetGenerator::for_field(&index, &*query, count_field).is_err());
+    }
+
+    #[test]
+    fn test_multi_field_snippet_generator() {
+        let mut schema_builder = SchemaBuilder::default();
+        let title_field = schema_builder.add_text_field("title", TEXT | STORED);
+        schema_builder.set_tokenizer(title_field, "en_stem");
+        let body_field = schema_builder.add_text_field("body", TEXT | STORED);
+        let other_field = schema_builder.add_text_field("other", TEXT | STORED);
+        let count_field = schema_builder.add_u32_field("count", U32_STORED);
+        let index = Index::create_in_ram(schema_builder.build());
+        let query_parser = QueryParser::for_index(&index, vec![title_field, body_field, other_field]);
+        let query = query_parser.parse_query("running compiler").unwrap();
+        let snippet_generator = MultiFieldSnippetGenerator::for_fields(&index, &*query, vec![title_field, body_field, other_field]).unwrap();
+        let doc = doc!(
+            title_field=>"Running the compiler",
+            body_field=>"The compiler is running. The compiler runs.",
+            other_field=>"Nothing to see here"
+        );
+        let snippets = snippet_generator.snippets_from_doc(&doc);
+        assert_eq!(snippets.len(), 3);
+        // the title is stemmed, the body is not.
+        assert_eq!(snippets[0].0, title_field);
+        assert_eq!(snippets[0].1.fragment(), "Running the compiler");
+        assert_eq!(snippets[0].1.highlighted(), &[0..7, 12..20]);
+        assert_eq!(snippets[1].0, body_field);
+        assert_eq!(snippets[1].1.fragment(), "The compiler is running. The compiler runs.");
+        assert_eq!(snippets[1].1.highlighted(), &[4..12, 16..23, 29..37]);
+        assert_eq!(snippets[2].0, other_field);
+        assert!(snippets[2].1.is_empty());
+        assert!(snippets[2].1.highlighted().is_empty());
+
+        // both snippets contain the two terms: the first one wins.
+        let (best_field, best_snippet) = snippet_generator.best_snippet_from_doc(&doc).unwrap();
+        assert_eq!(best_field, title_field);
+        assert_eq!(best_snippet, snippets[0].1);
+        let doc = doc!(title_field=>"Dogs", body_field=>"A compiler", other_field=>"Running compiler");
+        let (best_field, best_snippet) = snippet_generator.best_snippet_from_doc(&doc).unwrap();
+        assert_eq!(best_field, other_field);
+        assert_eq!(best_snippet.to_html(), "<b>Running</b> <b>compiler</b>");
+        assert!(snippet_generator.best_snippet_from_doc(&doc!(title_field=>"Dogs")).is_none());
+        assert!(MultiFieldSnippetGenerator::for_fields(&index, &*query, vec![title_field, count_field]).is_err());
+    }
+}
@@ -0,0 +1,125 @@
+use std::io::{self, Read, Write};
+use lz4;
+use super::snappy;
+
+/// Codec used to compress the blocks of documents of the store.
+pub trait StoreCompressor: Sync {
+    /// Compresses `data`, appending the result to `out`.
+    fn compress(&self, data: &[u8], out: &mut Vec<u8>) -> io::Result<()>;
+
+    /// Decompresses a block compressed by `.compress(...)`,
+    /// appending the result to `out`.
+    fn decompress(&self, data: &[u8], out: &mut Vec<u8>) -> io::Result<()>;
+}
+
+/// LZ4 compression, in the LZ4 frame format.
+pub struct Lz4Compressor;
+
+impl StoreCompressor for Lz4Compressor {
+    fn compress(&self, data: &[u8], out: &mut Vec<u8>) -> io::Result<()> {
+        let mut encoder = try!(lz4::EncoderBuilder::new().build(out));
+        try!(encoder.write_all(data));
+        let (_, encoder_result) = encoder.finish();
+        encoder_result
+    }
+
+    fn decompress(&self, data: &[u8], out: &mut Vec<u8>) -> io::Result<()> {
+        let mut lz4_decoder = try!(lz4::Decoder::new(data));
+        lz4_decoder.read_to_end(out).map(|_| ())
+    }
+}
+
+/// LZ4 high compression, in the LZ4 frame format.
+///
+/// Compressing is several times slower than with `Lz4Compressor`,
+/// for a better compression ratio. Decompressing is as fast.
+pub struct Lz4HighCompressor;
+
+// highest compression level of LZ4 HC.
+const LZ4_HIGH_COMPRESSION_LEVEL: u32 = 12;
+
+impl StoreCompressor for Lz4HighCompressor {
+    fn compress(&self, data: &[u8], out: &mut Vec<u8>) -> io::Result<()> {
+        let mut encoder = try!(lz4::EncoderBuilder::new()
+            .level(LZ4_HIGH_COMPRESSION_LEVEL)
+            .build(out));
+        try!(encoder.write_all(data));
+        let (_, encoder_result) = encoder.finish();
+        encoder_result
+    }
+
+    fn decompress(&self, data: &[u8], out: &mut Vec<u8>) -> io::Result<()> {
+        LZ4_COMPRESSOR.decompress(data, out)
+    }
+}
+
+/// Snappy compression, in the raw snappy format.
+///
+/// Snappy is faster than LZ4 to compress, but
+/// usually compresses a bit less.
+pub struct SnappyCompressor;
+
+impl StoreCompressor for SnappyCompressor {
+    fn compress(&self, data: &[u8], out: &mut Vec<u8>) -> io::Result<()> {
+        snappy::compress(data, out);
+        Ok(())
+    }
+
+    fn decompress(&self, data: &[u8], out: &mut Vec<u8>) -> io::Result<()> {
+        snappy::decompress(data, out)
+    }
+}
+
+/// Blocks are stored uncompressed.
+pub struct NoCompressor;
+
+impl StoreCompressor for NoCompressor {
+    fn compress(&self, data: &[u8], out: &mut Vec<u8>) -> io::Result<()> {
+        out.extend_from_slice(data);
+        Ok(())
+    }
+
+    fn decompress(&self, data: &[u8], out: &mut Vec<u8>) -> io::Result<()> {
+        out.extend_from_slice(data);
+        Ok(())
+    }
+}
+
+static LZ4_COMPRESSOR: Lz4Compressor = Lz4Compressor;
+static LZ4_HIGH_COMPRESSOR: Lz4HighCompressor = Lz4HighCompressor;
+static SNAPPY_COMPRESSOR: SnappyCompressor = SnappyCompressor;
+static NO_COMPRESSOR: NoCompressor = NoCompressor;
+
+/// Identifies the codec used to compress the store of a segment.
+///
+/// It is recorded in the `SegmentMeta` of the segment,
+/// so that the store can be read back without any configuration.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, RustcDecodable, RustcEncodable)]
+pub enum Compressor {
+    /// See `Lz4Compressor`.
+    Lz4,
+    /// See `Lz4HighCompressor`.
+    Lz4High,
+    /// See `SnappyCompressor`.
+    Snappy,
+    /// See `NoCompressor`.
+    None,
+}
+
+impl Compressor {
+    /// Returns the implementation of the codec.
+    pub fn store_compressor(&self) -> &'static StoreCompressor {
+        match *self {
+            Compressor::Lz4 => &LZ4_COMPRESSOR,
+            Compressor::Lz4High => &LZ4_HIGH_COMPRESSOR,
+            Compressor::Snappy => &SNAPPY_COMPRESSOR,
+            Compressor::None => &NO_COMPRESSOR,
+        }
+    }
+}
+
+impl Default for Compressor {
+    fn default() -> Compressor {
+        Compressor::Lz4
+    }
+}
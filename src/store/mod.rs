@@ -1,7 +1,14 @@
 mod reader;
 mod writer;
-pub use self::reader::StoreReader;
+mod compressor;
+mod snappy;
+pub use self::reader::{StoreReader, has_valid_footer};
 pub use self::writer::StoreWriter;
+pub use self::compressor::{StoreCompressor, Compressor};
+pub use self::compressor::{Lz4Compressor, Lz4HighCompressor, SnappyCompressor, NoCompressor};
+
+/// Default size of the blocks of the store, before compression.
+pub const DEFAULT_BLOCK_SIZE: usize = 16_384;
 
 
 #[cfg(test)]
@@ -14,15 +21,16 @@ mod tests {
     use schema::TextOptions;
     use schema::FieldValue;
     use directory::{RAMDirectory, Directory, MmapDirectory, WritePtr};
+    use DocId;
 
-    fn write_lorem_ipsum_store(writer: WritePtr, num_docs: usize) -> Schema {
+    fn write_lorem_ipsum_store(writer: WritePtr, num_docs: usize, compressor: Compressor) -> Schema {
         let mut schema_builder = SchemaBuilder::default();
         let field_body = schema_builder.add_text_field("body", TextOptions::default().set_stored());
         let field_title = schema_builder.add_text_field("title", TextOptions::default().set_stored());
         let schema = schema_builder.build();
         let lorem = String::from("Doc Lorem ipsum dolor sit amet, consectetur adipiscing elit, sed do eiusmod tempor incididunt ut labore et dolore magna aliqua. Ut enim ad minim veniam, quis nostrud exercitation ullamco laboris nisi ut aliquip ex ea commodo consequat. Duis aute irure dolor in reprehenderit in voluptate velit esse cillum dolore eu fugiat nulla pariatur. Excepteur sint occaecat cupidatat non proident, sunt in culpa qui officia deserunt mollit anim id est laborum.");
         {
-            let mut store_writer = StoreWriter::new(writer);
+            let mut store_writer = StoreWriter::with_compressor(writer, compressor);
             for i in 0..num_docs {
                 let mut fields: Vec<FieldValue> = Vec::new();
                 {
@@ -47,7 +55,7 @@ mod tests {
         let path = Path::new("store");
         let mut directory = RAMDirectory::create();
         let store_file = directory.open_write(path).unwrap();
-        let schema = write_lorem_ipsum_store(store_file, 1_000);
+        let schema = write_lorem_ipsum_store(store_file, 1_000, Compressor::Lz4);
         let field_title = schema.get_field("title").unwrap();
         let store_source = directory.open_read(path).unwrap();
         let store = StoreReader::from(store_source);
@@ -56,12 +64,129 @@ mod tests {
         }
     }
     
+    #[test]
+    fn test_store_get_first_fields() {
+        let path = Path::new("store");
+        let mut directory = RAMDirectory::create();
+        let store_file = directory.open_write(path).unwrap();
+        let schema = write_lorem_ipsum_store(store_file, 1_000, Compressor::Lz4);
+        let field_body = schema.get_field("body").unwrap();
+        let field_title = schema.get_field("title").unwrap();
+        let store_source = directory.open_read(path).unwrap();
+        let store = StoreReader::from(store_source);
+        for i in 0..1_000 {
+            let doc = store.get_first_fields(i, &[field_title]).unwrap();
+            assert_eq!(doc.len(), 1);
+            assert_eq!(*doc.get_first(field_title).unwrap().text(), format!("Doc {}", i));
+        }
+        let doc = store.get_first_fields(3, &[field_body]).unwrap();
+        assert_eq!(doc.len(), 1);
+        assert!(doc.get_first(field_title).is_none());
+    }
+    
+    #[test]
+    fn test_store_raw_blocks() {
+        let path = Path::new("store");
+        let mut directory = RAMDirectory::create();
+        let store_file = directory.open_write(path).unwrap();
+        let schema = write_lorem_ipsum_store(store_file, 1_000, Compressor::Lz4);
+        let field_title = schema.get_field("title").unwrap();
+        let store = StoreReader::from(directory.open_read(path).unwrap());
+        assert!(store.raw_blocks().count() > 1);
+        assert_eq!(store.raw_blocks().map(|(num_docs, _)| num_docs).sum::<u32>(), 1_000);
+
+        let copy_path = Path::new("store_copy");
+        {
+            let mut store_writer = StoreWriter::new(directory.open_write(copy_path).unwrap());
+            // a document that is not part of a raw block.
+            let title = FieldValue::new(field_title, From::from(String::from("First")));
+            store_writer.store(&[&title]).unwrap();
+            for (num_docs, block) in store.raw_blocks() {
+                store_writer.write_raw_block(num_docs, block).unwrap();
+            }
+            store_writer.close().unwrap();
+        }
+        let store_copy = StoreReader::from(directory.open_read(copy_path).unwrap());
+        assert_eq!(store_copy.max_doc, 1_001);
+        assert_eq!(store_copy.get(0).unwrap().get_first(field_title).unwrap().text(), "First");
+        for i in 0..1_000 {
+            assert_eq!(store_copy.get(i + 1).unwrap(), store.get(i).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_store_compressors() {
+        let mut directory = RAMDirectory::create();
+        let mut store_lengths = Vec::new();
+        for &compressor in &[Compressor::Lz4, Compressor::Snappy, Compressor::None, Compressor::Lz4High] {
+            let path = Path::new("store");
+            let schema = write_lorem_ipsum_store(directory.open_write(path).unwrap(), 1_000, compressor);
+            let field_title = schema.get_field("title").unwrap();
+            let store_source = directory.open_read(path).unwrap();
+            store_lengths.push(store_source.len());
+            let store = StoreReader::open(store_source, compressor);
+            for i in 0..1_000 {
+                assert_eq!(*store.get(i).unwrap().get_first(field_title).unwrap().text(), format!("Doc {}", i));
+            }
+            directory.delete(path).unwrap();
+        }
+        // the uncompressed store is the largest.
+        assert!(store_lengths[0] < store_lengths[2]);
+        assert!(store_lengths[1] < store_lengths[2]);
+        assert!(store_lengths[3] <= store_lengths[0]);
+    }
+
+    #[test]
+    fn test_store_block_size() {
+        let mut schema_builder = SchemaBuilder::default();
+        let field_title = schema_builder.add_text_field("title", TextOptions::default().set_stored());
+        let mut directory = RAMDirectory::create();
+        let path = Path::new("store");
+        let titles: Vec<String> = (0..100)
+            .map(|i| {
+                if i == 41 {
+                    // a document larger than a block.
+                    (0..200).map(|j| format!("large {} ", j)).collect()
+                } else {
+                    format!("Doc {}", i)
+                }
+            })
+            .collect();
+        {
+            let mut store_writer = StoreWriter::with_block_size(directory.open_write(path).unwrap(), Compressor::Lz4, 100);
+            for title in &titles {
+                let field_value = FieldValue::new(field_title, From::from(title.clone()));
+                store_writer.store(&[&field_value]).unwrap();
+            }
+            store_writer.close().unwrap();
+        }
+        let store = StoreReader::from(directory.open_read(path).unwrap());
+        let blocks: Vec<DocId> = store.raw_blocks().map(|(num_docs, _)| num_docs).collect();
+        // documents span many blocks.
+        assert!(blocks.len() > 10);
+        assert_eq!(blocks.iter().sum::<DocId>(), 100);
+        // the large document has a block of its own.
+        let mut first_doc = 0;
+        let large_doc_block = blocks
+            .iter()
+            .position(|&num_docs| {
+                first_doc += num_docs;
+                first_doc > 41
+            })
+            .unwrap();
+        assert_eq!(blocks[large_doc_block], 1);
+        // reading the documents in any order goes through the right block.
+        for i in (0..100).rev().chain(0..100) {
+            assert_eq!(store.get(i).unwrap().get_first(field_title).unwrap().text(), &titles[i as usize][..]);
+        }
+    }
+
     #[bench]
     fn bench_store_encode(b: &mut Bencher) {
         let mut directory = MmapDirectory::create_from_tempdir().unwrap();
         let path = Path::new("store");
         b.iter(|| {
-            write_lorem_ipsum_store(directory.open_write(path).unwrap(), 1_000);
+            write_lorem_ipsum_store(directory.open_write(path).unwrap(), 1_000, Compressor::Lz4);
             directory.delete(path).unwrap();
         });
     }
@@ -71,7 +196,7 @@ mod tests {
     fn bench_store_decode(b: &mut Bencher) {
         let mut directory = MmapDirectory::create_from_tempdir().unwrap();
         let path = Path::new("store");
-        write_lorem_ipsum_store(directory.open_write(path).unwrap(), 1_000);
+        write_lorem_ipsum_store(directory.open_write(path).unwrap(), 1_000, Compressor::Lz4);
         let store_source = directory.open_read(path).unwrap();
         let store = StoreReader::from(store_source);
         b.iter(|| {
@@ -79,4 +204,28 @@ mod tests {
         });
 
     }
+
+    fn bench_store_retrieval(b: &mut Bencher, compressor: Compressor) {
+        let mut directory = MmapDirectory::create_from_tempdir().unwrap();
+        let path = Path::new("store");
+        write_lorem_ipsum_store(directory.open_write(path).unwrap(), 1_000, compressor);
+        let store = StoreReader::open(directory.open_read(path).unwrap(), compressor);
+        // alternating between documents of different
+        // blocks, so that each retrieval decompresses a block.
+        let mut doc = 0;
+        b.iter(|| {
+            doc = (doc + 499) % 1_000;
+            store.get(doc).unwrap();
+        });
+    }
+
+    #[bench]
+    fn bench_store_retrieval_lz4(b: &mut Bencher) {
+        bench_store_retrieval(b, Compressor::Lz4);
+    }
+
+    #[bench]
+    fn bench_store_retrieval_lz4_high(b: &mut Bencher) {
+        bench_store_retrieval(b, Compressor::Lz4High);
+    }
 }
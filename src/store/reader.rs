@@ -1,53 +1,103 @@
 use Result;
 
 use directory::ReadOnlySource;
-use std::cell::RefCell;
+use std::sync::{Mutex, MutexGuard};
 use DocId;
 use schema::Document;
-use schema::FieldValue;
+use schema::{Field, FieldValue, Value};
 use common::BinarySerializable;
 use std::mem::size_of;
 use std::io::{self, Read};
 use datastruct::SkipList;
-use lz4;
+use super::Compressor;
+
+// the last block read, decompressed.
+struct CurrentBlock {
+    offset: usize,
+    data: Vec<u8>,
+}
 
 pub struct StoreReader {
     pub data: ReadOnlySource,
     pub offset_index_source: ReadOnlySource,
-    current_block_offset: RefCell<usize>,
-    current_block: RefCell<Vec<u8>>,
+    // behind a mutex, so that segments can be searched
+    // from several threads.
+    current_block: Mutex<CurrentBlock>,
+    compressor: Compressor,
     pub max_doc: DocId,
 }
 
 impl StoreReader {
+    /// Opens a store whose blocks were compressed with
+    /// the given codec.
+    ///
+    /// `StoreReader::from` opens a store compressed with
+    /// the default codec.
+    ///
+    /// # Panics
+    /// Panics if the footer of the store is invalid.
+    /// (See `has_valid_footer`)
+    pub fn open(data: ReadOnlySource, compressor: Compressor) -> StoreReader {
+        let (data_source, offset_index_source, max_doc) = split_source(data);
+        StoreReader {
+            data: data_source,
+            offset_index_source: offset_index_source,
+            current_block: Mutex::new(CurrentBlock {
+                offset: usize::max_value(),
+                data: Vec::new(),
+            }),
+            compressor: compressor,
+            max_doc: max_doc,
+        }
+    }
+
     fn block_offset(&self, doc_id: DocId) -> (DocId, u64) {
         SkipList::from(self.offset_index_source.as_slice())
             .seek(doc_id + 1)
             .unwrap_or((0u32, 0u64))
     }
 
-    fn read_block(&self, block_offset: usize) -> io::Result<()> {
-        if block_offset != *self.current_block_offset.borrow() {
-            let mut current_block_mut = self.current_block.borrow_mut();
-            current_block_mut.clear();
+    fn read_block(&self, block_offset: usize) -> io::Result<MutexGuard<CurrentBlock>> {
+        let mut current_block = self.current_block.lock().expect("Store block lock poisoned");
+        if block_offset != current_block.offset {
+            current_block.data.clear();
             let total_buffer = self.data.as_slice();
             let mut cursor = &total_buffer[block_offset..];
             let block_length = u32::deserialize(&mut cursor).unwrap();
             let block_array: &[u8] =
                 &total_buffer[(block_offset + 4 as usize)..(block_offset + 4 + block_length as usize)];
-            let mut lz4_decoder = try!(lz4::Decoder::new(block_array));
-            *self.current_block_offset.borrow_mut() = usize::max_value();
-            try!(lz4_decoder.read_to_end(&mut current_block_mut).map(|_| ()));
-            *self.current_block_offset.borrow_mut() = block_offset;
+            current_block.offset = usize::max_value();
+            try!(self.compressor
+                .store_compressor()
+                .decompress(block_array, &mut current_block.data));
+            current_block.offset = block_offset;
         }
-        Ok(()) 
+        Ok(current_block)
+    }
+
+    /// Returns an iterator over the compressed blocks of the store,
+    /// along with the number of documents they contain.
+    ///
+    /// The blocks are returned as is, without being decompressed.
+    /// They can be appended to another store using `StoreWriter::write_raw_block`.
+    pub fn raw_blocks<'a>(&'a self) -> impl Iterator<Item=(DocId, &'a [u8])> + 'a {
+        let data = self.data.as_slice();
+        let mut block_start: (DocId, usize) = (0u32, 0usize);
+        SkipList::<u64>::from(self.offset_index_source.as_slice())
+            .map(move |(block_end_doc, block_end_offset)| {
+                let (block_start_doc, block_start_offset) = block_start;
+                let block_end_offset = block_end_offset as usize;
+                block_start = (block_end_doc, block_end_offset);
+                // skipping the length of the block.
+                let block = &data[block_start_offset + 4..block_end_offset];
+                (block_end_doc - block_start_doc, block)
+            })
     }
 
     pub fn get(&self, doc_id: DocId) -> Result<Document> {
         let (first_doc_id, block_offset) = self.block_offset(doc_id);
-        try!(self.read_block(block_offset as usize));
-        let current_block_mut = self.current_block.borrow_mut();
-        let mut cursor = &current_block_mut[..];
+        let current_block = try!(self.read_block(block_offset as usize));
+        let mut cursor = &current_block.data[..];
         for _ in first_doc_id..doc_id {
             let block_length = try!(u32::deserialize(&mut cursor));
             cursor = &cursor[block_length as usize..];
@@ -61,9 +111,75 @@ impl StoreReader {
         }
         Ok(Document::from(field_values))
     }
+
+    /// Returns a document containing only the values
+    /// of the given fields.
+    ///
+    /// Decoding of the document record stops as soon as all of
+    /// the requested fields have been read, so fetching the fields
+    /// placed first by the schema's stored-field order
+    /// (see `SchemaBuilder::set_stored_fields_order`) is cheaper
+    /// than calling `.get(doc_id)`.
+    pub fn get_first_fields(&self, doc_id: DocId, fields: &[Field]) -> Result<Document> {
+        let (first_doc_id, block_offset) = self.block_offset(doc_id);
+        let current_block = try!(self.read_block(block_offset as usize));
+        let mut cursor = &current_block.data[..];
+        for _ in first_doc_id..doc_id {
+            let block_length = try!(u32::deserialize(&mut cursor));
+            cursor = &cursor[block_length as usize..];
+        }
+        try!(u32::deserialize(&mut cursor));
+        let field_values = try!(read_first_fields(&mut cursor, fields));
+        Ok(Document::from(field_values))
+    }
+}
+
+/// Reads the field values of a document record,
+/// keeping only the values of `fields`.
+///
+/// The values of a given field are contiguous within a record,
+/// so reading stops at the first value of a field that was not
+/// requested, once all of the requested fields have been seen.
+fn read_first_fields<R: Read>(reader: &mut R, fields: &[Field]) -> io::Result<Vec<FieldValue>> {
+    let mut field_values = Vec::new();
+    let mut remaining_fields: Vec<Field> = fields.to_vec();
+    let num_fields = try!(u32::deserialize(reader));
+    for _ in 0..num_fields {
+        let field = try!(Field::deserialize(reader));
+        if fields.contains(&field) {
+            remaining_fields.retain(|remaining_field| *remaining_field != field);
+            let value = try!(Value::deserialize(reader));
+            field_values.push(FieldValue::new(field, value));
+        }
+        else if remaining_fields.is_empty() {
+            break;
+        }
+        else {
+            try!(Value::deserialize(reader));
+        }
+    }
+    Ok(field_values)
 }
 
 
+/// Returns true iff the footer of the store data
+/// is well-formed.
+///
+/// `StoreReader::from` panics if it is not.
+pub fn has_valid_footer(data: &ReadOnlySource) -> bool {
+    let data_len = data.len();
+    let footer_size = size_of::<u64>() + size_of::<u32>();
+    if data_len < footer_size {
+        return false;
+    }
+    let footer_offset = data_len - footer_size;
+    let mut footer = &data.as_slice()[footer_offset..];
+    match u64::deserialize(&mut footer) {
+        Ok(offset) => offset as usize <= footer_offset,
+        Err(_) => false,
+    }
+}
+
 fn split_source(data: ReadOnlySource) -> (ReadOnlySource, ReadOnlySource, DocId) {
     let data_len = data.len();
     let footer_offset = data_len - size_of::<u64>() - size_of::<u32>();
@@ -78,13 +194,61 @@ fn split_source(data: ReadOnlySource) -> (ReadOnlySource, ReadOnlySource, DocId)
 
 impl From<ReadOnlySource> for StoreReader {
     fn from(data: ReadOnlySource) -> StoreReader {
-        let (data_source, offset_index_source, max_doc) = split_source(data);
-        StoreReader {
-            data: data_source,
-            offset_index_source: offset_index_source,
-            current_block_offset: RefCell::new(usize::max_value()),
-            current_block: RefCell::new(Vec::new()),
-            max_doc: max_doc,
+        StoreReader::open(data, Compressor::default())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::read_first_fields;
+    use common::BinarySerializable;
+    use schema::{Field, FieldValue, Value};
+    use std::io::{self, Read};
+
+    struct CountingReader<R: Read> {
+        underlying: R,
+        num_bytes_read: usize,
+    }
+
+    impl<R: Read> Read for CountingReader<R> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let num_bytes = try!(self.underlying.read(buf));
+            self.num_bytes_read += num_bytes;
+            Ok(num_bytes)
         }
     }
+
+    fn count_bytes_read(record: &[u8], fields: &[Field]) -> (Vec<FieldValue>, usize) {
+        let mut reader = CountingReader {
+            underlying: record,
+            num_bytes_read: 0,
+        };
+        let field_values = read_first_fields(&mut reader, fields).unwrap();
+        (field_values, reader.num_bytes_read)
+    }
+
+    #[test]
+    fn test_read_first_fields_stops_early() {
+        let title_field = Field(0);
+        let body_field = Field(1);
+        let mut field_values = vec!(FieldValue::new(title_field, Value::from("title")));
+        for _ in 0..10 {
+            field_values.push(FieldValue::new(body_field, Value::from("lorem ipsum dolor sit amet")));
+        }
+        let mut record: Vec<u8> = Vec::new();
+        (field_values.len() as u32).serialize(&mut record).unwrap();
+        for field_value in &field_values {
+            field_value.serialize(&mut record).unwrap();
+        }
+
+        let (title_values, title_num_bytes) = count_bytes_read(&record, &[title_field]);
+        assert_eq!(title_values, vec!(field_values[0].clone()));
+
+        let (all_values, all_num_bytes) = count_bytes_read(&record, &[title_field, body_field]);
+        assert_eq!(all_values, field_values);
+        assert_eq!(all_num_bytes, record.len());
+        assert!(title_num_bytes < all_num_bytes);
+    }
 }
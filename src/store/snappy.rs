@@ -0,0 +1,253 @@
+use std::io;
+
+// Implementation of the raw (unframed) snappy format.
+//
+// A compressed block starts with the length of the uncompressed
+// data encoded as a varint, followed by a sequence of elements
+// that are either literals or copies of previously decoded bytes.
+// The two lowest bits of the tag byte of an element give its type.
+
+const TAG_LITERAL: u8 = 0b00;
+const TAG_COPY_1: u8 = 0b01;
+const TAG_COPY_2: u8 = 0b10;
+const TAG_COPY_4: u8 = 0b11;
+
+const HASH_TABLE_NUM_BITS: usize = 14;
+const MAX_COPY_2_OFFSET: usize = 1 << 16;
+// a copy of 64 bytes takes 3 bytes, so that an element never
+// decompresses into more than 22 times its size.
+const MAX_EXPANSION_RATIO: usize = 22;
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("Corrupted snappy block: {}", msg))
+}
+
+fn read_u32_le(data: &[u8], pos: usize) -> u32 {
+    (data[pos] as u32) |
+        ((data[pos + 1] as u32) << 8) |
+        ((data[pos + 2] as u32) << 16) |
+        ((data[pos + 3] as u32) << 24)
+}
+
+fn hash(val: u32) -> usize {
+    (val.wrapping_mul(0x1e35a7bd) >> (32 - HASH_TABLE_NUM_BITS)) as usize
+}
+
+fn write_varint(mut val: usize, out: &mut Vec<u8>) {
+    while val >= 128 {
+        out.push((val & 127) as u8 | 128);
+        val >>= 7;
+    }
+    out.push(val as u8);
+}
+
+fn read_varint(data: &mut &[u8]) -> io::Result<usize> {
+    let mut val = 0usize;
+    let mut shift = 0;
+    loop {
+        let (&b, rest) = try!(data.split_first().ok_or_else(|| invalid_data("truncated length")));
+        *data = rest;
+        if shift > 28 {
+            return Err(invalid_data("length overflow"));
+        }
+        val |= ((b & 127) as usize) << shift;
+        if b < 128 {
+            return Ok(val);
+        }
+        shift += 7;
+    }
+}
+
+fn write_literal(literal: &[u8], out: &mut Vec<u8>) {
+    if literal.is_empty() {
+        return;
+    }
+    let n = literal.len() - 1;
+    if n < 60 {
+        out.push(TAG_LITERAL | ((n as u8) << 2));
+    } else {
+        let num_bytes = if n < 1 << 8 { 1 } else if n < 1 << 16 { 2 } else if n < 1 << 24 { 3 } else { 4 };
+        out.push(TAG_LITERAL | ((59 + num_bytes as u8) << 2));
+        for i in 0..num_bytes {
+            out.push((n >> (8 * i)) as u8);
+        }
+    }
+    out.extend_from_slice(literal);
+}
+
+fn write_copy(offset: usize, mut len: usize, out: &mut Vec<u8>) {
+    while len > 0 {
+        // copies of up to 64 bytes, but a copy of less than 4 bytes
+        // should not be left for the last chunk.
+        let chunk_len = if len > 64 && len < 68 { 60 } else if len > 64 { 64 } else { len };
+        if chunk_len >= 4 && chunk_len < 12 && offset < 2048 {
+            out.push(TAG_COPY_1 | (((chunk_len - 4) as u8) << 2) | (((offset >> 8) as u8) << 5));
+            out.push(offset as u8);
+        } else {
+            out.push(TAG_COPY_2 | (((chunk_len - 1) as u8) << 2));
+            out.push(offset as u8);
+            out.push((offset >> 8) as u8);
+        }
+        len -= chunk_len;
+    }
+}
+
+/// Compresses `data` into a raw snappy block, appended to `out`.
+pub fn compress(data: &[u8], out: &mut Vec<u8>) {
+    write_varint(data.len(), out);
+    let mut table = vec![usize::max_value(); 1 << HASH_TABLE_NUM_BITS];
+    let mut literal_start = 0;
+    let mut pos = 0;
+    while pos + 4 <= data.len() {
+        let val = read_u32_le(data, pos);
+        let h = hash(val);
+        let candidate = table[h];
+        table[h] = pos;
+        if candidate == usize::max_value() ||
+            pos - candidate >= MAX_COPY_2_OFFSET ||
+            read_u32_le(data, candidate) != val {
+            pos += 1;
+            continue;
+        }
+        let mut match_len = 4;
+        while pos + match_len < data.len() && data[candidate + match_len] == data[pos + match_len] {
+            match_len += 1;
+        }
+        write_literal(&data[literal_start..pos], out);
+        write_copy(pos - candidate, match_len, out);
+        pos += match_len;
+        literal_start = pos;
+    }
+    write_literal(&data[literal_start..], out);
+}
+
+/// Decompresses a raw snappy block, appending the
+/// decompressed data to `out`.
+pub fn decompress(mut data: &[u8], out: &mut Vec<u8>) -> io::Result<()> {
+    let uncompressed_len = try!(read_varint(&mut data));
+    // the length is read from the block, and is only
+    // trusted as far as the size of the block allows.
+    if uncompressed_len > data.len() * MAX_EXPANSION_RATIO {
+        return Err(invalid_data("length exceeding the size of the block"));
+    }
+    let start = out.len();
+    out.reserve(uncompressed_len);
+    while let Some((&tag, rest)) = data.split_first() {
+        data = rest;
+        let (offset, len) = match tag & 0b11 {
+            TAG_LITERAL => {
+                let mut len = (tag >> 2) as usize;
+                if len >= 60 {
+                    let num_bytes = len - 59;
+                    if data.len() < num_bytes {
+                        return Err(invalid_data("truncated literal length"));
+                    }
+                    len = 0;
+                    for i in 0..num_bytes {
+                        len |= (data[i] as usize) << (8 * i);
+                    }
+                    data = &data[num_bytes..];
+                }
+                len += 1;
+                if data.len() < len {
+                    return Err(invalid_data("truncated literal"));
+                }
+                out.extend_from_slice(&data[..len]);
+                data = &data[len..];
+                continue;
+            }
+            TAG_COPY_1 => {
+                if data.is_empty() {
+                    return Err(invalid_data("truncated copy"));
+                }
+                let offset = (((tag >> 5) as usize) << 8) | data[0] as usize;
+                data = &data[1..];
+                (offset, ((tag >> 2) & 0b111) as usize + 4)
+            }
+            TAG_COPY_2 => {
+                if data.len() < 2 {
+                    return Err(invalid_data("truncated copy"));
+                }
+                let offset = data[0] as usize | ((data[1] as usize) << 8);
+                data = &data[2..];
+                (offset, (tag >> 2) as usize + 1)
+            }
+            _ => {
+                debug_assert_eq!(tag & 0b11, TAG_COPY_4);
+                if data.len() < 4 {
+                    return Err(invalid_data("truncated copy"));
+                }
+                let offset = read_u32_le(data, 0) as usize;
+                data = &data[4..];
+                (offset, (tag >> 2) as usize + 1)
+            }
+        };
+        if offset == 0 || offset > out.len() - start {
+            return Err(invalid_data("invalid copy offset"));
+        }
+        // the source and the destination of the copy may overlap.
+        let copy_start = out.len() - offset;
+        for i in 0..len {
+            let b = out[copy_start + i];
+            out.push(b);
+        }
+    }
+    if out.len() - start != uncompressed_len {
+        return Err(invalid_data("unexpected length"));
+    }
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn roundtrip(data: &[u8]) -> usize {
+        let mut compressed = Vec::new();
+        compress(data, &mut compressed);
+        let mut decompressed = vec!(1u8, 2u8);
+        decompress(&compressed, &mut decompressed).unwrap();
+        assert_eq!(&decompressed[2..], data);
+        compressed.len()
+    }
+
+    #[test]
+    fn test_snappy_roundtrip() {
+        roundtrip(b"");
+        roundtrip(b"a");
+        roundtrip(b"abcabcabcabcabcabcabcabcabcabcabcabcabc");
+        let repetitive: Vec<u8> = (0..100_000).map(|i| (i % 251) as u8).collect();
+        assert!(roundtrip(&repetitive) < repetitive.len() / 10);
+        let run = vec![7u8; 1_000];
+        assert!(roundtrip(&run) < 100);
+        let noise: Vec<u8> = (0..70_000u32).map(|i| (i.wrapping_mul(2654435761) >> 13) as u8).collect();
+        roundtrip(&noise);
+    }
+
+    #[test]
+    fn test_snappy_reference_block() {
+        // "Wikipedia" followed by a copy of "pedia", as produced by the
+        // reference implementation.
+        let block = [14u8, 0x20, b'W', b'i', b'k', b'i', b'p', b'e', b'd', b'i', b'a', 0x05, 0x05];
+        let mut out = Vec::new();
+        decompress(&block, &mut out).unwrap();
+        assert_eq!(&out[..], b"Wikipediapedia");
+    }
+
+    #[test]
+    fn test_snappy_corrupted() {
+        let mut out = Vec::new();
+        assert!(decompress(&[5u8, 0x05, 0x01], &mut out).is_err());
+        assert!(decompress(&[5u8, 0x10, b'a'], &mut out).is_err());
+        assert!(decompress(&[3u8, 0x00, b'a'], &mut out).is_err());
+        // a length of about 32GB, for a block of 2 bytes.
+        assert!(decompress(&[0xff, 0xff, 0xff, 0xff, 0x7f, 0x00, b'a'], &mut out).is_err());
+        assert!(out.capacity() < 1_000);
+        // a run of 64 bytes, as a literal and a copy of 63 bytes.
+        let mut out = Vec::new();
+        decompress(&[64u8, 0x00, b'a', 0xfa, 0x01, 0x00], &mut out).unwrap();
+        assert_eq!(&out[..], &[b'a'; 64][..]);
+    }
+}
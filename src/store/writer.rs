@@ -3,11 +3,10 @@ use DocId;
 use schema::FieldValue;
 use common::BinarySerializable;
 use std::io::{self, Write};
-use lz4;
+use std::mem;
+use super::{Compressor, DEFAULT_BLOCK_SIZE};
 use datastruct::SkipListBuilder;
 
-const BLOCK_SIZE: usize = 16_384;
-
 pub struct StoreWriter {
     doc: DocId,
     written: u64,
@@ -15,11 +14,33 @@ pub struct StoreWriter {
     writer: WritePtr,
     intermediary_buffer: Vec<u8>,
     current_block: Vec<u8>,
+    compressor: Compressor,
+    block_size: usize,
 }
 
 
 impl StoreWriter {
     pub fn new(writer: WritePtr) -> StoreWriter {
+        StoreWriter::with_compressor(writer, Compressor::default())
+    }
+
+    /// Creates a `StoreWriter` compressing its blocks
+    /// with the given codec.
+    ///
+    /// The store must be read with the same codec.
+    pub fn with_compressor(writer: WritePtr, compressor: Compressor) -> StoreWriter {
+        StoreWriter::with_block_size(writer, compressor, DEFAULT_BLOCK_SIZE)
+    }
+
+    /// Creates a `StoreWriter` compressing its blocks
+    /// with the given codec, the documents being grouped
+    /// in blocks of about `block_size` bytes before compression.
+    ///
+    /// Reading a document requires decompressing its whole block:
+    /// smaller blocks make reads faster, at the expense of
+    /// the compression ratio. A document larger than `block_size`
+    /// gets a block of its own.
+    pub fn with_block_size(writer: WritePtr, compressor: Compressor, block_size: usize) -> StoreWriter {
         StoreWriter {
             doc: 0,
             written: 0,
@@ -27,32 +48,66 @@ impl StoreWriter {
             writer: writer,
             intermediary_buffer: Vec::new(),
             current_block: Vec::new(),
+            compressor: compressor,
+            block_size: block_size,
         }
     }
 
     pub fn store<'a>(&mut self, field_values: &[&'a FieldValue]) -> io::Result<()> {
+        self.store_with(field_values.len() as u32, |buffer| {
+            for field_value in field_values {
+                try!((*field_value).serialize(buffer));
+            }
+            Ok(())
+        })
+    }
+
+    /// Stores a document of `num_field_values` field values,
+    /// serialized by `serialize_field_values` as `store` would.
+    pub fn store_with<F>(&mut self, num_field_values: u32, serialize_field_values: F) -> io::Result<()>
+        where F: FnOnce(&mut Vec<u8>) -> io::Result<()> {
         self.intermediary_buffer.clear();
-        try!((field_values.len() as u32).serialize(&mut self.intermediary_buffer));
-        for field_value in field_values {
-            try!((*field_value).serialize(&mut self.intermediary_buffer));
+        try!(num_field_values.serialize(&mut self.intermediary_buffer));
+        try!(serialize_field_values(&mut self.intermediary_buffer));
+        if self.intermediary_buffer.len() > self.block_size && !self.current_block.is_empty() {
+            // the oversized document is not mixed with the previous ones,
+            // so that reading them does not require decompressing it.
+            let record = mem::replace(&mut self.intermediary_buffer, Vec::new());
+            try!(self.write_and_compress_block());
+            self.intermediary_buffer = record;
         }
         try!((self.intermediary_buffer.len() as u32).serialize(&mut self.current_block));
         try!(self.current_block.write_all(&self.intermediary_buffer[..]));
         self.doc += 1;
-        if self.current_block.len() > BLOCK_SIZE {
+        if self.current_block.len() > self.block_size {
             try!(self.write_and_compress_block());
         }
         Ok(())
     }
 
+    /// Appends a block of documents, as returned by
+    /// `StoreReader::raw_blocks`, without decompressing it.
+    ///
+    /// `num_docs` is the number of documents in the block.
+    /// The block must have been compressed with the codec
+    /// of this `StoreWriter`.
+    pub fn write_raw_block(&mut self, num_docs: DocId, compressed_block: &[u8]) -> io::Result<()> {
+        if !self.current_block.is_empty() {
+            try!(self.write_and_compress_block());
+        }
+        self.written += try!((compressed_block.len() as u32).serialize(&mut self.writer)) as u64;
+        try!(self.writer.write_all(compressed_block));
+        self.written += compressed_block.len() as u64;
+        self.doc += num_docs;
+        try!(self.offset_index_writer.insert(self.doc, &self.written));
+        Ok(())
+    }
+
     fn write_and_compress_block(&mut self) -> io::Result<()> {
         self.intermediary_buffer.clear();
-        {
-            let mut encoder = try!(lz4::EncoderBuilder::new().build(&mut self.intermediary_buffer));
-            try!(encoder.write_all(&self.current_block));
-            let (_, encoder_result) = encoder.finish();
-            try!(encoder_result);
-        }
+        try!(self.compressor
+            .store_compressor()
+            .compress(&self.current_block, &mut self.intermediary_buffer));
         let compressed_block_size = self.intermediary_buffer.len() as u64;
         self.written += try!((compressed_block_size as u32).serialize(&mut self.writer)) as u64;
         try!(self.writer.write_all(&self.intermediary_buffer));
@@ -0,0 +1,49 @@
+/// Term vector module
+///
+/// The term vector of a document lists the terms of its fields
+/// having term vectors, along with their frequency in the document.
+/// Unlike the postings, they are accessed given a `DocId`.
+///
+/// They are recorded for the text fields with
+/// `TextOptions::set_term_vectors`.
+
+mod reader;
+mod writer;
+
+pub use self::reader::TermVectorReader;
+pub use self::writer::TermVectorWriter;
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use std::path::Path;
+    use directory::{Directory, RAMDirectory};
+    use schema::{Field, Term};
+
+    #[test]
+    fn test_term_vectors() {
+        let path = Path::new("tv");
+        let mut directory = RAMDirectory::create();
+        let term_vectors: Vec<Vec<(Term, u32)>> = vec!(
+            vec!((Term::from_field_text(Field(0), "a"), 2), (Term::from_field_text(Field(1), "b"), 1)),
+            vec!(),
+            vec!((Term::from_field_text(Field(0), "c"), 300)),
+        );
+        {
+            let mut term_vector_writer = TermVectorWriter::new(directory.open_write(path).unwrap());
+            for term_vector in &term_vectors {
+                term_vector_writer.write(term_vector).unwrap();
+            }
+            term_vector_writer.close().unwrap();
+        }
+        let term_vector_reader = TermVectorReader::open(directory.open_read(path).unwrap()).unwrap();
+        assert_eq!(term_vector_reader.max_doc(), 3);
+        for (doc_id, term_vector) in term_vectors.iter().enumerate() {
+            assert_eq!(&term_vector_reader.get(doc_id as u32).unwrap(), term_vector);
+        }
+        assert!(term_vector_reader.get(3).unwrap().is_empty());
+        assert!(TermVectorReader::empty().get(0).unwrap().is_empty());
+    }
+}
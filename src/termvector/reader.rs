@@ -0,0 +1,85 @@
+use directory::ReadOnlySource;
+use DocId;
+use schema::Term;
+use common::{BinarySerializable, VInt, make_io_err};
+use std::io;
+use std::mem::size_of;
+
+
+/// Reads the term vectors of the documents of a segment,
+/// as written by the `TermVectorWriter`.
+pub struct TermVectorReader {
+    data: ReadOnlySource,
+    offsets: ReadOnlySource,
+    max_doc: DocId,
+}
+
+impl TermVectorReader {
+
+    /// Returns a reader without any term vector.
+    pub fn empty() -> TermVectorReader {
+        TermVectorReader {
+            data: ReadOnlySource::empty(),
+            offsets: ReadOnlySource::empty(),
+            max_doc: 0,
+        }
+    }
+
+    /// Opens the term vectors of a segment.
+    ///
+    /// # Errors
+    /// If the offsets of the term vectors are inconsistent
+    /// with the length of the file, returns an `io::Error`.
+    pub fn open(source: ReadOnlySource) -> io::Result<TermVectorReader> {
+        let len = source.as_slice().len();
+        if len < 2 * size_of::<u64>() {
+            return Err(make_io_err(format!("The term vector file is too short ({} bytes).", len)));
+        }
+        let offsets_end = len - size_of::<u64>();
+        let offsets_start = try!(u64::deserialize(&mut &source.as_slice()[offsets_end..])) as usize;
+        if offsets_start + size_of::<u64>() > offsets_end || (offsets_end - offsets_start) % size_of::<u64>() != 0 {
+            return Err(make_io_err(format!("Invalid term vector offsets start {}.", offsets_start)));
+        }
+        let max_doc = ((offsets_end - offsets_start) / size_of::<u64>() - 1) as DocId;
+        Ok(TermVectorReader {
+            data: source.slice(0, offsets_start),
+            offsets: source.slice(offsets_start, offsets_end),
+            max_doc: max_doc,
+        })
+    }
+
+    /// Returns the number of documents having a term vector.
+    pub fn max_doc(&self) -> DocId {
+        self.max_doc
+    }
+
+    fn offset(&self, doc_id: DocId) -> usize {
+        let start = doc_id as usize * size_of::<u64>();
+        let mut cursor = &self.offsets.as_slice()[start..start + size_of::<u64>()];
+        u64::deserialize(&mut cursor).expect("Reading from a slice of 8 bytes cannot fail") as usize
+    }
+
+    /// Returns the term vector of a document: its terms,
+    /// sorted, along with their frequency in the document.
+    ///
+    /// The term vector of a document beyond `max_doc` is empty.
+    pub fn get(&self, doc_id: DocId) -> io::Result<Vec<(Term, u32)>> {
+        if doc_id >= self.max_doc {
+            return Ok(Vec::new());
+        }
+        let mut cursor = &self.data.as_slice()[self.offset(doc_id)..self.offset(doc_id + 1)];
+        let num_terms = try!(VInt::deserialize(&mut cursor)).val() as usize;
+        let mut term_vector = Vec::with_capacity(num_terms);
+        for _ in 0..num_terms {
+            let term_len = try!(VInt::deserialize(&mut cursor)).val() as usize;
+            if term_len > cursor.len() {
+                return Err(make_io_err(format!("The term vector of doc {} is truncated.", doc_id)));
+            }
+            let term = Term::from_bytes(&cursor[..term_len]);
+            cursor = &cursor[term_len..];
+            let term_freq = try!(VInt::deserialize(&mut cursor)).val() as u32;
+            term_vector.push((term, term_freq));
+        }
+        Ok(term_vector)
+    }
+}
@@ -0,0 +1,60 @@
+use directory::WritePtr;
+use schema::Term;
+use common::{BinarySerializable, VInt};
+use std::io::{self, Write};
+
+
+/// Writes the term vectors of the documents of a segment,
+/// one document after the other, in the order of their doc ids.
+///
+/// The term vectors are followed by the offset of the term vector
+/// of each document, and by the offset of these offsets.
+pub struct TermVectorWriter {
+    writer: WritePtr,
+    written: u64,
+    // offset of the term vector of each of the documents written so far.
+    offsets: Vec<u64>,
+    // reused across documents to serialize their term vector.
+    buffer: Vec<u8>,
+}
+
+impl TermVectorWriter {
+
+    /// Creates a new `TermVectorWriter`.
+    pub fn new(writer: WritePtr) -> TermVectorWriter {
+        TermVectorWriter {
+            writer: writer,
+            written: 0,
+            offsets: Vec::new(),
+            buffer: Vec::new(),
+        }
+    }
+
+    /// Writes the term vector of the next document: its terms,
+    /// sorted, along with their frequency in the document.
+    pub fn write(&mut self, term_vector: &[(Term, u32)]) -> io::Result<()> {
+        self.buffer.clear();
+        try!(VInt(term_vector.len() as u64).serialize(&mut self.buffer));
+        for &(ref term, term_freq) in term_vector {
+            let term_bytes = term.as_slice();
+            try!(VInt(term_bytes.len() as u64).serialize(&mut self.buffer));
+            self.buffer.extend_from_slice(term_bytes);
+            try!(VInt(term_freq as u64).serialize(&mut self.buffer));
+        }
+        self.offsets.push(self.written);
+        try!(self.writer.write_all(&self.buffer));
+        self.written += self.buffer.len() as u64;
+        Ok(())
+    }
+
+    /// Writes the offsets of the term vectors, and flushes the file.
+    pub fn close(mut self) -> io::Result<()> {
+        let offsets_start = self.written;
+        self.offsets.push(self.written);
+        for offset in &self.offsets {
+            try!(offset.serialize(&mut self.writer));
+        }
+        try!(offsets_start.serialize(&mut self.writer));
+        self.writer.flush()
+    }
+}